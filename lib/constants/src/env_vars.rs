@@ -1,15 +1,38 @@
 use std::ffi::OsString;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use tracing::info;
 
 pub struct EnvVars;
 
+static ISOLATED: AtomicBool = AtomicBool::new(false);
+
 impl EnvVars {
     pub const PATH: &'static str = "PATH";
     pub const CI: &'static str = "CI";
+    pub const GITHUB_TOKEN: &'static str = "GITHUB_TOKEN";
+    pub const GITHUB_ACTIONS: &'static str = "GITHUB_ACTIONS";
+    pub const GITLAB_CI: &'static str = "GITLAB_CI";
+    pub const BUILDKITE: &'static str = "BUILDKITE";
+    pub const JENKINS_URL: &'static str = "JENKINS_URL";
 
     pub const SKIP: &'static str = "SKIP";
 
+    pub const HTTP_PROXY: &'static str = "HTTP_PROXY";
+    pub const HTTPS_PROXY: &'static str = "HTTPS_PROXY";
+    pub const XDG_CONFIG_HOME: &'static str = "XDG_CONFIG_HOME";
+    pub const LOCALAPPDATA: &'static str = "LOCALAPPDATA";
+    pub const AXOUPDATER_CONFIG_PATH: &'static str = "AXOUPDATER_CONFIG_PATH";
+    pub const AXOUPDATER_CONFIG_WORKING_DIR: &'static str = "AXOUPDATER_CONFIG_WORKING_DIR";
+    pub const NO_COLOR: &'static str = "NO_COLOR";
+    pub const CLICOLOR_FORCE: &'static str = "CLICOLOR_FORCE";
+    pub const FORCE_COLOR: &'static str = "FORCE_COLOR";
+    pub const OTEL_EXPORTER_OTLP_ENDPOINT: &'static str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+    pub const OTEL_EXPORTER_OTLP_TRACES_ENDPOINT: &'static str =
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT";
+    pub const OTEL_EXPORTER_OTLP_METRICS_ENDPOINT: &'static str =
+        "OTEL_EXPORTER_OTLP_METRICS_ENDPOINT";
+
     // PREK specific environment variables, public for users
     pub const PREK_HOME: &'static str = "PREK_HOME";
     pub const PREK_COLOR: &'static str = "PREK_COLOR";
@@ -17,7 +40,11 @@ impl EnvVars {
     pub const PREK_ALLOW_NO_CONFIG: &'static str = "PREK_ALLOW_NO_CONFIG";
     pub const PREK_NO_CONCURRENCY: &'static str = "PREK_NO_CONCURRENCY";
     pub const PREK_NO_FAST_PATH: &'static str = "PREK_NO_FAST_PATH";
+    pub const PREK_POLICY_FILE: &'static str = "PREK_POLICY_FILE";
+    pub const PREK_REQUIRED_CONFIG: &'static str = "PREK_REQUIRED_CONFIG";
     pub const PREK_UV_SOURCE: &'static str = "PREK_UV_SOURCE";
+    pub const PREK_LOG: &'static str = "PREK_LOG";
+    pub const PREK_CHANGED_LINES: &'static str = "PREK_CHANGED_LINES";
 
     // PREK internal environment variables
     pub const PREK_INTERNAL__TEST_DIR: &'static str = "PREK_INTERNAL__TEST_DIR";
@@ -31,6 +58,10 @@ impl EnvVars {
     pub const UV_CACHE_DIR: &'static str = "UV_CACHE_DIR";
     pub const UV_PYTHON_INSTALL_DIR: &'static str = "UV_PYTHON_INSTALL_DIR";
 
+    // Python related
+    pub const VIRTUAL_ENV: &'static str = "VIRTUAL_ENV";
+    pub const PYTHONHOME: &'static str = "PYTHONHOME";
+
     // Node/Npm related
     pub const NPM_CONFIG_USERCONFIG: &'static str = "NPM_CONFIG_USERCONFIG";
     pub const NPM_CONFIG_PREFIX: &'static str = "NPM_CONFIG_PREFIX";
@@ -51,8 +82,29 @@ impl EnvVars {
 }
 
 impl EnvVars {
+    /// Variables that override the behavior of prek, ignored in isolated mode.
+    const OVERRIDES: &'static [&'static str] = &[
+        Self::SKIP,
+        Self::PREK_SKIP,
+        Self::PREK_ALLOW_NO_CONFIG,
+        Self::PREK_NO_CONCURRENCY,
+        Self::PREK_NO_FAST_PATH,
+        Self::PREK_POLICY_FILE,
+        Self::PREK_REQUIRED_CONFIG,
+        Self::PREK_UV_SOURCE,
+    ];
+
+    /// Ignore the variables that override the behavior of prek from now on.
+    pub fn isolate() {
+        ISOLATED.store(true, Ordering::Relaxed);
+    }
+
     /// Read an environment variable, falling back to pre-commit corresponding variable if not found.
     pub fn var_os(name: &str) -> Option<OsString> {
+        if ISOLATED.load(Ordering::Relaxed) && Self::OVERRIDES.contains(&name) {
+            return None;
+        }
+
         #[allow(clippy::disallowed_methods)]
         std::env::var_os(name).or_else(|| {
             let name = Self::pre_commit_name(name)?;
@@ -0,0 +1,88 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// The file in the project directory that records the known failures of hooks.
+pub(crate) const BASELINE_FILE: &str = ".prek-baseline.json";
+
+/// The files each hook of a project is known to fail on, keyed by the hook id.
+///
+/// `prek run` doesn't pass these files to the hook, so only new failures are reported.
+/// File names are relative to the project root and always use `/` as separator.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub(crate) struct Baseline {
+    hooks: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Baseline {
+    pub(crate) fn path(project_root: &Path) -> PathBuf {
+        project_root.join(BASELINE_FILE)
+    }
+
+    /// Read the baseline of the project, returns `None` if the project has no baseline.
+    pub(crate) fn read(project_root: &Path) -> Result<Option<Self>> {
+        let path = Self::path(project_root);
+        let content = match fs_err::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let baseline = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse baseline file `{}`", path.display()))?;
+        Ok(Some(baseline))
+    }
+
+    /// Write the baseline to the project, or remove the baseline file if it's empty.
+    pub(crate) fn write(&self, project_root: &Path) -> Result<()> {
+        let path = Self::path(project_root);
+        if self.is_empty() {
+            return match fs_err::remove_file(&path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            };
+        }
+
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs_err::write(&path, content)?;
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// The number of recorded failures.
+    pub(crate) fn len(&self) -> usize {
+        self.hooks.values().map(BTreeSet::len).sum()
+    }
+
+    /// Whether `hook_id` is known to fail on `filename`.
+    pub(crate) fn contains(&self, hook_id: &str, filename: &Path) -> bool {
+        self.hooks
+            .get(hook_id)
+            .is_some_and(|files| files.contains(&key(filename)))
+    }
+
+    /// Record that `hook_id` fails on `filenames`.
+    pub(crate) fn insert(&mut self, hook_id: &str, filenames: &[&Path]) {
+        if filenames.is_empty() {
+            return;
+        }
+        self.hooks
+            .entry(hook_id.to_string())
+            .or_default()
+            .extend(filenames.iter().map(|filename| key(filename)));
+    }
+}
+
+fn key(filename: &Path) -> String {
+    filename
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
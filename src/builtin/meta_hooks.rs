@@ -1,13 +1,18 @@
+use std::fmt::Write as _;
 use std::io::Write;
 use std::path::Path;
 
 use anyhow::Result;
+use clap::Parser;
 use fancy_regex::Regex;
 use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
 
-use crate::cli::run::{CollectOptions, FileFilter, collect_files};
+use crate::cli::run::{CollectOptions, FileFilter, FileLimits, collect_files};
 use crate::config::{self, HookOptions, Language};
+use crate::git;
 use crate::hook::Hook;
+use crate::identify::tags_from_path;
 use crate::store::STORE;
 use crate::workspace::Project;
 
@@ -24,7 +29,7 @@ pub(crate) async fn check_hooks_apply(hook: &Hook, filenames: &[&Path]) -> Resul
 
     let relative_path = hook.project().relative_path();
     // Collect all files in the project
-    let input = collect_files(hook.work_dir(), CollectOptions::all_files()).await?;
+    let input = collect_files(hook.project().path(), CollectOptions::all_files()).await?;
     // Prepend the project relative path to each input file
     let input: Vec<_> = input.into_iter().map(|f| relative_path.join(f)).collect();
 
@@ -37,7 +42,12 @@ pub(crate) async fn check_hooks_apply(hook: &Hook, filenames: &[&Path]) -> Resul
         project.with_relative_path(relative_path.to_path_buf());
 
         let project_hooks = project.init_hooks(store, None).await?;
-        let filter = FileFilter::for_project(input.iter(), &project);
+        let filter = FileFilter::for_project(
+            input.iter(),
+            &project,
+            FileLimits::default(),
+            &FxHashSet::default(),
+        );
 
         for project_hook in project_hooks {
             if project_hook.always_run || matches!(project_hook.language, Language::Fail) {
@@ -71,9 +81,9 @@ fn excludes_any(
     }
 
     files.iter().any(|f| {
-        let Some(f) = f.as_ref().to_str() else {
-            return false; // Skip files that cannot be converted to a string
-        };
+        // Match names that aren't valid UTF-8 like `FilenameFilter` does.
+        let f = f.as_ref().to_string_lossy();
+        let f = f.as_ref();
 
         if let Some(re) = &include {
             if !re.is_match(f).unwrap_or(false) {
@@ -95,7 +105,7 @@ pub(crate) async fn check_useless_excludes(
     filenames: &[&Path],
 ) -> Result<(i32, Vec<u8>)> {
     let relative_path = hook.project().relative_path();
-    let input = collect_files(hook.work_dir(), CollectOptions::all_files()).await?;
+    let input = collect_files(hook.project().path(), CollectOptions::all_files()).await?;
     let input: Vec<_> = input.into_iter().map(|f| relative_path.join(f)).collect();
 
     let mut code = 0;
@@ -107,7 +117,8 @@ pub(crate) async fn check_useless_excludes(
         project.with_relative_path(relative_path.to_path_buf());
 
         let config = project.config();
-        if !excludes_any(&input, None, config.exclude.as_deref()) {
+        // Like upstream, the global exclude only needs to exclude files matching the global `files`.
+        if !excludes_any(&input, config.files.as_deref(), config.exclude.as_deref()) {
             code = 1;
             writeln!(
                 &mut output,
@@ -116,13 +127,21 @@ pub(crate) async fn check_useless_excludes(
             )?;
         }
 
-        let filter = FileFilter::for_project(input.iter(), &project);
+        let filter = FileFilter::for_project(
+            input.iter(),
+            &project,
+            FileLimits::default(),
+            &FxHashSet::default(),
+        );
 
         for repo in &config.repos {
             let hooks_iter: Box<dyn Iterator<Item = (&String, &HookOptions)>> = match repo {
                 config::Repo::Remote(r) => Box::new(r.hooks.iter().map(|h| (&h.id, &h.options))),
                 config::Repo::Local(r) => Box::new(r.hooks.iter().map(|h| (&h.id, &h.options))),
                 config::Repo::Meta(r) => Box::new(r.hooks.iter().map(|h| (&h.0.id, &h.0.options))),
+                config::Repo::Builtin(r) => {
+                    Box::new(r.hooks.iter().map(|h| (&h.0.id, &h.0.options)))
+                }
             };
 
             for (hook_id, opts) in hooks_iter {
@@ -151,16 +170,69 @@ pub(crate) async fn check_useless_excludes(
     Ok((code, output))
 }
 
+#[derive(Parser)]
+#[command(disable_help_subcommand = true)]
+#[command(disable_version_flag = true)]
+#[command(disable_help_flag = true)]
+struct IdentityArgs {
+    /// Print the identify tags of each file.
+    #[arg(long)]
+    tags: bool,
+    /// Print the size of each file in bytes.
+    #[arg(long)]
+    size: bool,
+    /// Print the hash of the staged blob of each file.
+    #[arg(long)]
+    hash: bool,
+}
+
 /// Prints all arguments passed to the hook. Useful for debugging.
-pub fn identity(_hook: &Hook, filenames: &[&Path]) -> (i32, Vec<u8>) {
-    (
-        0,
-        filenames
-            .iter()
-            .map(|f| f.to_string_lossy())
-            .join("\n")
-            .into_bytes(),
-    )
+///
+/// With `--tags`, `--size` or `--hash`, also prints the metadata of each file,
+/// which helps to debug `files`, `exclude` and `types` filters.
+pub(crate) async fn identity(hook: &Hook, filenames: &[&Path]) -> Result<(i32, Vec<u8>)> {
+    let args = IdentityArgs::try_parse_from(
+        std::iter::once("identity").chain(hook.args.iter().map(String::as_str)),
+    )?;
+    if !args.tags && !args.size && !args.hash {
+        return Ok((
+            0,
+            filenames
+                .iter()
+                .map(|f| f.to_string_lossy())
+                .join("\n")
+                .into_bytes(),
+        ));
+    }
+
+    let relative_path = hook.project().relative_path();
+    let blobs = if args.hash {
+        git::get_staged_blobs(hook.project().path()).await?
+    } else {
+        FxHashMap::default()
+    };
+
+    let mut lines = Vec::with_capacity(filenames.len());
+    for filename in filenames {
+        let path = relative_path.join(filename);
+        let mut line = filename.to_string_lossy().to_string();
+        if args.tags {
+            let mut tags = tags_from_path(&path)?;
+            tags.sort_unstable();
+            write!(line, " tags=[{}]", tags.join(", "))?;
+        }
+        if args.size {
+            let size = fs_err::tokio::symlink_metadata(&path).await?.len();
+            write!(line, " size={size}")?;
+        }
+        if args.hash {
+            let blob = blobs.get(*filename).map_or("-", String::as_str);
+            write!(line, " hash={blob}")?;
+        }
+        lines.push(line);
+    }
+
+    Ok((0, lines.join("\n").into_bytes()))
 }
 
 #[cfg(test)]
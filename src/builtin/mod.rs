@@ -4,18 +4,24 @@ use std::sync::LazyLock;
 
 use constants::env_vars::EnvVars;
 
-use crate::builtin::pre_commit_hooks::{Implemented, is_pre_commit_hooks};
+use crate::builtin::pre_commit_hooks::{Implemented, is_fixer, is_pre_commit_hooks};
 use crate::hook::{Hook, Repo};
+use crate::policy::policy;
 
 mod meta_hooks;
 mod pre_commit_hooks;
 
-static NO_FAST_PATH: LazyLock<bool> = LazyLock::new(|| EnvVars::is_set(EnvVars::PREK_NO_FAST_PATH));
+static NO_FAST_PATH: LazyLock<bool> = LazyLock::new(|| {
+    policy().builtin_fast_path.map_or_else(
+        || EnvVars::is_set(EnvVars::PREK_NO_FAST_PATH),
+        |enabled| !enabled,
+    )
+});
 
 /// Returns true if the hook has a builtin Rust implementation.
 pub fn check_fast_path(hook: &Hook) -> bool {
     match hook.repo() {
-        Repo::Meta { .. } => true,
+        Repo::Meta { .. } | Repo::Builtin { .. } => true,
         Repo::Remote { url, .. } if is_pre_commit_hooks(url) => {
             if *NO_FAST_PATH {
                 return false;
@@ -29,24 +35,48 @@ pub fn check_fast_path(hook: &Hook) -> bool {
     }
 }
 
+/// Returns true if the hook is known to modify files.
+pub fn modifies_files(hook: &Hook) -> bool {
+    match hook.repo() {
+        Repo::Remote { url, .. } if is_pre_commit_hooks(url) => is_fixer(hook),
+        Repo::Builtin { .. } => is_fixer(hook),
+        _ => false,
+    }
+}
+
 pub async fn run_fast_path(hook: &Hook, filenames: &[&Path]) -> anyhow::Result<(i32, Vec<u8>)> {
     match hook.repo() {
         Repo::Meta { .. } => run_meta_hook(hook, filenames).await,
+        Repo::Builtin { .. } => run_builtin_hook(hook, filenames).await,
         Repo::Remote { url, .. } if is_pre_commit_hooks(url) => {
-            Implemented::from_str(hook.id.as_str())
-                .unwrap()
-                .run(hook, filenames)
-                .await
+            run_builtin_hook(hook, filenames).await
         }
         _ => unreachable!(),
     }
 }
 
+/// Run the builtin implementation of a `pre-commit-hooks` hook.
+async fn run_builtin_hook(hook: &Hook, filenames: &[&Path]) -> anyhow::Result<(i32, Vec<u8>)> {
+    // These hooks read the files, a broken symlink would fail the whole run.
+    let filenames = filenames
+        .iter()
+        .copied()
+        .filter(|filename| {
+            let path = hook.project().relative_path().join(filename);
+            !path.is_symlink() || path.exists()
+        })
+        .collect::<Vec<_>>();
+    Implemented::from_str(hook.id.as_str())
+        .unwrap()
+        .run(hook, &filenames)
+        .await
+}
+
 async fn run_meta_hook(hook: &Hook, filenames: &[&Path]) -> anyhow::Result<(i32, Vec<u8>)> {
     match hook.id.as_str() {
         "check-hooks-apply" => meta_hooks::check_hooks_apply(hook, filenames).await,
         "check-useless-excludes" => meta_hooks::check_useless_excludes(hook, filenames).await,
-        "identity" => Ok(meta_hooks::identity(hook, filenames)),
+        "identity" => meta_hooks::identity(hook, filenames).await,
         _ => unreachable!(),
     }
 }
@@ -42,7 +42,7 @@ pub(crate) async fn check_added_large_files(
     let filter = if args.enforce_all {
         FileFilter::NoFilter
     } else {
-        let add_files = get_staged_files(hook.work_dir())
+        let add_files = get_staged_files(hook.project().path())
             .await?
             .into_iter()
             .collect::<FxHashSet<_>>();
@@ -0,0 +1,86 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use clap::Parser;
+use futures::StreamExt;
+
+use crate::git::get_git_dir;
+use crate::hook::Hook;
+use crate::run::CONCURRENCY;
+
+const CONFLICT_PATTERNS: [&[u8]; 5] = [
+    b"<<<<<<< ",
+    b"======= ",
+    b"=======\r\n",
+    b"=======\n",
+    b">>>>>>> ",
+];
+
+#[derive(Parser)]
+#[command(disable_help_subcommand = true)]
+#[command(disable_version_flag = true)]
+#[command(disable_help_flag = true)]
+struct Args {
+    /// Check the files even if no merge is in progress.
+    #[arg(long)]
+    assume_in_merge: bool,
+}
+
+pub(crate) async fn check_merge_conflict(
+    hook: &Hook,
+    filenames: &[&Path],
+) -> Result<(i32, Vec<u8>)> {
+    let args = Args::try_parse_from(hook.entry.resolve(None)?.iter().chain(&hook.args))?;
+    if !args.assume_in_merge && !is_in_merge().await? {
+        return Ok((0, Vec::new()));
+    }
+
+    let mut tasks = futures::stream::iter(filenames)
+        .map(async |filename| check_file(hook.project().relative_path(), filename).await)
+        .buffered(*CONCURRENCY);
+
+    let mut code = 0;
+    let mut output = Vec::new();
+
+    while let Some(result) = tasks.next().await {
+        let (c, o) = result?;
+        code |= c;
+        output.extend(o);
+    }
+
+    Ok((code, output))
+}
+
+/// Whether a merge, or a rebase that stopped on a conflict, is in progress.
+async fn is_in_merge() -> Result<bool> {
+    let git_dir = get_git_dir().await?;
+    Ok(git_dir.join("MERGE_MSG").try_exists()?
+        && (git_dir.join("MERGE_HEAD").try_exists()?
+            || git_dir.join("rebase-apply").try_exists()?
+            || git_dir.join("rebase-merge").try_exists()?))
+}
+
+async fn check_file(file_base: &Path, filename: &Path) -> Result<(i32, Vec<u8>)> {
+    let content = fs_err::tokio::read(file_base.join(filename)).await?;
+
+    let mut code = 0;
+    let mut output = String::new();
+    for (idx, line) in content.split_inclusive(|&b| b == b'\n').enumerate() {
+        for pattern in CONFLICT_PATTERNS {
+            if line.starts_with(pattern) {
+                let pattern = String::from_utf8_lossy(pattern);
+                writeln!(
+                    output,
+                    "{}:{}: Merge conflict string '{}' found",
+                    filename.display(),
+                    idx + 1,
+                    pattern.trim()
+                )?;
+                code = 1;
+            }
+        }
+    }
+
+    Ok((code, output.into_bytes()))
+}
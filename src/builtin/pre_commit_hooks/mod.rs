@@ -7,6 +7,7 @@ use crate::hook::Hook;
 
 mod check_added_large_files;
 mod check_json;
+mod check_merge_conflict;
 mod check_toml;
 mod check_yaml;
 mod fix_byte_order_marker;
@@ -20,6 +21,7 @@ pub(crate) enum Implemented {
     EndOfFileFixer,
     FixByteOrderMarker,
     CheckJson,
+    CheckMergeConflict,
     CheckToml,
     CheckYaml,
     MixedLineEnding,
@@ -35,6 +37,7 @@ impl FromStr for Implemented {
             "end-of-file-fixer" => Ok(Self::EndOfFileFixer),
             "fix-byte-order-marker" => Ok(Self::FixByteOrderMarker),
             "check-json" => Ok(Self::CheckJson),
+            "check-merge-conflict" => Ok(Self::CheckMergeConflict),
             "check-toml" => Ok(Self::CheckToml),
             "check-yaml" => Ok(Self::CheckYaml),
             "mixed-line-ending" => Ok(Self::MixedLineEnding),
@@ -65,6 +68,9 @@ impl Implemented {
                 fix_byte_order_marker::fix_byte_order_marker(hook, filenames).await
             }
             Self::CheckJson => check_json::check_json(hook, filenames).await,
+            Self::CheckMergeConflict => {
+                check_merge_conflict::check_merge_conflict(hook, filenames).await
+            }
             Self::CheckToml => check_toml::check_toml(hook, filenames).await,
             Self::CheckYaml => check_yaml::check_yaml(hook, filenames).await,
             Self::MixedLineEnding => mixed_line_ending::mixed_line_ending(hook, filenames).await,
@@ -76,3 +82,21 @@ impl Implemented {
 pub(crate) fn is_pre_commit_hooks(url: &str) -> bool {
     url == "https://github.com/pre-commit/pre-commit-hooks"
 }
+
+/// Returns `true` if the `pre-commit-hooks` hook modifies files, regardless of whether
+/// it has a builtin implementation.
+pub(crate) fn is_fixer(hook: &Hook) -> bool {
+    match hook.id.as_str() {
+        "double-quote-string-fixer"
+        | "end-of-file-fixer"
+        | "file-contents-sorter"
+        | "fix-byte-order-marker"
+        | "fix-encoding-pragma"
+        | "requirements-txt-fixer"
+        | "sort-simple-yaml"
+        | "trailing-whitespace" => true,
+        "mixed-line-ending" => !hook.args.iter().any(|arg| arg == "--fix=no"),
+        "pretty-format-json" => hook.args.iter().any(|arg| arg == "--autofix"),
+        _ => false,
+    }
+}
@@ -0,0 +1,71 @@
+use std::hash::Hasher;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+/// A hasher for the keys of caches persisted in the store.
+///
+/// `DefaultHasher` may change between Rust releases and only has 64 bits, so keys computed with
+/// it can silently go stale or collide. This one feeds everything to SHA-256 instead, types are
+/// hashed with their `Hash` implementation as usual.
+#[derive(Default)]
+pub(crate) struct CacheKey(Sha256);
+
+impl CacheKey {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The hex digest of everything hashed so far.
+    pub(crate) fn hex(self) -> String {
+        hex::encode(self.0.finalize())
+    }
+}
+
+impl Hasher for CacheKey {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().expect("digest is 32 bytes"))
+    }
+}
+
+/// Remove the entries of a cache directory that weren't modified for `max_age`.
+pub(crate) fn prune(dir: &Path, max_age: Duration) {
+    let Ok(entries) = fs_err::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() > max_age);
+        if expired && let Err(err) = fs_err::remove_file(entry.path()) {
+            debug!("Failed to remove expired cache entry: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hash;
+
+    use super::*;
+
+    #[test]
+    fn stable_key() {
+        let mut key = CacheKey::new();
+        "prek".hash(&mut key);
+        // The digest must never change, keys are persisted across prek versions.
+        assert_eq!(
+            key.hex(),
+            "29eb1160b8ed52e0416b5c8e868ca77d567289e85eb05f48e20f43586f6c864c"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// The file that stores the checksum manifest of a directory in the store.
+pub(crate) const MANIFEST_FILE: &str = ".prek-checksums.json";
+
+/// Directories that are regenerated at run time and excluded from the manifest.
+const IGNORED_DIRS: &[&str] = &["__pycache__"];
+
+/// How thoroughly a directory is verified against its manifest.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Verify {
+    /// Only check that files exist and have the recorded size.
+    Quick,
+    /// Also hash the content of every file.
+    Full,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+struct Entry {
+    size: u64,
+    hash: String,
+}
+
+/// The size and content hash of every file in a directory, keyed by the relative path.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct Manifest {
+    files: BTreeMap<String, Entry>,
+}
+
+impl Manifest {
+    /// Build the manifest of `root`, skipping the top-level entries in `skip`.
+    pub(crate) fn build(root: &Path, skip: &[&str]) -> std::io::Result<Self> {
+        let mut manifest = Self::default();
+        manifest.walk(root, root, skip)?;
+        Ok(manifest)
+    }
+
+    fn walk(&mut self, root: &Path, dir: &Path, skip: &[&str]) -> std::io::Result<()> {
+        for entry in fs_err::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if (dir == root && skip.contains(&name.as_ref()))
+                || IGNORED_DIRS.contains(&name.as_ref())
+            {
+                continue;
+            }
+
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                self.walk(root, &path, skip)?;
+            } else {
+                self.files
+                    .insert(relative_key(root, &path), Entry::from_path(&path)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the manifest into `root`.
+    pub(crate) fn write(&self, root: &Path) -> std::io::Result<()> {
+        let content = serde_json::to_string(self)?;
+        fs_err::write(root.join(MANIFEST_FILE), content)
+    }
+
+    /// Read the manifest of `root`, returns `None` if `root` has no manifest.
+    pub(crate) fn read(root: &Path) -> std::io::Result<Option<Self>> {
+        let content = match fs_err::read_to_string(root.join(MANIFEST_FILE)) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Verify the files under `root` against the manifest.
+    ///
+    /// Returns the relative paths of missing or modified files. Files created after the
+    /// manifest was written are not reported.
+    pub(crate) fn verify(&self, root: &Path, mode: Verify) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|(key, expected)| {
+                let path = root.join(key);
+                let Ok(metadata) = fs_err::symlink_metadata(&path) else {
+                    return true;
+                };
+                if metadata.is_file() && metadata.len() != expected.size {
+                    return true;
+                }
+                match mode {
+                    Verify::Quick => false,
+                    Verify::Full => !Entry::from_path(&path).is_ok_and(|entry| entry == **expected),
+                }
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
+
+impl Entry {
+    fn from_path(path: &Path) -> std::io::Result<Self> {
+        let metadata = fs_err::symlink_metadata(path)?;
+        if metadata.is_symlink() {
+            // Record the link target, links may point outside the directory, e.g. to a toolchain.
+            let target = fs_err::read_link(path)?;
+            let mut hasher = Fnv1a::default();
+            hasher.update(target.to_string_lossy().as_bytes());
+            return Ok(Self {
+                size: 0,
+                hash: hasher.hex(),
+            });
+        }
+
+        let mut file = fs_err::File::open(path)?;
+        let mut hasher = Fnv1a::default();
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+
+        Ok(Self {
+            size: metadata.len(),
+            hash: hasher.hex(),
+        })
+    }
+}
+
+fn relative_key(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The 64-bit FNV-1a hash, it's cheap and stable, and it's only used to detect corruption.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_manifest() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path();
+        fs_err::create_dir_all(root.join("bin"))?;
+        fs_err::create_dir_all(root.join("lib/__pycache__"))?;
+        fs_err::write(root.join("bin/tool"), "#!/bin/sh\necho tool\n")?;
+        fs_err::write(root.join("lib/mod.py"), "print('hello')\n")?;
+        fs_err::write(root.join("lib/__pycache__/mod.pyc"), "compiled")?;
+        fs_err::write(root.join("marker.json"), "{}")?;
+
+        let manifest = Manifest::build(root, &["marker.json"])?;
+        manifest.write(root)?;
+        let manifest = Manifest::read(root)?.expect("manifest should exist");
+        assert!(manifest.verify(root, Verify::Full).is_empty());
+
+        // Regenerated files and new files are not considered corruption.
+        fs_err::write(root.join("lib/__pycache__/mod.pyc"), "recompiled")?;
+        fs_err::write(root.join("lib/new.py"), "")?;
+        assert!(manifest.verify(root, Verify::Full).is_empty());
+
+        // Same size, different content: only detected by a full check.
+        fs_err::write(root.join("lib/mod.py"), "print('HELLO')\n")?;
+        assert!(manifest.verify(root, Verify::Quick).is_empty());
+        assert_eq!(manifest.verify(root, Verify::Full), vec!["lib/mod.py"]);
+
+        fs_err::remove_file(root.join("bin/tool"))?;
+        assert_eq!(manifest.verify(root, Verify::Quick), vec!["bin/tool"]);
+
+        assert!(Manifest::read(&root.join("bin"))?.is_none());
+
+        Ok(())
+    }
+}
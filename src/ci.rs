@@ -0,0 +1,138 @@
+use std::fmt::Display;
+use std::sync::LazyLock;
+use std::time::SystemTime;
+
+use constants::env_vars::EnvVars;
+
+use crate::cli::ColorChoice;
+
+/// The CI environment prek runs in, if any.
+pub(crate) static CI: LazyLock<Option<Ci>> = LazyLock::new(Ci::detect);
+
+/// A CI environment, detected from the variables its runners set.
+///
+/// In CI, prek shows the diff when hooks modify files, refuses interactive prompts, and wraps
+/// the output of hooks in collapsible groups where the CI supports them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Ci {
+    GitHubActions,
+    GitLab,
+    Buildkite,
+    Jenkins,
+    /// Any other CI, detected from `CI`.
+    Other,
+}
+
+impl Ci {
+    fn detect() -> Option<Self> {
+        let is_true = |name| EnvVars::var(name).is_ok_and(|value| value == "true");
+        if is_true(EnvVars::GITHUB_ACTIONS) {
+            Some(Self::GitHubActions)
+        } else if is_true(EnvVars::GITLAB_CI) {
+            Some(Self::GitLab)
+        } else if is_true(EnvVars::BUILDKITE) {
+            Some(Self::Buildkite)
+        } else if EnvVars::is_set(EnvVars::JENKINS_URL) {
+            Some(Self::Jenkins)
+        } else if EnvVars::var(EnvVars::CI)
+            .is_ok_and(|value| !matches!(&*value, "" | "0" | "false"))
+        {
+            Some(Self::Other)
+        } else {
+            None
+        }
+    }
+
+    /// The default color choice, `None` to detect it from the terminal.
+    ///
+    /// The Jenkins console shows ANSI escape codes as is, unless a plugin renders them.
+    pub(crate) fn color(self) -> Option<ColorChoice> {
+        match self {
+            Self::Jenkins => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    /// The line that starts a collapsible group of output named `name`, if the CI supports it.
+    pub(crate) fn group_start(self, name: &str) -> Option<String> {
+        match self {
+            Self::GitHubActions => Some(format!("::group::{name}")),
+            Self::GitLab => Some(format!(
+                "\x1b[0Ksection_start:{}:{}[collapsed=true]\r\x1b[0K{name}",
+                timestamp(),
+                section_id(name)
+            )),
+            Self::Buildkite => Some(format!("--- {name}")),
+            Self::Jenkins | Self::Other => None,
+        }
+    }
+
+    /// The line that ends the collapsible group of output named `name`.
+    pub(crate) fn group_end(self, name: &str) -> Option<String> {
+        match self {
+            Self::GitHubActions => Some("::endgroup::".to_string()),
+            Self::GitLab => Some(format!(
+                "\x1b[0Ksection_end:{}:{}\r\x1b[0K",
+                timestamp(),
+                section_id(name)
+            )),
+            // Groups end where the next one starts.
+            Self::Buildkite | Self::Jenkins | Self::Other => None,
+        }
+    }
+}
+
+impl Display for Ci {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::GitHubActions => "GitHub Actions",
+            Self::GitLab => "GitLab CI",
+            Self::Buildkite => "Buildkite",
+            Self::Jenkins => "Jenkins",
+            Self::Other => "CI",
+        };
+        f.write_str(name)
+    }
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// GitLab section ids may only contain letters, digits, `_`, `.` and `-`.
+fn section_id(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_markers() {
+        assert_eq!(
+            Ci::GitHubActions.group_start("ruff").as_deref(),
+            Some("::group::ruff")
+        );
+        assert_eq!(
+            Ci::GitHubActions.group_end("ruff").as_deref(),
+            Some("::endgroup::")
+        );
+        assert_eq!(
+            Ci::Buildkite.group_start("ruff").as_deref(),
+            Some("--- ruff")
+        );
+        assert_eq!(Ci::Jenkins.group_start("ruff"), None);
+        assert_eq!(section_id("check yaml (v2)"), "check_yaml__v2_");
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use itertools::Itertools;
+use owo_colors::OwoColorize;
+
+use crate::cli::ExitStatus;
+use crate::config::{RemoteRepo, Repo, is_full_sha};
+use crate::fs::CWD;
+use crate::git;
+use crate::printer::Printer;
+use crate::store::{STORE, Store};
+use crate::workspace::Workspace;
+
+/// Check the remote repos of the workspace against supply-chain policies.
+///
+/// The `rev` of each repo must be a full commit SHA or an annotated tag, the host of the repo
+/// must be allowed if `allowed_hosts` is not empty, and the checkout in the cache must be at the
+/// commit the `rev` points to.
+pub(crate) async fn audit(
+    config: Option<PathBuf>,
+    allowed_hosts: &[String],
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let workspace_root = Workspace::find_root(config.as_deref(), &CWD)?;
+    let workspace = Workspace::discover(workspace_root, config, None, false)?;
+    let store = STORE.as_ref()?;
+
+    let repos = workspace
+        .projects()
+        .iter()
+        .flat_map(|project| &project.config().repos)
+        .filter_map(|repo| match repo {
+            Repo::Remote(repo) => Some(repo),
+            _ => None,
+        })
+        .unique()
+        .sorted_by(|a, b| a.repo.cmp(&b.repo).then_with(|| a.rev.cmp(&b.rev)))
+        .collect::<Vec<_>>();
+
+    let mut failed = 0;
+    for repo in &repos {
+        let violations = audit_repo(repo, allowed_hosts, store).await;
+        if violations.is_empty() {
+            writeln!(printer.stdout(), "{} {}", "✓".green(), repo.cyan())?;
+            continue;
+        }
+
+        failed += 1;
+        writeln!(printer.stdout(), "{} {}", "✗".red(), repo.cyan())?;
+        for violation in violations {
+            writeln!(printer.stdout(), "  - {violation}")?;
+        }
+    }
+
+    writeln!(
+        printer.stdout_important(),
+        "Audited {} repo{}, {} with violations",
+        repos.len(),
+        if repos.len() == 1 { "" } else { "s" },
+        failed
+    )?;
+
+    if failed > 0 {
+        Ok(ExitStatus::Failure)
+    } else {
+        Ok(ExitStatus::Success)
+    }
+}
+
+async fn audit_repo(repo: &RemoteRepo, allowed_hosts: &[String], store: &Store) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    if !allowed_hosts.is_empty() {
+        match repo_host(&repo.repo) {
+            Some(host) if allowed_hosts.iter().any(|h| h.eq_ignore_ascii_case(&host)) => {}
+            Some(host) => violations.push(format!("host `{}` is not allowed", host.yellow())),
+            None => violations.push("local repos are not allowed".to_string()),
+        }
+    }
+
+    // The commit the `rev` points to, if it's pinned.
+    let commit = if is_full_sha(&repo.rev) {
+        Some(repo.rev.to_ascii_lowercase())
+    } else {
+        match git::get_remote_annotated_tag(&repo.repo, &repo.rev).await {
+            Ok(Some(commit)) => Some(commit),
+            Ok(None) => {
+                violations.push(format!(
+                    "`rev` `{}` is not a full commit SHA or an annotated tag",
+                    repo.rev.yellow()
+                ));
+                None
+            }
+            Err(err) => {
+                violations.push(format!("failed to resolve `rev` `{}`: {err}", repo.rev));
+                None
+            }
+        }
+    };
+
+    // Repos are cloned when their hooks are installed, there is nothing to check before.
+    let checkout = store.repo_path(repo);
+    if let Some(commit) = commit
+        && checkout.join(".prek-repo.json").is_file()
+    {
+        match git::get_head(&checkout).await {
+            Ok(head) if head == commit => {}
+            Ok(head) => violations.push(format!(
+                "the cached checkout is at `{}`, not at `{}`",
+                head.yellow(),
+                commit
+            )),
+            Err(err) => violations.push(format!("failed to read the cached checkout: {err}")),
+        }
+    }
+
+    violations
+}
+
+/// The host of a repo URL, `None` for local paths.
+fn repo_host(repo: &str) -> Option<String> {
+    if let Ok(url) = url::Url::parse(repo) {
+        return url
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .map(str::to_ascii_lowercase);
+    }
+    // The scp-like syntax of ssh, `user@host:path`.
+    let (host, _) = repo.split_once(':')?;
+    let host = host.rsplit('@').next()?;
+    (!host.is_empty() && !host.contains(['/', '\\'])).then(|| host.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_host() {
+        assert_eq!(
+            repo_host("https://GitHub.com/pre-commit/pre-commit-hooks").as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(
+            repo_host("ssh://git@gitlab.com:2222/a/b.git").as_deref(),
+            Some("gitlab.com")
+        );
+        assert_eq!(
+            repo_host("git@github.com:a/b.git").as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(repo_host("/home/user/hooks"), None);
+        assert_eq!(repo_host("file:///home/user/hooks"), None);
+    }
+}
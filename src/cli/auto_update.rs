@@ -53,16 +53,28 @@ pub(crate) async fn auto_update(
     #[allow(clippy::mutable_key_type)]
     let mut repo_updates: FxHashMap<&RemoteRepo, Vec<RepoInfo>> = FxHashMap::default();
 
-    for project in workspace.projects() {
-        let remote_size = project
-            .config()
-            .repos
+    // Only the repos written in the config file itself can be updated, not the extended ones.
+    let raw_configs = workspace
+        .projects()
+        .iter()
+        .map(|project| {
+            if project.config().extends.is_empty() {
+                Ok(None)
+            } else {
+                config::read_raw_config(project.config_file()).map(Some)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (project, raw_config) in workspace.projects().iter().zip(&raw_configs) {
+        let repos = &raw_config.as_ref().unwrap_or(project.config()).repos;
+        let remote_size = repos
             .iter()
             .filter(|r| matches!(r, Repo::Remote(_)))
             .count();
 
         let mut remote_index = 0;
-        for repo in &project.config().repos {
+        for repo in repos {
             if let Repo::Remote(remote_repo) = repo {
                 let updates = repo_updates.entry(remote_repo).or_default();
                 updates.push(RepoInfo {
@@ -0,0 +1,181 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use owo_colors::OwoColorize;
+use rustc_hash::FxHashSet;
+
+use crate::baseline::Baseline;
+use crate::cli::ExitStatus;
+use crate::cli::reporter::{HookInitReporter, HookInstallReporter};
+use crate::cli::run::{CollectOptions, FileFilter, FileLimits, collect_files, install_hooks};
+use crate::config::Stage;
+use crate::fs::{CWD, Simplified};
+use crate::git::{self, GIT_ROOT};
+use crate::hook::{InstalledHook, Repo};
+use crate::printer::Printer;
+use crate::store::{STORE, Store};
+use crate::workspace::Workspace;
+
+/// Record the files each hook fails on, or with `update`, remove the files that pass now.
+pub(crate) async fn baseline(
+    config: Option<PathBuf>,
+    update: bool,
+    refresh: bool,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let git_root = LazyLock::force(&GIT_ROOT).as_ref()?;
+
+    let workspace_root = Workspace::find_root(config.as_deref(), &CWD)?;
+    let mut workspace = Workspace::discover(workspace_root, config, None, refresh)?;
+
+    let store = STORE.as_ref()?;
+    let lock = store.lock_async().await?;
+    let hooks = workspace
+        .init_hooks(store, Some(&HookInitReporter::from(printer)))
+        .await?;
+    // Only hooks that are passed file names can skip the recorded files.
+    let hooks = hooks
+        .into_iter()
+        .filter(|hook| {
+            hook.stages.contains(Stage::PreCommit)
+                && hook.pass_filenames
+                && !matches!(hook.repo(), Repo::Meta { .. })
+                && hook.runs_on_current_platform()
+        })
+        .map(Arc::new)
+        .collect::<Vec<_>>();
+    let hooks = install_hooks(hooks, store, &HookInstallReporter::from(printer)).await?;
+    drop(lock);
+
+    let filenames = collect_files(workspace.root(), CollectOptions::all_files()).await?;
+    std::env::set_current_dir(workspace.root()).with_context(|| {
+        format!(
+            "Failed to change directory to `{}`",
+            workspace.root().display()
+        )
+    })?;
+
+    // Hooks run against a copy of the staged files, so fixers don't modify the working tree.
+    let scratch = store.scratch_path();
+    fs_err::tokio::create_dir_all(&scratch).await?;
+    let copy = tempfile::tempdir_in(&scratch)?;
+    git::checkout_index(git_root, copy.path()).await?;
+
+    for project in workspace.projects() {
+        let previous = Baseline::read(project.path())?;
+        if update && previous.is_none() {
+            continue;
+        }
+
+        let filter = FileFilter::for_project(
+            filenames.iter(),
+            project,
+            FileLimits::default(),
+            &FxHashSet::default(),
+        );
+        let mut baseline = Baseline::default();
+        for hook in hooks.iter().filter(|hook| hook.project() == &**project) {
+            let mut filenames = filter.for_hook(hook);
+            if let Some(previous) = previous.as_ref().filter(|_| update) {
+                filenames.retain(|filename| previous.contains(&hook.id, filename));
+            }
+            let work_dir = copy.path().join(
+                project
+                    .path()
+                    .strip_prefix(git_root)
+                    .unwrap_or(project.relative_path()),
+            );
+            let failing = failing_files(hook, filenames, work_dir, store).await?;
+            baseline.insert(&hook.id, &failing);
+        }
+
+        baseline.write(project.path())?;
+        let path = Baseline::path(project.path());
+        if !baseline.is_empty() {
+            writeln!(
+                printer.stdout(),
+                "Recorded {} known failure(s) in `{}`",
+                baseline.len(),
+                path.user_display().cyan()
+            )?;
+        } else if previous.is_some() {
+            writeln!(
+                printer.stdout(),
+                "No known failures left, removed `{}`",
+                path.user_display().cyan()
+            )?;
+        } else {
+            writeln!(
+                printer.stdout(),
+                "No known failures in `{}`",
+                project.to_string().cyan()
+            )?;
+        }
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+/// Find the files `hook` fails on by bisecting `filenames`.
+///
+/// A hook passing on a batch of files passes on each of them, so only the failing batches are
+/// split until the failing files are isolated.
+async fn failing_files<'a>(
+    hook: &InstalledHook,
+    filenames: Vec<&'a Path>,
+    work_dir: PathBuf,
+    store: &Store,
+) -> Result<Vec<&'a Path>> {
+    let hook = hook.with_work_dir(work_dir);
+
+    let mut failing = Vec::new();
+    let mut batches = vec![filenames];
+    while let Some(mut batch) = batches.pop() {
+        if batch.is_empty() || passes(&hook, &batch, store).await? {
+            continue;
+        }
+        if batch.len() == 1 {
+            failing.extend(batch);
+            continue;
+        }
+        let second = batch.split_off(batch.len() / 2);
+        batches.push(second);
+        batches.push(batch);
+    }
+
+    Ok(failing.into_iter().sorted().collect())
+}
+
+/// Run `hook` on `filenames`, and revert the changes it made to them.
+async fn passes(hook: &InstalledHook, filenames: &[&Path], store: &Store) -> Result<bool> {
+    let paths = filenames
+        .iter()
+        .map(|filename| hook.project().path().join(filename))
+        .collect::<Vec<_>>();
+    let contents = paths
+        .iter()
+        .map(|path| fs_err::read(path).ok())
+        .collect::<Vec<_>>();
+
+    let (status, _) = hook
+        .language
+        .run(hook, filenames, store)
+        .await
+        .with_context(|| format!("Failed to run hook `{hook}`"))?;
+
+    let mut modified = false;
+    for (path, content) in paths.iter().zip(contents) {
+        let Some(content) = content else {
+            continue;
+        };
+        if fs_err::read(path).ok().as_ref() != Some(&content) {
+            modified = true;
+            fs_err::write(path, content)?;
+        }
+    }
+
+    Ok(status == 0 && !modified)
+}
@@ -0,0 +1,71 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+
+use crate::cli::ExitStatus;
+use crate::fs::Simplified;
+use crate::printer::Printer;
+use crate::store::STORE;
+
+/// List the configs that used the store, most recently used first.
+pub(crate) fn cache_configs(printer: Printer) -> Result<ExitStatus> {
+    let store = STORE.as_ref()?;
+
+    let mut configs = store.tracked_configs()?.into_iter().collect::<Vec<_>>();
+    if configs.is_empty() {
+        writeln!(printer.stdout(), "No configs have been used yet")?;
+        return Ok(ExitStatus::Success);
+    }
+    configs.sort_by(|(a_path, a_time), (b_path, b_time)| {
+        b_time.cmp(a_time).then_with(|| a_path.cmp(b_path))
+    });
+
+    for (path, last_used) in configs {
+        write!(
+            printer.stdout(),
+            "{} {}",
+            format_timestamp(last_used).dimmed(),
+            path.simplified_display().cyan()
+        )?;
+        if path.exists() {
+            writeln!(printer.stdout())?;
+        } else {
+            writeln!(printer.stdout(), " {}", "(missing)".yellow())?;
+        }
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+/// Format seconds since the Unix epoch as a UTC date time, e.g. `2025-09-29 08:30:00`.
+fn format_timestamp(secs: u64) -> String {
+    let (days, rem) = (secs / 86400, secs % 86400);
+    let (hour, minute, second) = (rem / 3600, rem % 3600 / 60, rem % 60);
+
+    // Convert days since the epoch to a civil date, see
+    // <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_timestamp;
+
+    #[test]
+    fn timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1_759_134_600), "2025-09-29 08:30:00");
+    }
+}
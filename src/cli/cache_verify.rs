@@ -0,0 +1,76 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+
+use crate::checksum::Verify;
+use crate::cli::ExitStatus;
+use crate::fs::long_path;
+use crate::hook::{HOOK_MARKER, InstallInfo};
+use crate::printer::Printer;
+use crate::store::STORE;
+
+/// Verify all hook environments in the store, and remove the corrupted ones.
+pub(crate) async fn cache_verify(printer: Printer) -> Result<ExitStatus> {
+    let store = STORE.as_ref()?;
+    let _lock = store.lock_async().await?;
+
+    let Ok(entries) = fs_err::read_dir(store.hooks_dir()) else {
+        writeln!(printer.stdout(), "No hook environments to verify")?;
+        return Ok(ExitStatus::Success);
+    };
+
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    paths.sort_unstable();
+
+    let mut verified = 0;
+    let mut removed = 0;
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        let corrupted = match InstallInfo::from_env_path(&path).await {
+            Ok(info) => info.verify_checksums(Verify::Full).await?,
+            // An environment without install info was never completely built.
+            Err(_) => vec![HOOK_MARKER.to_string()],
+        };
+
+        if corrupted.is_empty() {
+            verified += 1;
+            continue;
+        }
+
+        writeln!(
+            printer.stdout(),
+            "{} `{}`: {} corrupted files",
+            "Removing".yellow().bold(),
+            name.cyan(),
+            corrupted.len()
+        )?;
+        for file in corrupted.iter().take(5) {
+            writeln!(printer.stdout(), "  - {}", file.dimmed())?;
+        }
+        if corrupted.len() > 5 {
+            writeln!(printer.stdout(), "  - ... and {} more", corrupted.len() - 5)?;
+        }
+        fs_err::tokio::remove_dir_all(long_path(&path)).await?;
+        removed += 1;
+    }
+
+    writeln!(
+        printer.stdout(),
+        "Verified {} hook environments, removed {} corrupted",
+        verified.cyan(),
+        removed.cyan()
+    )?;
+    if removed > 0 {
+        writeln!(
+            printer.stdout(),
+            "Removed environments will be rebuilt the next time their hooks run"
+        )?;
+    }
+
+    Ok(ExitStatus::Success)
+}
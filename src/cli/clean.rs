@@ -7,6 +7,7 @@ use owo_colors::OwoColorize;
 use tracing::error;
 
 use crate::cli::ExitStatus;
+use crate::fs::long_path;
 use crate::printer::Printer;
 use crate::store::{CacheBucket, STORE};
 
@@ -21,7 +22,7 @@ pub(crate) fn clean(printer: Printer) -> Result<ExitStatus> {
         error!("Failed to fix permissions: {}", e);
     }
 
-    fs_err::remove_dir_all(store.path())?;
+    fs_err::remove_dir_all(long_path(store.path()))?;
     writeln!(
         printer.stdout(),
         "Cleaned `{}`",
@@ -0,0 +1,142 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::cli::ExitStatus;
+use crate::cli::run::{HookResult, HookStatus, Report};
+use crate::printer::Printer;
+
+/// Hooks faster than this (in seconds) in both reports are never reported as regressed,
+/// their timings are dominated by noise.
+const MIN_DURATION: f64 = 0.1;
+
+pub(crate) fn compare(
+    base: &Path,
+    head: &Path,
+    threshold: u32,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let base = Report::read(base)?;
+    let head = Report::read(head)?;
+
+    let base_hooks: FxHashMap<&str, &HookResult> = base
+        .hooks
+        .iter()
+        .map(|hook| (hook.full_id.as_str(), hook))
+        .collect();
+    let head_ids: FxHashSet<&str> = head
+        .hooks
+        .iter()
+        .map(|hook| hook.full_id.as_str())
+        .collect();
+
+    let mut newly_failing = Vec::new();
+    let mut newly_passing = Vec::new();
+    let mut regressed = Vec::new();
+    let mut added = Vec::new();
+
+    for hook in &head.hooks {
+        let Some(before) = base_hooks.get(hook.full_id.as_str()) else {
+            added.push(hook);
+            continue;
+        };
+
+        // Skipped hooks neither passed nor failed, and their timings mean nothing.
+        let (Some(passed_before), Some(passed)) = (outcome(before), outcome(hook)) else {
+            continue;
+        };
+        if passed_before && !passed {
+            newly_failing.push(hook);
+        } else if !passed_before && passed {
+            newly_passing.push(hook);
+        }
+
+        let limit = before.duration * (1.0 + f64::from(threshold) / 100.0);
+        if hook.duration > limit && hook.duration.max(before.duration) >= MIN_DURATION {
+            regressed.push((*before, hook));
+        }
+    }
+
+    let removed = base
+        .hooks
+        .iter()
+        .filter(|hook| !head_ids.contains(hook.full_id.as_str()))
+        .collect::<Vec<_>>();
+
+    if newly_failing.is_empty()
+        && newly_passing.is_empty()
+        && regressed.is_empty()
+        && added.is_empty()
+        && removed.is_empty()
+    {
+        writeln!(printer.stdout(), "No differences found between the reports")?;
+        return Ok(ExitStatus::Success);
+    }
+
+    if !newly_failing.is_empty() {
+        writeln!(printer.stdout(), "{}", "Newly failing hooks:".red().bold())?;
+        for hook in &newly_failing {
+            writeln!(printer.stdout(), "  - {}", hook.full_id)?;
+        }
+    }
+    if !newly_passing.is_empty() {
+        writeln!(
+            printer.stdout(),
+            "{}",
+            "Newly passing hooks:".green().bold()
+        )?;
+        for hook in &newly_passing {
+            writeln!(printer.stdout(), "  - {}", hook.full_id)?;
+        }
+    }
+    if !added.is_empty() {
+        writeln!(printer.stdout(), "{}", "Added hooks:".bold())?;
+        for hook in &added {
+            writeln!(
+                printer.stdout(),
+                "  - {} ({})",
+                hook.full_id,
+                hook.status.as_str()
+            )?;
+        }
+    }
+    if !removed.is_empty() {
+        writeln!(printer.stdout(), "{}", "Removed hooks:".bold())?;
+        for hook in &removed {
+            writeln!(printer.stdout(), "  - {}", hook.full_id)?;
+        }
+    }
+    if !regressed.is_empty() {
+        writeln!(
+            printer.stdout(),
+            "{}",
+            "Timing regressions:".yellow().bold()
+        )?;
+        for (before, after) in &regressed {
+            writeln!(
+                printer.stdout(),
+                "  - {}: {:.2}s -> {:.2}s",
+                after.full_id,
+                before.duration,
+                after.duration,
+            )?;
+        }
+    }
+
+    if newly_failing.is_empty() && !added.iter().any(|hook| hook.failed()) {
+        Ok(ExitStatus::Success)
+    } else {
+        Ok(ExitStatus::Failure)
+    }
+}
+
+/// Whether the hook passed, `None` if it didn't run.
+fn outcome(hook: &HookResult) -> Option<bool> {
+    match hook.status {
+        HookStatus::Skipped | HookStatus::DryRun => None,
+        _ => Some(!hook.failed()),
+    }
+}
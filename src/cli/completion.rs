@@ -1,20 +1,44 @@
 use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::OsStr;
+use std::fmt::Write;
 use std::path::Path;
 
+use anyhow::Result;
 use clap::builder::StyledStr;
-use clap_complete::CompletionCandidate;
+use clap_complete::env::Shells;
+use clap_complete::{CompletionCandidate, Shell};
 
+use crate::cli::ExitStatus;
 use crate::config;
 use crate::fs::CWD;
+use crate::printer::Printer;
 use crate::workspace::{Project, Workspace};
 
+/// Print the script registering the completions of `shell`.
+///
+/// The script calls prek with `COMPLETE=<shell>` to complete, which is handled by
+/// `CompleteEnv` in `main`, so candidates like hook ids are computed when completing.
+pub(crate) fn completions(shell: Shell, printer: Printer) -> Result<ExitStatus> {
+    let shells = Shells::builtins();
+    let Some(completer) = shells.completer(&shell.to_string()) else {
+        anyhow::bail!("Completions are not supported for `{shell}`");
+    };
+
+    let mut script = Vec::new();
+    completer.write_registration("COMPLETE", "prek", "prek", "prek", &mut script)?;
+    write!(printer.stdout(), "{}", String::from_utf8_lossy(&script))?;
+
+    Ok(ExitStatus::Success)
+}
+
 /// Provide completion candidates for `include` and `skip` selectors.
 pub(crate) fn selector_completer(current: &OsStr) -> Vec<CompletionCandidate> {
     let Some(current_str) = current.to_str() else {
         return vec![];
     };
 
+    // Completing must never wait for the network.
+    config::set_offline();
     let Ok(workspace) = Workspace::find_root(None, &CWD)
         .and_then(|root| Workspace::discover(root, None, None, false))
     else {
@@ -169,6 +193,11 @@ fn all_hooks(proj: &Project) -> Vec<(String, Option<String>)> {
                     out.push((h.0.id.clone(), Some(h.0.name.clone())));
                 }
             }
+            config::Repo::Builtin(cfg) => {
+                for h in &cfg.hooks {
+                    out.push((h.0.id.clone(), Some(h.0.name.clone())));
+                }
+            }
         }
     }
     out
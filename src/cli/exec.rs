@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use itertools::Itertools;
+
+use crate::cli::ExitStatus;
+use crate::cli::reporter::{HookInitReporter, HookInstallReporter};
+use crate::cli::run::{Selectors, install_hooks};
+use crate::config::Language;
+use crate::fs::CWD;
+use crate::hook_env;
+use crate::languages::{env_path, resolve_command};
+use crate::printer::Printer;
+use crate::process::Cmd;
+use crate::store::STORE;
+use crate::workspace::Workspace;
+
+/// Run a command in the environment of a hook, installing the environment if needed.
+pub(crate) async fn exec(
+    config: Option<PathBuf>,
+    hook_id: String,
+    command: Vec<String>,
+    refresh: bool,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let workspace_root = Workspace::find_root(config.as_deref(), &CWD)?;
+    let selectors = Selectors::load(&[hook_id], &[], &workspace_root)?;
+    let mut workspace = Workspace::discover(workspace_root, config, Some(&selectors), refresh)?;
+
+    let store = STORE.as_ref()?;
+    let lock = store.lock_async().await?;
+    let hooks = workspace
+        .init_hooks(store, Some(&HookInitReporter::from(printer)))
+        .await?;
+
+    let mut filtered_hooks: Vec<_> = hooks
+        .iter()
+        .filter(|h| selectors.matches_hook(h))
+        .cloned()
+        .collect();
+    selectors.report_unused(&hooks);
+    let hook = match filtered_hooks.len() {
+        0 => return Ok(ExitStatus::Failure),
+        1 => filtered_hooks.remove(0),
+        _ => bail!(
+            "The hook matches {} hooks: {}, select one with `<project-path>:<hook-id>`",
+            filtered_hooks.len(),
+            filtered_hooks
+                .iter()
+                .map(|h| format!("`{}`", h.full_id()))
+                .join(", ")
+        ),
+    };
+    if matches!(hook.language, Language::Docker | Language::DockerImage) {
+        bail!(
+            "`{}` runs in a Docker container, run the command with `docker run` instead",
+            hook.full_id()
+        );
+    }
+
+    let mut hooks = install_hooks(
+        vec![Arc::new(hook)],
+        store,
+        &HookInstallReporter::from(printer),
+    )
+    .await?;
+    drop(lock);
+    let hook = hooks.remove(0);
+
+    let env_vars = hook.language.env_vars(&hook, store)?;
+    let command = resolve_command(command, env_path(&env_vars));
+    // The command runs in the current directory, so its arguments can be relative to it.
+    let status = Cmd::new(&command[0], "exec command")
+        .args(&command[1..])
+        .env_vars(&env_vars)
+        .envs(
+            hook.env
+                .iter()
+                .map(|(key, value)| (key, hook_env::interpolate(value))),
+        )
+        .check(false)
+        .status()
+        .await?;
+
+    Ok(match status.code() {
+        Some(0) => ExitStatus::Success,
+        Some(code) => ExitStatus::External(u8::try_from(code).unwrap_or(1)),
+        None => ExitStatus::Failure,
+    })
+}
@@ -1,10 +1,12 @@
+use std::collections::BTreeSet;
 use std::ffi::OsString;
 use std::io::{self, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anstream::eprintln;
 use anyhow::Result;
 use owo_colors::OwoColorize;
+use tracing::debug;
 
 use constants::env_vars::EnvVars;
 
@@ -16,6 +18,13 @@ use crate::workspace;
 use crate::workspace::Project;
 use crate::{git, warn_user};
 
+/// Whether a missing config skips the hooks instead of failing them.
+///
+/// Enabled by installing the hooks with `--allow-missing-config`, or with `PREK_ALLOW_NO_CONFIG`.
+pub(crate) fn allow_missing_config(skip_on_missing_config: bool) -> bool {
+    skip_on_missing_config || EnvVars::is_set(EnvVars::PREK_ALLOW_NO_CONFIG)
+}
+
 pub(crate) async fn hook_impl(
     config: Option<PathBuf>,
     includes: Vec<String>,
@@ -37,8 +46,7 @@ pub(crate) async fn hook_impl(
         );
     }
 
-    let allow_missing_config =
-        skip_on_missing_config || EnvVars::is_set(EnvVars::PREK_ALLOW_NO_CONFIG);
+    let allow_missing_config = allow_missing_config(skip_on_missing_config);
     let warn_for_no_config = || {
         eprintln!(
             "- To temporarily silence this, run `{}`",
@@ -55,6 +63,7 @@ pub(crate) async fn hook_impl(
     if let Some(ref config) = config {
         if !config.try_exists()? {
             return if allow_missing_config {
+                debug!("Config file `{}` not found, skipping", config.display());
                 Ok(ExitStatus::Success)
             } else {
                 eprintln!(
@@ -72,6 +81,7 @@ pub(crate) async fn hook_impl(
         match Project::discover(config.as_deref(), &CWD) {
             Err(e) if matches!(e, workspace::Error::MissingPreCommitConfig) => {
                 return if allow_missing_config {
+                    debug!("No config file found, skipping");
                     Ok(ExitStatus::Success)
                 } else {
                     eprintln!("{}: {e}", "error".red().bold());
@@ -94,25 +104,31 @@ pub(crate) async fn hook_impl(
         return Ok(ExitStatus::Success);
     };
 
-    cli::run(
-        config,
-        includes,
-        skips,
-        hook_type.into(),
-        run_args.from_ref,
-        run_args.to_ref,
-        run_args.all_files,
-        vec![],
-        vec![],
-        false, // last_commit is always false in hook implementation context
-        false,
-        false,
-        false,
-        run_args.extra,
-        false,
+    let status = Box::pin(cli::run(
+        cli::RunOptions {
+            config,
+            includes,
+            skips,
+            hook_stage: hook_type.into(),
+            from_ref: run_args.from_ref,
+            to_ref: run_args.to_ref,
+            all_files: run_args.all_files,
+            files: run_args.files,
+            extra_args: run_args.extra,
+            output: run_args.output,
+            ..cli::RunOptions::default()
+        },
         printer,
-    )
-    .await
+    ))
+    .await?;
+
+    // `post-*` hooks are informational, their failures are reported but must not turn into
+    // a failing exit code, e.g. git uses the status of `post-checkout` as its own.
+    if hook_type.is_post() && matches!(status, ExitStatus::Failure) {
+        return Ok(ExitStatus::Success);
+    }
+
+    Ok(status)
 }
 
 async fn to_run_args(hook_type: HookType, args: &[OsString]) -> Option<RunArgs> {
@@ -124,15 +140,26 @@ async fn to_run_args(hook_type: HookType, args: &[OsString]) -> Option<RunArgs>
             run_args.extra.remote_name = Some(args[0].to_string_lossy().into_owned());
             run_args.extra.remote_url = Some(args[1].to_string_lossy().into_owned());
 
-            if let Some(push_info) = parse_pre_push_info(&args[0].to_string_lossy()).await {
-                run_args.from_ref = push_info.from_ref;
-                run_args.to_ref = push_info.to_ref;
-                run_args.all_files = push_info.all_files;
-                run_args.extra.remote_branch = push_info.remote_branch;
-                run_args.extra.local_branch = push_info.local_branch;
-            } else {
-                // Nothing to push
-                return None;
+            let pushes = parse_pre_push_info(&args[0].to_string_lossy()).await;
+            let all_files = pushes.iter().any(|push| push.all_files);
+            let multiple = pushes.len() > 1;
+            if multiple && !all_files {
+                // Multiple refs are pushed at once, run on the union of the changed files.
+                run_args.files = changed_files_of_pushes(&pushes).await;
+                if run_args.files.is_empty() {
+                    return None;
+                }
+            }
+
+            // Nothing to push
+            let first = pushes.into_iter().next()?;
+            run_args.extra.remote_branch = first.remote_branch;
+            run_args.extra.local_branch = first.local_branch;
+            if all_files {
+                run_args.all_files = true;
+            } else if !multiple {
+                run_args.from_ref = first.from_ref;
+                run_args.to_ref = first.to_ref;
             }
         }
         HookType::CommitMsg => {
@@ -178,77 +205,103 @@ struct PushInfo {
     local_branch: Option<String>,
 }
 
-async fn parse_pre_push_info(remote_name: &str) -> Option<PushInfo> {
+/// Parse the `<local ref> <local sha> <remote ref> <remote sha>` lines that git passes to
+/// the `pre-push` hook on stdin, returns the range to check for each pushed ref.
+async fn parse_pre_push_info(remote_name: &str) -> Vec<PushInfo> {
     // Read from stdin
     let mut stdin = io::stdin();
     let mut buffer = String::new();
 
     if stdin.read_to_string(&mut buffer).is_err() {
-        return None;
+        return vec![];
     }
 
-    let z40 = "0".repeat(40);
-
+    let mut pushes = Vec::new();
     for line in buffer.lines() {
-        let parts: Vec<&str> = line.rsplitn(4, ' ').collect();
-        if parts.len() != 4 {
-            continue;
+        if let Some(push) = parse_pre_push_line(line, remote_name).await {
+            pushes.push(push);
         }
+    }
+    pushes
+}
 
-        let local_branch = parts[3];
-        let local_sha = parts[2];
-        let remote_branch = parts[1];
-        let remote_sha = parts[0];
+async fn parse_pre_push_line(line: &str, remote_name: &str) -> Option<PushInfo> {
+    let z40 = "0".repeat(40);
 
-        // Skip if local_sha is all zeros
-        if local_sha == z40 {
-            continue;
-        }
+    let parts: Vec<&str> = line.rsplitn(4, ' ').collect();
+    if parts.len() != 4 {
+        return None;
+    }
 
-        // If remote_sha exists and is not all zeros, and remote SHA exists
-        if remote_sha != z40 && git::rev_exists(remote_sha).await.unwrap_or(false) {
-            return Some(PushInfo {
-                from_ref: Some(remote_sha.to_string()),
-                to_ref: Some(local_sha.to_string()),
-                all_files: false,
-                remote_branch: Some(remote_branch.to_string()),
-                local_branch: Some(local_branch.to_string()),
-            });
-        }
+    let local_branch = parts[3];
+    let local_sha = parts[2];
+    let remote_branch = parts[1];
+    let remote_sha = parts[0];
 
-        // Find ancestors that don't exist in remote
-        let ancestors = git::get_ancestors_not_in_remote(local_sha, remote_name)
-            .await
-            .unwrap_or_default();
-        if ancestors.is_empty() {
-            continue;
-        }
+    // Deleting a remote branch, nothing to check.
+    if local_sha == z40 {
+        return None;
+    }
 
-        let first_ancestor = &ancestors[0];
-        let roots = git::get_root_commits(local_sha).await.unwrap_or_default();
-
-        if roots.contains(first_ancestor) {
-            // Pushing the whole tree including root commit
-            return Some(PushInfo {
-                from_ref: None,
-                to_ref: Some(local_sha.to_string()),
-                all_files: true,
-                remote_branch: Some(remote_branch.to_string()),
-                local_branch: Some(local_branch.to_string()),
-            });
-        }
-        // Find the source (first_ancestor^)
-        if let Ok(Some(source)) = git::get_parent_commit(first_ancestor).await {
-            return Some(PushInfo {
-                from_ref: Some(source),
-                to_ref: Some(local_sha.to_string()),
-                all_files: false,
-                remote_branch: Some(remote_branch.to_string()),
-                local_branch: Some(local_branch.to_string()),
-            });
+    // Updating an existing remote branch whose tip we know about.
+    if remote_sha != z40 && git::rev_exists(remote_sha).await.unwrap_or(false) {
+        return Some(PushInfo {
+            from_ref: Some(remote_sha.to_string()),
+            to_ref: Some(local_sha.to_string()),
+            all_files: false,
+            remote_branch: Some(remote_branch.to_string()),
+            local_branch: Some(local_branch.to_string()),
+        });
+    }
+
+    // A new branch, or the remote tip is unknown: find ancestors that don't exist in remote.
+    let ancestors = git::get_ancestors_not_in_remote(local_sha, remote_name)
+        .await
+        .unwrap_or_default();
+    let first_ancestor = ancestors.first()?;
+    let roots = git::get_root_commits(local_sha).await.unwrap_or_default();
+
+    if roots.contains(first_ancestor) {
+        // Pushing the whole tree including root commit
+        return Some(PushInfo {
+            from_ref: None,
+            to_ref: Some(local_sha.to_string()),
+            all_files: true,
+            remote_branch: Some(remote_branch.to_string()),
+            local_branch: Some(local_branch.to_string()),
+        });
+    }
+
+    // Find the source (first_ancestor^)
+    let Ok(Some(source)) = git::get_parent_commit(first_ancestor).await else {
+        return None;
+    };
+    Some(PushInfo {
+        from_ref: Some(source),
+        to_ref: Some(local_sha.to_string()),
+        all_files: false,
+        remote_branch: Some(remote_branch.to_string()),
+        local_branch: Some(local_branch.to_string()),
+    })
+}
+
+/// Collect the files changed in all pushed ranges.
+///
+/// Files that don't exist in the working tree, e.g. files only present on a pushed branch
+/// that is not checked out, are left out.
+async fn changed_files_of_pushes(pushes: &[PushInfo]) -> Vec<PathBuf> {
+    let mut files = BTreeSet::new();
+    for push in pushes {
+        let (Some(from_ref), Some(to_ref)) = (&push.from_ref, &push.to_ref) else {
+            continue;
+        };
+        match git::get_changed_files(from_ref, to_ref, Path::new(".")).await {
+            Ok(changed) => files.extend(changed),
+            Err(err) => {
+                debug!("Failed to get files changed between {from_ref} and {to_ref}: {err}");
+            }
         }
     }
 
-    // Nothing to push
-    None
+    files.into_iter().filter(|file| file.exists()).collect()
 }
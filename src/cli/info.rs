@@ -0,0 +1,162 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+
+use crate::cli::ExitStatus;
+use crate::cli::reporter::HookInitReporter;
+use crate::cli::run::Selectors;
+use crate::config::Language;
+use crate::fs::CWD;
+use crate::hook::{InstalledHook, Repo};
+use crate::languages::env_path;
+use crate::printer::Printer;
+use crate::store::{STORE, Store};
+use crate::workspace::Workspace;
+
+pub(crate) async fn info(
+    config: Option<PathBuf>,
+    hook_id: String,
+    refresh: bool,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let workspace_root = Workspace::find_root(config.as_deref(), &CWD)?;
+    let selectors = Selectors::load(&[hook_id], &[], &workspace_root)?;
+    let mut workspace = Workspace::discover(workspace_root, config, Some(&selectors), refresh)?;
+
+    let store = STORE.as_ref()?;
+    let reporter = HookInitReporter::from(printer);
+    let lock = store.lock_async().await?;
+    let hooks = workspace.init_hooks(store, Some(&reporter)).await?;
+    drop(lock);
+
+    let filtered_hooks: Vec<_> = hooks
+        .iter()
+        .filter(|h| selectors.matches_hook(h))
+        .cloned()
+        .collect();
+    selectors.report_unused(&hooks);
+    if filtered_hooks.is_empty() {
+        return Ok(ExitStatus::Failure);
+    }
+
+    let installed = store.installed_hooks().await;
+    for hook in filtered_hooks {
+        let info = installed.iter().find(|info| info.matches(&hook)).cloned();
+        let hook = Arc::new(hook);
+        // Hooks whose environment isn't installed are shown like hooks that don't need one.
+        let installed_hook = match info {
+            Some(info) => InstalledHook::Installed { hook, info },
+            None => InstalledHook::NoNeedInstall(hook),
+        };
+        print_hook(&installed_hook, store, printer)?;
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+fn field(printer: Printer, name: &str, value: impl std::fmt::Display) -> std::fmt::Result {
+    writeln!(
+        printer.stdout(),
+        "  {} {}",
+        format!("{name}:").bold().cyan(),
+        value
+    )
+}
+
+fn print_hook(hook: &InstalledHook, store: &Store, printer: Printer) -> Result<()> {
+    writeln!(printer.stdout(), "{}", hook.full_id().bold())?;
+
+    field(printer, "ID", &hook.id)?;
+    field(printer, "Name", &hook.name)?;
+    match hook.repo() {
+        Repo::Remote { url, rev, path, .. } => {
+            field(printer, "Repo", url)?;
+            field(printer, "Rev", rev)?;
+            field(printer, "Checkout", path.display())?;
+        }
+        repo => field(printer, "Repo", repo)?,
+    }
+    field(printer, "Language", hook.language.as_str())?;
+    field(printer, "Entry", hook.entry.raw())?;
+    if !hook.args.is_empty() {
+        field(printer, "Args", join(&hook.args))?;
+    }
+    if let Some(files) = &hook.files {
+        field(printer, "Files", files.as_str())?;
+    }
+    if let Some(exclude) = &hook.exclude {
+        field(printer, "Exclude", exclude.as_str())?;
+    }
+    for (name, types) in [
+        ("Types", &hook.types),
+        ("Types or", &hook.types_or),
+        ("Exclude types", &hook.exclude_types),
+    ] {
+        if !types.is_empty() {
+            field(printer, name, types.join(", "))?;
+        }
+    }
+    field(printer, "Stages", &hook.stages)?;
+    field(printer, "Work dir", hook.work_dir().display())?;
+
+    let env_vars = match hook {
+        InstalledHook::Installed { info, .. } => {
+            field(printer, "Environment", info.env_path.display())?;
+            field(printer, "Language version", &info.language_version)?;
+            if !info.toolchain.as_os_str().is_empty() {
+                field(printer, "Toolchain", info.toolchain.display())?;
+            }
+            hook.language.env_vars(hook, store)?
+        }
+        InstalledHook::NoNeedInstall(_) => {
+            if hook.language.supports_install_env() {
+                field(printer, "Environment", "not installed")?;
+            }
+            Vec::new()
+        }
+    };
+
+    if !env_vars.is_empty() || !hook.env.is_empty() {
+        writeln!(
+            printer.stdout(),
+            "  {}",
+            "Environment variables:".bold().cyan()
+        )?;
+        for (key, value) in &env_vars {
+            match value {
+                Some(value) => writeln!(printer.stdout(), "    {key}={}", value.to_string_lossy())?,
+                None => writeln!(printer.stdout(), "    {key} (unset)")?,
+            }
+        }
+        for (key, value) in &hook.env {
+            writeln!(printer.stdout(), "    {key}={value}")?;
+        }
+    }
+
+    // The entry of an environment that isn't installed can't be resolved yet, and Docker
+    // entries run in the container.
+    let mut command = match hook {
+        InstalledHook::Installed { .. } => hook.entry.resolve(env_path(&env_vars))?,
+        InstalledHook::NoNeedInstall(_) if hook.language == Language::System => {
+            hook.entry.resolve(None)?
+        }
+        InstalledHook::NoNeedInstall(_) => hook.entry.split()?,
+    };
+    command.extend(hook.args.iter().cloned());
+    let mut command = join(&command);
+    if hook.pass_filenames {
+        command.push_str(" [FILES]");
+    }
+    field(printer, "Command", command)?;
+
+    writeln!(printer.stdout())?;
+    Ok(())
+}
+
+/// Join the arguments of a command, quoted like a shell would need them.
+fn join(args: &[String]) -> String {
+    shlex::try_join(args.iter().map(String::as_str)).unwrap_or_else(|_| args.join(" "))
+}
@@ -6,6 +6,7 @@ use std::sync::Arc;
 use anyhow::Result;
 use bstr::ByteSlice;
 use owo_colors::OwoColorize;
+use path_clean::PathClean;
 use same_file::is_same_file;
 
 use crate::cli::reporter::{HookInitReporter, HookInstallReporter};
@@ -13,7 +14,7 @@ use crate::cli::run;
 use crate::cli::run::{SelectorSource, Selectors};
 use crate::cli::{ExitStatus, HookType};
 use crate::fs::{CWD, Simplified};
-use crate::git::git_cmd;
+use crate::git::{GIT_ROOT, git_cmd};
 use crate::printer::Printer;
 use crate::store::STORE;
 use crate::workspace::{Project, Workspace};
@@ -57,10 +58,31 @@ pub(crate) async fn install(
         None
     };
 
+    // Git runs hooks from the root of the working tree, and all linked worktrees share the
+    // hooks, so paths are relative to the git root. The template directory is used by many
+    // repositories so its paths are kept as is.
+    let (hook_config, hook_project_dir) = if git_dir.is_none() {
+        (
+            config
+                .as_deref()
+                .map(config_path_from_git_root)
+                .transpose()?,
+            project
+                .as_ref()
+                .map(|project| path_from_git_root(project.path()))
+                .transpose()?,
+        )
+    } else {
+        (
+            config.clone(),
+            project.as_ref().map(|project| project.path().to_path_buf()),
+        )
+    };
+
     for hook_type in hook_types {
         install_hook_script(
-            project.as_ref(),
-            config.clone(),
+            hook_project_dir.as_deref(),
+            hook_config.clone(),
             selectors.as_ref(),
             hook_type,
             &hooks_path,
@@ -126,8 +148,23 @@ fn get_hook_types(project: Option<&Project>, hook_types: Vec<HookType>) -> Vec<H
     hook_types
 }
 
+/// Make a `--config` path relative to the git root, or absolute if it's outside the repository.
+fn config_path_from_git_root(config: &Path) -> Result<PathBuf> {
+    path_from_git_root(&CWD.join(config).clean())
+}
+
+/// Make an absolute path relative to the git root, or keep it if it's outside the repository.
+fn path_from_git_root(path: &Path) -> Result<PathBuf> {
+    let git_root = GIT_ROOT.as_ref()?;
+    match path.strip_prefix(git_root) {
+        Ok(relative) if relative.as_os_str().is_empty() => Ok(PathBuf::from(".")),
+        Ok(relative) => Ok(relative.to_path_buf()),
+        Err(_) => Ok(path.to_path_buf()),
+    }
+}
+
 fn install_hook_script(
-    project: Option<&Project>,
+    project_dir: Option<&Path>,
     config: Option<PathBuf>,
     selectors: Option<&Selectors>,
     hook_type: HookType,
@@ -196,8 +233,8 @@ fn install_hook_script(
     // we're different with `pre-commit` which always sets `--config=.pre-commit-config.yaml`.
     if let Some(config) = config {
         args.push(format!(r#"--config="{}""#, config.display()));
-    } else if let Some(project) = project {
-        args.push(format!(r#"--cd="{}""#, project.path().display()));
+    } else if let Some(project_dir) = project_dir {
+        args.push(format!(r#"--cd="{}""#, project_dir.display()));
     }
 
     if skip_on_missing_config {
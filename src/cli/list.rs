@@ -1,5 +1,7 @@
+use std::collections::BTreeMap;
 use std::fmt::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use clap::ValueEnum;
 use owo_colors::OwoColorize;
@@ -10,7 +12,7 @@ use crate::cli::run::Selectors;
 use crate::cli::{ExitStatus, ListOutputFormat};
 use crate::config::{Language, Stage};
 use crate::fs::CWD;
-use crate::hook;
+use crate::hook::{self, Hook, InstallInfo, Repo};
 use crate::printer::Printer;
 use crate::store::STORE;
 use crate::workspace::Workspace;
@@ -21,11 +23,58 @@ struct SerializableHook {
     full_id: String,
     name: String,
     alias: String,
+    /// The URL of the repo, or `local` or `meta`.
+    repo: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rev: Option<String>,
     language: Language,
     description: Option<String>,
+    homepage: Option<String>,
     stages: Vec<Stage>,
+    /// Whether the hook is ready to run, it's true for hooks that don't need an environment.
+    installed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    env_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
 }
 
+/// The environment of a hook.
+#[derive(Copy, Clone)]
+enum Environment<'a> {
+    /// The language of the hook doesn't need an environment.
+    NotNeeded,
+    NotInstalled,
+    Installed(&'a Path),
+}
+
+impl<'a> Environment<'a> {
+    fn of(hook: &Hook, installed: &'a [Arc<InstallInfo>]) -> Self {
+        if !hook.language.supports_install_env() {
+            return Self::NotNeeded;
+        }
+        installed
+            .iter()
+            .find(|info| info.matches(hook))
+            .map_or(Self::NotInstalled, |info| Self::Installed(&info.env_path))
+    }
+}
+
+#[derive(Serialize)]
+struct SerializableGroup<'a> {
+    name: &'a str,
+    hooks: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SerializableProject {
+    path: String,
+    config: String,
+}
+
+#[allow(clippy::fn_params_excessive_bools)]
 pub(crate) async fn list(
     config: Option<PathBuf>,
     includes: Vec<String>,
@@ -33,6 +82,8 @@ pub(crate) async fn list(
     hook_stage: Option<Stage>,
     language: Option<Language>,
     output_format: ListOutputFormat,
+    projects: bool,
+    groups: bool,
     refresh: bool,
     verbose: bool,
     printer: Printer,
@@ -41,6 +92,10 @@ pub(crate) async fn list(
     let selectors = Selectors::load(&includes, &skips, &workspace_root)?;
     let mut workspace = Workspace::discover(workspace_root, config, Some(&selectors), refresh)?;
 
+    if projects {
+        return list_projects(&workspace, output_format, printer);
+    }
+
     let store = STORE.as_ref()?;
     let reporter = HookInitReporter::from(printer);
     let lock = store.lock_async().await?;
@@ -49,18 +104,29 @@ pub(crate) async fn list(
     drop(lock);
 
     let filtered_hooks: Vec<_> = hooks
-        .into_iter()
+        .iter()
         .filter(|h| selectors.matches_hook(h))
         .filter(|h| hook_stage.is_none_or(|hook_stage| h.stages.contains(hook_stage)))
         .filter(|h| language.is_none_or(|lang| h.language == lang))
+        .cloned()
         .collect();
 
-    selectors.report_unused();
+    selectors.report_unused(&hooks);
+
+    if groups {
+        return list_groups(&filtered_hooks, output_format, printer);
+    }
+
+    // Checking the installed environments is slow, only do it when they are shown.
+    let installed = if verbose || matches!(output_format, ListOutputFormat::Json) {
+        store.installed_hooks().await
+    } else {
+        Vec::new()
+    };
 
     match output_format {
         ListOutputFormat::Text => {
             if verbose {
-                // TODO: show repo path and environment path (if installed)
                 for hook in &filtered_hooks {
                     writeln!(printer.stdout(), "{}", hook.full_id().bold())?;
 
@@ -87,6 +153,23 @@ pub(crate) async fn list(
                             description
                         )?;
                     }
+                    if let Some(homepage) = &hook.homepage {
+                        writeln!(
+                            printer.stdout(),
+                            "  {} {}",
+                            "Homepage:".bold().cyan(),
+                            homepage
+                        )?;
+                    }
+                    match hook.repo() {
+                        Repo::Remote { url, rev, .. } => {
+                            writeln!(printer.stdout(), "  {} {}", "Repo:".bold().cyan(), url)?;
+                            writeln!(printer.stdout(), "  {} {}", "Rev:".bold().cyan(), rev)?;
+                        }
+                        repo => {
+                            writeln!(printer.stdout(), "  {} {}", "Repo:".bold().cyan(), repo)?;
+                        }
+                    }
                     writeln!(
                         printer.stdout(),
                         "  {} {}",
@@ -99,6 +182,35 @@ pub(crate) async fn list(
                         "Stages:".bold().cyan(),
                         hook.stages
                     )?;
+                    if let Some(group) = &hook.group {
+                        writeln!(printer.stdout(), "  {} {}", "Group:".bold().cyan(), group)?;
+                    }
+                    if !hook.tags.is_empty() {
+                        writeln!(
+                            printer.stdout(),
+                            "  {} {}",
+                            "Tags:".bold().cyan(),
+                            hook.tags.join(", ")
+                        )?;
+                    }
+                    match Environment::of(hook, &installed) {
+                        Environment::NotNeeded => {}
+                        Environment::NotInstalled => {
+                            writeln!(
+                                printer.stdout(),
+                                "  {} not installed",
+                                "Environment:".bold().cyan()
+                            )?;
+                        }
+                        Environment::Installed(path) => {
+                            writeln!(
+                                printer.stdout(),
+                                "  {} {}",
+                                "Environment:".bold().cyan(),
+                                path.display()
+                            )?;
+                        }
+                    }
                     writeln!(printer.stdout())?;
                 }
             } else {
@@ -114,6 +226,16 @@ pub(crate) async fn list(
                 .map(|h| {
                     let id = h.id.to_string();
                     let full_id = h.full_id();
+                    let (repo, rev) = match h.repo() {
+                        Repo::Remote { url, rev, .. } => (url.clone(), Some(rev.clone())),
+                        repo => (repo.to_string(), None),
+                    };
+                    let environment = Environment::of(&h, &installed);
+                    let env_path = if let Environment::Installed(path) = environment {
+                        Some(path.to_string_lossy().into_owned())
+                    } else {
+                        None
+                    };
                     let stages = match h.stages {
                         hook::Stages::All => Stage::value_variants().to_vec(),
                         hook::Stages::Some(s) => s.into_iter().collect(),
@@ -123,9 +245,16 @@ pub(crate) async fn list(
                         full_id,
                         name: h.name,
                         alias: h.alias,
+                        repo,
+                        rev,
                         language: h.language,
                         description: h.description,
+                        homepage: h.homepage,
                         stages,
+                        installed: !matches!(environment, Environment::NotInstalled),
+                        env_path,
+                        group: h.group,
+                        tags: h.tags,
                     }
                 })
                 .collect();
@@ -137,3 +266,76 @@ pub(crate) async fn list(
 
     Ok(ExitStatus::Success)
 }
+
+/// List the groups of the hooks, in alphabetical order, with the hooks of each group.
+fn list_groups(
+    hooks: &[Hook],
+    output_format: ListOutputFormat,
+    printer: Printer,
+) -> anyhow::Result<ExitStatus> {
+    let mut groups: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for hook in hooks {
+        if let Some(group) = &hook.group {
+            groups
+                .entry(group.as_str())
+                .or_default()
+                .push(hook.full_id());
+        }
+    }
+
+    match output_format {
+        ListOutputFormat::Text => {
+            for (group, hooks) in &groups {
+                writeln!(printer.stdout(), "{group}")?;
+                for hook in hooks {
+                    writeln!(printer.stdout(), "  {hook}")?;
+                }
+            }
+        }
+        ListOutputFormat::Json => {
+            let serializable_groups: Vec<_> = groups
+                .into_iter()
+                .map(|(name, hooks)| SerializableGroup { name, hooks })
+                .collect();
+
+            let json_output = serde_json::to_string_pretty(&serializable_groups)?;
+            writeln!(printer.stdout(), "{json_output}")?;
+        }
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+fn list_projects(
+    workspace: &Workspace,
+    output_format: ListOutputFormat,
+    printer: Printer,
+) -> anyhow::Result<ExitStatus> {
+    match output_format {
+        ListOutputFormat::Text => {
+            for project in workspace.projects() {
+                writeln!(printer.stdout(), "{project}")?;
+            }
+        }
+        ListOutputFormat::Json => {
+            let serializable_projects: Vec<_> = workspace
+                .projects()
+                .iter()
+                .map(|p| SerializableProject {
+                    path: p.to_string(),
+                    config: p
+                        .config_file()
+                        .strip_prefix(workspace.root())
+                        .unwrap_or(p.config_file())
+                        .to_string_lossy()
+                        .into_owned(),
+                })
+                .collect();
+
+            let json_output = serde_json::to_string_pretty(&serializable_projects)?;
+            writeln!(printer.stdout(), "{json_output}")?;
+        }
+    }
+
+    Ok(ExitStatus::Success)
+}
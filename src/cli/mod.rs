@@ -1,42 +1,73 @@
 use std::ffi::OsString;
 use std::path::PathBuf;
 use std::process::ExitCode;
+use std::time::Duration;
 
 use clap::builder::styling::{AnsiColor, Effects};
 use clap::builder::{ArgPredicate, Styles};
 use clap::{ArgAction, Args, Parser, Subcommand, ValueHint};
 use clap_complete::engine::ArgValueCompleter;
+use fancy_regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use constants::CONFIG_FILE;
 use constants::env_vars::EnvVars;
 
-use crate::config::{HookType, Language, Stage};
+use crate::cli::run::RunOutput;
+use crate::config::{FileSize, HookType, Language, Stage, StashMode};
+use crate::{config, hook, policy, settings, store, workspace};
 
+mod audit;
 mod auto_update;
+mod baseline;
+mod cache_configs;
+mod cache_verify;
 mod clean;
+mod compare;
 mod completion;
+mod debug_bundle;
+mod exec;
 mod hook_impl;
+mod info;
 mod install;
 mod list;
+mod new_hook;
 pub mod reporter;
 pub mod run;
 mod sample_config;
+mod schema;
+mod self_check;
 #[cfg(feature = "self-update")]
 mod self_update;
+mod stats;
 mod validate;
+mod watch;
 
+pub(crate) use audit::audit;
 pub(crate) use auto_update::auto_update;
+pub(crate) use baseline::baseline;
+pub(crate) use cache_configs::cache_configs;
+pub(crate) use cache_verify::cache_verify;
 pub(crate) use clean::clean;
+pub(crate) use compare::compare;
+pub(crate) use completion::completions;
 use completion::selector_completer;
-pub(crate) use hook_impl::hook_impl;
+pub(crate) use debug_bundle::debug_bundle;
+pub(crate) use exec::exec;
+pub(crate) use hook_impl::{allow_missing_config, hook_impl};
+pub(crate) use info::info;
 pub(crate) use install::{init_template_dir, install, install_hooks, uninstall};
 pub(crate) use list::list;
-pub(crate) use run::run;
+pub(crate) use new_hook::new_hook;
+pub(crate) use run::{RunOptions, run, run_repos, run_submodules};
 pub(crate) use sample_config::sample_config;
+pub(crate) use schema::schema;
+pub(crate) use self_check::{passive_check, self_check};
 #[cfg(feature = "self-update")]
 pub(crate) use self_update::self_update;
+pub(crate) use stats::stats;
 pub(crate) use validate::{validate_configs, validate_manifest};
+pub(crate) use watch::watch;
 
 #[derive(Copy, Clone)]
 pub(crate) enum ExitStatus {
@@ -49,6 +80,18 @@ pub(crate) enum ExitStatus {
     /// The command failed with an unexpected error.
     Error,
 
+    /// A config file, the user config or the policy file is invalid.
+    ConfigError,
+
+    /// No config file was found.
+    MissingConfig,
+
+    /// The store, where repos and hook environments are cached, failed or couldn't be locked.
+    StoreError,
+
+    /// `prek run --timeout` stopped the run.
+    TimedOut,
+
     /// The command was interrupted.
     Interrupted,
 
@@ -62,15 +105,61 @@ impl From<ExitStatus> for ExitCode {
             ExitStatus::Success => Self::from(0),
             ExitStatus::Failure => Self::from(1),
             ExitStatus::Error => Self::from(2),
+            ExitStatus::ConfigError => Self::from(3),
+            ExitStatus::MissingConfig => Self::from(4),
+            ExitStatus::StoreError => Self::from(5),
+            ExitStatus::TimedOut => Self::from(124),
             ExitStatus::Interrupted => Self::from(130),
             ExitStatus::External(code) => Self::from(code),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, clap::ValueEnum)]
+impl ExitStatus {
+    /// The exit status of a command that failed with `err`, from the first error in its chain
+    /// that prek knows the cause of.
+    pub(crate) fn from_error(err: &anyhow::Error) -> Self {
+        err.chain()
+            .find_map(|err| {
+                if let Some(err) = err.downcast_ref::<workspace::Error>() {
+                    match err {
+                        workspace::Error::Config(err) => Some(Self::from_config_error(err)),
+                        workspace::Error::Hook(_) | workspace::Error::HookNotFound { .. } => {
+                            Some(Self::ConfigError)
+                        }
+                        workspace::Error::MissingPreCommitConfig => Some(Self::MissingConfig),
+                        workspace::Error::Store { .. } => Some(Self::StoreError),
+                        workspace::Error::Git(_) => None,
+                    }
+                } else if let Some(err) = err.downcast_ref::<config::Error>() {
+                    Some(Self::from_config_error(err))
+                } else if err.is::<hook::Error>()
+                    || err.is::<settings::Error>()
+                    || err.is::<policy::Error>()
+                {
+                    Some(Self::ConfigError)
+                } else if err.is::<store::Error>() {
+                    Some(Self::StoreError)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(Self::Error)
+    }
+
+    fn from_config_error(err: &config::Error) -> Self {
+        match err {
+            config::Error::NotFound(_) => Self::MissingConfig,
+            _ => Self::ConfigError,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ColorChoice {
     /// Enables colored output only when the output is going to a terminal or TTY with support.
+    #[default]
     Auto,
 
     /// Enables colored output regardless of the detected environment.
@@ -96,11 +185,23 @@ const STYLES: Styles = Styles::styled()
     .literal(AnsiColor::Cyan.on_default().effects(Effects::BOLD))
     .placeholder(AnsiColor::Cyan.on_default());
 
+const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0    Success
+  1    A hook failed, or the command found problems, e.g. invalid configs for `validate-config`
+  2    prek failed with an unexpected error
+  3    A config file, the user config or the policy file is invalid
+  4    No config file was found
+  5    The store failed, e.g. a repo couldn't be cloned or the store couldn't be locked
+  124  The run took longer than `--timeout`
+  130  Interrupted";
+
 #[derive(Parser)]
 #[command(
     name = "prek",
     long_version = crate::version::version(),
-    about = "Better pre-commit, re-engineered in Rust"
+    about = "Better pre-commit, re-engineered in Rust",
+    after_long_help = EXIT_STATUS_HELP
 )]
 #[command(
     propagate_version = true,
@@ -140,14 +241,23 @@ pub(crate) struct GlobalArgs {
     pub(crate) cd: Option<PathBuf>,
 
     /// Whether to use color in output.
+    ///
+    /// With `auto`, `NO_COLOR` disables color and `CLICOLOR_FORCE` enables it. Hooks are told
+    /// the choice with `NO_COLOR`, or with `CLICOLOR_FORCE` and `FORCE_COLOR`.
     #[arg(
         global = true,
         long,
         value_enum,
         env = EnvVars::PREK_COLOR,
-        default_value_t = ColorChoice::Auto,
     )]
-    pub(crate) color: ColorChoice,
+    pub(crate) color: Option<ColorChoice>,
+
+    /// Ignore the user config, the policy file and environment variables that override prek.
+    ///
+    /// Only the project config and the built-in defaults are used, to reproduce bug reports and
+    /// for hermetic CI runs.
+    #[arg(global = true, long)]
+    pub(crate) isolated: bool,
 
     /// Refresh all cached data.
     #[arg(global = true, long)]
@@ -165,6 +275,8 @@ pub(crate) struct GlobalArgs {
 
     /// Use quiet output.
     ///
+    /// `prek run` only prints the hooks that failed, followed by a summary.
+    ///
     /// Repeating this option, e.g., `-qq`, will enable a silent mode in which
     /// prek will write no output to stdout.
     #[arg(global = true, short, long, conflicts_with = "verbose", action = ArgAction::Count)]
@@ -176,6 +288,9 @@ pub(crate) struct GlobalArgs {
 
     /// Write trace logs to the specified file.
     /// If not specified, trace logs will be written to `$PREK_HOME/prek.log`.
+    ///
+    /// The logs of the previous 4 runs are kept in `$PREK_HOME/prek.log.1` to `prek.log.4`.
+    /// `PREK_LOG` selects what is logged, e.g. `PREK_LOG=prek=debug`, regardless of `-v`.
     #[arg(global = true, long, value_name = "LOG_FILE", value_hint = ValueHint::FilePath)]
     pub(crate) log_file: Option<PathBuf>,
 
@@ -183,6 +298,14 @@ pub(crate) struct GlobalArgs {
     #[arg(global = true, long, overrides_with = "log_file", hide = true)]
     pub(crate) no_log_file: bool,
 
+    /// Record a profile of the command to the specified file, in the Chrome trace format.
+    ///
+    /// The profile has the time spent parsing configs, cloning repos, installing hook
+    /// environments and running each hook and batch of files. Open it in `chrome://tracing` or
+    /// <https://ui.perfetto.dev>.
+    #[arg(global = true, long, value_name = "PROFILE", value_hint = ValueHint::FilePath)]
+    pub(crate) profile: Option<PathBuf>,
+
     /// Display the prek version.
     #[arg(global = true, short = 'V', long, action = ArgAction::Version)]
     version: (),
@@ -204,8 +327,31 @@ pub(crate) enum Command {
     InstallHooks(InstallHooksArgs),
     /// Run hooks.
     Run(Box<RunArgs>),
+    /// Watch the working tree and run hooks on the files that change.
+    ///
+    /// Changes are collected until files stop changing for a moment, then the hooks matching the changed files are run on them. Only failures of hooks are printed.
+    Watch(WatchArgs),
     /// List available hooks.
     List(ListArgs),
+    /// Show how a hook is resolved and run.
+    ///
+    /// Prints the definition of the hook, the paths of its repo checkout and environment, the version of its language, and the command it runs.
+    Info(InfoArgs),
+    /// Run a command in the environment of a hook.
+    ///
+    /// The environment is installed first if needed. The command runs with the `PATH` and the other variables the hook runs with, e.g. `VIRTUAL_ENV` for Python hooks, and the `env` of the hook.
+    Exec(ExecArgs),
+    /// Compare the JSON reports of two runs.
+    ///
+    /// Prints hooks that started failing, hooks that started passing, and hooks that became slower.
+    /// Exits with a non-zero status if any hook started failing.
+    Compare(CompareArgs),
+    /// Show trends of the recorded runs of the workspace.
+    ///
+    /// Lists the hooks that fail the most, flaky hooks and slow hooks over the last runs.
+    Stats(StatsArgs),
+    /// Record the current failures of hooks to adopt them in an existing codebase.
+    Baseline(BaselineNamespace),
     /// Uninstall the prek git hook.
     Uninstall(UninstallArgs),
     /// Validate `.pre-commit-config.yaml` files.
@@ -214,11 +360,27 @@ pub(crate) enum Command {
     ValidateManifest(ValidateManifestArgs),
     /// Produce a sample `.pre-commit-config.yaml` file.
     SampleConfig(SampleConfigArgs),
+    /// Create a new hook repository.
+    ///
+    /// Writes a `.pre-commit-hooks.yaml` manifest, a skeleton of the hook in the chosen language and a smoke test that runs the hook with `prek try-repo`.
+    NewHook(NewHookArgs),
+    /// Print the JSON Schema of the config or manifest files.
+    ///
+    /// The schema is derived from the types prek reads the files into, so it's always in sync with what prek accepts. It can be used by editors, e.g. with `yaml-language-server`, for completion and validation.
+    Schema(SchemaNamespace),
     /// Auto-update pre-commit config to the latest repos' versions.
     #[command(alias = "autoupdate")]
     AutoUpdate(AutoUpdateArgs),
+    /// Check the remote repos of the config against supply-chain policies.
+    ///
+    /// The `rev` of each repo must be a full commit SHA or an annotated tag, the host of the repo must be allowed with `--allowed-host`, and the checkout of the repo in the cache must be at the commit of the `rev`. Exits with a non-zero status if any repo violates a policy.
+    Audit(AuditArgs),
     /// Manage the prek cache.
     Cache(CacheNamespace),
+    /// Create a zip archive with debug information to attach to bug reports.
+    ///
+    /// The archive contains the config, store metadata, hook environment manifests, recent logs and platform information, with secrets and the home directory scrubbed.
+    DebugBundle(DebugBundleArgs),
     /// Clean unused cached repos.
     #[command(hide = true)]
     GC,
@@ -239,6 +401,10 @@ pub(crate) enum Command {
     /// Generate shell completion scripts.
     #[command(hide = true)]
     GenerateShellCompletion(GenerateShellCompletionArgs),
+    /// Print the completion script of a shell.
+    ///
+    /// The script asks prek for the candidates while completing, so hook ids and projects of the current workspace are completed too, e.g. in `prek run <TAB>`.
+    Completions(CompletionsArgs),
 }
 
 #[derive(Debug, Args)]
@@ -395,17 +561,51 @@ pub(crate) struct RunArgs {
     #[arg(long = "skip", value_name = "HOOK|PROJECT", add = ArgValueCompleter::new(selector_completer))]
     pub(crate) skips: Vec<String>,
 
+    /// Run only the hooks of the specified project, including the projects nested in it.
+    ///
+    /// Can be specified multiple times. Equivalent to the `project-path/` selector.
+    #[arg(long = "project", value_name = "PROJECT", value_hint = ValueHint::DirPath)]
+    pub(crate) projects: Vec<String>,
+
+    /// Run only the hooks of the specified group, as set by the `group` hook option.
+    ///
+    /// Can be specified multiple times.
+    #[arg(long = "group", value_name = "GROUP", value_hint = ValueHint::Other)]
+    pub(crate) groups: Vec<String>,
+
+    /// Run only the hooks with the specified tag, as set by the `tags` hook option.
+    ///
+    /// Tags joined by `+` select the hooks that have all of them, e.g. `--tag security+fast`.
+    /// Can be specified multiple times to select the hooks that match any of them.
+    #[arg(long = "tag", value_name = "TAG", value_hint = ValueHint::Other)]
+    pub(crate) tags: Vec<String>,
+
     /// Run on all files in the repo.
     #[arg(short, long, conflicts_with_all = ["files", "from_ref", "to_ref"])]
     pub(crate) all_files: bool,
     /// Specific filenames to run hooks on.
+    ///
+    /// Shell-style glob patterns (e.g. `src/**/*.py`) are expanded against the files tracked by git.
+    /// Can be specified multiple times.
     #[arg(
         long,
         conflicts_with_all = ["all_files", "from_ref", "to_ref"],
         num_args = 0..,
         value_hint = ValueHint::AnyPath)
     ]
-    pub(crate) files: Vec<String>,
+    pub(crate) files: Vec<PathBuf>,
+
+    /// Read the filenames to run hooks on from a file, or from stdin with `-`.
+    ///
+    /// Filenames are separated by newlines, or by NUL characters if there are any, e.g. the output
+    /// of `git diff --name-only -z`. Use it instead of `--files` for long lists of files.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["all_files", "from_ref", "to_ref", "last_commit", "rebase_exec", "incremental", "failed", "workspace"],
+        value_hint = ValueHint::FilePath
+    )]
+    pub(crate) files_from: Option<PathBuf>,
 
     /// Run hooks on all files in the specified directories.
     ///
@@ -436,22 +636,224 @@ pub(crate) struct RunArgs {
     )]
     pub(crate) to_ref: Option<String>,
 
+    /// Only run hooks on files matching this regex.
+    ///
+    /// The pattern is matched against file paths relative to the workspace root, and intersects with the `files` and `exclude` patterns of each hook.
+    #[arg(long, value_name = "REGEX")]
+    pub(crate) only_files_matching: Option<Regex>,
+
+    /// Do not run hooks on files matching this regex.
+    ///
+    /// The pattern is matched against file paths relative to the workspace root, and intersects with the `files` and `exclude` patterns of each hook.
+    #[arg(long, value_name = "REGEX")]
+    pub(crate) exclude_files_matching: Option<Regex>,
+
+    /// Do not run hooks on files larger than this size, e.g. `500KB` or `2MB`.
+    ///
+    /// Overrides the `max_file_size` of the config. Skipped files are reported as a warning.
+    #[arg(long, value_name = "SIZE")]
+    pub(crate) max_file_size: Option<FileSize>,
+
+    /// Do not run hooks on binary files.
+    ///
+    /// Skipped files are reported as a warning.
+    #[arg(long)]
+    pub(crate) skip_binary_files: bool,
+
+    /// Do not run hooks on Git LFS files.
+    ///
+    /// Skipped files are reported as a warning.
+    #[arg(long)]
+    pub(crate) skip_lfs_files: bool,
+
+    /// Fail instead of skipping files that are not materialized by a sparse checkout.
+    ///
+    /// By default, files outside the sparse checkout are silently skipped.
+    #[arg(long)]
+    pub(crate) fail_on_sparse: bool,
+
+    /// Run hooks on the files recorded in `.prek-baseline.json` as well.
+    ///
+    /// By default, hooks are not run on the files they are known to fail on, see `prek baseline`.
+    #[arg(long)]
+    pub(crate) no_baseline: bool,
+
     /// Run hooks against the last commit. Equivalent to `--from-ref HEAD~1 --to-ref HEAD`.
     #[arg(long, conflicts_with_all = ["all_files", "files", "directory", "from_ref", "to_ref"])]
     pub(crate) last_commit: bool,
 
+    /// Run hooks as a `git rebase --exec` command, e.g. `git rebase -x 'prek run --rebase-exec'`.
+    ///
+    /// Checks the files changed by the commit being replayed, only prints failures, and skips
+    /// commits that already passed with the same configs and hooks.
+    #[arg(long, conflicts_with_all = ["all_files", "files", "directory", "from_ref", "to_ref", "last_commit"])]
+    pub(crate) rebase_exec: bool,
+
+    /// Run on the files changed since the last successful `--incremental` run.
+    ///
+    /// Runs on all files the first time, and again when the configs, hooks or their
+    /// environments changed.
+    #[arg(long, conflicts_with_all = ["all_files", "files", "directory", "from_ref", "to_ref", "last_commit", "rebase_exec"])]
+    pub(crate) incremental: bool,
+
+    /// Run only the hooks that failed in the last run, on the files they failed on.
+    ///
+    /// Hooks are remembered until they pass, including from runs of the git hooks.
+    #[arg(long, conflicts_with_all = ["all_files", "files", "directory", "from_ref", "to_ref", "last_commit", "rebase_exec", "incremental"])]
+    pub(crate) failed: bool,
+
+    /// Pick the hooks to run from a checklist, with the result of each hook in the last run.
+    #[arg(long, conflicts_with = "workspace")]
+    pub(crate) interactive: bool,
+
+    /// Run hooks in every repository listed in the file, one path per line.
+    ///
+    /// Repositories are checked concurrently, and a summary keyed by repository is printed at the end.
+    /// Relative paths are resolved against the directory of the list file. `--output json:<path>`
+    /// writes a report keyed by repository, `--output custom:<name>` sends the results of each
+    /// repository to the reporter.
+    #[arg(
+        long,
+        value_name = "LIST_FILE",
+        conflicts_with_all = ["files", "directory", "from_ref", "to_ref", "last_commit", "rebase_exec"],
+        value_hint = ValueHint::FilePath
+    )]
+    pub(crate) workspace: Option<PathBuf>,
+
     /// The stage during which the hook is fired.
     #[arg(long, default_value_t = Stage::PreCommit, value_enum)]
     pub(crate) hook_stage: Stage,
 
+    /// Also run hooks in initialized submodules that have their own config.
+    ///
+    /// Each submodule is run with its own config after the hooks of the current repository, with
+    /// the same options. Submodules only run on the given files inside them, and on the changes
+    /// between the commits `--from-ref` and `--to-ref` record for them.
+    #[arg(long, conflicts_with = "workspace")]
+    pub(crate) include_submodules: bool,
+
     /// When hooks fail, run `git diff` directly afterward.
     #[arg(long)]
     pub(crate) show_diff_on_failure: bool,
 
+    /// What to do with changes that are not staged before running hooks.
+    ///
+    /// Overrides the `stash_mode` of the workspace root config. Defaults to `stash`.
+    #[arg(long, value_name = "MODE", value_enum)]
+    pub(crate) stash_mode: Option<StashMode>,
+
+    /// Do not stash changes that are not staged, hooks see the working tree as is.
+    ///
+    /// Equivalent to `--stash-mode keep`.
+    #[arg(long, conflicts_with = "stash_mode")]
+    pub(crate) no_stash: bool,
+
     /// Do not run the hooks, but print the hooks that would have been run.
     #[arg(long)]
     pub(crate) dry_run: bool,
 
+    /// The number of hooks and environment installs to run in parallel.
+    ///
+    /// Environments of hooks with different dependencies are installed concurrently, hooks that
+    /// share an environment wait for it to be installed once. Defaults to `jobs` of the user
+    /// config, or the number of CPUs.
+    #[arg(short, long)]
+    pub(crate) jobs: Option<usize>,
+
+    /// With `--dry-run`, preview the changes of hooks as a diff.
+    ///
+    /// Hooks run in a temporary git worktree of the staged files, the working tree and the index
+    /// are not modified. Only the files passed to hooks are checked out, along with the other
+    /// files of their directories and parent directories.
+    #[arg(long, requires = "dry_run")]
+    pub(crate) diff: bool,
+
+    /// When hooks modify files, run all hooks once more to verify the fixes.
+    ///
+    /// The exit status reflects the second run, so a single command both fixes and verifies.
+    #[arg(long, conflicts_with = "dry_run")]
+    pub(crate) fix_and_verify: bool,
+
+    /// When hooks modify staged files, stage the fixes and run all hooks once more.
+    ///
+    /// The commit proceeds if the second run passes. Fixes are not staged if the modified files
+    /// also have changes that are not staged. Can also be enabled with `auto_stage_fixes: true`.
+    #[arg(long, conflicts_with_all = ["dry_run", "fix_and_verify"])]
+    pub(crate) fix_and_retry: bool,
+
+    /// Treat failures of hooks with `severity: warning` as errors.
+    ///
+    /// Useful in CI to enforce hooks that are advisory locally.
+    #[arg(long)]
+    pub(crate) strict: bool,
+
+    /// Exit with a non-zero status if deprecations were found.
+    ///
+    /// Deprecations listed in `suppress_warnings` of the config are ignored.
+    #[arg(long)]
+    pub(crate) strict_warnings: bool,
+
+    /// Refuse to run hooks of repos whose `rev` is not a full commit SHA.
+    ///
+    /// Branches and tags can be moved to other commits, changing the hooks that run. Can also be
+    /// enabled with `require_pinned_revs: true`.
+    #[arg(long)]
+    pub(crate) require_pinned_revs: bool,
+
+    /// Where to send hook results, in addition to the terminal output.
+    ///
+    /// `json:<path>` writes a JSON report of the run to `<path>`, which can be fed to `prek compare`.
+    ///
+    /// `custom:<name>` streams results as JSON lines to an executable named `prek-reporter-<name>`
+    /// found in `PATH`.
+    #[arg(long, value_name = "OUTPUT", default_value = "text")]
+    pub(crate) output: RunOutput,
+
+    /// Print one stable, tab-separated record per hook instead of the human readable output.
+    ///
+    /// The records are the status, the full hook id (just the id for hooks of the root project),
+    /// the duration in seconds and the number of files passed to the hook, after a
+    /// `# prek porcelain v1` header. The format only changes
+    /// with a new version in the header, so scripts can parse it across releases.
+    #[arg(long, conflicts_with_all = ["output", "workspace", "interactive", "stream"])]
+    pub(crate) porcelain: bool,
+
+    /// Print the output of hooks while they run, each line prefixed with the hook id.
+    ///
+    /// By default, the output of a hook is printed once it finished. Hooks can also enable this
+    /// with `stream_output: true`.
+    #[arg(long)]
+    pub(crate) stream: bool,
+
+    /// Run hooks in a pseudo-terminal, so tools print colors and progress like they do
+    /// interactively.
+    ///
+    /// Hooks are already run in a pseudo-terminal when the output of prek is colored. Hooks can
+    /// also enable this with `tty: true`. Only supported on Unix.
+    #[arg(long)]
+    pub(crate) pty: bool,
+
+    /// Run all hooks in a sandbox, with a read-only filesystem except for their project, and the
+    /// home directory hidden.
+    ///
+    /// Hooks can also enable this with `sandbox: true`, and disable network access with
+    /// `sandbox_network: false`. Only supported on Linux, with bubblewrap, and macOS.
+    #[arg(long)]
+    pub(crate) sandbox: bool,
+
+    /// Print the `N` slowest hooks and environment installs once all hooks ran.
+    ///
+    /// Useful to decide which hooks to move to a later stage, e.g. `pre-push`.
+    #[arg(long, value_name = "N")]
+    pub(crate) durations: Option<usize>,
+
+    /// Stop the run if it takes longer than this, e.g. `90s`, `10m` or `1h`.
+    ///
+    /// Running hooks are killed, the remaining hooks are skipped and stashed changes are
+    /// restored. prek then exits with status 124.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub(crate) timeout: Option<Duration>,
+
     #[command(flatten)]
     pub(crate) extra: RunExtraArgs,
 }
@@ -507,6 +909,80 @@ pub(crate) struct ListArgs {
     /// The output format.
     #[arg(long, value_enum, default_value_t = ListOutputFormat::Text)]
     pub(crate) output_format: ListOutputFormat,
+    /// Show details of each hook, such as its description, repo and environment.
+    #[arg(short, long)]
+    pub(crate) long: bool,
+    /// List the projects in the workspace instead of the hooks.
+    #[arg(long, conflicts_with_all = ["hook_stage", "language", "long", "groups"])]
+    pub(crate) projects: bool,
+    /// List the groups of the hooks, with their hooks, instead of the hooks.
+    #[arg(long, conflicts_with_all = ["long"])]
+    pub(crate) groups: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct InfoArgs {
+    /// The hook to show, `project-path:hook-id` selects the hook of a specific project.
+    #[arg(
+        value_name = "HOOK",
+        value_hint = ValueHint::Other,
+        add = ArgValueCompleter::new(selector_completer)
+    )]
+    pub(crate) hook: String,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct ExecArgs {
+    /// The hook whose environment to use, `project-path:hook-id` selects the hook of a specific project.
+    #[arg(
+        value_name = "HOOK",
+        value_hint = ValueHint::Other,
+        add = ArgValueCompleter::new(selector_completer)
+    )]
+    pub(crate) hook: String,
+
+    /// The command to run, after `--`.
+    #[arg(value_name = "COMMAND", required = true, last = true)]
+    pub(crate) command: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct WatchArgs {
+    /// Include the specified hooks or projects.
+    ///
+    /// Supports flexible selector syntax:
+    ///
+    /// - `hook-id`: Run all hooks with the specified ID across all projects
+    ///
+    /// - `project-path/`: Run all hooks from the specified project
+    ///
+    /// - `project-path:hook-id`: Run only the specified hook from the specified project
+    ///
+    /// Can be specified multiple times to select multiple hooks/projects.
+    #[arg(
+        value_name = "HOOK|PROJECT",
+        value_hint = ValueHint::Other,
+        add = ArgValueCompleter::new(selector_completer)
+    )]
+    pub(crate) includes: Vec<String>,
+
+    /// Skip the specified hooks or projects.
+    ///
+    /// Supports flexible selector syntax:
+    ///
+    /// - `hook-id`: Skip all hooks with the specified ID across all projects
+    ///
+    /// - `project-path/`: Skip all hooks from the specified project
+    ///
+    /// - `project-path:hook-id`: Skip only the specified hook from the specified project
+    ///
+    /// Can be specified multiple times. Also accepts `PREK_SKIP` or `SKIP` environment variables (comma-delimited).
+    #[arg(long = "skip", value_name = "HOOK|PROJECT", add = ArgValueCompleter::new(selector_completer))]
+    pub(crate) skips: Vec<String>,
+
+    /// Wait for files to stop changing for this many milliseconds before running hooks.
+    #[arg(long, value_name = "MS", default_value_t = 200)]
+    pub(crate) debounce: u64,
 }
 
 #[derive(Debug, Args)]
@@ -514,6 +990,10 @@ pub(crate) struct ValidateConfigArgs {
     /// The path to the configuration file.
     #[arg(value_name = "CONFIG")]
     pub(crate) configs: Vec<PathBuf>,
+
+    /// Treat warnings, e.g. unexpected keys or patterns that never match, as errors.
+    #[arg(long)]
+    pub(crate) strict: bool,
 }
 
 #[derive(Debug, Args)]
@@ -523,6 +1003,26 @@ pub(crate) struct ValidateManifestArgs {
     pub(crate) manifests: Vec<PathBuf>,
 }
 
+#[derive(Debug, Args)]
+pub(crate) struct CompareArgs {
+    /// The JSON report of the baseline run, e.g. from the main branch.
+    #[arg(value_name = "BASE", value_hint = ValueHint::FilePath)]
+    pub(crate) base: PathBuf,
+    /// The JSON report to compare against the baseline, e.g. from a pull request.
+    #[arg(value_name = "HEAD", value_hint = ValueHint::FilePath)]
+    pub(crate) head: PathBuf,
+    /// Report hooks whose duration grew by more than this percentage.
+    #[arg(long, value_name = "PERCENT", default_value_t = 50)]
+    pub(crate) threshold: u32,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct StatsArgs {
+    /// The number of recent runs to include.
+    #[arg(long, value_name = "N", default_value_t = 50)]
+    pub(crate) runs: usize,
+}
+
 #[derive(Debug, Args)]
 pub(crate) struct SampleConfigArgs {
     /// Write the sample config to a file (`.pre-commit-config.yaml` by default).
@@ -535,6 +1035,47 @@ pub(crate) struct SampleConfigArgs {
     pub(crate) file: Option<PathBuf>,
 }
 
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub(crate) enum NewHookLanguage {
+    /// A Python package with a console script.
+    #[default]
+    Python,
+    /// A Node package with a `bin` script.
+    Node,
+    /// A Cargo binary.
+    Rust,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct NewHookArgs {
+    /// The directory to create the hook repository in.
+    #[arg(value_name = "DIRECTORY", value_hint = ValueHint::DirPath)]
+    pub(crate) directory: PathBuf,
+    /// The id of the hook, also used as the package and executable name.
+    ///
+    /// Defaults to the name of the directory.
+    #[arg(long)]
+    pub(crate) id: Option<String>,
+    /// The name of the hook shown while running it. Defaults to the id.
+    #[arg(long)]
+    pub(crate) name: Option<String>,
+    /// The language of the hook.
+    #[arg(long, value_enum, default_value_t)]
+    pub(crate) language: NewHookLanguage,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct DebugBundleArgs {
+    /// Where to write the archive.
+    #[arg(
+        short,
+        long,
+        default_value = "prek-debug-bundle.zip",
+        value_hint = ValueHint::FilePath
+    )]
+    pub(crate) output: PathBuf,
+}
+
 #[derive(Debug, Args)]
 pub(crate) struct AutoUpdateArgs {
     /// Update to the bleeding edge of the default branch instead of the latest tagged version.
@@ -554,6 +1095,15 @@ pub(crate) struct AutoUpdateArgs {
     pub(crate) jobs: usize,
 }
 
+#[derive(Debug, Args)]
+pub(crate) struct AuditArgs {
+    /// Only allow repos hosted on this host, e.g. `github.com`. This option may be specified multiple times.
+    ///
+    /// Without it, repos on any host are allowed.
+    #[arg(long = "allowed-host", value_name = "HOST")]
+    pub(crate) allowed_hosts: Vec<String>,
+}
+
 #[derive(Debug, Args)]
 pub(crate) struct HookImplArgs {
     /// Include the specified hooks or projects.
@@ -610,12 +1160,48 @@ pub(crate) struct CacheNamespace {
 pub(crate) enum CacheCommand {
     /// Show the location of the prek cache.
     Dir,
+    /// List the configs that used the cache, with the time they were last used.
+    Configs,
     /// Remove unused cached repositories, hook environments, and other data.
     GC,
+    /// Verify hook environments against the checksums recorded when they were built.
+    ///
+    /// Corrupted environments are removed and rebuilt the next time their hooks run.
+    Verify,
     /// Remove all prek cached data.
     Clean,
 }
 
+#[derive(Debug, Args)]
+pub(crate) struct BaselineNamespace {
+    #[command(subcommand)]
+    pub(crate) command: BaselineCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum BaselineCommand {
+    /// Run all hooks on all files and record the files each hook fails on.
+    ///
+    /// Hooks are not run on the recorded files by `prek run`, so only new failures are reported.
+    Create,
+    /// Run hooks again on the recorded files, and remove the files that pass now.
+    Update,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct SchemaNamespace {
+    #[command(subcommand)]
+    pub(crate) command: SchemaCommand,
+}
+
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub(crate) enum SchemaCommand {
+    /// Print the JSON Schema of `.pre-commit-config.yaml` files.
+    Config,
+    /// Print the JSON Schema of `.pre-commit-hooks.yaml` files.
+    Manifest,
+}
+
 #[derive(Debug, Args)]
 pub(crate) struct SelfNamespace {
     #[command(subcommand)]
@@ -626,6 +1212,11 @@ pub(crate) struct SelfNamespace {
 pub(crate) enum SelfCommand {
     /// Update prek.
     Update(SelfUpdateArgs),
+    /// Check whether a newer release of prek exists, and whether the config requires an upgrade.
+    ///
+    /// Nothing is installed, use `prek self update` to update. Exits with a non-zero status if
+    /// the `minimum_prek_version` of the config is greater than the installed version.
+    Check,
 }
 
 #[derive(Debug, Args)]
@@ -647,6 +1238,13 @@ pub(crate) struct GenerateShellCompletionArgs {
     pub shell: clap_complete::Shell,
 }
 
+#[derive(Debug, Args)]
+pub(crate) struct CompletionsArgs {
+    /// The shell to print the completion script for.
+    #[arg(value_enum)]
+    pub(crate) shell: clap_complete::Shell,
+}
+
 #[derive(Debug, Args)]
 pub(crate) struct InitTemplateDirArgs {
     /// The directory in which to write the hook script.
@@ -661,6 +1259,23 @@ pub(crate) struct InitTemplateDirArgs {
     pub(crate) hook_types: Vec<HookType>,
 }
 
+/// Parse a duration like `90s`, `10m` or `1h`, plain numbers are seconds.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration `{value}`, expected e.g. `90s`, `10m` or `1h`");
+    let (number, unit) = value
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((value, ""), |index| value.split_at(index));
+    let number = number.parse::<u64>().map_err(|_| invalid())?;
+    let seconds = match unit {
+        "" | "s" => Some(number),
+        "m" => number.checked_mul(60),
+        "h" => number.checked_mul(60 * 60),
+        _ => return Err(invalid()),
+    };
+    let seconds = seconds.ok_or_else(|| format!("duration `{value}` is too long"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
 #[cfg(unix)]
 #[cfg(test)]
 mod tests {
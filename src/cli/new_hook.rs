@@ -0,0 +1,237 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use owo_colors::OwoColorize;
+
+use crate::cli::{ExitStatus, NewHookLanguage};
+use crate::config::Language;
+use crate::fs::Simplified;
+use crate::printer::Printer;
+
+/// Scaffold a new hook repository with a manifest, a skeleton of the hook and a smoke test.
+pub(crate) fn new_hook(
+    directory: &Path,
+    id: Option<String>,
+    name: Option<String>,
+    language: NewHookLanguage,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let id = match id {
+        Some(id) => id,
+        None => directory
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+    if !is_valid_id(&id) {
+        bail!(
+            "Invalid hook id `{}`, use lowercase letters, digits and dashes, starting with a letter",
+            id.cyan()
+        );
+    }
+    let name = name.unwrap_or_else(|| id.clone());
+
+    if directory
+        .read_dir()
+        .is_ok_and(|mut entries| entries.next().is_some())
+    {
+        bail!(
+            "Directory `{}` already exists and is not empty",
+            directory.simplified_display().cyan()
+        );
+    }
+
+    let files = scaffold(&id, &name, language)?;
+    for (path, content) in &files {
+        let path = directory.join(path);
+        fs_err::create_dir_all(path.parent().unwrap_or(directory))?;
+        fs_err::write(&path, content)?;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        for executable in [SMOKE_TEST, NODE_BIN] {
+            let path = directory.join(executable);
+            if path.exists() {
+                fs_err::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+            }
+        }
+    }
+
+    writeln!(
+        printer.stdout(),
+        "Created hook `{}` in `{}`",
+        id.cyan(),
+        directory.simplified_display().cyan()
+    )?;
+    for (path, _) in &files {
+        writeln!(printer.stdout(), "  {}", path.display())?;
+    }
+    writeln!(
+        printer.stdout(),
+        "Commit the files, then run `{}` to try the hook",
+        Path::new(SMOKE_TEST).display().cyan()
+    )?;
+
+    Ok(ExitStatus::Success)
+}
+
+impl From<NewHookLanguage> for Language {
+    fn from(language: NewHookLanguage) -> Self {
+        match language {
+            NewHookLanguage::Python => Language::Python,
+            NewHookLanguage::Node => Language::Node,
+            NewHookLanguage::Rust => Language::Rust,
+        }
+    }
+}
+
+const SMOKE_TEST: &str = "tests/smoke-test.sh";
+const NODE_BIN: &str = "index.js";
+
+/// Hook ids are also used as the package and executable names.
+fn is_valid_id(id: &str) -> bool {
+    id.starts_with(|c: char| c.is_ascii_lowercase())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn scaffold(id: &str, name: &str, language: NewHookLanguage) -> Result<Vec<(PathBuf, String)>> {
+    // The name is free text, quote it if YAML needs it.
+    let name = serde_yaml::to_string(name)?;
+    let name = name.trim_end();
+    let manifest = indoc::formatdoc! {r"
+        - id: {id}
+          name: {name}
+          description: TODO describe what the hook checks.
+          entry: {id}
+          language: {language}
+          types: [text]
+        ",
+        language = Language::from(language).as_str(),
+    };
+    let smoke_test = indoc::formatdoc! {r#"
+        #!/bin/sh
+        # Run the hook of this repository on its own files, as a user of the hook would.
+        set -eu
+        cd "$(dirname "$0")/.."
+        prek validate-manifest .pre-commit-hooks.yaml
+        prek try-repo . {id} --all-files
+        "#
+    };
+
+    let mut files = vec![
+        (PathBuf::from(".pre-commit-hooks.yaml"), manifest),
+        (PathBuf::from(SMOKE_TEST), smoke_test),
+    ];
+    match language {
+        NewHookLanguage::Python => {
+            let module = id.replace('-', "_");
+            files.push((
+                PathBuf::from("pyproject.toml"),
+                indoc::formatdoc! {r#"
+                    [build-system]
+                    requires = ["setuptools>=61"]
+                    build-backend = "setuptools.build_meta"
+
+                    [project]
+                    name = "{id}"
+                    version = "0.1.0"
+                    requires-python = ">=3.9"
+
+                    [project.scripts]
+                    {id} = "{module}.main:main"
+                    "#
+                },
+            ));
+            files.push((PathBuf::from(&module).join("__init__.py"), String::new()));
+            files.push((
+                PathBuf::from(&module).join("main.py"),
+                indoc::indoc! {r#"
+                    import argparse
+                    import sys
+
+
+                    def main(argv=None):
+                        parser = argparse.ArgumentParser()
+                        parser.add_argument("filenames", nargs="*")
+                        args = parser.parse_args(argv)
+
+                        ret = 0
+                        for filename in args.filenames:
+                            # TODO check the file, print the problems and set `ret = 1`.
+                            pass
+                        return ret
+
+
+                    if __name__ == "__main__":
+                        sys.exit(main())
+                "#}
+                .to_string(),
+            ));
+        }
+        NewHookLanguage::Node => {
+            files.push((
+                PathBuf::from("package.json"),
+                indoc::formatdoc! {r#"
+                    {{
+                      "name": "{id}",
+                      "version": "0.1.0",
+                      "bin": {{
+                        "{id}": "{NODE_BIN}"
+                      }}
+                    }}
+                    "#
+                },
+            ));
+            files.push((
+                PathBuf::from(NODE_BIN),
+                indoc::indoc! {r"
+                    #!/usr/bin/env node
+                    let ret = 0;
+                    for (const filename of process.argv.slice(2)) {
+                      // TODO check the file, print the problems and set `ret = 1`.
+                    }
+                    process.exit(ret);
+                "}
+                .to_string(),
+            ));
+        }
+        NewHookLanguage::Rust => {
+            files.push((
+                PathBuf::from("Cargo.toml"),
+                indoc::formatdoc! {r#"
+                    [package]
+                    name = "{id}"
+                    version = "0.1.0"
+                    edition = "2021"
+                    "#
+                },
+            ));
+            files.push((
+                PathBuf::from("src/main.rs"),
+                indoc::indoc! {r#"
+                    use std::process::ExitCode;
+
+                    fn main() -> ExitCode {
+                        let mut ret = ExitCode::SUCCESS;
+                        for filename in std::env::args().skip(1) {
+                            if let Err(err) = std::fs::read(&filename) {
+                                eprintln!("{filename}: {err}");
+                                ret = ExitCode::FAILURE;
+                            }
+                            // TODO check the file, print the problems and fail.
+                        }
+                        ret
+                    }
+                "#}
+                .to_string(),
+            ));
+        }
+    }
+
+    Ok(files)
+}
@@ -1,19 +1,37 @@
 use std::borrow::Cow;
+use std::fmt::Write;
+use std::io::IsTerminal;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use owo_colors::OwoColorize;
 use rustc_hash::FxHashMap;
+use tokio::task::JoinHandle;
 
 use crate::hook::Hook;
 use crate::printer::Printer;
 use crate::workspace;
 
+/// Without a terminal, steps that take longer than this are reported with plain lines.
+const SLOW_STEP: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct Bar {
+    progress: ProgressBar,
+    /// The message the bar was started with, steps are appended to it.
+    msg: Cow<'static, str>,
+    started: Instant,
+    /// Whether the bar was reported with a plain line, steps are then reported as well.
+    reported: bool,
+    /// Reports the bar with a plain line once it's slow.
+    timer: Option<JoinHandle<()>>,
+}
+
 #[derive(Default, Debug)]
 struct BarState {
     /// A map of progress bars, by ID.
-    bars: FxHashMap<usize, ProgressBar>,
+    bars: FxHashMap<usize, Bar>,
     /// A monotonic counter for bar IDs.
     id: usize,
 }
@@ -31,6 +49,8 @@ struct ProgressReporter {
     root: ProgressBar,
     state: Arc<Mutex<BarState>>,
     children: MultiProgress,
+    /// Progress bars are not drawn without a terminal, slow steps are printed instead.
+    plain: bool,
 }
 
 impl ProgressReporter {
@@ -40,33 +60,85 @@ impl ProgressReporter {
             root,
             state: Arc::default(),
             children,
+            plain: printer == Printer::Default && !std::io::stderr().is_terminal(),
         }
     }
 
     fn on_start(&self, msg: impl Into<Cow<'static, str>>) -> usize {
         let mut state = self.state.lock().unwrap();
         let id = state.id();
+        let msg = msg.into();
 
         let progress = self.children.insert_before(
             &self.root,
             ProgressBar::with_draw_target(None, self.printer.target()),
         );
 
-        progress.set_style(ProgressStyle::with_template("{wide_msg}").unwrap());
-        progress.set_message(msg);
-
-        state.bars.insert(id, progress);
+        progress.set_style(ProgressStyle::with_template("{wide_msg} {elapsed:.dim}").unwrap());
+        progress.set_message(msg.clone());
+
+        let timer = (self.plain && tokio::runtime::Handle::try_current().is_ok()).then(|| {
+            let state = self.state.clone();
+            let printer = self.printer;
+            tokio::spawn(async move {
+                tokio::time::sleep(SLOW_STEP).await;
+                let mut state = state.lock().unwrap();
+                if let Some(bar) = state.bars.get_mut(&id) {
+                    bar.reported = true;
+                    let _ = writeln!(printer.stderr(), "{}...", bar.progress.message());
+                }
+            })
+        });
+
+        state.bars.insert(
+            id,
+            Bar {
+                progress,
+                msg,
+                started: Instant::now(),
+                reported: false,
+                timer,
+            },
+        );
         id
     }
 
-    fn on_progress(&self, id: usize) {
-        let progress = {
+    /// Show the current step of a bar, e.g. installing dependencies.
+    fn on_step(&self, id: usize, step: &str) {
+        let state = self.state.lock().unwrap();
+        let Some(bar) = state.bars.get(&id) else {
+            return;
+        };
+
+        let msg = format!("{} {}", bar.msg, format!("({step})").dimmed());
+        if bar.reported {
+            let _ = writeln!(self.printer.stderr(), "{msg}...");
+        }
+        bar.progress.set_message(msg);
+    }
+
+    /// Finish a bar, returns how long it was shown.
+    fn on_progress(&self, id: usize) -> Duration {
+        let bar = {
             let mut state = self.state.lock().unwrap();
             state.bars.remove(&id).unwrap()
         };
 
+        if let Some(timer) = bar.timer {
+            timer.abort();
+        }
+        if bar.reported {
+            let _ = writeln!(
+                self.printer.stderr(),
+                "{} {}",
+                bar.msg,
+                format!("done in {:.1}s", bar.started.elapsed().as_secs_f64()).dimmed()
+            );
+        }
+
         self.root.inc(1);
-        progress.finish_and_clear();
+        bar.progress.finish_and_clear();
+        bar.started.elapsed()
     }
 
     fn on_complete(&self) {
@@ -105,6 +177,10 @@ impl workspace::HookInitReporter for HookInitReporter {
             .on_start(format!("{} {}", "Cloning".bold().cyan(), repo.dimmed()))
     }
 
+    fn on_clone_step(&self, id: usize, step: &str) {
+        self.reporter.on_step(id, step);
+    }
+
     fn on_clone_complete(&self, id: usize) {
         self.reporter.on_progress(id);
     }
@@ -116,6 +192,10 @@ impl workspace::HookInitReporter for HookInitReporter {
 
 pub(crate) struct HookInstallReporter {
     reporter: ProgressReporter,
+    /// The hook of each install bar.
+    hooks: Mutex<FxHashMap<usize, String>>,
+    /// How long installing the environment of each hook took.
+    durations: Mutex<Vec<(String, Duration)>>,
 }
 
 impl From<Printer> for HookInstallReporter {
@@ -130,7 +210,11 @@ impl From<Printer> for HookInstallReporter {
         );
 
         let reporter = ProgressReporter::new(root, multi, printer);
-        Self { reporter }
+        Self {
+            reporter,
+            hooks: Mutex::default(),
+            durations: Mutex::default(),
+        }
     }
 }
 
@@ -140,15 +224,29 @@ impl HookInstallReporter {
             .root
             .set_message(format!("{}", "Installing hooks...".bold().cyan()));
 
-        self.reporter.on_start(format!(
+        let id = self.reporter.on_start(format!(
             "{} {}",
             "Installing".bold().cyan(),
             hook.id.dimmed(),
-        ))
+        ));
+        self.hooks.lock().unwrap().insert(id, hook.full_id());
+        id
+    }
+
+    pub fn on_install_step(&self, id: usize, step: &str) {
+        self.reporter.on_step(id, step);
     }
 
     pub fn on_install_complete(&self, id: usize) {
-        self.reporter.on_progress(id);
+        let elapsed = self.reporter.on_progress(id);
+        if let Some(hook) = self.hooks.lock().unwrap().remove(&id) {
+            self.durations.lock().unwrap().push((hook, elapsed));
+        }
+    }
+
+    /// The hooks whose environment was installed, with how long it took.
+    pub fn durations(&self) -> Vec<(String, Duration)> {
+        self.durations.lock().unwrap().clone()
     }
 
     pub fn on_complete(&self) {
@@ -0,0 +1,187 @@
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
+use tracing::debug;
+
+use crate::cache_key::CacheKey;
+use crate::hook::InstalledHook;
+use crate::store::{CacheBucket, Store};
+use crate::version;
+
+/// The maximum number of files remembered per hook, older entries are dropped beyond it.
+const MAX_ENTRIES: usize = 1 << 17;
+
+/// Config files of common tools, looked up in the directory of each file and its parents.
+const TOOL_CONFIGS: &[&str] = &[
+    ".clang-format",
+    ".editorconfig",
+    ".eslintrc",
+    ".eslintrc.cjs",
+    ".eslintrc.js",
+    ".eslintrc.json",
+    ".eslintrc.yaml",
+    ".eslintrc.yml",
+    ".flake8",
+    ".golangci.yaml",
+    ".golangci.yml",
+    ".isort.cfg",
+    ".markdownlint.json",
+    ".markdownlint.yaml",
+    ".markdownlint.yml",
+    ".mypy.ini",
+    ".prettierrc",
+    ".prettierrc.json",
+    ".prettierrc.yaml",
+    ".prettierrc.yml",
+    ".pylintrc",
+    ".rubocop.yml",
+    ".ruff.toml",
+    ".rustfmt.toml",
+    ".shellcheckrc",
+    ".stylelintrc",
+    ".stylelintrc.json",
+    ".yamllint",
+    ".yamllint.yaml",
+    ".yamllint.yml",
+    "biome.json",
+    "biome.jsonc",
+    "clippy.toml",
+    "eslint.config.cjs",
+    "eslint.config.js",
+    "eslint.config.mjs",
+    "mypy.ini",
+    "package.json",
+    "pyproject.toml",
+    "ruff.toml",
+    "rustfmt.toml",
+    "setup.cfg",
+    "stylua.toml",
+    "tox.ini",
+    "tsconfig.json",
+];
+
+/// Remembers the files a hook with `cache_results: true` passed on.
+///
+/// A file is identified by its path, its content and the config files of common tools in its
+/// directory and the parent directories, and the cache of a hook by everything that affects its
+/// result: the hook definition, its environment and the prek version. A hook passing on a batch
+/// of files passes on each of them, so all of them are remembered.
+pub(crate) struct ResultCache {
+    path: PathBuf,
+    passed: FxHashSet<String>,
+    /// The digests of the files checked in this run.
+    checked: Vec<String>,
+    /// The digests of the tool configs that apply to each directory.
+    configs: FxHashMap<PathBuf, String>,
+}
+
+impl ResultCache {
+    pub(crate) fn load(store: &Store, hook: &InstalledHook) -> Self {
+        let mut key = CacheKey::new();
+        version::version().hash(&mut key);
+        format!("{hook:#}").hash(&mut key);
+        hook.project().config_file().hash(&mut key);
+        hook.entry.raw().hash(&mut key);
+        hook.args.hash(&mut key);
+        hook.env.hash(&mut key);
+        hook.working_directory.hash(&mut key);
+        hook.language.hash(&mut key);
+        hook.install_info().hash(&mut key);
+        let digest = key.hex();
+
+        let path = store
+            .cache_path(CacheBucket::Prek)
+            .join("results")
+            .join(digest);
+        let passed = fs_err::read_to_string(&path)
+            .map(|content| {
+                content
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            passed,
+            checked: Vec::new(),
+            configs: FxHashMap::default(),
+        }
+    }
+
+    /// Remove the files the hook already passed on from `filenames`.
+    pub(crate) fn retain_uncached(&mut self, work_dir: &Path, filenames: &mut Vec<&Path>) {
+        filenames.retain(|filename| {
+            let path = work_dir.join(filename);
+            // Files that can't be read are always passed to the hook.
+            let Ok(content) = fs_err::read(&path) else {
+                return true;
+            };
+            let mut key = CacheKey::new();
+            filename.hash(&mut key);
+            content.hash(&mut key);
+            if let Some(dir) = path.parent() {
+                self.configs_digest(dir).hash(&mut key);
+            }
+            let digest = key.hex();
+
+            if self.passed.contains(&digest) {
+                false
+            } else {
+                self.checked.push(digest);
+                true
+            }
+        });
+    }
+
+    /// The digest of the tool configs in `dir` and its parents.
+    fn configs_digest(&mut self, dir: &Path) -> String {
+        if let Some(digest) = self.configs.get(dir) {
+            return digest.clone();
+        }
+
+        let mut key = CacheKey::new();
+        if let Some(parent) = dir.parent() {
+            self.configs_digest(parent).hash(&mut key);
+        }
+        for name in TOOL_CONFIGS {
+            if let Ok(content) = fs_err::read(dir.join(name)) {
+                name.hash(&mut key);
+                content.hash(&mut key);
+            }
+        }
+        let digest = key.hex();
+        self.configs.insert(dir.to_path_buf(), digest.clone());
+        digest
+    }
+
+    /// Remember that the hook passed on the files checked in this run.
+    pub(crate) fn mark_passed(mut self) {
+        if self.checked.is_empty() {
+            return;
+        }
+        if self.passed.len() + self.checked.len() > MAX_ENTRIES {
+            self.passed.clear();
+        }
+        self.passed.extend(self.checked);
+
+        let mut content = self.passed.iter().join("\n");
+        content.push('\n');
+        // Write to a temporary file first, other prek processes may read the cache.
+        let dir = self.path.parent().expect("has parent");
+        let result = fs_err::create_dir_all(dir)
+            .and_then(|()| tempfile::NamedTempFile::new_in(dir))
+            .and_then(|temp| {
+                fs_err::write(temp.path(), content)?;
+                temp.persist(&self.path).map_err(|err| err.error)?;
+                Ok(())
+            });
+        if let Err(err) = result {
+            debug!("Failed to save hook results cache: {err}");
+        }
+    }
+}
@@ -1,22 +1,32 @@
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use fancy_regex::Regex;
+use globset::{GlobBuilder, GlobSetBuilder};
+use ignore::gitignore::Gitignore;
 use itertools::{Either, Itertools};
-use rayon::iter::{IntoParallelRefIterator, ParallelBridge, ParallelIterator};
-use rustc_hash::FxHashSet;
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelRefIterator, ParallelBridge, ParallelIterator,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
 use tracing::{debug, error};
 
 use constants::env_vars::EnvVars;
 
-use crate::config::Stage;
+use crate::baseline::Baseline;
+use crate::config::{FileSize, Stage};
 use crate::fs::normalize_path;
 use crate::git::GIT_ROOT;
 use crate::hook::Hook;
-use crate::identify::tags_from_path;
+use crate::identify::{self, tags_from_path};
 use crate::workspace::Project;
 use crate::{fs, git, warn_user};
 
+/// The file with gitignore-style patterns of files that hooks never run on.
+const PREKIGNORE: &str = ".prekignore";
+
 /// Filter filenames by include/exclude patterns.
 pub(crate) struct FilenameFilter<'a> {
     include: Option<&'a Regex>,
@@ -29,9 +39,10 @@ impl<'a> FilenameFilter<'a> {
     }
 
     pub(crate) fn filter(&self, filename: &Path) -> bool {
-        let Some(filename) = filename.to_str() else {
-            return false;
-        };
+        // Patterns are matched against the lossy conversion of names that aren't valid UTF-8,
+        // so non-ASCII characters of such names only match `.`.
+        let filename = filename.to_string_lossy();
+        let filename = filename.as_ref();
         if let Some(re) = &self.include {
             if !re.is_match(filename).unwrap_or(false) {
                 return false;
@@ -88,14 +99,71 @@ impl<'a> FileTagFilter<'a> {
     }
 }
 
+/// Compute the tags of a file, logging (and excluding the file) on failure.
+fn file_tags(filename: &Path) -> Option<Vec<&'static str>> {
+    match tags_from_path(filename) {
+        Ok(tags) => Some(tags),
+        Err(err) => {
+            error!(filename = ?filename.display(), error = %err, "Failed to get tags");
+            None
+        }
+    }
+}
+
+/// The first line of a Git LFS pointer file.
+const LFS_POINTER_HEADER: &[u8] = b"version https://git-lfs.github.com/spec/v1\n";
+
+/// Check if a file is a Git LFS pointer, i.e. it was not replaced by its content on checkout.
+fn is_lfs_pointer(filename: &Path) -> bool {
+    // Pointer files are always smaller than 1024 bytes.
+    if !fs_err::metadata(filename).is_ok_and(|metadata| metadata.len() < 1024) {
+        return false;
+    }
+    let mut header = [0; LFS_POINTER_HEADER.len()];
+    fs_err::File::open(filename)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|()| header == LFS_POINTER_HEADER)
+}
+
+/// Limits on the files passed to hooks, set on the command line, they override the project config.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct FileLimits {
+    pub(crate) max_file_size: Option<FileSize>,
+    pub(crate) skip_binary_files: bool,
+    pub(crate) skip_lfs_files: bool,
+    /// Pass the files recorded in the baseline to hooks as well.
+    pub(crate) ignore_baseline: bool,
+}
+
 pub(crate) struct FileFilter<'a> {
     filenames: Vec<&'a Path>,
+    /// Tags of each file in `filenames`, computed when a hook first needs them and shared by all
+    /// hooks of the project.
+    tags: Vec<OnceLock<Option<Vec<&'static str>>>>,
     filename_prefix: &'a Path,
+    /// Pass all files to every hook, ignoring the hook filters.
+    unfiltered: bool,
+    /// Files larger than the `max_file_size` limit, they are not passed to any hook.
+    oversized: Vec<&'a Path>,
+    /// Binary files, not passed to any hook with `skip_binary_files`.
+    binary: Vec<&'a Path>,
+    /// Git LFS files, not passed to any hook with `skip_lfs_files`.
+    lfs: Vec<&'a Path>,
+    /// Git LFS pointer files whose content is not downloaded, they are not passed to any hook.
+    lfs_pointers: Vec<&'a Path>,
+    /// The known failures of the project, these files are not passed to the failing hooks.
+    baseline: Option<Baseline>,
 }
 
 impl<'a> FileFilter<'a> {
-    // Here, `filenames` are paths relative to the workspace root.
-    pub(crate) fn for_project<I>(filenames: I, project: &'a Project) -> Self
+    // Here, `filenames` are paths relative to the workspace root, and `lfs_files` are the
+    // Git LFS files among them.
+    pub(crate) fn for_project<I>(
+        filenames: I,
+        project: &'a Project,
+        limits: FileLimits,
+        lfs_files: &FxHashSet<PathBuf>,
+    ) -> Self
     where
         I: Iterator<Item = &'a PathBuf> + Send,
     {
@@ -116,17 +184,125 @@ impl<'a> FileFilter<'a> {
 
         // Keep filename order consistent
         filenames.sort_by_key(|&(i, _)| i);
+        let filenames: Vec<_> = filenames.into_iter().map(|(_, p)| p).collect();
 
-        Self {
-            filenames: filenames.into_iter().map(|(_, p)| p).collect(),
+        let max_file_size = limits.max_file_size.or(project.config().max_file_size);
+        let skip_binary_files =
+            limits.skip_binary_files || project.config().skip_binary_files.unwrap_or(false);
+        let skip_lfs_files =
+            limits.skip_lfs_files || project.config().skip_lfs_files.unwrap_or(false);
+
+        let mut filter = Self {
+            filenames: Vec::with_capacity(filenames.len()),
+            tags: Vec::with_capacity(filenames.len()),
             filename_prefix: project.relative_path(),
+            unfiltered: false,
+            oversized: Vec::new(),
+            binary: Vec::new(),
+            lfs: Vec::new(),
+            lfs_pointers: Vec::new(),
+            baseline: None,
+        };
+        for filename in filenames {
+            // Most hooks filter by `types`, identify each file only once instead of once per
+            // hook, and only if a hook matches it by name.
+            let tags = OnceLock::new();
+            if lfs_files.contains(filename) && skip_lfs_files {
+                filter.lfs.push(filename);
+            } else if lfs_files.contains(filename) && is_lfs_pointer(filename) {
+                filter.lfs_pointers.push(filename);
+            } else if max_file_size.is_some_and(|max| {
+                fs_err::metadata(filename).is_ok_and(|metadata| metadata.len() > max.0)
+            }) {
+                filter.oversized.push(filename);
+            } else if skip_binary_files
+                && tags
+                    .get_or_init(|| file_tags(filename))
+                    .as_ref()
+                    .is_some_and(|tags| tags.contains(&identify::tags::BINARY))
+            {
+                filter.binary.push(filename);
+            } else {
+                filter.filenames.push(filename);
+                filter.tags.push(tags);
+            }
         }
+
+        filter
+    }
+
+    /// A filter that passes `filenames` to every hook as is, e.g. the commit message file.
+    pub(crate) fn unfiltered<I>(filenames: I) -> Self
+    where
+        I: Iterator<Item = &'a PathBuf>,
+    {
+        let filenames: Vec<_> = filenames.map(PathBuf::as_path).collect();
+        Self {
+            tags: filenames.iter().map(|_| OnceLock::from(None)).collect(),
+            filenames,
+            filename_prefix: Path::new(""),
+            unfiltered: true,
+            oversized: Vec::new(),
+            binary: Vec::new(),
+            lfs: Vec::new(),
+            lfs_pointers: Vec::new(),
+            baseline: None,
+        }
+    }
+
+    #[must_use]
+    pub(crate) fn with_baseline(mut self, baseline: Option<Baseline>) -> Self {
+        self.baseline = baseline;
+        self
     }
 
     pub(crate) fn len(&self) -> usize {
         self.filenames.len()
     }
 
+    /// Files larger than the `max_file_size` limit.
+    pub(crate) fn oversized(&self) -> &[&'a Path] {
+        &self.oversized
+    }
+
+    /// Binary files skipped with `skip_binary_files`.
+    pub(crate) fn binary(&self) -> &[&'a Path] {
+        &self.binary
+    }
+
+    /// Git LFS files skipped with `skip_lfs_files`.
+    pub(crate) fn lfs(&self) -> &[&'a Path] {
+        &self.lfs
+    }
+
+    /// Git LFS pointer files whose content is not downloaded.
+    pub(crate) fn lfs_pointers(&self) -> &[&'a Path] {
+        &self.lfs_pointers
+    }
+
+    /// Files a hook matches by patterns and tags, but that are skipped by the file limits,
+    /// relative to the project.
+    pub(crate) fn skipped_for_hook(&self, hook: &Hook) -> Vec<&Path> {
+        if self.unfiltered {
+            return Vec::new();
+        }
+
+        let filter = FilenameFilter::for_hook(hook);
+        let tag_filter = FileTagFilter::for_hook(hook);
+        self.oversized
+            .iter()
+            .chain(&self.binary)
+            .chain(&self.lfs)
+            .chain(&self.lfs_pointers)
+            .filter_map(|filename| {
+                let stripped = filename.strip_prefix(self.filename_prefix).ok()?;
+                (filter.filter(stripped)
+                    && file_tags(filename).is_some_and(|tags| tag_filter.filter(&tags)))
+                .then_some(stripped)
+            })
+            .collect()
+    }
+
     /// Filter filenames by type tags for a specific hook.
     pub(crate) fn by_type(
         &self,
@@ -138,14 +314,13 @@ impl<'a> FileFilter<'a> {
         let filenames: Vec<_> = self
             .filenames
             .par_iter()
-            .filter(|filename| match tags_from_path(filename) {
-                Ok(tags) => filter.filter(&tags),
-                Err(err) => {
-                    error!(filename = ?filename.display(), error = %err, "Failed to get tags");
-                    false
-                }
+            .zip(self.tags.par_iter())
+            .filter(|(filename, tags)| {
+                tags.get_or_init(|| file_tags(filename))
+                    .as_ref()
+                    .is_some_and(|tags| filter.filter(tags))
             })
-            .copied()
+            .map(|(filename, _)| *filename)
             .collect();
 
         filenames
@@ -153,32 +328,57 @@ impl<'a> FileFilter<'a> {
 
     /// Filter filenames by file patterns and tags for a specific hook.
     pub(crate) fn for_hook(&self, hook: &Hook) -> Vec<&Path> {
+        if self.unfiltered {
+            return self.filenames.clone();
+        }
+
         // Filter by hook `files` and `exclude` patterns.
         let filter = FilenameFilter::for_hook(hook);
-        let filenames = self.filenames.par_iter().filter(|filename| {
-            if let Ok(stripped) = filename.strip_prefix(self.filename_prefix) {
-                filter.filter(stripped)
-            } else {
-                false
-            }
-        });
+        let filenames =
+            self.filenames
+                .par_iter()
+                .zip(self.tags.par_iter())
+                .filter(|(filename, _)| {
+                    if let Ok(stripped) = filename.strip_prefix(self.filename_prefix) {
+                        filter.filter(stripped)
+                    } else {
+                        false
+                    }
+                });
 
         // Filter by hook `types`, `types_or` and `exclude_types`.
         let filter = FileTagFilter::for_hook(hook);
-        let filenames = filenames.filter(|filename| match tags_from_path(filename) {
-            Ok(tags) => filter.filter(&tags),
-            Err(err) => {
-                error!(filename = ?filename.display(), error = %err, "Failed to get tags");
+        let filenames = filenames.filter(|(filename, tags)| {
+            let Some(tags) = tags.get_or_init(|| file_tags(filename)) else {
+                return false;
+            };
+            if !tags.contains(&identify::tags::SYMLINK) {
+                return filter.filter(tags);
+            }
+            if hook.skip_symlinks {
                 false
+            } else if hook.follow_symlinks {
+                // Broken symlinks have no target to take the tags from.
+                fs_err::canonicalize(filename)
+                    .ok()
+                    .and_then(|target| file_tags(&target))
+                    .is_some_and(|tags| filter.filter(&tags))
+            } else {
+                filter.filter(tags)
             }
         });
 
         // Strip the prefix to get relative paths.
         let filenames: Vec<_> = filenames
-            .map(|p| {
+            .map(|(p, _)| {
                 p.strip_prefix(self.filename_prefix)
                     .expect("Failed to strip prefix")
             })
+            .filter(|filename| {
+                self.baseline
+                    .as_ref()
+                    .is_none_or(|baseline| !baseline.contains(&hook.id, filename))
+            })
             .collect();
 
         filenames
@@ -191,9 +391,16 @@ pub(crate) struct CollectOptions {
     pub(crate) from_ref: Option<String>,
     pub(crate) to_ref: Option<String>,
     pub(crate) all_files: bool,
-    pub(crate) files: Vec<String>,
+    pub(crate) files: Vec<PathBuf>,
     pub(crate) directories: Vec<String>,
     pub(crate) commit_msg_filename: Option<String>,
+    /// Ad-hoc include/exclude patterns from the command line, intersected with each hook's own filters.
+    pub(crate) only_files_matching: Option<Regex>,
+    pub(crate) exclude_files_matching: Option<Regex>,
+    /// Fail instead of skipping files that are not materialized by a sparse checkout.
+    pub(crate) fail_on_sparse: bool,
+    /// With `all_files`, only the files changed in the working tree since this tree.
+    pub(crate) since_tree: Option<String>,
 }
 
 impl CollectOptions {
@@ -217,8 +424,18 @@ pub(crate) async fn collect_files(root: &Path, opts: CollectOptions) -> Result<V
         files,
         directories,
         commit_msg_filename,
+        only_files_matching,
+        exclude_files_matching,
+        fail_on_sparse,
+        since_tree,
     } = opts;
 
+    // The commit message file is passed to hooks as is, it's not subject to any filters.
+    if hook_stage.operate_on_commit_msg() {
+        let path = commit_msg_filename.expect("commit_msg_filename should be set");
+        return Ok(vec![std::path::absolute(path)?]);
+    }
+
     let git_root = GIT_ROOT.as_ref()?;
 
     // The workspace root relative to the git root.
@@ -231,15 +448,47 @@ pub(crate) async fn collect_files(root: &Path, opts: CollectOptions) -> Result<V
         from_ref,
         to_ref,
         all_files,
+        since_tree,
         files,
         directories,
-        commit_msg_filename,
     )
     .await?;
 
+    let filter = FilenameFilter::new(
+        only_files_matching.as_ref(),
+        exclude_files_matching.as_ref(),
+    );
+    let prekignore = PrekIgnore::load(git_root, &filenames)?;
+    // Submodules are listed as a single path, they are not files of this repository.
+    let submodules: FxHashSet<_> = git::get_submodule_paths(git_root)
+        .await?
+        .into_iter()
+        .collect();
+
+    let unmaterialized = unmaterialized_files(git_root, &filenames).await?;
+    if !unmaterialized.is_empty() {
+        if fail_on_sparse {
+            anyhow::bail!(
+                "Files are not materialized by the sparse checkout, add them to the sparse checkout to run hooks on them:\n{}",
+                unmaterialized
+                    .iter()
+                    .sorted()
+                    .map(|path| format!("  - {}", path.display()))
+                    .join("\n")
+            );
+        }
+        debug!(
+            "Skipped files outside the sparse checkout: {}",
+            unmaterialized.len()
+        );
+    }
+
     // Convert filenames to be relative to the workspace root.
     let mut filenames = filenames
         .into_iter()
+        .filter(|filename| !submodules.contains(filename))
+        .filter(|filename| !unmaterialized.contains(filename))
+        .filter(|filename| !prekignore.is_ignored(filename))
         .filter_map(|filename| {
             // Only keep files under the workspace root.
             filename
@@ -247,6 +496,7 @@ pub(crate) async fn collect_files(root: &Path, opts: CollectOptions) -> Result<V
                 .map(|p| normalize_path(p.to_path_buf()))
                 .ok()
         })
+        .filter(|filename| filter.filter(filename))
         .collect::<Vec<_>>();
 
     // Sort filenames if in tests to make the order consistent.
@@ -257,10 +507,178 @@ pub(crate) async fn collect_files(root: &Path, opts: CollectOptions) -> Result<V
     Ok(filenames)
 }
 
-fn adjust_relative_path(path: &str, new_cwd: &Path) -> Result<PathBuf, std::io::Error> {
+/// The files that are in the index but not in the working tree because of a sparse checkout.
+///
+/// Only the index is read, so in a partial clone no blobs are fetched for these files.
+async fn unmaterialized_files(
+    git_root: &Path,
+    filenames: &[PathBuf],
+) -> Result<FxHashSet<PathBuf>> {
+    if filenames.is_empty() || !git::is_sparse_checkout().await? {
+        return Ok(FxHashSet::default());
+    }
+
+    let skip_worktree: FxHashSet<_> = git::get_skip_worktree_paths(git_root)
+        .await?
+        .into_iter()
+        .collect();
+    Ok(filenames
+        .iter()
+        .filter(|filename| skip_worktree.contains(*filename) && !git_root.join(filename).exists())
+        .cloned()
+        .collect())
+}
+
+/// The `.prekignore` files of the git repository.
+struct PrekIgnore {
+    /// The matcher of each directory that has a `.prekignore` file, relative to the git root.
+    matchers: FxHashMap<PathBuf, Gitignore>,
+}
+
+impl PrekIgnore {
+    /// Load the `.prekignore` files in the parent directories of `filenames`.
+    fn load(git_root: &Path, filenames: &[PathBuf]) -> Result<Self> {
+        let dirs: FxHashSet<&Path> = filenames
+            .iter()
+            .flat_map(|filename| filename.ancestors().skip(1))
+            .collect();
+
+        let mut matchers = FxHashMap::default();
+        for dir in dirs {
+            let path = git_root.join(dir).join(PREKIGNORE);
+            if !path.is_file() {
+                continue;
+            }
+            let (matcher, err) = Gitignore::new(&path);
+            if let Some(err) = err {
+                return Err(err).with_context(|| format!("Failed to parse `{}`", path.display()));
+            }
+            debug!("Loaded `{}`", path.display());
+            matchers.insert(dir.to_path_buf(), matcher);
+        }
+
+        Ok(Self { matchers })
+    }
+
+    /// Check if `filename`, relative to the git root, is ignored. Like `.gitignore`, the
+    /// `.prekignore` file closest to the file takes precedence.
+    fn is_ignored(&self, filename: &Path) -> bool {
+        if self.matchers.is_empty() {
+            return false;
+        }
+        filename
+            .ancestors()
+            .skip(1)
+            .find_map(|dir| {
+                let matcher = self.matchers.get(dir)?;
+                let relative = filename.strip_prefix(dir).ok()?;
+                let matched = matcher.matched_path_or_any_parents(relative, false);
+                (!matched.is_none()).then(|| matched.is_ignore())
+            })
+            .unwrap_or(false)
+    }
+}
+
+fn adjust_relative_path(path: impl AsRef<Path>, new_cwd: &Path) -> Result<PathBuf, std::io::Error> {
     fs::relative_to(std::path::absolute(path)?, new_cwd)
 }
 
+fn is_glob(pattern: &Path) -> bool {
+    pattern
+        .to_str()
+        .is_some_and(|pattern| pattern.contains(['*', '?', '[']))
+}
+
+/// Expand shell-style glob patterns relative to the current directory against the files
+/// tracked by git. Returns paths relative to the git root.
+async fn expand_globs(patterns: &[PathBuf], git_root: &Path) -> Result<Vec<PathBuf>> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // Make the pattern relative to the git root, like the file paths from `git ls-files`.
+        let relative = normalize_path(adjust_relative_path(pattern, git_root)?);
+        let glob = GlobBuilder::new(&relative.to_string_lossy())
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid glob pattern `{}`", pattern.display()))?;
+        builder.add(glob);
+    }
+    let glob_set = builder.build()?;
+
+    let files = git::ls_files(git_root, Path::new(".")).await?;
+    let mut matched_patterns = FxHashSet::default();
+    let matched = files
+        .into_iter()
+        .filter(|file| {
+            let matches = glob_set.matches(file);
+            matched_patterns.extend(matches.iter().copied());
+            !matches.is_empty()
+        })
+        .map(normalize_path)
+        .collect();
+
+    for (idx, pattern) in patterns.iter().enumerate() {
+        if !matched_patterns.contains(&idx) {
+            warn_user!("No files matched the pattern: `{}`", pattern.display());
+        }
+    }
+
+    Ok(matched)
+}
+
+/// Expand the glob patterns among the `--files` arguments against the files tracked by git, so
+/// the expansion doesn't depend on the shell.
+///
+/// Only arguments that aren't existing paths are expanded, the matched files are returned as
+/// absolute paths. File names that come from git or `--files-from` must not be passed here,
+/// `a[1].txt` is a valid file name.
+pub(crate) async fn expand_file_args(files: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    let (globs, mut files): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .partition(|filename| !std::fs::exists(filename).unwrap_or(false) && is_glob(filename));
+    if globs.is_empty() {
+        return Ok(files);
+    }
+
+    let git_root = GIT_ROOT.as_ref()?;
+    let matched = expand_globs(&globs, git_root).await?;
+    debug!("Files matching globs: {}", matched.len());
+    files.extend(matched.into_iter().map(|file| git_root.join(file)));
+    Ok(files)
+}
+
+/// Read the filenames of `--files-from` from a file, or from stdin if `path` is `-`.
+///
+/// Filenames are separated by NUL if there is any, e.g. the output of `git diff -z`,
+/// and by newlines otherwise. They are taken literally, glob patterns are not expanded.
+pub(crate) fn read_files_from(path: &Path) -> Result<Vec<PathBuf>> {
+    let content = if path == Path::new("-") {
+        let mut content = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut content)
+            .context("Failed to read filenames from stdin")?;
+        content
+    } else {
+        fs_err::read(path)?
+    };
+
+    let filenames = if content.contains(&b'\0') {
+        content.split(|&b| b == b'\0').collect::<Vec<_>>()
+    } else {
+        content
+            .split(|&b| b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .collect()
+    };
+    filenames
+        .into_iter()
+        .filter(|filename| !filename.is_empty())
+        .map(|filename| {
+            git::path_from_bytes(filename)
+                .with_context(|| format!("Filenames in `{}` are not valid UTF-8", path.display()))
+        })
+        .collect()
+}
+
 /// Collect files to run hooks on.
 /// Returns a list of file paths relative to the git root.
 #[allow(clippy::too_many_arguments)]
@@ -271,20 +689,14 @@ async fn collect_files_from_args(
     from_ref: Option<String>,
     to_ref: Option<String>,
     all_files: bool,
-    files: Vec<String>,
+    since_tree: Option<String>,
+    files: Vec<PathBuf>,
     directories: Vec<String>,
-    commit_msg_filename: Option<String>,
 ) -> Result<Vec<PathBuf>> {
     if !hook_stage.operate_on_files() {
         return Ok(vec![]);
     }
 
-    if hook_stage == Stage::PrepareCommitMsg || hook_stage == Stage::CommitMsg {
-        let path = commit_msg_filename.expect("commit_msg_filename should be set");
-        let path = adjust_relative_path(&path, git_root)?;
-        return Ok(vec![path]);
-    }
-
     if let (Some(from_ref), Some(to_ref)) = (from_ref, to_ref) {
         let files = git::get_changed_files(&from_ref, &to_ref, workspace_root).await?;
         debug!(
@@ -304,6 +716,7 @@ async fn collect_files_from_args(
 
         // Fun fact: if a hook specified `types: [directory]`, it won't run in `--all-files` mode.
 
+        // Glob patterns of the command line were already expanded, see `expand_file_args`.
         let (exists, non_exists): (FxHashSet<_>, Vec<_>) =
             files.into_iter().partition_map(|filename| {
                 if std::fs::exists(&filename).unwrap_or(false) {
@@ -316,12 +729,12 @@ async fn collect_files_from_args(
             if non_exists.len() == 1 {
                 warn_user!(
                     "This file does not exist, it will be ignored: `{}`",
-                    non_exists[0]
+                    non_exists[0].display()
                 );
             } else {
                 warn_user!(
                     "These files do not exist, they will be ignored: `{}`",
-                    non_exists.join(", ")
+                    non_exists.iter().map(|path| path.display()).join(", ")
                 );
             }
         }
@@ -344,6 +757,17 @@ async fn collect_files_from_args(
         return Ok(exists.into_iter().collect());
     }
 
+    if let (true, Some(tree)) = (all_files, since_tree) {
+        // Deleted files are reported as changed too.
+        let files = git::get_changed_worktree_files(&tree, workspace_root)
+            .await?
+            .into_iter()
+            .filter(|file| git_root.join(file).symlink_metadata().is_ok())
+            .collect::<Vec<_>>();
+        debug!("Files changed since tree {tree}: {}", files.len());
+        return Ok(files);
+    }
+
     if all_files {
         let files = git::ls_files(git_root, workspace_root).await?;
         debug!("All files in the workspace: {}", files.len());
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::cli::run::output::{HookResult, HookStatus};
+use crate::store::Store;
+
+/// Number of runs kept in the history of a workspace.
+const MAX_RUNS: usize = 500;
+
+/// A run of the hooks of a workspace, as recorded in its history for `prek stats`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Run {
+    /// Seconds since the Unix epoch.
+    pub(crate) timestamp: u64,
+    pub(crate) hooks: Vec<RunHook>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RunHook {
+    /// The full hook id.
+    pub(crate) id: String,
+    pub(crate) status: HookStatus,
+    /// Wall time of the hook in seconds.
+    pub(crate) duration: f64,
+    /// The number of files passed to the hook.
+    pub(crate) files: usize,
+}
+
+/// The runs of a workspace, oldest first.
+///
+/// The runs of all workspaces are kept in the `history.json` registry of the store, keyed by the
+/// canonical path of the workspace root. It's updated under a lock and replaced atomically, so
+/// concurrent runs don't lose each other's records.
+pub(crate) struct History<'a> {
+    store: &'a Store,
+    root: PathBuf,
+}
+
+impl<'a> History<'a> {
+    pub(crate) fn new(store: &'a Store, workspace_root: &Path) -> Self {
+        let root =
+            dunce::canonicalize(workspace_root).unwrap_or_else(|_| workspace_root.to_path_buf());
+        Self { store, root }
+    }
+
+    fn read(&self) -> anyhow::Result<BTreeMap<PathBuf, Vec<Run>>> {
+        match fs_err::read_to_string(self.store.history_file()) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// The last `limit` runs, oldest first.
+    pub(crate) fn runs(&self, limit: usize) -> Vec<Run> {
+        let mut history = match self.read() {
+            Ok(history) => history,
+            Err(err) => {
+                debug!("Failed to read the history: {err}");
+                return Vec::new();
+            }
+        };
+        let mut runs = history.remove(&self.root).unwrap_or_default();
+        runs.drain(..runs.len().saturating_sub(limit));
+        runs
+    }
+
+    /// Append the hooks that ran to the history, dropping the oldest runs.
+    pub(crate) async fn record(&self, results: &[HookResult]) {
+        let hooks = results
+            .iter()
+            .filter(|result| {
+                matches!(
+                    result.status,
+                    HookStatus::Passed
+                        | HookStatus::Failed
+                        | HookStatus::Warning
+                        | HookStatus::TimedOut
+                        | HookStatus::OverLimit
+                )
+            })
+            .map(|result| RunHook {
+                id: result.full_id.clone(),
+                status: result.status,
+                duration: result.duration,
+                files: result.files.len(),
+            })
+            .collect::<Vec<_>>();
+        if hooks.is_empty() {
+            return;
+        }
+        let run = Run {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            hooks,
+        };
+
+        if let Err(err) = self.append(run).await {
+            debug!("Failed to record the run in the history: {err}");
+        }
+    }
+
+    async fn append(&self, run: Run) -> anyhow::Result<()> {
+        let _lock = self.store.lock_history().await?;
+
+        let mut history = self.read()?;
+        let runs = history.entry(self.root.clone()).or_default();
+        runs.push(run);
+        runs.drain(..runs.len().saturating_sub(MAX_RUNS));
+
+        let path = self.store.history_file();
+        let temp = tempfile::NamedTempFile::new_in(path.parent().expect("has parent"))?;
+        serde_json::to_writer(temp.as_file(), &history)?;
+        temp.persist(&path).map_err(|err| err.error)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::hash::Hash;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::cache_key::CacheKey;
+use crate::config::Stage;
+use crate::git;
+use crate::hook::InstalledHook;
+use crate::store::{CacheBucket, Store};
+use crate::version;
+use crate::workspace::Workspace;
+
+#[derive(Deserialize, Serialize)]
+struct Record {
+    /// The digest of the configs, hooks and environments of the run.
+    hooks: String,
+    /// The tree of the tracked files in the working tree when the run started.
+    tree: String,
+}
+
+/// Remembers the working tree of the last successful `prek run --incremental`.
+///
+/// The state is kept per workspace and selectors, and only reused if the configs, hooks and
+/// their environments are unchanged, otherwise all files are checked again.
+pub(crate) struct IncrementalRun {
+    path: PathBuf,
+    hooks: String,
+    /// The tree of the working tree before hooks run.
+    tree: Option<String>,
+}
+
+impl IncrementalRun {
+    pub(crate) async fn new(
+        store: &Store,
+        workspace: &Workspace,
+        hooks: &[InstalledHook],
+        includes: &[String],
+        skips: &[String],
+        projects: &[String],
+        hook_stage: Stage,
+    ) -> Result<Self> {
+        let mut key = CacheKey::new();
+        workspace.root().hash(&mut key);
+        includes.hash(&mut key);
+        skips.hash(&mut key);
+        projects.hash(&mut key);
+        hook_stage.as_str().hash(&mut key);
+        let key = key.hex();
+
+        let mut digest = CacheKey::new();
+        version::version().hash(&mut digest);
+        for project in workspace.projects() {
+            fs_err::read(project.config_file()).ok().hash(&mut digest);
+        }
+        let mut digests = hooks
+            .iter()
+            .map(|hook| {
+                let mut key = CacheKey::new();
+                format!("{hook:#}").hash(&mut key);
+                hook.project().config_file().hash(&mut key);
+                hook.install_info().hash(&mut key);
+                key.hex()
+            })
+            .collect::<Vec<_>>();
+        digests.sort_unstable();
+        digests.hash(&mut digest);
+
+        Ok(Self {
+            path: store
+                .cache_path(CacheBucket::Prek)
+                .join("incremental")
+                .join(key),
+            hooks: digest.hex(),
+            tree: git::get_worktree_tree().await?,
+        })
+    }
+
+    /// The tree of the last successful run, if the hooks and their environments are unchanged.
+    pub(crate) async fn last_tree(&self) -> Result<Option<String>> {
+        let Ok(content) = fs_err::read_to_string(&self.path) else {
+            return Ok(None);
+        };
+        let Ok(record) = serde_json::from_str::<Record>(&content) else {
+            return Ok(None);
+        };
+        if record.hooks != self.hooks {
+            debug!("Hooks changed since the last successful run, checking all files");
+            return Ok(None);
+        }
+        // The tree is not referenced by anything, it may have been garbage collected.
+        Ok(git::get_tree(&record.tree).await?)
+    }
+
+    /// Remember the working tree the run started with.
+    pub(crate) fn mark_passed(self) {
+        let Some(tree) = self.tree else {
+            return;
+        };
+        let record = Record {
+            hooks: self.hooks,
+            tree,
+        };
+        let result = fs_err::create_dir_all(self.path.parent().expect("has parent"))
+            .and_then(|()| Ok(serde_json::to_string(&record)?))
+            .and_then(|content| fs_err::write(&self.path, content));
+        if let Err(err) = result {
+            debug!("Failed to save incremental run state: {err}");
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::io::IsTerminal;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use dialoguer::MultiSelect;
+use dialoguer::theme::ColorfulTheme;
+use unicode_width::UnicodeWidthStr;
+
+use crate::ci::CI;
+use crate::cli::run::last_run::LastRun;
+use crate::cli::run::output::HookStatus;
+use crate::hook::Hook;
+
+/// Let the user pick the hooks to run from a checklist, with the last result of each hook.
+///
+/// Returns the selected hooks, in the order they were given.
+pub(crate) fn select_hooks(hooks: Vec<Arc<Hook>>, last_run: &LastRun) -> Result<Vec<Arc<Hook>>> {
+    if let Some(ci) = *CI {
+        anyhow::bail!("`--interactive` is not available in {ci}");
+    }
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        anyhow::bail!("`--interactive` requires a terminal");
+    }
+
+    let ids = hooks.iter().map(|hook| hook.full_id()).collect::<Vec<_>>();
+    let width = ids.iter().map(|id| id.width()).max().unwrap_or(0);
+    let items = hooks
+        .iter()
+        .zip(&ids)
+        .map(|(hook, id)| {
+            let last = match last_run.result(id) {
+                Some(result) => {
+                    let status = match result.status {
+                        HookStatus::Passed => "passed",
+                        HookStatus::Failed => "failed",
+                        HookStatus::Warning => "warning",
+                        HookStatus::TimedOut => "timed out",
+                        HookStatus::OverLimit => "over limit",
+                        HookStatus::Skipped | HookStatus::DryRun => "skipped",
+                    };
+                    format!("{status} in {:.2}s", result.duration)
+                }
+                None => "not run yet".to_string(),
+            };
+            let padding = " ".repeat(width - id.width());
+            format!("{id}{padding}  {} ({last})", hook.name)
+        })
+        .collect::<Vec<_>>();
+
+    let selected = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select hooks to run (space to toggle, enter to confirm)")
+        .items(&items)
+        .report(false)
+        .interact_opt()
+        .context("Failed to read the selected hooks")?
+        .unwrap_or_default();
+
+    Ok(hooks
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| selected.contains(index))
+        .map(|(_, hook)| hook)
+        .collect())
+}
@@ -0,0 +1,130 @@
+use std::fmt::Write;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use itertools::Itertools;
+use owo_colors::OwoColorize;
+use rustc_hash::FxHashSet;
+
+use crate::cli::run::scratch::ScratchTree;
+use crate::git;
+use crate::hook::InstalledHook;
+use crate::printer::Printer;
+use crate::store::Store;
+
+/// Runs hooks on the staged content under `stash_mode: isolate`.
+///
+/// Hooks run in a scratch worktree of the staged content. The working tree is not stashed, and
+/// the fixes of hooks are applied to the index and the working tree once they finished.
+pub(crate) struct IsolatedTree {
+    worktree: ScratchTree,
+    git_root: PathBuf,
+}
+
+impl IsolatedTree {
+    /// `files` are the files passed to hooks, relative to the git root.
+    pub(crate) async fn new(store: &Store, git_root: &Path, files: &[PathBuf]) -> Result<Self> {
+        Ok(Self {
+            worktree: ScratchTree::new(store, git_root, files).await?,
+            git_root: git_root.to_path_buf(),
+        })
+    }
+
+    /// A copy of `hook` that runs in the copy of its project directory.
+    pub(crate) fn hook(&self, hook: &InstalledHook) -> InstalledHook {
+        self.worktree.hook(hook)
+    }
+
+    /// The changes hooks made to the copy of `path`, in the format of `git diff`.
+    pub(crate) async fn get_diff(&self, path: &Path) -> Result<Vec<u8>> {
+        self.worktree.get_diff(path).await
+    }
+
+    /// Merge the fix of `file` into its working tree content, `None` if they conflict.
+    async fn merge(&self, file: &Path) -> Result<Option<Vec<u8>>> {
+        let staged = git::get_staged_content(&self.git_root, file)
+            .await?
+            .unwrap_or_default();
+        let mut base = tempfile::NamedTempFile::new()?;
+        base.write_all(&staged)?;
+
+        let output = git::git_cmd("git merge-file")?
+            .arg("merge-file")
+            .arg("-p")
+            .arg(self.git_root.join(file))
+            .arg(base.path())
+            .arg(self.worktree.path().join(file))
+            .check(false)
+            .output()
+            .await?;
+        // The exit code is the number of conflicts, negative on errors.
+        match output.status.code() {
+            Some(0) => Ok(Some(output.stdout)),
+            Some(1..=127) => Ok(None),
+            _ => bail!(
+                "Failed to merge the fixes of `{}`: {}",
+                file.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }
+    }
+
+    /// Apply the changes hooks made to the copy to the index and the working tree.
+    ///
+    /// The fixes of files that also have changes that are not staged are merged into the working
+    /// tree. If they conflict with those changes, nothing is applied.
+    pub(crate) async fn apply_fixes(&self, printer: Printer) -> Result<()> {
+        let fixed = self.worktree.modified_files().await?;
+        if fixed.is_empty() {
+            return Ok(());
+        }
+
+        let unstaged = git::files_not_staged(&[&self.git_root])
+            .await?
+            .into_iter()
+            .collect::<FxHashSet<_>>();
+
+        let mut merged = Vec::new();
+        let mut conflicting = Vec::new();
+        for file in fixed.iter().filter(|file| unstaged.contains(*file)) {
+            match self.merge(file).await? {
+                Some(content) => merged.push((file, content)),
+                None => conflicting.push(file),
+            }
+        }
+        if !conflicting.is_empty() {
+            bail!(
+                "The fixes of hooks conflict with changes that are not staged, they were not applied:\n{}\nStage or stash the changes and run the hooks again",
+                format_paths(conflicting.into_iter())
+            );
+        }
+
+        for file in &fixed {
+            let copy = self.worktree.path().join(file);
+            git::stage_file_content(&self.git_root, file, &copy).await?;
+            if !unstaged.contains(file) {
+                fs_err::tokio::copy(&copy, self.git_root.join(file)).await?;
+            }
+        }
+        for (file, content) in merged {
+            fs_err::tokio::write(self.git_root.join(file), content).await?;
+        }
+
+        writeln!(
+            printer.stdout_important(),
+            "\n{}\n{}",
+            "Applied the fixes of hooks to the index and the working tree:".bold(),
+            format_paths(fixed.iter())
+        )?;
+
+        Ok(())
+    }
+}
+
+fn format_paths<'a>(paths: impl Iterator<Item = &'a PathBuf>) -> String {
+    paths
+        .sorted()
+        .map(|path| format!("  - {}", path.display()))
+        .join("\n")
+}
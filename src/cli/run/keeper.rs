@@ -1,5 +1,7 @@
+use std::ffi::{OsStr, OsString};
+use std::hash::Hash;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Output};
 use std::sync::Mutex;
 
 use anstream::eprintln;
@@ -9,53 +11,83 @@ use tracing::{error, trace};
 
 use constants::env_vars::EnvVars;
 
+use crate::cache_key::CacheKey;
 use crate::cleanup::add_cleanup;
-use crate::fs::Simplified;
-use crate::git::{self, GIT, git_cmd};
+use crate::fs::{LockedFile, Simplified};
+use crate::git::{self, GIT, GIT_ROOT, PATHS_PER_COMMAND, git_cmd};
 use crate::store::Store;
 
 static RESTORE_WORKTREE: Mutex<Option<WorkTreeKeeper>> = Mutex::new(None);
 
-struct IntentToAddKeeper(Vec<PathBuf>);
+/// The file listing the saved Git LFS files, in the order of their copies.
+const LFS_PATHS: &str = "paths";
+
+struct IntentToAddKeeper {
+    git_root: PathBuf,
+    /// Intent-to-add files relative to the git root.
+    files: Vec<PathBuf>,
+}
 struct WorkingTreeKeeper {
     root: PathBuf,
     patch: Option<PathBuf>,
+    /// Records the patch while it's not restored, so a crashed run can be recovered.
+    pending: PathBuf,
+    /// Held while the working tree is stashed, a pending patch without the lock is left by a
+    /// crashed run.
+    _lock: LockedFile,
 }
 
 impl IntentToAddKeeper {
     async fn clean(root: &Path) -> Result<Self> {
+        let git_root = GIT_ROOT.as_ref()?.clone();
         let files = git::intent_to_add_files(root).await?;
         if files.is_empty() {
-            return Ok(Self(vec![]));
+            return Ok(Self { git_root, files });
         }
+        trace!(
+            "Removing intent-to-add files from the index: {}",
+            files.len()
+        );
 
-        // TODO: xargs
-        git_cmd("git rm")?
-            .arg("rm")
-            .arg("--cached")
-            .arg("--")
-            .args(&files)
-            .check(true)
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .status()
-            .await?;
+        // Paths from `git diff` are relative to the git root, not the current directory.
+        for chunk in files.chunks(PATHS_PER_COMMAND) {
+            git_cmd("git rm")?
+                .arg("rm")
+                .arg("--cached")
+                .arg("--quiet")
+                .arg("--")
+                .args(chunk)
+                .current_dir(&git_root)
+                .check(true)
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .await?;
+        }
 
-        Ok(Self(files))
+        Ok(Self { git_root, files })
     }
 
     fn restore(&self) -> Result<()> {
-        // Restore the intent-to-add changes.
-        if !self.0.is_empty() {
-            Command::new(GIT.as_ref()?)
+        // Files deleted by hooks can't be added again, and would fail the whole command.
+        let files = self
+            .files
+            .iter()
+            .filter(|file| self.git_root.join(file).exists())
+            .collect::<Vec<_>>();
+
+        for chunk in files.chunks(PATHS_PER_COMMAND) {
+            let output = Command::new(GIT.as_ref()?)
                 .arg("add")
                 .arg("--intent-to-add")
                 .arg("--")
-                // TODO: xargs
-                .args(&self.0)
-                .stdout(std::process::Stdio::null())
-                .stderr(std::process::Stdio::null())
-                .status()?;
+                .args(chunk)
+                .current_dir(&self.git_root)
+                .output()?;
+            if !output.status.success() {
+                error!("Failed to restore intent-to-add files: {:?}", output);
+                anyhow::bail!("Failed to restore intent-to-add files");
+            }
         }
         Ok(())
     }
@@ -74,8 +106,98 @@ impl Drop for IntentToAddKeeper {
 
 impl WorkingTreeKeeper {
     async fn clean(root: &Path, patch_dir: &Path) -> Result<Self> {
+        let mut key = CacheKey::new();
+        root.hash(&mut key);
+        let key = key.hex();
+
+        fs_err::create_dir_all(patch_dir)?;
+        let lock =
+            LockedFile::acquire(patch_dir.join(format!("{key}.lock")), "working tree").await?;
+        let pending = patch_dir.join(format!("{key}.pending"));
+        Self::recover(&pending)?;
+
         let tree = git::write_tree().await?;
+        let output = Self::diff_index(&tree, [root]).await?;
+
+        if output.status.success() {
+            trace!("No non-staged changes detected");
+            // No non-staged changes
+            Ok(Self {
+                root: root.to_path_buf(),
+                patch: None,
+                pending,
+                _lock: lock,
+            })
+        } else if output.stdout.trim_ascii().is_empty() {
+            trace!("diff-index status code 1 with empty stdout");
+            // probably git auto crlf behavior quirks
+            Ok(Self {
+                root: root.to_path_buf(),
+                patch: None,
+                pending,
+                _lock: lock,
+            })
+        } else {
+            let now = std::time::SystemTime::now();
+            let pid = std::process::id();
+            let patch_name = format!(
+                "{}-{}.patch",
+                now.duration_since(std::time::UNIX_EPOCH)?.as_millis(),
+                pid
+            );
+            let patch_path = patch_dir.join(&patch_name);
+
+            eprintln!(
+                "{}",
+                format!(
+                    "Non-staged changes detected, saving to `{}`",
+                    patch_path.user_display()
+                )
+                .yellow()
+            );
+
+            // Git LFS files are saved as is: a patch only has their pointers, and applying it
+            // runs the LFS filters, leaving a pointer in the working tree if they fail.
+            let (lfs_files, other_files) = Self::changed_files(&tree, root).await?;
+            let patch = if lfs_files.is_empty() {
+                output.stdout
+            } else {
+                Self::save_lfs_files(&lfs_files, &lfs_backup_dir(&patch_path))?;
+                // The other files are diffed in chunks, excluding the LFS files would need them
+                // all on a single command line. Without rename detection, the diffs of the
+                // chunks add up to the patch of all files.
+                let mut patch = Vec::new();
+                for chunk in other_files.chunks(PATHS_PER_COMMAND) {
+                    let pathspecs = chunk.iter().map(|path| {
+                        let mut pathspec = OsString::from(":(top,literal)");
+                        pathspec.push(path);
+                        pathspec
+                    });
+                    patch.extend(Self::diff_index(&tree, pathspecs).await?.stdout);
+                }
+                patch
+            };
+            fs_err::write(&patch_path, patch)?;
+            fs_err::write(&pending, patch_path.to_string_lossy().as_bytes())?;
+
+            // Clean the working tree
+            Self::checkout_working_tree(root)?;
 
+            Ok(Self {
+                root: root.to_path_buf(),
+                patch: Some(patch_path),
+                pending,
+                _lock: lock,
+            })
+        }
+    }
+
+    /// Diff the working tree paths matching `pathspecs` against `tree`.
+    async fn diff_index<I>(tree: &str, pathspecs: I) -> Result<Output>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<OsStr>,
+    {
         let mut cmd = git_cmd("git diff-index")?;
         let output = cmd
             .arg("diff-index")
@@ -86,60 +208,120 @@ impl WorkingTreeKeeper {
             .arg("--no-ext-diff")
             .arg(tree)
             .arg("--")
-            .arg(root)
+            .args(pathspecs)
             .check(false)
             .output()
             .await?;
 
-        if output.status.success() {
-            trace!("No non-staged changes detected");
-            // No non-staged changes
-            Ok(Self {
-                root: root.to_path_buf(),
-                patch: None,
-            })
-        } else if output.status.code() == Some(1) {
-            if output.stdout.trim_ascii().is_empty() {
-                trace!("diff-index status code 1 with empty stdout");
-                // probably git auto crlf behavior quirks
-                Ok(Self {
-                    root: root.to_path_buf(),
-                    patch: None,
-                })
-            } else {
-                let now = std::time::SystemTime::now();
-                let pid = std::process::id();
-                let patch_name = format!(
-                    "{}-{}.patch",
-                    now.duration_since(std::time::UNIX_EPOCH)?.as_millis(),
-                    pid
-                );
-                let patch_path = patch_dir.join(&patch_name);
-
-                eprintln!(
-                    "{}",
-                    format!(
-                        "Non-staged changes detected, saving to `{}`",
-                        patch_path.user_display()
-                    )
-                    .yellow()
-                );
-                fs_err::create_dir_all(patch_dir)?;
-                fs_err::write(&patch_path, output.stdout)?;
-
-                // Clean the working tree
-                Self::checkout_working_tree(root)?;
-
-                Ok(Self {
-                    root: root.to_path_buf(),
-                    patch: Some(patch_path),
-                })
-            }
+        if output.status.success() || output.status.code() == Some(1) {
+            Ok(output)
         } else {
             Err(cmd.check_status(output.status).unwrap_err().into())
         }
     }
 
+    /// The Git LFS files and the other files with non-staged changes, relative to the git root.
+    async fn changed_files(tree: &str, root: &Path) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+        let git_root = GIT_ROOT.as_ref()?;
+        let changed = git::get_changed_worktree_files(tree, root)
+            .await?
+            .into_iter()
+            .map(|file| git_root.join(file))
+            .collect::<Vec<_>>();
+        let lfs_files =
+            git::get_lfs_files(&changed.iter().map(PathBuf::as_path).collect::<Vec<_>>()).await?;
+
+        let (lfs, other): (Vec<_>, Vec<_>) =
+            changed.iter().partition(|file| lfs_files.contains(*file));
+        let relative = |files: Vec<&PathBuf>| -> Vec<PathBuf> {
+            files
+                .into_iter()
+                .filter_map(|file| file.strip_prefix(git_root).ok())
+                .map(Path::to_path_buf)
+                .collect()
+        };
+        Ok((relative(lfs), relative(other)))
+    }
+
+    /// Copy the working tree version of Git LFS files into `dir`, deleted files have no copy.
+    fn save_lfs_files(files: &[PathBuf], dir: &Path) -> Result<()> {
+        let git_root = GIT_ROOT.as_ref()?;
+        fs_err::create_dir_all(dir)?;
+
+        let mut paths = Vec::new();
+        for (i, file) in files.iter().enumerate() {
+            let path = git_root.join(file);
+            if path.exists() {
+                fs_err::copy(&path, dir.join(i.to_string()))?;
+            }
+            paths.extend_from_slice(file.as_os_str().as_encoded_bytes());
+            paths.push(b'\0');
+        }
+        fs_err::write(dir.join(LFS_PATHS), paths)?;
+
+        Ok(())
+    }
+
+    /// Copy back the Git LFS files saved with the patch, if any.
+    fn restore_lfs_files(patch: &Path) -> Result<()> {
+        let dir = lfs_backup_dir(patch);
+        let paths = match fs_err::read(dir.join(LFS_PATHS)) {
+            Ok(paths) => paths,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let git_root = GIT_ROOT.as_ref()?;
+        for (i, file) in paths
+            .split(|&b| b == b'\0')
+            .filter(|file| !file.is_empty())
+            .enumerate()
+        {
+            let path = git_root.join(git::path_from_bytes(file)?);
+            let backup = dir.join(i.to_string());
+            if backup.exists() {
+                fs_err::copy(&backup, &path)?;
+            } else if path.exists() {
+                fs_err::remove_file(&path)?;
+            }
+        }
+        fs_err::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    /// Restore the changes stashed by a run that crashed before restoring them.
+    fn recover(pending: &Path) -> Result<()> {
+        let patch = match fs_err::read_to_string(pending) {
+            Ok(patch) => PathBuf::from(patch),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        if Self::git_apply(&patch).is_ok() && Self::restore_lfs_files(&patch).is_ok() {
+            eprintln!(
+                "{}",
+                format!(
+                    "Restored working tree changes of an interrupted run from `{}`",
+                    patch.user_display()
+                )
+                .yellow()
+            );
+        } else {
+            eprintln!(
+                "{}",
+                format!(
+                    "Failed to restore working tree changes of an interrupted run, they are saved in `{}`",
+                    patch.user_display()
+                )
+                .red()
+            );
+        }
+        fs_err::remove_file(pending)?;
+
+        Ok(())
+    }
+
     fn checkout_working_tree(root: &Path) -> Result<()> {
         let output = Command::new(GIT.as_ref()?)
             .arg("-c")
@@ -159,10 +341,18 @@ impl WorkingTreeKeeper {
     }
 
     fn git_apply(patch: &Path) -> Result<()> {
+        // Only Git LFS files had changes.
+        if fs_err::metadata(patch)?.len() == 0 {
+            return Ok(());
+        }
+
         let output = Command::new(GIT.as_ref()?)
             .arg("apply")
             .arg("--whitespace=nowarn")
             .arg(patch)
+            // Paths in the patch are relative to the git root, `git apply` ignores paths outside
+            // of the current directory.
+            .current_dir(GIT_ROOT.as_ref()?)
             .output()?;
         if output.status.success() {
             Ok(())
@@ -188,6 +378,8 @@ impl WorkingTreeKeeper {
             Self::checkout_working_tree(&self.root)?;
             Self::git_apply(patch)?;
         }
+        Self::restore_lfs_files(patch)?;
+        fs_err::remove_file(&self.pending)?;
 
         eprintln!(
             "{}",
@@ -202,6 +394,11 @@ impl WorkingTreeKeeper {
     }
 }
 
+/// The directory with the Git LFS files saved along with `patch`.
+fn lfs_backup_dir(patch: &Path) -> PathBuf {
+    patch.with_extension("lfs")
+}
+
 impl Drop for WorkingTreeKeeper {
     fn drop(&mut self) {
         if let Err(err) = self.restore() {
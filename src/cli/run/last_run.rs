@@ -0,0 +1,123 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::cache_key::CacheKey;
+use crate::cli::run::output::{HookResult, HookStatus};
+use crate::store::{CacheBucket, Store};
+
+/// Remembers the hooks that failed in the last runs of a workspace, for `prek run --failed`.
+///
+/// Results are merged into the previous ones: a hook is remembered until it passes, together
+/// with the files it failed on.
+pub(crate) struct LastRun {
+    path: PathBuf,
+    state: State,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct State {
+    /// The files each failed hook ran on, keyed by the full hook id.
+    failed: BTreeMap<String, BTreeSet<PathBuf>>,
+    /// The last result of each hook that ran, keyed by the full hook id.
+    #[serde(default)]
+    hooks: BTreeMap<String, LastResult>,
+}
+
+/// The last result of a hook, shown by `prek run --interactive`.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct LastResult {
+    pub(crate) status: HookStatus,
+    /// Wall time of the hook in seconds.
+    pub(crate) duration: f64,
+}
+
+impl LastRun {
+    pub(crate) fn load(store: &Store, workspace_root: &Path) -> Self {
+        let mut key = CacheKey::new();
+        workspace_root.hash(&mut key);
+        let key = key.hex();
+
+        let path = store
+            .cache_path(CacheBucket::Prek)
+            .join("last-run")
+            .join(key);
+        let state = fs_err::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Self { path, state }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.state.failed.is_empty()
+    }
+
+    /// Whether the hook with `full_id` failed in the last run.
+    pub(crate) fn failed(&self, full_id: &str) -> bool {
+        self.state.failed.contains_key(full_id)
+    }
+
+    /// The last result of the hook with `full_id`, if it ran before.
+    pub(crate) fn result(&self, full_id: &str) -> Option<LastResult> {
+        self.state.hooks.get(full_id).copied()
+    }
+
+    /// The files the failed hooks ran on, relative to the workspace root.
+    pub(crate) fn files(&self) -> impl Iterator<Item = &Path> {
+        self.state
+            .failed
+            .values()
+            .flatten()
+            .map(PathBuf::as_path)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+    }
+
+    /// Record the results of a run, hooks that didn't run keep their previous result.
+    pub(crate) fn record(&mut self, results: &[HookResult]) {
+        for result in results {
+            match result.status {
+                HookStatus::Passed => {
+                    self.state.failed.remove(&result.full_id);
+                }
+                HookStatus::Failed | HookStatus::TimedOut | HookStatus::OverLimit => {
+                    self.state.failed.insert(
+                        result.full_id.clone(),
+                        result.files.iter().cloned().collect(),
+                    );
+                }
+                HookStatus::Warning | HookStatus::Skipped | HookStatus::DryRun => {}
+            }
+            if matches!(
+                result.status,
+                HookStatus::Passed
+                    | HookStatus::Failed
+                    | HookStatus::Warning
+                    | HookStatus::TimedOut
+                    | HookStatus::OverLimit
+            ) {
+                self.state.hooks.insert(
+                    result.full_id.clone(),
+                    LastResult {
+                        status: result.status,
+                        duration: result.duration,
+                    },
+                );
+            }
+        }
+    }
+
+    pub(crate) fn save(&self) {
+        let result = fs_err::create_dir_all(self.path.parent().expect("has parent"))
+            .and_then(|()| Ok(serde_json::to_string(&self.state)?))
+            .and_then(|content| fs_err::write(&self.path, content));
+        if let Err(err) = result {
+            debug!("Failed to save the results of the run: {err}");
+        }
+    }
+}
@@ -1,9 +1,25 @@
-pub(crate) use filter::{CollectOptions, FileFilter, collect_files};
-pub(crate) use run::{install_hooks, run};
+pub(crate) use filter::{
+    CollectOptions, FileFilter, FileLimits, collect_files, expand_file_args, read_files_from,
+};
+pub(crate) use history::History;
+pub(crate) use output::{HookResult, HookStatus, Report, RunOutput};
+pub(crate) use repos::{SubmoduleOptions, run_repos, run_submodules};
+pub(crate) use run::{RunOptions, install_hooks, run};
 pub(crate) use selector::{SelectorSource, Selectors};
 
+mod cache;
 mod filter;
+mod history;
+mod incremental;
+mod interactive;
+mod isolate;
 mod keeper;
+mod last_run;
+mod output;
+mod preview;
+mod rebase;
+mod repos;
 #[allow(clippy::module_inception)]
 mod run;
+mod scratch;
 mod selector;
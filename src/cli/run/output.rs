@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace};
+
+use crate::fs::CWD;
+use crate::hook::Hook;
+use crate::printer::Stdout;
+use crate::warn_user;
+
+/// Where `prek run` sends hook results, in addition to the terminal.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) enum RunOutput {
+    /// Human readable output only.
+    #[default]
+    Text,
+    /// Also write a JSON report of the run to the given file.
+    Json(PathBuf),
+    /// Also send hook results to the named custom reporter.
+    Custom(String),
+    /// Stable, line-oriented records on stdout instead of the human readable output, set by
+    /// `--porcelain`.
+    Porcelain,
+}
+
+impl FromStr for RunOutput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "text" {
+            return Ok(Self::Text);
+        }
+        if let Some(path) = s.strip_prefix("json:") {
+            if path.is_empty() {
+                return Err("json report path must not be empty".to_string());
+            }
+            return Ok(Self::Json(PathBuf::from(path)));
+        }
+        match s.strip_prefix("custom:") {
+            Some(name) if !name.is_empty() => Ok(Self::Custom(name.to_string())),
+            Some(_) => Err("custom reporter name must not be empty".to_string()),
+            None => Err(format!(
+                "invalid output `{s}`, expected `text`, `json:<path>` or `custom:<name>`"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum HookStatus {
+    Passed,
+    Failed,
+    /// A hook with `severity: warning` failed.
+    Warning,
+    /// The hook was killed after its `timeout_seconds`.
+    TimedOut,
+    /// The hook was killed for exceeding its `limits`.
+    OverLimit,
+    Skipped,
+    DryRun,
+}
+
+impl HookStatus {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Passed => "passed",
+            Self::Failed => "failed",
+            Self::Warning => "warning",
+            Self::TimedOut => "timed-out",
+            Self::OverLimit => "over-limit",
+            Self::Skipped => "skipped",
+            Self::DryRun => "dry-run",
+        }
+    }
+}
+
+/// The result of a single hook run, as seen by output reporters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct HookResult {
+    pub(crate) id: String,
+    pub(crate) full_id: String,
+    pub(crate) name: String,
+    pub(crate) project: String,
+    pub(crate) status: HookStatus,
+    pub(crate) exit_code: i32,
+    pub(crate) files_modified: bool,
+    /// Wall time of the hook in seconds.
+    pub(crate) duration: f64,
+    /// The hook output, invalid UTF-8 is replaced with `U+FFFD`.
+    pub(crate) output: String,
+    /// The raw hook output encoded as base64, only present if it's not valid UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) output_base64: Option<String>,
+    /// The files passed to the hook, relative to the workspace root, not part of reports.
+    #[serde(skip)]
+    pub(crate) files: Vec<PathBuf>,
+    /// The number of files passed to the hook, zero with `pass_filenames: false`.
+    #[serde(skip)]
+    pub(crate) files_passed: usize,
+    /// Files the hook matches that were not passed to it because of `max_file_size`,
+    /// `skip_binary_files` or `skip_lfs_files`, relative to the workspace root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) skipped_files: Vec<PathBuf>,
+}
+
+impl HookResult {
+    pub(crate) fn new(hook: &Hook, status: HookStatus) -> Self {
+        Self {
+            id: hook.id.clone(),
+            full_id: hook.full_id(),
+            name: hook.name.clone(),
+            project: hook.project().to_string(),
+            status,
+            exit_code: 0,
+            files_modified: false,
+            duration: 0.0,
+            output: String::new(),
+            output_base64: None,
+            files: Vec::new(),
+            files_passed: 0,
+            skipped_files: Vec::new(),
+        }
+    }
+
+    /// Record the hook output, keeping the raw bytes if they can't be decoded as UTF-8.
+    pub(crate) fn set_output(&mut self, output: &[u8]) {
+        if let Ok(output) = std::str::from_utf8(output) {
+            self.output = output.to_string();
+            self.output_base64 = None;
+        } else {
+            self.output = String::from_utf8_lossy(output).into_owned();
+            self.output_base64 = Some(BASE64_STANDARD.encode(output));
+        }
+    }
+
+    pub(crate) fn failed(&self) -> bool {
+        matches!(
+            self.status,
+            HookStatus::Failed | HookStatus::TimedOut | HookStatus::OverLimit
+        )
+    }
+}
+
+/// Summary of a whole `prek run`, sent to reporters once all hooks finished.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RunSummary {
+    pub(crate) success: bool,
+    pub(crate) passed: usize,
+    pub(crate) failed: usize,
+    #[serde(default)]
+    pub(crate) warnings: usize,
+    pub(crate) skipped: usize,
+}
+
+impl RunSummary {
+    pub(crate) fn from_results(results: &[HookResult]) -> Self {
+        let count = |status| results.iter().filter(|r| r.status == status).count();
+        let failed =
+            count(HookStatus::Failed) + count(HookStatus::TimedOut) + count(HookStatus::OverLimit);
+        Self {
+            success: failed == 0,
+            passed: count(HookStatus::Passed),
+            failed,
+            warnings: count(HookStatus::Warning),
+            skipped: count(HookStatus::Skipped),
+        }
+    }
+}
+
+/// A sink for hook results.
+///
+/// Reporters are invoked sequentially while hooks run, so implementations can
+/// stream results to a database or an internal API without buffering the whole run.
+pub(crate) trait OutputReporter {
+    fn on_hook_result(&mut self, result: &HookResult) -> Result<()>;
+
+    fn on_run_complete(&mut self, summary: &RunSummary) -> Result<()>;
+}
+
+/// A JSON report of a whole `prek run`, as written by `--output json:<path>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Report {
+    pub(crate) summary: RunSummary,
+    pub(crate) hooks: Vec<HookResult>,
+    /// Reports of the submodules run with `--include-submodules`, keyed by their path.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) submodules: BTreeMap<String, Report>,
+}
+
+impl Report {
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        let content = fs_err::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse report `{}`", path.display()))
+    }
+}
+
+/// Collects all hook results and writes them to a file once the run completes.
+struct JsonReporter {
+    path: PathBuf,
+    hooks: Vec<HookResult>,
+}
+
+impl OutputReporter for JsonReporter {
+    fn on_hook_result(&mut self, result: &HookResult) -> Result<()> {
+        self.hooks.push(result.clone());
+        Ok(())
+    }
+
+    fn on_run_complete(&mut self, summary: &RunSummary) -> Result<()> {
+        let report = Report {
+            summary: summary.clone(),
+            hooks: std::mem::take(&mut self.hooks),
+            submodules: BTreeMap::new(),
+        };
+        let content = serde_json::to_string_pretty(&report)?;
+        fs_err::write(&self.path, content)?;
+        Ok(())
+    }
+}
+
+/// The version of the porcelain format, bumped on any incompatible change.
+const PORCELAIN_VERSION: u32 = 1;
+
+/// Prints one tab-separated record per hook: status, full id, duration in seconds and the number
+/// of files passed to the hook.
+///
+/// Every run of the hooks starts with a `# prek porcelain v<version>` header line.
+struct PorcelainReporter;
+
+impl PorcelainReporter {
+    fn new() -> Result<Self> {
+        writeln!(Stdout::Enabled, "# prek porcelain v{PORCELAIN_VERSION}")?;
+        Ok(Self)
+    }
+}
+
+impl OutputReporter for PorcelainReporter {
+    fn on_hook_result(&mut self, result: &HookResult) -> Result<()> {
+        // Hooks of the root project are printed without the `.:` prefix.
+        let id = result.full_id.strip_prefix(".:").unwrap_or(&result.full_id);
+        writeln!(
+            Stdout::Enabled,
+            "{}\t{}\t{:.3}\t{}",
+            result.status.as_str(),
+            id,
+            result.duration,
+            result.files_passed
+        )?;
+        Ok(())
+    }
+
+    fn on_run_complete(&mut self, _summary: &RunSummary) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Create the reporter for the given output, if any.
+pub(crate) fn output_reporter(output: RunOutput) -> Result<Option<Box<dyn OutputReporter>>> {
+    match output {
+        RunOutput::Text => Ok(None),
+        RunOutput::Json(path) => Ok(Some(Box::new(JsonReporter {
+            // Hooks run from the workspace root, resolve the path against the original directory.
+            path: CWD.join(path),
+            hooks: Vec::new(),
+        }))),
+        RunOutput::Custom(name) => Ok(Some(Box::new(SubprocessReporter::spawn(&name)?))),
+        RunOutput::Porcelain => Ok(Some(Box::new(PorcelainReporter::new()?))),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    HookResult(&'a HookResult),
+    RunComplete(&'a RunSummary),
+}
+
+/// A reporter that streams events as JSON lines to the stdin of an external program.
+///
+/// Each line is a JSON object with an `event` field, either `hook_result` or `run_complete`.
+struct SubprocessReporter {
+    name: String,
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+impl SubprocessReporter {
+    fn spawn(name: &str) -> Result<Self> {
+        let program = format!("prek-reporter-{name}");
+        let path = which::which(&program)
+            .with_context(|| format!("Reporter `{name}` not found, `{program}` is not in PATH"))?;
+
+        debug!("Spawning reporter `{}`", path.display());
+        let mut child = Command::new(&path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("Failed to spawn reporter `{}`", path.display()))?;
+        let stdin = child.stdin.take();
+
+        Ok(Self {
+            name: name.to_string(),
+            child,
+            stdin,
+        })
+    }
+
+    fn send(&mut self, event: &Event) -> Result<()> {
+        let Some(stdin) = self.stdin.as_mut() else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        trace!("Sending event to reporter `{}`", self.name);
+        if let Err(err) = stdin.write_all(&line).and_then(|()| stdin.flush()) {
+            // The reporter went away, don't fail the run because of it.
+            warn_user!("Reporter `{}` stopped accepting events: {err}", self.name);
+            self.stdin = None;
+        }
+        Ok(())
+    }
+}
+
+impl OutputReporter for SubprocessReporter {
+    fn on_hook_result(&mut self, result: &HookResult) -> Result<()> {
+        self.send(&Event::HookResult(result))
+    }
+
+    fn on_run_complete(&mut self, summary: &RunSummary) -> Result<()> {
+        self.send(&Event::RunComplete(summary))?;
+        // Close stdin to signal the end of the stream.
+        drop(self.stdin.take());
+
+        let status = self.child.wait()?;
+        if !status.success() {
+            warn_user!("Reporter `{}` exited with {status}", self.name);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_run_output() {
+        assert_eq!(RunOutput::from_str("text"), Ok(RunOutput::Text));
+        assert_eq!(
+            RunOutput::from_str("custom:db"),
+            Ok(RunOutput::Custom("db".to_string()))
+        );
+        assert_eq!(
+            RunOutput::from_str("json:report.json"),
+            Ok(RunOutput::Json(PathBuf::from("report.json")))
+        );
+        assert!(RunOutput::from_str("json:").is_err());
+        assert!(RunOutput::from_str("custom:").is_err());
+        assert!(RunOutput::from_str("xml").is_err());
+    }
+
+    #[test]
+    fn non_utf8_output() -> Result<()> {
+        let mut result = HookResult {
+            id: "latin1".to_string(),
+            full_id: ".:latin1".to_string(),
+            name: "latin1".to_string(),
+            project: ".".to_string(),
+            status: HookStatus::Failed,
+            exit_code: 1,
+            files_modified: false,
+            duration: 0.0,
+            output: String::new(),
+            output_base64: None,
+            files: Vec::new(),
+            files_passed: 0,
+            skipped_files: Vec::new(),
+        };
+
+        result.set_output(b"caf\xe9");
+        assert_eq!(result.output, "caf\u{fffd}");
+        assert_eq!(result.output_base64.as_deref(), Some("Y2Fm6Q=="));
+        let json = serde_json::to_value(&result)?;
+        assert_eq!(json["output_base64"], "Y2Fm6Q==");
+
+        result.set_output("café".as_bytes());
+        assert_eq!(result.output, "café");
+        assert_eq!(result.output_base64, None);
+        let json = serde_json::to_value(&result)?;
+        assert!(json.get("output_base64").is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use owo_colors::OwoColorize;
+
+use crate::cli::run::scratch::ScratchTree;
+use crate::hook::InstalledHook;
+use crate::printer::Printer;
+use crate::run::USE_COLOR;
+use crate::store::Store;
+
+/// Previews the changes of hooks under `prek run --dry-run --diff`.
+///
+/// Hooks run in a scratch worktree of the staged content, the working tree and the index are
+/// never touched.
+pub(crate) struct DiffPreview {
+    worktree: ScratchTree,
+}
+
+impl DiffPreview {
+    /// `files` are the files passed to hooks, relative to the git root.
+    pub(crate) async fn new(store: &Store, git_root: &Path, files: &[PathBuf]) -> Result<Self> {
+        Ok(Self {
+            worktree: ScratchTree::new(store, git_root, files).await?,
+        })
+    }
+
+    /// Run `hook` against the copy, its result is ignored, only the changes matter.
+    pub(crate) async fn run_hook(
+        &self,
+        hook: &InstalledHook,
+        filenames: &[&Path],
+        store: &Store,
+    ) -> Result<()> {
+        let hook = self.worktree.hook(hook);
+        hook.language
+            .run(&hook, filenames, store)
+            .await
+            .context(format!(
+                "Failed to run hook `{hook}` to preview its changes"
+            ))?;
+        Ok(())
+    }
+
+    /// Print the diff of the changes hooks made to the copy.
+    pub(crate) async fn print(&self, printer: Printer) -> Result<()> {
+        let color = if *USE_COLOR {
+            "--color=always"
+        } else {
+            "--color=never"
+        };
+        self.worktree.add_created_files().await?;
+        let output = self
+            .worktree
+            .git_cmd("git diff")?
+            .arg("diff")
+            .arg("--no-ext-diff")
+            .arg("--no-textconv")
+            .arg("--ignore-submodules")
+            .arg(color)
+            .check(true)
+            .output()
+            .await?;
+
+        if output.stdout.is_empty() {
+            writeln!(
+                printer.stdout(),
+                "\n{}",
+                "Hooks would not modify any files".bold()
+            )?;
+        } else {
+            writeln!(printer.stdout(), "\n{}", "Changes hooks would make:".bold())?;
+            write!(
+                printer.stdout(),
+                "{}",
+                String::from_utf8_lossy(&output.stdout)
+            )?;
+        }
+
+        Ok(())
+    }
+}
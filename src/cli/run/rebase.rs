@@ -0,0 +1,94 @@
+use std::hash::Hash;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tracing::debug;
+
+use crate::cache_key::{self, CacheKey};
+use crate::config::Stage;
+use crate::git;
+use crate::hook::Hook;
+use crate::store::{CacheBucket, Store};
+use crate::version;
+use crate::workspace::Workspace;
+
+/// Entries not used for this long are removed, a rebase rarely spans more than a few days.
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Remembers commits that already passed under `prek run --rebase-exec`.
+///
+/// `git rebase -x` replays every commit, most of them unchanged. A commit is identified by its
+/// tree and its parent's tree (which together determine the files that are checked), so a
+/// replayed commit that already passed with the same configs and hooks can exit immediately.
+pub(crate) struct RebaseExecCache {
+    path: PathBuf,
+}
+
+impl RebaseExecCache {
+    pub(crate) async fn new(
+        store: &Store,
+        workspace: &Workspace,
+        hooks: &[Arc<Hook>],
+        includes: &[String],
+        skips: &[String],
+        projects: &[String],
+        hook_stage: Stage,
+    ) -> Result<Option<Self>> {
+        let Some(tree) = git::get_tree("HEAD").await? else {
+            return Ok(None);
+        };
+        let parent_tree = git::get_tree("HEAD~1").await?;
+
+        let mut key = CacheKey::new();
+        version::version().hash(&mut key);
+        workspace.root().hash(&mut key);
+        tree.hash(&mut key);
+        parent_tree.hash(&mut key);
+        includes.hash(&mut key);
+        skips.hash(&mut key);
+        projects.hash(&mut key);
+        hook_stage.as_str().hash(&mut key);
+        // The config may be passed with `--config` or live outside of the tree.
+        for project in workspace.projects() {
+            project.config_file().hash(&mut key);
+            fs_err::read(project.config_file()).ok().hash(&mut key);
+        }
+        let mut digests = hooks
+            .iter()
+            .map(|hook| {
+                let mut key = CacheKey::new();
+                format!("{hook:#}").hash(&mut key);
+                hook.project().config_file().hash(&mut key);
+                hook.entry.raw().hash(&mut key);
+                hook.args.hash(&mut key);
+                hook.env.hash(&mut key);
+                hook.language.hash(&mut key);
+                key.hex()
+            })
+            .collect::<Vec<_>>();
+        digests.sort_unstable();
+        digests.hash(&mut key);
+
+        Ok(Some(Self {
+            path: store
+                .cache_path(CacheBucket::Prek)
+                .join("rebase-exec")
+                .join(key.hex()),
+        }))
+    }
+
+    pub(crate) fn is_passed(&self) -> bool {
+        self.path.exists()
+    }
+
+    pub(crate) fn mark_passed(&self) {
+        let dir = self.path.parent().expect("has parent");
+        cache_key::prune(dir, MAX_AGE);
+        let result = fs_err::create_dir_all(dir).and_then(|()| fs_err::write(&self.path, b""));
+        if let Err(err) = result {
+            debug!("Failed to save rebase exec cache: {err}");
+        }
+    }
+}
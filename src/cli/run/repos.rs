@@ -0,0 +1,425 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use tracing::debug;
+
+use constants::{ALT_CONFIG_FILE, CONFIG_FILE};
+
+use crate::cli::ExitStatus;
+use crate::cli::run::output::RunSummary;
+use crate::cli::run::{FileLimits, Report, RunOutput};
+use crate::config::Stage;
+use crate::fs::{CWD, Simplified};
+use crate::git::{self, GIT_ROOT};
+use crate::printer::Printer;
+use crate::process::Cmd;
+use crate::run::{CONCURRENCY, USE_COLOR};
+use crate::store::STORE;
+
+/// A cross-repo report, as written by `prek run --workspace <list-file> --output json:<path>`.
+#[derive(Debug, Serialize)]
+struct ReposReport {
+    repos: BTreeMap<String, Option<Report>>,
+}
+
+/// Read the repository paths from a list file, one path per line.
+///
+/// Blank lines and lines starting with `#` are ignored, relative paths are resolved against
+/// the directory of the list file.
+fn read_list_file(list_file: &Path) -> Result<Vec<PathBuf>> {
+    let content = fs_err::read_to_string(list_file)?;
+    let base = list_file.parent().unwrap_or(Path::new("."));
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base.join(line))
+        .collect())
+}
+
+/// Pass the file limits set on the command line to a nested `prek run`.
+fn file_limit_args(cmd: &mut Cmd, limits: FileLimits) {
+    if let Some(max_file_size) = limits.max_file_size {
+        cmd.arg("--max-file-size").arg(max_file_size.to_string());
+    }
+    if limits.skip_binary_files {
+        cmd.arg("--skip-binary-files");
+    }
+    if limits.skip_lfs_files {
+        cmd.arg("--skip-lfs-files");
+    }
+    if limits.ignore_baseline {
+        cmd.arg("--no-baseline");
+    }
+}
+
+/// The options of `prek run --include-submodules` passed on to the runs in submodules.
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct SubmoduleOptions {
+    pub(crate) includes: Vec<String>,
+    pub(crate) skips: Vec<String>,
+    pub(crate) projects: Vec<String>,
+    pub(crate) groups: Vec<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) hook_stage: Stage,
+    /// Refs of the current repository, each submodule runs on the commits they record for it.
+    pub(crate) from_ref: Option<String>,
+    pub(crate) to_ref: Option<String>,
+    pub(crate) all_files: bool,
+    /// Files and directories relative to the current directory, each submodule runs on the ones
+    /// inside it.
+    pub(crate) files: Vec<PathBuf>,
+    pub(crate) directories: Vec<String>,
+    pub(crate) file_limits: FileLimits,
+    pub(crate) dry_run: bool,
+    pub(crate) strict: bool,
+    pub(crate) output: RunOutput,
+    pub(crate) verbose: bool,
+}
+
+/// Run hooks in the initialized submodules of the current repository that have a config.
+///
+/// Submodules are run one after another with their own config, and they recurse into their
+/// own submodules. With `--output json:<path>`, their reports are added to the report of the
+/// current repository.
+pub(crate) async fn run_submodules(
+    options: SubmoduleOptions,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let git_root = GIT_ROOT.as_ref()?;
+    let current_exe = std::env::current_exe()?;
+    let reports_dir = if matches!(options.output, RunOutput::Json(_)) {
+        let scratch = STORE.as_ref()?.scratch_path();
+        fs_err::create_dir_all(&scratch)?;
+        Some(tempfile::tempdir_in(scratch)?)
+    } else {
+        None
+    };
+    let has_files = !options.files.is_empty() || !options.directories.is_empty();
+
+    let mut success = true;
+    let mut reports = BTreeMap::new();
+    for (idx, path) in git::get_submodule_paths(git_root)
+        .await?
+        .into_iter()
+        .enumerate()
+    {
+        let submodule = git_root.join(&path);
+        if !submodule.join(".git").exists() {
+            debug!("Skipping uninitialized submodule `{}`", path.display());
+            continue;
+        }
+        if ![CONFIG_FILE, ALT_CONFIG_FILE]
+            .iter()
+            .any(|file| submodule.join(file).is_file())
+        {
+            debug!("Skipping submodule `{}` without a config", path.display());
+            continue;
+        }
+
+        let mut cmd = Cmd::new(&current_exe, "run prek");
+        cmd.arg("run")
+            .arg("--cd")
+            .arg(&submodule)
+            .arg("--include-submodules")
+            .arg("--hook-stage")
+            .arg(options.hook_stage.as_str())
+            .arg(if *USE_COLOR {
+                "--color=always"
+            } else {
+                "--color=never"
+            })
+            .remove_git_env()
+            .check(false);
+        // Before `--files`, which takes all the values that follow it.
+        cmd.args(&options.includes);
+
+        if has_files {
+            let files: Vec<_> = options
+                .files
+                .iter()
+                .map(|file| CWD.join(file))
+                .filter(|file| file.starts_with(&submodule))
+                .collect();
+            let directories: Vec<_> = options
+                .directories
+                .iter()
+                .map(|directory| CWD.join(directory))
+                .filter(|directory| directory.starts_with(&submodule))
+                .collect();
+            if files.is_empty() && directories.is_empty() {
+                debug!("Skipping submodule `{}` without files", path.display());
+                continue;
+            }
+            for file in files {
+                cmd.arg("--files").arg(file);
+            }
+            for directory in directories {
+                cmd.arg("--directory").arg(directory);
+            }
+        }
+        if let (Some(from_ref), Some(to_ref)) = (&options.from_ref, &options.to_ref) {
+            let Some(to) = git::get_submodule_commit(git_root, to_ref, &path).await? else {
+                debug!("Skipping submodule `{}` not in `{to_ref}`", path.display());
+                continue;
+            };
+            // A submodule added since `from_ref` is new as a whole.
+            match git::get_submodule_commit(git_root, from_ref, &path).await? {
+                Some(from) => {
+                    cmd.arg("--from-ref").arg(from).arg("--to-ref").arg(to);
+                }
+                None => {
+                    cmd.arg("--all-files");
+                }
+            }
+        }
+        if options.all_files {
+            cmd.arg("--all-files");
+        }
+
+        let report = reports_dir
+            .as_ref()
+            .map(|dir| dir.path().join(format!("{idx}.json")));
+        match (&options.output, &report) {
+            (RunOutput::Json(_), Some(report)) => {
+                cmd.arg("--output")
+                    .arg(format!("json:{}", report.display()));
+            }
+            (RunOutput::Custom(name), _) => {
+                cmd.arg("--output").arg(format!("custom:{name}"));
+            }
+            (RunOutput::Porcelain, _) => {
+                cmd.arg("--porcelain");
+            }
+            _ => {}
+        }
+        file_limit_args(&mut cmd, options.file_limits);
+        if options.dry_run {
+            cmd.arg("--dry-run");
+        }
+        if options.strict {
+            cmd.arg("--strict");
+        }
+        if options.verbose {
+            cmd.arg("--verbose");
+        }
+        for skip in &options.skips {
+            cmd.arg("--skip").arg(skip);
+        }
+        for project in &options.projects {
+            cmd.arg("--project").arg(project);
+        }
+        for group in &options.groups {
+            cmd.arg("--group").arg(group);
+        }
+        for tag in &options.tags {
+            cmd.arg("--tag").arg(tag);
+        }
+
+        writeln!(
+            printer.stdout(),
+            "\n{}",
+            format!("Running hooks in submodule `{}`:", path.display().cyan()).bold()
+        )?;
+        success &= cmd.status().await?.success();
+
+        if let Some(report) = report.and_then(|report| Report::read(&report).ok()) {
+            reports.insert(path.to_string_lossy().replace('\\', "/"), report);
+        }
+    }
+
+    if let RunOutput::Json(path) = &options.output
+        && !reports.is_empty()
+    {
+        // There is no report of the current repository if it had nothing to run.
+        let path = CWD.join(path);
+        let mut report = Report::read(&path).unwrap_or_else(|_| Report {
+            summary: RunSummary::from_results(&[]),
+            hooks: Vec::new(),
+            submodules: BTreeMap::new(),
+        });
+        report.submodules = reports;
+        fs_err::write(&path, serde_json::to_string_pretty(&report)?)?;
+    }
+
+    if success {
+        Ok(ExitStatus::Success)
+    } else {
+        Ok(ExitStatus::Failure)
+    }
+}
+
+/// Run hooks in every repository listed in `list_file`, with bounded parallelism.
+#[allow(clippy::fn_params_excessive_bools)]
+pub(crate) async fn run_repos(
+    list_file: &Path,
+    config: Option<PathBuf>,
+    includes: Vec<String>,
+    skips: Vec<String>,
+    projects: Vec<String>,
+    groups: Vec<String>,
+    tags: Vec<String>,
+    hook_stage: Stage,
+    all_files: bool,
+    file_limits: FileLimits,
+    dry_run: bool,
+    strict: bool,
+    output: RunOutput,
+    verbose: bool,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let list_file = CWD.join(list_file);
+    let repos = read_list_file(&list_file)
+        .with_context(|| format!("Failed to read `{}`", list_file.simplified_display()))?;
+    if repos.is_empty() {
+        anyhow::bail!(
+            "No repositories found in `{}`",
+            list_file.simplified_display()
+        );
+    }
+
+    let config = config.map(|config| CWD.join(config));
+    let scratch = STORE.as_ref()?.scratch_path();
+    fs_err::create_dir_all(&scratch)?;
+    let reports_dir = tempfile::tempdir_in(scratch)?;
+    let current_exe = std::env::current_exe()?;
+
+    let mut tasks = futures::stream::iter(repos.iter().enumerate())
+        .map(|(idx, repo)| {
+            let report = reports_dir.path().join(format!("{idx}.json"));
+            // Custom reporters receive the results of each repository, there is no
+            // cross-repo report then.
+            let report_output = match &output {
+                RunOutput::Custom(name) => format!("custom:{name}"),
+                _ => format!("json:{}", report.display()),
+            };
+            let mut cmd = Cmd::new(&current_exe, "run prek");
+            cmd.arg("run")
+                .arg("--cd")
+                .arg(repo)
+                .arg("--hook-stage")
+                .arg(hook_stage.as_str())
+                .arg("--output")
+                .arg(report_output)
+                .arg(if *USE_COLOR {
+                    "--color=always"
+                } else {
+                    "--color=never"
+                })
+                .remove_git_env()
+                .check(false);
+            if let Some(config) = &config {
+                cmd.arg("--config").arg(config);
+            }
+            if all_files {
+                cmd.arg("--all-files");
+            }
+            file_limit_args(&mut cmd, file_limits);
+            if dry_run {
+                cmd.arg("--dry-run");
+            }
+            if strict {
+                cmd.arg("--strict");
+            }
+            if verbose {
+                cmd.arg("--verbose");
+            }
+            for skip in &skips {
+                cmd.arg("--skip").arg(skip);
+            }
+            for project in &projects {
+                cmd.arg("--project").arg(project);
+            }
+            for group in &groups {
+                cmd.arg("--group").arg(group);
+            }
+            for tag in &tags {
+                cmd.arg("--tag").arg(tag);
+            }
+            cmd.args(&includes);
+
+            async move {
+                debug!("Running hooks in `{}`", repo.display());
+                let result = cmd.output().await.map(|output| {
+                    let report = Report::read(&report).ok();
+                    (output, report)
+                });
+                (repo, result)
+            }
+        })
+        .buffered(*CONCURRENCY);
+
+    let mut success = true;
+    let mut reports = BTreeMap::new();
+    let mut summary = Vec::with_capacity(repos.len());
+    while let Some((repo, result)) = tasks.next().await {
+        writeln!(
+            printer.stdout(),
+            "{}",
+            format!("Running hooks in `{}`:", repo.user_display().cyan()).bold()
+        )?;
+
+        // A repository that fails to run doesn't stop the others.
+        let (output, report) = match result {
+            Ok(result) => result,
+            Err(err) => {
+                writeln!(
+                    printer.stderr(),
+                    "{}: {:#}",
+                    "error".red().bold(),
+                    anyhow::Error::from(err)
+                )?;
+                success = false;
+                summary.push((repo, false));
+                reports.insert(repo.user_display().to_string(), None);
+                continue;
+            }
+        };
+        let passed = output.status.success();
+        success &= passed;
+
+        write!(
+            printer.stdout(),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        )?;
+        write!(
+            printer.stderr(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        )?;
+
+        summary.push((repo, passed));
+        reports.insert(repo.user_display().to_string(), report);
+    }
+
+    writeln!(printer.stdout_important(), "{}", "Summary:".bold())?;
+    for (repo, passed) in summary {
+        writeln!(
+            printer.stdout_important(),
+            "  {} {}",
+            if passed {
+                "Passed".green().to_string()
+            } else {
+                "Failed".red().to_string()
+            },
+            repo.user_display()
+        )?;
+    }
+
+    if let RunOutput::Json(path) = &output {
+        let content = serde_json::to_string_pretty(&ReposReport { repos: reports })?;
+        fs_err::write(CWD.join(path), content)?;
+    }
+
+    if success {
+        Ok(ExitStatus::Success)
+    } else {
+        Ok(ExitStatus::Failure)
+    }
+}
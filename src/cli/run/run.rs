@@ -1,60 +1,157 @@
+use std::collections::BTreeMap;
 use std::fmt::Write as _;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
+use fancy_regex::Regex;
 use futures::stream::{FuturesUnordered, StreamExt};
+use itertools::Itertools;
 use owo_colors::{OwoColorize, Style};
 use rand::SeedableRng;
 use rand::prelude::{SliceRandom, StdRng};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use tokio::io::AsyncWriteExt;
 use tokio::sync::Semaphore;
-use tracing::{debug, trace};
+use tokio::time::Instant;
+use tracing::{Instrument, Span, debug, field, instrument, trace, trace_span, warn};
 use unicode_width::UnicodeWidthStr;
 
 use constants::env_vars::EnvVars;
 
+use crate::baseline::Baseline;
+use crate::checksum::Verify;
+use crate::ci::CI;
 use crate::cli::reporter::{HookInitReporter, HookInstallReporter};
+use crate::cli::run::cache::ResultCache;
+use crate::cli::run::history::History;
+use crate::cli::run::incremental::IncrementalRun;
+use crate::cli::run::interactive::select_hooks;
+use crate::cli::run::isolate::IsolatedTree;
 use crate::cli::run::keeper::WorkTreeKeeper;
-use crate::cli::run::{CollectOptions, FileFilter, Selectors, collect_files};
+use crate::cli::run::last_run::LastRun;
+use crate::cli::run::output::{HookResult, HookStatus, RunOutput, RunSummary, output_reporter};
+use crate::cli::run::preview::DiffPreview;
+use crate::cli::run::rebase::RebaseExecCache;
+use crate::cli::run::{CollectOptions, FileFilter, FileLimits, Selectors, collect_files};
 use crate::cli::{ExitStatus, RunExtraArgs};
-use crate::config::{Language, Stage};
+use crate::config::{Language, PassFileContents, Severity, Stage, StashMode, is_full_sha};
 use crate::fs::CWD;
-use crate::git;
-use crate::git::GIT_ROOT;
-use crate::hook::{Hook, InstalledHook};
+use crate::git::{ChangedLines, GIT_ROOT};
+use crate::hook::{Hook, InstallInfo, InstalledHook, Repo};
+use crate::limits::Exceeded;
+use crate::policy::policy;
 use crate::printer::{Printer, Stdout};
+use crate::process::{self, HOOK_OUTPUT, HookOutput};
 use crate::run::{CONCURRENCY, USE_COLOR};
+use crate::sandbox::Sandbox;
+use crate::settings::settings;
 use crate::store::{STORE, Store};
 use crate::workspace::{Project, Workspace};
+use crate::{builtin, fs, git, hook_env, warn_user};
+
+/// How long hooks that run in-process may run past their deadline before they are stopped.
+const DEADLINE_GRACE: Duration = Duration::from_secs(5);
+
+/// The options of `prek run`, the defaults run the hooks of the `pre-commit` stage on the staged
+/// files.
+#[derive(Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct RunOptions {
+    pub(crate) config: Option<PathBuf>,
+    pub(crate) includes: Vec<String>,
+    pub(crate) skips: Vec<String>,
+    pub(crate) projects: Vec<String>,
+    pub(crate) groups: Vec<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) hook_stage: Stage,
+    pub(crate) from_ref: Option<String>,
+    pub(crate) to_ref: Option<String>,
+    pub(crate) all_files: bool,
+    pub(crate) files: Vec<PathBuf>,
+    pub(crate) directories: Vec<String>,
+    pub(crate) only_files_matching: Option<Regex>,
+    pub(crate) exclude_files_matching: Option<Regex>,
+    pub(crate) file_limits: FileLimits,
+    pub(crate) fail_on_sparse: bool,
+    pub(crate) last_commit: bool,
+    pub(crate) rebase_exec: bool,
+    pub(crate) incremental: bool,
+    pub(crate) failed: bool,
+    pub(crate) interactive: bool,
+    pub(crate) show_diff_on_failure: bool,
+    pub(crate) stash_mode: Option<StashMode>,
+    pub(crate) dry_run: bool,
+    pub(crate) preview_diff: bool,
+    pub(crate) fix_and_verify: bool,
+    pub(crate) fix_and_retry: bool,
+    pub(crate) strict: bool,
+    pub(crate) require_pinned_revs: bool,
+    pub(crate) refresh: bool,
+    pub(crate) extra_args: RunExtraArgs,
+    pub(crate) output: RunOutput,
+    pub(crate) stream: bool,
+    pub(crate) pty: bool,
+    pub(crate) sandbox: bool,
+    pub(crate) durations: Option<usize>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) verbose: bool,
+}
 
-#[allow(clippy::too_many_arguments, clippy::fn_params_excessive_bools)]
-pub(crate) async fn run(
-    config: Option<PathBuf>,
-    includes: Vec<String>,
-    skips: Vec<String>,
-    hook_stage: Stage,
-    from_ref: Option<String>,
-    to_ref: Option<String>,
-    all_files: bool,
-    files: Vec<String>,
-    directories: Vec<String>,
-    last_commit: bool,
-    show_diff_on_failure: bool,
-    dry_run: bool,
-    refresh: bool,
-    extra_args: RunExtraArgs,
-    verbose: bool,
-    printer: Printer,
-) -> Result<ExitStatus> {
-    // Convert `--last-commit` to `HEAD~1..HEAD`
-    let (from_ref, to_ref) = if last_commit {
-        (Some("HEAD~1".to_string()), Some("HEAD".to_string()))
+#[instrument(level = "trace", skip_all, fields(stage = %options.hook_stage))]
+pub(crate) async fn run(options: RunOptions, printer: Printer) -> Result<ExitStatus> {
+    let RunOptions {
+        config,
+        includes,
+        skips,
+        projects,
+        groups,
+        tags,
+        hook_stage,
+        from_ref,
+        to_ref,
+        all_files,
+        files,
+        directories,
+        only_files_matching,
+        exclude_files_matching,
+        file_limits,
+        fail_on_sparse,
+        last_commit,
+        rebase_exec,
+        incremental,
+        failed,
+        interactive,
+        show_diff_on_failure,
+        stash_mode,
+        dry_run,
+        preview_diff,
+        fix_and_verify,
+        fix_and_retry,
+        strict,
+        require_pinned_revs,
+        refresh,
+        extra_args,
+        output,
+        stream,
+        pty,
+        sandbox,
+        durations,
+        timeout,
+        verbose,
+    } = options;
+    // `--timeout` bounds the whole run, including installing hook environments.
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    // With `--quiet`, passed hooks are not printed, a summary is printed once all hooks ran.
+    let print_summary = printer == Printer::Quiet;
+    // Only report failures when replaying commits under `git rebase -x`.
+    let printer = if rebase_exec && printer != Printer::Silent {
+        Printer::Quiet
     } else {
-        (from_ref, to_ref)
+        printer
     };
 
     // Prevent recursive post-checkout hooks.
@@ -67,76 +164,322 @@ pub(crate) async fn run(
     // Ensure we are in a git repository.
     LazyLock::force(&GIT_ROOT).as_ref()?;
 
-    let should_stash = !all_files && files.is_empty() && directories.is_empty();
+    // Convert `--last-commit` to `HEAD~1..HEAD`, under `git rebase -x` this is the replayed commit.
+    // The root commit has no parent, all files in the repo were added by it.
+    let (from_ref, to_ref, all_files) = if last_commit || rebase_exec {
+        if git::get_parent_commit("HEAD").await?.is_some() {
+            (Some("HEAD~1".to_string()), Some("HEAD".to_string()), false)
+        } else {
+            (None, None, true)
+        }
+    } else {
+        (from_ref, to_ref, all_files || incremental)
+    };
+
+    let workspace_root = Workspace::find_root(config.as_deref(), &CWD)?;
+    let store = STORE.as_ref()?;
+
+    // `--failed` runs the hooks that failed last time on the files they failed on.
+    let mut last_run = LastRun::load(store, &workspace_root);
+    let files = if failed {
+        if last_run.is_empty() {
+            writeln!(printer.stdout(), "No hooks failed in the last run")?;
+            return Ok(ExitStatus::Success);
+        }
+        last_run
+            .files()
+            .map(|file| workspace_root.join(file))
+            .collect()
+    } else {
+        files
+    };
+
+    let should_stash = !rebase_exec && !all_files && files.is_empty() && directories.is_empty();
 
     // Check if we have unresolved merge conflict files and fail fast.
-    if should_stash && git::has_unmerged_paths().await? {
-        anyhow::bail!("You have unmerged paths. Resolve them before running prek");
+    if should_stash {
+        let unmerged = git::get_unmerged_paths().await?;
+        if !unmerged.is_empty() {
+            anyhow::bail!(
+                "You have unmerged paths. Resolve them before running prek:\n{}",
+                format_paths(&unmerged)
+            );
+        }
     }
 
-    let workspace_root = Workspace::find_root(config.as_deref(), &CWD)?;
-    let selectors = Selectors::load(&includes, &skips, &workspace_root)?;
+    // Resolved files of a merge with conflict markers left in are almost always a mistake.
+    if should_stash && git::is_in_merge_conflict().await? {
+        let git_root = GIT_ROOT.as_ref()?;
+        let with_markers = git::get_conflicted_files(&workspace_root)
+            .await?
+            .into_iter()
+            .filter(|path| has_conflict_markers(&git_root.join(path)))
+            .collect::<Vec<_>>();
+        if !with_markers.is_empty() {
+            anyhow::bail!(
+                "Conflict markers found in merged files. Resolve them before running prek:\n{}",
+                format_paths(&with_markers)
+            );
+        }
+    }
+    let show_diff_on_failure =
+        show_diff_on_failure || settings().show_diff_on_failure || CI.is_some();
+
+    // Fixers can't modify files in a read-only checkout, don't let them fail with IO errors.
+    let read_only = !dry_run && fs::is_read_only(&workspace_root);
+    if read_only {
+        warn_user!(
+            "The working tree is read-only, hooks that modify files will be run in dry-run mode"
+        );
+    }
+    let should_stash = should_stash && !read_only;
+
+    let selectors = Selectors::load(&includes, &skips, &workspace_root)?
+        .with_projects(&projects, &workspace_root)?
+        .with_groups(&groups)
+        .with_tags(&tags);
     let mut workspace = Workspace::discover(workspace_root, config, Some(&selectors), refresh)?;
 
+    let stash_mode = stash_mode
+        .or_else(|| {
+            workspace
+                .projects()
+                .iter()
+                .find(|project| project.is_root())
+                .and_then(|project| project.config().stash_mode)
+        })
+        .unwrap_or_default();
+    // Hooks run on a copy of the staged content, the working tree is not stashed.
+    let isolate = should_stash && stash_mode == StashMode::Isolate && !dry_run;
+    let should_stash = should_stash && !matches!(stash_mode, StashMode::Keep | StashMode::Isolate);
+    if should_stash
+        && stash_mode == StashMode::Refuse
+        && git::has_unstaged_changes(workspace.root()).await?
+    {
+        anyhow::bail!(
+            "You have changes that are not staged, stage or stash them before running prek (`stash_mode: refuse`)"
+        );
+    }
+
+    // Fixes can only be staged when hooks run on the staged files, other changes are stashed.
+    let auto_stage_fixes = should_stash
+        && (fix_and_retry
+            || workspace
+                .projects()
+                .iter()
+                .find(|project| project.is_root())
+                .and_then(|project| project.config().auto_stage_fixes)
+                .unwrap_or(false));
+
     if should_stash {
         workspace.check_configs_staged().await?;
     }
 
-    let store = STORE.as_ref()?;
+    let configs = workspace
+        .projects()
+        .iter()
+        .map(|project| project.config_file())
+        .collect::<Vec<_>>();
+    if let Err(err) = store.track_configs(&configs).await {
+        warn!(?err, "Failed to track configs");
+    }
+
     let reporter = HookInitReporter::from(printer);
     let lock = store.lock_async().await?;
 
-    let hooks = workspace.init_hooks(store, Some(&reporter)).await?;
-    let filtered_hooks: Vec<_> = hooks
+    let hooks = workspace
+        .init_hooks(store, Some(&reporter))
+        .await?
         .into_iter()
-        .filter(|h| selectors.matches_hook(h))
         .map(Arc::new)
-        .collect();
+        .collect::<Vec<_>>();
+    let (filtered_hooks, skipped_hooks): (Vec<_>, Vec<_>) = hooks
+        .iter()
+        .filter(|h| !failed || last_run.failed(&h.full_id()))
+        .cloned()
+        .partition(|h| selectors.matches_hook(h) && !skipped_by_ci(h));
+    let skipped_hooks = skipped_hooks
+        .into_iter()
+        .filter(|h| {
+            (selectors.skipped_by_env(h) || (skipped_by_ci(h) && selectors.matches_hook(h)))
+                && h.stages.contains(hook_stage)
+        })
+        .collect::<Vec<_>>();
 
-    selectors.report_unused();
+    selectors.report_unused(hooks.iter().map(|h| &**h));
 
-    if filtered_hooks.is_empty() {
+    if filtered_hooks.is_empty() && skipped_hooks.is_empty() {
         writeln!(
             printer.stderr(),
             "{}: No hooks found after filtering with the given selectors",
             "error".red().bold(),
         )?;
+        if !hooks.is_empty() {
+            writeln!(
+                printer.stderr(),
+                "Available hooks: {}",
+                hooks
+                    .iter()
+                    .map(|h| h.id.as_str())
+                    .unique()
+                    .map(|id| id.cyan().to_string())
+                    .join(", ")
+            )?;
+        }
         return Ok(ExitStatus::Failure);
     }
 
-    let filtered_hooks = filtered_hooks
+    let (filtered_hooks, other_stage_hooks): (Vec<_>, Vec<_>) = filtered_hooks
         .into_iter()
-        .filter(|h| h.stages.contains(hook_stage))
-        .collect::<Vec<_>>();
+        .partition(|h| h.stages.contains(hook_stage));
 
-    if filtered_hooks.is_empty() {
+    if filtered_hooks.is_empty() && skipped_hooks.is_empty() {
         writeln!(
             printer.stderr(),
             "{}: No hooks found for stage `{}` after filtering",
             "error".red().bold(),
             hook_stage.cyan()
         )?;
+        // Manual hooks are never run by the git shims, point users to the explicit invocation.
+        if hook_stage != Stage::Manual
+            && other_stage_hooks
+                .iter()
+                .any(|h| h.stages.contains(Stage::Manual))
+        {
+            writeln!(
+                printer.stderr(),
+                "Hooks in the `manual` stage only run with `{}`",
+                "--hook-stage manual".cyan()
+            )?;
+        }
         return Ok(ExitStatus::Failure);
     }
 
+    let rebase_cache = if rebase_exec && !dry_run {
+        RebaseExecCache::new(
+            store,
+            &workspace,
+            &filtered_hooks,
+            &includes,
+            &skips,
+            &projects,
+            hook_stage,
+        )
+        .await?
+    } else {
+        None
+    };
+    if !refresh
+        && rebase_cache
+            .as_ref()
+            .is_some_and(RebaseExecCache::is_passed)
+    {
+        debug!("Commit already passed with the same hooks, skipping");
+        return Ok(ExitStatus::Success);
+    }
+
+    let filtered_hooks = if interactive {
+        let selected = select_hooks(filtered_hooks, &last_run)?;
+        if selected.is_empty() {
+            writeln!(printer.stdout(), "No hooks selected")?;
+            return Ok(ExitStatus::Success);
+        }
+        selected
+    } else {
+        filtered_hooks
+    };
+
+    let require_pinned_revs = require_pinned_revs
+        || workspace
+            .projects()
+            .iter()
+            .find(|project| project.is_root())
+            .and_then(|project| project.config().require_pinned_revs)
+            .unwrap_or(false);
+    if require_pinned_revs {
+        check_pinned_revs(&filtered_hooks)?;
+    }
+
     debug!(
         "Hooks going to run: {:?}",
         filtered_hooks.iter().map(|h| &h.id).collect::<Vec<_>>()
     );
+    let branch = git::get_current_branch().await?;
+    let (filtered_hooks, conditionally_skipped_hooks): (Vec<_>, Vec<_>) = filtered_hooks
+        .into_iter()
+        .partition(|h| condition_skip_reason(h, branch.as_deref()).is_none());
+
     let reporter = HookInstallReporter::from(printer);
-    let installed_hooks = install_hooks(filtered_hooks, store, &reporter).await?;
+    let install = install_hooks(filtered_hooks, store, &reporter);
+    let mut installed_hooks = match deadline {
+        Some(deadline) => {
+            let Ok(installed_hooks) = tokio::time::timeout_at(deadline, install).await else {
+                warn_user!("The run timed out while installing hook environments");
+                return Ok(ExitStatus::TimedOut);
+            };
+            installed_hooks?
+        }
+        None => install.await?,
+    };
+    // Skipped hooks are only reported, they don't need to be installed.
+    installed_hooks.extend(
+        skipped_hooks
+            .into_iter()
+            .chain(conditionally_skipped_hooks)
+            .map(InstalledHook::NoNeedInstall),
+    );
 
     // Release the store lock.
     drop(lock);
 
+    let unstaged_files = if auto_stage_fixes {
+        git::files_not_staged(&[workspace.root()]).await?
+    } else {
+        Vec::new()
+    };
+
     // Clear any unstaged changes from the git working directory.
     let mut _guard = None;
     if should_stash {
         _guard = Some(WorkTreeKeeper::clean(store, workspace.root()).await?);
     }
 
+    let incremental = if incremental && !dry_run {
+        Some(
+            IncrementalRun::new(
+                store,
+                &workspace,
+                &installed_hooks,
+                &includes,
+                &skips,
+                &projects,
+                hook_stage,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+    let since_tree = match &incremental {
+        Some(incremental) => incremental.last_tree().await?,
+        None => None,
+    };
+
     set_env_vars(from_ref.as_ref(), to_ref.as_ref(), &extra_args);
 
+    // The changed lines are only known for staged files and files changed between refs.
+    let changed_lines = if installed_hooks.iter().any(|hook| hook.changed_lines)
+        && !all_files
+        && files.is_empty()
+        && directories.is_empty()
+        && !hook_stage.operate_on_commit_msg()
+    {
+        let refs = from_ref.as_deref().zip(to_ref.as_deref());
+        Some(git::get_changed_lines(workspace.root(), refs).await?)
+    } else {
+        None
+    };
+
     let filenames = collect_files(
         workspace.root(),
         CollectOptions {
@@ -147,6 +490,10 @@ pub(crate) async fn run(
             files,
             directories,
             commit_msg_filename: extra_args.commit_msg_filename,
+            only_files_matching,
+            exclude_files_matching,
+            fail_on_sparse,
+            since_tree,
         },
     )
     .await?;
@@ -159,17 +506,280 @@ pub(crate) async fn run(
         )
     })?;
 
-    run_hooks(
+    // Hooks only get these files in the scratch worktrees, relative to the git root.
+    let scratch_files = if preview_diff || isolate {
+        let git_root = GIT_ROOT.as_ref()?;
+        let prefix = workspace
+            .root()
+            .strip_prefix(git_root)
+            .unwrap_or(Path::new(""));
+        filenames.iter().map(|file| prefix.join(file)).collect()
+    } else {
+        Vec::new()
+    };
+
+    let preview = if preview_diff {
+        Some(DiffPreview::new(store, GIT_ROOT.as_ref()?, &scratch_files).await?)
+    } else {
+        None
+    };
+
+    let isolated = if isolate {
+        Some(IsolatedTree::new(store, GIT_ROOT.as_ref()?, &scratch_files).await?)
+    } else {
+        None
+    };
+
+    let diff = if fix_and_verify {
+        get_diff(workspace.root(), isolated.as_ref()).await?
+    } else {
+        Vec::new()
+    };
+
+    let (mut status, mut results) = run_hooks(
         &workspace,
         &installed_hooks,
-        filenames,
+        &selectors,
+        branch.as_deref(),
+        hook_stage,
+        filenames.clone(),
+        file_limits,
+        changed_lines.as_ref(),
         store,
-        show_diff_on_failure,
+        show_diff_on_failure && !fix_and_verify && !auto_stage_fixes,
         dry_run,
+        preview.as_ref(),
+        isolated.as_ref(),
+        read_only,
+        strict,
+        output.clone(),
+        stream,
+        pty,
+        sandbox,
+        deadline,
         verbose,
         printer,
     )
-    .await
+    .await?;
+
+    // Run all hooks once more against the fixed files.
+    let rerun = if !matches!(status, ExitStatus::Failure) {
+        false
+    } else if auto_stage_fixes {
+        stage_fixes(workspace.root(), &unstaged_files, printer).await?
+    } else if fix_and_verify && get_diff(workspace.root(), isolated.as_ref()).await? != diff {
+        writeln!(
+            printer.stdout_important(),
+            "\n{}",
+            "Files were modified by hooks, running hooks again to verify the fixes:".bold()
+        )?;
+        true
+    } else {
+        false
+    };
+    if !dry_run {
+        last_run.record(&results);
+    }
+    if rerun {
+        (status, results) = run_hooks(
+            &workspace,
+            &installed_hooks,
+            &selectors,
+            branch.as_deref(),
+            hook_stage,
+            filenames,
+            file_limits,
+            changed_lines.as_ref(),
+            store,
+            show_diff_on_failure,
+            dry_run,
+            None,
+            isolated.as_ref(),
+            read_only,
+            strict,
+            output,
+            stream,
+            pty,
+            sandbox,
+            deadline,
+            verbose,
+            printer,
+        )
+        .await?;
+        if !dry_run {
+            last_run.record(&results);
+        }
+    }
+    if !dry_run {
+        last_run.save();
+        History::new(store, workspace.root()).record(&results).await;
+    }
+    if print_summary && !dry_run {
+        write_summary(&RunSummary::from_results(&results), printer)?;
+    }
+    if let Some(limit) = durations {
+        write_durations(&results, &reporter.durations(), limit, printer)?;
+    }
+
+    if let Some(preview) = preview {
+        preview.print(printer).await?;
+    }
+    if let Some(isolated) = isolated {
+        isolated.apply_fixes(printer).await?;
+    }
+
+    if let (ExitStatus::Success, Some(cache)) = (status, rebase_cache) {
+        cache.mark_passed();
+    }
+    if let (ExitStatus::Success, Some(incremental)) = (status, incremental) {
+        incremental.mark_passed();
+    }
+
+    Ok(status)
+}
+
+/// The unstaged changes in `path`, in the copy of the staged content if hooks run on one.
+async fn get_diff(path: &Path, isolated: Option<&IsolatedTree>) -> Result<Vec<u8>> {
+    match isolated {
+        Some(isolated) => isolated.get_diff(path).await,
+        None => Ok(git::get_diff(path).await?),
+    }
+}
+
+/// Refuse to run hooks of repos whose `rev` is a branch or a tag, which can be moved.
+fn check_pinned_revs(hooks: &[Arc<Hook>]) -> Result<()> {
+    let unpinned = hooks
+        .iter()
+        .filter(|hook| matches!(hook.repo(), Repo::Remote { rev, .. } if !is_full_sha(rev)))
+        .map(|hook| hook.repo().to_string())
+        .unique()
+        .sorted()
+        .collect::<Vec<_>>();
+    if unpinned.is_empty() {
+        return Ok(());
+    }
+
+    anyhow::bail!(
+        "The `rev` of these repos is not a full commit SHA (`require_pinned_revs`):\n{}\nPin them with `{}`",
+        unpinned
+            .iter()
+            .map(|repo| format!("  - {}", repo.cyan()))
+            .join("\n"),
+        "prek auto-update --freeze".cyan()
+    )
+}
+
+/// Print the number of hooks per status, e.g. `Ran 3 hooks: 2 passed, 1 failed`.
+fn write_summary(summary: &RunSummary, printer: Printer) -> Result<()> {
+    let total = summary.passed + summary.failed + summary.warnings + summary.skipped;
+    let mut line = format!(
+        "Ran {total} hook{}: {} passed, {} failed",
+        if total == 1 { "" } else { "s" },
+        summary.passed,
+        summary.failed
+    );
+    if summary.warnings > 0 {
+        write!(line, ", {} with warnings", summary.warnings)?;
+    }
+    if summary.skipped > 0 {
+        write!(line, ", {} skipped", summary.skipped)?;
+    }
+    writeln!(printer.stdout_important(), "{line}")?;
+    Ok(())
+}
+
+/// Print the slowest hooks and environment installs, slowest first.
+fn write_durations(
+    results: &[HookResult],
+    installs: &[(String, Duration)],
+    limit: usize,
+    printer: Printer,
+) -> Result<()> {
+    let hooks = results
+        .iter()
+        .filter(|result| !matches!(result.status, HookStatus::Skipped | HookStatus::DryRun))
+        .map(|result| (display_id(&result.full_id), result.duration))
+        .sorted_by(|(_, a), (_, b)| b.total_cmp(a))
+        .take(limit)
+        .collect::<Vec<_>>();
+    let installs = installs
+        .iter()
+        .map(|(hook, duration)| (display_id(hook), duration.as_secs_f64()))
+        .sorted_by(|(_, a), (_, b)| b.total_cmp(a))
+        .take(limit)
+        .collect::<Vec<_>>();
+
+    for (title, durations) in [
+        ("Slowest hooks:", hooks),
+        ("Slowest environment installs:", installs),
+    ] {
+        if durations.is_empty() {
+            continue;
+        }
+        writeln!(printer.stdout(), "\n{}", title.bold())?;
+        for (id, duration) in durations {
+            let duration = format!("{duration:.2}s");
+            writeln!(printer.stdout(), "  {duration:>8}  {id}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Hooks of the root project are printed without the `.:` prefix.
+fn display_id(full_id: &str) -> &str {
+    full_id.strip_prefix(".:").unwrap_or(full_id)
+}
+
+/// Stage the files modified by hooks, so they are part of the commit.
+///
+/// Returns `false` if hooks didn't modify any file, or if a modified file also has changes that
+/// are not staged: staging it would commit those changes too.
+async fn stage_fixes(root: &Path, unstaged_files: &[PathBuf], printer: Printer) -> Result<bool> {
+    let fixed = git::files_not_staged(&[root]).await?;
+    if fixed.is_empty() {
+        return Ok(false);
+    }
+
+    let conflicting = fixed
+        .iter()
+        .filter(|file| unstaged_files.contains(*file))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !conflicting.is_empty() {
+        warn_user!(
+            "Fixes were not staged, files modified by hooks also have changes that are not staged:\n{}",
+            format_paths(&conflicting)
+        );
+        return Ok(false);
+    }
+
+    git::add_files(GIT_ROOT.as_ref()?, &fixed).await?;
+    writeln!(
+        printer.stdout_important(),
+        "\n{}\n{}",
+        "Staged files modified by hooks, running hooks again:".bold(),
+        format_paths(&fixed)
+    )?;
+
+    Ok(true)
+}
+
+fn format_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .sorted()
+        .map(|path| format!("  - {}", path.display()))
+        .join("\n")
+}
+
+/// Check if a file has lines starting with both `<<<<<<< ` and `>>>>>>> ` conflict markers.
+fn has_conflict_markers(path: &Path) -> bool {
+    let Ok(content) = fs_err::read(path) else {
+        return false;
+    };
+    let mut lines = content.split(|&b| b == b'\n');
+    lines.any(|line| line.starts_with(b"<<<<<<< "))
+        && lines.any(|line| line.starts_with(b">>>>>>> "))
 }
 
 // `pre-commit` sets these environment variables for other git hooks.
@@ -255,8 +865,10 @@ pub async fn install_hooks(
                 let mut newly_installed = Vec::new();
 
                 for hook in hooks {
+                    let span = trace_span!("environment", hook = %hook, cache = field::Empty);
+
                     // Find a matching installed hook environment.
-                    if let Some(info) = store_hooks
+                    let found = store_hooks
                         .iter()
                         .chain(newly_installed.iter().filter_map(|h| {
                             if let InstalledHook::Installed { info, .. } = h {
@@ -266,17 +878,18 @@ pub async fn install_hooks(
                             }
                         }))
                         .find(|info| info.matches(&hook))
-                    {
-                        debug!(
-                            "Found installed environment for hook `{}` at `{}`",
-                            &hook,
-                            info.env_path.display()
-                        );
-                        hook_envs.push(InstalledHook::Installed {
-                            hook,
-                            info: info.clone(),
-                        });
-                        continue;
+                        .cloned();
+                    if let Some(info) = found {
+                        if is_intact(&info).await {
+                            debug!(
+                                "Found installed environment for hook `{}` at `{}`",
+                                &hook,
+                                info.env_path.display()
+                            );
+                            span.record("cache", "hit");
+                            hook_envs.push(InstalledHook::Installed { hook, info });
+                            continue;
+                        }
                     }
 
                     let _permit = semaphore.acquire().await.unwrap();
@@ -285,6 +898,7 @@ pub async fn install_hooks(
                     let installed_hook = hook
                         .language
                         .install(hook.clone(), store, reporter)
+                        .instrument(span.clone())
                         .await
                         .context(format!("Failed to install hook `{hook}`"))?;
 
@@ -296,9 +910,11 @@ pub async fn install_hooks(
                     match &installed_hook {
                         InstalledHook::Installed { info, .. } => {
                             debug!("Installed hook `{hook}` in `{}`", info.env_path.display());
+                            span.record("cache", "miss");
                         }
                         InstalledHook::NoNeedInstall { .. } => {
                             debug!("Hook `{hook}` does not need installation");
+                            span.record("cache", "none");
                         }
                     }
 
@@ -326,6 +942,33 @@ pub async fn install_hooks(
     Ok(installed_hooks)
 }
 
+/// Check an installed environment against the checksums recorded at install time before
+/// reusing it. Corrupted environments are removed, so the hook is installed again.
+async fn is_intact(info: &InstallInfo) -> bool {
+    // The environment may have been removed by another hook sharing it.
+    if !info.env_path.is_dir() {
+        return false;
+    }
+    match info.verify_checksums(Verify::Quick).await {
+        Ok(corrupted) if corrupted.is_empty() => true,
+        Ok(corrupted) => {
+            warn!(
+                ?corrupted,
+                path = %info.env_path.display(),
+                "Removing corrupted installed hook"
+            );
+            if let Err(err) = fs_err::tokio::remove_dir_all(fs::long_path(&info.env_path)).await {
+                warn!(?err, path = %info.env_path.display(), "Failed to remove corrupted hook");
+            }
+            false
+        }
+        Err(err) => {
+            warn!(?err, path = %info.env_path.display(), "Skipping unverifiable hook");
+            false
+        }
+    }
+}
+
 /// Partition hooks into groups where hooks in the same group have same dependencies.
 /// Hooks in different groups can be installed in parallel.
 fn partition_hooks(hooks: &[Arc<Hook>]) -> Vec<Vec<Arc<Hook>>> {
@@ -374,6 +1017,25 @@ fn partition_hooks(hooks: &[Arc<Hook>]) -> Vec<Vec<Arc<Hook>>> {
     groups
 }
 
+/// Check if a hook is skipped by the `ci.skip` list of its config, which is honored in CI like
+/// the `SKIP` environment variable.
+fn skipped_by_ci(hook: &Hook) -> bool {
+    CI.is_some() && !hook.required && hook.project().config().ci_skip().any(|id| id == hook.id)
+}
+
+/// The reason a hook is skipped by its `platforms`, `*_if_env` or `*_on_branches` conditions.
+fn condition_skip_reason(hook: &Hook, branch: Option<&str>) -> Option<&'static str> {
+    if !hook.runs_on_current_platform() {
+        Some(StatusPrinter::PLATFORM)
+    } else if !hook.enabled_by_env() {
+        Some(StatusPrinter::DISABLED)
+    } else if !hook.runs_on_branch(branch) {
+        Some(StatusPrinter::BRANCH)
+    } else {
+        None
+    }
+}
+
 struct StatusPrinter {
     printer: Printer,
     columns: usize,
@@ -382,10 +1044,20 @@ struct StatusPrinter {
 impl StatusPrinter {
     const PASSED: &'static str = "Passed";
     const FAILED: &'static str = "Failed";
+    const WARNING: &'static str = "Warning";
+    const TIMED_OUT: &'static str = "Timed Out";
+    const OVER_LIMIT: &'static str = "Over Limit";
     const SKIPPED: &'static str = "Skipped";
     const DRY_RUN: &'static str = "Dry Run";
     const NO_FILES: &'static str = "(no files to check)";
+    const FILES_SKIPPED: &'static str = "(files skipped)";
+    const CACHED: &'static str = "(passed before)";
     const UNIMPLEMENTED: &'static str = "(unimplemented yet)";
+    const BRANCH: &'static str = "(skipped on branch)";
+    const PLATFORM: &'static str = "(other platform)";
+    const DISABLED: &'static str = "(disabled by env)";
+    const RUN_TIMED_OUT: &'static str = "(run timed out)";
+    const DEPENDENCY_FAILED: &'static str = "(dependency failed)";
 
     fn for_hooks(hooks: &[InstalledHook], printer: Printer) -> Self {
         let columns = Self::calculate_columns(hooks);
@@ -445,6 +1117,28 @@ impl StatusPrinter {
         writeln!(self.printer.stdout_important(), "{}", Self::FAILED.on_red())
     }
 
+    fn write_warning(&self) -> Result<(), std::fmt::Error> {
+        // Written in one piece, so the escape sequence isn't split when colors are stripped.
+        let status = Self::WARNING.black().on_yellow().to_string();
+        writeln!(self.printer.stdout_important(), "{status}")
+    }
+
+    fn write_timed_out(&self) -> Result<(), std::fmt::Error> {
+        writeln!(
+            self.printer.stdout_important(),
+            "{}",
+            Self::TIMED_OUT.on_red()
+        )
+    }
+
+    fn write_over_limit(&self) -> Result<(), std::fmt::Error> {
+        writeln!(
+            self.printer.stdout_important(),
+            "{}",
+            Self::OVER_LIMIT.on_red()
+        )
+    }
+
     fn stdout(&self) -> Stdout {
         self.printer.stdout()
     }
@@ -459,18 +1153,35 @@ impl StatusPrinter {
 async fn run_hooks(
     workspace: &Workspace,
     hooks: &[InstalledHook],
+    selectors: &Selectors,
+    branch: Option<&str>,
+    hook_stage: Stage,
     filenames: Vec<PathBuf>,
+    file_limits: FileLimits,
+    changed_lines: Option<&ChangedLines>,
     store: &Store,
     show_diff_on_failure: bool,
     dry_run: bool,
+    preview: Option<&DiffPreview>,
+    isolated: Option<&IsolatedTree>,
+    read_only: bool,
+    strict: bool,
+    output: RunOutput,
+    stream: bool,
+    pty: bool,
+    sandbox: bool,
+    deadline: Option<Instant>,
     verbose: bool,
     printer: Printer,
-) -> Result<ExitStatus> {
+) -> Result<(ExitStatus, Vec<HookResult>)> {
     debug_assert!(!hooks.is_empty(), "No hooks to run");
 
     let printer = StatusPrinter::for_hooks(hooks, printer);
+    let mut reporter = output_reporter(output)?;
+    let mut results = Vec::with_capacity(hooks.len());
 
     let mut success = true;
+    let mut timed_out = false;
 
     // Group hooks by project to run them in order of their depth in the workspace.
     #[allow(clippy::mutable_key_type)]
@@ -486,6 +1197,13 @@ async fn run_hooks(
     let mut project_to_hooks: Vec<_> = project_to_hooks.into_iter().collect();
     project_to_hooks.sort_by_key(|(_, hooks)| hooks[0].project().idx());
 
+    // Identify Git LFS files once for all projects.
+    let lfs_files = if hook_stage.operate_on_commit_msg() {
+        FxHashSet::default()
+    } else {
+        git::get_lfs_files(&filenames.iter().map(PathBuf::as_path).collect::<Vec<_>>()).await?
+    };
+
     let projects_len = project_to_hooks.len();
     let mut first = true;
     let mut file_modified = false;
@@ -493,6 +1211,7 @@ async fn run_hooks(
     // Hooks might modify the files, so they must be run sequentially.
     'outer: for (_, mut hooks) in project_to_hooks {
         hooks.sort_by_key(|h| h.idx);
+        let hooks = order_by_dependencies(hooks);
 
         let project = hooks[0].project();
         if projects_len > 1 || !project.is_root() {
@@ -504,30 +1223,148 @@ async fn run_hooks(
             )?;
             first = false;
         }
-        let mut diff = git::get_diff(project.path()).await?;
+        let mut diff = get_diff(project.path(), isolated).await?;
 
-        let fail_fast = project.config().fail_fast.unwrap_or(false);
+        let policy = policy();
+        let fail_fast = policy
+            .fail_fast
+            .unwrap_or_else(|| project.config().fail_fast.unwrap_or(false));
 
-        let filter = FileFilter::for_project(filenames.iter(), project);
+        let filter = if hook_stage.operate_on_commit_msg() {
+            FileFilter::unfiltered(filenames.iter())
+        } else {
+            let baseline = if file_limits.ignore_baseline {
+                None
+            } else {
+                Baseline::read(project.path())?
+            };
+            FileFilter::for_project(filenames.iter(), project, file_limits, &lfs_files)
+                .with_baseline(baseline)
+        };
         trace!(
             "Files for project `{project}` after filtered: {}",
             filter.len()
         );
+        if !filter.oversized().is_empty() {
+            let max_file_size = file_limits
+                .max_file_size
+                .or(project.config().max_file_size)
+                .expect("max_file_size should be set");
+            warn_user!(
+                "Skipped files larger than {max_file_size}: {}",
+                filter
+                    .oversized()
+                    .iter()
+                    .map(|path| format!("`{}`", path.display()))
+                    .join(", ")
+            );
+        }
+        if !filter.binary().is_empty() {
+            warn_user!(
+                "Skipped binary files: {}",
+                filter
+                    .binary()
+                    .iter()
+                    .map(|path| format!("`{}`", path.display()))
+                    .join(", ")
+            );
+        }
+        if !filter.lfs().is_empty() {
+            warn_user!(
+                "Skipped Git LFS files: {}",
+                filter
+                    .lfs()
+                    .iter()
+                    .map(|path| format!("`{}`", path.display()))
+                    .join(", ")
+            );
+        }
+        if !filter.lfs_pointers().is_empty() {
+            warn_user!(
+                "Skipped Git LFS files whose content is not downloaded, run `git lfs pull` to run hooks on them: {}",
+                filter
+                    .lfs_pointers()
+                    .iter()
+                    .map(|path| format!("`{}`", path.display()))
+                    .join(", ")
+            );
+        }
 
-        let mut hook_succeed;
+        // Hooks that failed, or were skipped because a hook they depend on failed.
+        let mut blocked = FxHashSet::default();
         for hook in hooks {
-            (hook_succeed, diff, file_modified) =
-                run_hook(hook, &filter, store, diff, verbose, dry_run, &printer).await?;
+            let dry_run = dry_run || (read_only && builtin::modifies_files(hook));
+            let result;
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                printer.write_skipped(
+                    &hook.name,
+                    StatusPrinter::RUN_TIMED_OUT,
+                    Style::new().black().on_red(),
+                )?;
+                result = HookResult::new(hook, HookStatus::Skipped);
+                timed_out = true;
+            } else if selectors.skipped_by_env(hook) || skipped_by_ci(hook) {
+                printer.write_skipped(&hook.name, "", Style::new().black().on_yellow())?;
+                result = HookResult::new(hook, HookStatus::Skipped);
+            } else if let Some(reason) = condition_skip_reason(hook, branch) {
+                printer.write_skipped(&hook.name, reason, Style::new().black().on_cyan())?;
+                result = HookResult::new(hook, HookStatus::Skipped);
+            } else if hook.depends_on.iter().any(|id| blocked.contains(id)) {
+                printer.write_skipped(
+                    &hook.name,
+                    StatusPrinter::DEPENDENCY_FAILED,
+                    Style::new().black().on_red(),
+                )?;
+                result = HookResult::new(hook, HookStatus::Skipped);
+                blocked.insert(&hook.id);
+            } else {
+                (result, diff) = run_hook(
+                    hook,
+                    &filter,
+                    changed_lines,
+                    store,
+                    diff,
+                    stream,
+                    pty,
+                    sandbox,
+                    deadline,
+                    verbose,
+                    dry_run,
+                    preview,
+                    isolated,
+                    strict,
+                    &printer,
+                )
+                .await?;
+                // The hook was killed at the deadline of the run.
+                timed_out |= result.status == HookStatus::TimedOut
+                    && deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            }
+
+            if result.failed() {
+                blocked.insert(&hook.id);
+            }
+            success &= !result.failed();
+            file_modified = result.files_modified;
+            if let Some(reporter) = reporter.as_mut() {
+                reporter.on_hook_result(&result)?;
+            }
+            #[cfg(feature = "opentelemetry")]
+            crate::telemetry::record_hook(&result.full_id, result.status.as_str(), result.duration);
+            results.push(result);
 
-            success &= hook_succeed;
-            if !success && (fail_fast || hook.fail_fast) {
+            if !success && (fail_fast || policy.fail_fast.unwrap_or(hook.fail_fast)) {
                 break 'outer;
             }
         }
     }
 
+    if let Some(reporter) = reporter.as_mut() {
+        reporter.on_run_complete(&RunSummary::from_results(&results))?;
+    }
+
     if !success && show_diff_on_failure && file_modified {
-        if EnvVars::is_set(EnvVars::CI) {
+        if CI.is_some() {
             writeln!(
                 printer.stdout(),
                 "{}",
@@ -562,11 +1399,58 @@ async fn run_hooks(
             .await?;
     }
 
-    if success {
-        Ok(ExitStatus::Success)
+    let status = if timed_out {
+        warn_user!("The run timed out, the remaining hooks were skipped");
+        ExitStatus::TimedOut
+    } else if success {
+        ExitStatus::Success
     } else {
-        Ok(ExitStatus::Failure)
+        ExitStatus::Failure
+    };
+    Ok((status, results))
+}
+
+/// Order the hooks of a project so that hooks run after the hooks they depend on, otherwise in
+/// the order of the config.
+fn order_by_dependencies(mut hooks: Vec<&InstalledHook>) -> Vec<&InstalledHook> {
+    let mut ordered = Vec::with_capacity(hooks.len());
+    while !hooks.is_empty() {
+        let ready = hooks
+            .iter()
+            .position(|hook| {
+                hook.depends_on
+                    .iter()
+                    .all(|id| hooks.iter().all(|other| other.id != *id))
+            })
+            // Cycles are rejected when the hooks are loaded.
+            .unwrap_or(0);
+        ordered.push(hooks.remove(ready));
     }
+    ordered
+}
+
+/// The lines changed in the files of a hook, as a JSON object of ranges of line numbers keyed
+/// by the filenames as they're passed to the hook.
+fn changed_lines_json(
+    changed_lines: &ChangedLines,
+    hook: &Hook,
+    filenames: &[&Path],
+) -> Result<String> {
+    let relative = hook.relative_to_work_dir(filenames);
+    let names = relative
+        .as_ref()
+        .map(|names| names.iter().map(PathBuf::as_path).collect::<Vec<_>>());
+    let names = names.as_deref().unwrap_or(filenames);
+
+    let lines: BTreeMap<_, _> = filenames
+        .iter()
+        .zip(names)
+        .filter_map(|(filename, name)| {
+            let ranges = changed_lines.get(&hook.project().relative_path().join(filename))?;
+            Some((name.to_string_lossy(), ranges))
+        })
+        .collect();
+    Ok(serde_json::to_string(&lines)?)
 }
 
 /// Shuffle the files so that they more evenly fill out the xargs
@@ -577,15 +1461,25 @@ fn shuffle<T>(filenames: &mut [T]) {
     filenames.shuffle(&mut rng);
 }
 
+#[allow(clippy::fn_params_excessive_bools)]
+#[instrument(level = "trace", skip_all, fields(hook = %hook, status = field::Empty))]
 async fn run_hook(
     hook: &InstalledHook,
     filter: &FileFilter<'_>,
+    changed_lines: Option<&ChangedLines>,
     store: &Store,
     diff: Vec<u8>,
+    stream: bool,
+    pty: bool,
+    sandbox: bool,
+    run_deadline: Option<Instant>,
     verbose: bool,
     dry_run: bool,
+    preview: Option<&DiffPreview>,
+    isolated: Option<&IsolatedTree>,
+    strict: bool,
     printer: &StatusPrinter,
-) -> Result<(bool, Vec<u8>, bool)> {
+) -> Result<(HookResult, Vec<u8>)> {
     let mut filenames = filter.for_hook(hook);
     trace!(
         "Files for hook `{}` after filtered: {}",
@@ -593,13 +1487,39 @@ async fn run_hook(
         filenames.len()
     );
 
+    // Reported with the hook result, so that hooks whose files were all skipped by the file
+    // limits don't look like hooks without files.
+    let skipped_files: Vec<_> = filter
+        .skipped_for_hook(hook)
+        .into_iter()
+        .map(|filename| hook.project().relative_path().join(filename))
+        .collect();
+
     if filenames.is_empty() && !hook.always_run {
-        printer.write_skipped(
-            &hook.name,
-            StatusPrinter::NO_FILES,
-            Style::new().black().on_cyan(),
-        )?;
-        return Ok((true, diff, false));
+        let reason = if skipped_files.is_empty() {
+            StatusPrinter::NO_FILES
+        } else {
+            StatusPrinter::FILES_SKIPPED
+        };
+        printer.write_skipped(&hook.name, reason, Style::new().black().on_cyan())?;
+        let mut result = HookResult::new(hook, HookStatus::Skipped);
+        result.skipped_files = skipped_files;
+        return Ok((result, diff));
+    }
+
+    // Files the hook already passed on are not checked again.
+    let mut cache = (hook.cache_results && hook.pass_filenames && !hook.always_run && !dry_run)
+        .then(|| ResultCache::load(store, hook));
+    if let Some(cache) = cache.as_mut() {
+        cache.retain_uncached(hook.project().path(), &mut filenames);
+        if filenames.is_empty() {
+            printer.write_skipped(
+                &hook.name,
+                StatusPrinter::CACHED,
+                Style::new().black().on_green(),
+            )?;
+            return Ok((HookResult::new(hook, HookStatus::Skipped), diff));
+        }
     }
 
     if !Language::supported(hook.language) {
@@ -608,24 +1528,47 @@ async fn run_hook(
             StatusPrinter::UNIMPLEMENTED,
             Style::new().black().on_yellow(),
         )?;
-        return Ok((true, diff, false));
+        return Ok((HookResult::new(hook, HookStatus::Skipped), diff));
     }
 
-    printer.write_running(&hook.name, false)?;
-    std::io::stdout().flush()?;
+    // Streamed output is printed before the status line, which is printed once the hook finished.
+    let stream = (stream || hook.stream_output) && !dry_run && printer.stdout() == Stdout::Enabled;
+    if !stream {
+        printer.write_running(&hook.name, false)?;
+        std::io::stdout().flush()?;
+    }
 
     let start = std::time::Instant::now();
+    let hook_deadline = hook.timeout.map(|timeout| Instant::now() + timeout);
+    let deadline = hook_deadline.into_iter().chain(run_deadline).min();
 
-    let filenames = if hook.pass_filenames {
+    // Remembered for `prek run --failed`, relative to the workspace root.
+    let files = filenames
+        .iter()
+        .map(|filename| hook.project().relative_path().join(filename))
+        .collect();
+    // Computed before the filenames are dropped, hooks that don't get filenames need it too.
+    let changed_lines = match changed_lines {
+        Some(changed_lines) if hook.changed_lines => {
+            Some(changed_lines_json(changed_lines, hook, &filenames)?)
+        }
+        _ => None,
+    };
+
+    // With `pass_file_contents`, the hook runs once per file even if the names aren't passed.
+    let per_file = hook.pass_filenames || hook.pass_file_contents.is_some();
+    let filenames = if per_file {
         shuffle(&mut filenames);
         filenames
     } else {
         vec![]
     };
 
-    let (status, output) = if dry_run {
+    let (status, output, killed) = if dry_run {
         let mut output = Vec::new();
-        if !filenames.is_empty() {
+        if !per_file {
+            writeln!(output, "`{hook}` would be run once without filenames")?;
+        } else if !filenames.is_empty() {
             writeln!(
                 output,
                 "`{}` would be run on {} files:",
@@ -636,19 +1579,78 @@ async fn run_hook(
         for filename in &filenames {
             writeln!(output, "- {}", filename.to_string_lossy())?;
         }
-        (0, output)
+        // Builtin checks are known to not modify files.
+        if let Some(preview) = preview
+            && (!builtin::check_fast_path(hook) || builtin::modifies_files(hook))
+        {
+            preview.run_hook(hook, &filenames, store).await?;
+        }
+        (0, output, None)
     } else {
-        hook.language
-            .run(hook, &filenames, store)
-            .await
-            .context(format!("Failed to run hook `{hook}`"))?
+        // Serialize hooks of the same concurrency group, also across prek processes.
+        let _group_lock = match hook.concurrency_group.as_deref() {
+            Some(group) => Some(store.lock_group_async(group).await?),
+            None => None,
+        };
+        let mut env: Vec<_> = hook
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), hook_env::interpolate(value)))
+            .collect();
+        if let Some(changed_lines) = changed_lines {
+            env.push((EnvVars::PREK_CHANGED_LINES.to_string(), changed_lines));
+        }
+        // Under `stash_mode: isolate`, hooks run in the copy of the staged content.
+        let isolated_hook = isolated.map(|isolated| isolated.hook(hook));
+        let hook = isolated_hook.as_ref().unwrap_or(hook);
+        let file_contents = if hook.pass_file_contents == Some(PassFileContents::Stdin) {
+            Some(crate::run::read_file_contents(hook.project().path(), &filenames).await?)
+        } else {
+            None
+        };
+        let hook_output = HookOutput {
+            stream: stream.then(|| format!("[{}]", hook.id)),
+            pty: pty || hook.tty,
+            deadline,
+            limits: hook.limits,
+            sandbox: (sandbox || hook.sandbox || policy().require_sandbox)
+                .then(|| Sandbox::for_hook(hook, store)),
+            pass_env: Some(hook.pass_env.clone()),
+            env,
+            file_contents,
+        };
+        let run = HOOK_OUTPUT.scope(hook_output, hook.language.run(hook, &filenames, store));
+        // Commands are killed at the deadline and their output is kept. Hooks that run
+        // in-process are only stopped after a grace period, so they don't race the commands.
+        let result = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline + DEADLINE_GRACE, run)
+                .await
+                .ok(),
+            None => Some(run.await),
+        };
+        match result {
+            Some(Ok((status, output))) => (status, output, None),
+            Some(Err(err)) => match killed_output(&err) {
+                Some((killed, output)) => (1, output, Some(killed)),
+                None => return Err(err.context(format!("Failed to run hook `{hook}`"))),
+            },
+            None => (1, Vec::new(), Some(Killed::TimedOut)),
+        }
     };
 
     let duration = start.elapsed();
 
-    let new_diff = git::get_diff(hook.work_dir()).await?;
+    let new_diff = get_diff(hook.project().path(), isolated).await?;
     let file_modified = diff != new_diff;
     let success = status == 0 && !file_modified;
+    if success && let Some(cache) = cache {
+        cache.mark_passed();
+    }
+    // Failures of advisory hooks are reported, but don't fail the run.
+    let warning = !success && hook.severity == Severity::Warning && !strict;
+    if stream {
+        printer.write_running(&hook.name, false)?;
+    }
     if dry_run {
         printer.write_dry_run()?;
     } else if success {
@@ -659,7 +1661,14 @@ async fn run_hook(
         if printer.stdout() == Stdout::Disabled {
             printer.write_running(&hook.name, true)?;
         }
-        printer.write_failed()?;
+        match killed {
+            _ if warning => printer.write_warning()?,
+            Some(Killed::TimedOut) => printer.write_timed_out()?,
+            Some(Killed::OverLimit(exceeded)) if exceeded.is_over_limit() => {
+                printer.write_over_limit()?;
+            }
+            Some(Killed::OverLimit(_)) | None => printer.write_failed()?,
+        }
     }
 
     if verbose || hook.verbose || !success {
@@ -669,6 +1678,12 @@ async fn run_hook(
             printer.stdout_important()
         };
 
+        // Collapse the details of the hook in CI logs, the status line stays visible.
+        let group = CI.map(|ci| (ci.group_start(&hook.name), ci.group_end(&hook.name)));
+        if let Some((Some(start), _)) = &group {
+            writeln!(stdout, "{start}")?;
+        }
+
         writeln!(stdout, "{}", format!("- hook id: {}", hook.id).dimmed())?;
         if verbose || hook.verbose {
             writeln!(
@@ -677,8 +1692,23 @@ async fn run_hook(
                 format!("- duration: {:.2?}s", duration.as_secs_f64()).dimmed()
             )?;
         }
-        if status != 0 {
-            writeln!(stdout, "{}", format!("- exit code: {status}").dimmed())?;
+        match killed {
+            Some(Killed::TimedOut) => {
+                let message = match hook.timeout {
+                    Some(timeout) if deadline == hook_deadline => {
+                        format!("- timed out after {}s", timeout.as_secs())
+                    }
+                    _ => "- killed at the timeout of the run".to_string(),
+                };
+                writeln!(stdout, "{}", message.dimmed())?;
+            }
+            Some(Killed::OverLimit(exceeded)) => {
+                writeln!(stdout, "{}", format!("- {exceeded}").dimmed())?;
+            }
+            None if status != 0 => {
+                writeln!(stdout, "{}", format!("- exit code: {status}").dimmed())?;
+            }
+            None => {}
         }
         if file_modified {
             writeln!(stdout, "{}", "- files were modified by this hook".dimmed())?;
@@ -686,23 +1716,86 @@ async fn run_hook(
 
         let output = output.trim_ascii();
         if !output.is_empty() {
+            if !stream {
+                writeln!(
+                    stdout,
+                    "{}",
+                    textwrap::indent(&String::from_utf8_lossy(output), "  ").dimmed()
+                )?;
+            }
+
+            // Also append the output to the log file, relative to the project root.
             if let Some(file) = hook.log_file.as_deref() {
                 let mut file = fs_err::tokio::OpenOptions::new()
                     .create(true)
                     .append(true)
-                    .open(file)
+                    .open(hook.project().path().join(file))
                     .await?;
                 file.write_all(output).await?;
+                file.write_all(b"\n").await?;
                 file.sync_all().await?;
-            } else {
-                writeln!(
-                    stdout,
-                    "{}",
-                    textwrap::indent(&String::from_utf8_lossy(output), "  ").dimmed()
-                )?;
             }
         }
+
+        if !success && let Some(message) = hook.on_fail_message.as_deref() {
+            writeln!(stdout, "{}", message.trim_end().yellow())?;
+        }
+
+        if let Some((_, Some(end))) = &group {
+            writeln!(stdout, "{end}")?;
+        }
     }
 
-    Ok((success, new_diff, file_modified))
+    let mut result = HookResult::new(
+        hook,
+        if dry_run {
+            HookStatus::DryRun
+        } else if success {
+            HookStatus::Passed
+        } else if warning {
+            HookStatus::Warning
+        } else {
+            match killed {
+                Some(Killed::TimedOut) => HookStatus::TimedOut,
+                Some(Killed::OverLimit(exceeded)) if exceeded.is_over_limit() => {
+                    HookStatus::OverLimit
+                }
+                Some(Killed::OverLimit(_)) | None => HookStatus::Failed,
+            }
+        },
+    );
+    result.exit_code = status;
+    result.files_modified = file_modified;
+    result.duration = duration.as_secs_f64();
+    result.set_output(output.trim_ascii());
+    result.files = files;
+    result.files_passed = filenames.len();
+    result.skipped_files = skipped_files;
+    Span::current().record("status", result.status.as_str());
+
+    Ok((result, new_diff))
+}
+
+/// Why prek killed a hook.
+#[derive(Debug, Clone, Copy)]
+enum Killed {
+    TimedOut,
+    OverLimit(Exceeded),
+}
+
+/// The output a hook produced until it was killed, if it failed because it timed out or
+/// exceeded a resource limit.
+fn killed_output(err: &anyhow::Error) -> Option<(Killed, Vec<u8>)> {
+    err.chain().find_map(|err| {
+        let (killed, output) = match err.downcast_ref::<process::Error>() {
+            Some(process::Error::TimedOut { output, .. }) => (Killed::TimedOut, output),
+            Some(process::Error::OverLimit {
+                exceeded, output, ..
+            }) => (Killed::OverLimit(*exceeded), output),
+            _ => return None,
+        };
+        let mut combined = output.stdout.clone();
+        combined.extend_from_slice(&output.stderr);
+        Some((killed, combined))
+    })
 }
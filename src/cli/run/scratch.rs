@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use rustc_hash::FxHashSet;
+use tempfile::TempDir;
+use tracing::warn;
+
+use crate::git;
+use crate::hook::InstalledHook;
+use crate::process::Cmd;
+use crate::store::Store;
+
+/// A worktree of the staged content, for hooks to run on without touching the working tree or
+/// the index, under `prek run --dry-run --diff` and `stash_mode: isolate`.
+///
+/// It's a git worktree at `HEAD` with the index of the repository, so hooks can run git in it.
+/// Only the files passed to hooks are checked out, along with the files of their directories and
+/// of the parent directories, where tools look up their configuration. The other files are
+/// marked `skip-worktree`.
+pub(crate) struct ScratchTree {
+    dir: TempDir,
+    git_root: PathBuf,
+}
+
+impl ScratchTree {
+    /// Check out the worktree for hooks that run on `files`, relative to the git root.
+    pub(crate) async fn new(store: &Store, git_root: &Path, files: &[PathBuf]) -> Result<Self> {
+        let scratch = store.scratch_path();
+        fs_err::tokio::create_dir_all(&scratch).await?;
+        let dir = tempfile::tempdir_in(&scratch)?;
+
+        let tree = git::write_tree().await?;
+        let commit = if git::rev_exists("HEAD").await? {
+            "HEAD".to_string()
+        } else {
+            git::commit_tree(&tree).await?
+        };
+        git::add_worktree(git_root, &dir.path().join("worktree"), &commit).await?;
+        // Removed when dropped, even if the checkout fails.
+        let worktree = Self {
+            dir,
+            git_root: git_root.to_path_buf(),
+        };
+
+        let dirs = files
+            .iter()
+            .flat_map(|file| file.ancestors().skip(1))
+            .collect::<FxHashSet<_>>();
+        git::sparse_checkout(&worktree.path(), &tree, |file| {
+            file.parent().is_some_and(|parent| dirs.contains(parent))
+        })
+        .await?;
+
+        Ok(worktree)
+    }
+
+    /// The root of the worktree.
+    pub(crate) fn path(&self) -> PathBuf {
+        self.dir.path().join("worktree")
+    }
+
+    /// `path` of the repository relative to the git root, `.` for the root itself.
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        match path.strip_prefix(&self.git_root) {
+            Ok(relative) if relative.as_os_str().is_empty() => Path::new("."),
+            Ok(relative) => relative,
+            Err(_) => path,
+        }
+    }
+
+    /// A copy of `hook` that runs in the copy of its project directory.
+    pub(crate) fn hook(&self, hook: &InstalledHook) -> InstalledHook {
+        hook.with_work_dir(self.path().join(self.relative(hook.project().path())))
+    }
+
+    /// A git command that runs in the worktree.
+    pub(crate) fn git_cmd(&self, summary: &str) -> Result<Cmd> {
+        let mut cmd = git::git_cmd(summary)?;
+        cmd.current_dir(self.path()).remove_git_env();
+        Ok(cmd)
+    }
+
+    /// The changes hooks made to the copy of `path`, in the format of `git diff`.
+    pub(crate) async fn get_diff(&self, path: &Path) -> Result<Vec<u8>> {
+        let output = self
+            .git_cmd("git diff")?
+            .arg("diff")
+            .arg("--no-ext-diff")
+            .arg("--no-textconv")
+            .arg("--ignore-submodules")
+            .arg("--")
+            .arg(self.relative(path))
+            .check(true)
+            .output()
+            .await?;
+        Ok(output.stdout)
+    }
+
+    /// Tracked files modified by hooks, relative to the git root. Files removed by hooks are not
+    /// included.
+    pub(crate) async fn modified_files(&self) -> Result<Vec<PathBuf>> {
+        let output = self
+            .git_cmd("git diff")?
+            .arg("diff")
+            .arg("--name-only")
+            .arg("--no-renames")
+            .arg("--diff-filter=M")
+            .arg("--ignore-submodules")
+            .arg("-z")
+            .check(true)
+            .output()
+            .await?;
+
+        output
+            .stdout
+            .split(|&b| b == b'\0')
+            .filter(|name| !name.is_empty())
+            .map(|name| Ok(git::path_from_bytes(name)?))
+            .collect()
+    }
+
+    /// Mark the files hooks created as intent-to-add, so that they show up in diffs.
+    pub(crate) async fn add_created_files(&self) -> Result<()> {
+        let output = self
+            .git_cmd("git ls-files")?
+            .arg("ls-files")
+            .arg("--others")
+            .arg("--exclude-standard")
+            .arg("-z")
+            .check(true)
+            .output()
+            .await?;
+        let created = output
+            .stdout
+            .split(|&b| b == b'\0')
+            .filter(|name| !name.is_empty())
+            .map(git::path_from_bytes)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for chunk in created.chunks(git::PATHS_PER_COMMAND) {
+            self.git_cmd("git add")?
+                .arg("add")
+                .arg("--intent-to-add")
+                .arg("--")
+                .args(chunk)
+                .check(true)
+                .output()
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ScratchTree {
+    fn drop(&mut self) {
+        if let Err(err) = git::remove_worktree(&self.git_root, &self.path()) {
+            warn!("Failed to remove the scratch worktree: {err}");
+        }
+    }
+}
@@ -4,11 +4,12 @@ use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use crate::hook::Hook;
-use crate::warn_user;
+use crate::{warn_user, warn_user_once};
 
 use anyhow::anyhow;
 use constants::env_vars::EnvVars;
 use itertools::Itertools;
+use owo_colors::OwoColorize;
 use path_clean::PathClean;
 use rustc_hash::FxHashSet;
 use tracing::trace;
@@ -45,6 +46,9 @@ pub(crate) enum SelectorExpr {
         project_path: PathBuf,
         hook_id: String,
     },
+    Group(String),
+    /// Hooks that have all the tags.
+    Tags(Vec<String>),
 }
 
 #[derive(Debug, Clone)]
@@ -75,6 +79,8 @@ impl Display for Selector {
                     write!(f, "{}:{hook_id}", project_path.display())
                 }
             }
+            SelectorExpr::Group(group) => write!(f, "{group}"),
+            SelectorExpr::Tags(tags) => write!(f, "{}", tags.join("+")),
         }
     }
 }
@@ -102,13 +108,47 @@ impl Selector {
 
     pub(crate) fn kind_str(&self) -> &'static str {
         match &self.expr {
-            SelectorExpr::HookId(_) | SelectorExpr::ProjectHook { .. } => "hooks",
+            SelectorExpr::HookId(_)
+            | SelectorExpr::ProjectHook { .. }
+            | SelectorExpr::Group(_)
+            | SelectorExpr::Tags(_) => "hooks",
             SelectorExpr::ProjectPrefix(_) => "projects",
         }
     }
 }
 
 impl Selector {
+    /// The hook ids or aliases closest to the hook id of the selector, to point out typos.
+    fn suggestions<'a>(&self, hook_ids: &[&'a str]) -> Vec<&'a str> {
+        let SelectorExpr::HookId(hook_id) = &self.expr else {
+            return Vec::new();
+        };
+        let max_distance = (hook_id.chars().count() / 3).max(1);
+        hook_ids
+            .iter()
+            .map(|id| (levenshtein::levenshtein(hook_id, id), *id))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .sorted()
+            .dedup()
+            .map(|(_, id)| id)
+            .take(3)
+            .collect()
+    }
+
+    fn did_you_mean(&self, hook_ids: &[&str]) -> String {
+        let suggestions = self.suggestions(hook_ids);
+        if suggestions.is_empty() {
+            return String::new();
+        }
+        format!(
+            ", did you mean {}?",
+            suggestions
+                .iter()
+                .map(|id| format!("`{}`", id.cyan()))
+                .join(" or ")
+        )
+    }
+
     pub(crate) fn matches_hook(&self, hook: &Hook) -> bool {
         match &self.expr {
             SelectorExpr::HookId(hook_id) => {
@@ -127,6 +167,8 @@ impl Selector {
                 (&hook.id == hook_id || &hook.alias == hook_id)
                     && project_path == hook.project().relative_path()
             }
+            SelectorExpr::Group(group) => hook.group.as_ref() == Some(group),
+            SelectorExpr::Tags(tags) => tags.iter().all(|tag| hook.tags.contains(tag)),
         }
     }
 }
@@ -185,6 +227,51 @@ impl Selectors {
         })
     }
 
+    /// Include the hooks of the projects given with `--project`.
+    pub(crate) fn with_projects(
+        mut self,
+        projects: &[String],
+        workspace_root: &Path,
+    ) -> Result<Selectors, Error> {
+        for project in projects.iter().unique() {
+            self.includes.push(Selector {
+                source: SelectorSource::CliFlag("--project"),
+                original: project.clone(),
+                expr: SelectorExpr::ProjectPrefix(normalize_path(
+                    project,
+                    workspace_root,
+                    RealFileSystem,
+                )?),
+            });
+        }
+        Ok(self)
+    }
+
+    /// Include the hooks of the groups given with `--group`.
+    pub(crate) fn with_groups(mut self, groups: &[String]) -> Selectors {
+        for group in groups.iter().unique() {
+            self.includes.push(Selector {
+                source: SelectorSource::CliFlag("--group"),
+                original: group.clone(),
+                expr: SelectorExpr::Group(group.clone()),
+            });
+        }
+        self
+    }
+
+    /// Include the hooks with the tags given with `--tag`, a hook must have all the tags joined
+    /// by `+`.
+    pub(crate) fn with_tags(mut self, tags: &[String]) -> Selectors {
+        for tag in tags.iter().unique() {
+            self.includes.push(Selector {
+                source: SelectorSource::CliFlag("--tag"),
+                original: tag.clone(),
+                expr: SelectorExpr::Tags(tag.split('+').map(ToString::to_string).collect()),
+            });
+        }
+        self
+    }
+
     pub(crate) fn includes(&self) -> &[Selector] {
         &self.includes
     }
@@ -206,7 +293,10 @@ impl Selectors {
             }
         }
         if skipped {
-            return false;
+            if !hook.required {
+                return false;
+            }
+            warn_user_once!("Hook `{}` is required and cannot be skipped", hook.id);
         }
 
         if self.includes.is_empty() {
@@ -223,6 +313,25 @@ impl Selectors {
         included
     }
 
+    /// Check if a hook is otherwise selected but skipped by its id in the `SKIP` or `PREK_SKIP`
+    /// environment variables. Like in `pre-commit`, such hooks are reported as skipped.
+    pub(crate) fn skipped_by_env(&self, hook: &Hook) -> bool {
+        if hook.required {
+            return false;
+        }
+        let skipped = self.skips.iter().any(|skip| {
+            matches!(skip.source, SelectorSource::EnvVar(_))
+                && !matches!(skip.expr, SelectorExpr::ProjectPrefix(_))
+                && skip.matches_hook(hook)
+        });
+        skipped
+            && (self.includes.is_empty()
+                || self
+                    .includes
+                    .iter()
+                    .any(|include| include.matches_hook(hook)))
+    }
+
     pub(crate) fn matches_path(&self, path: &Path) -> bool {
         let mut usage = self.usage.lock().unwrap();
 
@@ -260,9 +369,16 @@ impl Selectors {
         included
     }
 
-    pub(crate) fn report_unused(&self) {
+    /// Warn about the selectors that did not match, suggesting the closest ids or aliases of
+    /// `hooks` for selectors of a hook id.
+    pub(crate) fn report_unused<'a>(&self, hooks: impl IntoIterator<Item = &'a Hook>) {
+        let hook_ids = hooks
+            .into_iter()
+            .flat_map(|hook| [hook.id.as_str(), hook.alias.as_str()])
+            .filter(|id| !id.is_empty())
+            .collect::<Vec<_>>();
         let usage = self.usage.lock().unwrap();
-        usage.report_unused(self);
+        usage.report_unused(self, &hook_ids);
     }
 }
 
@@ -281,7 +397,7 @@ impl SelectorUsage {
         self.used_skips.insert(idx);
     }
 
-    fn report_unused(&self, selectors: &Selectors) {
+    fn report_unused(&self, selectors: &Selectors, hook_ids: &[&str]) {
         let unused = selectors
             .includes
             .iter()
@@ -301,14 +417,15 @@ impl SelectorUsage {
             [(_, selector)] => {
                 let flag = selector.as_flag();
                 let normalized = selector.as_normalized_flag();
+                let did_you_mean = selector.did_you_mean(hook_ids);
                 if flag == normalized {
                     warn_user!(
-                        "selector `{flag}` did not match any {}",
+                        "selector `{flag}` did not match any {}{did_you_mean}",
                         selector.kind_str()
                     );
                 } else {
                     warn_user!(
-                        "selector `{flag}` ({}) did not match any {}",
+                        "selector `{flag}` ({}) did not match any {}{did_you_mean}",
                         format!("normalized to `{normalized}`").dimmed(),
                         selector.kind_str()
                     );
@@ -320,11 +437,12 @@ impl SelectorUsage {
                     .map(|(_, sel)| {
                         let flag = sel.as_flag();
                         let normalized = sel.as_normalized_flag();
+                        let did_you_mean = sel.did_you_mean(hook_ids);
                         if flag == normalized {
-                            format!("  - `{flag}`")
+                            format!("  - `{flag}`{did_you_mean}")
                         } else {
                             format!(
-                                "  - `{flag}` ({})",
+                                "  - `{flag}` ({}){did_you_mean}",
                                 format!("normalized to `{normalized}`").dimmed()
                             )
                         }
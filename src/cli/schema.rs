@@ -0,0 +1,21 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::cli::{ExitStatus, SchemaCommand};
+use crate::config::{Config, Manifest};
+use crate::printer::Printer;
+
+pub(crate) fn schema(command: SchemaCommand, printer: Printer) -> Result<ExitStatus> {
+    let schema = match command {
+        SchemaCommand::Config => schemars::schema_for!(Config),
+        SchemaCommand::Manifest => schemars::schema_for!(Manifest),
+    };
+    writeln!(
+        printer.stdout(),
+        "{}",
+        serde_json::to_string_pretty(&schema)?
+    )?;
+
+    Ok(ExitStatus::Success)
+}
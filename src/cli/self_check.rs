@@ -0,0 +1,224 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use http::header::USER_AGENT;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use constants::env_vars::EnvVars;
+use constants::{ALT_CONFIG_FILE, CONFIG_FILE};
+
+use crate::cli::ExitStatus;
+use crate::fs::{CWD, Simplified};
+use crate::http_client::HTTP_CLIENT;
+use crate::printer::Printer;
+use crate::store::Store;
+use crate::version::version;
+use crate::warn_user;
+use crate::workspace::Workspace;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/j178/prek/releases/latest";
+
+/// How often the passive check looks for a newer release.
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Report whether a newer release of prek exists, and whether the config requires an upgrade.
+pub(crate) async fn self_check(config: Option<PathBuf>, printer: Printer) -> Result<ExitStatus> {
+    let current = current_version();
+    writeln!(
+        printer.stdout(),
+        "Installed version: {}",
+        format!("v{current}").bold()
+    )?;
+
+    let latest = latest_release(Duration::from_secs(10)).await?;
+    if latest > current {
+        writeln!(
+            printer.stdout(),
+            "A newer release is available: {}, update with `{}`",
+            format!("v{latest}").green().bold(),
+            "prek self update".cyan()
+        )?;
+    } else {
+        writeln!(printer.stdout(), "You're on the latest release")?;
+    }
+
+    // The config is not parsed, parsing fails if it requires a newer prek.
+    let Some(config_file) = config_file(config) else {
+        return Ok(ExitStatus::Success);
+    };
+    let Some(minimum) = read_minimum_version(&config_file)? else {
+        return Ok(ExitStatus::Success);
+    };
+    if minimum > current {
+        writeln!(
+            printer.stdout(),
+            "`{}` requires prek {}, upgrade required",
+            config_file.user_display().cyan(),
+            format!("v{minimum}").red().bold()
+        )?;
+        return Ok(ExitStatus::Failure);
+    }
+    writeln!(
+        printer.stdout(),
+        "`{}` requires prek {}, no upgrade required",
+        config_file.user_display().cyan(),
+        format!("v{minimum}").bold()
+    )?;
+
+    Ok(ExitStatus::Success)
+}
+
+#[derive(Serialize, Deserialize)]
+struct UpdateCheck {
+    /// The last time the release feed was checked, in seconds since the Unix epoch.
+    checked_at: u64,
+    latest: semver::Version,
+}
+
+/// Warn if a newer release of prek exists, checking the release feed at most once a day.
+///
+/// Enabled with `check-updates = true` in the user config. Failures are only logged, the
+/// check must never get in the way of the command.
+pub(crate) async fn passive_check(store: &Store) {
+    if let Err(err) = passive_check_inner(store).await {
+        debug!("Failed to check for a newer release: {err:#}");
+    }
+}
+
+async fn passive_check_inner(store: &Store) -> Result<()> {
+    let path = store.path().join("update-check.json");
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let cached = fs_err::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<UpdateCheck>(&content).ok());
+    let latest = match cached {
+        Some(check) if now.saturating_sub(check.checked_at) < CHECK_INTERVAL.as_secs() => {
+            check.latest
+        }
+        cached => {
+            // A failed check is recorded too, so that prek doesn't wait for the release feed on
+            // every command while offline.
+            let result = latest_release(Duration::from_secs(2)).await;
+            let latest = match &result {
+                Ok(latest) => latest.clone(),
+                Err(_) => cached.map_or_else(current_version, |check| check.latest),
+            };
+            let check = UpdateCheck {
+                checked_at: now,
+                latest,
+            };
+            fs_err::create_dir_all(store.path())?;
+            fs_err::write(&path, serde_json::to_string(&check)?)?;
+            result?
+        }
+    };
+
+    let current = current_version();
+    if latest > current {
+        warn_user!(
+            "A newer release of prek is available: v{current} -> v{latest}, update with `prek self update`"
+        );
+    }
+
+    Ok(())
+}
+
+fn current_version() -> semver::Version {
+    version().version.parse().expect("Invalid prek version")
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+/// The version of the latest release of prek on GitHub.
+async fn latest_release(timeout: Duration) -> Result<semver::Version> {
+    let client = HTTP_CLIENT.clone();
+    let mut request = client
+        .get(LATEST_RELEASE_URL)
+        .header(USER_AGENT, format!("prek/{}", version().version))
+        .timeout(timeout);
+    if let Some(token) = EnvVars::var_os(EnvVars::GITHUB_TOKEN) {
+        request = request.bearer_auth(token.to_string_lossy());
+    }
+
+    let release: Release = request
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .context("Failed to fetch the latest release of prek")?
+        .json()
+        .await
+        .context("Failed to parse the latest release of prek")?;
+
+    release
+        .tag_name
+        .trim_start_matches('v')
+        .parse()
+        .with_context(|| format!("Invalid release tag `{}`", release.tag_name))
+}
+
+/// The config of the workspace, if there is one.
+fn config_file(config: Option<PathBuf>) -> Option<PathBuf> {
+    if config.is_some() {
+        return config;
+    }
+    let root = Workspace::find_root(None, &CWD).ok()?;
+    [CONFIG_FILE, ALT_CONFIG_FILE]
+        .into_iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Read `minimum_prek_version` from a config without validating the rest of it.
+fn read_minimum_version(path: &Path) -> Result<Option<semver::Version>> {
+    let content = fs_err::read_to_string(path)?;
+    let config: serde_yaml::Value = serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse `{}`", path.display()))?;
+    parse_minimum_version(&config)
+}
+
+fn parse_minimum_version(config: &serde_yaml::Value) -> Result<Option<semver::Version>> {
+    let Some(version) = config
+        .get("minimum_prek_version")
+        .and_then(serde_yaml::Value::as_str)
+    else {
+        return Ok(None);
+    };
+    let version = version
+        .parse()
+        .with_context(|| format!("Invalid `minimum_prek_version` `{version}`"))?;
+    Ok(Some(version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimum_version() {
+        let config = serde_yaml::from_str(indoc::indoc! {r"
+            minimum_prek_version: '99.0.0'
+            repos: []
+        "})
+        .unwrap();
+        assert_eq!(
+            parse_minimum_version(&config).unwrap(),
+            Some(semver::Version::new(99, 0, 0))
+        );
+
+        let config = serde_yaml::from_str("repos: []").unwrap();
+        assert_eq!(parse_minimum_version(&config).unwrap(), None);
+
+        let config = serde_yaml::from_str("minimum_prek_version: latest").unwrap();
+        assert!(parse_minimum_version(&config).is_err());
+    }
+}
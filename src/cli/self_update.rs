@@ -20,53 +20,69 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use std::env;
 use std::fmt::Write;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
-use axoupdater::{AxoUpdater, AxoupdateError, UpdateRequest};
+use anyhow::{Context, Result};
+use axoupdater::AxoUpdater;
 use owo_colors::OwoColorize;
-use tracing::{debug, enabled};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::debug;
 
+use constants::env_vars::EnvVars;
+
+use crate::archive::{self, ArchiveExtension};
 use crate::cli::ExitStatus;
+use crate::http_client::HTTP_CLIENT;
 use crate::printer::Printer;
 
+const RELEASES_URL: &str = "https://api.github.com/repos/j178/prek/releases";
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn version(&self) -> Result<semver::Version> {
+        self.tag_name
+            .trim_start_matches('v')
+            .parse()
+            .with_context(|| format!("Invalid release tag `{}`", self.tag_name))
+    }
+
+    fn asset_url(&self, name: &str) -> Result<&str> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .map(|asset| asset.browser_download_url.as_str())
+            .with_context(|| format!("Release `{}` has no asset `{name}`", self.tag_name))
+    }
+}
+
 /// Attempt to update the prek binary.
+///
+/// The archive of the release for this platform is verified against the SHA-256 checksum
+/// published with the release, then the binary replaces the current executable with a rename.
 pub(crate) async fn self_update(
     version: Option<String>,
     token: Option<String>,
     printer: Printer,
 ) -> Result<ExitStatus> {
-    let mut updater = AxoUpdater::new_for("prek");
-    if enabled!(tracing::Level::DEBUG) {
-        unsafe { env::set_var("INSTALLER_PRINT_VERBOSE", "1") };
-        updater.enable_installer_output();
-    } else {
-        updater.disable_installer_output();
-    }
-
-    if let Some(ref token) = token {
-        updater.set_github_token(token);
-    }
-
     // Load the "install receipt" for the current binary. If the receipt is not found, then
     // prek was likely installed via a package manager.
+    let mut updater = AxoUpdater::new_for("prek");
     let Ok(updater) = updater.load_receipt() else {
         debug!("no receipt found; assuming prek was installed via a package manager");
-        writeln!(
-            printer.stderr(),
-            "{}",
-            format_args!(
-                concat!(
-                    "{}{} Self-update is only available for prek binaries installed via the standalone installation scripts.",
-                    "\n",
-                    "\n",
-                    "If you installed prek with pip, brew, or another package manager, update prek with `pip install --upgrade`, `brew upgrade`, or similar."
-                ),
-                "warning".yellow().bold(),
-                ":".bold()
-            )
-        )?;
+        warn_package_manager(printer)?;
         return Ok(ExitStatus::Error);
     };
 
@@ -77,20 +93,7 @@ pub(crate) async fn self_update(
         debug!(
             "receipt is not for this executable; assuming prek was installed via a package manager"
         );
-        writeln!(
-            printer.stderr(),
-            "{}",
-            format_args!(
-                concat!(
-                    "{}{} Self-update is only available for prek binaries installed via the standalone installation scripts.",
-                    "\n",
-                    "\n",
-                    "If you installed prek with pip, brew, or another package manager, update prek with `pip install --upgrade`, `brew upgrade`, or similar."
-                ),
-                "warning".yellow().bold(),
-                ":".bold()
-            )
-        )?;
+        warn_package_manager(printer)?;
         return Ok(ExitStatus::Error);
     }
 
@@ -104,78 +107,229 @@ pub(crate) async fn self_update(
         )
     )?;
 
-    let update_request = if let Some(version) = version {
-        UpdateRequest::SpecificTag(version)
-    } else {
-        UpdateRequest::Latest
-    };
-
-    updater.configure_version_specifier(update_request);
-
-    // Run the updater. This involves a network request, since we need to determine the latest
-    // available version of prek.
-    match updater.run().await {
-        Ok(Some(result)) => {
-            let version_information = if let Some(old_version) = result.old_version {
-                format!(
-                    "from {} to {}",
-                    format!("v{old_version}").bold().white(),
-                    format!("v{}", result.new_version).bold().white(),
-                )
-            } else {
-                format!("to {}", format!("v{}", result.new_version).bold().white())
-            };
-
-            writeln!(
-                printer.stderr(),
-                "{}",
-                format_args!(
-                    "{}{} Upgraded prek {}! {}",
-                    "success".green().bold(),
-                    ":".bold(),
-                    version_information,
-                    format!(
-                        "https://github.com/j178/prek/releases/tag/{}",
-                        result.new_version_tag
-                    )
-                    .cyan()
-                )
-            )?;
-        }
-        Ok(None) => {
+    // This involves a network request, since we need to determine the latest available version
+    // of prek.
+    let release = match fetch_release(version.as_deref(), token.as_deref()).await {
+        Ok(release) => release,
+        Err(err) if err.status() == Some(http::StatusCode::FORBIDDEN) && token.is_none() => {
             writeln!(
                 printer.stderr(),
                 "{}",
                 format_args!(
-                    "{}{} You're on the latest version of prek ({})",
-                    "success".green().bold(),
+                    "{}{} GitHub API rate limit exceeded. Please provide a GitHub token via the {} option.",
+                    "error".red().bold(),
                     ":".bold(),
-                    format!("v{}", env!("CARGO_PKG_VERSION")).bold().white()
+                    "`--token`".green().bold()
                 )
             )?;
+            return Ok(ExitStatus::Error);
         }
-        Err(err) => {
-            return if let AxoupdateError::Reqwest(err) = err {
-                if err.status() == Some(http::StatusCode::FORBIDDEN) && token.is_none() {
-                    writeln!(
-                        printer.stderr(),
-                        "{}",
-                        format_args!(
-                            "{}{} GitHub API rate limit exceeded. Please provide a GitHub token via the {} option.",
-                            "error".red().bold(),
-                            ":".bold(),
-                            "`--token`".green().bold()
-                        )
-                    )?;
-                    Ok(ExitStatus::Error)
-                } else {
-                    Err(err.into())
-                }
-            } else {
-                Err(err.into())
-            };
-        }
+        Err(err) => return Err(err).context("Failed to fetch the release of prek"),
+    };
+
+    let old_version: semver::Version = env!("CARGO_PKG_VERSION").parse()?;
+    let new_version = release.version()?;
+    if version.is_none() && new_version <= old_version {
+        writeln!(
+            printer.stderr(),
+            "{}",
+            format_args!(
+                "{}{} You're on the latest version of prek ({})",
+                "success".green().bold(),
+                ":".bold(),
+                format!("v{old_version}").bold().white()
+            )
+        )?;
+        return Ok(ExitStatus::Success);
     }
 
+    let (name, ext) = artifact();
+    let archive = download(release.asset_url(&name)?).await?;
+    let checksum = download(release.asset_url(&format!("{name}.sha256"))?).await?;
+    verify_checksum(&archive, &String::from_utf8_lossy(&checksum))
+        .with_context(|| format!("Failed to verify `{name}`"))?;
+    replace_executable(&archive, ext).await?;
+    update_receipt(&new_version).context("Failed to update the install receipt")?;
+
+    writeln!(
+        printer.stderr(),
+        "{}",
+        format_args!(
+            "{}{} Upgraded prek from {} to {}! {}",
+            "success".green().bold(),
+            ":".bold(),
+            format!("v{old_version}").bold().white(),
+            format!("v{new_version}").bold().white(),
+            format!(
+                "https://github.com/j178/prek/releases/tag/{}",
+                release.tag_name
+            )
+            .cyan()
+        )
+    )?;
+
     Ok(ExitStatus::Success)
 }
+
+fn warn_package_manager(printer: Printer) -> Result<()> {
+    writeln!(
+        printer.stderr(),
+        "{}",
+        format_args!(
+            concat!(
+                "{}{} Self-update is only available for prek binaries installed via the standalone installation scripts.",
+                "\n",
+                "\n",
+                "If you installed prek with pip, brew, or another package manager, update prek with `pip install --upgrade`, `brew upgrade`, or similar."
+            ),
+            "warning".yellow().bold(),
+            ":".bold()
+        )
+    )?;
+    Ok(())
+}
+
+/// The release `version`, or the latest release.
+async fn fetch_release(version: Option<&str>, token: Option<&str>) -> reqwest::Result<Release> {
+    let url = match version {
+        Some(version) => format!("{RELEASES_URL}/tags/v{}", version.trim_start_matches('v')),
+        None => format!("{RELEASES_URL}/latest"),
+    };
+    let mut request = HTTP_CLIENT.get(url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    request.send().await?.error_for_status()?.json().await
+}
+
+async fn download(url: &str) -> Result<Vec<u8>> {
+    let response = HTTP_CLIENT
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .with_context(|| format!("Failed to download {url}"))?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// The name of the release archive for this platform.
+fn artifact() -> (String, ArchiveExtension) {
+    let target = target_lexicon::HOST;
+    if cfg!(windows) {
+        (format!("prek-{target}.zip"), ArchiveExtension::Zip)
+    } else {
+        (format!("prek-{target}.tar.gz"), ArchiveExtension::TarGz)
+    }
+}
+
+/// Verify `content` against a checksum file in the format of `sha256sum`.
+fn verify_checksum(content: &[u8], checksum_file: &str) -> Result<()> {
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .context("The checksum file is empty")?;
+    let actual = hex::encode(Sha256::digest(content));
+    if !expected.eq_ignore_ascii_case(&actual) {
+        anyhow::bail!("Checksum mismatch:\n  expected: {expected}\n  actual:   {actual}");
+    }
+    Ok(())
+}
+
+/// Unpack the binary next to the current executable, and move it in place.
+async fn replace_executable(archive: &[u8], ext: ArchiveExtension) -> Result<()> {
+    let current = std::env::current_exe()?;
+    let dir = current
+        .parent()
+        .context("The current executable has no parent directory")?;
+    // In the same directory, so the binary is moved with a rename on the same filesystem.
+    let temp_dir = tempfile::tempdir_in(dir)?;
+    archive::unpack(archive, ext, temp_dir.path()).await?;
+    let binary = find_binary(temp_dir.path())?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs_err::set_permissions(&binary, std::fs::Permissions::from_mode(0o755))?;
+        fs_err::rename(&binary, &current)?;
+    }
+    #[cfg(windows)]
+    {
+        // A running executable can't be replaced, but it can be renamed.
+        let old = current.with_extension("exe.old");
+        let _ = fs_err::remove_file(&old);
+        fs_err::rename(&current, &old)?;
+        if let Err(err) = fs_err::rename(&binary, &current) {
+            let _ = fs_err::rename(&old, &current);
+            return Err(err.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Record `version` in the install receipt, as the standalone installers do, so later updates
+/// compare against the installed version.
+fn update_receipt(version: &semver::Version) -> Result<()> {
+    let path = receipt_path()?.context("The install receipt was not found")?;
+    let mut receipt: serde_json::Value = serde_json::from_str(&fs_err::read_to_string(&path)?)?;
+    receipt["version"] = serde_json::Value::String(version.to_string());
+    fs_err::write(&path, serde_json::to_string(&receipt)?)?;
+    Ok(())
+}
+
+/// The install receipt that `AxoUpdater::load_receipt` reads, looked up the same way.
+fn receipt_path() -> Result<Option<PathBuf>> {
+    let dirs = if EnvVars::is_set(EnvVars::AXOUPDATER_CONFIG_WORKING_DIR) {
+        vec![std::env::current_dir()?]
+    } else if let Some(path) = EnvVars::var_os(EnvVars::AXOUPDATER_CONFIG_PATH) {
+        vec![PathBuf::from(path)]
+    } else {
+        let xdg = EnvVars::var_os(EnvVars::XDG_CONFIG_HOME)
+            .map(|home| PathBuf::from(home).join("prek"))
+            .filter(|dir| dir.exists());
+        let home = if cfg!(windows) {
+            EnvVars::var_os(EnvVars::LOCALAPPDATA).map(|home| PathBuf::from(home).join("prek"))
+        } else {
+            etcetera::home_dir()
+                .ok()
+                .map(|home| home.join(".config").join("prek"))
+        };
+        xdg.into_iter().chain(home).collect()
+    };
+
+    Ok(dirs
+        .into_iter()
+        .map(|dir| dir.join("prek-receipt.json"))
+        .find(|path| path.exists()))
+}
+
+/// The binary in the unpacked archive, at the top level or in its top-level directory.
+fn find_binary(dir: &Path) -> Result<PathBuf> {
+    let name = format!("prek{}", std::env::consts::EXE_SUFFIX);
+    let top_level = archive::strip_component(dir).unwrap_or_else(|_| dir.to_path_buf());
+    [top_level.join(&name), dir.join(&name)]
+        .into_iter()
+        .find(|path| path.is_file())
+        .with_context(|| format!("The release archive has no `{name}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_checksum;
+
+    #[test]
+    fn checksum() {
+        let checksum = "a75f0a4982620c631641ae4be9dade10bb643ba112013c0f622ba2b298b9ab32";
+        verify_checksum(
+            b"prek",
+            &format!("{checksum} *prek-x86_64-unknown-linux-gnu.tar.gz\n"),
+        )
+        .unwrap();
+        verify_checksum(b"prek", &checksum.to_uppercase()).unwrap();
+
+        let err = verify_checksum(b"prek!", checksum).unwrap_err();
+        assert!(err.to_string().starts_with("Checksum mismatch"), "{err}");
+        assert!(verify_checksum(b"prek", "").is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+use rustc_hash::FxHashMap;
+use unicode_width::UnicodeWidthStr;
+
+use crate::cli::ExitStatus;
+use crate::cli::run::{History, HookStatus};
+use crate::fs::CWD;
+use crate::printer::Printer;
+use crate::store::STORE;
+use crate::workspace::Workspace;
+
+#[derive(Default)]
+struct HookStats {
+    runs: u32,
+    failures: u32,
+    /// How often the hook went from passing to failing or back between runs.
+    flips: u32,
+    last_failed: Option<bool>,
+    total_duration: f64,
+    max_duration: f64,
+}
+
+impl HookStats {
+    fn failure_rate(&self) -> f64 {
+        f64::from(self.failures) / f64::from(self.runs)
+    }
+
+    fn average_duration(&self) -> f64 {
+        self.total_duration / f64::from(self.runs)
+    }
+}
+
+pub(crate) fn stats(config: Option<&Path>, runs: usize, printer: Printer) -> Result<ExitStatus> {
+    let workspace_root = Workspace::find_root(config, &CWD)?;
+    let store = STORE.as_ref()?;
+
+    let runs = History::new(store, &workspace_root).runs(runs);
+    if runs.is_empty() {
+        writeln!(printer.stdout(), "No runs recorded yet")?;
+        return Ok(ExitStatus::Success);
+    }
+
+    let mut hooks: FxHashMap<&str, HookStats> = FxHashMap::default();
+    for run in &runs {
+        for hook in &run.hooks {
+            let stats = hooks.entry(hook.id.as_str()).or_default();
+            let failed = matches!(
+                hook.status,
+                HookStatus::Failed
+                    | HookStatus::Warning
+                    | HookStatus::TimedOut
+                    | HookStatus::OverLimit
+            );
+            stats.runs += 1;
+            stats.failures += u32::from(failed);
+            if stats.last_failed.is_some_and(|last| last != failed) {
+                stats.flips += 1;
+            }
+            stats.last_failed = Some(failed);
+            stats.total_duration += hook.duration;
+            stats.max_duration = stats.max_duration.max(hook.duration);
+        }
+    }
+
+    // The hooks that fail the most come first, then the flaky ones, then the slow ones.
+    let mut hooks = hooks.into_iter().collect::<Vec<_>>();
+    hooks.sort_by(|(a_id, a), (b_id, b)| {
+        b.failure_rate()
+            .total_cmp(&a.failure_rate())
+            .then(b.flips.cmp(&a.flips))
+            .then(b.average_duration().total_cmp(&a.average_duration()))
+            .then(a_id.cmp(b_id))
+    });
+
+    writeln!(
+        printer.stdout(),
+        "{}",
+        format!(
+            "Last {} run{}:",
+            runs.len(),
+            if runs.len() == 1 { "" } else { "s" }
+        )
+        .bold()
+    )?;
+
+    let width = hooks
+        .iter()
+        .map(|(id, _)| id.width())
+        .max()
+        .unwrap_or(0)
+        .max("Hook".len());
+    writeln!(
+        printer.stdout(),
+        "{}",
+        format!(
+            "{:<width$}  {:>5}  {:>8}  {:>5}  {:>8}  {:>8}",
+            "Hook", "Runs", "Failures", "Flips", "Average", "Max"
+        )
+        .dimmed()
+    )?;
+    for (id, stats) in hooks {
+        let padding = " ".repeat(width - id.width());
+        let failures = format!("{:.0}%", stats.failure_rate() * 100.0);
+        let average = format!("{:.2}s", stats.average_duration());
+        let max = format!("{:.2}s", stats.max_duration);
+        writeln!(
+            printer.stdout(),
+            "{id}{padding}  {:>5}  {failures:>8}  {:>5}  {average:>8}  {max:>8}",
+            stats.runs,
+            stats.flips,
+        )?;
+    }
+
+    Ok(ExitStatus::Success)
+}
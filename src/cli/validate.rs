@@ -6,12 +6,24 @@ use anstream::eprintln;
 use owo_colors::OwoColorize;
 
 use crate::cli::ExitStatus;
-use crate::config::{read_config, read_manifest};
+use crate::config::{lint_raw_config, read_config, read_manifest};
 
-pub(crate) fn validate_configs(configs: Vec<PathBuf>) -> ExitStatus {
+pub(crate) fn validate_configs(configs: Vec<PathBuf>, strict: bool) -> ExitStatus {
     let mut status = ExitStatus::Success;
 
     for config in configs {
+        // Errors that prevent reading the config are reported by `read_config` below.
+        if strict
+            && let Ok((_, lints)) = lint_raw_config(&config)
+            && !lints.is_empty()
+        {
+            for lint in lints {
+                eprintln!("{}: {}", "error".red().bold(), lint);
+            }
+            status = ExitStatus::Failure;
+            continue;
+        }
+
         if let Err(err) = read_config(&config) {
             eprintln!("{}: {}", "error".red().bold(), err);
             for source in iter::successors(err.source(), |&err| err.source()) {
@@ -0,0 +1,187 @@
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use notify::{Event, RecursiveMode, Watcher};
+use owo_colors::OwoColorize;
+use rustc_hash::FxHashSet;
+use tracing::debug;
+
+use crate::cli::{self, ExitStatus};
+use crate::config::{HookOptions, Repo};
+use crate::fs::{CWD, Simplified};
+use crate::git::{self, GIT_ROOT};
+use crate::printer::Printer;
+use crate::workspace::Workspace;
+
+/// Run hooks on the files changed in the working tree, every time files change.
+pub(crate) async fn watch(
+    config: Option<PathBuf>,
+    includes: Vec<String>,
+    skips: Vec<String>,
+    debounce: Duration,
+    refresh: bool,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    LazyLock::force(&GIT_ROOT).as_ref()?;
+    let workspace_root = Workspace::find_root(config.as_deref(), &CWD)?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .context("Failed to create file watcher")?;
+    watcher
+        .watch(&workspace_root, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch `{}`", workspace_root.display()))?;
+
+    writeln!(
+        printer.stdout(),
+        "Watching `{}` for changes, press Ctrl-C to stop",
+        workspace_root.user_display().cyan()
+    )?;
+
+    // Only failures of hooks are printed, followed by a single status line per run.
+    let run_printer = if printer == Printer::Silent {
+        Printer::Silent
+    } else {
+        Printer::Quiet
+    };
+    let mut refresh = refresh;
+
+    loop {
+        let changed = wait_for_changes(&rx, &workspace_root, debounce)?;
+        let ignored = git::get_ignored_files(
+            &workspace_root,
+            &changed.iter().map(PathBuf::as_path).collect::<Vec<_>>(),
+        )
+        .await?;
+        let log_files = log_files(&workspace_root, config.as_deref());
+        let changed = changed
+            .into_iter()
+            .filter(|path| !ignored.contains(path) && !log_files.contains(path))
+            .sorted()
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            continue;
+        }
+        debug!("Files changed: {}", changed.len());
+
+        let files = changed
+            .iter()
+            .map(|path| workspace_root.join(path))
+            .collect();
+        let status = Box::pin(cli::run(
+            cli::RunOptions {
+                config: config.clone(),
+                includes: includes.clone(),
+                skips: skips.clone(),
+                files,
+                refresh,
+                ..cli::RunOptions::default()
+            },
+            run_printer,
+        ))
+        .await;
+        refresh = false;
+
+        let files = if changed.len() > 3 {
+            format!("{} files", changed.len())
+        } else {
+            changed
+                .iter()
+                .map(|path| format!("`{}`", path.display()))
+                .join(", ")
+        };
+        match status {
+            Ok(ExitStatus::Success) => {
+                writeln!(printer.stdout(), "{} {files}", "Passed".green().bold())?;
+            }
+            Ok(_) => {
+                writeln!(printer.stdout(), "{} {files}", "Failed".red().bold())?;
+            }
+            // Keep watching, e.g. the config may be invalid while it's being edited.
+            Err(err) => {
+                writeln!(printer.stderr(), "{}: {err:#}", "error".red().bold())?;
+            }
+        }
+    }
+}
+
+/// The log files of the hooks of the workspace, relative to `root`.
+///
+/// Hooks append their output to them, which must not trigger another run.
+fn log_files(root: &Path, config: Option<&Path>) -> FxHashSet<PathBuf> {
+    let workspace = match Workspace::discover(
+        root.to_path_buf(),
+        config.map(Path::to_path_buf),
+        None,
+        false,
+    ) {
+        Ok(workspace) => workspace,
+        Err(err) => {
+            debug!("Failed to discover the workspace: {err}");
+            return FxHashSet::default();
+        }
+    };
+
+    let mut log_files = FxHashSet::default();
+    for project in workspace.projects() {
+        for repo in &project.config().repos {
+            let options: Vec<&HookOptions> = match repo {
+                Repo::Remote(repo) => repo.hooks.iter().map(|hook| &hook.options).collect(),
+                Repo::Local(repo) => repo.hooks.iter().map(|hook| &hook.options).collect(),
+                Repo::Meta(repo) => repo.hooks.iter().map(|hook| &hook.0.options).collect(),
+                Repo::Builtin(repo) => repo.hooks.iter().map(|hook| &hook.0.options).collect(),
+            };
+            for file in options
+                .iter()
+                .filter_map(|options| options.log_file.as_deref())
+            {
+                let path = project.path().join(file);
+                if let Ok(relative) = path.strip_prefix(root) {
+                    log_files.insert(relative.to_path_buf());
+                }
+            }
+        }
+    }
+    log_files
+}
+
+/// Wait for files to change, and collect the changes until none happen for `debounce`.
+///
+/// Returns the changed files relative to `root`, excluding deleted files and the `.git`
+/// directory. Nothing else runs while watching, so blocking the runtime is fine.
+fn wait_for_changes(
+    rx: &Receiver<notify::Result<Event>>,
+    root: &Path,
+    debounce: Duration,
+) -> Result<FxHashSet<PathBuf>> {
+    let mut changed = FxHashSet::default();
+    let mut event = rx.recv().context("File watcher stopped")?;
+    loop {
+        match event {
+            Ok(event) if !event.kind.is_access() => {
+                changed.extend(event.paths.into_iter().filter_map(|path| {
+                    let relative = path.strip_prefix(root).ok()?;
+                    let in_git_dir = relative
+                        .components()
+                        .any(|component| component == Component::Normal(".git".as_ref()));
+                    (!in_git_dir && path.is_file()).then(|| relative.to_path_buf())
+                }));
+            }
+            Ok(_) => {}
+            Err(err) => debug!("Failed to watch files: {err}"),
+        }
+
+        event = match rx.recv_timeout(debounce) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => return Ok(changed),
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("File watcher stopped"),
+        };
+    }
+}
@@ -1,19 +1,28 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::ops::{Deref, RangeInclusive};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use anyhow::Result;
+use clap::ValueEnum;
 use constants::{ALT_CONFIG_FILE, CONFIG_FILE};
 use fancy_regex::{self as regex, Regex};
+use itertools::Itertools;
+use owo_colors::OwoColorize;
 use rustc_hash::FxHashMap;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tracing::instrument;
 
 use crate::fs::Simplified;
 use crate::identify;
+use crate::store::{STORE, Store};
 use crate::version;
-use crate::warn_user;
+use crate::{warn_deprecated, warn_user_once};
 
 #[derive(Clone)]
 pub struct SerdeRegex(Regex);
@@ -43,6 +52,19 @@ impl<'de> Deserialize<'de> for SerdeRegex {
     }
 }
 
+impl JsonSchema for SerdeRegex {
+    fn schema_name() -> Cow<'static, str> {
+        "Regex".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "format": "regex",
+        })
+    }
+}
+
 impl Serialize for SerdeRegex {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -52,7 +74,88 @@ impl Serialize for SerdeRegex {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, clap::ValueEnum)]
+/// A file size, written as a number of bytes, or with a `KB`, `MB` or `GB` suffix (powers of 1024).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FileSize(pub u64);
+
+const FILE_SIZE_UNITS: [(&str, u64); 4] = [
+    ("GB", 1024 * 1024 * 1024),
+    ("MB", 1024 * 1024),
+    ("KB", 1024),
+    ("B", 1),
+];
+
+impl FromStr for FileSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let upper = s.to_ascii_uppercase();
+        let (number, multiplier) = FILE_SIZE_UNITS
+            .iter()
+            .find_map(|(unit, multiplier)| {
+                upper
+                    .strip_suffix(unit)
+                    .map(|number| (number.trim_end(), *multiplier))
+            })
+            .unwrap_or((&upper, 1));
+
+        number
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(multiplier))
+            .map(FileSize)
+            .ok_or_else(|| format!("invalid file size `{s}`, expected e.g. `500KB` or `2MB`"))
+    }
+}
+
+impl Display for FileSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (unit, multiplier) = FILE_SIZE_UNITS
+            .iter()
+            .find(|(_, multiplier)| self.0 >= *multiplier && self.0.is_multiple_of(*multiplier))
+            .unwrap_or(&("B", 1));
+        write!(f, "{}{unit}", self.0 / multiplier)
+    }
+}
+
+impl<'de> Deserialize<'de> for FileSize {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Size {
+            Bytes(u64),
+            Text(String),
+        }
+
+        match Size::deserialize(deserializer)? {
+            Size::Bytes(bytes) => Ok(FileSize(bytes)),
+            Size::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl JsonSchema for FileSize {
+    fn schema_name() -> Cow<'static, str> {
+        "FileSize".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "anyOf": [
+                { "type": "integer", "minimum": 0 },
+                { "type": "string", "pattern": "^\\s*[0-9]+\\s*([kKmMgG]?[bB])?\\s*$" },
+            ],
+        })
+    }
+}
+
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema, clap::ValueEnum,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum Language {
     Conda,
@@ -110,7 +213,7 @@ impl Display for Language {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, Deserialize, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, clap::ValueEnum)]
 #[serde(rename_all = "kebab-case")]
 pub enum HookType {
     CommitMsg,
@@ -157,6 +260,14 @@ impl HookType {
             Self::PrepareCommitMsg => 1..=3,
         }
     }
+
+    /// Whether the hook runs after git has completed the operation and can't abort it.
+    pub fn is_post(self) -> bool {
+        matches!(
+            self,
+            Self::PostCheckout | Self::PostCommit | Self::PostMerge | Self::PostRewrite
+        )
+    }
 }
 
 impl Display for HookType {
@@ -165,9 +276,7 @@ impl Display for HookType {
     }
 }
 
-#[derive(
-    Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Deserialize, Serialize, clap::ValueEnum,
-)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Serialize, clap::ValueEnum)]
 #[serde(rename_all = "kebab-case")]
 pub enum Stage {
     Manual,
@@ -177,16 +286,65 @@ pub enum Stage {
     PostMerge,
     PostRewrite,
     #[default]
-    #[serde(alias = "commit")]
     PreCommit,
-    #[serde(alias = "merge-commit")]
     PreMergeCommit,
-    #[serde(alias = "push")]
     PrePush,
     PreRebase,
     PrepareCommitMsg,
 }
 
+impl<'de> Deserialize<'de> for Stage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        if let Some(stage) = Stage::from_legacy_name(&s) {
+            warn_deprecated!(
+                "legacy-stage-name",
+                "Stage name `{}` is deprecated, use `{}` instead",
+                s.yellow(),
+                stage.cyan()
+            );
+            return Ok(stage);
+        }
+
+        Stage::value_variants()
+            .iter()
+            .find(|stage| stage.as_str() == s)
+            .copied()
+            .ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "unknown stage `{s}`, expected one of {}",
+                    Stage::value_variants()
+                        .iter()
+                        .map(|stage| format!("`{stage}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
+impl JsonSchema for Stage {
+    fn schema_name() -> Cow<'static, str> {
+        "Stage".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        // The legacy names are still accepted, with a warning.
+        let names = Stage::value_variants()
+            .iter()
+            .map(Stage::as_str)
+            .chain(["commit", "merge-commit", "push"])
+            .collect::<Vec<_>>();
+        json_schema!({
+            "type": "string",
+            "enum": names,
+        })
+    }
+}
+
 impl From<HookType> for Stage {
     fn from(value: HookType) -> Self {
         match value {
@@ -205,6 +363,16 @@ impl From<HookType> for Stage {
 }
 
 impl Stage {
+    /// Map the stage names used before pre-commit 3.0 to their current names.
+    fn from_legacy_name(name: &str) -> Option<Self> {
+        match name {
+            "commit" => Some(Self::PreCommit),
+            "merge-commit" => Some(Self::PreMergeCommit),
+            "push" => Some(Self::PrePush),
+            _ => None,
+        }
+    }
+
     pub fn as_str(&self) -> &str {
         match self {
             Self::Manual => "manual",
@@ -240,6 +408,11 @@ impl Stage {
                 | Stage::PrepareCommitMsg
         )
     }
+
+    /// Hooks of these stages receive the commit message file as their only argument.
+    pub fn operate_on_commit_msg(self) -> bool {
+        matches!(self, Stage::CommitMsg | Stage::PrepareCommitMsg)
+    }
 }
 
 fn deserialize_minimum_version<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
@@ -267,9 +440,46 @@ where
     Ok(Some(s))
 }
 
+/// Deserialize the `minimum_pre_commit_version` field of pre-commit.
+///
+/// The version is compared against the pre-commit version prek is compatible with,
+/// configs requiring a newer pre-commit may rely on schema features prek doesn't support yet.
+fn deserialize_minimum_pre_commit_version<'de, D>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    if s.is_empty() || s == "0" {
+        return Ok(None);
+    }
+
+    // pre-commit allows versions like `2.9`, pad them to a full semver version.
+    let padded = match s.split('.').count() {
+        1 => format!("{s}.0.0"),
+        2 => format!("{s}.0"),
+        _ => s.clone(),
+    };
+    let version = padded
+        .parse::<semver::Version>()
+        .map_err(serde::de::Error::custom)?;
+    let compat_version = version::PRE_COMMIT_COMPAT_VERSION
+        .parse::<semver::Version>()
+        .expect("Invalid pre-commit compatible version");
+    if version > compat_version {
+        return Err(serde::de::Error::custom(format!(
+            "Required minimum pre-commit version `{s}` is greater than the pre-commit version `{compat_version}` supported by prek `{}`. Please consider updating prek.",
+            version::version().version,
+        )));
+    }
+
+    Ok(Some(s))
+}
+
 // TODO: warn deprecated stage
 // TODO: warn sensible regex
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Config {
     pub repos: Vec<Repo>,
@@ -290,9 +500,162 @@ pub struct Config {
     pub fail_fast: Option<bool>,
     /// The minimum version of prek required to run this configuration.
     #[serde(deserialize_with = "deserialize_minimum_version", default)]
+    #[schemars(with = "Option<String>")]
     pub minimum_prek_version: Option<String>,
+    /// The minimum version of pre-commit required to run this configuration.
+    #[serde(deserialize_with = "deserialize_minimum_pre_commit_version", default)]
+    #[schemars(with = "Option<String>")]
+    pub minimum_pre_commit_version: Option<String>,
     /// Configuration for pre-commit.ci service.
+    #[schemars(with = "Option<BTreeMap<String, serde_json::Value>>")]
     pub ci: Option<FxHashMap<String, serde_yaml::Value>>,
+    /// Paths of the configs this config is merged on top of, relative to this config.
+    #[serde(deserialize_with = "deserialize_extends", default)]
+    #[schemars(schema_with = "extends_schema")]
+    pub extends: Vec<String>,
+    /// Files larger than this are not passed to hooks.
+    pub max_file_size: Option<FileSize>,
+    /// Set to true to not pass binary files to hooks.
+    pub skip_binary_files: Option<bool>,
+    /// Set to true to not pass Git LFS files to hooks.
+    pub skip_lfs_files: Option<bool>,
+    /// What to do with changes that are not staged, only read from the workspace root config.
+    pub stash_mode: Option<StashMode>,
+    /// Stage files fixed by hooks and run hooks again, only read from the workspace root config.
+    pub auto_stage_fixes: Option<bool>,
+    /// Refuse to run hooks of repos whose `rev` is not a full commit SHA, only read from the
+    /// workspace root config.
+    pub require_pinned_revs: Option<bool>,
+    /// A configuration-wide default for the `timeout_seconds` of hooks.
+    pub default_timeout_seconds: Option<u64>,
+    /// Variables passed to all hooks, in addition to their own `pass_env`.
+    #[serde(alias = "extra_env_keep")]
+    pub pass_env: Option<Vec<String>>,
+    /// Ids of deprecation warnings not to print, e.g. `legacy-stage-name`.
+    pub suppress_warnings: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Merge this config on top of `base`.
+    ///
+    /// Top-level keys set in this config replace the base values, and `default_language_version`
+    /// is merged per language. Repos of this config are appended to the base repos, except a
+    /// remote repo with the same URL, or the `local`, `meta` and `builtin` repos, are merged into
+    /// the base repo: the `rev` is replaced, and a hook overrides every base hook with the same
+    /// id, e.g. all aliases of a hook.
+    fn merge_onto(self, mut base: Config) -> Config {
+        for repo in self.repos {
+            let existing = base.repos.iter_mut().find(|r| match (&**r, &repo) {
+                (Repo::Remote(a), Repo::Remote(b)) => a.repo == b.repo,
+                (Repo::Local(_), Repo::Local(_))
+                | (Repo::Meta(_), Repo::Meta(_))
+                | (Repo::Builtin(_), Repo::Builtin(_)) => true,
+                _ => false,
+            });
+            match (existing, repo) {
+                (Some(Repo::Remote(existing)), Repo::Remote(repo)) => {
+                    existing.rev = repo.rev;
+                    merge_hooks(
+                        &mut existing.hooks,
+                        repo.hooks,
+                        |h| &h.id,
+                        RemoteHook::merge,
+                    );
+                }
+                (Some(Repo::Local(existing)), Repo::Local(repo)) => {
+                    merge_hooks(
+                        &mut existing.hooks,
+                        repo.hooks,
+                        |h| &h.id,
+                        |base, hook| *base = hook,
+                    );
+                }
+                (Some(Repo::Meta(existing)), Repo::Meta(repo)) => {
+                    merge_hooks(
+                        &mut existing.hooks,
+                        repo.hooks,
+                        |h| &h.0.id,
+                        |base, hook| *base = hook,
+                    );
+                }
+                (Some(Repo::Builtin(existing)), Repo::Builtin(repo)) => {
+                    merge_hooks(
+                        &mut existing.hooks,
+                        repo.hooks,
+                        |h| &h.0.id,
+                        |base, hook| *base = hook,
+                    );
+                }
+                (_, repo) => base.repos.push(repo),
+            }
+        }
+
+        if let Some(versions) = self.default_language_version {
+            base.default_language_version
+                .get_or_insert_with(FxHashMap::default)
+                .extend(versions);
+        }
+
+        Config {
+            repos: base.repos,
+            default_install_hook_types: self
+                .default_install_hook_types
+                .or(base.default_install_hook_types),
+            default_language_version: base.default_language_version,
+            default_stages: self.default_stages.or(base.default_stages),
+            files: self.files.or(base.files),
+            exclude: self.exclude.or(base.exclude),
+            fail_fast: self.fail_fast.or(base.fail_fast),
+            minimum_prek_version: self.minimum_prek_version.or(base.minimum_prek_version),
+            minimum_pre_commit_version: self
+                .minimum_pre_commit_version
+                .or(base.minimum_pre_commit_version),
+            ci: self.ci.or(base.ci),
+            extends: self.extends,
+            max_file_size: self.max_file_size.or(base.max_file_size),
+            skip_binary_files: self.skip_binary_files.or(base.skip_binary_files),
+            skip_lfs_files: self.skip_lfs_files.or(base.skip_lfs_files),
+            stash_mode: self.stash_mode.or(base.stash_mode),
+            auto_stage_fixes: self.auto_stage_fixes.or(base.auto_stage_fixes),
+            require_pinned_revs: self.require_pinned_revs.or(base.require_pinned_revs),
+            default_timeout_seconds: self
+                .default_timeout_seconds
+                .or(base.default_timeout_seconds),
+            pass_env: self.pass_env.or(base.pass_env),
+            suppress_warnings: self.suppress_warnings.or(base.suppress_warnings),
+        }
+    }
+
+    /// The hook ids in `ci.skip`, the pre-commit.ci list of hooks not to run in CI.
+    pub(crate) fn ci_skip(&self) -> impl Iterator<Item = &str> {
+        self.ci
+            .as_ref()
+            .and_then(|ci| ci.get("skip"))
+            .and_then(serde_yaml::Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(serde_yaml::Value::as_str)
+    }
+}
+
+/// Merge `hooks` into `base`: hooks with an id already in `base` are combined with `merge`,
+/// the others are appended.
+fn merge_hooks<H: Clone>(
+    base: &mut Vec<H>,
+    hooks: Vec<H>,
+    id: impl Fn(&H) -> &str,
+    merge: impl Fn(&mut H, H),
+) {
+    for hook in hooks {
+        let mut matched = false;
+        for base_hook in base.iter_mut().filter(|h| id(h) == id(&hook)) {
+            merge(base_hook, hook.clone());
+            matched = true;
+        }
+        if !matched {
+            base.push(hook);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -300,6 +663,7 @@ pub struct Config {
 pub enum RepoLocation {
     Local,
     Meta,
+    Builtin,
     Remote(String),
 }
 
@@ -310,6 +674,7 @@ impl FromStr for RepoLocation {
         match s {
             "local" => Ok(RepoLocation::Local),
             "meta" => Ok(RepoLocation::Meta),
+            "builtin" => Ok(RepoLocation::Builtin),
             _ => Ok(RepoLocation::Remote(s.to_string())),
         }
     }
@@ -330,6 +695,7 @@ impl RepoLocation {
         match self {
             RepoLocation::Local => "local",
             RepoLocation::Meta => "meta",
+            RepoLocation::Builtin => "builtin",
             RepoLocation::Remote(url) => url.as_str(),
         }
     }
@@ -341,8 +707,88 @@ impl Display for RepoLocation {
     }
 }
 
+/// How a failing hook affects the run.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The failure fails the run.
+    #[default]
+    Error,
+    /// The failure is reported, but doesn't fail the run unless `--strict` is given.
+    Warning,
+}
+
+/// How the content of the files is passed to a hook, besides their names.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PassFileContents {
+    /// The hook runs once per file, with the content of the file in the index on stdin.
+    Stdin,
+}
+
+/// Limits on the resources used by the commands of a hook.
+///
+/// On Unix, the limits apply to each process. On Windows, they apply to the command and its
+/// child processes together.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// Memory in megabytes.
+    pub memory_mb: Option<u64>,
+    /// CPU time in seconds.
+    pub cpu_seconds: Option<u64>,
+    /// Number of processes. Only supported on Windows.
+    pub processes: Option<u64>,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        self.memory_mb.is_none() && self.cpu_seconds.is_none() && self.processes.is_none()
+    }
+}
+
+/// What `prek run` does with changes that are not staged before running hooks.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StashMode {
+    /// Stash the changes while hooks run, and restore them afterwards.
+    #[default]
+    Stash,
+    /// Leave the changes in the working tree, hooks see the files as they are.
+    Keep,
+    /// Refuse to run hooks when there are changes that are not staged.
+    Refuse,
+    /// Run hooks in a temporary git worktree of the staged content, and apply their fixes to the
+    /// index and the working tree.
+    Isolate,
+}
+
+/// The operating systems a hook can be limited to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Linux,
+    Macos,
+    Windows,
+}
+
+impl Platform {
+    /// The platform prek is running on, `None` for other operating systems.
+    pub fn current() -> Option<Self> {
+        if cfg!(target_os = "linux") {
+            Some(Self::Linux)
+        } else if cfg!(target_os = "macos") {
+            Some(Self::Macos)
+        } else if cfg!(windows) {
+            Some(Self::Windows)
+        } else {
+            None
+        }
+    }
+}
+
 /// Common hook options.
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
 pub struct HookOptions {
     /// Not documented in the official docs.
     pub alias: Option<String>,
@@ -354,15 +800,24 @@ pub struct HookOptions {
     /// List of file types to run on (AND).
     /// Default is `[file]`, which matches all files.
     #[serde(deserialize_with = "deserialize_and_validate_tags", default)]
+    #[schemars(with = "Option<Vec<String>>")]
     pub types: Option<Vec<String>>,
     /// List of file types to run on (OR).
     /// Default is `[]`.
     #[serde(deserialize_with = "deserialize_and_validate_tags", default)]
+    #[schemars(with = "Option<Vec<String>>")]
     pub types_or: Option<Vec<String>>,
     /// List of file types to exclude.
     /// Default is `[]`.
     #[serde(deserialize_with = "deserialize_and_validate_tags", default)]
+    #[schemars(with = "Option<Vec<String>>")]
     pub exclude_types: Option<Vec<String>>,
+    /// Match symlinks by the type of the file they point to, broken symlinks are never matched.
+    /// Default is false, symlinks only have the `symlink` type.
+    pub follow_symlinks: Option<bool>,
+    /// Never pass symlinks to the hook, regardless of `types`.
+    /// Default is false.
+    pub skip_symlinks: Option<bool>,
     /// Not documented in the official docs.
     pub additional_dependencies: Option<Vec<String>>,
     /// Additional arguments to pass to the hook.
@@ -376,8 +831,16 @@ pub struct HookOptions {
     /// Append filenames that would be checked to the hook entry as arguments.
     /// Default is true.
     pub pass_filenames: Option<bool>,
+    /// Run the hook once per file, with the content of the file in the index on stdin, so it
+    /// checks what will be committed. Default is to read the files from the working tree.
+    pub pass_file_contents: Option<PassFileContents>,
+    /// Set `PREK_CHANGED_LINES` to the line ranges changed in each file, as JSON, so tools can
+    /// only check the changed lines. Default is false.
+    pub changed_lines: Option<bool>,
     /// A description of the hook. For metadata only.
     pub description: Option<String>,
+    /// A URL with more information about the hook. For metadata only.
+    pub homepage: Option<String>,
     /// Run the hook on a specific version of the language.
     /// Default is `default`.
     /// See <https://pre-commit.com/#overriding-language-version>.
@@ -387,6 +850,50 @@ pub struct HookOptions {
     /// This hook will execute using a single process instead of in parallel.
     /// Default is false.
     pub require_serial: Option<bool>,
+    /// Hooks in the same concurrency group never run at the same time,
+    /// including hooks run by other prek processes, e.g. `prek run --workspace`.
+    pub concurrency_group: Option<String>,
+    /// Remember the files the hook passed on, and don't run it again on them until the file
+    /// content or the hook changes. Only for hooks whose result only depends on each file.
+    /// Default is false.
+    pub cache_results: Option<bool>,
+    /// Print the output of the hook while it runs, each line prefixed with the hook id,
+    /// instead of once it finished. Default is false.
+    pub stream_output: Option<bool>,
+    /// Run the hook in a pseudo-terminal, so tools print colors and progress like they do
+    /// interactively. Only supported on Unix. Default is false.
+    pub tty: Option<bool>,
+    /// Kill the hook and its child processes if it runs longer than this many seconds,
+    /// and report it as timed out. Default is no timeout.
+    pub timeout_seconds: Option<u64>,
+    /// Limit the memory, CPU time and number of processes of the hook, and report it as over
+    /// the limit if it's killed for exceeding one. Default is no limits.
+    pub limits: Option<ResourceLimits>,
+    /// Run the hook in a sandbox, with a read-only filesystem except for its project, and the
+    /// home directory hidden. Only supported on Linux, with bubblewrap, and macOS.
+    /// Default is false.
+    pub sandbox: Option<bool>,
+    /// Allow network access in the sandbox. Default is true.
+    pub sandbox_network: Option<bool>,
+    /// Variables passed to the hook besides the curated default ones, e.g. `AWS_*`.
+    /// `*` passes all of them.
+    #[serde(alias = "extra_env_keep")]
+    pub pass_env: Option<Vec<String>>,
+    /// Environment variables set for the hook, `${VAR}` in values is replaced by the variable
+    /// of prek.
+    pub env: Option<BTreeMap<String, String>>,
+    /// The directory the hook runs in, relative to the project root. Filenames passed to the
+    /// hook are relative to it.
+    #[serde(alias = "cwd")]
+    pub working_directory: Option<String>,
+    /// Ids of hooks of the same project that must run before this hook. The hook is skipped if
+    /// one of them fails.
+    pub depends_on: Option<Vec<String>>,
+    /// The group of the hook, e.g. `format` or `lint`, to run related hooks with
+    /// `prek run --group`.
+    pub group: Option<String>,
+    /// Free-form tags of the hook, to select hooks with `prek run --tag`.
+    pub tags: Option<Vec<String>>,
     /// Select which git hook(s) to run for.
     /// Default all stages are selected.
     /// See <https://pre-commit.com/#confining-hooks-to-run-at-certain-stages>.
@@ -394,9 +901,32 @@ pub struct HookOptions {
     /// Print the output of the hook even if it passes.
     /// Default is false.
     pub verbose: Option<bool>,
+    /// A message to print after the hook output when the hook fails.
+    pub on_fail_message: Option<String>,
+    /// Whether a failure of the hook fails the run.
+    /// Default is `error`.
+    pub severity: Option<Severity>,
+    /// Skip the hook when the current branch matches any of these glob patterns.
+    pub skip_on_branches: Option<Vec<String>>,
+    /// Only run the hook when the current branch matches any of these glob patterns.
+    pub only_on_branches: Option<Vec<String>>,
+    /// Only run the hook on these operating systems.
+    /// Default is all platforms.
+    pub platforms: Option<Vec<Platform>>,
+    /// Only run the hook when any of these environment variables is set, either `NAME`
+    /// (set to a non-empty value) or `NAME=value`.
+    pub enabled_if_env: Option<Vec<String>>,
+    /// Skip the hook when any of these environment variables is set, either `NAME`
+    /// (set to a non-empty value) or `NAME=value`.
+    pub disabled_if_env: Option<Vec<String>>,
     /// The minimum version of prek required to run this hook.
     #[serde(deserialize_with = "deserialize_minimum_version", default)]
+    #[schemars(with = "Option<String>")]
     pub minimum_prek_version: Option<String>,
+    /// The minimum version of pre-commit required to run this hook.
+    #[serde(deserialize_with = "deserialize_minimum_pre_commit_version", default)]
+    #[schemars(with = "Option<String>")]
+    pub minimum_pre_commit_version: Option<String>,
 }
 
 impl HookOptions {
@@ -418,18 +948,45 @@ impl HookOptions {
             types,
             types_or,
             exclude_types,
+            follow_symlinks,
+            skip_symlinks,
             additional_dependencies,
             args,
             always_run,
             fail_fast,
             pass_filenames,
+            pass_file_contents,
+            changed_lines,
             description,
+            homepage,
             language_version,
             log_file,
             require_serial,
+            concurrency_group,
+            cache_results,
+            stream_output,
+            tty,
+            timeout_seconds,
+            limits,
+            sandbox,
+            sandbox_network,
+            pass_env,
+            env,
+            working_directory,
+            depends_on,
+            group,
+            tags,
             stages,
             verbose,
+            on_fail_message,
+            severity,
+            skip_on_branches,
+            only_on_branches,
+            platforms,
+            enabled_if_env,
+            disabled_if_env,
             minimum_prek_version,
+            minimum_pre_commit_version,
         );
     }
 }
@@ -437,7 +994,7 @@ impl HookOptions {
 /// A remote hook in the configuration file.
 ///
 /// All keys in manifest hook dict are valid in a config hook dict, but are optional.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct RemoteHook {
     /// The id of the hook.
@@ -452,12 +1009,28 @@ pub struct RemoteHook {
     pub options: HookOptions,
 }
 
+impl RemoteHook {
+    /// Override this hook with the keys set in `other`, lists like `args` are replaced.
+    fn merge(&mut self, other: RemoteHook) {
+        if other.name.is_some() {
+            self.name = other.name;
+        }
+        if other.entry.is_some() {
+            self.entry = other.entry;
+        }
+        if other.language.is_some() {
+            self.language = other.language;
+        }
+        self.options.update(&other.options);
+    }
+}
+
 /// A local hook in the configuration file.
 ///
 /// It's the same as the manifest hook definition.
 pub type LocalHook = ManifestHook;
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+#[derive(Debug, Copy, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum MetaHookID {
     CheckHooksApply,
@@ -570,12 +1143,253 @@ impl<'de> Deserialize<'de> for MetaHook {
     }
 }
 
+impl JsonSchema for MetaHook {
+    fn schema_name() -> Cow<'static, str> {
+        "MetaHook".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        /// A meta hook predefined in pre-commit.
+        #[derive(JsonSchema)]
+        #[serde(rename_all = "snake_case")]
+        struct MetaHook {
+            /// The id of the meta hook.
+            id: MetaHookID,
+            /// Override the name of the hook.
+            name: Option<String>,
+            #[serde(flatten)]
+            options: HookOptions,
+        }
+
+        MetaHook::json_schema(generator)
+    }
+}
+
 impl From<MetaHook> for ManifestHook {
     fn from(hook: MetaHook) -> Self {
         hook.0
     }
 }
 
+#[derive(Debug, Copy, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuiltinHookID {
+    CheckAddedLargeFiles,
+    CheckJson,
+    CheckMergeConflict,
+    CheckToml,
+    CheckYaml,
+    EndOfFileFixer,
+    FixByteOrderMarker,
+    MixedLineEnding,
+    TrailingWhitespace,
+}
+
+impl Display for BuiltinHookID {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            BuiltinHookID::CheckAddedLargeFiles => "check-added-large-files",
+            BuiltinHookID::CheckJson => "check-json",
+            BuiltinHookID::CheckMergeConflict => "check-merge-conflict",
+            BuiltinHookID::CheckToml => "check-toml",
+            BuiltinHookID::CheckYaml => "check-yaml",
+            BuiltinHookID::EndOfFileFixer => "end-of-file-fixer",
+            BuiltinHookID::FixByteOrderMarker => "fix-byte-order-marker",
+            BuiltinHookID::MixedLineEnding => "mixed-line-ending",
+            BuiltinHookID::TrailingWhitespace => "trailing-whitespace",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for BuiltinHookID {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "check-added-large-files" => Ok(BuiltinHookID::CheckAddedLargeFiles),
+            "check-json" => Ok(BuiltinHookID::CheckJson),
+            "check-merge-conflict" => Ok(BuiltinHookID::CheckMergeConflict),
+            "check-toml" => Ok(BuiltinHookID::CheckToml),
+            "check-yaml" => Ok(BuiltinHookID::CheckYaml),
+            "end-of-file-fixer" => Ok(BuiltinHookID::EndOfFileFixer),
+            "fix-byte-order-marker" => Ok(BuiltinHookID::FixByteOrderMarker),
+            "mixed-line-ending" => Ok(BuiltinHookID::MixedLineEnding),
+            "trailing-whitespace" => Ok(BuiltinHookID::TrailingWhitespace),
+            _ => Err(()),
+        }
+    }
+}
+
+impl BuiltinHookID {
+    /// The definition of the hook, the same as in the manifest of `pre-commit-hooks`.
+    fn manifest_hook(self) -> ManifestHook {
+        let all_stages = || Some(vec![Stage::PreCommit, Stage::PrePush, Stage::Manual]);
+        let types = |tag: &str| Some(vec![tag.to_string()]);
+        let (name, entry, description, options) = match self {
+            BuiltinHookID::CheckAddedLargeFiles => (
+                "check for added large files",
+                "check-added-large-files",
+                "prevents giant files from being committed.",
+                HookOptions {
+                    stages: all_stages(),
+                    ..Default::default()
+                },
+            ),
+            BuiltinHookID::CheckJson => (
+                "check json",
+                "check-json",
+                "checks json files for parseable syntax.",
+                HookOptions {
+                    types: types("json"),
+                    ..Default::default()
+                },
+            ),
+            BuiltinHookID::CheckMergeConflict => (
+                "check for merge conflicts",
+                "check-merge-conflict",
+                "checks for files that contain merge conflict strings.",
+                HookOptions {
+                    types: types("text"),
+                    ..Default::default()
+                },
+            ),
+            BuiltinHookID::CheckToml => (
+                "check toml",
+                "check-toml",
+                "checks toml files for parseable syntax.",
+                HookOptions {
+                    types: types("toml"),
+                    ..Default::default()
+                },
+            ),
+            BuiltinHookID::CheckYaml => (
+                "check yaml",
+                "check-yaml",
+                "checks yaml files for parseable syntax.",
+                HookOptions {
+                    types: types("yaml"),
+                    ..Default::default()
+                },
+            ),
+            BuiltinHookID::EndOfFileFixer => (
+                "fix end of files",
+                "end-of-file-fixer",
+                "ensures that a file is either empty, or ends with one newline.",
+                HookOptions {
+                    types: types("text"),
+                    stages: all_stages(),
+                    ..Default::default()
+                },
+            ),
+            BuiltinHookID::FixByteOrderMarker => (
+                "fix utf-8 byte order marker",
+                "fix-byte-order-marker",
+                "removes utf-8 byte order marker.",
+                HookOptions {
+                    types: types("text"),
+                    ..Default::default()
+                },
+            ),
+            BuiltinHookID::MixedLineEnding => (
+                "mixed line ending",
+                "mixed-line-ending",
+                "replaces or checks mixed line ending.",
+                HookOptions {
+                    types: types("text"),
+                    ..Default::default()
+                },
+            ),
+            BuiltinHookID::TrailingWhitespace => (
+                "trim trailing whitespace",
+                "trailing-whitespace-fixer",
+                "trims trailing whitespace.",
+                HookOptions {
+                    types: types("text"),
+                    stages: all_stages(),
+                    ..Default::default()
+                },
+            ),
+        };
+
+        ManifestHook {
+            id: self.to_string(),
+            name: name.to_string(),
+            language: Language::System,
+            entry: entry.to_string(),
+            options: HookOptions {
+                description: Some(description.to_string()),
+                ..options
+            },
+        }
+    }
+}
+
+/// A hook built into prek, a native implementation of a hook of `pre-commit-hooks`.
+///
+/// It's the same as the manifest hook definition but with only the implemented ids allowed,
+/// so it runs without cloning a repo or installing an environment.
+#[derive(Debug, Clone)]
+pub struct BuiltinHook(pub(crate) ManifestHook);
+
+impl<'de> Deserialize<'de> for BuiltinHook {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hook = RemoteHook::deserialize(deserializer)?;
+
+        let id = BuiltinHookID::from_str(&hook.id)
+            .map_err(|()| serde::de::Error::custom("Unknown builtin hook id"))?;
+        if hook.language.is_some_and(|l| l != Language::System) {
+            return Err(serde::de::Error::custom(
+                "language must be system for builtin hook",
+            ));
+        }
+        if hook.entry.is_some() {
+            return Err(serde::de::Error::custom(
+                "entry is not allowed for builtin hook",
+            ));
+        }
+
+        let mut defaults = id.manifest_hook();
+        if let Some(name) = hook.name {
+            defaults.name = name;
+        }
+        defaults.options.update(&hook.options);
+
+        Ok(BuiltinHook(defaults))
+    }
+}
+
+impl JsonSchema for BuiltinHook {
+    fn schema_name() -> Cow<'static, str> {
+        "BuiltinHook".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        /// A hook built into prek, a native implementation of a hook of `pre-commit-hooks`.
+        #[derive(JsonSchema)]
+        #[serde(rename_all = "snake_case")]
+        struct BuiltinHook {
+            /// The id of the builtin hook.
+            id: BuiltinHookID,
+            /// Override the name of the hook.
+            name: Option<String>,
+            #[serde(flatten)]
+            options: HookOptions,
+        }
+
+        BuiltinHook::json_schema(generator)
+    }
+}
+
+impl From<BuiltinHook> for ManifestHook {
+    fn from(hook: BuiltinHook) -> Self {
+        hook.0
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct RemoteRepo {
     pub repo: String,
@@ -627,11 +1441,23 @@ impl Display for MetaRepo {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct BuiltinRepo {
+    pub hooks: Vec<BuiltinHook>,
+}
+
+impl Display for BuiltinRepo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("builtin")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Repo {
     Remote(RemoteRepo),
     Local(LocalRepo),
     Meta(MetaRepo),
+    Builtin(BuiltinRepo),
 }
 
 impl<'de> Deserialize<'de> for Repo {
@@ -685,11 +1511,91 @@ impl<'de> Deserialize<'de> for Repo {
                     .map_err(|e| serde::de::Error::custom(format!("Invalid meta repo: {e}")))?;
                 Ok(Repo::Meta(MetaRepo { hooks }))
             }
+            RepoLocation::Builtin => {
+                #[derive(Deserialize)]
+                #[serde(deny_unknown_fields)]
+                struct _BuiltinRepo {
+                    hooks: Vec<BuiltinHook>,
+                }
+                let _BuiltinRepo { hooks } = _BuiltinRepo::deserialize(rest)
+                    .map_err(|e| serde::de::Error::custom(format!("Invalid builtin repo: {e}")))?;
+                Ok(Repo::Builtin(BuiltinRepo { hooks }))
+            }
+        }
+    }
+}
+
+impl JsonSchema for Repo {
+    fn schema_name() -> Cow<'static, str> {
+        "Repo".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        #[derive(JsonSchema)]
+        #[serde(rename_all = "lowercase")]
+        enum Local {
+            Local,
+        }
+
+        #[derive(JsonSchema)]
+        #[serde(rename_all = "lowercase")]
+        enum Meta {
+            Meta,
+        }
+
+        #[derive(JsonSchema)]
+        #[serde(rename_all = "lowercase")]
+        enum Builtin {
+            Builtin,
+        }
+
+        /// A repository of hooks, cloned from its URL.
+        #[derive(JsonSchema)]
+        #[serde(deny_unknown_fields)]
+        struct RemoteRepo {
+            /// The URL of the repository to clone.
+            repo: String,
+            /// The revision to clone, a tag or a commit SHA.
+            rev: String,
+            hooks: Vec<RemoteHook>,
         }
+
+        /// Hooks defined in the config.
+        #[derive(JsonSchema)]
+        #[serde(deny_unknown_fields)]
+        struct LocalRepo {
+            repo: Local,
+            hooks: Vec<LocalHook>,
+        }
+
+        /// Hooks predefined in pre-commit, to check the config.
+        #[derive(JsonSchema)]
+        #[serde(deny_unknown_fields)]
+        struct MetaRepo {
+            repo: Meta,
+            hooks: Vec<MetaHook>,
+        }
+
+        /// Hooks built into prek, they need no repo checkout or environment.
+        #[derive(JsonSchema)]
+        #[serde(deny_unknown_fields)]
+        struct BuiltinRepo {
+            repo: Builtin,
+            hooks: Vec<BuiltinHook>,
+        }
+
+        json_schema!({
+            "anyOf": [
+                generator.subschema_for::<LocalRepo>(),
+                generator.subschema_for::<MetaRepo>(),
+                generator.subschema_for::<BuiltinRepo>(),
+                generator.subschema_for::<RemoteRepo>(),
+            ],
+        })
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct ManifestHook {
     /// The id of the hook.
@@ -704,7 +1610,7 @@ pub struct ManifestHook {
     pub options: HookOptions,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 #[serde(transparent)]
 pub struct Manifest {
@@ -721,10 +1627,196 @@ pub enum Error {
 
     #[error("Failed to parse `{0}`")]
     Yaml(String, #[source] serde_yaml::Error),
+
+    #[error("Configs extend each other: {0}")]
+    CircularExtends(String),
+
+    #[error("Failed to fetch the shared config `{0}`")]
+    Fetch(String, #[source] anyhow::Error),
+}
+
+/// A config published outside the repository, referenced in `extends`.
+#[derive(Debug, PartialEq, Eq)]
+enum SharedConfig {
+    /// An HTTP(S) URL of the config file.
+    Url(String),
+    /// A config file inside a git repo, `<repo>@<rev>:<path>`.
+    Repo {
+        repo: String,
+        rev: String,
+        path: String,
+    },
+}
+
+impl SharedConfig {
+    /// Parse a shared config reference, returns `None` for a local path.
+    fn parse(reference: &str) -> Option<Self> {
+        let repo_reference = reference
+            .rsplit_once('@')
+            .and_then(|(repo, rest)| Some((repo, rest.split_once(':')?)))
+            .filter(|(repo, (rev, path))| {
+                !repo.is_empty() && !rev.is_empty() && !rev.contains('/') && !path.is_empty()
+            });
+        if let Some((repo, (rev, path))) = repo_reference {
+            // `github.com/org/repo` is short for `https://github.com/org/repo`.
+            let host = repo.split('/').next().unwrap_or_default();
+            let repo = if host.contains('.') && !host.starts_with('.') && !host.contains(':') {
+                format!("https://{repo}")
+            } else {
+                repo.to_string()
+            };
+            return Some(Self::Repo {
+                repo,
+                rev: rev.to_string(),
+                path: path.to_string(),
+            });
+        }
+
+        if reference.starts_with("https://") || reference.starts_with("http://") {
+            return Some(Self::Url(reference.to_string()));
+        }
+
+        None
+    }
+
+    /// Fetch the shared config into the store, returns the path to the config file.
+    fn fetch(&self) -> Result<PathBuf> {
+        let store = STORE.as_ref()?;
+
+        // Configs are read synchronously, run the fetch on its own runtime.
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| -> Result<PathBuf> {
+                    tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()?
+                        .block_on(self.fetch_async(store))
+                })
+                .join()
+                .expect("Failed to fetch the shared config")
+        })
+    }
+
+    async fn fetch_async(&self, store: &Store) -> Result<PathBuf> {
+        let url = match self {
+            Self::Url(url) => url,
+            Self::Repo { repo, .. } => repo,
+        };
+        if url.starts_with("http://") {
+            anyhow::bail!("Shared configs must be fetched over HTTPS");
+        }
+
+        match self {
+            Self::Url(url) => {
+                if OFFLINE.load(Ordering::Relaxed) {
+                    return cached(store.config_dir(url)).map(|dir| dir.join(CONFIG_FILE));
+                }
+                let _lock = store.lock_async().await?;
+                Ok(store.download_config(url).await?)
+            }
+            Self::Repo { repo, rev, path } => {
+                let repo = RemoteRepo {
+                    repo: repo.clone(),
+                    rev: rev.clone(),
+                    hooks: vec![],
+                };
+                let repo_path = if OFFLINE.load(Ordering::Relaxed) {
+                    cached(store.repo_path(&repo))?
+                } else {
+                    let _lock = store.lock_async().await?;
+                    store.clone_repo(&repo, None).await?
+                };
+
+                // The path must not escape the repo, e.g. with `../` or a symlink.
+                let config = dunce::canonicalize(repo_path.join(path))?;
+                if !config.starts_with(dunce::canonicalize(&repo_path)?) {
+                    anyhow::bail!("`{path}` is outside of the repo");
+                }
+                Ok(config)
+            }
+        }
+    }
+}
+
+/// Only use the shared configs already in the store, set while completing the command line.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Never fetch shared configs, the ones not in the store fail to load.
+pub(crate) fn set_offline() {
+    OFFLINE.store(true, Ordering::Relaxed);
 }
 
-/// Read the configuration file from the given path.
+/// Returns `dir` if a shared config was fetched into it.
+fn cached(dir: PathBuf) -> Result<PathBuf> {
+    if dir.join(".prek-repo.json").try_exists()? {
+        Ok(dir)
+    } else {
+        anyhow::bail!("The shared config is not in the cache")
+    }
+}
+
+/// Read the configuration file from the given path, merged on top of the configs it `extends`.
+#[instrument(level = "trace", skip_all, fields(path = %path.display()))]
 pub fn read_config(path: &Path) -> Result<Config, Error> {
+    read_extended_config(path, &mut Vec::new())
+}
+
+fn read_extended_config(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Config, Error> {
+    let config = read_raw_config(path)?;
+    if config.extends.is_empty() {
+        return Ok(config);
+    }
+
+    let canonical = dunce::canonicalize(path)?;
+    if let Some(start) = stack.iter().position(|p| *p == canonical) {
+        let chain = stack[start..]
+            .iter()
+            .chain([&canonical])
+            .map(|p| format!("`{}`", p.user_display()))
+            .join(" -> ");
+        return Err(Error::CircularExtends(chain));
+    }
+    stack.push(canonical);
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut base: Option<Config> = None;
+    for extends in &config.extends {
+        let extends_path = match SharedConfig::parse(extends) {
+            Some(shared) => shared
+                .fetch()
+                .map_err(|e| Error::Fetch(extends.clone(), e))?,
+            None => dir.join(extends),
+        };
+        let extended = read_extended_config(&extends_path, stack)?;
+        base = Some(match base {
+            Some(base) => extended.merge_onto(base),
+            None => extended,
+        });
+    }
+
+    stack.pop();
+
+    Ok(match base {
+        Some(base) => config.merge_onto(base),
+        None => config,
+    })
+}
+
+/// Read the configuration file from the given path, without resolving `extends`.
+///
+/// Problems that don't prevent using the config are warned about once, configs may be read
+/// several times in a run.
+pub fn read_raw_config(path: &Path) -> Result<Config, Error> {
+    let (config, lints) = lint_raw_config(path)?;
+    for lint in lints {
+        warn_user_once!("{lint}");
+    }
+    Ok(config)
+}
+
+/// Read the configuration file from the given path, without resolving `extends`, with the
+/// problems that don't prevent using it, e.g. unexpected keys.
+pub fn lint_raw_config(path: &Path) -> Result<(Config, Vec<String>), Error> {
     let content = match fs_err::read_to_string(path) {
         Ok(content) => content,
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
@@ -733,28 +1825,58 @@ pub fn read_config(path: &Path) -> Result<Config, Error> {
         Err(e) => return Err(e.into()),
     };
 
-    let expected_unused = ["minimum_pre_commit_version"];
-
     let deserializer = serde_yaml::Deserializer::from_str(&content);
     let mut unused = Vec::new();
     let config: Config = serde_ignored::deserialize(deserializer, |path| {
-        let key = path.to_string();
-        if !expected_unused.contains(&key.as_str()) {
-            unused.push(key);
-        }
+        unused.push(path.to_string());
     })
     .map_err(|e| Error::Yaml(path.user_display().to_string(), e))?;
 
+    let mut lints = Vec::new();
     if !unused.is_empty() {
-        warn_user!(
+        lints.push(format!(
             "Ignored unexpected keys in `{}`: {}",
             path.display().cyan(),
-            unused
-                .into_iter()
-                .map(|key| format!("`{}`", key.yellow()))
-                .collect::<Vec<_>>()
-                .join(", ")
-        );
+            format_unexpected_keys(&unused, struct_keys::<Config>())
+        ));
+    }
+
+    // Hook options are flattened, so their unexpected keys are not reported by serde.
+    let hook_keys = ["id", "name", "entry", "language"]
+        .into_iter()
+        .chain(struct_keys::<HookOptions>().iter().copied())
+        .collect::<Vec<_>>();
+    let value: serde_yaml::Value = serde_yaml::from_str(&content).unwrap_or_default();
+    let hooks = value
+        .get("repos")
+        .and_then(serde_yaml::Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(|repo| repo.get("hooks")?.as_sequence())
+        .flatten()
+        .filter_map(serde_yaml::Value::as_mapping);
+    for hook in hooks {
+        let mut options = hook.clone();
+        for key in ["id", "name", "entry", "language"] {
+            options.remove(key);
+        }
+        let mut unused = Vec::new();
+        let _: Result<HookOptions, _> =
+            serde_ignored::deserialize(serde_yaml::Value::Mapping(options), |path| {
+                unused.push(path.to_string());
+            });
+        if !unused.is_empty() {
+            let id = hook
+                .get("id")
+                .and_then(serde_yaml::Value::as_str)
+                .unwrap_or_default();
+            lints.push(format!(
+                "Ignored unexpected keys in hook `{}` of `{}`: {}",
+                id.cyan(),
+                path.display().cyan(),
+                format_unexpected_keys(&unused, &hook_keys)
+            ));
+        }
     }
 
     // Check for mutable revs and warn the user.
@@ -780,9 +1902,7 @@ pub fn read_config(path: &Path) -> Result<Config, Error> {
             .collect::<Vec<_>>()
             .join("\n");
 
-        warn_user!(
-            "{}",
-            indoc::formatdoc! { r#"
+        lints.push(indoc::formatdoc! { r#"
             The following repos have mutable `rev` fields (moving tag / branch):
             {}
             Mutable references are never updated after first install and are not supported.
@@ -790,11 +1910,220 @@ pub fn read_config(path: &Path) -> Result<Config, Error> {
             Hint: `prek autoupdate` often fixes this",
             "#,
             msg
+        });
+    }
+
+    lints.extend(lint_patterns(&config));
+    lints.extend(lint_unused_defaults(&config));
+
+    Ok((config, lints))
+}
+
+/// The ids and options of the hooks of a config.
+fn hook_options(config: &Config) -> impl Iterator<Item = (&str, &HookOptions)> {
+    config
+        .repos
+        .iter()
+        .flat_map(|repo| -> Vec<(&str, &HookOptions)> {
+            match repo {
+                Repo::Remote(repo) => repo
+                    .hooks
+                    .iter()
+                    .map(|hook| (hook.id.as_str(), &hook.options))
+                    .collect(),
+                Repo::Local(repo) => repo
+                    .hooks
+                    .iter()
+                    .map(|hook| (hook.id.as_str(), &hook.options))
+                    .collect(),
+                Repo::Meta(repo) => repo
+                    .hooks
+                    .iter()
+                    .map(|hook| (hook.0.id.as_str(), &hook.0.options))
+                    .collect(),
+                Repo::Builtin(repo) => repo
+                    .hooks
+                    .iter()
+                    .map(|hook| (hook.0.id.as_str(), &hook.0.options))
+                    .collect(),
             }
-        );
+        })
+}
+
+/// Warn about `files` and `exclude` patterns that can't match any path.
+fn lint_patterns(config: &Config) -> Vec<String> {
+    let mut lints = Vec::new();
+    let mut check = |key: &str, pattern: Option<&SerdeRegex>, hook: Option<&str>| {
+        let Some(pattern) = pattern else {
+            return;
+        };
+        if let Some(reason) = never_matches(pattern.as_str()) {
+            let owner = match hook {
+                Some(id) => format!(" of hook `{}`", id.cyan()),
+                None => String::new(),
+            };
+            lints.push(format!(
+                "The `{key}` pattern `{}`{owner} never matches: {reason}",
+                pattern.as_str().yellow()
+            ));
+        }
+    };
+    check("files", config.files.as_ref(), None);
+    check("exclude", config.exclude.as_ref(), None);
+    for (id, options) in hook_options(config) {
+        check("files", options.files.as_ref(), Some(id));
+        check("exclude", options.exclude.as_ref(), Some(id));
     }
+    lints
+}
 
-    Ok(config)
+/// Why a pattern can't match a path relative to the project root, if it can't.
+fn never_matches(pattern: &str) -> Option<&'static str> {
+    if pattern.starts_with("^/") || pattern.starts_with("^\\./") {
+        return Some("paths are relative to the project root, without a leading `/` or `./`");
+    }
+    // Flags like `(?m)` change the meaning of anchors.
+    if pattern.contains("(?") {
+        return None;
+    }
+
+    let mut chars = pattern.chars().peekable();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if chars.next() == Some('\\') && !in_class {
+                    return Some("paths are separated by `/`, also on Windows");
+                }
+            }
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '$' if !in_class => {
+                if chars
+                    .peek()
+                    .is_some_and(|next| !matches!(next, '|' | ')' | '$'))
+                {
+                    return Some("nothing can follow the end of the path `$`");
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Warn about top-level defaults that don't apply to any hook.
+fn lint_unused_defaults(config: &Config) -> Vec<String> {
+    let mut lints = Vec::new();
+    if config.default_stages.is_some()
+        && hook_options(config).next().is_some()
+        && hook_options(config).all(|(_, options)| options.stages.is_some())
+    {
+        lints.push("`default_stages` is not used, all hooks set `stages`".to_string());
+    }
+
+    // The languages of remote hooks are only known from their manifests.
+    if let Some(versions) = &config.default_language_version
+        && config
+            .repos
+            .iter()
+            .all(|repo| !matches!(repo, Repo::Remote(_)))
+    {
+        let languages = config
+            .repos
+            .iter()
+            .flat_map(|repo| -> Vec<Language> {
+                match repo {
+                    Repo::Local(repo) => repo.hooks.iter().map(|hook| hook.language).collect(),
+                    Repo::Meta(repo) => repo.hooks.iter().map(|hook| hook.0.language).collect(),
+                    Repo::Builtin(repo) => repo.hooks.iter().map(|hook| hook.0.language).collect(),
+                    Repo::Remote(_) => Vec::new(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut unused = versions
+            .keys()
+            .filter(|language| !languages.contains(language))
+            .map(Language::as_str)
+            .collect::<Vec<_>>();
+        unused.sort_unstable();
+        if !unused.is_empty() {
+            lints.push(format!(
+                "`default_language_version` is not used for {}, no hook uses the language",
+                unused
+                    .iter()
+                    .map(|language| format!("`{}`", language.cyan()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+    }
+    lints
+}
+
+/// Format unexpected keys, with the known key that is likely meant for typos.
+fn format_unexpected_keys(unused: &[String], known: &[&str]) -> String {
+    unused
+        .iter()
+        .map(|path| {
+            let key = path.rsplit('.').next().unwrap_or(path);
+            match closest_key(key, known) {
+                Some(closest) => {
+                    format!("`{}` (did you mean `{}`?)", path.yellow(), closest.cyan())
+                }
+                None => format!("`{}`", path.yellow()),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The known key closest to `key`, if `key` is likely a typo of it.
+fn closest_key<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    let max_distance = (key.chars().count() / 4).max(1);
+    known
+        .iter()
+        .map(|known| (levenshtein::levenshtein(key, known), *known))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, known)| known)
+}
+
+/// The keys of a struct deserialized by serde, read from what its `Deserialize` implementation
+/// asks for.
+fn struct_keys<T: serde::de::DeserializeOwned>() -> &'static [&'static str] {
+    struct Keys<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for Keys<'_> {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: serde::de::Visitor<'de>>(
+            self,
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            Err(serde::de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: serde::de::Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(serde::de::Error::custom("only the keys are read"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    let mut keys: &'static [&'static str] = &[];
+    let _ = T::deserialize(Keys(&mut keys));
+    keys
 }
 
 /// Read the manifest file from the given path.
@@ -805,6 +2134,40 @@ pub fn read_manifest(path: &Path) -> Result<Manifest, Error> {
     Ok(manifest)
 }
 
+/// Deserializes `extends`, which is a single path or a list of paths.
+fn deserialize_extends<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Extends {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Extends::deserialize(deserializer)? {
+        Extends::One(path) => vec![path],
+        Extends::Many(paths) => paths,
+    })
+}
+
+/// The schema of `extends`, which is a single path or a list of paths.
+fn extends_schema(_generator: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "anyOf": [
+            { "type": "string" },
+            { "type": "array", "items": { "type": "string" } },
+        ],
+    })
+}
+
+/// Check if a `rev` is a full SHA-1 or SHA-256 commit hash, abbreviated hashes can become
+/// ambiguous.
+pub(crate) fn is_full_sha(rev: &str) -> bool {
+    matches!(rev.len(), 40 | 64) && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
 /// Check if a string looks like a git SHA
 fn looks_like_sha(s: &str) -> bool {
     static SHA_RE: OnceLock<Regex> = OnceLock::new();
@@ -834,6 +2197,68 @@ where
 mod tests {
     use super::*;
 
+    #[test]
+    fn legacy_stage_names() {
+        let stages: Vec<Stage> =
+            serde_yaml::from_str("[commit, merge-commit, push, pre-push, manual]").unwrap();
+        assert_eq!(
+            stages,
+            vec![
+                Stage::PreCommit,
+                Stage::PreMergeCommit,
+                Stage::PrePush,
+                Stage::PrePush,
+                Stage::Manual,
+            ]
+        );
+
+        let err = serde_yaml::from_str::<Stage>("pre-commmit").unwrap_err();
+        assert!(err.to_string().starts_with("unknown stage `pre-commmit`"));
+    }
+
+    #[test]
+    fn parse_shared_config() {
+        assert_eq!(
+            SharedConfig::parse("github.com/org/standards@v3:precommit.yaml"),
+            Some(SharedConfig::Repo {
+                repo: "https://github.com/org/standards".to_string(),
+                rev: "v3".to_string(),
+                path: "precommit.yaml".to_string(),
+            })
+        );
+        assert_eq!(
+            SharedConfig::parse("git@github.com:org/standards.git@v3:ci/precommit.yaml"),
+            Some(SharedConfig::Repo {
+                repo: "git@github.com:org/standards.git".to_string(),
+                rev: "v3".to_string(),
+                path: "ci/precommit.yaml".to_string(),
+            })
+        );
+        assert_eq!(
+            SharedConfig::parse("https://example.com/v3/.pre-commit-config.yaml"),
+            Some(SharedConfig::Url(
+                "https://example.com/v3/.pre-commit-config.yaml".to_string()
+            ))
+        );
+        assert_eq!(
+            SharedConfig::parse("../shared/.pre-commit-config.yaml"),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_file_size() {
+        assert_eq!("512".parse(), Ok(FileSize(512)));
+        assert_eq!("500KB".parse(), Ok(FileSize(500 * 1024)));
+        assert_eq!("2 mb".parse(), Ok(FileSize(2 * 1024 * 1024)));
+        assert_eq!("1GB".parse(), Ok(FileSize(1024 * 1024 * 1024)));
+        assert!("2.5MB".parse::<FileSize>().is_err());
+        assert!("MB".parse::<FileSize>().is_err());
+
+        assert_eq!(FileSize(2 * 1024 * 1024).to_string(), "2MB");
+        assert_eq!(FileSize(1536).to_string(), "1536B");
+    }
+
     #[test]
     fn parse_repos() {
         // Local hook should not have `rev`
@@ -866,18 +2291,45 @@ mod tests {
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
+                                        follow_symlinks: None,
+                                        skip_symlinks: None,
                                         additional_dependencies: None,
                                         args: None,
                                         always_run: None,
                                         fail_fast: None,
                                         pass_filenames: None,
+                                        pass_file_contents: None,
+                                        changed_lines: None,
                                         description: None,
+                                        homepage: None,
                                         language_version: None,
                                         log_file: None,
                                         require_serial: None,
+                                        concurrency_group: None,
+                                        cache_results: None,
+                                        stream_output: None,
+                                        tty: None,
+                                        timeout_seconds: None,
+                                        limits: None,
+                                        sandbox: None,
+                                        sandbox_network: None,
+                                        pass_env: None,
+                                        env: None,
+                                        working_directory: None,
+                                        depends_on: None,
+                                        group: None,
+                                        tags: None,
                                         stages: None,
                                         verbose: None,
+                                        on_fail_message: None,
+                                        severity: None,
+                                        skip_on_branches: None,
+                                        only_on_branches: None,
+                                        platforms: None,
+                                        enabled_if_env: None,
+                                        disabled_if_env: None,
                                         minimum_prek_version: None,
+                                        minimum_pre_commit_version: None,
                                     },
                                 },
                             ],
@@ -891,7 +2343,18 @@ mod tests {
                 exclude: None,
                 fail_fast: None,
                 minimum_prek_version: None,
+                minimum_pre_commit_version: None,
                 ci: None,
+                extends: [],
+                max_file_size: None,
+                skip_binary_files: None,
+                skip_lfs_files: None,
+                stash_mode: None,
+                auto_stage_fixes: None,
+                require_pinned_revs: None,
+                default_timeout_seconds: None,
+                pass_env: None,
+                suppress_warnings: None,
             },
         )
         "#);
@@ -943,18 +2406,45 @@ mod tests {
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
+                                        follow_symlinks: None,
+                                        skip_symlinks: None,
                                         additional_dependencies: None,
                                         args: None,
                                         always_run: None,
                                         fail_fast: None,
                                         pass_filenames: None,
+                                        pass_file_contents: None,
+                                        changed_lines: None,
                                         description: None,
+                                        homepage: None,
                                         language_version: None,
                                         log_file: None,
                                         require_serial: None,
+                                        concurrency_group: None,
+                                        cache_results: None,
+                                        stream_output: None,
+                                        tty: None,
+                                        timeout_seconds: None,
+                                        limits: None,
+                                        sandbox: None,
+                                        sandbox_network: None,
+                                        pass_env: None,
+                                        env: None,
+                                        working_directory: None,
+                                        depends_on: None,
+                                        group: None,
+                                        tags: None,
                                         stages: None,
                                         verbose: None,
+                                        on_fail_message: None,
+                                        severity: None,
+                                        skip_on_branches: None,
+                                        only_on_branches: None,
+                                        platforms: None,
+                                        enabled_if_env: None,
+                                        disabled_if_env: None,
                                         minimum_prek_version: None,
+                                        minimum_pre_commit_version: None,
                                     },
                                 },
                             ],
@@ -968,7 +2458,18 @@ mod tests {
                 exclude: None,
                 fail_fast: None,
                 minimum_prek_version: None,
+                minimum_pre_commit_version: None,
                 ci: None,
+                extends: [],
+                max_file_size: None,
+                skip_binary_files: None,
+                skip_lfs_files: None,
+                stash_mode: None,
+                auto_stage_fixes: None,
+                require_pinned_revs: None,
+                default_timeout_seconds: None,
+                pass_env: None,
+                suppress_warnings: None,
             },
         )
         "#);
@@ -1052,18 +2553,45 @@ mod tests {
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
+                                        follow_symlinks: None,
+                                        skip_symlinks: None,
                                         additional_dependencies: None,
                                         args: None,
                                         always_run: None,
                                         fail_fast: None,
                                         pass_filenames: None,
+                                        pass_file_contents: None,
+                                        changed_lines: None,
                                         description: None,
+                                        homepage: None,
                                         language_version: None,
                                         log_file: None,
                                         require_serial: None,
+                                        concurrency_group: None,
+                                        cache_results: None,
+                                        stream_output: None,
+                                        tty: None,
+                                        timeout_seconds: None,
+                                        limits: None,
+                                        sandbox: None,
+                                        sandbox_network: None,
+                                        pass_env: None,
+                                        env: None,
+                                        working_directory: None,
+                                        depends_on: None,
+                                        group: None,
+                                        tags: None,
                                         stages: None,
                                         verbose: None,
+                                        on_fail_message: None,
+                                        severity: None,
+                                        skip_on_branches: None,
+                                        only_on_branches: None,
+                                        platforms: None,
+                                        enabled_if_env: None,
+                                        disabled_if_env: None,
                                         minimum_prek_version: None,
+                                        minimum_pre_commit_version: None,
                                     },
                                 },
                             ],
@@ -1077,7 +2605,18 @@ mod tests {
                 exclude: None,
                 fail_fast: None,
                 minimum_prek_version: None,
+                minimum_pre_commit_version: None,
                 ci: None,
+                extends: [],
+                max_file_size: None,
+                skip_binary_files: None,
+                skip_lfs_files: None,
+                stash_mode: None,
+                auto_stage_fixes: None,
+                require_pinned_revs: None,
+                default_timeout_seconds: None,
+                pass_env: None,
+                suppress_warnings: None,
             },
         )
         "#);
@@ -1179,18 +2718,45 @@ mod tests {
                                             types: None,
                                             types_or: None,
                                             exclude_types: None,
+                                            follow_symlinks: None,
+                                            skip_symlinks: None,
                                             additional_dependencies: None,
                                             args: None,
                                             always_run: None,
                                             fail_fast: None,
                                             pass_filenames: None,
+                                            pass_file_contents: None,
+                                            changed_lines: None,
                                             description: None,
+                                            homepage: None,
                                             language_version: None,
                                             log_file: None,
                                             require_serial: None,
+                                            concurrency_group: None,
+                                            cache_results: None,
+                                            stream_output: None,
+                                            tty: None,
+                                            timeout_seconds: None,
+                                            limits: None,
+                                            sandbox: None,
+                                            sandbox_network: None,
+                                            pass_env: None,
+                                            env: None,
+                                            working_directory: None,
+                                            depends_on: None,
+                                            group: None,
+                                            tags: None,
                                             stages: None,
                                             verbose: None,
+                                            on_fail_message: None,
+                                            severity: None,
+                                            skip_on_branches: None,
+                                            only_on_branches: None,
+                                            platforms: None,
+                                            enabled_if_env: None,
+                                            disabled_if_env: None,
                                             minimum_prek_version: None,
+                                            minimum_pre_commit_version: None,
                                         },
                                     },
                                 ),
@@ -1211,18 +2777,45 @@ mod tests {
                                             types: None,
                                             types_or: None,
                                             exclude_types: None,
+                                            follow_symlinks: None,
+                                            skip_symlinks: None,
                                             additional_dependencies: None,
                                             args: None,
                                             always_run: None,
                                             fail_fast: None,
                                             pass_filenames: None,
+                                            pass_file_contents: None,
+                                            changed_lines: None,
                                             description: None,
+                                            homepage: None,
                                             language_version: None,
                                             log_file: None,
                                             require_serial: None,
+                                            concurrency_group: None,
+                                            cache_results: None,
+                                            stream_output: None,
+                                            tty: None,
+                                            timeout_seconds: None,
+                                            limits: None,
+                                            sandbox: None,
+                                            sandbox_network: None,
+                                            pass_env: None,
+                                            env: None,
+                                            working_directory: None,
+                                            depends_on: None,
+                                            group: None,
+                                            tags: None,
                                             stages: None,
                                             verbose: None,
+                                            on_fail_message: None,
+                                            severity: None,
+                                            skip_on_branches: None,
+                                            only_on_branches: None,
+                                            platforms: None,
+                                            enabled_if_env: None,
+                                            disabled_if_env: None,
                                             minimum_prek_version: None,
+                                            minimum_pre_commit_version: None,
                                         },
                                     },
                                 ),
@@ -1239,20 +2832,47 @@ mod tests {
                                             types: None,
                                             types_or: None,
                                             exclude_types: None,
+                                            follow_symlinks: None,
+                                            skip_symlinks: None,
                                             additional_dependencies: None,
                                             args: None,
                                             always_run: None,
                                             fail_fast: None,
                                             pass_filenames: None,
+                                            pass_file_contents: None,
+                                            changed_lines: None,
                                             description: None,
+                                            homepage: None,
                                             language_version: None,
                                             log_file: None,
                                             require_serial: None,
+                                            concurrency_group: None,
+                                            cache_results: None,
+                                            stream_output: None,
+                                            tty: None,
+                                            timeout_seconds: None,
+                                            limits: None,
+                                            sandbox: None,
+                                            sandbox_network: None,
+                                            pass_env: None,
+                                            env: None,
+                                            working_directory: None,
+                                            depends_on: None,
+                                            group: None,
+                                            tags: None,
                                             stages: None,
                                             verbose: Some(
                                                 true,
                                             ),
+                                            on_fail_message: None,
+                                            severity: None,
+                                            skip_on_branches: None,
+                                            only_on_branches: None,
+                                            platforms: None,
+                                            enabled_if_env: None,
+                                            disabled_if_env: None,
                                             minimum_prek_version: None,
+                                            minimum_pre_commit_version: None,
                                         },
                                     },
                                 ),
@@ -1267,12 +2887,77 @@ mod tests {
                 exclude: None,
                 fail_fast: None,
                 minimum_prek_version: None,
+                minimum_pre_commit_version: None,
                 ci: None,
+                extends: [],
+                max_file_size: None,
+                skip_binary_files: None,
+                skip_lfs_files: None,
+                stash_mode: None,
+                auto_stage_fixes: None,
+                require_pinned_revs: None,
+                default_timeout_seconds: None,
+                pass_env: None,
+                suppress_warnings: None,
             },
         )
         "#);
     }
 
+    #[test]
+    fn builtin_hooks() {
+        // Invalid builtin hook id
+        let yaml = indoc::indoc! { r"
+            repos:
+              - repo: builtin
+                hooks:
+                  - id: check-ast
+        "};
+        let result = serde_yaml::from_str::<Config>(yaml);
+        insta::assert_debug_snapshot!(result, @r###"
+        Err(
+            Error("repos: Invalid builtin repo: Unknown builtin hook id", line: 2, column: 3),
+        )
+        "###);
+
+        // Invalid entry
+        let yaml = indoc::indoc! { r"
+            repos:
+              - repo: builtin
+                hooks:
+                  - id: trailing-whitespace
+                    entry: trailing-whitespace-fixer
+        "};
+        let result = serde_yaml::from_str::<Config>(yaml);
+        insta::assert_debug_snapshot!(result, @r###"
+        Err(
+            Error("repos: Invalid builtin repo: entry is not allowed for builtin hook", line: 2, column: 3),
+        )
+        "###);
+
+        // The defaults of `pre-commit-hooks` can be overridden.
+        let yaml = indoc::indoc! { r"
+            repos:
+              - repo: builtin
+                hooks:
+                  - id: trailing-whitespace
+                    name: Trim whitespace
+                    args: [--markdown-linebreak-ext=md]
+        "};
+        let config = serde_yaml::from_str::<Config>(yaml).unwrap();
+        let Repo::Builtin(repo) = &config.repos[0] else {
+            panic!("expected a builtin repo");
+        };
+        let hook = &repo.hooks[0].0;
+        assert_eq!(hook.name, "Trim whitespace");
+        assert_eq!(hook.entry, "trailing-whitespace-fixer");
+        assert_eq!(hook.options.types, Some(vec!["text".to_string()]));
+        assert_eq!(
+            hook.options.args,
+            Some(vec!["--markdown-linebreak-ext=md".to_string()])
+        );
+    }
+
     #[test]
     fn language_version() {
         let yaml = indoc::indoc! { r"
@@ -1315,20 +3000,47 @@ mod tests {
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
+                                        follow_symlinks: None,
+                                        skip_symlinks: None,
                                         additional_dependencies: None,
                                         args: None,
                                         always_run: None,
                                         fail_fast: None,
                                         pass_filenames: None,
+                                        pass_file_contents: None,
+                                        changed_lines: None,
                                         description: None,
+                                        homepage: None,
                                         language_version: Some(
                                             "default",
                                         ),
                                         log_file: None,
                                         require_serial: None,
+                                        concurrency_group: None,
+                                        cache_results: None,
+                                        stream_output: None,
+                                        tty: None,
+                                        timeout_seconds: None,
+                                        limits: None,
+                                        sandbox: None,
+                                        sandbox_network: None,
+                                        pass_env: None,
+                                        env: None,
+                                        working_directory: None,
+                                        depends_on: None,
+                                        group: None,
+                                        tags: None,
                                         stages: None,
                                         verbose: None,
+                                        on_fail_message: None,
+                                        severity: None,
+                                        skip_on_branches: None,
+                                        only_on_branches: None,
+                                        platforms: None,
+                                        enabled_if_env: None,
+                                        disabled_if_env: None,
                                         minimum_prek_version: None,
+                                        minimum_pre_commit_version: None,
                                     },
                                 },
                                 ManifestHook {
@@ -1343,20 +3055,47 @@ mod tests {
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
+                                        follow_symlinks: None,
+                                        skip_symlinks: None,
                                         additional_dependencies: None,
                                         args: None,
                                         always_run: None,
                                         fail_fast: None,
                                         pass_filenames: None,
+                                        pass_file_contents: None,
+                                        changed_lines: None,
                                         description: None,
+                                        homepage: None,
                                         language_version: Some(
                                             "system",
                                         ),
                                         log_file: None,
                                         require_serial: None,
+                                        concurrency_group: None,
+                                        cache_results: None,
+                                        stream_output: None,
+                                        tty: None,
+                                        timeout_seconds: None,
+                                        limits: None,
+                                        sandbox: None,
+                                        sandbox_network: None,
+                                        pass_env: None,
+                                        env: None,
+                                        working_directory: None,
+                                        depends_on: None,
+                                        group: None,
+                                        tags: None,
                                         stages: None,
                                         verbose: None,
+                                        on_fail_message: None,
+                                        severity: None,
+                                        skip_on_branches: None,
+                                        only_on_branches: None,
+                                        platforms: None,
+                                        enabled_if_env: None,
+                                        disabled_if_env: None,
                                         minimum_prek_version: None,
+                                        minimum_pre_commit_version: None,
                                     },
                                 },
                                 ManifestHook {
@@ -1371,20 +3110,47 @@ mod tests {
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
+                                        follow_symlinks: None,
+                                        skip_symlinks: None,
                                         additional_dependencies: None,
                                         args: None,
                                         always_run: None,
                                         fail_fast: None,
                                         pass_filenames: None,
+                                        pass_file_contents: None,
+                                        changed_lines: None,
                                         description: None,
+                                        homepage: None,
                                         language_version: Some(
                                             "3.8",
                                         ),
                                         log_file: None,
                                         require_serial: None,
+                                        concurrency_group: None,
+                                        cache_results: None,
+                                        stream_output: None,
+                                        tty: None,
+                                        timeout_seconds: None,
+                                        limits: None,
+                                        sandbox: None,
+                                        sandbox_network: None,
+                                        pass_env: None,
+                                        env: None,
+                                        working_directory: None,
+                                        depends_on: None,
+                                        group: None,
+                                        tags: None,
                                         stages: None,
                                         verbose: None,
+                                        on_fail_message: None,
+                                        severity: None,
+                                        skip_on_branches: None,
+                                        only_on_branches: None,
+                                        platforms: None,
+                                        enabled_if_env: None,
+                                        disabled_if_env: None,
                                         minimum_prek_version: None,
+                                        minimum_pre_commit_version: None,
                                     },
                                 },
                             ],
@@ -1398,7 +3164,18 @@ mod tests {
                 exclude: None,
                 fail_fast: None,
                 minimum_prek_version: None,
+                minimum_pre_commit_version: None,
                 ci: None,
+                extends: [],
+                max_file_size: None,
+                skip_binary_files: None,
+                skip_lfs_files: None,
+                stash_mode: None,
+                auto_stage_fixes: None,
+                require_pinned_revs: None,
+                default_timeout_seconds: None,
+                pass_env: None,
+                suppress_warnings: None,
             },
         )
         "#);
@@ -1480,6 +3257,54 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_minimum_pre_commit_version() {
+        let yaml = indoc::indoc! {r"
+            repos:
+              - repo: local
+                hooks:
+                  - id: test-hook
+                    name: Test Hook
+                    entry: echo test
+                    language: system
+                    minimum_pre_commit_version: '2.9'
+            minimum_pre_commit_version: '3.2.0'
+        "};
+        let config = serde_yaml::from_str::<Config>(yaml).unwrap();
+        assert_eq!(config.minimum_pre_commit_version.as_deref(), Some("3.2.0"));
+        let Repo::Local(repo) = &config.repos[0] else {
+            panic!("Expected local repo");
+        };
+        assert_eq!(
+            repo.hooks[0].options.minimum_pre_commit_version.as_deref(),
+            Some("2.9")
+        );
+
+        // Newer than the pre-commit version prek is compatible with.
+        let yaml = indoc::indoc! {r"
+            repos: []
+            minimum_pre_commit_version: '100.0.0'
+        "};
+        let err = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Required minimum pre-commit version `100.0.0` is greater than")
+        );
+
+        let yaml = indoc::indoc! {r"
+            repos:
+              - repo: local
+                hooks:
+                  - id: test-hook
+                    name: Test Hook
+                    entry: echo test
+                    language: system
+                    minimum_pre_commit_version: '100.0.0'
+        "};
+        let result = serde_yaml::from_str::<Config>(yaml);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_validate_type_tags() {
         // Valid tags should parse successfully
@@ -1579,4 +3404,40 @@ mod tests {
                 .contains("Type tag \"not-a-real-tag\" is not recognized")
         );
     }
+
+    #[test]
+    fn test_is_full_sha() {
+        assert!(is_full_sha("cef0300fd0fc4d2a87a85fa2093c6b283ea36f4b"));
+        assert!(!is_full_sha("cef0300"));
+        assert!(!is_full_sha("v5.0.0"));
+    }
+
+    #[test]
+    fn test_never_matches() {
+        assert!(never_matches(r"^src/.*\.py$").is_none());
+        assert!(never_matches(r"^(a|b)$|^c/").is_none());
+        assert!(never_matches(r"[$\\]").is_none());
+        assert!(never_matches(r"(?m)^a$\n^b$").is_none());
+        assert!(never_matches(r"^/src/").is_some());
+        assert!(never_matches(r"^\./src/").is_some());
+        assert!(never_matches(r"^src\\main\.rs$").is_some());
+        assert!(never_matches(r"\.py$/").is_some());
+    }
+
+    #[test]
+    fn test_closest_key() {
+        let known = ["files", "exclude", "additional_dependencies"];
+        assert_eq!(closest_key("fils", &known), Some("files"));
+        assert_eq!(
+            closest_key("additional_dependencys", &known),
+            Some("additional_dependencies")
+        );
+        assert_eq!(closest_key("unexpected", &known), None);
+    }
+
+    #[test]
+    fn test_struct_keys() {
+        assert!(struct_keys::<Config>().contains(&"default_stages"));
+        assert!(struct_keys::<HookOptions>().contains(&"additional_dependencies"));
+    }
 }
@@ -20,6 +20,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use std::borrow::Cow;
 use std::fmt::Display;
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
@@ -120,8 +121,97 @@ pub(crate) fn normalize_path(path: PathBuf) -> PathBuf {
         *c = b'/';
     }
 
-    let os_str = OsString::from(String::from_utf8_lossy(&path).to_string());
-    PathBuf::from(os_str)
+    // SAFETY: only ASCII separators were replaced, so the bytes are still valid.
+    PathBuf::from(unsafe { OsString::from_encoded_bytes_unchecked(path) })
+}
+
+/// Returns `true` if no files can be created in `dir`, e.g. in a read-only mounted checkout.
+#[cfg(unix)]
+pub(crate) fn is_read_only(dir: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string.
+    if unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0 {
+        return false;
+    }
+    let err = std::io::Error::last_os_error();
+    trace!(path = %dir.display(), error = %err, "Directory is not writable");
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+/// Returns `true` if no files can be created in `dir`, e.g. in a read-only mounted checkout.
+#[cfg(not(unix))]
+pub(crate) fn is_read_only(dir: &Path) -> bool {
+    fs_err::metadata(dir).is_ok_and(|metadata| metadata.permissions().readonly())
+}
+
+/// Returns the extended-length form of an absolute path, e.g. `\\?\C:\Users\...`, which is not
+/// limited to `MAX_PATH` characters.
+///
+/// Hook environments and cloned repos in the store can be nested deeper than `MAX_PATH`, e.g.
+/// with `node_modules`. Only use it for prek's own file operations, external tools don't all
+/// support extended-length paths.
+#[cfg(windows)]
+pub(crate) fn long_path(path: &Path) -> Cow<'_, Path> {
+    use std::ffi::OsString;
+    use std::path::{Component, Prefix};
+
+    if !path.is_absolute() {
+        return Cow::Borrowed(path);
+    }
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return Cow::Borrowed(path);
+    };
+    let mut long = match prefix.kind() {
+        Prefix::Disk(_) => {
+            let mut long = OsString::from(r"\\?\");
+            long.push(prefix.as_os_str());
+            long
+        }
+        Prefix::UNC(server, share) => {
+            let mut long = OsString::from(r"\\?\UNC\");
+            long.push(server);
+            long.push(r"\");
+            long.push(share);
+            long
+        }
+        // Already extended-length, or a device path.
+        _ => return Cow::Borrowed(path),
+    };
+    let mut is_root = true;
+    for component in components {
+        match component {
+            Component::RootDir => {}
+            Component::Normal(name) => {
+                long.push(r"\");
+                long.push(name);
+                is_root = false;
+            }
+            // Extended-length paths are not normalized, `..` would be a file name.
+            Component::CurDir | Component::ParentDir | Component::Prefix(_) => {
+                return Cow::Borrowed(path);
+            }
+        }
+    }
+    if is_root {
+        long.push(r"\");
+    }
+    Cow::Owned(PathBuf::from(long))
+}
+
+/// Returns the extended-length form of an absolute path on Windows, paths are not limited in
+/// length on other platforms.
+#[cfg(not(windows))]
+pub(crate) fn long_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
 }
 
 /// Compute a path describing `path` relative to `base`.
@@ -304,3 +394,55 @@ pub(crate) async fn rename_or_copy(source: &Path, target: &Path) -> std::io::Res
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_path_relative() {
+        assert_eq!(long_path(Path::new("hooks/env")), Path::new("hooks/env"));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn long_path_windows() {
+        assert_eq!(
+            long_path(Path::new(r"C:\Users\prek\cache\hooks\env")),
+            Path::new(r"\\?\C:\Users\prek\cache\hooks\env")
+        );
+        assert_eq!(
+            long_path(Path::new("C:/Users/prek/cache")),
+            Path::new(r"\\?\C:\Users\prek\cache")
+        );
+        assert_eq!(long_path(Path::new(r"C:\")), Path::new(r"\\?\C:\"));
+        assert_eq!(
+            long_path(Path::new(r"\\server\share\prek")),
+            Path::new(r"\\?\UNC\server\share\prek")
+        );
+        assert_eq!(
+            long_path(Path::new(r"\\?\C:\Users\prek")),
+            Path::new(r"\\?\C:\Users\prek")
+        );
+        assert_eq!(
+            long_path(Path::new(r"C:\Users\..\prek")),
+            Path::new(r"C:\Users\..\prek")
+        );
+    }
+
+    /// Directories nested deeper than `MAX_PATH` can be removed.
+    #[test]
+    fn long_path_remove_dir_all() -> anyhow::Result<()> {
+        let temp = tempfile::tempdir()?;
+        let mut deep = long_path(temp.path()).into_owned();
+        for _ in 0..30 {
+            deep.push("node_modules_nested_directory");
+        }
+        fs_err::create_dir_all(&deep)?;
+        fs_err::write(deep.join("index.js"), "")?;
+
+        fs_err::remove_dir_all(long_path(temp.path()))?;
+        assert!(!temp.path().exists());
+        Ok(())
+    }
+}
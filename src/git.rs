@@ -1,16 +1,18 @@
 use std::collections::HashSet;
+use std::io::{Seek, Write as _};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::str::Utf8Error;
 use std::sync::LazyLock;
 
 use anyhow::Result;
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tracing::{debug, warn};
 
 use crate::process;
 use crate::process::{Cmd, StatusError};
+use crate::settings::settings;
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum Error {
@@ -27,6 +29,9 @@ pub(crate) enum Error {
     UTF8(#[from] Utf8Error),
 }
 
+/// The maximum number of paths passed to a single git command.
+pub(crate) const PATHS_PER_COMMAND: usize = 1000;
+
 pub(crate) static GIT: LazyLock<Result<PathBuf, which::Error>> =
     LazyLock::new(|| which::which("git"));
 
@@ -68,15 +73,35 @@ pub(crate) static GIT_ENV_TO_REMOVE: LazyLock<Vec<(String, String)>> = LazyLock:
 pub(crate) fn git_cmd(summary: &str) -> Result<Cmd, Error> {
     let mut cmd = Cmd::new(GIT.as_ref().map_err(|&e| Error::GitNotFound(e))?, summary);
     cmd.arg("-c").arg("core.useBuiltinFSMonitor=false");
+    // Store paths of cloned repos can exceed `MAX_PATH` on Windows.
+    #[cfg(windows)]
+    cmd.arg("-c").arg("core.longpaths=true");
 
     Ok(cmd)
 }
 
+/// Convert a file name from git output to a path.
+///
+/// On Unix, file names are arbitrary bytes, e.g. Latin-1 encoded names are not valid UTF-8.
+#[cfg_attr(unix, allow(clippy::unnecessary_wraps))]
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> Result<PathBuf, Utf8Error> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Ok(PathBuf::from(std::ffi::OsStr::from_bytes(bytes)))
+    }
+    #[cfg(not(unix))]
+    {
+        str::from_utf8(bytes).map(PathBuf::from)
+    }
+}
+
+fn nul_split(s: &[u8]) -> impl Iterator<Item = &[u8]> {
+    s.split(|&b| b == b'\0').filter(|slice| !slice.is_empty())
+}
+
 fn zsplit(s: &[u8]) -> Result<Vec<PathBuf>, Utf8Error> {
-    s.split(|&b| b == b'\0')
-        .filter(|slice| !slice.is_empty())
-        .map(|slice| str::from_utf8(slice).map(PathBuf::from))
-        .collect()
+    nul_split(s).map(path_from_bytes).collect()
 }
 
 pub(crate) async fn intent_to_add_files(root: &Path) -> Result<Vec<PathBuf>, Error> {
@@ -104,6 +129,7 @@ pub(crate) async fn get_changed_files(
         .arg("diff")
         .arg("--name-only")
         .arg("--diff-filter=ACMRT")
+        .arg("--no-renames") // Rename detection reads blobs, which a partial clone fetches on demand
         .arg("--no-ext-diff") // Disable external diff drivers
         .arg("-z") // Use NUL as line terminator
         .arg(format!("{old}...{new}"))
@@ -129,6 +155,50 @@ pub(crate) async fn ls_files(cwd: &Path, path: &Path) -> Result<Vec<PathBuf>, Er
     Ok(zsplit(&output.stdout)?)
 }
 
+/// Get the blob hashes of the files in the index, keyed by their path relative to `cwd`.
+pub(crate) async fn get_staged_blobs(cwd: &Path) -> Result<FxHashMap<PathBuf, String>, Error> {
+    let output = git_cmd("get staged blobs")?
+        .current_dir(cwd)
+        .arg("ls-files")
+        .arg("--stage")
+        .arg("-z")
+        .arg("--")
+        .arg(".")
+        .check(true)
+        .output()
+        .await?;
+
+    // Each entry is `<mode> <object> <stage>\t<path>`.
+    let mut blobs = FxHashMap::default();
+    for entry in nul_split(&output.stdout) {
+        let Some(tab) = entry.iter().position(|&b| b == b'\t') else {
+            continue;
+        };
+        let info = std::str::from_utf8(&entry[..tab])?;
+        if let Some(object) = info.split(' ').nth(1) {
+            blobs.insert(path_from_bytes(&entry[tab + 1..])?, object.to_string());
+        }
+    }
+
+    Ok(blobs)
+}
+
+/// Get the content of `path`, relative to `cwd`, in the index, `None` if it's not in the index.
+pub(crate) async fn get_staged_content(cwd: &Path, path: &Path) -> Result<Option<Vec<u8>>, Error> {
+    let mut object = std::ffi::OsString::from(":./");
+    object.push(path);
+    let output = git_cmd("get staged content")?
+        .current_dir(cwd)
+        .arg("cat-file")
+        .arg("blob")
+        .arg(object)
+        .check(false)
+        .output()
+        .await?;
+
+    Ok(output.status.success().then_some(output.stdout))
+}
+
 pub(crate) async fn get_git_dir() -> Result<PathBuf, Error> {
     let output = git_cmd("get git dir")?
         .arg("rev-parse")
@@ -157,12 +227,74 @@ pub(crate) async fn get_git_common_dir() -> Result<PathBuf, Error> {
     }
 }
 
+/// The line ranges added or modified in each file, keyed by the path of the file.
+pub(crate) type ChangedLines = FxHashMap<PathBuf, Vec<(u32, u32)>>;
+
+/// Get the lines added or modified in the files under `root`, in the index, or between the refs
+/// `from` and `to`. The paths are relative to `root`.
+pub(crate) async fn get_changed_lines(
+    root: &Path,
+    refs: Option<(&str, &str)>,
+) -> Result<ChangedLines, Error> {
+    let mut cmd = git_cmd("get changed lines")?;
+    cmd.current_dir(root)
+        .arg("diff")
+        .arg("--unified=0")
+        .arg("--no-color")
+        .arg("--no-renames")
+        .arg("--no-ext-diff")
+        .arg("--relative")
+        // Don't let the config of the user change the prefixes parsed below.
+        .arg("--src-prefix=a/")
+        .arg("--dst-prefix=b/");
+    match refs {
+        Some((from, to)) => cmd.arg(format!("{from}...{to}")),
+        None => cmd.arg("--staged"),
+    };
+    let output = cmd.arg("--").arg(".").check(true).output().await?;
+
+    let mut changed = ChangedLines::default();
+    let mut path = None;
+    let mut in_header = false;
+    for line in output.stdout.split(|&b| b == b'\n') {
+        if line.starts_with(b"diff --git ") {
+            in_header = true;
+            path = None;
+        } else if in_header && let Some(name) = line.strip_prefix(b"+++ ") {
+            // Deleted files are `/dev/null`. Names git quotes, e.g. with a newline, are skipped.
+            path = name.strip_prefix(b"b/").map(path_from_bytes).transpose()?;
+        } else if let Some(hunk) = line.strip_prefix(b"@@ ") {
+            in_header = false;
+            if let Some(path) = &path
+                && let Some((start, count)) = parse_hunk(hunk)
+                && count > 0
+            {
+                changed
+                    .entry(path.clone())
+                    .or_default()
+                    .push((start, start + count - 1));
+            }
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Parse the start and the number of lines of the new side of a hunk, from `-a,b +c,d @@`.
+fn parse_hunk(hunk: &[u8]) -> Option<(u32, u32)> {
+    let hunk = std::str::from_utf8(hunk).ok()?;
+    let new = hunk.split(' ').find_map(|part| part.strip_prefix('+'))?;
+    let (start, count) = new.split_once(',').unwrap_or((new, "1"));
+    Some((start.parse().ok()?, count.parse().ok()?))
+}
+
 pub(crate) async fn get_staged_files(root: &Path) -> Result<Vec<PathBuf>, Error> {
     let output = git_cmd("get staged files")?
         .arg("diff")
         .arg("--staged")
         .arg("--name-only")
         .arg("--diff-filter=ACMRTUXB") // Everything except for D
+        .arg("--no-renames") // Rename detection reads blobs, which a partial clone fetches on demand
         .arg("--no-ext-diff") // Disable external diff drivers
         .arg("-z") // Use NUL as line terminator
         .arg("--")
@@ -192,14 +324,213 @@ pub(crate) async fn files_not_staged(files: &[&Path]) -> Result<Vec<PathBuf>> {
     Ok(vec![])
 }
 
-pub(crate) async fn has_unmerged_paths() -> Result<bool, Error> {
-    let output = git_cmd("check has unmerged paths")?
+/// Stage `files`, relative to the git root.
+pub(crate) async fn add_files(git_root: &Path, files: &[PathBuf]) -> Result<(), Error> {
+    for chunk in files.chunks(PATHS_PER_COMMAND) {
+        git_cmd("git add")?
+            .arg("add")
+            .arg("--")
+            .args(chunk)
+            .current_dir(git_root)
+            .check(true)
+            .output()
+            .await?;
+    }
+    Ok(())
+}
+
+/// Stage the content of `source` as `file`, relative to the git root, without touching the
+/// working tree. `file` must already be in the index.
+pub(crate) async fn stage_file_content(
+    git_root: &Path,
+    file: &Path,
+    source: &Path,
+) -> Result<(), Error> {
+    let output = git_cmd("git ls-files")?
         .arg("ls-files")
-        .arg("--unmerged")
+        .arg("--stage")
+        .arg("-z")
+        .arg("--")
+        .arg(file)
+        .current_dir(git_root)
+        .check(true)
+        .output()
+        .await?;
+    // The format is `<mode> <object> <stage>\t<file>`.
+    let mode = output
+        .stdout
+        .split(|&b| b == b' ')
+        .next()
+        .map(|mode| String::from_utf8_lossy(mode).to_string())
+        .filter(|mode| !mode.is_empty())
+        .unwrap_or_else(|| "100644".to_string());
+
+    // The path selects the filters to apply, e.g. line ending conversion.
+    let output = git_cmd("git hash-object")?
+        .arg("hash-object")
+        .arg("-w")
+        .arg("--path")
+        .arg(file)
+        .arg(source)
+        .current_dir(git_root)
+        .check(true)
+        .output()
+        .await?;
+    let object = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    git_cmd("git update-index")?
+        .arg("update-index")
+        .arg("--cacheinfo")
+        .arg(mode)
+        .arg(object)
+        .arg(file)
+        .current_dir(git_root)
         .check(true)
         .output()
         .await?;
-    Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty())
+    Ok(())
+}
+
+/// Get the paths of the submodules registered in `.gitmodules`, relative to the git root.
+pub(crate) async fn get_submodule_paths(git_root: &Path) -> Result<Vec<PathBuf>, Error> {
+    if !git_root.join(".gitmodules").is_file() {
+        return Ok(vec![]);
+    }
+
+    // Exits with 1 if there are no matching keys.
+    let output = git_cmd("get submodule paths")?
+        .arg("config")
+        .arg("--file")
+        .arg(".gitmodules")
+        .arg("--null")
+        .arg("--get-regexp")
+        .arg(r"^submodule\..*\.path$")
+        .current_dir(git_root)
+        .check(false)
+        .output()
+        .await?;
+
+    // With `--null`, each entry is the key and the value separated by a newline.
+    nul_split(&output.stdout)
+        .filter_map(|entry| {
+            let newline = entry.iter().position(|&b| b == b'\n')?;
+            Some(path_from_bytes(&entry[newline + 1..]))
+        })
+        .collect::<Result<_, _>>()
+        .map_err(Error::from)
+}
+
+/// Get the commit of the submodule at `path` recorded in `rev` of the superproject, `None` if
+/// the submodule doesn't exist in `rev`.
+pub(crate) async fn get_submodule_commit(
+    git_root: &Path,
+    rev: &str,
+    path: &Path,
+) -> Result<Option<String>, Error> {
+    // Paths in `<rev>:<path>` always use forward slashes.
+    let output = git_cmd("get submodule commit")?
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(format!(
+            "{rev}:{}",
+            path.to_string_lossy().replace('\\', "/")
+        ))
+        .current_dir(git_root)
+        .check(false)
+        .output()
+        .await?;
+    if output.status.success() {
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Check if the repository uses a sparse checkout.
+pub(crate) async fn is_sparse_checkout() -> Result<bool, Error> {
+    // Exits with 1 if the key is not set.
+    let output = git_cmd("check sparse checkout")?
+        .arg("config")
+        .arg("--type=bool")
+        .arg("--get")
+        .arg("core.sparseCheckout")
+        .check(false)
+        .output()
+        .await?;
+    Ok(output.stdout.trim_ascii() == b"true")
+}
+
+/// Get the index entries with the skip-worktree bit, relative to the git root.
+pub(crate) async fn get_skip_worktree_paths(git_root: &Path) -> Result<Vec<PathBuf>, Error> {
+    let output = git_cmd("get skip-worktree paths")?
+        .current_dir(git_root)
+        .arg("ls-files")
+        .arg("-t")
+        .arg("-z")
+        .check(true)
+        .output()
+        .await?;
+
+    // With `-t`, each entry is prefixed by its status, `S` for skip-worktree entries.
+    nul_split(&output.stdout)
+        .filter_map(|entry| entry.strip_prefix(b"S ").map(path_from_bytes))
+        .collect::<Result<_, _>>()
+        .map_err(Error::from)
+}
+
+/// Get the paths with unmerged index entries, relative to the git root.
+pub(crate) async fn get_unmerged_paths() -> Result<Vec<PathBuf>, Error> {
+    let output = git_cmd("get unmerged paths")?
+        .arg("diff")
+        .arg("--name-only")
+        .arg("--diff-filter=U")
+        .arg("--no-ext-diff") // Disable external diff drivers
+        .arg("-z") // Use NUL as line terminator
+        .check(true)
+        .output()
+        .await?;
+    Ok(zsplit(&output.stdout)?)
+}
+
+/// Get the files under `root` whose working tree differs from `tree`, relative to the git root.
+pub(crate) async fn get_changed_worktree_files(
+    tree: &str,
+    root: &Path,
+) -> Result<Vec<PathBuf>, Error> {
+    // `git diff-index` reports files with stale stat info in the index as changed, `git diff`
+    // compares the content.
+    let output = git_cmd("get changed working tree files")?
+        .arg("diff")
+        .arg("--name-only")
+        .arg("--no-renames")
+        .arg("--ignore-submodules")
+        .arg("--no-ext-diff") // Disable external diff drivers
+        .arg("-z") // Use NUL as line terminator
+        .arg(tree)
+        .arg("--")
+        .arg(root)
+        .check(true)
+        .output()
+        .await?;
+    Ok(zsplit(&output.stdout)?)
+}
+
+/// Check if tracked files under `root` have changes that are not staged.
+pub(crate) async fn has_unstaged_changes(root: &Path) -> Result<bool, Error> {
+    let status = git_cmd("check unstaged changes")?
+        .arg("diff")
+        .arg("--quiet")
+        .arg("--ignore-submodules")
+        .arg("--no-ext-diff")
+        .arg("--")
+        .arg(root)
+        .check(false)
+        .status()
+        .await?;
+    Ok(!status.success())
 }
 
 pub(crate) async fn is_in_merge_conflict() -> Result<bool, Error> {
@@ -316,7 +647,7 @@ pub(crate) async fn init_repo(url: &str, path: &Path) -> Result<(), Error> {
         .arg("remote")
         .arg("add")
         .arg("origin")
-        .arg(url)
+        .arg(settings().rewrite_url(url))
         .remove_git_env()
         .check(true)
         .output()
@@ -325,7 +656,8 @@ pub(crate) async fn init_repo(url: &str, path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
-async fn shallow_clone(rev: &str, path: &Path) -> Result<(), Error> {
+async fn shallow_clone(rev: &str, path: &Path, on_step: &impl Fn(&str)) -> Result<(), Error> {
+    on_step("fetching");
     git_cmd("git shallow clone")?
         .current_dir(path)
         .arg("-c")
@@ -339,6 +671,7 @@ async fn shallow_clone(rev: &str, path: &Path) -> Result<(), Error> {
         .output()
         .await?;
 
+    on_step("checking out");
     git_cmd("git checkout")?
         .current_dir(path)
         .arg("checkout")
@@ -348,6 +681,7 @@ async fn shallow_clone(rev: &str, path: &Path) -> Result<(), Error> {
         .output()
         .await?;
 
+    on_step("updating submodules");
     git_cmd("update git submodules")?
         .current_dir(path)
         .arg("-c")
@@ -365,7 +699,8 @@ async fn shallow_clone(rev: &str, path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
-async fn full_clone(rev: &str, path: &Path) -> Result<(), Error> {
+async fn full_clone(rev: &str, path: &Path, on_step: &impl Fn(&str)) -> Result<(), Error> {
+    on_step("fetching all revisions");
     git_cmd("git full clone")?
         .current_dir(path)
         .arg("fetch")
@@ -376,6 +711,7 @@ async fn full_clone(rev: &str, path: &Path) -> Result<(), Error> {
         .output()
         .await?;
 
+    on_step("checking out");
     git_cmd("git checkout")?
         .current_dir(path)
         .arg("checkout")
@@ -385,6 +721,7 @@ async fn full_clone(rev: &str, path: &Path) -> Result<(), Error> {
         .output()
         .await?;
 
+    on_step("updating submodules");
     git_cmd("update git submodules")?
         .current_dir(path)
         .arg("submodule")
@@ -399,17 +736,61 @@ async fn full_clone(rev: &str, path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
-pub(crate) async fn clone_repo(url: &str, rev: &str, path: &Path) -> Result<(), Error> {
+/// Clone the repo at `url` and check out `rev`, `on_step` is called when each step starts.
+pub(crate) async fn clone_repo(
+    url: &str,
+    rev: &str,
+    path: &Path,
+    on_step: impl Fn(&str),
+) -> Result<(), Error> {
     init_repo(url, path).await?;
 
-    if let Err(err) = shallow_clone(rev, path).await {
+    if let Err(err) = shallow_clone(rev, path, &on_step).await {
         warn!(?err, "Failed to shallow clone, falling back to full clone");
-        full_clone(rev, path).await
+        full_clone(rev, path, &on_step).await
     } else {
         Ok(())
     }
 }
 
+/// Get the commit that the annotated tag `tag` of the repo at `url` points to, `None` if there
+/// is no such tag or it's a lightweight tag.
+pub(crate) async fn get_remote_annotated_tag(
+    url: &str,
+    tag: &str,
+) -> Result<Option<String>, Error> {
+    // Annotated tags are listed twice, the peeled entry has the commit.
+    let peeled = format!("refs/tags/{tag}^{{}}");
+    let output = git_cmd("git ls-remote")?
+        .arg("ls-remote")
+        .arg("--tags")
+        .arg(settings().rewrite_url(url))
+        .arg(format!("refs/tags/{tag}"))
+        .arg(&peeled)
+        .remove_git_env()
+        .check(true)
+        .output()
+        .await?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .find(|(_, name)| *name == peeled)
+        .map(|(commit, _)| commit.to_string()))
+}
+
+/// Get the commit checked out in the repo at `path`.
+pub(crate) async fn get_head(path: &Path) -> Result<String, Error> {
+    let output = git_cmd("git rev-parse")?
+        .arg("rev-parse")
+        .arg("HEAD")
+        .current_dir(path)
+        .remove_git_env()
+        .check(true)
+        .output()
+        .await?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 pub(crate) async fn has_hooks_path_set() -> Result<bool> {
     let output = git_cmd("get git hooks path")?
         .arg("config")
@@ -446,7 +827,7 @@ pub(crate) async fn get_lfs_files(paths: &[&Path]) -> Result<FxHashSet<PathBuf>,
 
     let writer = async move {
         for path in paths {
-            stdin.write_all(path.to_string_lossy().as_bytes()).await?;
+            stdin.write_all(path.as_os_str().as_encoded_bytes()).await?;
             stdin.write_all(b"\0").await?;
         }
         stdin.shutdown().await?;
@@ -472,20 +853,94 @@ pub(crate) async fn get_lfs_files(paths: &[&Path]) -> Result<FxHashSet<PathBuf>,
     }
 
     let mut lfs_files = FxHashSet::default();
-    let read_result = String::from_utf8_lossy(&read_result);
-    let mut it = read_result.split_terminator('\0');
+    let mut it = read_result.split(|&b| b == b'\0');
     loop {
         let (Some(file), Some(_attr), Some(value)) = (it.next(), it.next(), it.next()) else {
             break;
         };
-        if value == "lfs" {
-            lfs_files.insert(PathBuf::from(file));
+        if value == b"lfs" {
+            lfs_files.insert(path_from_bytes(file)?);
         }
     }
 
     Ok(lfs_files)
 }
 
+/// Get the name of the current branch, `None` if `HEAD` is detached.
+pub(crate) async fn get_current_branch() -> Result<Option<String>, Error> {
+    let output = git_cmd("get current branch")?
+        .arg("symbolic-ref")
+        .arg("--short")
+        .arg("--quiet")
+        .arg("HEAD")
+        .check(false)
+        .output()
+        .await?;
+    if output.status.success() {
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Get the paths among `paths` that are ignored by git, e.g. build outputs.
+pub(crate) async fn get_ignored_files(
+    root: &Path,
+    paths: &[&Path],
+) -> Result<FxHashSet<PathBuf>, Error> {
+    if paths.is_empty() {
+        return Ok(FxHashSet::default());
+    }
+
+    let mut child = git_cmd("git check-ignore")?
+        .arg("check-ignore")
+        .arg("-z")
+        .arg("--stdin")
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .check(false)
+        .spawn()?;
+
+    let mut stdout = child.stdout.take().expect("failed to open stdout");
+    let mut stdin = child.stdin.take().expect("failed to open stdin");
+
+    let writer = async move {
+        for path in paths {
+            stdin.write_all(path.as_os_str().as_encoded_bytes()).await?;
+            stdin.write_all(b"\0").await?;
+        }
+        stdin.shutdown().await?;
+        Ok::<(), std::io::Error>(())
+    };
+    let reader = async move {
+        let mut out = Vec::new();
+        stdout.read_to_end(&mut out).await?;
+        Ok::<_, std::io::Error>(out)
+    };
+
+    let (read_result, _write_result) = tokio::try_join!(biased; reader, writer)?;
+
+    // `git check-ignore` exits with 1 if none of the paths are ignored.
+    let status = child.wait().await?;
+    if !matches!(status.code(), Some(0 | 1)) {
+        return Err(Error::Command(process::Error::Status {
+            summary: "git check-ignore".to_string(),
+            error: StatusError {
+                status,
+                output: None,
+            },
+        }));
+    }
+
+    Ok(nul_split(&read_result)
+        .map(path_from_bytes)
+        .collect::<Result<_, _>>()?)
+}
+
 /// Check if a git revision exists
 pub(crate) async fn rev_exists(rev: &str) -> Result<bool, Error> {
     let output = git_cmd("check if revision exists")?
@@ -552,3 +1007,190 @@ pub(crate) async fn get_parent_commit(commit: &str) -> Result<Option<String>, Er
         Ok(None)
     }
 }
+
+/// Write the files in the index to `dir`, without changing the working tree.
+pub(crate) async fn checkout_index(git_root: &Path, dir: &Path) -> Result<(), Error> {
+    // The prefix is prepended to the paths as is, it must end with a separator.
+    let mut prefix = dir.as_os_str().to_os_string();
+    prefix.push("/");
+    git_cmd("copy staged files")?
+        .arg("checkout-index")
+        .arg("--all")
+        .arg("--prefix")
+        .arg(prefix)
+        // `checkout-index` only writes the files under the current directory.
+        .current_dir(git_root)
+        .check(true)
+        .output()
+        .await?;
+    Ok(())
+}
+
+/// Add a worktree of `commit` at `path`, without checking out any file.
+pub(crate) async fn add_worktree(git_root: &Path, path: &Path, commit: &str) -> Result<(), Error> {
+    git_cmd("git worktree add")?
+        .arg("worktree")
+        .arg("add")
+        .arg("--detach")
+        .arg("--no-checkout")
+        .arg(path)
+        .arg(commit)
+        .current_dir(git_root)
+        // The `GIT_INDEX_FILE` of a commit in progress is not the index of the new worktree.
+        .remove_git_env()
+        .check(true)
+        .output()
+        .await?;
+    Ok(())
+}
+
+/// Remove a worktree added with [`add_worktree`], along with its files.
+///
+/// It's blocking, so that it can be called when dropping the worktree.
+pub(crate) fn remove_worktree(git_root: &Path, path: &Path) -> Result<(), Error> {
+    let git = GIT.as_ref().map_err(|&e| Error::GitNotFound(e))?;
+    let mut cmd = std::process::Command::new(git);
+    cmd.arg("worktree")
+        .arg("remove")
+        .arg("--force")
+        .arg(path)
+        .current_dir(git_root);
+    for (key, _) in GIT_ENV_TO_REMOVE.iter() {
+        cmd.env_remove(key);
+    }
+    let output = cmd.output()?;
+    if !output.status.success() {
+        return Err(Error::Command(process::Error::Status {
+            summary: "git worktree remove".to_string(),
+            error: StatusError {
+                status: output.status,
+                output: Some(output),
+            },
+        }));
+    }
+    Ok(())
+}
+
+/// Create a commit of `tree` without parents, e.g. to add a worktree to a repository without
+/// commits.
+pub(crate) async fn commit_tree(tree: &str) -> Result<String, Error> {
+    let output = git_cmd("git commit-tree")?
+        .arg("-c")
+        .arg("user.name=prek")
+        .arg("-c")
+        .arg("user.email=prek@localhost")
+        .arg("commit-tree")
+        .arg("-m")
+        .arg("prek")
+        .arg(tree)
+        .check(true)
+        .output()
+        .await?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Set the index of the worktree at `cwd` to `tree`, and only check out the files `checkout`
+/// selects. The other files are marked `skip-worktree`, so git doesn't see them as deleted.
+pub(crate) async fn sparse_checkout(
+    cwd: &Path,
+    tree: &str,
+    checkout: impl Fn(&Path) -> bool,
+) -> Result<(), Error> {
+    git_cmd("git read-tree")?
+        .arg("read-tree")
+        .arg(tree)
+        .current_dir(cwd)
+        .remove_git_env()
+        .check(true)
+        .output()
+        .await?;
+
+    let output = git_cmd("git ls-files")?
+        .arg("ls-files")
+        .arg("-z")
+        .current_dir(cwd)
+        .remove_git_env()
+        .check(true)
+        .output()
+        .await?;
+    let (checked_out, skipped): (Vec<_>, Vec<_>) = zsplit(&output.stdout)?
+        .into_iter()
+        .partition(|file| checkout(file));
+
+    // Paths are read from a file, there may be too many for the command line.
+    for (files, summary, args) in [
+        (
+            skipped,
+            "git update-index",
+            ["update-index", "--skip-worktree"],
+        ),
+        (
+            checked_out,
+            "git checkout-index",
+            ["checkout-index", "--force"],
+        ),
+    ] {
+        if files.is_empty() {
+            continue;
+        }
+        let mut paths = tempfile::tempfile()?;
+        for file in &files {
+            paths.write_all(file.as_os_str().as_encoded_bytes())?;
+            paths.write_all(b"\0")?;
+        }
+        paths.rewind()?;
+        git_cmd(summary)?
+            .args(args)
+            .arg("-z")
+            .arg("--stdin")
+            .current_dir(cwd)
+            .remove_git_env()
+            // `output` would replace stdin.
+            .stdin(paths)
+            .stdout(Stdio::null())
+            .check(true)
+            .status()
+            .await?;
+    }
+    Ok(())
+}
+
+/// Create a tree object of the tracked files in the working tree, without changing the index
+/// or the stash. Returns `None` if the repository has no commits yet.
+pub(crate) async fn get_worktree_tree() -> Result<Option<String>, Error> {
+    let output = git_cmd("git stash create")?
+        .arg("stash")
+        .arg("create")
+        .check(false)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if commit.is_empty() {
+        // No local changes, the working tree matches `HEAD`.
+        return get_tree("HEAD").await;
+    }
+    get_tree(&commit).await
+}
+
+/// Get the tree object of the given commit
+pub(crate) async fn get_tree(commit: &str) -> Result<Option<String>, Error> {
+    let output = git_cmd("get tree")?
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(format!("{commit}^{{tree}}"))
+        .check(false)
+        .output()
+        .await?;
+    if output.status.success() {
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    } else {
+        Ok(None)
+    }
+}
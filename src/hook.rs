@@ -1,26 +1,35 @@
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use clap::ValueEnum;
 use constants::MANIFEST_FILE;
+use constants::env_vars::EnvVars;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use rand::Rng;
 use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{error, trace};
 
+use crate::checksum::{self, Verify};
 use crate::config::{
-    self, Config, HookOptions, Language, LocalHook, ManifestHook, MetaHook, RemoteHook, SerdeRegex,
-    Stage, read_manifest,
+    self, BuiltinHook, Config, HookOptions, Language, LocalHook, ManifestHook, MetaHook,
+    PassFileContents, Platform, RemoteHook, ResourceLimits, SerdeRegex, Severity, Stage,
+    read_manifest,
 };
+use crate::git::GIT_ROOT;
 use crate::languages::version::LanguageRequest;
 use crate::languages::{extract_metadata_from_entry, resolve_command};
+use crate::run::CONCURRENCY;
 use crate::store::Store;
+use crate::warn_user_once;
 use crate::workspace::Project;
 
 #[derive(Error, Debug)]
@@ -58,6 +67,9 @@ pub(crate) enum Repo {
     Meta {
         hooks: Vec<ManifestHook>,
     },
+    Builtin {
+        hooks: Vec<ManifestHook>,
+    },
 }
 
 impl Repo {
@@ -89,6 +101,13 @@ impl Repo {
         }
     }
 
+    /// Construct a builtin repo.
+    pub(crate) fn builtin(hooks: Vec<BuiltinHook>) -> Self {
+        Self::Builtin {
+            hooks: hooks.into_iter().map(ManifestHook::from).collect(),
+        }
+    }
+
     /// Get the path to the cloned repo if it is a remote repo.
     pub(crate) fn path(&self) -> Option<&Path> {
         match self {
@@ -103,6 +122,7 @@ impl Repo {
             Repo::Remote { hooks, .. } => hooks,
             Repo::Local { hooks } => hooks,
             Repo::Meta { hooks } => hooks,
+            Repo::Builtin { hooks } => hooks,
         };
         hooks.iter().find(|hook| hook.id == id)
     }
@@ -114,6 +134,7 @@ impl Display for Repo {
             Repo::Remote { url, rev, .. } => write!(f, "{url}@{rev}"),
             Repo::Local { .. } => write!(f, "local"),
             Repo::Meta { .. } => write!(f, "meta"),
+            Repo::Builtin { .. } => write!(f, "builtin"),
         }
     }
 }
@@ -172,6 +193,17 @@ impl HookBuilder {
         if options.stages.is_none() {
             options.stages.clone_from(&config.default_stages);
         }
+
+        if options.timeout_seconds.is_none() {
+            options.timeout_seconds = config.default_timeout_seconds;
+        }
+
+        if let Some(pass_env) = &config.pass_env {
+            options
+                .pass_env
+                .get_or_insert_default()
+                .extend(pass_env.iter().cloned());
+        }
     }
 
     /// Fill in the default values for the hook configuration.
@@ -183,6 +215,12 @@ impl HookBuilder {
         options.types.get_or_insert(vec!["file".to_string()]);
         options.types_or.get_or_insert_default();
         options.exclude_types.get_or_insert_default();
+        options.follow_symlinks.get_or_insert(false);
+        options.skip_symlinks.get_or_insert(false);
+        options.cache_results.get_or_insert(false);
+        options.stream_output.get_or_insert(false);
+        options.tty.get_or_insert(false);
+        options.changed_lines.get_or_insert(false);
         options.always_run.get_or_insert(false);
         options.fail_fast.get_or_insert(false);
         options.pass_filenames.get_or_insert(true);
@@ -197,9 +235,20 @@ impl HookBuilder {
         let HookOptions {
             language_version,
             additional_dependencies,
+            follow_symlinks,
+            skip_symlinks,
             ..
         } = &self.config.options;
 
+        if follow_symlinks.unwrap_or(false) && skip_symlinks.unwrap_or(false) {
+            return Err(Error::Hook {
+                hook: self.config.id.clone(),
+                error: anyhow::anyhow!(
+                    "Hook specified both `follow_symlinks` and `skip_symlinks`, only one can be set"
+                ),
+            });
+        }
+
         let additional_dependencies = additional_dependencies
             .as_ref()
             .map_or(&[][..], |deps| deps.as_slice());
@@ -275,6 +324,55 @@ impl HookBuilder {
             None => Stages::All,
         };
 
+        let branch_globs = |patterns: Option<Vec<String>>| {
+            patterns
+                .map(|patterns| build_glob_set(&patterns))
+                .transpose()
+                .map_err(|e| Error::Hook {
+                    hook: self.config.id.clone(),
+                    error: anyhow::anyhow!(e),
+                })
+        };
+        let skip_on_branches = branch_globs(options.skip_on_branches)?;
+        let only_on_branches = branch_globs(options.only_on_branches)?;
+
+        let working_directory = options
+            .working_directory
+            .map(|dir| {
+                let dir = Path::new(&dir);
+                if dir
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+                {
+                    // Drop `.` components, so filenames can be made relative to it.
+                    Ok(dir
+                        .components()
+                        .filter(|component| *component != Component::CurDir)
+                        .collect::<PathBuf>())
+                } else {
+                    Err(Error::Hook {
+                        hook: self.config.id.clone(),
+                        error: anyhow::anyhow!(
+                            "`working_directory` must be a relative path inside the project, got `{}`",
+                            dir.display()
+                        ),
+                    })
+                }
+            })
+            .transpose()?;
+        let work_dir = match &working_directory {
+            Some(dir) => self.project.path().join(dir),
+            None => self.project.path().to_path_buf(),
+        };
+
+        let mut limits = options.limits.unwrap_or_default();
+        if cfg!(unix) && limits.processes.take().is_some() {
+            warn_user_once!(
+                "Hook `{}` sets `limits.processes`, which is only supported on Windows and is ignored",
+                self.config.id
+            );
+        }
+
         let mut hook = Hook {
             entry,
             stages,
@@ -293,15 +391,43 @@ impl HookBuilder {
             types: options.types.expect("types not set"),
             types_or: options.types_or.expect("types_or not set"),
             exclude_types: options.exclude_types.expect("exclude_types not set"),
+            follow_symlinks: options.follow_symlinks.expect("follow_symlinks not set"),
+            skip_symlinks: options.skip_symlinks.expect("skip_symlinks not set"),
             args: options.args.expect("args not set"),
             always_run: options.always_run.expect("always_run not set"),
             fail_fast: options.fail_fast.expect("fail_fast not set"),
             pass_filenames: options.pass_filenames.expect("pass_filenames not set"),
+            pass_file_contents: options.pass_file_contents,
+            changed_lines: options.changed_lines.expect("changed_lines not set"),
             description: options.description,
+            homepage: options.homepage,
             log_file: options.log_file,
             require_serial: options.require_serial.expect("require_serial not set"),
+            concurrency_group: options.concurrency_group,
+            cache_results: options.cache_results.expect("cache_results not set"),
+            stream_output: options.stream_output.expect("stream_output not set"),
+            tty: options.tty.expect("tty not set"),
+            timeout: options.timeout_seconds.map(Duration::from_secs),
+            limits,
+            sandbox: options.sandbox.unwrap_or(false),
+            sandbox_network: options.sandbox_network.unwrap_or(true),
+            pass_env: options.pass_env.unwrap_or_default(),
+            env: options.env.unwrap_or_default(),
+            work_dir,
+            working_directory,
+            depends_on: options.depends_on.unwrap_or_default(),
+            group: options.group,
+            tags: options.tags.unwrap_or_default(),
             verbose: options.verbose.expect("verbose not set"),
+            on_fail_message: options.on_fail_message,
+            severity: options.severity.unwrap_or_default(),
+            skip_on_branches,
+            only_on_branches,
+            platforms: options.platforms,
+            enabled_if_env: options.enabled_if_env.unwrap_or_default(),
+            disabled_if_env: options.disabled_if_env.unwrap_or_default(),
             minimum_prek_version: options.minimum_prek_version,
+            required: false,
         };
 
         if let Err(err) = extract_metadata_from_entry(&mut hook).await {
@@ -317,6 +443,79 @@ impl HookBuilder {
     }
 }
 
+/// Check that the `depends_on` of the hooks of a project refer to hooks of the project, without
+/// cycles.
+pub(crate) fn check_dependencies(hooks: &[Hook]) -> Result<(), Error> {
+    let mut dependencies: FxHashMap<&str, Vec<&str>> = FxHashMap::default();
+    for hook in hooks {
+        dependencies
+            .entry(hook.id.as_str())
+            .or_default()
+            .extend(hook.depends_on.iter().map(String::as_str));
+    }
+    for hook in hooks {
+        if let Some(id) = hook
+            .depends_on
+            .iter()
+            .find(|id| !dependencies.contains_key(id.as_str()))
+        {
+            return Err(Error::Hook {
+                hook: hook.id.clone(),
+                error: anyhow::anyhow!("`depends_on` refers to unknown hook `{id}`"),
+            });
+        }
+    }
+
+    let mut done = FxHashSet::default();
+    for hook in hooks {
+        visit_dependencies(&hook.id, &dependencies, &mut Vec::new(), &mut done)?;
+    }
+    Ok(())
+}
+
+/// Depth-first search of the dependencies, a hook reached again from itself is a cycle.
+fn visit_dependencies<'a>(
+    id: &'a str,
+    dependencies: &FxHashMap<&'a str, Vec<&'a str>>,
+    path: &mut Vec<&'a str>,
+    done: &mut FxHashSet<&'a str>,
+) -> Result<(), Error> {
+    if done.contains(id) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|&other| other == id) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(id);
+        return Err(Error::Hook {
+            hook: id.to_string(),
+            error: anyhow::anyhow!("`depends_on` has a cycle: {}", cycle.join(" -> ")),
+        });
+    }
+    path.push(id);
+    for dependency in &dependencies[id] {
+        visit_dependencies(dependency, dependencies, path, done)?;
+    }
+    path.pop();
+    done.insert(id);
+    Ok(())
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    builder.build()
+}
+
+/// `NAME` matches if the variable is set to a non-empty value, `NAME=value` if it's set to `value`.
+fn env_condition_matches(condition: &str) -> bool {
+    match condition.split_once('=') {
+        Some((name, value)) => EnvVars::var_os(name).is_some_and(|v| v == value),
+        None => EnvVars::var_os(condition).is_some_and(|v| !v.is_empty()),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum Stages {
     All,
@@ -400,18 +599,48 @@ pub(crate) struct Hook {
     pub types: Vec<String>,
     pub types_or: Vec<String>,
     pub exclude_types: Vec<String>,
+    pub follow_symlinks: bool,
+    pub skip_symlinks: bool,
     pub additional_dependencies: FxHashSet<String>,
     pub args: Vec<String>,
     pub always_run: bool,
     pub fail_fast: bool,
     pub pass_filenames: bool,
+    pub pass_file_contents: Option<PassFileContents>,
+    pub changed_lines: bool,
     pub description: Option<String>,
+    pub homepage: Option<String>,
     pub language_request: LanguageRequest,
     pub log_file: Option<String>,
     pub require_serial: bool,
+    pub concurrency_group: Option<String>,
+    pub cache_results: bool,
+    pub stream_output: bool,
+    pub tty: bool,
+    pub timeout: Option<Duration>,
+    pub limits: ResourceLimits,
+    pub sandbox: bool,
+    pub sandbox_network: bool,
+    pub pass_env: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    /// The directory the hook runs in, relative to the project root.
+    pub working_directory: Option<PathBuf>,
+    work_dir: PathBuf,
+    pub depends_on: Vec<String>,
+    pub group: Option<String>,
+    pub tags: Vec<String>,
     pub stages: Stages,
     pub verbose: bool,
+    pub on_fail_message: Option<String>,
+    pub severity: Severity,
+    pub skip_on_branches: Option<GlobSet>,
+    pub only_on_branches: Option<GlobSet>,
+    pub platforms: Option<Vec<Platform>>,
+    pub enabled_if_env: Vec<String>,
+    pub disabled_if_env: Vec<String>,
     pub minimum_prek_version: Option<String>,
+    /// Whether the hook comes from the required config, it can't be skipped.
+    pub required: bool,
 }
 
 impl Display for Hook {
@@ -425,6 +654,43 @@ impl Display for Hook {
 }
 
 impl Hook {
+    /// Whether the hook runs on the current branch, `None` if `HEAD` is detached.
+    pub(crate) fn runs_on_branch(&self, branch: Option<&str>) -> bool {
+        let Some(branch) = branch else {
+            return self.only_on_branches.is_none();
+        };
+        if self
+            .skip_on_branches
+            .as_ref()
+            .is_some_and(|globs| globs.is_match(branch))
+        {
+            return false;
+        }
+        self.only_on_branches
+            .as_ref()
+            .is_none_or(|globs| globs.is_match(branch))
+    }
+
+    /// Whether the hook runs on the current operating system.
+    pub(crate) fn runs_on_current_platform(&self) -> bool {
+        self.platforms.as_ref().is_none_or(|platforms| {
+            Platform::current().is_some_and(|platform| platforms.contains(&platform))
+        })
+    }
+
+    /// Whether the hook is enabled by its `enabled_if_env` and `disabled_if_env` conditions.
+    pub(crate) fn enabled_by_env(&self) -> bool {
+        (self.enabled_if_env.is_empty()
+            || self
+                .enabled_if_env
+                .iter()
+                .any(|condition| env_condition_matches(condition)))
+            && !self
+                .disabled_if_env
+                .iter()
+                .any(|condition| env_condition_matches(condition))
+    }
+
     pub(crate) fn project(&self) -> &Project {
         &self.project
     }
@@ -449,7 +715,25 @@ impl Hook {
 
     /// Get the path where the hook should be executed.
     pub(crate) fn work_dir(&self) -> &Path {
-        self.project.path()
+        &self.work_dir
+    }
+
+    /// Rewrite filenames relative to the project root to be relative to the working directory
+    /// of the hook, `None` if the hook runs in the project root.
+    pub(crate) fn relative_to_work_dir(&self, filenames: &[&Path]) -> Option<Vec<PathBuf>> {
+        let dir = self.working_directory.as_deref()?;
+        let depth = dir.components().count();
+        Some(
+            filenames
+                .iter()
+                .map(|filename| match filename.strip_prefix(dir) {
+                    Ok(relative) => relative.to_path_buf(),
+                    Err(_) => std::iter::repeat_n(Path::new(".."), depth)
+                        .collect::<PathBuf>()
+                        .join(filename),
+                })
+                .collect(),
+        )
     }
 
     pub(crate) fn is_remote(&self) -> bool {
@@ -500,9 +784,94 @@ impl Display for InstalledHook {
     }
 }
 
-const HOOK_MARKER: &str = ".prek-hook.json";
+pub(crate) const HOOK_MARKER: &str = ".prek-hook.json";
+
+/// Placeholders expanded in the `entry` and `args` of a hook when it runs.
+const PLACEHOLDERS: [&str; 4] = ["{config_root}", "{git_root}", "{hook_env}", "{nproc}"];
 
 impl InstalledHook {
+    /// A copy of the hook that runs in `work_dir` instead of the project directory.
+    pub(crate) fn with_work_dir(&self, work_dir: PathBuf) -> Self {
+        let mut hook = Hook::clone(self);
+        hook.work_dir = match &hook.working_directory {
+            Some(dir) => work_dir.join(dir),
+            None => work_dir.clone(),
+        };
+        hook.project = Arc::new(hook.project.relocated(work_dir));
+        self.with_hook(hook)
+    }
+
+    /// A copy of the hook with the placeholders in its `entry` and `args` expanded, `None` if
+    /// there are none.
+    ///
+    /// Unknown `{...}` are kept as is, so entries like `find -exec {} +` are unaffected.
+    pub(crate) fn expand_placeholders(&self) -> Result<Option<Self>> {
+        let has_placeholder = |value: &str| PLACEHOLDERS.iter().any(|p| value.contains(p));
+        if !has_placeholder(self.entry.raw()) && !self.args.iter().any(|arg| has_placeholder(arg)) {
+            return Ok(None);
+        }
+
+        let entry = self
+            .entry
+            .split()?
+            .iter()
+            .map(|part| self.expand(part))
+            .collect::<Result<Vec<_>>>()?;
+        let mut hook = Hook::clone(self);
+        hook.entry = Entry::new(
+            self.entry.hook.clone(),
+            shlex::try_join(entry.iter().map(String::as_str))?,
+        );
+        hook.args = self
+            .args
+            .iter()
+            .map(|arg| self.expand(arg))
+            .collect::<Result<_>>()?;
+
+        Ok(Some(self.with_hook(hook)))
+    }
+
+    fn expand(&self, value: &str) -> Result<String> {
+        let mut value = value.to_string();
+        for placeholder in PLACEHOLDERS {
+            if !value.contains(placeholder) {
+                continue;
+            }
+            let replacement = match placeholder {
+                "{config_root}" => self.project().path().to_string_lossy().to_string(),
+                "{git_root}" => GIT_ROOT
+                    .as_ref()
+                    .map_err(|err| anyhow::anyhow!("Failed to find the git root: {err}"))?
+                    .to_string_lossy()
+                    .to_string(),
+                "{hook_env}" => self
+                    .env_path()
+                    .with_context(|| {
+                        format!(
+                            "`{{hook_env}}` is used by hook `{}`, which has no environment",
+                            self.id
+                        )
+                    })?
+                    .to_string_lossy()
+                    .to_string(),
+                "{nproc}" => CONCURRENCY.to_string(),
+                _ => unreachable!(),
+            };
+            value = value.replace(placeholder, &replacement);
+        }
+        Ok(value)
+    }
+
+    fn with_hook(&self, hook: Hook) -> Self {
+        match self {
+            InstalledHook::Installed { info, .. } => InstalledHook::Installed {
+                hook: Arc::new(hook),
+                info: info.clone(),
+            },
+            InstalledHook::NoNeedInstall(_) => InstalledHook::NoNeedInstall(Arc::new(hook)),
+        }
+    }
+
     /// Get the path to the environment where the hook is installed.
     pub(crate) fn env_path(&self) -> Option<&Path> {
         match self {
@@ -532,6 +901,16 @@ impl InstalledHook {
             .await
             .context("Failed to write install info")?;
 
+        // Record the checksums of the environment to detect corruption later,
+        // e.g. in caches restored from CI archives.
+        let env_path = info.env_path.clone();
+        tokio::task::spawn_blocking(move || {
+            checksum::Manifest::build(&env_path, &[HOOK_MARKER, checksum::MANIFEST_FILE])?
+                .write(&env_path)
+        })
+        .await?
+        .context("Failed to write checksum manifest")?;
+
         Ok(())
     }
 }
@@ -594,6 +973,25 @@ impl InstallInfo {
         self.language.check_health(self).await
     }
 
+    /// Verify the environment against the checksums recorded at install time.
+    ///
+    /// Returns the missing or modified files, environments installed without checksums are
+    /// considered intact.
+    pub(crate) async fn verify_checksums(&self, mode: Verify) -> Result<Vec<String>> {
+        let env_path = self.env_path.clone();
+        let corrupted = tokio::task::spawn_blocking(move || {
+            let manifest = checksum::Manifest::read(&env_path)?;
+            anyhow::Ok(
+                manifest
+                    .map(|manifest| manifest.verify(&env_path, mode))
+                    .unwrap_or_default(),
+            )
+        })
+        .await??;
+
+        Ok(corrupted)
+    }
+
     pub(crate) fn with_language_version(&mut self, version: semver::Version) -> &mut Self {
         self.language_version = version;
         self
@@ -0,0 +1,159 @@
+//! The environment of the commands run by hooks.
+//!
+//! Hooks only see a curated set of the variables of prek, so they behave the same on every
+//! machine, plus the variables allowed with `pass_env`. The variables set by prek itself, e.g.
+//! `PATH` of a hook environment or `PRE_COMMIT_FROM_REF`, and the `env` of the hook are always
+//! passed.
+
+use std::ffi::{OsStr, OsString};
+
+use constants::env_vars::EnvVars;
+
+/// Variables passed to hooks by default, a trailing `*` matches any suffix.
+const DEFAULT_PASS_ENV: &[&str] = &[
+    // System
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "COLORTERM",
+    "LANG",
+    "LANGUAGE",
+    "LC_*",
+    "TZ",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "XDG_*",
+    "SSH_AUTH_SOCK",
+    // Windows
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERNAME",
+    "USERPROFILE",
+    "HOMEDRIVE",
+    "HOMEPATH",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+    "PROGRAMFILES*",
+    "COMMONPROGRAMFILES*",
+    "NUMBER_OF_PROCESSORS",
+    "PROCESSOR_ARCHITECTURE",
+    "OS",
+    // Network
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "ALL_PROXY",
+    "NO_PROXY",
+    "http_proxy",
+    "https_proxy",
+    "all_proxy",
+    "no_proxy",
+    "SSL_CERT_FILE",
+    "SSL_CERT_DIR",
+    // Toolchains and package indexes
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "JAVA_HOME",
+    "GOPATH",
+    "GOPROXY",
+    "GOFLAGS",
+    "NODE_OPTIONS",
+    "NODE_EXTRA_CA_CERTS",
+    "VIRTUAL_ENV",
+    "PYTHONPATH",
+    "LD_LIBRARY_PATH",
+    "REQUESTS_CA_BUNDLE",
+    "PIP_INDEX_URL",
+    "PIP_EXTRA_INDEX_URL",
+    // Colors
+    "NO_COLOR",
+    "FORCE_COLOR",
+    "CLICOLOR",
+    "CLICOLOR_FORCE",
+    // Docker hooks
+    "DOCKER_*",
+    // Git, pre-commit and prek
+    "CI",
+    "GIT_*",
+    "PRE_COMMIT*",
+    "PREK_*",
+];
+
+/// Whether the variable `name` matches `pattern`, a trailing `*` matches any suffix.
+fn matches(name: &OsStr, pattern: &str) -> bool {
+    let name = name.to_string_lossy();
+    let (name, pattern) = if cfg!(windows) {
+        // Variable names are case-insensitive on Windows.
+        (name.to_uppercase(), pattern.to_uppercase())
+    } else {
+        (name.into_owned(), pattern.to_string())
+    };
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+/// Whether `pass_env` passes all the variables of prek.
+pub(crate) fn passes_all(pass_env: &[String]) -> bool {
+    pass_env.iter().any(|pattern| pattern == "*")
+}
+
+/// The variables of prek passed to a hook, by default or with `pass_env`.
+pub(crate) fn passed_vars(pass_env: &[String]) -> impl Iterator<Item = (OsString, OsString)> {
+    std::env::vars_os().filter(move |(name, _)| {
+        DEFAULT_PASS_ENV
+            .iter()
+            .copied()
+            .chain(pass_env.iter().map(String::as_str))
+            .any(|pattern| matches(name, pattern))
+    })
+}
+
+/// Replace each `${VAR}` in `value` with the variable of prek, empty if it's not set.
+pub(crate) fn interpolate(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        let name = &rest[start + 2..start + 2 + len];
+        result.push_str(&EnvVars::var(name).unwrap_or_default());
+        rest = &rest[start + 2 + len + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::{interpolate, matches};
+
+    #[test]
+    fn pattern() {
+        assert!(matches(OsStr::new("PATH"), "PATH"));
+        assert!(!matches(OsStr::new("PATHS"), "PATH"));
+        assert!(matches(OsStr::new("LC_ALL"), "LC_*"));
+        assert!(matches(OsStr::new("AWS_PROFILE"), "AWS_*"));
+        assert!(!matches(OsStr::new("MY_AWS_PROFILE"), "AWS_*"));
+        assert!(matches(OsStr::new("ANYTHING"), "*"));
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(interpolate("plain"), "plain");
+        assert_eq!(interpolate("a${PREK_TEST_UNSET_VARIABLE}b"), "ab");
+        assert_eq!(interpolate("$HOME ${unclosed"), "$HOME ${unclosed");
+    }
+}
@@ -0,0 +1,25 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use tracing::warn;
+
+use crate::settings::settings;
+use crate::version;
+
+/// The HTTP client shared by all downloads.
+///
+/// The `proxy` user setting is used unless `HTTP_PROXY` or `HTTPS_PROXY` is set, which reqwest
+/// reads itself. Connections that stall are aborted, downloads of any size are not.
+pub(crate) static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(format!("prek/{}", version::version().version))
+        .connect_timeout(Duration::from_secs(30))
+        .read_timeout(Duration::from_secs(60));
+    if let Some(proxy) = settings().proxy() {
+        match reqwest::Proxy::all(proxy) {
+            Ok(proxy) => builder = builder.proxy(proxy),
+            Err(err) => warn!("Ignoring invalid proxy `{proxy}`: {err}"),
+        }
+    }
+    builder.build().expect("Failed to build the HTTP client")
+});
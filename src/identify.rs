@@ -26,7 +26,7 @@ use std::vec;
 use anyhow::Result;
 use rustc_hash::{FxHashMap, FxHashSet};
 
-mod tags {
+pub(crate) mod tags {
     pub const DIRECTORY: &str = "directory";
     pub const SYMLINK: &str = "symlink";
     pub const SOCKET: &str = "socket";
@@ -716,7 +716,7 @@ fn is_encoding_tag(tag: &str) -> bool {
     matches!(tag, tags::TEXT | tags::BINARY)
 }
 
-pub(crate) fn tags_from_path(path: &Path) -> Result<Vec<&str>> {
+pub(crate) fn tags_from_path(path: &Path) -> Result<Vec<&'static str>> {
     let metadata = std::fs::symlink_metadata(path)?;
     if metadata.is_dir() {
         return Ok(vec![tags::DIRECTORY]);
@@ -779,16 +779,17 @@ pub(crate) fn tags_from_path(path: &Path) -> Result<Vec<&str>> {
     Ok(tags.into_iter().collect())
 }
 
-fn tags_from_filename(filename: &Path) -> Vec<&str> {
+fn tags_from_filename(filename: &Path) -> Vec<&'static str> {
     let ext = filename.extension().and_then(|ext| ext.to_str());
+    // Names that aren't valid UTF-8 can still match by their extension.
     let filename = filename
         .file_name()
-        .and_then(|name| name.to_str())
-        .expect("Invalid filename");
+        .expect("Invalid filename")
+        .to_string_lossy();
 
     let mut result = FxHashSet::default();
 
-    if let Some(tags) = by_filename().get(filename) {
+    if let Some(tags) = by_filename().get(filename.as_ref()) {
         for tag in *tags {
             result.insert(*tag);
         }
@@ -150,11 +150,16 @@ impl Docker {
         Ok(path.into())
     }
 
-    pub(crate) fn docker_run_cmd(work_dir: &Path) -> Result<Cmd> {
+    /// The project of the hook is mounted at `/src`, the command runs in the working directory
+    /// of the hook.
+    pub(crate) fn docker_run_cmd(hook: &Hook) -> Result<Cmd> {
         let mut command = Cmd::new("docker", "run container");
         command.arg("run").arg("--rm");
 
-        if *USE_COLOR {
+        // The content of files is passed on stdin, which can't go through a terminal.
+        if hook.pass_file_contents.is_some() {
+            command.arg("--interactive");
+        } else if *USE_COLOR {
             command.arg("--tty");
         }
 
@@ -167,17 +172,21 @@ impl Docker {
             }));
         }
 
-        let work_dir = Self::get_docker_path(work_dir)?;
+        let project = Self::get_docker_path(hook.project().path())?;
+        let work_dir = match &hook.working_directory {
+            Some(dir) => format!("/src/{}", dir.to_string_lossy().replace('\\', "/")),
+            None => "/src".to_string(),
+        };
         command
             // https://docs.docker.com/engine/reference/commandline/run/#mount-volumes-from-container-volumes-from
             // The `Z` option tells Docker to label the content with a private
             // unshared label. Only the current container can use a private volume.
             .arg("--volume")
-            .arg(format!("{}:/src:rw,Z", work_dir.display()))
+            .arg(format!("{}:/src:rw,Z", project.display()))
             // Run an init inside the container that forwards signals and reaps processes
             .arg("--init")
             .arg("--workdir")
-            .arg("/src");
+            .arg(work_dir);
 
         Ok(command)
     }
@@ -202,6 +211,7 @@ impl LanguageImpl for Docker {
             info: Arc::new(info),
         };
 
+        reporter.on_install_step(progress, "building image");
         Docker::build_docker_image(&installed_hook, true)
             .await
             .context("Failed to build docker image")?;
@@ -235,7 +245,7 @@ impl LanguageImpl for Docker {
 
         let run = async move |batch: &[&Path]| {
             // docker run [OPTIONS] IMAGE [COMMAND] [ARG...]
-            let mut cmd = Docker::docker_run_cmd(hook.work_dir())?;
+            let mut cmd = Docker::docker_run_cmd(hook)?;
             let mut output = cmd
                 .current_dir(hook.work_dir())
                 .arg("--entrypoint")
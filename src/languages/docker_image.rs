@@ -35,7 +35,7 @@ impl LanguageImpl for DockerImage {
     ) -> Result<(i32, Vec<u8>)> {
         let entry = hook.entry.resolve(None)?;
         let run = async move |batch: &[&Path]| {
-            let mut cmd = Docker::docker_run_cmd(hook.work_dir())?;
+            let mut cmd = Docker::docker_run_cmd(hook)?;
             let mut output = cmd
                 .current_dir(hook.work_dir())
                 .args(&entry[..])
@@ -8,10 +8,10 @@ use constants::env_vars::EnvVars;
 
 use crate::cli::reporter::HookInstallReporter;
 use crate::hook::{Hook, InstallInfo, InstalledHook};
-use crate::languages::LanguageImpl;
 use crate::languages::golang::GoRequest;
 use crate::languages::golang::installer::GoInstaller;
 use crate::languages::version::LanguageRequest;
+use crate::languages::{HookEnvVars, LanguageImpl, env_path};
 use crate::process::Cmd;
 use crate::run::{prepend_paths, run_by_batch};
 use crate::store::{CacheBucket, Store, ToolBucket};
@@ -29,6 +29,7 @@ impl LanguageImpl for Golang {
         let progress = reporter.on_install_start(&hook);
 
         // 1. Install Go
+        reporter.on_install_step(progress, "installing go");
         let go_dir = store.tools_path(crate::store::ToolBucket::Go);
         let installer = GoInstaller::new(go_dir);
 
@@ -85,6 +86,7 @@ impl LanguageImpl for Golang {
         };
 
         // GOPATH used to store downloaded source code (in $GOPATH/pkg/mod)
+        reporter.on_install_step(progress, "installing dependencies");
         if let Some(repo) = hook.repo_path() {
             go_install_cmd()
                 .arg("./...")
@@ -115,14 +117,9 @@ impl LanguageImpl for Golang {
         Ok(())
     }
 
-    async fn run(
-        &self,
-        hook: &InstalledHook,
-        filenames: &[&Path],
-        store: &Store,
-    ) -> anyhow::Result<(i32, Vec<u8>)> {
-        let env_dir = hook.env_path().expect("Node hook must have env path");
-        let info = hook.install_info().expect("Node hook must be installed");
+    fn env_vars(&self, hook: &InstalledHook, store: &Store) -> anyhow::Result<HookEnvVars> {
+        let env_dir = hook.env_path().expect("Go hook must have env path");
+        let info = hook.install_info().expect("Go hook must be installed");
 
         let go_bin = bin_dir(env_dir);
         let go_tools = store.tools_path(ToolBucket::Go);
@@ -130,23 +127,34 @@ impl LanguageImpl for Golang {
         let go_root = go_root_bin.parent().expect("Go root should exist");
         let go_cache = store.cache_path(CacheBucket::Go);
 
-        // Only set GOROOT and GOPATH if using the Go installed by prek
-        let go_envs = if go_root_bin.starts_with(go_tools) {
-            vec![(EnvVars::GOROOT, go_root), (EnvVars::GOPATH, &go_cache)]
-        } else {
-            vec![]
-        };
         let new_path = prepend_paths(&[&go_bin, go_root_bin]).context("Failed to join PATH")?;
+        let mut env_vars = vec![
+            (EnvVars::PATH, Some(new_path)),
+            (EnvVars::GOTOOLCHAIN, Some("local".into())),
+            (EnvVars::GOBIN, Some(go_bin.into())),
+        ];
+        // Only set GOROOT and GOPATH if using the Go installed by prek
+        if go_root_bin.starts_with(go_tools) {
+            env_vars.push((EnvVars::GOROOT, Some(go_root.into())));
+            env_vars.push((EnvVars::GOPATH, Some(go_cache.into())));
+        }
+        Ok(env_vars)
+    }
+
+    async fn run(
+        &self,
+        hook: &InstalledHook,
+        filenames: &[&Path],
+        store: &Store,
+    ) -> anyhow::Result<(i32, Vec<u8>)> {
+        let env_vars = self.env_vars(hook, store)?;
 
-        let entry = hook.entry.resolve(Some(&new_path))?;
+        let entry = hook.entry.resolve(env_path(&env_vars))?;
         let run = async move |batch: &[&Path]| {
             let mut output = Cmd::new(&entry[0], "go hook")
                 .current_dir(hook.work_dir())
                 .args(&entry[1..])
-                .env("PATH", &new_path)
-                .env(EnvVars::GOTOOLCHAIN, "local")
-                .env(EnvVars::GOBIN, &go_bin)
-                .envs(go_envs.iter().copied())
+                .env_vars(&env_vars)
                 .args(&hook.args)
                 .args(batch)
                 .check(false)
@@ -9,8 +9,9 @@ use reqwest::Client;
 use target_lexicon::{Architecture, HOST, OperatingSystem};
 use tracing::{debug, trace, warn};
 
-use crate::fs::LockedFile;
+use crate::fs::{LockedFile, long_path};
 use crate::git;
+use crate::http_client::HTTP_CLIENT;
 use crate::languages::download_and_extract;
 use crate::languages::golang::GoRequest;
 use crate::languages::golang::golang::bin_dir;
@@ -95,7 +96,7 @@ impl GoInstaller {
     pub(crate) fn new(root: PathBuf) -> Self {
         Self {
             root,
-            client: Client::new(),
+            client: HTTP_CLIENT.clone(),
         }
     }
 
@@ -214,7 +215,7 @@ impl GoInstaller {
         download_and_extract(&self.client, &url, &filename, async |extracted| {
             if target.exists() {
                 debug!(target = %target.display(), "Removing existing go");
-                fs_err::tokio::remove_dir_all(&target).await?;
+                fs_err::tokio::remove_dir_all(long_path(&target)).await?;
             }
 
             debug!(?extracted, target = %target.display(), "Moving go to target");
@@ -1,17 +1,20 @@
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
-use futures::TryStreamExt;
+use futures::{Stream, TryStreamExt};
 use http::header::USER_AGENT;
+use sha2::{Digest, Sha256};
 use tokio_util::compat::FuturesAsyncReadCompatExt;
-use tracing::{debug, trace};
+use tracing::{debug, instrument, trace};
+
+use constants::env_vars::EnvVars;
 
 use crate::archive::ArchiveExtension;
 use crate::cli::reporter::HookInstallReporter;
 use crate::config::Language;
-use crate::fs::CWD;
+use crate::fs::{CWD, long_path};
 use crate::hook::{Hook, InstallInfo, InstalledHook};
 use crate::identify::parse_shebang;
 use crate::store::{STORE, Store};
@@ -48,6 +51,11 @@ trait LanguageImpl {
         reporter: &HookInstallReporter,
     ) -> Result<InstalledHook>;
     async fn check_health(&self, info: &InstallInfo) -> Result<()>;
+    /// The environment variables the commands of an installed hook run with, besides the
+    /// variables of prek. Variables without a value are removed.
+    fn env_vars(&self, _hook: &InstalledHook, _store: &Store) -> Result<HookEnvVars> {
+        Ok(Vec::new())
+    }
     async fn run(
         &self,
         hook: &InstalledHook,
@@ -56,6 +64,16 @@ trait LanguageImpl {
     ) -> Result<(i32, Vec<u8>)>;
 }
 
+/// Environment variables set for a hook, `None` removes the variable.
+pub(crate) type HookEnvVars = Vec<(&'static str, Option<OsString>)>;
+
+/// The `PATH` of the environment variables of a hook, if they set it.
+pub(crate) fn env_path<'a>(vars: &'a [(&'static str, Option<OsString>)]) -> Option<&'a OsStr> {
+    vars.iter()
+        .find(|(key, _)| *key == EnvVars::PATH)
+        .and_then(|(_, val)| val.as_deref())
+}
+
 #[derive(thiserror::Error, Debug)]
 #[error("Language `{0}` is not implemented yet")]
 struct UnimplementedError(String);
@@ -159,6 +177,7 @@ impl Language {
         )
     }
 
+    #[instrument(level = "trace", skip_all, fields(hook = %hook))]
     pub async fn install(
         &self,
         hook: Arc<Hook>,
@@ -194,17 +213,36 @@ impl Language {
         }
     }
 
+    /// The environment variables the commands of an installed hook run with.
+    pub(crate) fn env_vars(self, hook: &InstalledHook, store: &Store) -> Result<HookEnvVars> {
+        match self {
+            Self::Golang => GOLANG.env_vars(hook, store),
+            Self::Python => PYTHON.env_vars(hook, store),
+            Self::Node => NODE.env_vars(hook, store),
+            _ => Ok(Vec::new()),
+        }
+    }
+
     pub async fn run(
         &self,
         hook: &InstalledHook,
         filenames: &[&Path],
         store: &Store,
     ) -> Result<(i32, Vec<u8>)> {
+        let expanded = hook.expand_placeholders()?;
+        let hook = expanded.as_ref().unwrap_or(hook);
+
         // fast path for hooks implemented in Rust
         if builtin::check_fast_path(hook) {
             return builtin::run_fast_path(hook, filenames).await;
         }
 
+        let relative = hook.relative_to_work_dir(filenames);
+        let relative = relative
+            .as_ref()
+            .map(|filenames| filenames.iter().map(PathBuf::as_path).collect::<Vec<_>>());
+        let filenames = relative.as_deref().unwrap_or(filenames);
+
         match self {
             Self::Golang => GOLANG.run(hook, filenames, store).await,
             Self::Python => PYTHON.run(hook, filenames, store).await,
@@ -243,10 +281,12 @@ pub(crate) fn resolve_command(mut cmds: Vec<String>, env_path: Option<&OsStr>) -
     };
     trace!("Resolved command: {}", exe_path.display());
 
-    if let Ok(mut interpreter) = parse_shebang(&exe_path) {
+    if let Ok(mut interpreter) = parse_shebang(&exe_path)
+        && !interpreter.is_empty()
+    {
         trace!("Found shebang: {:?}", interpreter);
         // Resolve the interpreter path, convert "python3" to "python3.exe" on Windows
-        if let Ok(p) = which::which_in(&interpreter[0], env_path, &*CWD) {
+        if let Some(p) = resolve_interpreter(&interpreter[0], env_path) {
             interpreter[0] = p.to_string_lossy().to_string();
             trace!("Resolved interpreter: {}", &interpreter[0]);
         }
@@ -259,6 +299,61 @@ pub(crate) fn resolve_command(mut cmds: Vec<String>, env_path: Option<&OsStr>) -
     }
 }
 
+/// Resolve the interpreter of a shebang line.
+///
+/// Unix interpreter paths like `/bin/sh` or `/usr/bin/python3` don't exist on Windows,
+/// fall back to looking up the file name in `PATH`, and `python3` to `python`, which is
+/// what the official Windows installers provide.
+fn resolve_interpreter(interpreter: &str, env_path: Option<&OsStr>) -> Option<PathBuf> {
+    if let Ok(path) = which::which_in(interpreter, env_path, &*CWD) {
+        return Some(path);
+    }
+    if !cfg!(windows) {
+        return None;
+    }
+
+    let name = Path::new(interpreter).file_name()?.to_str()?;
+    which::which_in(name, env_path, &*CWD).ok().or_else(|| {
+        let name = name.strip_suffix('3').filter(|name| *name == "python")?;
+        which::which_in(name, env_path, &*CWD).ok()
+    })
+}
+
+/// Unpack the archive downloaded from `url` into `target`, and verify its SHA-256 checksum
+/// against the one recorded the first time it was downloaded.
+async fn unpack_verified<S, B>(
+    store: &Store,
+    url: &str,
+    stream: S,
+    ext: ArchiveExtension,
+    target: &Path,
+) -> Result<()>
+where
+    S: Stream<Item = std::io::Result<B>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    let mut hasher = Sha256::new();
+    let mut tarball = stream
+        .inspect_ok(|chunk| hasher.update(chunk))
+        .into_async_read()
+        .compat();
+    archive::unpack(&mut tarball, ext, target).await?;
+    // Archives may end before the download does, the checksum covers the whole file.
+    tokio::io::copy(&mut tarball, &mut tokio::io::sink()).await?;
+    drop(tarball);
+
+    let checksum = hex::encode(hasher.finalize());
+    if let Some(recorded) = store.record_checksum(url, &checksum).await?
+        && recorded != checksum
+    {
+        anyhow::bail!(
+            "Checksum mismatch for {url}:\n  expected: {recorded}\n  actual:   {checksum}\nIf the file was changed upstream on purpose, remove its entry from `{}`",
+            store.checksums_file().display()
+        );
+    }
+    Ok(())
+}
+
 async fn download_and_extract(
     client: &reqwest::Client,
     url: &str,
@@ -279,20 +374,16 @@ async fn download_and_extract(
         );
     }
 
-    let tarball = response
-        .bytes_stream()
-        .map_err(std::io::Error::other)
-        .into_async_read()
-        .compat();
-
-    let scratch = STORE.as_ref()?.scratch_path();
+    let store = STORE.as_ref()?;
+    let scratch = store.scratch_path();
     fs_err::tokio::create_dir_all(&scratch).await?;
 
     let temp_dir = tempfile::tempdir_in(&scratch)?;
     debug!(url = %url, temp_dir = ?temp_dir.path(), "Downloading");
 
     let ext = ArchiveExtension::from_path(filename)?;
-    archive::unpack(tarball, ext, temp_dir.path()).await?;
+    let stream = response.bytes_stream().map_err(std::io::Error::other);
+    unpack_verified(store, url, stream, ext, &long_path(temp_dir.path())).await?;
 
     let extracted = match archive::strip_component(temp_dir.path()) {
         Ok(top_level) => top_level,
@@ -302,7 +393,66 @@ async fn download_and_extract(
 
     callback(&extracted).await?;
 
-    fs_err::tokio::remove_dir_all(temp_dir.path()).await?;
+    fs_err::tokio::remove_dir_all(long_path(temp_dir.path())).await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/tool.tar";
+
+    async fn tarball(content: &[u8]) -> Vec<u8> {
+        let mut builder = tokio_tar::Builder::new(Vec::new());
+        let mut header = tokio_tar::Header::new_gnu();
+        header.set_size(u64::try_from(content.len()).unwrap());
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "tool/bin", content)
+            .await
+            .unwrap();
+        builder.into_inner().await.unwrap()
+    }
+
+    async fn unpack(store: &Store, tarball: Vec<u8>, target: &Path) -> Result<()> {
+        fs_err::tokio::create_dir_all(target).await?;
+        let stream = futures::stream::iter([Ok(tarball)]);
+        unpack_verified(store, URL, stream, ArchiveExtension::Tar, target).await
+    }
+
+    #[tokio::test]
+    async fn checksum_matches() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = Store::from_path(dir.path());
+
+        // The first download records the checksum, later ones are verified against it.
+        unpack(&store, tarball(b"v1").await, &dir.path().join("first")).await?;
+        let target = dir.path().join("second");
+        unpack(&store, tarball(b"v1").await, &target).await?;
+        assert_eq!(fs_err::read(target.join("tool/bin"))?, b"v1");
+
+        let checksums = fs_err::read_to_string(store.checksums_file())?;
+        assert!(checksums.contains(URL));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = Store::from_path(dir.path());
+
+        unpack(&store, tarball(b"v1").await, &dir.path().join("first")).await?;
+        let err = unpack(&store, tarball(b"v2").await, &dir.path().join("second"))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with(&format!("Checksum mismatch for {URL}")),
+            "{err}"
+        );
+        Ok(())
+    }
+}
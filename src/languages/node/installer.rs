@@ -10,7 +10,8 @@ use reqwest::Client;
 use target_lexicon::{Architecture, HOST, OperatingSystem};
 use tracing::{debug, trace, warn};
 
-use crate::fs::LockedFile;
+use crate::fs::{LockedFile, long_path};
+use crate::http_client::HTTP_CLIENT;
 use crate::languages::download_and_extract;
 use crate::languages::node::NodeRequest;
 use crate::languages::node::version::NodeVersion;
@@ -91,7 +92,7 @@ impl NodeInstaller {
     pub(crate) fn new(root: PathBuf) -> Self {
         Self {
             root,
-            client: Client::new(),
+            client: HTTP_CLIENT.clone(),
         }
     }
 
@@ -204,7 +205,7 @@ impl NodeInstaller {
         download_and_extract(&self.client, &url, &filename, async |extracted| {
             if target.exists() {
                 debug!(target = %target.display(), "Removing existing node");
-                fs_err::tokio::remove_dir_all(&target).await?;
+                fs_err::tokio::remove_dir_all(long_path(&target)).await?;
             }
 
             debug!(?extracted, target = %target.display(), "Moving node to target");
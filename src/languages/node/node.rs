@@ -11,11 +11,11 @@ use tracing::debug;
 use crate::cli::reporter::HookInstallReporter;
 use crate::hook::InstalledHook;
 use crate::hook::{Hook, InstallInfo};
-use crate::languages::LanguageImpl;
 use crate::languages::node::NodeRequest;
 use crate::languages::node::installer::{NodeInstaller, NodeResult, bin_dir, lib_dir};
 use crate::languages::node::version::EXTRA_KEY_LTS;
 use crate::languages::version::LanguageRequest;
+use crate::languages::{HookEnvVars, LanguageImpl, env_path};
 use crate::process::Cmd;
 use crate::run::{prepend_paths, run_by_batch};
 use crate::store::{Store, ToolBucket};
@@ -40,6 +40,7 @@ impl LanguageImpl for Node {
         // 3. Install dependencies
 
         // 1. Install node
+        reporter.on_install_step(progress, "installing node");
         let node_dir = store.tools_path(ToolBucket::Node);
         let installer = NodeInstaller::new(node_dir);
 
@@ -90,6 +91,7 @@ impl LanguageImpl for Node {
         if deps.is_empty() {
             debug!("No dependencies to install");
         } else {
+            reporter.on_install_step(progress, "installing dependencies");
             // npm install <folder>:
             // If <folder> sits inside the root of your project, its dependencies will be installed
             // and may be hoisted to the top-level node_modules as they would for other types of dependencies.
@@ -146,24 +148,31 @@ impl LanguageImpl for Node {
         Ok(())
     }
 
+    fn env_vars(&self, hook: &InstalledHook, _store: &Store) -> Result<HookEnvVars> {
+        let env_dir = hook.env_path().expect("Node must have env path");
+        let new_path = prepend_paths(&[&bin_dir(env_dir)]).context("Failed to join PATH")?;
+        Ok(vec![
+            (EnvVars::PATH, Some(new_path)),
+            (EnvVars::NPM_CONFIG_PREFIX, Some(env_dir.into())),
+            (EnvVars::NPM_CONFIG_USERCONFIG, None),
+            (EnvVars::NODE_PATH, Some(lib_dir(env_dir).into())),
+        ])
+    }
+
     async fn run(
         &self,
         hook: &InstalledHook,
         filenames: &[&Path],
-        _store: &Store,
+        store: &Store,
     ) -> Result<(i32, Vec<u8>)> {
-        let env_dir = hook.env_path().expect("Node must have env path");
-        let new_path = prepend_paths(&[&bin_dir(env_dir)]).context("Failed to join PATH")?;
+        let env_vars = self.env_vars(hook, store)?;
 
-        let entry = hook.entry.resolve(Some(&new_path))?;
+        let entry = hook.entry.resolve(env_path(&env_vars))?;
         let run = async move |batch: &[&Path]| {
             let mut output = Cmd::new(&entry[0], "node hook")
                 .current_dir(hook.work_dir())
                 .args(&entry[1..])
-                .env("PATH", &new_path)
-                .env(EnvVars::NPM_CONFIG_PREFIX, env_dir)
-                .env_remove(EnvVars::NPM_CONFIG_USERCONFIG)
-                .env(EnvVars::NODE_PATH, lib_dir(env_dir))
+                .env_vars(&env_vars)
                 .args(&hook.args)
                 .args(batch)
                 .check(false)
@@ -13,7 +13,7 @@ use crate::languages::LanguageImpl;
 use crate::languages::python::{Uv, python_exec, query_python_info};
 use crate::process::Cmd;
 use crate::run::CONCURRENCY;
-use crate::store::{CacheBucket, Store, ToolBucket};
+use crate::store::{Store, ToolBucket};
 
 #[derive(Debug, Default)]
 struct Args {
@@ -190,14 +190,12 @@ impl LanguageImpl for Pygrep {
         &self,
         hook: &InstalledHook,
         filenames: &[&Path],
-        store: &Store,
+        _store: &Store,
     ) -> Result<(i32, Vec<u8>)> {
         let info = hook.install_info().expect("Pygrep hook must be installed");
 
-        let cache = store.cache_path(CacheBucket::Python);
-        fs_err::tokio::create_dir_all(&cache).await?;
-
-        let py_script = tempfile::NamedTempFile::new_in(cache)?;
+        // In the environment of the hook, so that it's readable in the sandbox.
+        let py_script = tempfile::NamedTempFile::new_in(&info.env_path)?;
         fs_err::tokio::write(&py_script, SCRIPT)
             .await
             .context("Failed to write Python script")?;
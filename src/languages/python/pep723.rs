@@ -259,7 +259,7 @@ pub(crate) async fn extract_pep723_metadata(hook: &mut Hook) -> Result<()> {
         return Ok(());
     }
 
-    let repo_path = hook.repo_path().unwrap_or(hook.work_dir());
+    let repo_path = hook.repo_path().unwrap_or(hook.project().path());
 
     let split = hook.entry.split()?;
     let file = repo_path.join(&split[0]);
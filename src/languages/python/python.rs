@@ -10,10 +10,10 @@ use constants::env_vars::EnvVars;
 use crate::cli::reporter::HookInstallReporter;
 use crate::hook::InstalledHook;
 use crate::hook::{Hook, InstallInfo};
-use crate::languages::LanguageImpl;
 use crate::languages::python::PythonRequest;
 use crate::languages::python::uv::Uv;
 use crate::languages::version::LanguageRequest;
+use crate::languages::{HookEnvVars, LanguageImpl, env_path};
 use crate::process;
 use crate::process::Cmd;
 use crate::run::{prepend_paths, run_by_batch};
@@ -103,11 +103,15 @@ impl LanguageImpl for Python {
         let python_request = to_uv_python_request(&hook.language_request);
 
         // Create venv (auto download Python if needed)
+        reporter.on_install_step(progress, "creating virtual environment");
         Self::create_venv_with_retry(&uv, store, &info, python_request.as_ref())
             .await
             .context("Failed to create Python virtual environment")?;
 
         // Install dependencies
+        if hook.repo_path().is_some() || !hook.additional_dependencies.is_empty() {
+            reporter.on_install_step(progress, "installing dependencies");
+        }
         if let Some(repo_path) = hook.repo_path() {
             trace!(
                 "Installing dependencies from repo path: {}",
@@ -178,23 +182,30 @@ impl LanguageImpl for Python {
         Ok(())
     }
 
+    fn env_vars(&self, hook: &InstalledHook, _store: &Store) -> Result<HookEnvVars> {
+        let env_dir = hook.env_path().expect("Python must have env path");
+        let new_path = prepend_paths(&[&bin_dir(env_dir)]).context("Failed to join PATH")?;
+        Ok(vec![
+            (EnvVars::VIRTUAL_ENV, Some(env_dir.into())),
+            (EnvVars::PATH, Some(new_path)),
+            (EnvVars::PYTHONHOME, None),
+        ])
+    }
+
     async fn run(
         &self,
         hook: &InstalledHook,
         filenames: &[&Path],
-        _store: &Store,
+        store: &Store,
     ) -> Result<(i32, Vec<u8>)> {
-        let env_dir = hook.env_path().expect("Python must have env path");
-        let new_path = prepend_paths(&[&bin_dir(env_dir)]).context("Failed to join PATH")?;
-        let entry = hook.entry.resolve(Some(&new_path))?;
+        let env_vars = self.env_vars(hook, store)?;
+        let entry = hook.entry.resolve(env_path(&env_vars))?;
 
         let run = async move |batch: &[&Path]| {
             let mut output = Cmd::new(&entry[0], "python hook")
                 .current_dir(hook.work_dir())
                 .args(&entry[1..])
-                .env("VIRTUAL_ENV", env_dir)
-                .env("PATH", &new_path)
-                .env_remove("PYTHONHOME")
+                .env_vars(&env_vars)
                 .args(&hook.args)
                 .args(batch)
                 .check(false)
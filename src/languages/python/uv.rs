@@ -13,7 +13,8 @@ use tracing::{debug, trace, warn};
 
 use constants::env_vars::EnvVars;
 
-use crate::fs::LockedFile;
+use crate::fs::{LockedFile, long_path};
+use crate::http_client::HTTP_CLIENT;
 use crate::languages::download_and_extract;
 use crate::process::Cmd;
 use crate::store::{CacheBucket, Store};
@@ -157,14 +158,14 @@ impl InstallSource {
             "https://github.com/astral-sh/uv/releases/download/{CUR_UV_VERSION}/{archive_name}"
         );
 
-        let client = reqwest::Client::new();
+        let client = HTTP_CLIENT.clone();
         download_and_extract(&client, &download_url, &archive_name, async |extracted| {
             let source = extracted.join("uv").with_extension(EXE_EXTENSION);
             let target_path = target.join("uv").with_extension(EXE_EXTENSION);
 
             if target_path.exists() {
                 debug!(target = %target.display(), "Removing existing uv");
-                fs_err::tokio::remove_dir_all(&target).await?;
+                fs_err::tokio::remove_dir_all(long_path(target)).await?;
             }
 
             debug!(?source, target = %target_path.display(), "Moving uv to target");
@@ -184,7 +185,7 @@ impl InstallSource {
         let wheel_name = format!("uv-{CUR_UV_VERSION}-py3-none-{platform_tag}.whl");
 
         // Use PyPI JSON API instead of parsing HTML
-        let client = reqwest::Client::new();
+        let client = HTTP_CLIENT.clone();
         let api_url = match source {
             PyPiMirror::Pypi => format!("https://pypi.org/pypi/uv/{CUR_UV_VERSION}/json"),
             // For mirrors, we'll fall back to simple API approach
@@ -236,7 +237,7 @@ impl InstallSource {
         let wheel_name = format!("uv-{CUR_UV_VERSION}-py3-none-{platform_tag}.whl");
 
         let simple_url = format!("{}uv/", source.url());
-        let client = reqwest::Client::new();
+        let client = HTTP_CLIENT.clone();
 
         debug!("Fetching from simple API: {}", simple_url);
         let response = client
@@ -299,7 +300,7 @@ impl InstallSource {
             let target_path = target.join("uv").with_extension(EXE_EXTENSION);
             if target_path.exists() {
                 debug!(target = %target.display(), "Removing existing uv");
-                fs_err::tokio::remove_dir_all(&target).await?;
+                fs_err::tokio::remove_dir_all(long_path(target)).await?;
             }
 
             debug!(?extracted_uv, target = %target_path.display(), "Moving uv to target");
@@ -424,7 +425,7 @@ impl Uv {
             Ok(best)
         }
 
-        let client = reqwest::Client::new();
+        let client = HTTP_CLIENT.clone();
         let source = tokio::select! {
                 Ok(true) = check_github(&client) => InstallSource::GitHub,
                 Ok(source) = select_best_pypi(&client) => InstallSource::PyPi(source),
@@ -38,7 +38,7 @@ impl LanguageImpl for Script {
         // For remote hooks, the path is relative to the repo root.
         // For local hooks, the path is relative to the current working directory.
 
-        let repo_path = hook.repo_path().unwrap_or(hook.work_dir());
+        let repo_path = hook.repo_path().unwrap_or(hook.project().path());
         let mut split = hook.entry.split()?;
 
         let cmd = repo_path.join(&split[0]);
@@ -0,0 +1,339 @@
+//! Enforce the `limits` of hooks on the commands they run.
+//!
+//! On Unix, the limits are resource limits (`setrlimit`) of the command, inherited by its
+//! child processes. Each process has its own limits, a command can use more than the limit
+//! across the processes it starts. The process limit of `setrlimit` counts all the processes
+//! of the user, so the number of processes is not limited. On Windows, the command is assigned
+//! to a Job Object, whose limits apply to the command and its child processes together.
+
+use std::fmt::Display;
+use std::process::Output;
+
+use tokio::process::{Child, Command};
+
+use crate::config::ResourceLimits;
+
+/// The limit a command was killed for exceeding.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Exceeded {
+    /// Only detected on Windows, a failed allocation on Unix is not attributed to the limit.
+    #[cfg_attr(unix, allow(dead_code))]
+    Memory(u64),
+    Cpu(u64),
+    /// Killed by a signal that can't be attributed to a limit, e.g. `SIGKILL` sent at the hard
+    /// CPU time limit or by the OOM killer.
+    Signal(i32),
+}
+
+impl Exceeded {
+    /// Whether the command is known to have exceeded one of its limits.
+    pub(crate) fn is_over_limit(self) -> bool {
+        !matches!(self, Self::Signal(_))
+    }
+}
+
+impl Display for Exceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Memory(mb) => write!(f, "exceeded the memory limit of {mb} MB"),
+            Self::Cpu(seconds) => write!(f, "exceeded the CPU time limit of {seconds}s"),
+            Self::Signal(signal) => write!(f, "was killed by signal {signal}"),
+        }
+    }
+}
+
+/// The limits of a running command.
+pub(crate) struct Limited {
+    limits: ResourceLimits,
+    #[cfg(windows)]
+    job: Option<windows::Job>,
+}
+
+impl Limited {
+    /// Set the limits of `cmd`, before it's spawned.
+    pub(crate) fn prepare(cmd: &mut Command, limits: ResourceLimits) {
+        #[cfg(unix)]
+        if !limits.is_empty() {
+            unsafe { cmd.pre_exec(move || unix::apply(limits)) };
+        }
+        #[cfg(windows)]
+        let _ = (cmd, limits);
+    }
+
+    /// Track the limits of `child`, once it's spawned.
+    #[allow(clippy::unnecessary_wraps)] // Only fails on Windows.
+    pub(crate) fn new(child: &Child, limits: ResourceLimits) -> std::io::Result<Self> {
+        #[cfg(windows)]
+        let job = if limits.is_empty() {
+            None
+        } else {
+            Some(windows::Job::assign(child, limits)?)
+        };
+        #[cfg(unix)]
+        let _ = child;
+
+        Ok(Self {
+            limits,
+            #[cfg(windows)]
+            job,
+        })
+    }
+
+    /// The limit the command exceeded, if it was killed for it.
+    pub(crate) fn exceeded(&self, output: &Output) -> Option<Exceeded> {
+        if self.limits.is_empty() || output.status.success() {
+            return None;
+        }
+        #[cfg(unix)]
+        {
+            unix::exceeded(self.limits, output)
+        }
+        #[cfg(windows)]
+        {
+            self.job.as_ref()?.exceeded(self.limits)
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Output;
+
+    use super::Exceeded;
+    use crate::config::ResourceLimits;
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    type Resource = libc::__rlimit_resource_t;
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    type Resource = libc::c_int;
+
+    /// `RLIMIT_AS` also counts reserved address space, which runtimes like Node.js or Go reserve
+    /// plenty of. Linux has a limit for the memory actually allocated.
+    #[cfg(target_os = "linux")]
+    const MEMORY: Resource = libc::RLIMIT_DATA;
+    #[cfg(not(target_os = "linux"))]
+    const MEMORY: Resource = libc::RLIMIT_AS;
+
+    /// Set the limits of the current process, run in the child after `fork`.
+    pub(super) fn apply(limits: ResourceLimits) -> std::io::Result<()> {
+        if let Some(mb) = limits.memory_mb {
+            let bytes = mb.saturating_mul(1024 * 1024);
+            lower(MEMORY, bytes, bytes)?;
+        }
+        if let Some(seconds) = limits.cpu_seconds {
+            // The process receives `SIGXCPU` at the soft limit, and `SIGKILL` at the hard limit.
+            lower(libc::RLIMIT_CPU, seconds, seconds.saturating_add(1))?;
+        }
+        Ok(())
+    }
+
+    /// Lower a limit, a limit that is already lower is kept.
+    #[allow(clippy::useless_conversion)]
+    fn lower(resource: Resource, soft: u64, hard: u64) -> std::io::Result<()> {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { libc::getrlimit(resource, &raw mut limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let hard = libc::rlim_t::try_from(hard).unwrap_or(libc::RLIM_INFINITY);
+        let soft = libc::rlim_t::try_from(soft).unwrap_or(libc::RLIM_INFINITY);
+        limit.rlim_max = limit.rlim_max.min(hard);
+        limit.rlim_cur = limit.rlim_cur.min(soft).min(limit.rlim_max);
+        if unsafe { libc::setrlimit(resource, &raw const limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// A process that exceeds its CPU time receives `SIGXCPU`. A failed allocation only makes
+    /// the allocation return an error, which the process may handle in many ways, so nothing
+    /// attributes the failure to the memory limit. Other signals may or may not come from a limit.
+    pub(super) fn exceeded(limits: ResourceLimits, output: &Output) -> Option<Exceeded> {
+        let signal = output.status.signal()?;
+        if let Some(seconds) = limits.cpu_seconds
+            && signal == libc::SIGXCPU
+        {
+            return Some(Exceeded::Cpu(seconds));
+        }
+        Some(Exceeded::Signal(signal))
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::io;
+
+    use tokio::process::Child;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JOB_OBJECT_LIMIT_ACTIVE_PROCESS,
+        JOB_OBJECT_LIMIT_JOB_MEMORY, JOB_OBJECT_LIMIT_JOB_TIME,
+        JOBOBJECT_BASIC_ACCOUNTING_INFORMATION, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JobObjectBasicAccountingInformation, JobObjectExtendedLimitInformation,
+        QueryInformationJobObject, SetInformationJobObject,
+    };
+
+    use super::Exceeded;
+    use crate::config::ResourceLimits;
+
+    /// CPU times of Job Objects are in units of 100 nanoseconds.
+    const TICKS_PER_SECOND: i64 = 10_000_000;
+
+    pub(super) struct Job(HANDLE);
+
+    // The handle is only used to set and query the limits of the job.
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    impl Job {
+        /// Create a job with the limits and assign `child` to it.
+        ///
+        /// The processes started by the child before it's assigned are not part of the job.
+        pub(super) fn assign(child: &Child, limits: ResourceLimits) -> io::Result<Self> {
+            let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let job = Self(handle);
+
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+            if let Some(mb) = limits.memory_mb {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+                info.JobMemoryLimit =
+                    usize::try_from(mb.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX);
+            }
+            if let Some(seconds) = limits.cpu_seconds {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_TIME;
+                info.BasicLimitInformation.PerJobUserTimeLimit = i64::try_from(seconds)
+                    .unwrap_or(i64::MAX)
+                    .saturating_mul(TICKS_PER_SECOND);
+            }
+            if let Some(processes) = limits.processes {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_ACTIVE_PROCESS;
+                info.BasicLimitInformation.ActiveProcessLimit =
+                    u32::try_from(processes).unwrap_or(u32::MAX);
+            }
+            let set = unsafe {
+                SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    (&raw const info).cast(),
+                    u32::try_from(size_of_val(&info)).expect("small struct"),
+                )
+            };
+            if set == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let Some(process) = child.raw_handle() else {
+                // The child already exited.
+                return Ok(job);
+            };
+            if unsafe { AssignProcessToJobObject(job.0, process) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(job)
+        }
+
+        /// The job is terminated when it exceeds its CPU time, allocations fail when it
+        /// exceeds its memory.
+        pub(super) fn exceeded(&self, limits: ResourceLimits) -> Option<Exceeded> {
+            if let Some(seconds) = limits.cpu_seconds {
+                let mut accounting: JOBOBJECT_BASIC_ACCOUNTING_INFORMATION =
+                    unsafe { std::mem::zeroed() };
+                if self.query(JobObjectBasicAccountingInformation, &mut accounting)
+                    && accounting.TotalUserTime
+                        >= i64::try_from(seconds)
+                            .unwrap_or(i64::MAX)
+                            .saturating_mul(TICKS_PER_SECOND)
+                {
+                    return Some(Exceeded::Cpu(seconds));
+                }
+            }
+            if let Some(mb) = limits.memory_mb {
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+                // An allocation that would exceed the limit fails, so the peak stays below it:
+                // count a job that came close as killed by the limit.
+                if self.query(JobObjectExtendedLimitInformation, &mut info)
+                    && u64::try_from(info.PeakJobMemoryUsed).unwrap_or(u64::MAX)
+                        >= mb.saturating_mul(1024 * 1024) / 10 * 9
+                {
+                    return Some(Exceeded::Memory(mb));
+                }
+            }
+            None
+        }
+
+        fn query<T>(&self, class: i32, info: &mut T) -> bool {
+            unsafe {
+                QueryInformationJobObject(
+                    self.0,
+                    class,
+                    std::ptr::from_mut(info).cast(),
+                    u32::try_from(size_of::<T>()).expect("small struct"),
+                    std::ptr::null_mut(),
+                ) != 0
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+
+    use super::{Exceeded, unix};
+    use crate::config::ResourceLimits;
+
+    fn output(status: ExitStatus, stderr: &str) -> Output {
+        Output {
+            status,
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn attribute_exceeded() {
+        let limits = ResourceLimits {
+            memory_mb: Some(100),
+            cpu_seconds: Some(1),
+            processes: None,
+        };
+
+        let xcpu = output(ExitStatus::from_raw(libc::SIGXCPU), "");
+        assert_eq!(unix::exceeded(limits, &xcpu), Some(Exceeded::Cpu(1)));
+
+        // The output is no evidence of a failed allocation, the hook may print anything.
+        let abort = output(
+            ExitStatus::from_raw(libc::SIGABRT),
+            "memory allocation of 1048576 bytes failed",
+        );
+        assert_eq!(
+            unix::exceeded(limits, &abort),
+            Some(Exceeded::Signal(libc::SIGABRT))
+        );
+
+        let python = output(ExitStatus::from_raw(1 << 8), "MemoryError");
+        assert_eq!(unix::exceeded(limits, &python), None);
+
+        let kill = output(ExitStatus::from_raw(libc::SIGKILL), "");
+        assert_eq!(
+            unix::exceeded(limits, &kill),
+            Some(Exceeded::Signal(libc::SIGKILL))
+        );
+
+        let failed = output(ExitStatus::from_raw(1 << 8), "error");
+        assert_eq!(unix::exceeded(limits, &failed), None);
+    }
+}
@@ -1,8 +1,9 @@
 use std::fmt::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::str::FromStr;
 use std::sync::Mutex;
+use std::time::Duration;
 
 use anstream::{ColorChoice, StripStream, eprintln};
 use anyhow::{Context, Result};
@@ -11,36 +12,58 @@ use clap_complete::CompleteEnv;
 use owo_colors::OwoColorize;
 use tracing::debug;
 use tracing::level_filters::LevelFilter;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard, TraceStyle};
 use tracing_subscriber::filter::Directive;
 use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::{EnvFilter, Layer};
 
+use constants::env_vars::EnvVars;
+
 use crate::cleanup::cleanup;
-use crate::cli::{CacheCommand, CacheNamespace, Cli, Command, ExitStatus};
 #[cfg(feature = "self-update")]
-use crate::cli::{SelfCommand, SelfNamespace, SelfUpdateArgs};
+use crate::cli::SelfUpdateArgs;
+use crate::cli::run::{FileLimits, RunOutput, SubmoduleOptions, expand_file_args, read_files_from};
+use crate::cli::{
+    BaselineCommand, BaselineNamespace, CacheCommand, CacheNamespace, Cli, Command, ExitStatus,
+    SchemaNamespace, SelfCommand, SelfNamespace,
+};
+use crate::config::StashMode;
+use crate::fs::CWD;
 use crate::printer::Printer;
-use crate::run::USE_COLOR;
+use crate::run::{USE_COLOR, set_jobs};
+use crate::settings::settings;
 use crate::store::STORE;
 
 mod archive;
+mod baseline;
 mod builtin;
+mod cache_key;
+mod checksum;
+mod ci;
 mod cleanup;
 mod cli;
 mod config;
 mod fs;
 mod git;
 mod hook;
+mod hook_env;
+mod http_client;
 mod identify;
 mod languages;
+mod limits;
+mod policy;
 mod printer;
 mod process;
 #[cfg(all(unix, feature = "profiler"))]
 mod profiler;
 mod run;
+mod sandbox;
+mod settings;
 mod store;
+#[cfg(feature = "opentelemetry")]
+mod telemetry;
 mod version;
 mod warnings;
 mod workspace;
@@ -82,7 +105,35 @@ impl LogFile {
     }
 }
 
-fn setup_logging(level: Level, log_file: LogFile) -> Result<()> {
+/// Number of log files kept in the store, `prek.log` and `prek.log.1` to `prek.log.4`.
+const LOG_FILES: usize = 5;
+
+/// Keep the logs of previous runs: `prek.log` becomes `prek.log.1` and so on, the oldest is dropped.
+fn rotate_log_files(path: &Path) {
+    let rotated = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    };
+    for n in (1..LOG_FILES).rev() {
+        let from = if n == 1 {
+            path.to_path_buf()
+        } else {
+            rotated(n - 1)
+        };
+        // The file doesn't exist yet for the first runs.
+        let _ = fs_err::rename(from, rotated(n));
+    }
+}
+
+/// Writes the profile and exports the telemetry of the command once dropped.
+struct LoggingGuard {
+    _profile: Option<FlushGuard>,
+    #[cfg(feature = "opentelemetry")]
+    _telemetry: Option<telemetry::TelemetryGuard>,
+}
+
+fn setup_logging(level: Level, log_file: LogFile, profile: Option<&Path>) -> Result<LoggingGuard> {
     let directive = match level {
         Level::Default | Level::Verbose => LevelFilter::OFF.into(),
         Level::Debug => Directive::from_str("prek=debug")?,
@@ -104,13 +155,15 @@ fn setup_logging(level: Level, log_file: LogFile) -> Result<()> {
         .with_writer(anstream::stderr)
         .with_filter(stderr_filter);
 
-    let registry = tracing_subscriber::registry().with(stderr_layer);
-
-    if log_file.is_disabled() {
-        registry.init();
+    let file_layer = if log_file.is_disabled() {
+        None
     } else {
         let log_file_path = match log_file {
-            LogFile::Default => STORE.as_ref()?.log_file(),
+            LogFile::Default => {
+                let path = STORE.as_ref()?.log_file();
+                rotate_log_files(&path);
+                path
+            }
             LogFile::Path(path) => path,
             LogFile::Disabled => unreachable!(),
         };
@@ -125,23 +178,78 @@ fn setup_logging(level: Level, log_file: LogFile) -> Result<()> {
         let file_format = tracing_subscriber::fmt::format()
             .with_target(false)
             .with_ansi(false);
-        let file_layer = tracing_subscriber::fmt::layer()
-            .with_span_events(FmtSpan::CLOSE)
-            .event_format(file_format)
-            .with_writer(log_file)
-            .with_filter(EnvFilter::new("prek=trace"));
+        // The file gets all prek logs by default, regardless of `-v` and `RUST_LOG`.
+        let file_filter = EnvFilter::builder()
+            .with_default_directive(Directive::from_str("prek=trace")?)
+            .with_env_var(EnvVars::PREK_LOG)
+            .from_env()
+            .context("Invalid PREK_LOG directive")?;
+        Some(
+            tracing_subscriber::fmt::layer()
+                .with_span_events(FmtSpan::CLOSE)
+                .event_format(file_format)
+                .with_writer(log_file)
+                .with_filter(file_filter),
+        )
+    };
+
+    // Spans of concurrent tasks overlap on the same thread, record them as async events.
+    let (profile_layer, profile_guard) = match profile {
+        Some(path) => {
+            let (layer, guard) = ChromeLayerBuilder::new()
+                .file(CWD.join(path))
+                .trace_style(TraceStyle::Async)
+                .include_args(true)
+                .build();
+            (
+                Some(layer.with_filter(EnvFilter::new("prek=trace"))),
+                Some(guard),
+            )
+        }
+        None => (None, None),
+    };
+
+    let registry = tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(file_layer)
+        .with(profile_layer);
 
-        registry.with(file_layer).init();
+    #[cfg(feature = "opentelemetry")]
+    {
+        let (telemetry_layer, telemetry_guard) = telemetry::layer()?.unzip();
+        registry.with(telemetry_layer.flatten()).init();
+        Ok(LoggingGuard {
+            _profile: profile_guard,
+            _telemetry: telemetry_guard,
+        })
     }
 
-    Ok(())
+    #[cfg(not(feature = "opentelemetry"))]
+    {
+        registry.init();
+        Ok(LoggingGuard {
+            _profile: profile_guard,
+        })
+    }
 }
 
 async fn run(mut cli: Cli) -> Result<ExitStatus> {
-    ColorChoice::write_global(cli.globals.color.into());
+    if cli.globals.isolated {
+        EnvVars::isolate();
+    } else {
+        settings::init()?;
+    }
+
+    let color = cli
+        .globals
+        .color
+        .or(settings().color)
+        .or_else(|| ci::CI.and_then(ci::Ci::color))
+        .unwrap_or_default();
+    ColorChoice::write_global(color.into());
 
     let log_file = LogFile::from_args(cli.globals.log_file.clone(), cli.globals.no_log_file);
-    setup_logging(
+    let _logging_guard = setup_logging(
         match cli.globals.verbose {
             0 => Level::Default,
             1 => Level::Verbose,
@@ -150,6 +258,7 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
             _ => Level::TraceAll,
         },
         log_file,
+        cli.globals.profile.as_deref(),
     )?;
 
     let printer = if cli.globals.quiet == 1 {
@@ -175,10 +284,23 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
     }
 
     if let Some(dir) = cli.globals.cd.as_ref() {
-        std::env::set_current_dir(dir)?;
+        if let Err(err) = std::env::set_current_dir(dir) {
+            // The project of an installed hook may not exist in the checked out commit.
+            if err.kind() == std::io::ErrorKind::NotFound
+                && let Some(Command::HookImpl(args)) = &cli.command
+                && cli::allow_missing_config(args.skip_on_missing_config)
+            {
+                debug!("Project `{}` not found, skipping", dir.display());
+                return Ok(ExitStatus::Success);
+            }
+            return Err(err.into());
+        }
     }
 
     debug!("prek: {}", version::version());
+    if let Some(ci) = *ci::CI {
+        debug!("Running in {ci}");
+    }
 
     macro_rules! show_settings {
         ($arg:expr) => {
@@ -195,6 +317,19 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
     }
     show_settings!(cli.globals, false);
 
+    if cli.globals.isolated {
+        debug!("Running in isolated mode");
+    } else {
+        policy::init()?;
+    }
+
+    if settings().check_updates
+        && !matches!(cli.command, Some(Command::Self_(_) | Command::HookImpl(_)))
+        && let Ok(store) = STORE.as_ref()
+    {
+        cli::passive_check(store).await;
+    }
+
     match cli.command.unwrap() {
         Command::Install(args) => {
             show_settings!(args);
@@ -232,38 +367,206 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
         Command::Run(args) => {
             show_settings!(args);
 
-            cli::run(
+            if args.strict_warnings {
+                warnings::set_strict();
+            }
+            if let Some(jobs) = args.jobs {
+                set_jobs(jobs);
+            }
+
+            let file_limits = FileLimits {
+                max_file_size: args.max_file_size,
+                skip_binary_files: args.skip_binary_files,
+                skip_lfs_files: args.skip_lfs_files,
+                ignore_baseline: args.no_baseline,
+            };
+
+            if let Some(list_file) = args.workspace {
+                return cli::run_repos(
+                    &list_file,
+                    cli.globals.config,
+                    args.includes,
+                    args.skips,
+                    args.projects,
+                    args.groups,
+                    args.tags,
+                    args.hook_stage,
+                    args.all_files,
+                    file_limits,
+                    args.dry_run,
+                    args.strict,
+                    args.output,
+                    cli.globals.verbose > 0,
+                    printer,
+                )
+                .await;
+            }
+
+            // Porcelain records replace the human readable output on stdout.
+            let (output, printer) = if args.porcelain {
+                (RunOutput::Porcelain, Printer::Silent)
+            } else {
+                (args.output, printer)
+            };
+            let has_files = !args.files.is_empty();
+            let mut files = expand_file_args(args.files).await?;
+            // Patterns that match no tracked files leave nothing to check, not the staged files.
+            if has_files
+                && files.is_empty()
+                && args.directory.is_empty()
+                && args.files_from.is_none()
+            {
+                writeln!(printer.stdout(), "No files to run hooks on")?;
+                return Ok(ExitStatus::Success);
+            }
+            if let Some(files_from) = &args.files_from {
+                let files_from = read_files_from(files_from)?;
+                // An empty list means there is nothing to check, not the staged files.
+                if files_from.is_empty() && files.is_empty() && args.directory.is_empty() {
+                    writeln!(printer.stdout(), "No files to run hooks on")?;
+                    return Ok(ExitStatus::Success);
+                }
+                files.extend(files_from);
+            }
+            let submodule_options = args.include_submodules.then(|| SubmoduleOptions {
+                includes: args.includes.clone(),
+                skips: args.skips.clone(),
+                projects: args.projects.clone(),
+                groups: args.groups.clone(),
+                tags: args.tags.clone(),
+                hook_stage: args.hook_stage,
+                from_ref: args.from_ref.clone(),
+                to_ref: args.to_ref.clone(),
+                all_files: args.all_files,
+                files: files.clone(),
+                directories: args.directory.clone(),
+                file_limits,
+                dry_run: args.dry_run,
+                strict: args.strict,
+                output: output.clone(),
+                verbose: cli.globals.verbose > 0,
+            });
+            let status = Box::pin(cli::run(
+                cli::RunOptions {
+                    config: cli.globals.config,
+                    includes: args.includes,
+                    skips: args.skips,
+                    projects: args.projects,
+                    groups: args.groups,
+                    tags: args.tags,
+                    hook_stage: args.hook_stage,
+                    from_ref: args.from_ref,
+                    to_ref: args.to_ref,
+                    all_files: args.all_files,
+                    files,
+                    directories: args.directory,
+                    only_files_matching: args.only_files_matching,
+                    exclude_files_matching: args.exclude_files_matching,
+                    file_limits,
+                    fail_on_sparse: args.fail_on_sparse,
+                    last_commit: args.last_commit,
+                    rebase_exec: args.rebase_exec,
+                    incremental: args.incremental,
+                    failed: args.failed,
+                    interactive: args.interactive,
+                    show_diff_on_failure: args.show_diff_on_failure,
+                    stash_mode: if args.no_stash {
+                        Some(StashMode::Keep)
+                    } else {
+                        args.stash_mode
+                    },
+                    dry_run: args.dry_run,
+                    preview_diff: args.diff,
+                    fix_and_verify: args.fix_and_verify,
+                    fix_and_retry: args.fix_and_retry,
+                    strict: args.strict,
+                    require_pinned_revs: args.require_pinned_revs,
+                    refresh: cli.globals.refresh,
+                    extra_args: args.extra,
+                    output,
+                    stream: args.stream,
+                    pty: args.pty,
+                    sandbox: args.sandbox,
+                    durations: args.durations,
+                    timeout: args.timeout,
+                    verbose: cli.globals.verbose > 0,
+                },
+                printer,
+            ))
+            .await?;
+            let Some(submodule_options) = submodule_options else {
+                return Ok(status);
+            };
+
+            let submodules_status = cli::run_submodules(submodule_options, printer).await?;
+            match status {
+                ExitStatus::Success => Ok(submodules_status),
+                status => Ok(status),
+            }
+        }
+        Command::List(args) => {
+            show_settings!(args);
+
+            cli::list(
                 cli.globals.config,
                 args.includes,
                 args.skips,
                 args.hook_stage,
-                args.from_ref,
-                args.to_ref,
-                args.all_files,
-                args.files,
-                args.directory,
-                args.last_commit,
-                args.show_diff_on_failure,
-                args.dry_run,
+                args.language,
+                args.output_format,
+                args.projects,
+                args.groups,
                 cli.globals.refresh,
-                args.extra,
-                cli.globals.verbose > 0,
+                args.long || cli.globals.verbose > 0,
                 printer,
             )
             .await
         }
-        Command::List(args) => {
+        Command::Info(args) => {
             show_settings!(args);
 
-            cli::list(
+            cli::info(cli.globals.config, args.hook, cli.globals.refresh, printer).await
+        }
+        Command::Exec(args) => {
+            show_settings!(args);
+
+            cli::exec(
+                cli.globals.config,
+                args.hook,
+                args.command,
+                cli.globals.refresh,
+                printer,
+            )
+            .await
+        }
+        Command::Compare(args) => {
+            show_settings!(args);
+
+            cli::compare(&args.base, &args.head, args.threshold, printer)
+        }
+        Command::Stats(args) => {
+            show_settings!(args);
+
+            cli::stats(cli.globals.config.as_deref(), args.runs, printer)
+        }
+        Command::Watch(args) => {
+            show_settings!(args);
+
+            cli::watch(
                 cli.globals.config,
                 args.includes,
                 args.skips,
-                args.hook_stage,
-                args.language,
-                args.output_format,
+                Duration::from_millis(args.debounce),
+                cli.globals.refresh,
+                printer,
+            )
+            .await
+        }
+        Command::Baseline(BaselineNamespace { command }) => {
+            cli::baseline(
+                cli.globals.config,
+                matches!(command, BaselineCommand::Update),
                 cli.globals.refresh,
-                cli.globals.verbose > 0,
                 printer,
             )
             .await
@@ -293,16 +596,23 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
                 writeln!(printer.stdout(), "{}", store.path().display().cyan())?;
                 Ok(ExitStatus::Success)
             }
+            CacheCommand::Configs => cli::cache_configs(printer),
+            CacheCommand::Verify => cli::cache_verify(printer).await,
             CacheCommand::GC => {
                 writeln!(printer.stderr(), "Command not implemented yet")?;
                 Ok(ExitStatus::Failure)
             }
         },
+        Command::DebugBundle(args) => {
+            show_settings!(args);
+
+            cli::debug_bundle(cli.globals.config, &args.output, printer).await
+        }
         Command::Clean => cli::clean(printer),
         Command::ValidateConfig(args) => {
             show_settings!(args);
 
-            Ok(cli::validate_configs(args.configs))
+            Ok(cli::validate_configs(args.configs, args.strict))
         }
         Command::ValidateManifest(args) => {
             show_settings!(args);
@@ -310,6 +620,12 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
             Ok(cli::validate_manifest(args.manifests))
         }
         Command::SampleConfig(args) => cli::sample_config(args.file, printer),
+        Command::NewHook(args) => {
+            show_settings!(args);
+
+            cli::new_hook(&args.directory, args.id, args.name, args.language, printer)
+        }
+        Command::Schema(SchemaNamespace { command }) => cli::schema(command, printer),
         Command::AutoUpdate(args) => {
             cli::auto_update(
                 cli.globals.config,
@@ -322,6 +638,14 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
             )
             .await
         }
+        Command::Audit(args) => {
+            show_settings!(args);
+
+            cli::audit(cli.globals.config, &args.allowed_hosts, printer).await
+        }
+        Command::Self_(SelfNamespace {
+            command: SelfCommand::Check,
+        }) => cli::self_check(cli.globals.config, printer).await,
         #[cfg(feature = "self-update")]
         Command::Self_(SelfNamespace {
             command:
@@ -331,7 +655,9 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
                 }),
         }) => cli::self_update(target_version, token, printer).await,
         #[cfg(not(feature = "self-update"))]
-        Command::Self_(_) => {
+        Command::Self_(SelfNamespace {
+            command: SelfCommand::Update(_),
+        }) => {
             anyhow::bail!(
                 "prek was installed through an external package manager, and self-update \
                 is not available. Please use your package manager to update prek."
@@ -349,6 +675,11 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
             clap_complete::generate(args.shell, &mut command, bin_name, &mut std::io::stdout());
             Ok(ExitStatus::Success)
         }
+        Command::Completions(args) => {
+            show_settings!(args);
+
+            cli::completions(args.shell, printer)
+        }
         Command::InitTemplateDir(args) => {
             show_settings!(args);
 
@@ -369,20 +700,58 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
     }
 }
 
-fn main() -> ExitCode {
-    CompleteEnv::with_factory(Cli::command).complete();
+/// Stop the running commands and restore the working tree when prek is interrupted, then exit.
+///
+/// The store locks are file locks, the system releases them when prek exits.
+#[cfg(unix)]
+fn handle_interrupts() {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to create tokio runtime");
+    // Register the handlers now, the signals are received on a separate thread: the main thread
+    // may be blocked, e.g. waiting for a hook to finish.
+    let (mut interrupt, mut terminate) = {
+        let _guard = runtime.enter();
+        (
+            signal(SignalKind::interrupt()).expect("Error setting SIGINT handler"),
+            signal(SignalKind::terminate()).expect("Error setting SIGTERM handler"),
+        )
+    };
+    std::thread::spawn(move || {
+        let signal = runtime.block_on(async {
+            tokio::select! {
+                _ = interrupt.recv() => libc::SIGINT,
+                _ = terminate.recv() => libc::SIGTERM,
+            }
+        });
+        process::interrupt(signal);
+        cleanup();
+
+        #[allow(clippy::exit)]
+        std::process::exit(128 + signal);
+    });
+}
 
+/// Stop the running commands and restore the working tree when prek is interrupted, then exit.
+#[cfg(windows)]
+fn handle_interrupts() {
     ctrlc::set_handler(move || {
+        process::interrupt();
         cleanup();
 
         #[allow(clippy::exit, clippy::cast_possible_wrap)]
-        std::process::exit(if cfg!(windows) {
-            0xC000_013A_u32 as i32
-        } else {
-            130
-        });
+        std::process::exit(0xC000_013A_u32 as i32);
     })
     .expect("Error setting Ctrl-C handler");
+}
+
+fn main() -> ExitCode {
+    CompleteEnv::with_factory(Cli::command).complete();
+
+    handle_interrupts();
 
     let cli = match Cli::try_parse() {
         Ok(cli) => cli,
@@ -407,6 +776,8 @@ fn main() -> ExitCode {
     let result = runtime.block_on(Box::pin(run(cli)));
     runtime.shutdown_background();
 
+    let strict_failed = warnings::flush_deprecations();
+
     // Report the profiler if the feature is enabled
     #[cfg(all(unix, feature = "profiler"))]
     {
@@ -414,6 +785,13 @@ fn main() -> ExitCode {
     }
 
     match result {
+        Ok(code) if strict_failed && matches!(code, ExitStatus::Success) => {
+            eprintln!(
+                "{}: Found deprecated usages with `--strict-warnings`",
+                "error".red().bold()
+            );
+            ExitStatus::Failure.into()
+        }
         Ok(code) => code.into(),
         Err(err) => {
             let mut causes = err.chain();
@@ -421,7 +799,7 @@ fn main() -> ExitCode {
             for err in causes {
                 eprintln!("  {}: {}", "caused by".red().bold(), err);
             }
-            ExitStatus::Error.into()
+            ExitStatus::from_error(&err).into()
         }
     }
 }
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+use tracing::debug;
+
+use constants::env_vars::EnvVars;
+
+use crate::config::{self, Config, read_config};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error("Failed to read policy file `{0}`")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("Failed to parse policy file `{0}`")]
+    Yaml(PathBuf, #[source] serde_yaml::Error),
+    #[error("Failed to read required config `{0}`")]
+    RequiredConfig(PathBuf, #[source] config::Error),
+}
+
+/// Settings pinned by an organization-managed policy file.
+///
+/// The policy file is set with `PREK_POLICY_FILE`, and its settings take precedence over
+/// repository configs and environment variables, so they cannot be overridden per repository.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Policy {
+    /// Force `fail_fast` on or off for all projects and hooks.
+    pub(crate) fail_fast: Option<bool>,
+    /// Force the Rust-native implementations of builtin hooks on or off.
+    pub(crate) builtin_fast_path: Option<bool>,
+    /// A config whose hooks are run in every project and cannot be skipped, overrides
+    /// `PREK_REQUIRED_CONFIG`.
+    pub(crate) required_config: Option<PathBuf>,
+    /// Run every hook in a sandbox, as with `prek run --sandbox`.
+    #[serde(default)]
+    pub(crate) require_sandbox: bool,
+}
+
+static POLICY: OnceLock<Policy> = OnceLock::new();
+static REQUIRED_CONFIG: OnceLock<Option<Config>> = OnceLock::new();
+
+impl Policy {
+    fn load() -> Result<Self, Error> {
+        let Some(path) = EnvVars::var_os(EnvVars::PREK_POLICY_FILE) else {
+            return Ok(Self::default());
+        };
+        let path = PathBuf::from(path);
+        debug!("Loading policy file `{}`", path.display());
+
+        let content = fs_err::read_to_string(&path).map_err(|e| Error::Io(path.clone(), e))?;
+        serde_yaml::from_str(&content).map_err(|e| Error::Yaml(path, e))
+    }
+
+    fn load_required_config(&self) -> Result<Option<Config>, Error> {
+        let Some(path) = self
+            .required_config
+            .clone()
+            .or_else(|| EnvVars::var_os(EnvVars::PREK_REQUIRED_CONFIG).map(PathBuf::from))
+        else {
+            return Ok(None);
+        };
+        debug!("Loading required config `{}`", path.display());
+
+        read_config(&path)
+            .map(Some)
+            .map_err(|e| Error::RequiredConfig(path, e))
+    }
+}
+
+/// Load the policy file, must be called before any call to [`policy`].
+pub(crate) fn init() -> Result<(), Error> {
+    let policy = Policy::load()?;
+    debug!("Policy: {policy:?}");
+    let required = policy.load_required_config()?;
+    REQUIRED_CONFIG.get_or_init(|| required);
+    POLICY.get_or_init(|| policy);
+    Ok(())
+}
+
+/// The policy of the current process.
+pub(crate) fn policy() -> &'static Policy {
+    POLICY.get_or_init(Policy::default)
+}
+
+/// The config whose hooks are appended to every project, set by the policy file or
+/// `PREK_REQUIRED_CONFIG`.
+pub(crate) fn required_config() -> Option<&'static Config> {
+    REQUIRED_CONFIG.get().and_then(Option::as_ref)
+}
@@ -25,8 +25,11 @@
 // DEALINGS IN THE SOFTWARE.
 
 /// Adapt [axoprocess] to use [`tokio::process::Process`] instead of [`std::process::Command`].
-use std::fmt::Display;
+use std::fmt::{Display, Write};
 use std::process::Output;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::{
     ffi::OsStr,
     path::Path,
@@ -35,9 +38,61 @@ use std::{
 
 use owo_colors::OwoColorize;
 use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin};
+use tokio::time::Instant;
 use tracing::trace;
 
+use constants::env_vars::EnvVars;
+
+use crate::config::ResourceLimits;
 use crate::git::GIT;
+use crate::hook_env;
+use crate::limits::{Exceeded, Limited};
+use crate::printer::Stdout;
+use crate::sandbox::Sandbox;
+use crate::settings::settings;
+
+/// How the output of the commands run by a hook is captured, set while the hook runs.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HookOutput {
+    /// Print the output as it's produced, each line prefixed with this value, besides
+    /// capturing it.
+    pub(crate) stream: Option<String>,
+    /// Run commands in a pseudo-terminal, even if colors are disabled.
+    pub(crate) pty: bool,
+    /// Kill commands and their child processes that are still running at this instant.
+    pub(crate) deadline: Option<Instant>,
+    /// Limits on the resources of the commands.
+    pub(crate) limits: ResourceLimits,
+    /// Run the commands in this sandbox.
+    pub(crate) sandbox: Option<Sandbox>,
+    /// Only pass the curated variables of prek and these ones to the commands, instead of all
+    /// of them.
+    pub(crate) pass_env: Option<Vec<String>>,
+    /// Variables set for the commands, unless the command sets them itself.
+    pub(crate) env: Vec<(String, String)>,
+    /// The content of each file the hook runs on, for `pass_file_contents: stdin`, read before
+    /// the hook runs so that reading it isn't subject to the settings above.
+    pub(crate) file_contents: Option<Arc<[Arc<[u8]>]>>,
+}
+
+tokio::task_local! {
+    pub(crate) static HOOK_OUTPUT: HookOutput;
+    /// Written to the stdin of the commands, instead of leaving it empty, set while a hook runs
+    /// on a file with `pass_file_contents: stdin`.
+    pub(crate) static HOOK_STDIN: Arc<[u8]>;
+}
+
+/// Set once prek is interrupted, no command is started after that.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The pids of the running commands that capture their output, and whether they lead their own
+/// process group.
+static RUNNING: Mutex<Vec<(u32, bool)>> = Mutex::new(Vec::new());
+
+/// How long the commands get to exit after the signal is forwarded, before they're killed.
+const INTERRUPT_GRACE_PERIOD: Duration = Duration::from_secs(2);
 
 /// An error from executing a Command
 #[derive(Debug, Error)]
@@ -58,6 +113,17 @@ pub enum Error {
     Pty(#[from] pty::Error),
     #[error("failed to setup subprocess for pty")]
     PtySetup(#[from] std::io::Error),
+    /// The command was killed at the deadline of the hook, with the output it produced so far.
+    #[error("command `{summary}` timed out")]
+    TimedOut { summary: String, output: Output },
+    /// The command was killed for exceeding a resource limit of the hook, or by a signal while
+    /// it had limits.
+    #[error("command `{summary}` {exceeded}")]
+    OverLimit {
+        summary: String,
+        exceeded: Exceeded,
+        output: Output,
+    },
 }
 
 /// The command ran but signaled some kind of error condition
@@ -108,17 +174,31 @@ pub struct Cmd {
     pub inner: tokio::process::Command,
     summary: String,
     check_status: bool,
+    /// The stdio of the command, set when it's spawned, as the inner Command is replaced to run
+    /// it in a sandbox.
+    stdin: Option<Stdio>,
+    stdout: Option<Stdio>,
+    stderr: Option<Stdio>,
 }
 
 /// Constructors
 impl Cmd {
     /// Create a new Command with an additional "summary" of what this is trying to do
     pub fn new(command: impl AsRef<OsStr>, summary: impl Into<String>) -> Self {
-        let inner = tokio::process::Command::new(command);
+        let mut inner = tokio::process::Command::new(command);
+        // The `proxy` user setting, for commands that download, e.g. git and package managers.
+        if let Some(proxy) = settings().proxy() {
+            inner
+                .env(EnvVars::HTTP_PROXY, proxy)
+                .env(EnvVars::HTTPS_PROXY, proxy);
+        }
         Self {
             summary: summary.into(),
             inner,
             check_status: true,
+            stdin: None,
+            stdout: None,
+            stderr: None,
         }
     }
 }
@@ -131,7 +211,7 @@ impl Cmd {
     /// the output of a command to give your user realtime feedback, but the command
     /// randomly writes some things to stdout, and you don't want your own stdout tainted.
     pub fn stdout_to_stderr(&mut self) -> &mut Self {
-        self.inner.stdout(std::io::stderr());
+        self.stdout = Some(std::io::stderr().into());
 
         self
     }
@@ -162,7 +242,18 @@ impl Cmd {
 
     /// Equivalent to [`std::process::Command::spawn`][],
     /// but logged and with the error wrapped.
+    ///
+    /// When run by a hook, the command gets the environment and sandbox of the hook.
     pub fn spawn(&mut self) -> Result<tokio::process::Child, Error> {
+        if let Ok(hook_output) = HOOK_OUTPUT.try_with(Clone::clone) {
+            self.for_hook(&hook_output)?;
+        }
+        self.spawn_prepared()
+    }
+
+    /// Spawn the command as is, after the hook set it up.
+    fn spawn_prepared(&mut self) -> Result<tokio::process::Child, Error> {
+        self.apply_stdio();
         self.log_command();
         self.inner.spawn().map_err(|cause| Error::Exec {
             summary: self.summary.clone(),
@@ -173,43 +264,48 @@ impl Cmd {
     /// Equivalent to [`std::process::Command::output`][],
     /// but logged, with the error wrapped, and status checked (by default)
     pub async fn output(&mut self) -> Result<Output, Error> {
-        self.log_command();
-        let output = self.inner.output().await.map_err(|cause| Error::Exec {
-            summary: self.summary.clone(),
-            cause,
-        })?;
-        self.maybe_check_output(&output)?;
-        Ok(output)
+        self.streamed_output(HOOK_OUTPUT.try_with(Clone::clone).ok())
+            .await
     }
 
     #[cfg(windows)]
     pub async fn pty_output(&mut self) -> Result<Output, Error> {
+        self.color_env();
         return self.output().await;
     }
 
     #[cfg(not(windows))]
     pub async fn pty_output(&mut self) -> Result<Output, Error> {
-        use tokio::io::AsyncReadExt;
+        self.color_env();
+        wait_if_interrupted().await;
 
-        // If color is not used, fallback to piped output.
-        if !*crate::run::USE_COLOR {
+        // If color is not used, fallback to piped output, unless the hook asks for a terminal.
+        // Content for stdin is always piped, a terminal would echo it.
+        let hook_output = hook_output();
+        if (!*crate::run::USE_COLOR && !hook_output.pty) || HOOK_STDIN.try_with(|_| ()).is_ok() {
             return self.output().await;
         }
 
+        self.for_hook(&hook_output)?;
         let (mut pty, pts) = pty::open()?;
         let (stdin, stdout, stderr) = pts.setup_subprocess()?;
 
-        self.inner.stdin(stdin);
-        self.inner.stdout(stdout);
-        self.inner.stderr(stderr);
+        self.stdin(stdin);
+        self.stdout(stdout);
+        self.stderr(stderr);
 
         let session_leader = pts.session_leader();
         unsafe { self.inner.pre_exec(session_leader) };
+        Limited::prepare(&mut self.inner, hook_output.limits);
 
-        let mut child = self.spawn()?;
+        // The child leads its own session, so its process group contains all its children.
+        let mut child = self.spawn_prepared()?;
+        let tree = ProcessTree::new(&child, true);
+        let limited = self.limited(&child, hook_output.limits)?;
 
         let mut stdout = Vec::new();
         let mut buffer = [0u8; 4096];
+        let mut streamer = hook_output.stream.map(LineStreamer::new);
 
         let status = loop {
             tokio::select! {
@@ -217,15 +313,18 @@ impl Cmd {
                     match read_result {
                         Ok(0) => {
                             // EOF from PTY, child should be done
-                            break child.wait().await?;
+                            break Some(child.wait().await?);
                         }
                         Ok(n) => {
                             stdout.extend_from_slice(&buffer[..n]);
+                            if let Some(streamer) = streamer.as_mut() {
+                                streamer.feed(&buffer[..n]);
+                            }
                         }
                         Err(e) => {
                             // PTY error, try to get child status
                             if let Ok(Some(status)) = child.try_wait() {
-                                break status;
+                                break Some(status);
                             }
                             return Err(Error::PtySetup(e));
                         }
@@ -245,14 +344,23 @@ impl Cmd {
                     //         Err(_) => break, // Other errors, stop reading
                     //     }
                     // }
-                    break status;
+                    break Some(status);
                 }
+                () = wait_until(hook_output.deadline) => break None,
             }
         };
+        let Some(status) = status else {
+            return Err(self.timed_out(&mut child, tree, stdout, Vec::new()).await);
+        };
+        tree.finished();
+        wait_if_interrupted().await;
 
         child.stdin.take();
         child.stdout.take();
         child.stderr.take();
+        if let Some(streamer) = streamer {
+            streamer.finish();
+        }
 
         let output = Output {
             status,
@@ -260,13 +368,210 @@ impl Cmd {
             stderr: Vec::new(),
         };
 
+        self.check_limits(&limited, &output)?;
+        self.maybe_check_output(&output)?;
+        Ok(output)
+    }
+
+    /// Captures the output of the command, as set by `hook_output` when run by a hook.
+    async fn streamed_output(&mut self, hook_output: Option<HookOutput>) -> Result<Output, Error> {
+        wait_if_interrupted().await;
+        let stdin = hook_output
+            .is_some()
+            .then(|| HOOK_STDIN.try_with(Clone::clone).ok())
+            .flatten();
+        // Commands run by hooks lead their own process group, so their children can be signaled
+        // with them. Other commands, e.g. git prompting for credentials, keep the terminal.
+        let group = hook_output.is_some();
+        let hook_output = hook_output.unwrap_or_default();
+        self.for_hook(&hook_output)?;
+        self.stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        });
+        self.stdout(Stdio::piped());
+        self.stderr(Stdio::piped());
+        #[cfg(unix)]
+        if group {
+            self.inner.process_group(0);
+        }
+        let HookOutput {
+            stream: prefix,
+            deadline,
+            limits,
+            ..
+        } = hook_output;
+        Limited::prepare(&mut self.inner, limits);
+        let mut child = self.spawn_prepared()?;
+        let tree = ProcessTree::new(&child, group);
+        let limited = self.limited(&child, limits)?;
+
+        let stdin_pipe = child.stdin.take();
+        let stdout_pipe = child.stdout.take().expect("stdout is piped");
+        let stderr_pipe = child.stderr.take().expect("stderr is piped");
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let run = async {
+            tokio::try_join!(
+                write_stdin(stdin_pipe, stdin.as_deref()),
+                read_streamed(
+                    stdout_pipe,
+                    &mut stdout,
+                    prefix.clone().map(LineStreamer::new)
+                ),
+                read_streamed(stderr_pipe, &mut stderr, prefix.map(LineStreamer::new)),
+            )?;
+            child.wait().await
+        };
+        let status = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, run).await.ok(),
+            None => Some(run.await),
+        };
+        let Some(status) = status else {
+            return Err(self.timed_out(&mut child, tree, stdout, stderr).await);
+        };
+        let status = status?;
+        tree.finished();
+        wait_if_interrupted().await;
+
+        let output = Output {
+            status,
+            stdout,
+            stderr,
+        };
+        self.check_limits(&limited, &output)?;
         self.maybe_check_output(&output)?;
         Ok(output)
     }
 
+    fn apply_stdio(&mut self) {
+        if let Some(stdin) = self.stdin.take() {
+            self.inner.stdin(stdin);
+        }
+        if let Some(stdout) = self.stdout.take() {
+            self.inner.stdout(stdout);
+        }
+        if let Some(stderr) = self.stderr.take() {
+            self.inner.stderr(stderr);
+        }
+    }
+
+    /// Set up the command as run by a hook: with its environment, in its sandbox.
+    fn for_hook(&mut self, hook_output: &HookOutput) -> Result<(), Error> {
+        self.hook_env(&hook_output.env);
+        if let Some(sandbox) = &hook_output.sandbox {
+            self.sandboxed(sandbox)?;
+        }
+        if let Some(pass_env) = &hook_output.pass_env {
+            self.hermetic_env(pass_env);
+        }
+        Ok(())
+    }
+
+    /// Run the command in `sandbox`, as arguments of the program setting up the sandbox.
+    fn sandboxed(&mut self, sandbox: &Sandbox) -> Result<(), Error> {
+        let (wrapper, wrapper_args) = sandbox.wrapper().map_err(|cause| Error::Exec {
+            summary: self.summary.clone(),
+            cause,
+        })?;
+        let mut inner = tokio::process::Command::new(wrapper);
+        inner
+            .args(wrapper_args)
+            .arg(self.get_program())
+            .args(self.get_args());
+        for (key, value) in self.get_envs() {
+            match value {
+                Some(value) => inner.env(key, value),
+                None => inner.env_remove(key),
+            };
+        }
+        if let Some(dir) = self.get_current_dir() {
+            inner.current_dir(dir);
+        }
+        self.inner = inner;
+        Ok(())
+    }
+
+    /// Set the `env` of the hook, the variables set for the command by prek, e.g. the `PATH` of
+    /// the hook environment, take precedence.
+    fn hook_env(&mut self, env: &[(String, String)]) {
+        for (key, value) in env {
+            if !self.get_envs().any(|(set, _)| set == OsStr::new(key)) {
+                self.inner.env(key, value);
+            }
+        }
+    }
+
+    /// Only pass the variables of prek allowed by `pass_env`, besides the ones set for the command.
+    fn hermetic_env(&mut self, pass_env: &[String]) {
+        if hook_env::passes_all(pass_env) {
+            return;
+        }
+        let set = self
+            .get_envs()
+            .map(|(key, value)| (key.to_os_string(), value.map(OsStr::to_os_string)))
+            .collect::<Vec<_>>();
+        self.inner.env_clear();
+        self.inner.envs(hook_env::passed_vars(pass_env));
+        for (key, value) in set {
+            match value {
+                Some(value) => self.inner.env(key, value),
+                None => self.inner.env_remove(key),
+            };
+        }
+    }
+
+    fn limited(&self, child: &Child, limits: ResourceLimits) -> Result<Limited, Error> {
+        Limited::new(child, limits).map_err(|cause| Error::Exec {
+            summary: self.summary.clone(),
+            cause,
+        })
+    }
+
+    /// Fail if the command was killed for exceeding a resource limit.
+    fn check_limits(&self, limited: &Limited, output: &Output) -> Result<(), Error> {
+        match limited.exceeded(output) {
+            Some(exceeded) => Err(Error::OverLimit {
+                summary: self.summary.clone(),
+                exceeded,
+                output: output.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Kill the command and its child processes, and return the error with the output so far.
+    async fn timed_out(
+        &self,
+        child: &mut Child,
+        tree: ProcessTree,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+    ) -> Error {
+        drop(tree);
+        let _ = child.start_kill();
+        let status = child.wait().await.unwrap_or_default();
+        Error::TimedOut {
+            summary: self.summary.clone(),
+            output: Output {
+                status,
+                stdout,
+                stderr,
+            },
+        }
+    }
+
     /// Equivalent to [`std::process::Command::status`][]
     /// but logged, with the error wrapped, and status checked (by default)
+    ///
+    /// When run by a hook, the command gets the environment and sandbox of the hook.
     pub async fn status(&mut self) -> Result<ExitStatus, Error> {
+        wait_if_interrupted().await;
+        if let Ok(hook_output) = HOOK_OUTPUT.try_with(Clone::clone) {
+            self.for_hook(&hook_output)?;
+        }
+        self.apply_stdio();
         self.log_command();
         let status = self.inner.status().await.map_err(|cause| Error::Exec {
             summary: self.summary.clone(),
@@ -316,6 +621,21 @@ impl Cmd {
         self
     }
 
+    /// Set the variables with a value, and remove the variables without one.
+    pub fn env_vars<K, V>(&mut self, vars: &[(K, Option<V>)]) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        for (key, val) in vars {
+            match val {
+                Some(val) => self.inner.env(key, val),
+                None => self.inner.env_remove(key),
+            };
+        }
+        self
+    }
+
     /// Forwards to [`std::process::Command::env_remove`][]
     pub fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Self {
         self.inner.env_remove(key);
@@ -336,19 +656,19 @@ impl Cmd {
 
     /// Forwards to [`std::process::Command::stdin`][]
     pub fn stdin<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
-        self.inner.stdin(cfg);
+        self.stdin = Some(cfg.into());
         self
     }
 
     /// Forwards to [`std::process::Command::stdout`][]
     pub fn stdout<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
-        self.inner.stdout(cfg);
+        self.stdout = Some(cfg.into());
         self
     }
 
     /// Forwards to [`std::process::Command::stderr`][]
     pub fn stderr<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
-        self.inner.stderr(cfg);
+        self.stderr = Some(cfg.into());
         self
     }
 
@@ -378,6 +698,22 @@ impl Cmd {
         }
         self
     }
+
+    /// Tell the hook whether prek uses color, with the conventional environment variables.
+    fn color_env(&mut self) -> &mut Self {
+        if *crate::run::USE_COLOR {
+            self.inner
+                .env(EnvVars::CLICOLOR_FORCE, "1")
+                .env(EnvVars::FORCE_COLOR, "1")
+                .env_remove(EnvVars::NO_COLOR);
+        } else {
+            self.inner
+                .env(EnvVars::NO_COLOR, "1")
+                .env_remove(EnvVars::CLICOLOR_FORCE)
+                .env_remove(EnvVars::FORCE_COLOR);
+        }
+        self
+    }
 }
 
 /// Diagnostic APIs (used internally, but available for yourself)
@@ -436,6 +772,193 @@ impl Cmd {
     }
 }
 
+fn hook_output() -> HookOutput {
+    HOOK_OUTPUT.try_with(Clone::clone).unwrap_or_default()
+}
+
+/// Wait until `deadline`, forever if there is none.
+async fn wait_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Read all of `reader` into `output`, printing each line as soon as it's complete if a
+/// `streamer` is given.
+async fn read_streamed(
+    mut reader: impl AsyncRead + Unpin,
+    output: &mut Vec<u8>,
+    mut streamer: Option<LineStreamer>,
+) -> std::io::Result<()> {
+    let mut buffer = [0u8; 4096];
+    loop {
+        let n = reader.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        output.extend_from_slice(&buffer[..n]);
+        if let Some(streamer) = streamer.as_mut() {
+            streamer.feed(&buffer[..n]);
+        }
+    }
+    if let Some(streamer) = streamer {
+        streamer.finish();
+    }
+    Ok(())
+}
+
+/// Write `content` to the stdin of a command and close it. The command may exit without
+/// reading all of it.
+async fn write_stdin(stdin: Option<ChildStdin>, content: Option<&[u8]>) -> std::io::Result<()> {
+    if let (Some(mut stdin), Some(content)) = (stdin, content)
+        && let Err(err) = stdin.write_all(content).await
+        && err.kind() != std::io::ErrorKind::BrokenPipe
+    {
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Never returns once prek is interrupted: it's about to exit, and the commands it runs are
+/// killed.
+async fn wait_if_interrupted() {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Stop the running commands when prek receives `signal`.
+///
+/// The signal is forwarded to the process group of each command, the commands that are still
+/// running after a grace period are killed. On Windows, the commands receive Ctrl-C from the
+/// console themselves.
+pub(crate) fn interrupt(#[cfg(unix)] signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+
+    #[cfg(unix)]
+    for &(pid, group) in RUNNING.lock().unwrap().iter() {
+        send_signal(pid, group, signal);
+    }
+
+    let start = std::time::Instant::now();
+    while !RUNNING.lock().unwrap().is_empty() && start.elapsed() < INTERRUPT_GRACE_PERIOD {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    for &(pid, group) in RUNNING.lock().unwrap().iter() {
+        kill(pid, group);
+    }
+}
+
+#[cfg(unix)]
+fn send_signal(pid: u32, group: bool, signal: libc::c_int) {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return;
+    };
+    if group {
+        unsafe { libc::killpg(pid, signal) };
+    } else {
+        unsafe { libc::kill(pid, signal) };
+    }
+}
+
+/// Kill a command, and all its child processes if it leads its process group.
+fn kill(pid: u32, group: bool) {
+    #[cfg(unix)]
+    send_signal(pid, group, libc::SIGKILL);
+    #[cfg(windows)]
+    {
+        let _ = group;
+        let _ = std::process::Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+/// A running command, killed when dropped unless it finished.
+///
+/// The command is registered in [`RUNNING`] until then, so that signals can be forwarded to it.
+struct ProcessTree {
+    pid: Option<u32>,
+    /// The command leads its own process group, its child processes are signaled with it.
+    group: bool,
+}
+
+impl ProcessTree {
+    fn new(child: &Child, group: bool) -> Self {
+        let pid = child.id();
+        if let Some(pid) = pid {
+            RUNNING.lock().unwrap().push((pid, group));
+        }
+        Self { pid, group }
+    }
+
+    fn finished(mut self) {
+        if let Some(pid) = self.pid.take() {
+            Self::unregister(pid);
+        }
+    }
+
+    fn unregister(pid: u32) {
+        RUNNING
+            .lock()
+            .unwrap()
+            .retain(|&(running, _)| running != pid);
+    }
+}
+
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        if let Some(pid) = self.pid {
+            kill(pid, self.group);
+            Self::unregister(pid);
+        }
+    }
+}
+
+/// Prints the output of a command line by line, with a prefix.
+struct LineStreamer {
+    prefix: String,
+    /// The incomplete last line.
+    line: Vec<u8>,
+}
+
+impl LineStreamer {
+    fn new(prefix: String) -> Self {
+        Self {
+            prefix,
+            line: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, bytes: &[u8]) {
+        self.line.extend_from_slice(bytes);
+        while let Some(end) = memchr::memchr(b'\n', &self.line) {
+            let line = self.line.drain(..=end).collect::<Vec<_>>();
+            self.print(&line);
+        }
+    }
+
+    fn finish(self) {
+        if !self.line.is_empty() {
+            self.print(&self.line);
+        }
+    }
+
+    fn print(&self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        let _ = writeln!(
+            Stdout::Enabled,
+            "{} {}",
+            self.prefix.dimmed(),
+            line.trim_end_matches(['\r', '\n'])
+        );
+    }
+}
+
 /// Returns the number of arguments to skip.
 fn skip_args(cmd: &OsStr, cur: &OsStr, next: Option<&&OsStr>) -> usize {
     if GIT.as_ref().is_ok_and(|git| cmd == git) {
@@ -1,15 +1,19 @@
 use std::cmp::max;
 use std::ffi::OsString;
 use std::path::Path;
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock, OnceLock};
 
 use anstream::ColorChoice;
-use futures::StreamExt;
-use tracing::trace;
+use futures::{StreamExt, TryStreamExt};
+use tracing::{Instrument, trace, trace_span};
 
 use constants::env_vars::EnvVars;
 
+use crate::config::PassFileContents;
+use crate::git;
 use crate::hook::Hook;
+use crate::process::{HOOK_OUTPUT, HOOK_STDIN};
+use crate::settings::settings;
 
 pub(crate) static USE_COLOR: LazyLock<bool> = LazyLock::new(|| {
     match anstream::Stderr::choice(&std::io::stderr()) {
@@ -20,9 +24,20 @@ pub(crate) static USE_COLOR: LazyLock<bool> = LazyLock::new(|| {
     }
 });
 
+/// The number of jobs given with `--jobs`, set before [`CONCURRENCY`] is first used.
+static JOBS: OnceLock<usize> = OnceLock::new();
+
+pub(crate) fn set_jobs(jobs: usize) {
+    let _ = JOBS.set(jobs.max(1));
+}
+
 pub(crate) static CONCURRENCY: LazyLock<usize> = LazyLock::new(|| {
-    if EnvVars::is_set(EnvVars::PREK_NO_CONCURRENCY) {
+    if let Some(jobs) = JOBS.get() {
+        *jobs
+    } else if EnvVars::is_set(EnvVars::PREK_NO_CONCURRENCY) {
         1
+    } else if let Some(jobs) = settings().jobs {
+        jobs.max(1)
     } else {
         std::thread::available_parallelism()
             .map(std::num::NonZero::get)
@@ -107,7 +122,7 @@ impl<'a> Iterator for Partitions<'a> {
 
         while self.current_index < self.filenames.len() {
             let filename = self.filenames[self.current_index];
-            let length = filename.to_string_lossy().len() + 1;
+            let length = filename.as_os_str().len() + 1;
 
             if current_length + length > self.max_cli_length
                 || self.current_index - start_index >= self.max_per_batch
@@ -138,6 +153,10 @@ where
 {
     let concurrency = target_concurrency(hook.require_serial);
 
+    if hook.pass_file_contents == Some(PassFileContents::Stdin) {
+        return run_by_file(hook, filenames, concurrency, run).await;
+    }
+
     // Split files into batches
     let partitions = Partitions::new(hook, filenames, concurrency);
     trace!(
@@ -147,9 +166,77 @@ where
         hook.id,
     );
 
-    #[allow(clippy::redundant_closure)]
     let mut tasks = futures::stream::iter(partitions)
-        .map(|batch| run(batch))
+        .map(|batch| run(batch).instrument(trace_span!("batch", files = batch.len())))
+        .buffered(concurrency);
+
+    let mut results = Vec::new();
+    while let Some(result) = tasks.next().await {
+        results.push(result?);
+    }
+
+    Ok(results)
+}
+
+/// The content of a file in the index, or on disk for files that aren't in it, e.g. given with
+/// `--files`.
+async fn read_file_content(dir: &Path, filename: &Path) -> anyhow::Result<Arc<[u8]>> {
+    let content = match git::get_staged_content(dir, filename).await? {
+        Some(content) => content,
+        None => fs_err::tokio::read(dir.join(filename)).await?,
+    };
+    Ok(Arc::from(content))
+}
+
+/// The content of each file for a hook with `pass_file_contents: stdin`, relative to `dir`.
+pub(crate) async fn read_file_contents(
+    dir: &Path,
+    filenames: &[&Path],
+) -> anyhow::Result<Arc<[Arc<[u8]>]>> {
+    futures::stream::iter(filenames)
+        .map(|filename| read_file_content(dir, filename))
+        .buffered(*CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await
+        .map(Arc::from)
+}
+
+/// Run the hook once per file, with the content of the file in the index on stdin.
+///
+/// The contents are read before the hook runs if it runs under `prek run`, and here otherwise.
+async fn run_by_file<T, F>(
+    hook: &Hook,
+    filenames: &[&Path],
+    concurrency: usize,
+    run: F,
+) -> anyhow::Result<Vec<T>>
+where
+    F: for<'a> AsyncFn(&'a [&'a Path]) -> anyhow::Result<T>,
+    T: Send + 'static,
+{
+    let run = &run;
+    let contents = HOOK_OUTPUT
+        .try_with(|output| output.file_contents.clone())
+        .ok()
+        .flatten()
+        .filter(|contents| contents.len() == filenames.len());
+    let contents = contents.as_deref();
+    let mut tasks = futures::stream::iter(filenames.iter().enumerate())
+        .map(|(i, filename)| {
+            async move {
+                let content = match contents {
+                    Some(contents) => contents[i].clone(),
+                    None => read_file_content(hook.work_dir(), filename).await?,
+                };
+                let batch = if hook.pass_filenames {
+                    std::slice::from_ref(filename)
+                } else {
+                    &[]
+                };
+                HOOK_STDIN.scope(content, run(batch)).await
+            }
+            .instrument(trace_span!("file"))
+        })
         .buffered(concurrency);
 
     let mut results = Vec::new();
@@ -0,0 +1,208 @@
+//! Run the commands of hooks with a restricted view of the filesystem, for `sandbox: true` hooks
+//! and `prek run --sandbox`.
+//!
+//! On Linux, commands run under bubblewrap (`bwrap`): only the system directories, the
+//! repository, the repository and environment of the hook and the toolchains of the store are
+//! mounted, read-only, and the project of the hook is writable, apart from its git directory. The
+//! home directory and `/tmp` are empty directories. On macOS, commands run under `sandbox-exec`
+//! with a profile that hides the home directory instead.
+
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::git::GIT_ROOT;
+use crate::hook::InstalledHook;
+use crate::store::Store;
+
+/// System directories with the programs, libraries and configuration commands need, mounted
+/// read-only if they exist.
+#[cfg(target_os = "linux")]
+const SYSTEM_DIRS: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/opt", "/nix",
+];
+
+#[derive(Debug, Clone)]
+pub(crate) struct Sandbox {
+    /// The project of the hook, the only writable directory.
+    project: PathBuf,
+    /// Directories that can be read even inside the home directory, e.g. the repository and
+    /// the environment of the hook.
+    readable: Vec<PathBuf>,
+    /// Git directories, read-only even inside the project, so hooks can't install git hooks or
+    /// change the git configuration.
+    git_dirs: Vec<PathBuf>,
+    /// Allow network access.
+    network: bool,
+}
+
+impl Sandbox {
+    pub(crate) fn for_hook(hook: &InstalledHook, store: &Store) -> Self {
+        let git_root = GIT_ROOT.as_ref().ok().map(PathBuf::as_path);
+        let project = hook.project().path().to_path_buf();
+        let readable = git_root
+            .into_iter()
+            .chain(hook.repo_path())
+            .chain(hook.env_path())
+            .map(Path::to_path_buf)
+            .chain([store.path().join("tools")])
+            .collect();
+        let mut git_dirs: Vec<_> = git_root.into_iter().map(|root| root.join(".git")).collect();
+        if !git_dirs.contains(&project.join(".git")) {
+            git_dirs.push(project.join(".git"));
+        }
+        Self {
+            project,
+            readable,
+            git_dirs,
+            network: hook.sandbox_network,
+        }
+    }
+
+    /// The program running a command in the sandbox, and its arguments before the command.
+    pub(crate) fn wrapper(&self) -> io::Result<(PathBuf, Vec<OsString>)> {
+        let home = etcetera::home_dir().ok();
+
+        #[cfg(target_os = "linux")]
+        {
+            let bwrap = which::which("bwrap").map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "Sandboxed hooks require bubblewrap (`bwrap`) on Linux",
+                )
+            })?;
+            Ok((bwrap, self.bwrap_args(home.as_deref())))
+        }
+        #[cfg(target_os = "macos")]
+        {
+            let args = self.sandbox_exec_args(home.as_deref());
+            Ok((PathBuf::from("/usr/bin/sandbox-exec"), args))
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        {
+            let _ = home;
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Sandboxed hooks are only supported on Linux and macOS",
+            ))
+        }
+    }
+
+    /// Mounts are applied in order, later mounts cover earlier ones.
+    #[cfg(target_os = "linux")]
+    fn bwrap_args(&self, home: Option<&Path>) -> Vec<OsString> {
+        let mut args = Vec::new();
+        for dir in SYSTEM_DIRS {
+            args.extend(["--ro-bind-try".into(), dir.into(), dir.into()]);
+        }
+        args.extend([
+            "--dev".into(),
+            "/dev".into(),
+            "--proc".into(),
+            "/proc".into(),
+            "--tmpfs".into(),
+            "/tmp".into(),
+        ]);
+        if let Some(home) = home {
+            args.extend(["--tmpfs".into(), home.into()]);
+        }
+        for path in &self.readable {
+            args.extend(["--ro-bind-try".into(), path.into(), path.into()]);
+        }
+        args.extend([
+            "--bind".into(),
+            self.project.clone().into(),
+            self.project.clone().into(),
+        ]);
+        for path in &self.git_dirs {
+            args.extend(["--ro-bind-try".into(), path.into(), path.into()]);
+        }
+        if !self.network {
+            args.push("--unshare-net".into());
+        }
+        args.extend(["--die-with-parent".into(), "--".into()]);
+        args
+    }
+
+    /// The last matching rule of a profile wins.
+    #[cfg(target_os = "macos")]
+    fn sandbox_exec_args(&self, home: Option<&Path>) -> Vec<OsString> {
+        use std::fmt::Write;
+
+        let mut args = Vec::new();
+        let mut profile = String::from("(version 1)\n(allow default)\n(deny file-write*)\n");
+        if let Some(home) = home {
+            args.extend(["-D".into(), param("HOME", home)]);
+            profile.push_str("(deny file-read* (subpath (param \"HOME\")))\n");
+        }
+        for (i, path) in self.readable.iter().enumerate() {
+            args.extend(["-D".into(), param(&format!("READABLE_{i}"), path)]);
+            let _ = writeln!(
+                profile,
+                "(allow file-read* (subpath (param \"READABLE_{i}\")))"
+            );
+        }
+        args.extend(["-D".into(), param("PROJECT", &self.project)]);
+        profile.push_str(
+            "(allow file-read* file-write* (subpath (param \"PROJECT\")))\n\
+             (allow file-write* (subpath \"/private/tmp\") (subpath \"/private/var/folders\") \
+             (subpath \"/dev\"))\n",
+        );
+        for (i, path) in self.git_dirs.iter().enumerate() {
+            args.extend(["-D".into(), param(&format!("GIT_DIR_{i}"), path)]);
+            let _ = writeln!(
+                profile,
+                "(deny file-write* (subpath (param \"GIT_DIR_{i}\")))"
+            );
+        }
+        if !self.network {
+            profile.push_str("(deny network*)\n");
+        }
+        args.extend(["-p".into(), profile.into()]);
+        args
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn param(name: &str, path: &Path) -> OsString {
+    let mut param = OsString::from(format!("{name}="));
+    param.push(path);
+    param
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn sandbox(network: bool) -> Sandbox {
+        Sandbox {
+            project: PathBuf::from("/repo/project"),
+            readable: vec![PathBuf::from("/repo"), PathBuf::from("/store/tools")],
+            git_dirs: vec![PathBuf::from("/repo/.git")],
+            network,
+        }
+    }
+
+    #[test]
+    fn bwrap_args() {
+        let args = sandbox(false).bwrap_args(Some(Path::new("/home/user")));
+        let args = args
+            .iter()
+            .map(|arg| arg.to_str().unwrap())
+            .collect::<Vec<_>>()
+            .join(" ");
+        insta::assert_snapshot!(args, @"--ro-bind-try /usr /usr --ro-bind-try /bin /bin --ro-bind-try /sbin /sbin --ro-bind-try /lib /lib --ro-bind-try /lib32 /lib32 --ro-bind-try /lib64 /lib64 --ro-bind-try /etc /etc --ro-bind-try /opt /opt --ro-bind-try /nix /nix --dev /dev --proc /proc --tmpfs /tmp --tmpfs /home/user --ro-bind-try /repo /repo --ro-bind-try /store/tools /store/tools --bind /repo/project /repo/project --ro-bind-try /repo/.git /repo/.git --unshare-net --die-with-parent --");
+    }
+
+    #[test]
+    fn bwrap_args_network() {
+        let args = sandbox(true).bwrap_args(None);
+        assert!(!args.iter().any(|arg| arg == "--unshare-net"));
+        assert!(!args.iter().any(|arg| arg == "/"));
+        // The git directory is mounted read-only after the project.
+        let project = args.iter().position(|arg| arg == "--bind").unwrap();
+        let git_dir = args.iter().rposition(|arg| arg == "/repo/.git").unwrap();
+        assert!(git_dir > project);
+        assert_eq!(args[git_dir - 2], "--ro-bind-try");
+    }
+}
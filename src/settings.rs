@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use etcetera::BaseStrategy;
+use serde::Deserialize;
+use tracing::debug;
+
+use constants::env_vars::EnvVars;
+
+use crate::cli::ColorChoice;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error("Failed to read user config file `{0}`")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("Failed to parse user config file `{0}`")]
+    Toml(PathBuf, #[source] toml::de::Error),
+    #[error("`cache-dir` in `{0}` must be an absolute path, got `{1}`")]
+    RelativeCacheDir(PathBuf, PathBuf),
+}
+
+/// User defaults read from `~/.config/prek/config.toml`.
+///
+/// Command line flags and environment variables take precedence over these settings.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct Settings {
+    /// Whether to use color in output.
+    pub(crate) color: Option<ColorChoice>,
+    /// The number of hooks and installs to run in parallel.
+    pub(crate) jobs: Option<usize>,
+    /// The prek data directory, overridden by `PREK_HOME`.
+    pub(crate) cache_dir: Option<PathBuf>,
+    /// The proxy for HTTP(S) downloads and git, overridden by `HTTP_PROXY` and `HTTPS_PROXY`.
+    pub(crate) proxy: Option<String>,
+    /// Rewrite repo URLs starting with the key to start with the value instead.
+    #[serde(default)]
+    pub(crate) url_rewrites: BTreeMap<String, String>,
+    /// Show the diff when hooks modify files, like `prek run --show-diff-on-failure`.
+    #[serde(default)]
+    pub(crate) show_diff_on_failure: bool,
+    /// Warn once a day if a newer release of prek is available.
+    #[serde(default)]
+    pub(crate) check_updates: bool,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        etcetera::choose_base_strategy()
+            .map(|dirs| dirs.config_dir().join("prek").join("config.toml"))
+            .ok()
+    }
+
+    fn load() -> Result<Self, Error> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        let content = match fs_err::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(Error::Io(path, e)),
+        };
+        debug!("Loading user config file `{}`", path.display());
+
+        let mut settings: Self =
+            toml::from_str(&content).map_err(|e| Error::Toml(path.clone(), e))?;
+        if let Some(cache_dir) = settings.cache_dir.take() {
+            settings.cache_dir = Some(expand_home(&path, cache_dir)?);
+        }
+        Ok(settings)
+    }
+
+    /// The proxy for child processes and the HTTP client, unless `HTTP_PROXY` or `HTTPS_PROXY`
+    /// is set, which take precedence.
+    pub(crate) fn proxy(&self) -> Option<&str> {
+        if EnvVars::is_set(EnvVars::HTTP_PROXY) || EnvVars::is_set(EnvVars::HTTPS_PROXY) {
+            return None;
+        }
+        self.proxy.as_deref()
+    }
+
+    /// Apply the URL rewrites to a repo URL, the longest matching prefix wins.
+    pub(crate) fn rewrite_url(&self, url: &str) -> String {
+        self.url_rewrites
+            .iter()
+            .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or_else(
+                || url.to_string(),
+                |(prefix, replacement)| format!("{replacement}{}", &url[prefix.len()..]),
+            )
+    }
+}
+
+/// Load the user config file, must be called before any call to [`settings`].
+pub(crate) fn init() -> Result<(), Error> {
+    let settings = Settings::load()?;
+    debug!("User settings: {settings:?}");
+    SETTINGS.get_or_init(|| settings);
+    Ok(())
+}
+
+/// Expand a leading `~` of `path` to the home directory, other relative paths are rejected, they
+/// would depend on the current directory.
+fn expand_home(config: &Path, path: PathBuf) -> Result<PathBuf, Error> {
+    if let Ok(rest) = path.strip_prefix("~")
+        && let Ok(home) = etcetera::home_dir()
+    {
+        return Ok(home.join(rest));
+    }
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Err(Error::RelativeCacheDir(config.to_path_buf(), path))
+    }
+}
+
+/// The user settings of the current process.
+pub(crate) fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{Settings, expand_home};
+
+    #[test]
+    fn rewrite_url() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [url-rewrites]
+            "https://github.com/" = "https://mirror.example.com/github/"
+            "https://github.com/org/" = "git@git.example.com:org/"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings.rewrite_url("https://github.com/astral-sh/ruff-pre-commit"),
+            "https://mirror.example.com/github/astral-sh/ruff-pre-commit"
+        );
+        assert_eq!(
+            settings.rewrite_url("https://github.com/org/standards"),
+            "git@git.example.com:org/standards"
+        );
+        assert_eq!(
+            settings.rewrite_url("https://gitlab.com/org/standards"),
+            "https://gitlab.com/org/standards"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cache_dir() {
+        let config = Path::new("/home/alice/.config/prek/config.toml");
+        let home = etcetera::home_dir().unwrap();
+
+        assert_eq!(
+            expand_home(config, PathBuf::from("~/prek")).unwrap(),
+            home.join("prek")
+        );
+        assert_eq!(
+            expand_home(config, PathBuf::from("/data/prek")).unwrap(),
+            PathBuf::from("/data/prek")
+        );
+        assert!(expand_home(config, PathBuf::from("prek")).is_err());
+    }
+}
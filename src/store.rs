@@ -1,21 +1,27 @@
+use std::collections::BTreeMap;
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock};
+use std::time::SystemTime;
 
 use anyhow::Result;
 use etcetera::BaseStrategy;
 use futures::StreamExt;
 use thiserror::Error;
-use tracing::{debug, warn};
+use tracing::{debug, instrument, warn};
 
+use constants::CONFIG_FILE;
 use constants::env_vars::EnvVars;
 
+use crate::cache_key::CacheKey;
 use crate::config::RemoteRepo;
-use crate::fs::LockedFile;
+use crate::fs::{LockedFile, long_path};
 use crate::git::clone_repo;
 use crate::hook::InstallInfo;
+use crate::http_client::HTTP_CLIENT;
 use crate::run::CONCURRENCY;
+use crate::settings::settings;
 use crate::workspace::HookInitReporter;
 
 #[derive(Debug, Error)]
@@ -28,11 +34,17 @@ pub enum Error {
     Git(#[from] crate::git::Error),
     #[error(transparent)]
     Serde(#[from] serde_json::Error),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
+    Lock(std::io::Error),
 }
 
 pub(crate) static STORE: LazyLock<Result<Store, Error>> = LazyLock::new(|| {
     let path = if let Some(path) = EnvVars::var_os(EnvVars::PREK_HOME) {
         Some(path.into())
+    } else if let Some(path) = &settings().cache_dir {
+        Some(path.clone())
     } else {
         etcetera::choose_base_strategy()
             .map(|path| path.cache_dir().join("prek"))
@@ -78,6 +90,7 @@ impl Store {
     }
 
     /// Clone a remote repo into the store.
+    #[instrument(level = "trace", skip_all, fields(repo = %repo))]
     pub(crate) async fn clone_repo(
         &self,
         repo: &RemoteRepo,
@@ -103,10 +116,15 @@ impl Store {
             %repo,
             "Cloning repo",
         );
-        clone_repo(&repo.repo, &repo.rev, temp.path()).await?;
+        clone_repo(&repo.repo, &repo.rev, temp.path(), |step| {
+            if let Some((reporter, progress)) = progress {
+                reporter.on_clone_step(progress, step);
+            }
+        })
+        .await?;
 
         // TODO: add windows retry
-        fs_err::tokio::remove_dir_all(&target).await.ok();
+        fs_err::tokio::remove_dir_all(long_path(&target)).await.ok();
         fs_err::tokio::rename(temp, &target).await?;
 
         let content = serde_json::to_string_pretty(&repo)?;
@@ -119,6 +137,38 @@ impl Store {
         Ok(target)
     }
 
+    /// Download a config published at `url` into the store, returns the path to the config.
+    ///
+    /// Like cloned repos, a downloaded config is never updated, so the URL should be versioned.
+    pub(crate) async fn download_config(&self, url: &str) -> Result<PathBuf, Error> {
+        let target = self.config_dir(url);
+        let config = target.join(CONFIG_FILE);
+        if target.join(".prek-repo.json").try_exists()? {
+            return Ok(config);
+        }
+
+        debug!(%url, "Downloading config");
+        let content = HTTP_CLIENT
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        fs_err::tokio::create_dir_all(self.scratch_path()).await?;
+        let temp = tempfile::tempdir_in(self.scratch_path())?;
+        fs_err::tokio::write(temp.path().join(CONFIG_FILE), content).await?;
+
+        fs_err::tokio::remove_dir_all(long_path(&target)).await.ok();
+        fs_err::tokio::rename(temp, &target).await?;
+
+        let content = serde_json::to_string_pretty(&serde_json::json!({ "repo": url }))?;
+        fs_err::tokio::write(target.join(".prek-repo.json"), content).await?;
+
+        Ok(config)
+    }
+
     /// Returns installed hooks in the store.
     pub(crate) async fn installed_hooks(&self) -> Vec<Arc<InstallInfo>> {
         let Ok(dirs) = fs_err::read_dir(self.hooks_dir()) else {
@@ -160,18 +210,109 @@ impl Store {
         healthy_hooks
     }
 
-    pub(crate) async fn lock_async(&self) -> Result<LockedFile, std::io::Error> {
-        LockedFile::acquire(self.path.join(".lock"), "store").await
+    pub(crate) async fn lock_async(&self) -> Result<LockedFile, Error> {
+        LockedFile::acquire(self.path.join(".lock"), "store")
+            .await
+            .map_err(Error::Lock)
+    }
+
+    /// Acquire a cross-process lock shared by all hooks of a concurrency group.
+    pub(crate) async fn lock_group_async(&self, group: &str) -> Result<LockedFile, Error> {
+        let mut key = CacheKey::new();
+        group.hash(&mut key);
+        let dir = self.path.join("locks");
+        fs_err::create_dir_all(&dir)?;
+        LockedFile::acquire(dir.join(key.hex()), format!("concurrency group `{group}`"))
+            .await
+            .map_err(Error::Lock)
+    }
+
+    /// Record that the configs were used, with the current time as their last used time.
+    ///
+    /// Configs are keyed by their canonical path, so the same config used from different
+    /// working directories or symlinks is recorded once. The registry is updated under a lock
+    /// and replaced atomically, so concurrent runs, e.g. from multiple worktrees, don't race.
+    pub(crate) async fn track_configs(&self, configs: &[&Path]) -> Result<(), Error> {
+        let _lock = LockedFile::acquire(self.path.join(".configs.lock"), "config registry").await?;
+
+        let mut tracked = self.tracked_configs()?;
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        for config in configs {
+            let key = dunce::canonicalize(config).unwrap_or_else(|_| config.to_path_buf());
+            tracked.insert(key, now);
+        }
+
+        let temp = tempfile::NamedTempFile::new_in(&self.path)?;
+        serde_json::to_writer_pretty(temp.as_file(), &tracked)?;
+        temp.persist(self.configs_file()).map_err(|e| e.error)?;
+
+        Ok(())
+    }
+
+    /// Returns the configs that used the store, with their last used time in seconds since the
+    /// Unix epoch.
+    pub(crate) fn tracked_configs(&self) -> Result<BTreeMap<PathBuf, u64>, Error> {
+        match fs_err::read_to_string(self.configs_file()) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Record the SHA-256 checksum of a file downloaded from `url`.
+    ///
+    /// The checksum is only recorded the first time the file is downloaded. Returns the
+    /// checksum recorded before, if any, so callers can verify later downloads against it.
+    pub(crate) async fn record_checksum(
+        &self,
+        url: &str,
+        checksum: &str,
+    ) -> Result<Option<String>, Error> {
+        let _lock =
+            LockedFile::acquire(self.path.join(".checksums.lock"), "checksum registry").await?;
+
+        let mut checksums: BTreeMap<String, String> =
+            match fs_err::read_to_string(self.checksums_file()) {
+                Ok(content) => serde_json::from_str(&content)?,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+                Err(err) => return Err(err.into()),
+            };
+        if let Some(recorded) = checksums.get(url) {
+            return Ok(Some(recorded.clone()));
+        }
+        checksums.insert(url.to_string(), checksum.to_string());
+
+        let temp = tempfile::NamedTempFile::new_in(&self.path)?;
+        serde_json::to_writer_pretty(temp.as_file(), &checksums)?;
+        temp.persist(self.checksums_file()).map_err(|e| e.error)?;
+
+        Ok(None)
+    }
+
+    /// Acquire the lock of the run history, held while it's updated.
+    pub(crate) async fn lock_history(&self) -> Result<LockedFile, Error> {
+        LockedFile::acquire(self.path.join(".history.lock"), "run history")
+            .await
+            .map_err(Error::Lock)
     }
 
     /// Returns the path to the cloned repo.
-    fn repo_path(&self, repo: &RemoteRepo) -> PathBuf {
+    pub(crate) fn repo_path(&self, repo: &RemoteRepo) -> PathBuf {
         let mut hasher = DefaultHasher::new();
         repo.hash(&mut hasher);
         let digest = to_hex(hasher.finish());
         self.repos_dir().join(digest)
     }
 
+    /// The directory of a config downloaded from `url`.
+    pub(crate) fn config_dir(&self, url: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        self.repos_dir().join(to_hex(hasher.finish()))
+    }
+
     pub(crate) fn repos_dir(&self) -> PathBuf {
         self.path.join("repos")
     }
@@ -201,6 +342,20 @@ impl Store {
     pub(crate) fn log_file(&self) -> PathBuf {
         self.path.join("prek.log")
     }
+
+    fn configs_file(&self) -> PathBuf {
+        self.path.join("configs.json")
+    }
+
+    /// The checksums of downloaded toolchains and archives, keyed by their URL.
+    pub(crate) fn checksums_file(&self) -> PathBuf {
+        self.path.join("checksums.json")
+    }
+
+    /// The runs of each workspace, keyed by the canonical path of its root.
+    pub(crate) fn history_file(&self) -> PathBuf {
+        self.path.join("history.json")
+    }
 }
 
 #[derive(Copy, Clone)]
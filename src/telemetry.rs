@@ -0,0 +1,159 @@
+//! Export the spans of a run and the metrics of hooks to an OpenTelemetry collector.
+//!
+//! Export is enabled by the standard `OTEL_EXPORTER_OTLP_ENDPOINT` (or
+//! `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` and `OTEL_EXPORTER_OTLP_METRICS_ENDPOINT`) environment
+//! variables, the other `OTEL_*` variables configure the exporters as usual.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use constants::env_vars::EnvVars;
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::{Counter, Histogram, MeterProvider};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing::Subscriber;
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::version;
+
+/// The time to wait for the collector.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The instruments recording the results of hooks, only set when metrics are exported.
+static HOOK_METRICS: OnceLock<HookMetrics> = OnceLock::new();
+
+struct HookMetrics {
+    runs: Counter<u64>,
+    duration: Histogram<f64>,
+}
+
+/// Exports the remaining spans and metrics once dropped.
+pub(crate) struct TelemetryGuard {
+    tracer_provider: Option<SdkTracerProvider>,
+    meter_provider: Option<SdkMeterProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = &self.tracer_provider
+            && let Err(err) = provider.shutdown()
+        {
+            tracing::warn!("Failed to export telemetry: {err}");
+        }
+        if let Some(provider) = &self.meter_provider
+            && let Err(err) = provider.shutdown()
+        {
+            tracing::warn!("Failed to export metrics: {err}");
+        }
+    }
+}
+
+fn resource() -> Resource {
+    Resource::builder()
+        .with_service_name("prek")
+        .with_attribute(KeyValue::new(
+            "service.version",
+            version::version().to_string(),
+        ))
+        .build()
+}
+
+/// Record a run of the hook with `full_id` in the `prek.hook.runs` and `prek.hook.duration`
+/// metrics, if they are exported.
+pub(crate) fn record_hook(full_id: &str, status: &str, duration: f64) {
+    let Some(metrics) = HOOK_METRICS.get() else {
+        return;
+    };
+    let attributes = [
+        KeyValue::new("hook", full_id.to_string()),
+        KeyValue::new("status", status.to_string()),
+    ];
+    metrics.runs.add(1, &attributes);
+    metrics.duration.record(duration, &attributes);
+}
+
+fn meter_provider() -> Result<SdkMeterProvider> {
+    let exporter = MetricExporter::builder()
+        .with_http()
+        .with_timeout(EXPORT_TIMEOUT)
+        .build()
+        .context("Failed to create the OpenTelemetry metrics exporter")?;
+    let provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter)
+        .with_resource(resource())
+        .build();
+
+    let meter = provider.meter("prek");
+    let _ = HOOK_METRICS.set(HookMetrics {
+        runs: meter
+            .u64_counter("prek.hook.runs")
+            .with_description("Runs of hooks")
+            .with_unit("{run}")
+            .build(),
+        duration: meter
+            .f64_histogram("prek.hook.duration")
+            .with_description("Wall time of hooks")
+            .with_unit("s")
+            .build(),
+    });
+    Ok(provider)
+}
+
+/// The layer exporting prek spans and the exporter of hook metrics, if an OTLP endpoint is
+/// configured.
+pub(crate) fn layer<S>() -> Result<Option<(Option<impl Layer<S>>, TelemetryGuard)>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let endpoint = EnvVars::is_set(EnvVars::OTEL_EXPORTER_OTLP_ENDPOINT);
+    let traces = endpoint || EnvVars::is_set(EnvVars::OTEL_EXPORTER_OTLP_TRACES_ENDPOINT);
+    let metrics = endpoint || EnvVars::is_set(EnvVars::OTEL_EXPORTER_OTLP_METRICS_ENDPOINT);
+    if !traces && !metrics {
+        return Ok(None);
+    }
+
+    let tracer_provider = if traces {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_timeout(EXPORT_TIMEOUT)
+            .build()
+            .context("Failed to create the OpenTelemetry exporter")?;
+        Some(
+            SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(resource())
+                .build(),
+        )
+    } else {
+        None
+    };
+    let meter_provider = if metrics {
+        Some(meter_provider()?)
+    } else {
+        None
+    };
+
+    // Only spans are exported, events are in the log file.
+    let layer = tracer_provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer("prek"))
+            .with_filter(filter_fn(|metadata| {
+                metadata.is_span() && metadata.target().starts_with("prek")
+            }))
+    });
+
+    Ok(Some((
+        layer,
+        TelemetryGuard {
+            tracer_provider,
+            meter_provider,
+        },
+    )))
+}
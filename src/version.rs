@@ -27,7 +27,7 @@ use std::fmt;
 use serde::Serialize;
 
 /// Information about the git repository where prek was built from.
-#[derive(Serialize)]
+#[derive(Serialize, Hash)]
 pub(crate) struct CommitInfo {
     pub(crate) short_commit_hash: String,
     pub(crate) commit_hash: String,
@@ -37,7 +37,7 @@ pub(crate) struct CommitInfo {
 }
 
 /// prek's version.
-#[derive(Serialize)]
+#[derive(Serialize, Hash)]
 pub(crate) struct VersionInfo {
     /// prek's version, such as "0.0.6"
     pub(crate) version: String,
@@ -69,6 +69,11 @@ impl From<VersionInfo> for clap::builder::Str {
     }
 }
 
+/// The version of pre-commit whose configuration schema prek is compatible with.
+///
+/// Used to check the `minimum_pre_commit_version` field of configs and manifests.
+pub const PRE_COMMIT_COMPAT_VERSION: &str = "4.3.0";
+
 /// Returns information about prek's version.
 pub fn version() -> VersionInfo {
     // Environment variables are only read at compile-time
@@ -20,10 +20,13 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use std::collections::HashSet;
-use std::sync::atomic::AtomicBool;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{LazyLock, Mutex};
 
+use anstream::eprintln;
+use owo_colors::OwoColorize;
+
 // macro hygiene: The user might not have direct dependencies on those crates
 #[doc(hidden)]
 pub use anstream;
@@ -78,3 +81,75 @@ macro_rules! warn_user_once {
         }
     };
 }
+
+/// Deprecations found during the run, grouped by their id, in the order they were found.
+pub static DEPRECATIONS: LazyLock<Mutex<BTreeMap<&'static str, Vec<String>>>> =
+    LazyLock::new(Mutex::default);
+
+/// Ids of deprecations not to print, from `suppress_warnings` of configs.
+static SUPPRESSED: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Mutex::default);
+
+/// Whether unsuppressed deprecations make the command fail, set by `--strict-warnings`.
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Record a deprecation under `id`, to be printed once at the end of the run.
+///
+/// The ids are stable, they can be listed in `suppress_warnings` of the config to not print
+/// the deprecation.
+#[macro_export]
+macro_rules! warn_deprecated {
+    ($id:expr, $($arg:tt)*) => {
+        if let Ok(mut deprecations) = $crate::warnings::DEPRECATIONS.lock() {
+            let message = format!("{}", format_args!($($arg)*));
+            let messages = deprecations.entry($id).or_default();
+            if !messages.contains(&message) {
+                messages.push(message);
+            }
+        }
+    };
+}
+
+/// Do not print the deprecations with the given ids.
+pub fn suppress<'a>(ids: impl IntoIterator<Item = &'a String>) {
+    if let Ok(mut suppressed) = SUPPRESSED.lock() {
+        suppressed.extend(ids.into_iter().cloned());
+    }
+}
+
+/// Make unsuppressed deprecations fail the command.
+pub fn set_strict() {
+    STRICT.store(true, Ordering::SeqCst);
+}
+
+/// Print the deprecations recorded during the run, if warnings are enabled.
+///
+/// Returns true if there are unsuppressed deprecations and `--strict-warnings` is set.
+pub fn flush_deprecations() -> bool {
+    let Ok(mut deprecations) = DEPRECATIONS.lock() else {
+        return false;
+    };
+    let suppressed = SUPPRESSED.lock().map(|s| s.clone()).unwrap_or_default();
+    let deprecations = std::mem::take(&mut *deprecations)
+        .into_iter()
+        .filter(|(id, _)| !suppressed.contains(*id))
+        .collect::<Vec<_>>();
+    if deprecations.is_empty() {
+        return false;
+    }
+
+    if ENABLED.load(Ordering::SeqCst) {
+        eprintln!(
+            "{}{} {}",
+            "warning".yellow().bold(),
+            ":".bold(),
+            "Found deprecated usages, suppress them with `suppress_warnings` in the config:".bold()
+        );
+        for (id, messages) in &deprecations {
+            for message in messages {
+                eprintln!("  - {message} ({})", id.dimmed());
+            }
+        }
+    }
+
+    STRICT.load(Ordering::SeqCst)
+}
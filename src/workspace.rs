@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 use std::fmt::Display;
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::hash::Hash;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
@@ -16,14 +16,16 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{debug, error, instrument};
 
+use crate::cache_key::CacheKey;
 use crate::cli::run::Selectors;
 use crate::config::{self, Config, ManifestHook, read_config};
 use crate::fs::Simplified;
 use crate::git::GIT_ROOT;
 use crate::hook::{self, Hook, HookBuilder, Repo};
+use crate::policy::required_config;
 use crate::store::{CacheBucket, STORE, Store};
 use crate::workspace::Error::MissingPreCommitConfig;
-use crate::{git, store, warn_user};
+use crate::{git, store, warn_user, warnings};
 
 #[derive(Error, Debug)]
 pub(crate) enum Error {
@@ -54,6 +56,7 @@ pub(crate) enum Error {
 
 pub(crate) trait HookInitReporter {
     fn on_clone_start(&self, repo: &str) -> usize;
+    fn on_clone_step(&self, id: usize, step: &str);
     fn on_clone_complete(&self, id: usize);
     fn on_complete(&self);
 }
@@ -110,6 +113,9 @@ impl Project {
 
         let config = read_config(&config_path)?;
         let size = config.repos.len();
+        if let Some(ids) = &config.suppress_warnings {
+            warnings::suppress(ids);
+        }
 
         let root = root.unwrap_or_else(|| {
             config_path
@@ -173,6 +179,17 @@ impl Project {
         self.relative_path = relative_path;
     }
 
+    /// A copy of the project in `root`, e.g. in a temporary copy of the repository.
+    ///
+    /// The relative path is replaced by `root` as well, builtin hooks join it with file names.
+    pub(crate) fn relocated(&self, root: PathBuf) -> Self {
+        Self {
+            relative_path: root.clone(),
+            root,
+            ..self.clone()
+        }
+    }
+
     fn with_idx(&mut self, idx: usize) {
         self.idx = idx;
     }
@@ -239,7 +256,7 @@ impl Project {
         let mut seen = FxHashSet::default();
 
         // Prepare remote repos in parallel.
-        let remotes_iter = self.config.repos.iter().filter_map(|repo| match repo {
+        let remotes_iter = self.repo_configs().filter_map(|repo| match repo {
             // Deduplicate remote repos.
             config::Repo::Remote(repo) if seen.insert(repo) => Some(repo),
             _ => None,
@@ -278,7 +295,7 @@ impl Project {
         let remote_repos = remote_repos.into_inner().unwrap();
         let mut repos = Vec::with_capacity(self.config.repos.len());
 
-        for repo in &self.config.repos {
+        for repo in self.repo_configs() {
             match repo {
                 config::Repo::Remote(repo) => {
                     let repo = remote_repos.get(repo).expect("repo not found");
@@ -292,6 +309,10 @@ impl Project {
                     let repo = Repo::meta(repo.hooks.clone());
                     repos.push(Arc::new(repo));
                 }
+                config::Repo::Builtin(repo) => {
+                    let repo = Repo::builtin(repo.hooks.clone());
+                    repos.push(Arc::new(repo));
+                }
             }
         }
 
@@ -303,8 +324,13 @@ impl Project {
     /// Load and prepare hooks for the project.
     async fn internal_init_hooks(self: Arc<Self>) -> Result<Vec<Hook>, Error> {
         let mut hooks = Vec::new();
+        let mut first_required_hook = None;
 
-        for (repo_config, repo) in zip_eq(self.config.repos.iter(), self.repos.iter()) {
+        for (idx, (repo_config, repo)) in zip_eq(self.repo_configs(), self.repos.iter()).enumerate()
+        {
+            if idx == self.config.repos.len() {
+                first_required_hook = Some(hooks.len());
+            }
             match repo_config {
                 config::Repo::Remote(repo_config) => {
                     for hook_config in &repo_config.hooks {
@@ -349,11 +375,39 @@ impl Project {
                         hooks.push(hook);
                     }
                 }
+                config::Repo::Builtin(repo_config) => {
+                    for hook_config in &repo_config.hooks {
+                        let repo = Arc::clone(repo);
+                        let hook_config = ManifestHook::from(hook_config.clone());
+                        let mut builder =
+                            HookBuilder::new(self.clone(), repo, hook_config, hooks.len());
+                        builder.combine(&self.config);
+
+                        let hook = builder.build().await?;
+                        hooks.push(hook);
+                    }
+                }
             }
         }
 
+        if let Some(first) = first_required_hook {
+            for hook in &mut hooks[first..] {
+                hook.required = true;
+            }
+        }
+        hook::check_dependencies(&hooks)?;
+
         Ok(hooks)
     }
+
+    /// The repos of the project config, followed by the repos of the required config.
+    fn repo_configs(&self) -> impl Iterator<Item = &config::Repo> {
+        self.config.repos.iter().chain(
+            required_config()
+                .into_iter()
+                .flat_map(|config| &config.repos),
+        )
+    }
 }
 
 /// Cache entry for a project configuration file
@@ -459,9 +513,9 @@ impl WorkspaceCache {
 
     /// Get cache file path for a workspace
     fn cache_path(workspace_root: &Path) -> Option<PathBuf> {
-        let mut hasher = DefaultHasher::new();
-        workspace_root.hash(&mut hasher);
-        let digest = hex::encode(hasher.finish().to_le_bytes());
+        let mut key = CacheKey::new();
+        workspace_root.hash(&mut key);
+        let digest = key.hex();
         STORE
             .as_ref()
             .map(|store| {
@@ -649,6 +703,10 @@ impl Workspace {
                     if !file_type.is_dir() {
                         return WalkState::Continue;
                     }
+                    // Submodules and nested repositories are not part of the workspace.
+                    if entry.depth() > 0 && entry.path().join(".git").exists() {
+                        return WalkState::Skip;
+                    }
 
                     match Project::from_directory(entry.path()) {
                         Ok(mut project) => {
@@ -725,7 +783,7 @@ impl Workspace {
             let remotes_iter = self
                 .projects
                 .iter()
-                .flat_map(|proj| proj.config.repos.iter())
+                .flat_map(|proj| proj.repo_configs())
                 .filter_map(|repo| match repo {
                     // Deduplicate remote repos.
                     config::Repo::Remote(repo) if seen.insert(repo) => Some(repo),
@@ -769,7 +827,7 @@ impl Workspace {
         for project in &mut self.projects {
             let mut repos = Vec::with_capacity(project.config.repos.len());
 
-            for repo in &project.config.repos {
+            for repo in project.repo_configs() {
                 match repo {
                     config::Repo::Remote(repo) => {
                         let repo = remote_repos.get(repo).expect("repo not found");
@@ -783,6 +841,10 @@ impl Workspace {
                         let repo = Repo::meta(repo.hooks.clone());
                         repos.push(Arc::new(repo));
                     }
+                    config::Repo::Builtin(repo) => {
+                        let repo = Repo::builtin(repo.hooks.clone());
+                        repos.push(Arc::new(repo));
+                    }
                 }
             }
 
@@ -0,0 +1,141 @@
+use std::process::Command;
+
+use anyhow::Result;
+use assert_cmd::assert::OutputAssertExt;
+use assert_fs::prelude::*;
+
+use crate::common::{TestContext, cmd_snapshot};
+
+mod common;
+
+const GIT_AUTHOR: [&str; 4] = [
+    "-c",
+    "user.name=Prek Test",
+    "-c",
+    "user.email=test@prek.dev",
+];
+
+/// Create a hook repo with an annotated tag `v1.0.0` and a lightweight tag `latest`.
+fn create_hook_repo(context: &TestContext) -> Result<String> {
+    let repo_dir = context.home_dir().child("test-repos/hooks");
+    repo_dir.create_dir_all()?;
+    repo_dir
+        .child(".pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+        - id: test-hook
+          name: Test Hook
+          entry: echo
+          language: system
+    "})?;
+
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(GIT_AUTHOR)
+            .args(args)
+            .current_dir(&repo_dir)
+            .assert()
+            .success();
+    };
+    git(&["init"]);
+    git(&["add", "."]);
+    git(&["commit", "-m", "Initial commit"]);
+    git(&["tag", "v1.0.0", "-m", "v1.0.0"]);
+    git(&["tag", "latest"]);
+
+    Ok(repo_dir.to_string_lossy().to_string())
+}
+
+#[test]
+fn audit() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let repo_path = create_hook_repo(&context)?;
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        repos:
+          - repo: {repo_path}
+            rev: v1.0.0
+            hooks:
+              - id: test-hook
+          - repo: {repo_path}
+            rev: latest
+            hooks:
+              - id: test-hook
+    "});
+    context.git_add(".");
+
+    let mut filters = context.filters();
+    filters.push((r"[0-9a-f]{40}", "[SHA]"));
+
+    cmd_snapshot!(filters.clone(), context.command().arg("audit"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    ✗ [HOME]/test-repos/hooks@latest
+      - `rev` `latest` is not a full commit SHA or an annotated tag
+    ✓ [HOME]/test-repos/hooks@v1.0.0
+    Audited 2 repos, 1 with violations
+
+    ----- stderr -----
+    warning: The following repos have mutable `rev` fields (moving tag / branch):
+    [HOME]/test-repos/hooks: latest
+    Mutable references are never updated after first install and are not supported.
+    See https://pre-commit.com/#using-the-latest-version-for-a-repository for more details.
+    Hint: `prek autoupdate` often fixes this",
+    "#);
+
+    cmd_snapshot!(filters.clone(), context.command().arg("audit").arg("--allowed-host").arg("github.com"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    ✗ [HOME]/test-repos/hooks@latest
+      - local repos are not allowed
+      - `rev` `latest` is not a full commit SHA or an annotated tag
+    ✗ [HOME]/test-repos/hooks@v1.0.0
+      - local repos are not allowed
+    Audited 2 repos, 2 with violations
+
+    ----- stderr -----
+    warning: The following repos have mutable `rev` fields (moving tag / branch):
+    [HOME]/test-repos/hooks: latest
+    Mutable references are never updated after first install and are not supported.
+    See https://pre-commit.com/#using-the-latest-version-for-a-repository for more details.
+    Hint: `prek autoupdate` often fixes this",
+    "#);
+
+    // The cached checkout must be at the commit of the tag.
+    context.install_hooks().assert().success();
+    let checkout = fs_err::read_dir(context.home_dir().child("repos").path())?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| {
+            fs_err::read_to_string(path.join(".prek-repo.json"))
+                .is_ok_and(|content| content.contains("v1.0.0"))
+        })
+        .expect("The repo is not cloned");
+    Command::new("git")
+        .args(GIT_AUTHOR)
+        .args(["commit", "--allow-empty", "-m", "Tampered"])
+        .current_dir(&checkout)
+        .assert()
+        .success();
+
+    cmd_snapshot!(filters, context.command().arg("audit"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    ✗ [HOME]/test-repos/hooks@latest
+      - `rev` `latest` is not a full commit SHA or an annotated tag
+    ✗ [HOME]/test-repos/hooks@v1.0.0
+      - the cached checkout is at `[SHA]`, not at `[SHA]`
+    Audited 2 repos, 2 with violations
+
+    ----- stderr -----
+    warning: The following repos have mutable `rev` fields (moving tag / branch):
+    [HOME]/test-repos/hooks: latest
+    Mutable references are never updated after first install and are not supported.
+    See https://pre-commit.com/#using-the-latest-version-for-a-repository for more details.
+    Hint: `prek autoupdate` often fixes this",
+    "#);
+
+    Ok(())
+}
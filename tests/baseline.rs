@@ -0,0 +1,145 @@
+use assert_fs::fixture::{FileWriteStr, PathChild};
+use insta::assert_snapshot;
+
+use crate::common::{TestContext, cmd_snapshot};
+
+mod common;
+
+const CONFIG: &str = indoc::indoc! {r#"
+    repos:
+      - repo: local
+        hooks:
+          - id: no-todo
+            name: no-todo
+            language: system
+            entry: python3 -c 'import sys; sys.exit(any("TODO" in open(f).read() for f in sys.argv[1:]))'
+            files: \.txt$
+"#};
+
+#[test]
+fn baseline_create() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(CONFIG);
+
+    let cwd = context.work_dir();
+    cwd.child("a.txt").write_str("TODO")?;
+    cwd.child("b.txt").write_str("done")?;
+    cwd.child("c.txt").write_str("TODO")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.command().arg("baseline").arg("create"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Recorded 2 known failure(s) in `.prek-baseline.json`
+
+    ----- stderr -----
+    ");
+
+    assert_snapshot!(context.read(".prek-baseline.json"), @r#"
+    {
+      "no-todo": [
+        "a.txt",
+        "c.txt"
+      ]
+    }
+    "#);
+
+    // Known failures are not reported.
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    no-todo..................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    // New failures are.
+    cwd.child("b.txt").write_str("TODO")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    no-todo..................................................................Failed
+    - hook id: no-todo
+    - exit code: 1
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files").arg("--no-baseline"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    no-todo..................................................................Failed
+    - hook id: no-todo
+    - exit code: 1
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+#[test]
+fn baseline_update() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(CONFIG);
+
+    let cwd = context.work_dir();
+    cwd.child("a.txt").write_str("TODO")?;
+    cwd.child("b.txt").write_str("TODO")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.command().arg("baseline").arg("create"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Recorded 2 known failure(s) in `.prek-baseline.json`
+
+    ----- stderr -----
+    ");
+
+    // Fixed files are removed from the baseline.
+    cwd.child("a.txt").write_str("done")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.command().arg("baseline").arg("update"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Recorded 1 known failure(s) in `.prek-baseline.json`
+
+    ----- stderr -----
+    ");
+
+    assert_snapshot!(context.read(".prek-baseline.json"), @r#"
+    {
+      "no-todo": [
+        "b.txt"
+      ]
+    }
+    "#);
+
+    // The baseline file is removed once all files pass.
+    cwd.child("b.txt").write_str("done")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.command().arg("baseline").arg("update"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    No known failures left, removed `.prek-baseline.json`
+
+    ----- stderr -----
+    ");
+
+    assert!(!context.work_dir().join(".prek-baseline.json").exists());
+
+    Ok(())
+}
@@ -758,3 +758,117 @@ fn fix_byte_order_marker_hook() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn identity_metadata() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.disable_auto_crlf();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: meta
+            hooks:
+              - id: identity
+                files: \.txt$
+                args: [--tags, --size, --hash]
+    "});
+    context.work_dir().child("hello.txt").write_str("Hello\n")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    identity.................................................................Passed
+    - hook id: identity
+    - duration: [TIME]
+      hello.txt tags=[file, non-executable, plain-text, text] size=6 hash=e965047ad7c57865823c7d992b1d046ea66edf78
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Builtin hooks read the files, broken symlinks are never passed to them.
+#[cfg(unix)]
+#[test]
+fn broken_symlinks() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: https://github.com/pre-commit/pre-commit-hooks
+            rev: v5.0.0
+            hooks:
+              - id: end-of-file-fixer
+                types_or: [file, symlink]
+    "});
+
+    let cwd = context.work_dir();
+    cwd.child("file.txt").write_str("Hello World\n")?;
+    cwd.child("link.txt").symlink_to_file("file.txt")?;
+    cwd.child("broken.txt").symlink_to_file("missing.txt")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    fix end of files.........................................................Passed
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Hooks of the `builtin` repo run without cloning `pre-commit-hooks`.
+#[test]
+fn builtin_repo() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.disable_auto_crlf();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: builtin
+            hooks:
+              - id: end-of-file-fixer
+              - id: check-merge-conflict
+                args: [--assume-in-merge]
+    "});
+
+    let cwd = context.work_dir();
+    cwd.child("no_newline.txt")
+        .write_str("No trailing newline")?;
+    cwd.child("conflict.txt")
+        .write_str("<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> branch\n")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fix end of files.........................................................Failed
+    - hook id: end-of-file-fixer
+    - exit code: 1
+    - files were modified by this hook
+      Fixing no_newline.txt
+    check for merge conflicts................................................Failed
+    - hook id: check-merge-conflict
+    - exit code: 1
+      conflict.txt:1: Merge conflict string '<<<<<<<' found
+      conflict.txt:3: Merge conflict string '=======' found
+      conflict.txt:5: Merge conflict string '>>>>>>>' found
+
+    ----- stderr -----
+    ");
+
+    // Builtin hooks don't need a cloned repo.
+    assert!(!context.home_dir().child("repos").exists());
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
+use assert_cmd::assert::OutputAssertExt;
 use assert_fs::assert::PathAssert;
-use assert_fs::fixture::{PathChild, PathCreateDir};
+use assert_fs::fixture::{FileWriteStr, PathChild, PathCreateDir};
 
 use crate::common::{TestContext, cmd_snapshot};
 
@@ -53,3 +54,94 @@ fn cache_clean() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn cache_verify() -> anyhow::Result<()> {
+    let context = TestContext::new();
+
+    let home = context.work_dir().child("home");
+    let hooks = home.child("hooks");
+
+    // An intact environment.
+    let intact = hooks.child("system-intact");
+    intact.create_dir_all()?;
+    intact.child(".prek-hook.json").write_str(&format!(
+        r#"{{"language":"system","language_version":"0.0.0","dependencies":[],"env_path":{:?},"toolchain":"","extra":{{}}}}"#,
+        intact.path()
+    ))?;
+    intact
+        .child(".prek-checksums.json")
+        .write_str(r#"{"files":{}}"#)?;
+
+    // An environment with a missing file.
+    let corrupted = hooks.child("system-corrupted");
+    corrupted.create_dir_all()?;
+    corrupted.child(".prek-hook.json").write_str(&format!(
+        r#"{{"language":"system","language_version":"0.0.0","dependencies":[],"env_path":{:?},"toolchain":"","extra":{{}}}}"#,
+        corrupted.path()
+    ))?;
+    corrupted
+        .child(".prek-checksums.json")
+        .write_str(r#"{"files":{"bin/tool":{"size":3,"hash":"0000000000000000"}}}"#)?;
+
+    cmd_snapshot!(context.filters(), context.command().arg("cache").arg("verify").env("PREK_HOME", &*home), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Removing `system-corrupted`: 1 corrupted files
+      - bin/tool
+    Verified 1 hook environments, removed 1 corrupted
+    Removed environments will be rebuilt the next time their hooks run
+
+    ----- stderr -----
+    ");
+
+    intact.assert(predicates::path::exists());
+    corrupted.assert(predicates::path::missing());
+
+    Ok(())
+}
+
+#[test]
+fn cache_configs() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: echo
+                name: echo
+                language: system
+                entry: echo
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.command().arg("cache").arg("configs"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    No configs have been used yet
+
+    ----- stderr -----
+    ");
+
+    context.run().assert().success();
+    // Running again updates the last used time instead of adding a duplicate entry.
+    context.run().assert().success();
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([(r"\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}", "[TIME]")])
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters, context.command().arg("cache").arg("configs"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [TIME] [TEMP_DIR]/.pre-commit-config.yaml
+
+    ----- stderr -----
+    ");
+}
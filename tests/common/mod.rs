@@ -15,6 +15,7 @@ use constants::env_vars::EnvVars;
 pub struct TestContext {
     temp_dir: ChildPath,
     home_dir: ChildPath,
+    config_dir: ChildPath,
 
     /// Standard filters for this test context.
     filters: Vec<(String, String)>,
@@ -37,6 +38,9 @@ impl TestContext {
         let home_dir = ChildPath::new(root.path()).child("home");
         fs_err::create_dir_all(&home_dir).expect("Failed to create test home directory");
 
+        let config_dir = ChildPath::new(root.path()).child("config");
+        fs_err::create_dir_all(&config_dir).expect("Failed to create test config directory");
+
         let mut filters = Vec::new();
 
         filters.extend(
@@ -50,6 +54,12 @@ impl TestContext {
                 .map(|pattern| (pattern, "[HOME]/".to_string())),
         );
 
+        filters.extend(
+            Self::path_patterns(&config_dir)
+                .into_iter()
+                .map(|pattern| (pattern, "[CONFIG_DIR]/".to_string())),
+        );
+
         let current_exe = assert_cmd::cargo::cargo_bin("prek");
         filters.extend(
             Self::path_patterns(&current_exe)
@@ -60,6 +70,7 @@ impl TestContext {
         Self {
             temp_dir,
             home_dir,
+            config_dir,
             filters,
             _root: root,
         }
@@ -126,7 +137,18 @@ impl TestContext {
             let mut cmd = Command::new(bin);
             cmd.current_dir(self.work_dir());
             cmd.env(EnvVars::PREK_HOME, &**self.home_dir());
+            cmd.env(EnvVars::XDG_CONFIG_HOME, &**self.config_dir());
             cmd.env(EnvVars::PREK_INTERNAL__SORT_FILENAMES, "1");
+            // Don't let the CI the tests run in change the output.
+            for var in [
+                EnvVars::CI,
+                EnvVars::GITHUB_ACTIONS,
+                EnvVars::GITLAB_CI,
+                EnvVars::BUILDKITE,
+                EnvVars::JENKINS_URL,
+            ] {
+                cmd.env_remove(var);
+            }
             cmd
         }
     }
@@ -206,6 +228,11 @@ impl TestContext {
         &self.home_dir
     }
 
+    /// Get the user config directory for the test context, `prek/config.toml` is read from it.
+    pub fn config_dir(&self) -> &ChildPath {
+        &self.config_dir
+    }
+
     /// Initialize a sample project for prek.
     pub fn init_project(&self) {
         Command::new("git")
@@ -0,0 +1,84 @@
+use assert_fs::fixture::{FileWriteStr, PathChild};
+
+use crate::common::{TestContext, cmd_snapshot};
+
+mod common;
+
+fn report(hooks: &[(&str, &str, f64)]) -> String {
+    let hooks = hooks
+        .iter()
+        .map(|(id, status, duration)| {
+            serde_json::json!({
+                "id": id,
+                "full_id": format!(".:{id}"),
+                "name": id,
+                "project": ".",
+                "status": status,
+                "exit_code": i32::from(*status == "failed"),
+                "files_modified": false,
+                "duration": duration,
+                "output": "",
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({
+        "summary": { "success": true, "passed": 0, "failed": 0, "skipped": 0 },
+        "hooks": hooks,
+    })
+    .to_string()
+}
+
+/// Skipped hooks are neither passing nor failing, added and removed hooks are listed.
+#[test]
+fn compare_reports() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    let cwd = context.work_dir();
+
+    cwd.child("base.json").write_str(&report(&[
+        ("fixed", "failed", 0.5),
+        ("broken", "passed", 0.5),
+        ("slow", "passed", 1.0),
+        ("fast", "passed", 0.01),
+        ("no-files", "skipped", 0.0),
+        ("no-more-files", "failed", 0.5),
+        ("removed", "passed", 0.5),
+    ]))?;
+    cwd.child("head.json").write_str(&report(&[
+        ("fixed", "passed", 0.5),
+        ("broken", "failed", 0.5),
+        ("slow", "passed", 3.0),
+        ("fast", "passed", 0.05),
+        ("no-files", "passed", 2.0),
+        ("no-more-files", "skipped", 0.0),
+        ("added", "failed", 0.5),
+    ]))?;
+
+    cmd_snapshot!(context.filters(), context.command().arg("compare").arg("base.json").arg("head.json"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Newly failing hooks:
+      - .:broken
+    Newly passing hooks:
+      - .:fixed
+    Added hooks:
+      - .:added (failed)
+    Removed hooks:
+      - .:removed
+    Timing regressions:
+      - .:slow: [TIME] -> [TIME]
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.command().arg("compare").arg("base.json").arg("base.json"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    No differences found between the reports
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use anyhow::Result;
+
+use crate::common::TestContext;
+
+mod common;
+
+#[test]
+fn completions() -> Result<()> {
+    let context = TestContext::new();
+
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        let output = context.command().args(["completions", shell]).output()?;
+        assert!(output.status.success(), "`{shell}` is supported");
+        let script = String::from_utf8(output.stdout)?;
+        // The script calls prek back to complete dynamically.
+        assert!(
+            script.contains("COMPLETE") && script.contains(shell),
+            "`{shell}` script registers dynamic completions: {script}"
+        );
+    }
+
+    Ok(())
+}
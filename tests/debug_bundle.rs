@@ -0,0 +1,36 @@
+use assert_fs::assert::PathAssert;
+use assert_fs::fixture::PathChild;
+
+use crate::common::{TestContext, cmd_snapshot};
+
+mod common;
+
+#[test]
+fn debug_bundle() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: echo
+                name: echo
+                language: system
+                entry: echo
+    "});
+
+    cmd_snapshot!(context.filters(), context.command().arg("debug-bundle").arg("-o").arg("bundle.zip"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Debug bundle written to `bundle.zip`
+    Secrets are scrubbed on a best-effort basis, please review the bundle before sharing it
+
+    ----- stderr -----
+    ");
+
+    context
+        .work_dir()
+        .child("bundle.zip")
+        .assert(predicates::path::is_file());
+}
@@ -0,0 +1,31 @@
+use crate::common::{TestContext, cmd_snapshot};
+
+mod common;
+
+#[cfg(unix)]
+#[test]
+fn exec_with_hook_env() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: greet
+                name: Greet
+                entry: echo
+                language: system
+                env:
+                  GREETING: hello
+    "});
+
+    cmd_snapshot!(context.filters(), context.command().args(["exec", "greet", "--", "sh", "-c", "echo $GREETING; exit 3"]), @r"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+    hello
+
+    ----- stderr -----
+    ");
+}
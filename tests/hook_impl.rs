@@ -1,5 +1,6 @@
 use std::process::Command;
 
+use assert_cmd::assert::OutputAssertExt;
 use assert_fs::fixture::{FileWriteStr, PathChild, PathCreateDir};
 use constants::CONFIG_FILE;
 use constants::env_vars::EnvVars;
@@ -59,6 +60,290 @@ fn hook_impl() {
     "#);
 }
 
+/// `commit-msg` hooks receive the commit message file as the only argument, regardless of their
+/// `files` and `types` filters.
+#[test]
+fn hook_impl_commit_msg() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc! { r#"
+        repos:
+        - repo: local
+          hooks:
+           - id: conventional-commit
+             name: conventional commit
+             language: system
+             entry: python3 -c 'import sys; msg = open(sys.argv[1]).read(); print(len(sys.argv) - 1, msg.strip()); sys.exit(not msg.startswith("feat:"))'
+             stages: [commit-msg]
+             files: \.py$
+    "#});
+
+    context.git_add(".");
+    context.configure_git_author();
+
+    cmd_snapshot!(context.filters(), context.install().arg("--hook-type").arg("commit-msg"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    prek installed at `.git/hooks/commit-msg`
+
+    ----- stderr -----
+    "#);
+
+    let mut commit = Command::new("git");
+    commit
+        .arg("commit")
+        .arg("-m")
+        .arg("bad message")
+        .current_dir(context.work_dir());
+    cmd_snapshot!(context.filters(), commit, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    conventional commit......................................................Failed
+    - hook id: conventional-commit
+    - exit code: 1
+      1 bad message
+    ");
+
+    let mut filters = context.filters();
+    filters.push((r"\b[0-9a-f]{7}\b", "[SHA1]"));
+
+    let mut commit = Command::new("git");
+    commit
+        .arg("commit")
+        .arg("-m")
+        .arg("feat: add config")
+        .current_dir(context.work_dir());
+    cmd_snapshot!(filters, commit, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [master (root-commit) [SHA1]] feat: add config
+     1 file changed, 9 insertions(+)
+     create mode 100644 .pre-commit-config.yaml
+
+    ----- stderr -----
+    conventional commit......................................................Passed
+    ");
+}
+
+/// `prepare-commit-msg` hooks receive the message file and can read the commit source from
+/// the environment.
+#[test]
+fn hook_impl_prepare_commit_msg() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc! { r#"
+        repos:
+        - repo: local
+          hooks:
+           - id: ticket-number
+             name: ticket number
+             language: system
+             entry: python3 -c 'import os, re, subprocess, sys; branch = subprocess.check_output(["git", "branch", "--show-current"], text=True); ticket = re.search(r"[A-Z]+-\d+", branch).group(); source = os.environ["PRE_COMMIT_COMMIT_MSG_SOURCE"]; msg = open(sys.argv[1]).read().strip(); open(sys.argv[1], "w").write(f"[{ticket}] {msg} ({source})\n")'
+             stages: [prepare-commit-msg]
+    "#});
+
+    context.git_add(".");
+    context.configure_git_author();
+
+    cmd_snapshot!(context.filters(), context.install().arg("--hook-type").arg("prepare-commit-msg"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    prek installed at `.git/hooks/prepare-commit-msg`
+
+    ----- stderr -----
+    "#);
+
+    Command::new("git")
+        .arg("checkout")
+        .arg("-b")
+        .arg("feature/ABC-123-login")
+        .current_dir(context.work_dir())
+        .assert()
+        .success();
+
+    let mut filters = context.filters();
+    filters.push((r"\b[0-9a-f]{7}\b", "[SHA1]"));
+
+    let mut commit = Command::new("git");
+    commit
+        .arg("commit")
+        .arg("-m")
+        .arg("add config")
+        .current_dir(context.work_dir());
+    cmd_snapshot!(filters, commit, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [feature/ABC-123-login (root-commit) [SHA1]] [ABC-123] add config (message)
+     1 file changed, 8 insertions(+)
+     create mode 100644 .pre-commit-config.yaml
+
+    ----- stderr -----
+    ticket number............................................................Passed
+    ");
+}
+
+/// `post-*` hooks receive git's arguments through the environment, and their failures don't
+/// affect the exit status of the git command.
+#[test]
+fn hook_impl_post_checkout() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc! { r#"
+        repos:
+        - repo: local
+          hooks:
+           - id: sync-env
+             name: sync env
+             language: system
+             entry: python3 -c 'import os; print(os.environ["PRE_COMMIT_FROM_REF"] == os.environ["PRE_COMMIT_TO_REF"], os.environ["PRE_COMMIT_CHECKOUT_TYPE"])'
+             stages: [post-checkout]
+             always_run: true
+             verbose: true
+           - id: fail
+             name: fail
+             language: system
+             entry: python3 -c 'raise SystemExit(1)'
+             stages: [post-checkout]
+             always_run: true
+    "#});
+
+    context.git_add(".");
+    context.configure_git_author();
+    context.git_commit("Initial commit");
+
+    cmd_snapshot!(context.filters(), context.install().arg("--hook-type").arg("post-checkout"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    prek installed at `.git/hooks/post-checkout`
+
+    ----- stderr -----
+    "#);
+
+    let mut checkout = Command::new("git");
+    checkout
+        .arg("checkout")
+        .arg("-b")
+        .arg("feature")
+        .current_dir(context.work_dir());
+    cmd_snapshot!(context.filters(), checkout, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    Switched to a new branch 'feature'
+    sync env.................................................................Passed
+    - hook id: sync-env
+    - duration: [TIME]
+      True 1
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+    ");
+}
+
+/// `pre-rebase` hooks receive the upstream and the branch being rebased, and can refuse the
+/// rebase.
+#[test]
+fn hook_impl_pre_rebase() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc! { r#"
+        repos:
+        - repo: local
+          hooks:
+           - id: protect-release
+             name: protect release branches
+             language: system
+             entry: python3 -c 'import os, sys; upstream = os.environ["PRE_COMMIT_PRE_REBASE_UPSTREAM"]; branch = os.environ.get("PRE_COMMIT_PRE_REBASE_BRANCH", ""); print(upstream, branch); sys.exit(branch.startswith("release/"))'
+             stages: [pre-rebase]
+             always_run: true
+             verbose: true
+    "#});
+
+    context.git_add(".");
+    context.configure_git_author();
+    context.git_commit("Initial commit");
+
+    Command::new("git")
+        .arg("branch")
+        .arg("release/1.0")
+        .current_dir(context.work_dir())
+        .assert()
+        .success();
+    Command::new("git")
+        .arg("branch")
+        .arg("feature")
+        .current_dir(context.work_dir())
+        .assert()
+        .success();
+    // Git doesn't run the hook when the branch is already up to date.
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello, world!\n")?;
+    context.git_add(".");
+    context.git_commit("Second commit");
+
+    cmd_snapshot!(context.filters(), context.install().arg("--hook-type").arg("pre-rebase"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    prek installed at `.git/hooks/pre-rebase`
+
+    ----- stderr -----
+    "#);
+
+    let mut rebase = Command::new("git");
+    rebase
+        .arg("rebase")
+        .arg("master")
+        .arg("release/1.0")
+        .current_dir(context.work_dir());
+    cmd_snapshot!(context.filters(), rebase, @r"
+    success: false
+    exit_code: 128
+    ----- stdout -----
+
+    ----- stderr -----
+    protect release branches.................................................Failed
+    - hook id: protect-release
+    - duration: [TIME]
+    - exit code: 1
+      master release/1.0
+    fatal: The pre-rebase hook refused to rebase.
+    ");
+
+    let mut rebase = Command::new("git");
+    rebase
+        .arg("rebase")
+        .arg("--quiet")
+        .arg("master")
+        .arg("feature")
+        .current_dir(context.work_dir());
+    cmd_snapshot!(context.filters(), rebase, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    protect release branches.................................................Passed
+    - hook id: protect-release
+    - duration: [TIME]
+      master feature
+    ");
+
+    Ok(())
+}
+
 #[test]
 fn hook_impl_pre_push() -> anyhow::Result<()> {
     let context = TestContext::new();
@@ -180,6 +465,97 @@ fn hook_impl_pre_push() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Pushing several refs at once runs hooks on the union of the changed files.
+#[test]
+fn hook_impl_pre_push_multiple_refs() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+    context.write_pre_commit_config(indoc! { r"
+        repos:
+        - repo: local
+          hooks:
+           - id: print-files
+             name: print files
+             language: system
+             entry: python3 -c 'import sys; print(sys.argv[1:])'
+             verbose: true
+    "});
+    context.git_add(".");
+    context.git_commit("Initial commit");
+
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(context.work_dir())
+            .assert()
+            .success();
+    };
+
+    let remote_repo_path = context.home_dir().join("remote.git");
+    Command::new("git")
+        .arg("init")
+        .arg("--bare")
+        .arg("--initial-branch=master")
+        .arg(&remote_repo_path)
+        .assert()
+        .success();
+    git(&[
+        "remote",
+        "add",
+        "origin",
+        &remote_repo_path.to_string_lossy(),
+    ]);
+    git(&["branch", "old"]);
+    git(&["push", "origin", "master", "old"]);
+
+    context
+        .install()
+        .arg("--hook-type")
+        .arg("pre-push")
+        .assert()
+        .success();
+
+    // Update `master`, create a new `feature` branch on top of it and delete `old`.
+    context.work_dir().child("a.txt").write_str("a")?;
+    context.git_add("a.txt");
+    context.git_commit("Add a.txt");
+    git(&["checkout", "-b", "feature"]);
+    context.work_dir().child("b.txt").write_str("b")?;
+    // File names from git are never expanded as glob patterns.
+    context.work_dir().child("c[b].txt").write_str("c")?;
+    context.git_add(".");
+    context.git_commit("Add b.txt and c[b].txt");
+
+    let mut filters = context.filters();
+    filters.push((r"\b[0-9a-f]{7}\b", "[SHA1]"));
+
+    let mut push = Command::new("git");
+    push.arg("push")
+        .arg("origin")
+        .arg("master")
+        .arg("feature")
+        .arg(":old")
+        .current_dir(context.work_dir());
+    cmd_snapshot!(filters, push, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['a.txt', 'b.txt', 'c[b].txt']
+
+    ----- stderr -----
+    To [HOME]/remote.git
+       [SHA1]..[SHA1]  master -> master
+     - [deleted]         old
+     * [new branch]      feature -> feature
+    ");
+
+    Ok(())
+}
+
 #[test]
 fn workspace_hook_impl_root() -> anyhow::Result<()> {
     let context = TestContext::new();
@@ -433,6 +809,145 @@ fn workspace_hook_impl_no_project_found() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Hooks installed with `--allow-missing-config` are skipped in commits predating the project.
+#[test]
+fn workspace_hook_impl_allow_missing_project() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    let cwd = context.work_dir();
+    context.init_project();
+    context.configure_git_author();
+    context.disable_auto_crlf();
+
+    cwd.child("file.txt").write_str("Some content")?;
+    context.git_add(".");
+    Command::new("git")
+        .current_dir(cwd)
+        .args(["commit", "-m", "Before the project"])
+        .assert()
+        .success();
+
+    cwd.child("project2").create_dir_all()?;
+    cwd.child("project2")
+        .child(CONFIG_FILE)
+        .write_str(indoc! {r"
+        repos:
+        - repo: local
+          hooks:
+           - id: fail
+             name: fail
+             entry: fail
+             language: fail
+    "})?;
+    context.git_add(".");
+    Command::new("git")
+        .current_dir(cwd)
+        .args(["commit", "-m", "Add the project"])
+        .assert()
+        .success();
+
+    cmd_snapshot!(context.filters(), context.install().arg("--allow-missing-config").current_dir(cwd.join("project2")), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    prek installed at `../.git/hooks/pre-commit`
+
+    ----- stderr -----
+    ");
+
+    // Check out a branch from before the project was added.
+    Command::new("git")
+        .current_dir(cwd)
+        .args(["checkout", "-b", "old", "HEAD~1"])
+        .assert()
+        .success();
+    assert!(!cwd.join("project2").exists());
+
+    cwd.child("file.txt").write_str("Changed content")?;
+    context.git_add(".");
+
+    let mut commit = Command::new("git");
+    commit
+        .current_dir(cwd)
+        .arg("commit")
+        .arg("-m")
+        .arg("Old commit");
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([("[a-f0-9]{7}", "abc1234")])
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters, commit, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [old abc1234] Old commit
+     1 file changed, 1 insertion(+), 1 deletion(-)
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Installed hooks whose config or project is missing are skipped when missing configs are
+/// allowed, and fail otherwise.
+#[test]
+fn hook_impl_allow_missing_config_path() {
+    let context = TestContext::new();
+    context.init_project();
+
+    let hook_impl = |args: &[&str]| {
+        let mut command = context.command();
+        command
+            .args(args)
+            .arg("hook-impl")
+            .arg("--hook-type=pre-commit")
+            .arg("--hook-dir")
+            .arg(context.work_dir().join(".git/hooks"))
+            .arg("--script-version=2");
+        command
+    };
+
+    cmd_snapshot!(context.filters(), hook_impl(&["--config", "missing.yaml"]), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: config file not found: `missing.yaml`
+    - To temporarily silence this, run `PREK_ALLOW_NO_CONFIG=1 git ...`
+    - To permanently silence this, install hooks with the `--allow-missing-config` flag
+    - To uninstall hooks, run `prek uninstall`
+    ");
+
+    cmd_snapshot!(context.filters(), hook_impl(&["--config", "missing.yaml"]).arg("--skip-on-missing-config"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), hook_impl(&["--config", "missing.yaml"]).env(EnvVars::PREK_ALLOW_NO_CONFIG, "1"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+
+    // The project directory of the hook doesn't exist.
+    cmd_snapshot!(context.filters(), hook_impl(&["--cd", "missing"]).arg("--skip-on-missing-config"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+}
+
 #[test]
 fn workspace_hook_impl_with_selectors() -> anyhow::Result<()> {
     let context = TestContext::new();
@@ -497,3 +1012,75 @@ fn workspace_hook_impl_with_selectors() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Hooks installed in the main working tree also run in linked worktrees, on the files of the
+/// worktree being committed to.
+#[test]
+fn hook_impl_linked_worktree() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+    context.disable_auto_crlf();
+    context.write_pre_commit_config(indoc! { r"
+        repos:
+        - repo: local
+          hooks:
+           - id: show-cwd
+             name: show cwd
+             language: system
+             entry: python3 -c 'import os, sys; print(os.path.basename(os.getcwd()), sys.argv[1:])'
+             files: \.txt$
+             verbose: true
+    "});
+    context.git_add(".");
+    context.git_commit("Initial commit");
+
+    cmd_snapshot!(context.filters(), context.install(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    prek installed at `.git/hooks/pre-commit`
+
+    ----- stderr -----
+    "#);
+
+    let worktree = context.work_dir().join("../worktree");
+    Command::new("git")
+        .current_dir(context.work_dir())
+        .arg("worktree")
+        .arg("add")
+        .arg("-q")
+        .arg(&worktree)
+        .assert()
+        .success();
+
+    fs_err::write(worktree.join("file.txt"), "Hello, world!\n")?;
+    Command::new("git")
+        .current_dir(&worktree)
+        .arg("add")
+        .arg("file.txt")
+        .assert()
+        .success();
+
+    let mut commit = Command::new("git");
+    commit
+        .current_dir(&worktree)
+        .arg("commit")
+        .arg("-q")
+        .arg("-m")
+        .arg("Commit from worktree");
+
+    cmd_snapshot!(context.filters(), commit, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    show cwd.................................................................Passed
+    - hook id: show-cwd
+    - duration: [TIME]
+      worktree ['file.txt']
+    ");
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use crate::common::{TestContext, cmd_snapshot};
+
+mod common;
+
+#[test]
+fn info_local_hook() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: my-linter
+                name: My linter
+                entry: my-linter --check
+                language: system
+                args: [--fix, 'two words']
+                files: \.py$
+                types: [python]
+                env:
+                  LINTER_LEVEL: strict
+              - id: other
+                name: Other
+                entry: other
+                language: system
+    "});
+
+    cmd_snapshot!(context.filters(), context.command().arg("info").arg("my-linter"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    .:my-linter
+      ID: my-linter
+      Name: My linter
+      Repo: local
+      Language: system
+      Entry: my-linter --check
+      Args: --fix 'two words'
+      Files: \.py$
+      Types: python
+      Stages: all
+      Work dir: [TEMP_DIR]/
+      Environment variables:
+        LINTER_LEVEL=strict
+      Command: my-linter --check --fix 'two words' [FILES]
+
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.command().arg("info").arg("my-lint"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: selector `my-lint` did not match any hooks, did you mean `my-linter`?
+    ");
+}
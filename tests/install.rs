@@ -161,6 +161,54 @@ fn install() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// A relative `--config` path is rebased onto the git root, where git runs the hooks.
+#[test]
+fn install_with_config_from_subdirectory() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([("#!/bin/sh", "#!/usr/bin/env bash")])
+        .collect::<Vec<_>>();
+
+    let cwd = context.work_dir();
+    cwd.child("ci/.pre-commit-config.yaml")
+        .write_str("repos: []\n")?;
+    cwd.child("src").create_dir_all()?;
+
+    cmd_snapshot!(context.filters(), context.install().current_dir(cwd.child("src")).arg("-c").arg("../ci/.pre-commit-config.yaml"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    prek installed at `../.git/hooks/pre-commit`
+
+    ----- stderr -----
+    "#);
+
+    insta::with_settings!(
+        { filters => filters.clone() },
+        {
+            assert_snapshot!(context.read(".git/hooks/pre-commit"), @r#"
+            #!/usr/bin/env bash
+            # File generated by prek: https://github.com/j178/prek
+            # ID: 182c10f181da4464a3eec51b83331688
+
+            ARGS=(hook-impl --hook-type=pre-commit --config="ci/.pre-commit-config.yaml" --script-version=2)
+
+            HERE="$(cd "$(dirname "$0")" && pwd)"
+            ARGS+=(--hook-dir "$HERE" -- "$@")
+            PREK="[CURRENT_EXE]"
+
+            exec "$PREK" "${ARGS[@]}"
+            "#);
+        }
+    );
+
+    Ok(())
+}
+
 /// Run `prek install --install-hooks` to install the git hook and create prek hook environments.
 #[test]
 fn install_with_hooks() -> anyhow::Result<()> {
@@ -214,7 +262,7 @@ fn install_with_hooks() -> anyhow::Result<()> {
             # File generated by prek: https://github.com/j178/prek
             # ID: 182c10f181da4464a3eec51b83331688
 
-            ARGS=(hook-impl --hook-type=pre-commit --cd="[TEMP_DIR]/" --script-version=2)
+            ARGS=(hook-impl --hook-type=pre-commit --cd="." --script-version=2)
 
             HERE="$(cd "$(dirname "$0")" && pwd)"
             ARGS+=(--hook-dir "$HERE" -- "$@")
@@ -504,7 +552,7 @@ fn workspace_install() -> anyhow::Result<()> {
             # File generated by prek: https://github.com/j178/prek
             # ID: 182c10f181da4464a3eec51b83331688
 
-            ARGS=(hook-impl --hook-type=pre-commit --cd="[TEMP_DIR]/" --script-version=2)
+            ARGS=(hook-impl --hook-type=pre-commit --cd="." --script-version=2)
 
             HERE="$(cd "$(dirname "$0")" && pwd)"
             ARGS+=(--hook-dir "$HERE" -- "$@")
@@ -534,7 +582,7 @@ fn workspace_install() -> anyhow::Result<()> {
             # File generated by prek: https://github.com/j178/prek
             # ID: 182c10f181da4464a3eec51b83331688
 
-            ARGS=(hook-impl --hook-type=pre-commit --cd="[TEMP_DIR]/project3" --script-version=2)
+            ARGS=(hook-impl --hook-type=pre-commit --cd="project3" --script-version=2)
 
             HERE="$(cd "$(dirname "$0")" && pwd)"
             ARGS+=(--hook-dir "$HERE" -- "$@")
@@ -563,7 +611,7 @@ fn workspace_install() -> anyhow::Result<()> {
             # File generated by prek: https://github.com/j178/prek
             # ID: 182c10f181da4464a3eec51b83331688
 
-            ARGS=(hook-impl project3/ --skip=project2/ --hook-type=pre-commit --cd="[TEMP_DIR]/" --script-version=2)
+            ARGS=(hook-impl project3/ --skip=project2/ --hook-type=pre-commit --cd="." --script-version=2)
 
             HERE="$(cd "$(dirname "$0")" && pwd)"
             ARGS+=(--hook-dir "$HERE" -- "$@")
@@ -604,7 +652,7 @@ fn workspace_install() -> anyhow::Result<()> {
             # File generated by prek: https://github.com/j178/prek
             # ID: 182c10f181da4464a3eec51b83331688
 
-            ARGS=(hook-impl project3/ --hook-type=pre-commit --cd="[TEMP_DIR]/" --script-version=2)
+            ARGS=(hook-impl project3/ --hook-type=pre-commit --cd="." --script-version=2)
 
             HERE="$(cd "$(dirname "$0")" && pwd)"
             ARGS+=(--hook-dir "$HERE" -- "$@")
@@ -130,6 +130,81 @@ fn language_version() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Test top-level `default_language_version` for hooks without `language_version`.
+#[test]
+fn default_language_version() {
+    if !EnvVars::is_set(EnvVars::CI) {
+        // Skip when not running in CI, as we may have other Python versions installed locally.
+        return;
+    }
+
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        default_language_version:
+          python: '3.12'
+        repos:
+          - repo: local
+            hooks:
+              - id: python-version
+                name: python-version
+                language: python
+                entry: python -c 'import sys; print(sys.version_info[:2])'
+                always_run: true
+              - id: pinned-version
+                name: pinned-version
+                language: python
+                entry: python -c 'import sys; print(sys.version_info[:2])'
+                language_version: '3.11'
+                always_run: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("-v"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    python-version...........................................................Passed
+    - hook id: python-version
+    - duration: [TIME]
+      (3, 12)
+    pinned-version...........................................................Passed
+    - hook id: pinned-version
+    - duration: [TIME]
+      (3, 11)
+
+    ----- stderr -----
+    "#);
+
+    // Changing the default rebuilds the environment of hooks that don't pin a version.
+    context.write_pre_commit_config(indoc::indoc! {r"
+        default_language_version:
+          python: '3.11'
+        repos:
+          - repo: local
+            hooks:
+              - id: python-version
+                name: python-version
+                language: python
+                entry: python -c 'import sys; print(sys.version_info[:2])'
+                always_run: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("-v"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    python-version...........................................................Passed
+    - hook id: python-version
+    - duration: [TIME]
+      (3, 11)
+
+    ----- stderr -----
+    "#);
+
+}
+
 #[test]
 fn invalid_version() {
     let context = TestContext::new();
@@ -152,7 +227,7 @@ fn invalid_version() {
 
     cmd_snapshot!(context.filters(), context.run(), @r#"
     success: false
-    exit_code: 2
+    exit_code: 3
     ----- stdout -----
 
     ----- stderr -----
@@ -67,6 +67,7 @@ fn list_verbose() {
     .:check-yaml
       ID: check-yaml
       Name: Check YAML
+      Repo: local
       Language: system
       Stages: all
 
@@ -74,6 +75,7 @@ fn list_verbose() {
       ID: check-json
       Name: Check JSON
       Description: Validate JSON files
+      Repo: local
       Language: system
       Stages: all
 
@@ -114,6 +116,7 @@ fn list_verbose() {
       Alias: fmt
       Name: Custom Code Formatter
       Description: Custom formatting tool with specific requirements
+      Repo: local
       Language: script
       Stages: pre-commit, pre-push
 
@@ -122,6 +125,76 @@ fn list_verbose() {
     ");
 }
 
+#[test]
+fn list_long() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: check-json
+                name: Check JSON
+                entry: check-json
+                language: system
+                types: [json]
+                description: Validate JSON files
+                homepage: https://example.com/check-json
+    "});
+
+    cmd_snapshot!(context.filters(), context.list().arg("--long"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    .:check-json
+      ID: check-json
+      Name: Check JSON
+      Description: Validate JSON files
+      Homepage: https://example.com/check-json
+      Repo: local
+      Language: system
+      Stages: all
+
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.list().arg("--output-format=json"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [
+      {
+        "id": "check-json",
+        "full_id": ".:check-json",
+        "name": "Check JSON",
+        "alias": "",
+        "repo": "local",
+        "language": "system",
+        "description": "Validate JSON files",
+        "homepage": "https://example.com/check-json",
+        "stages": [
+          "manual",
+          "commit-msg",
+          "post-checkout",
+          "post-commit",
+          "post-merge",
+          "post-rewrite",
+          "pre-commit",
+          "pre-merge-commit",
+          "pre-push",
+          "pre-rebase",
+          "prepare-commit-msg"
+        ],
+        "installed": true
+      }
+    ]
+
+    ----- stderr -----
+    "#);
+}
+
 #[test]
 fn list_with_hook_ids_filter() {
     let context = TestContext::new();
@@ -308,6 +381,7 @@ fn list_with_aliases() {
       ID: check-yaml
       Alias: yaml-check
       Name: Check YAML
+      Repo: local
       Language: system
       Stages: all
 
@@ -316,6 +390,73 @@ fn list_with_aliases() {
     ");
 }
 
+#[test]
+fn list_groups() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: ruff
+                name: ruff
+                entry: ruff check
+                language: system
+                group: lint
+              - id: ruff-format
+                name: ruff-format
+                entry: ruff format
+                language: system
+                group: format
+              - id: mypy
+                name: mypy
+                entry: mypy
+                language: system
+                group: lint
+              - id: check-json
+                name: Check JSON
+                entry: check-json
+                language: system
+    "});
+
+    cmd_snapshot!(context.filters(), context.list().arg("--groups"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    format
+      .:ruff-format
+    lint
+      .:ruff
+      .:mypy
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.list().arg("--groups").arg("--output-format=json"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [
+      {
+        "name": "format",
+        "hooks": [
+          ".:ruff-format"
+        ]
+      },
+      {
+        "name": "lint",
+        "hooks": [
+          ".:ruff",
+          ".:mypy"
+        ]
+      }
+    ]
+
+    ----- stderr -----
+    "#);
+}
+
 #[test]
 fn list_empty_config() {
     let context = TestContext::new();
@@ -348,7 +489,7 @@ fn list_no_config_file() {
     // No config file exists
     cmd_snapshot!(context.filters(), context.list(), @r"
     success: false
-    exit_code: 2
+    exit_code: 4
     ----- stdout -----
 
     ----- stderr -----
@@ -390,8 +531,10 @@ fn list_json_output() {
         "full_id": ".:check-yaml",
         "name": "Check YAML",
         "alias": "yaml-check",
+        "repo": "local",
         "language": "system",
         "description": null,
+        "homepage": null,
         "stages": [
           "manual",
           "commit-msg",
@@ -404,15 +547,18 @@ fn list_json_output() {
           "pre-push",
           "pre-rebase",
           "prepare-commit-msg"
-        ]
+        ],
+        "installed": true
       },
       {
         "id": "check-json",
         "full_id": ".:check-json",
         "name": "Check JSON",
         "alias": "",
+        "repo": "local",
         "language": "system",
         "description": "Validate JSON files",
+        "homepage": null,
         "stages": [
           "manual",
           "commit-msg",
@@ -425,7 +571,8 @@ fn list_json_output() {
           "pre-push",
           "pre-rebase",
           "prepare-commit-msg"
-        ]
+        ],
+        "installed": true
       }
     ]
 
@@ -443,8 +590,10 @@ fn list_json_output() {
         "full_id": ".:check-json",
         "name": "Check JSON",
         "alias": "",
+        "repo": "local",
         "language": "system",
         "description": "Validate JSON files",
+        "homepage": null,
         "stages": [
           "manual",
           "commit-msg",
@@ -457,7 +606,8 @@ fn list_json_output() {
           "pre-push",
           "pre-rebase",
           "prepare-commit-msg"
-        ]
+        ],
+        "installed": true
       }
     ]
 
@@ -518,8 +668,10 @@ fn workspace_list() -> anyhow::Result<()> {
         "full_id": "nested/project4:show-cwd",
         "name": "Show CWD",
         "alias": "",
+        "repo": "local",
         "language": "python",
         "description": null,
+        "homepage": null,
         "stages": [
           "manual",
           "commit-msg",
@@ -532,15 +684,18 @@ fn workspace_list() -> anyhow::Result<()> {
           "pre-push",
           "pre-rebase",
           "prepare-commit-msg"
-        ]
+        ],
+        "installed": false
       },
       {
         "id": "show-cwd",
         "full_id": "project3/project5:show-cwd",
         "name": "Show CWD",
         "alias": "",
+        "repo": "local",
         "language": "python",
         "description": null,
+        "homepage": null,
         "stages": [
           "manual",
           "commit-msg",
@@ -553,15 +708,18 @@ fn workspace_list() -> anyhow::Result<()> {
           "pre-push",
           "pre-rebase",
           "prepare-commit-msg"
-        ]
+        ],
+        "installed": false
       },
       {
         "id": "show-cwd",
         "full_id": "project2:show-cwd",
         "name": "Show CWD",
         "alias": "",
+        "repo": "local",
         "language": "python",
         "description": null,
+        "homepage": null,
         "stages": [
           "manual",
           "commit-msg",
@@ -574,15 +732,18 @@ fn workspace_list() -> anyhow::Result<()> {
           "pre-push",
           "pre-rebase",
           "prepare-commit-msg"
-        ]
+        ],
+        "installed": false
       },
       {
         "id": "show-cwd",
         "full_id": "project3:show-cwd",
         "name": "Show CWD",
         "alias": "",
+        "repo": "local",
         "language": "python",
         "description": null,
+        "homepage": null,
         "stages": [
           "manual",
           "commit-msg",
@@ -595,15 +756,18 @@ fn workspace_list() -> anyhow::Result<()> {
           "pre-push",
           "pre-rebase",
           "prepare-commit-msg"
-        ]
+        ],
+        "installed": false
       },
       {
         "id": "show-cwd",
         "full_id": ".:show-cwd",
         "name": "Show CWD",
         "alias": "",
+        "repo": "local",
         "language": "python",
         "description": null,
+        "homepage": null,
         "stages": [
           "manual",
           "commit-msg",
@@ -616,7 +780,8 @@ fn workspace_list() -> anyhow::Result<()> {
           "pre-push",
           "pre-rebase",
           "prepare-commit-msg"
-        ]
+        ],
+        "installed": false
       }
     ]
 
@@ -640,14 +805,18 @@ fn workspace_list() -> anyhow::Result<()> {
     project5:show-cwd
       ID: show-cwd
       Name: Show CWD
+      Repo: local
       Language: python
       Stages: all
+      Environment: not installed
 
     .:show-cwd
       ID: show-cwd
       Name: Show CWD
+      Repo: local
       Language: python
       Stages: all
+      Environment: not installed
 
 
     ----- stderr -----
@@ -38,7 +38,7 @@ fn meta_hooks() -> anyhow::Result<()> {
     "});
     context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run(), @r#"
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: false
     exit_code: 1
     ----- stdout -----
@@ -62,7 +62,8 @@ fn meta_hooks() -> anyhow::Result<()> {
     useless exclude..........................................................Passed
 
     ----- stderr -----
-    "#);
+    warning: The `exclude` pattern `$nonexistent^` of hook `useless-exclude` never matches: nothing can follow the end of the path `$`
+    ");
 
     Ok(())
 }
@@ -179,7 +180,46 @@ fn meta_hooks_workspace() -> anyhow::Result<()> {
     useless exclude..........................................................Passed
 
     ----- stderr -----
+    warning: The `exclude` pattern `$nonexistent^` of hook `useless-exclude` never matches: nothing can follow the end of the path `$`
     ");
 
     Ok(())
 }
+
+#[test]
+fn check_useless_global_exclude() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.work_dir();
+    cwd.child("src").create_dir_all()?;
+    cwd.child("src/main.py").write_str("print('hello')\n")?;
+    cwd.child("docs").create_dir_all()?;
+    cwd.child("docs/index.md").write_str("# Docs\n")?;
+
+    // `docs/` exists, but the global `files` pattern never includes it,
+    // so the global exclude has nothing to exclude.
+    context.write_pre_commit_config(indoc::indoc! {r"
+        files: ^(src/|\.pre-commit-config\.yaml$)
+        exclude: ^docs/
+        repos:
+          - repo: meta
+            hooks:
+              - id: check-useless-excludes
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Check useless excludes...................................................Failed
+    - hook id: check-useless-excludes
+    - exit code: 1
+      The global exclude pattern `^docs/` does not match any files
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
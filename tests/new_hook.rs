@@ -0,0 +1,65 @@
+use crate::common::{TestContext, cmd_snapshot};
+
+mod common;
+
+#[cfg(unix)]
+#[test]
+fn new_hook_python() {
+    let context = TestContext::new();
+
+    cmd_snapshot!(context.filters(), context.command().arg("new-hook").arg("my-hook").arg("--name").arg("My hook: check"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Created hook `my-hook` in `my-hook`
+      .pre-commit-hooks.yaml
+      tests/smoke-test.sh
+      pyproject.toml
+      my_hook/__init__.py
+      my_hook/main.py
+    Commit the files, then run `tests/smoke-test.sh` to try the hook
+
+    ----- stderr -----
+    ");
+
+    insta::assert_snapshot!(context.read("my-hook/.pre-commit-hooks.yaml"), @r"
+    - id: my-hook
+      name: 'My hook: check'
+      description: TODO describe what the hook checks.
+      entry: my-hook
+      language: python
+      types: [text]
+    ");
+
+    cmd_snapshot!(context.filters(), context.command().arg("validate-manifest").arg("my-hook/.pre-commit-hooks.yaml"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+
+    // The directory isn't empty anymore.
+    cmd_snapshot!(context.filters(), context.command().arg("new-hook").arg("my-hook"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Directory `my-hook` already exists and is not empty
+    ");
+}
+
+#[test]
+fn new_hook_invalid_id() {
+    let context = TestContext::new();
+
+    cmd_snapshot!(context.filters(), context.command().arg("new-hook").arg("hooks").arg("--id").arg("My_Hook").arg("--language").arg("rust"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Invalid hook id `My_Hook`, use lowercase letters, digits and dashes, starting with a letter
+    ");
+}
@@ -81,7 +81,7 @@ fn invalid_config() {
 
     cmd_snapshot!(context.filters(), context.run(), @r#"
     success: false
-    exit_code: 2
+    exit_code: 3
     ----- stdout -----
 
     ----- stderr -----
@@ -103,7 +103,7 @@ fn invalid_config() {
 
     cmd_snapshot!(context.filters(), context.run(), @r#"
     success: false
-    exit_code: 2
+    exit_code: 3
     ----- stdout -----
 
     ----- stderr -----
@@ -125,7 +125,7 @@ fn invalid_config() {
 
     cmd_snapshot!(context.filters(), context.run(), @r#"
     success: false
-    exit_code: 2
+    exit_code: 3
     ----- stdout -----
 
     ----- stderr -----
@@ -270,6 +270,19 @@ fn multiple_hook_ids() {
     ----- stderr -----
     warning: selector `nonexistent-hook` did not match any hooks
     error: No hooks found after filtering with the given selectors
+    Available hooks: hook1, hook2, shared-name, shared-name-2
+    ");
+
+    // Hook-id with a typo
+    cmd_snapshot!(context.filters(), context.run().arg("hook3"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: selector `hook3` did not match any hooks, did you mean `hook1` or `hook2`?
+    error: No hooks found after filtering with the given selectors
+    Available hooks: hook1, hook2, shared-name, shared-name-2
     ");
 
     // Multiple hook_ids match nothing
@@ -283,6 +296,7 @@ fn multiple_hook_ids() {
       - `nonexistent-hook`
       - `nonexistent-hook-2`
     error: No hooks found after filtering with the given selectors
+    Available hooks: hook1, hook2, shared-name, shared-name-2
     ");
 
     // Hook-id matches one hook
@@ -319,6 +333,17 @@ fn multiple_hook_ids() {
     warning: selector `nonexistent-hook` did not match any hooks
     ");
 
+    // Selected hooks run in the order of the config
+    cmd_snapshot!(context.filters(), context.run().arg("hook2").arg("hook1"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    First Hook...............................................................Passed
+    Second Hook..............................................................Passed
+
+    ----- stderr -----
+    ");
+
     // Multiple hook-ids with some duplicates and aliases
     cmd_snapshot!(context.filters(), context.run().arg("hook1").arg("shared-name").arg("hook1"), @r#"
     success: true
@@ -468,6 +493,7 @@ fn skips() {
     trailing-whitespace......................................................Failed
     - hook id: trailing-whitespace
     - exit code: 1
+    fix end of files........................................................Skipped
     check json...............................................................Failed
     - hook id: check-json
     - exit code: 1
@@ -479,591 +505,786 @@ fn skips() {
     success: false
     exit_code: 1
     ----- stdout -----
+    trailing-whitespace.....................................................Skipped
+    fix end of files........................................................Skipped
+    check json...............................................................Failed
+    - hook id: check-json
+    - exit code: 1
+
+    ----- stderr -----
+    ");
+
+    // Hooks skipped with `--skip` are not reported.
+    cmd_snapshot!(context.filters(), context.run().arg("--skip").arg("trailing-whitespace").arg("--skip").arg("end-of-file-fixer"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
     check json...............................................................Failed
     - hook id: check-json
     - exit code: 1
 
     ----- stderr -----
     ");
+
+    cmd_snapshot!(context.filters(), context.run().env("SKIP", "trailing-whitespace,end-of-file-fixer,check-json"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    trailing-whitespace.....................................................Skipped
+    fix end of files........................................................Skipped
+    check json..............................................................Skipped
+
+    ----- stderr -----
+    ");
 }
 
-/// Run hooks with matched `stage`.
+/// The `ci` block of pre-commit.ci is accepted, and `ci.skip` is honored in CI.
 #[test]
-fn stage() {
+fn ci_skip() {
     let context = TestContext::new();
     context.init_project();
     context.write_pre_commit_config(indoc::indoc! {r"
+        ci:
+          autofix_prs: false
+          autoupdate_schedule: weekly
+          skip: [fail-hook]
+          submodules: false
         repos:
           - repo: local
             hooks:
-              - id: manual-stage
-                name: manual-stage
-                language: system
-                entry: echo manual-stage
-                stages: [ manual ]
-              # Defaults to all stages.
-              - id: default-stage
-                name: default-stage
-                language: system
-                entry: echo default-stage
-              - id: post-commit-stage
-                name: post-commit-stage
+              - id: pass-hook
+                name: pass-hook
                 language: system
-                entry: echo post-commit-stage
-                stages: [ post-commit ]
+                entry: echo
+              - id: fail-hook
+                name: fail-hook
+                language: fail
+                entry: fail-hook failed
     "});
     context.git_add(".");
 
-    // By default, run hooks with `pre-commit` stage.
-    cmd_snapshot!(context.filters(), context.run(), @r#"
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    pass-hook................................................................Passed
+    fail-hook................................................................Failed
+    - hook id: fail-hook
+    - exit code: 1
+      fail-hook failed
+
+      .pre-commit-config.yaml
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().env(EnvVars::CI, "true"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    default-stage............................................................Passed
+    pass-hook................................................................Passed
+    fail-hook...............................................................Skipped
 
     ----- stderr -----
-    "#);
+    ");
+}
 
-    // Run hooks with `manual` stage.
-    cmd_snapshot!(context.filters(), context.run().arg("--hook-stage").arg("manual"), @r#"
+/// `skip_on_branches` and `only_on_branches` select hooks by the current branch.
+#[test]
+fn branch_conditions() {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: release-only
+                name: release-only
+                language: system
+                entry: echo release-only
+                only_on_branches: ['release/*']
+              - id: not-on-wip
+                name: not-on-wip
+                language: system
+                entry: echo not-on-wip
+                skip_on_branches: ['wip/*']
+    "});
+    context.git_add(".");
+    context.git_commit("Initial commit");
+
+    let checkout = |branch: &str| {
+        Command::new("git")
+            .args(["checkout", "-b", branch])
+            .current_dir(context.work_dir())
+            .assert()
+            .success();
+    };
+
+    checkout("wip/experiment");
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r#"
     success: true
     exit_code: 0
     ----- stdout -----
-    manual-stage.............................................................Passed
-    default-stage............................................................Passed
+    release-only.........................................(skipped on branch)Skipped
+    not-on-wip...........................................(skipped on branch)Skipped
 
     ----- stderr -----
     "#);
 
-    // Run hooks with `post-commit` stage.
-    cmd_snapshot!(context.filters(), context.run().arg("--hook-stage").arg("post-commit"), @r#"
+    checkout("release/1.0");
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r#"
     success: true
     exit_code: 0
     ----- stdout -----
-    default-stage........................................(no files to check)Skipped
-    post-commit-stage....................................(no files to check)Skipped
+    release-only.............................................................Passed
+    not-on-wip...............................................................Passed
 
     ----- stderr -----
     "#);
 }
 
-/// Test global `files`, `exclude`, and hook level `files`, `exclude`.
+/// Hooks limited to other platforms are skipped.
+#[cfg(not(windows))]
 #[test]
-fn files_and_exclude() -> Result<()> {
+fn platforms() {
     let context = TestContext::new();
-
     context.init_project();
-
-    let cwd = context.work_dir();
-    cwd.child("file.txt").write_str("Hello, world!  \n")?;
-    cwd.child("valid.json").write_str("{}\n  ")?;
-    cwd.child("invalid.json").write_str("{}")?;
-    cwd.child("main.py").write_str(r#"print "abc"  "#)?;
-
-    // Global files and exclude.
     context.write_pre_commit_config(indoc::indoc! {r"
-        files: file.txt
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing whitespace
+              - id: windows-only
+                name: windows-only
                 language: system
-                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
-                types: [text]
-              - id: end-of-file-fixer
-                name: fix end of files
-                language: system
-                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
-                types: [text]
-              - id: check-json
-                name: check json
+                entry: cmd /c exit 1
+                platforms: [windows]
+              - id: unix-only
+                name: unix-only
                 language: system
-                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
-                types: [json]
+                entry: echo unix-only
+                platforms: [linux, macos]
     "});
     context.git_add(".");
 
     cmd_snapshot!(context.filters(), context.run(), @r#"
-    success: false
-    exit_code: 1
+    success: true
+    exit_code: 0
     ----- stdout -----
-    trailing whitespace......................................................Failed
-    - hook id: trailing-whitespace
-    - exit code: 1
-      ['file.txt']
-    fix end of files.........................................................Failed
-    - hook id: end-of-file-fixer
-    - exit code: 1
-      ['file.txt']
-    check json...........................................(no files to check)Skipped
+    windows-only............................................(other platform)Skipped
+    unix-only................................................................Passed
 
     ----- stderr -----
     "#);
+}
 
-    // Override hook level files and exclude.
+/// `enabled_if_env` and `disabled_if_env` toggle hooks with environment variables.
+#[test]
+fn env_conditions() {
+    let context = TestContext::new();
+    context.init_project();
     context.write_pre_commit_config(indoc::indoc! {r"
-        files: file.txt
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing whitespace
+              - id: slow
+                name: slow
                 language: system
-                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
-                files: valid.json
-              - id: end-of-file-fixer
-                name: fix end of files
-                language: system
-                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
-                exclude: (valid.json|main.py)
-              - id: check-json
-                name: check json
+                entry: echo slow
+                disabled_if_env: [PREK_FAST]
+              - id: deploy
+                name: deploy
                 language: system
-                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                entry: echo deploy
+                enabled_if_env: [DEPLOY_CHECKS=1]
     "});
     context.git_add(".");
 
     cmd_snapshot!(context.filters(), context.run(), @r#"
-    success: false
-    exit_code: 1
+    success: true
+    exit_code: 0
     ----- stdout -----
-    trailing whitespace..................................(no files to check)Skipped
-    fix end of files.........................................................Failed
-    - hook id: end-of-file-fixer
-    - exit code: 1
-      ['file.txt']
-    check json...............................................................Failed
-    - hook id: check-json
-    - exit code: 1
-      ['file.txt']
+    slow.....................................................................Passed
+    deploy.................................................(disabled by env)Skipped
 
     ----- stderr -----
     "#);
 
-    Ok(())
+    cmd_snapshot!(context.filters(), context.run().env("PREK_FAST", "1").env("DEPLOY_CHECKS", "1"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    slow...................................................(disabled by env)Skipped
+    deploy...................................................................Passed
+
+    ----- stderr -----
+    "#);
 }
 
-/// Test selecting files by type, `types`, `types_or`, and `exclude_types`.
+/// A config can extend shared configs, overriding their hooks.
 #[test]
-fn file_types() -> Result<()> {
+fn extends_config() -> Result<()> {
     let context = TestContext::new();
-
     context.init_project();
 
-    let cwd = context.work_dir();
-    cwd.child("file.txt").write_str("Hello, world!  ")?;
-    cwd.child("json.json").write_str("{}\n  ")?;
-    cwd.child("main.py").write_str(r#"print "abc"  "#)?;
-
-    context.write_pre_commit_config(indoc::indoc! {r#"
+    context
+        .work_dir()
+        .child("shared/base.yaml")
+        .write_str(indoc::indoc! {r"
+        fail_fast: true
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: greet
+                name: greet
                 language: system
-                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
-                types: ["json"]
-          - repo: local
-            hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+                entry: echo base
+                pass_filenames: false
+                verbose: true
+              - id: lint
+                name: lint
                 language: system
-                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
-                types_or: ["json", "python"]
+                entry: echo lint
+                pass_filenames: false
+    "})?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        extends: shared/base.yaml
+        repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: greet
+                name: greet
                 language: system
-                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
-                exclude_types: ["json"]
-          - repo: local
-            hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+                entry: echo override
+                pass_filenames: false
+                verbose: true
+              - id: extra
+                name: extra
                 language: system
-                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
-                types: ["json" ]
-                exclude_types: ["json"]
-    "#});
+                entry: echo extra
+                pass_filenames: false
+    "});
     context.git_add(".");
 
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    greet....................................................................Passed
+    - hook id: greet
+    - duration: [TIME]
+      override
+    lint.....................................................................Passed
+    extra....................................................................Passed
+
+    ----- stderr -----
+    "#);
+
+    // Circular `extends` is an error.
+    context
+        .work_dir()
+        .child("shared/base.yaml")
+        .write_str("extends: [../.pre-commit-config.yaml]\nrepos: []\n")?;
+
     cmd_snapshot!(context.filters(), context.run(), @r#"
     success: false
-    exit_code: 1
+    exit_code: 3
     ----- stdout -----
-    trailing-whitespace......................................................Failed
-    - hook id: trailing-whitespace
-    - exit code: 1
-      ['json.json']
-    trailing-whitespace......................................................Failed
-    - hook id: trailing-whitespace
-    - exit code: 1
-      ['main.py', 'json.json']
-    trailing-whitespace......................................................Failed
-    - hook id: trailing-whitespace
-    - exit code: 1
-      ['file.txt', '.pre-commit-config.yaml', 'main.py']
-    trailing-whitespace..................................(no files to check)Skipped
 
     ----- stderr -----
+    error: Configs extend each other: `.pre-commit-config.yaml` -> `shared/base.yaml` -> `.pre-commit-config.yaml`
     "#);
 
     Ok(())
 }
 
-/// Abort the run if a hook fails.
+/// A hook of the extending config overrides every hook of the base config with the same id.
 #[test]
-fn fail_fast() {
+fn extends_config_duplicate_ids() -> Result<()> {
     let context = TestContext::new();
     context.init_project();
 
-    context.write_pre_commit_config(indoc::indoc! {r#"
+    context
+        .work_dir()
+        .child("base.yaml")
+        .write_str(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: mypy
+                name: mypy strict
+                alias: mypy-strict
                 language: system
-                entry: python3 -c 'print("Fixing files"); exit(1)'
-                always_run: true
-                fail_fast: false
-              - id: trailing-whitespace
-                name: trailing-whitespace
+                entry: echo strict
+                pass_filenames: false
+              - id: mypy
+                name: mypy loose
+                alias: mypy-loose
                 language: system
-                entry: python3 -c 'print("Fixing files"); exit(1)'
-                always_run: true
-                fail_fast: true
-              - id: trailing-whitespace
-                name: trailing-whitespace
-                language: system
-                entry: python3 -V
-                always_run: true
-              - id: trailing-whitespace
-                name: trailing-whitespace
+                entry: echo loose
+                pass_filenames: false
+    "})?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        extends: base.yaml
+        repos:
+          - repo: local
+            hooks:
+              - id: mypy
+                name: mypy
                 language: system
-                entry: python3 -V
-                always_run: true
-    "#});
+                entry: echo override
+                pass_filenames: false
+    "});
     context.git_add(".");
 
     cmd_snapshot!(context.filters(), context.run(), @r#"
-    success: false
-    exit_code: 1
+    success: true
+    exit_code: 0
     ----- stdout -----
-    trailing-whitespace......................................................Failed
-    - hook id: trailing-whitespace
-    - exit code: 1
-      Fixing files
-    trailing-whitespace......................................................Failed
-    - hook id: trailing-whitespace
-    - exit code: 1
-      Fixing files
+    mypy.....................................................................Passed
+    mypy.....................................................................Passed
 
     ----- stderr -----
     "#);
+
+    Ok(())
 }
 
-/// Run from a subdirectory. File arguments should be fixed to be relative to the root.
+/// A config can extend a shared config inside a git repo, `<repo>@<rev>:<path>`.
 #[test]
-fn subdirectory() -> Result<()> {
+fn extends_shared_config_from_repo() -> Result<()> {
     let context = TestContext::new();
     context.init_project();
 
-    let cwd = context.work_dir();
-    let child = cwd.child("foo/bar/baz");
-    child.create_dir_all()?;
-    child.child("file.txt").write_str("Hello, world!\n")?;
-
-    context.write_pre_commit_config(indoc::indoc! {r"
+    let repo = context.home_dir().child("standards");
+    repo.create_dir_all()?;
+    repo.child("precommit.yaml").write_str(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: org-check
+                name: org-check
                 language: system
-                entry: python3 -c 'import sys; print(sys.argv[1]); exit(1)'
-                always_run: true
-    "});
+                entry: echo org
+                pass_filenames: false
+    "})?;
+    for args in [
+        &["init", "--initial-branch=master"][..],
+        &["add", "."],
+        &[
+            "-c",
+            "user.name=Prek Test",
+            "-c",
+            "user.email=test@prek.dev",
+            "commit",
+            "-m",
+            "Initial commit",
+        ],
+        &["tag", "v1"],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo)
+            .assert()
+            .success();
+    }
 
+    context.write_pre_commit_config(&format!(
+        indoc::indoc! {r"
+        extends: '{}@v1:precommit.yaml'
+        repos:
+          - repo: local
+            hooks:
+              - id: project-check
+                name: project-check
+                language: system
+                entry: echo project
+                pass_filenames: false
+        "},
+        repo.display()
+    ));
     context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run().current_dir(&child).arg("--files").arg("file.txt"), @r#"
-    success: false
-    exit_code: 1
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: true
+    exit_code: 0
     ----- stdout -----
-    trailing-whitespace......................................................Failed
-    - hook id: trailing-whitespace
-    - exit code: 1
-      foo/bar/baz/file.txt
+    org-check................................................................Passed
+    project-check............................................................Passed
 
     ----- stderr -----
     "#);
 
-    cmd_snapshot!(context.filters(), context.run().arg("--cd").arg(&*child).arg("--files").arg("file.txt"), @r#"
+    // Shared configs are never fetched over plain HTTP.
+    context.write_pre_commit_config("extends: http://example.com/precommit.yaml\nrepos: []\n");
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
     success: false
-    exit_code: 1
+    exit_code: 3
     ----- stdout -----
-    trailing-whitespace......................................................Failed
-    - hook id: trailing-whitespace
-    - exit code: 1
-      foo/bar/baz/file.txt
 
     ----- stderr -----
+    error: Failed to fetch the shared config `http://example.com/precommit.yaml`
+      caused by: Shared configs must be fetched over HTTPS
     "#);
 
     Ok(())
 }
 
-/// Test hook `log_file` option.
+/// Run hooks with matched `stage`.
 #[test]
-fn log_file() {
+fn stage() {
     let context = TestContext::new();
     context.init_project();
-
-    context.write_pre_commit_config(indoc::indoc! {r#"
+    context.write_pre_commit_config(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: manual-stage
+                name: manual-stage
                 language: system
-                entry: python3 -c 'print("Fixing files"); exit(1)'
-                always_run: true
-                log_file: log.txt
-    "#});
+                entry: echo manual-stage
+                stages: [ manual ]
+              # Defaults to all stages.
+              - id: default-stage
+                name: default-stage
+                language: system
+                entry: echo default-stage
+              - id: post-commit-stage
+                name: post-commit-stage
+                language: system
+                entry: echo post-commit-stage
+                stages: [ post-commit ]
+    "});
     context.git_add(".");
 
+    // By default, run hooks with `pre-commit` stage.
     cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    default-stage............................................................Passed
+
+    ----- stderr -----
+    "#);
+
+    // Run hooks with `manual` stage.
+    cmd_snapshot!(context.filters(), context.run().arg("--hook-stage").arg("manual"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    manual-stage.............................................................Passed
+    default-stage............................................................Passed
+
+    ----- stderr -----
+    "#);
+
+    // Manual hooks only run when the `manual` stage is selected explicitly.
+    cmd_snapshot!(context.filters(), context.run().arg("manual-stage"), @r"
     success: false
     exit_code: 1
     ----- stdout -----
-    trailing-whitespace......................................................Failed
-    - hook id: trailing-whitespace
-    - exit code: 1
+
+    ----- stderr -----
+    error: No hooks found for stage `pre-commit` after filtering
+    Hooks in the `manual` stage only run with `--hook-stage manual`
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--hook-stage").arg("manual").arg("manual-stage"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    manual-stage.............................................................Passed
 
     ----- stderr -----
     "#);
 
-    let log = context.read("log.txt");
-    assert_eq!(log, "Fixing files");
+    // Run hooks with `post-commit` stage.
+    cmd_snapshot!(context.filters(), context.run().arg("--hook-stage").arg("post-commit"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    default-stage........................................(no files to check)Skipped
+    post-commit-stage....................................(no files to check)Skipped
+
+    ----- stderr -----
+    "#);
 }
 
-/// Pass pre-commit environment variables to the hook.
+/// Hooks only run for the stages they are confined to.
 #[test]
-fn pass_env_vars() {
+fn stage_matrix() {
     let context = TestContext::new();
-
     context.init_project();
-
-    context.write_pre_commit_config(indoc::indoc! {r#"
+    context.write_pre_commit_config(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: env-vars
-                name: Pass environment
+              - id: push-hook
+                name: push-hook
                 language: system
-                entry: python3 -c "import os, sys; print(os.getenv('PRE_COMMIT')); sys.exit(1)"
-                always_run: true
-    "#});
+                entry: echo push-hook
+                stages: [ pre-push ]
+              - id: merge-hook
+                name: merge-hook
+                language: system
+                entry: echo merge-hook
+                stages: [ pre-merge-commit ]
+              - id: commit-and-push-hook
+                name: commit-and-push-hook
+                language: system
+                entry: echo commit-and-push-hook
+                stages: [ pre-commit, pre-push ]
+    "});
+    context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run(), @r###"
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    commit-and-push-hook.....................................................Passed
+
+    ----- stderr -----
+    "#);
+
+    cmd_snapshot!(context.filters(), context.run().arg("--hook-stage").arg("pre-push"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    push-hook................................................................Passed
+    commit-and-push-hook.....................................................Passed
+
+    ----- stderr -----
+    "#);
+
+    cmd_snapshot!(context.filters(), context.run().arg("--hook-stage").arg("pre-merge-commit"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    merge-hook...............................................................Passed
+
+    ----- stderr -----
+    "#);
+
+    cmd_snapshot!(context.filters(), context.run().arg("--hook-stage").arg("post-merge"), @r#"
     success: false
     exit_code: 1
     ----- stdout -----
-    Pass environment.........................................................Failed
-    - hook id: env-vars
-    - exit code: 1
-      1
 
     ----- stderr -----
-    "###);
+    error: No hooks found for stage `post-merge` after filtering
+    "#);
 }
 
+/// Legacy stage names are mapped to their current names with a deprecation warning.
 #[test]
-fn staged_files_only() -> Result<()> {
+fn legacy_stage_names() {
     let context = TestContext::new();
     context.init_project();
-    context.write_pre_commit_config(indoc::indoc! {r#"
+    context.write_pre_commit_config(indoc::indoc! {r"
+        default_stages: [ commit ]
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: commit-hook
+                name: commit-hook
                 language: system
-                entry: python3 -c 'print(open("file.txt", "rt").read())'
-                verbose: true
-                types: [text]
-   "#});
-
-    context
-        .work_dir()
-        .child("file.txt")
-        .write_str("Hello, world!")?;
+                entry: echo commit-hook
+              - id: push-hook
+                name: push-hook
+                language: system
+                entry: echo push-hook
+                stages: [ push, merge-commit ]
+    "});
     context.git_add(".");
 
-    // Non-staged files should be stashed and restored.
-    context
-        .work_dir()
-        .child("file.txt")
-        .write_str("Hello world again!")?;
+    cmd_snapshot!(context.filters(), context.run().arg("--hook-stage").arg("pre-push"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    push-hook................................................................Passed
 
-    let filters: Vec<_> = context
-        .filters()
-        .into_iter()
-        .chain([(r"/\d+-\d+.patch", "/[TIME]-[PID].patch")])
-        .collect();
+    ----- stderr -----
+    warning: Found deprecated usages, suppress them with `suppress_warnings` in the config:
+      - Stage name `commit` is deprecated, use `pre-commit` instead (legacy-stage-name)
+      - Stage name `push` is deprecated, use `pre-push` instead (legacy-stage-name)
+      - Stage name `merge-commit` is deprecated, use `pre-merge-commit` instead (legacy-stage-name)
+    "#);
 
-    cmd_snapshot!(filters, context.run(), @r#"
+    cmd_snapshot!(context.filters(), context.run(), @r#"
     success: true
     exit_code: 0
     ----- stdout -----
-    trailing-whitespace......................................................Passed
-    - hook id: trailing-whitespace
-    - duration: [TIME]
-      Hello, world!
+    commit-hook..............................................................Passed
 
     ----- stderr -----
-    Non-staged changes detected, saving to `[HOME]/patches/[TIME]-[PID].patch`
+    warning: Found deprecated usages, suppress them with `suppress_warnings` in the config:
+      - Stage name `commit` is deprecated, use `pre-commit` instead (legacy-stage-name)
+      - Stage name `push` is deprecated, use `pre-push` instead (legacy-stage-name)
+      - Stage name `merge-commit` is deprecated, use `pre-merge-commit` instead (legacy-stage-name)
+    "#);
 
-    Restored working tree changes from `[HOME]/patches/[TIME]-[PID].patch`
+    cmd_snapshot!(context.filters(), context.run().arg("--strict-warnings"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    commit-hook..............................................................Passed
+
+    ----- stderr -----
+    warning: Found deprecated usages, suppress them with `suppress_warnings` in the config:
+      - Stage name `commit` is deprecated, use `pre-commit` instead (legacy-stage-name)
+      - Stage name `push` is deprecated, use `pre-push` instead (legacy-stage-name)
+      - Stage name `merge-commit` is deprecated, use `pre-merge-commit` instead (legacy-stage-name)
+    error: Found deprecated usages with `--strict-warnings`
     "#);
 
-    let content = context.read("file.txt");
-    assert_snapshot!(content, @"Hello world again!");
+    // Suppressed deprecations are neither printed nor fail the run.
+    let config = context.read(".pre-commit-config.yaml");
+    context.write_pre_commit_config(&format!("suppress_warnings: [legacy-stage-name]\n{config}"));
+    context.git_add(".");
 
-    Ok(())
+    cmd_snapshot!(context.filters(), context.run().arg("--strict-warnings"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    commit-hook..............................................................Passed
+
+    ----- stderr -----
+    "#);
 }
 
-#[cfg(unix)]
+/// Hooks without `stages` default to the top-level `default_stages`.
 #[test]
-fn restore_on_interrupt() -> Result<()> {
+fn default_stages() {
     let context = TestContext::new();
     context.init_project();
-    // The hook will sleep for 3 seconds.
-    context.write_pre_commit_config(indoc::indoc! {r#"
+    context.write_pre_commit_config(indoc::indoc! {r"
+        default_stages: [ pre-push ]
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: slow-hook
+                name: slow-hook
                 language: system
-                entry: python3 -c 'import time; open("out.txt", "wt").write(open("file.txt", "rt").read()); time.sleep(10)'
-                verbose: true
-                types: [text]
-   "#});
+                entry: echo slow-hook
+              - id: fast-hook
+                name: fast-hook
+                language: system
+                entry: echo fast-hook
+                stages: [ pre-commit ]
+    "});
+    context.git_add(".");
 
-    context
-        .work_dir()
-        .child("file.txt")
-        .write_str("Hello, world!")?;
-    context.git_add(".");
-
-    // Non-staged files should be stashed and restored.
-    context
-        .work_dir()
-        .child("file.txt")
-        .write_str("Hello world again!")?;
-
-    let mut child = context.run().spawn()?;
-    let child_id = child.id();
-
-    // Send an interrupt signal to the process.
-    let handle = std::thread::spawn(move || {
-        std::thread::sleep(std::time::Duration::from_secs(1));
-        #[allow(clippy::cast_possible_wrap)]
-        unsafe {
-            libc::kill(child_id as i32, libc::SIGINT)
-        };
-    });
-
-    handle.join().unwrap();
-    child.wait()?;
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    fast-hook................................................................Passed
 
-    let content = context.read("out.txt");
-    assert_snapshot!(content, @"Hello, world!");
+    ----- stderr -----
+    "#);
 
-    let content = context.read("file.txt");
-    assert_snapshot!(content, @"Hello world again!");
+    cmd_snapshot!(context.filters(), context.run().arg("--hook-stage").arg("pre-push"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    slow-hook................................................................Passed
 
-    Ok(())
+    ----- stderr -----
+    "#);
 }
 
-/// When in merge conflict, runs on files that have conflicts fixed.
+/// Test global `files`, `exclude`, and hook level `files`, `exclude`.
 #[test]
-fn merge_conflicts() -> Result<()> {
+fn files_and_exclude() -> Result<()> {
     let context = TestContext::new();
+
     context.init_project();
 
-    // Create a merge conflict.
     let cwd = context.work_dir();
-    cwd.child("file.txt").write_str("Hello, world!")?;
-    context.git_add(".");
-    context.configure_git_author();
-    context.git_commit("Initial commit");
-
-    Command::new("git")
-        .arg("checkout")
-        .arg("-b")
-        .arg("feature")
-        .current_dir(cwd)
-        .assert()
-        .success();
-    cwd.child("file.txt").write_str("Hello, world again!")?;
-    context.git_add(".");
-    context.git_commit("Feature commit");
-
-    Command::new("git")
-        .arg("checkout")
-        .arg("master")
-        .current_dir(cwd)
-        .assert()
-        .success();
-    cwd.child("file.txt")
-        .write_str("Hello, world from master!")?;
-    context.git_add(".");
-    context.git_commit("Master commit");
-
-    Command::new("git")
-        .arg("merge")
-        .arg("feature")
-        .current_dir(cwd)
-        .assert()
-        .code(1);
+    cwd.child("file.txt").write_str("Hello, world!  \n")?;
+    cwd.child("valid.json").write_str("{}\n  ")?;
+    cwd.child("invalid.json").write_str("{}")?;
+    cwd.child("main.py").write_str(r#"print "abc"  "#)?;
 
+    // Global files and exclude.
     context.write_pre_commit_config(indoc::indoc! {r"
+        files: file.txt
         repos:
           - repo: local
             hooks:
               - id: trailing-whitespace
-                name: trailing-whitespace
+                name: trailing whitespace
                 language: system
-                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
-                verbose: true
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                types: [text]
+              - id: end-of-file-fixer
+                name: fix end of files
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                types: [text]
+              - id: check-json
+                name: check json
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                types: [json]
     "});
+    context.git_add(".");
 
-    // Abort on merge conflicts.
     cmd_snapshot!(context.filters(), context.run(), @r#"
     success: false
-    exit_code: 2
+    exit_code: 1
     ----- stdout -----
+    trailing whitespace......................................................Failed
+    - hook id: trailing-whitespace
+    - exit code: 1
+      ['file.txt']
+    fix end of files.........................................................Failed
+    - hook id: end-of-file-fixer
+    - exit code: 1
+      ['file.txt']
+    check json...........................................(no files to check)Skipped
 
     ----- stderr -----
-    error: You have unmerged paths. Resolve them before running prek
     "#);
 
-    // Fix the conflict and run again.
+    // Override hook level files and exclude.
+    context.write_pre_commit_config(indoc::indoc! {r"
+        files: file.txt
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing whitespace
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                files: valid.json
+              - id: end-of-file-fixer
+                name: fix end of files
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                exclude: (valid.json|main.py)
+              - id: check-json
+                name: check json
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+    "});
     context.git_add(".");
+
     cmd_snapshot!(context.filters(), context.run(), @r#"
-    success: true
-    exit_code: 0
+    success: false
+    exit_code: 1
     ----- stdout -----
-    trailing-whitespace......................................................Passed
-    - hook id: trailing-whitespace
-    - duration: [TIME]
-      ['.pre-commit-config.yaml', 'file.txt']
+    trailing whitespace..................................(no files to check)Skipped
+    fix end of files.........................................................Failed
+    - hook id: end-of-file-fixer
+    - exit code: 1
+      ['file.txt']
+    check json...............................................................Failed
+    - hook id: check-json
+    - exit code: 1
+      ['file.txt']
 
     ----- stderr -----
     "#);
@@ -1071,243 +1292,301 @@ fn merge_conflicts() -> Result<()> {
     Ok(())
 }
 
-/// Local python hook with no additional dependencies.
+/// Global `exclude` is intersected with hook level `files`.
 #[test]
-fn local_python_hook() {
+fn global_exclude() -> Result<()> {
     let context = TestContext::new();
+
     context.init_project();
 
-    context.write_pre_commit_config(indoc::indoc! {r#"
+    let cwd = context.work_dir();
+    cwd.child("main.py").write_str("print('hello')\n")?;
+    cwd.child("api_pb2.py").write_str("# Generated code\n")?;
+    cwd.child("vendor/lib.py")
+        .write_str("print('vendored')\n")?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        exclude: ^(vendor/|.*_pb2\.py$)
         repos:
           - repo: local
             hooks:
-              - id: local-python-hook
-                name: local-python-hook
-                language: python
-                entry: python3 -c 'import sys; print("Hello, world!"); sys.exit(1)'
-    "#});
-
+              - id: python-files
+                name: python files
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                files: \.py$
+    "});
     context.git_add(".");
 
     cmd_snapshot!(context.filters(), context.run(), @r#"
     success: false
     exit_code: 1
     ----- stdout -----
-    local-python-hook........................................................Failed
-    - hook id: local-python-hook
+    python files.............................................................Failed
+    - hook id: python-files
     - exit code: 1
-      Hello, world!
+      ['main.py']
 
     ----- stderr -----
     "#);
+
+    Ok(())
 }
 
-/// Invalid `entry`
+/// Files matching `.prekignore` patterns are removed from every hook's files.
 #[test]
-fn invalid_entry() {
+fn prekignore() -> Result<()> {
     let context = TestContext::new();
+
     context.init_project();
 
-    context.write_pre_commit_config(indoc::indoc! {r#"
+    let cwd = context.work_dir();
+    cwd.child("main.py").write_str("print('hello')\n")?;
+    cwd.child("api_pb2.py").write_str("# Generated code\n")?;
+    cwd.child("docs/conf.py").write_str("project = 'prek'\n")?;
+    cwd.child("scripts/new.py").write_str("print('new')\n")?;
+    cwd.child("scripts/legacy.py")
+        .write_str("print('legacy')\n")?;
+    cwd.child(".prekignore")
+        .write_str("# Generated code\n*_pb2.py\n/docs/\n")?;
+    cwd.child("scripts/.prekignore").write_str("legacy.py\n")?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: entry
-                name: entry
-                language: python
-                entry: '"'
-    "#});
-
+              - id: python-files
+                name: python files
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                files: \.py$
+    "});
     context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run(), @r#"
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r#"
     success: false
-    exit_code: 2
+    exit_code: 1
     ----- stdout -----
-    entry....................................................................
+    python files.............................................................Failed
+    - hook id: python-files
+    - exit code: 1
+      ['scripts/new.py', 'main.py']
+
     ----- stderr -----
-    error: Failed to run hook `entry`
-      caused by: Hook `entry` is invalid
-      caused by: Failed to parse entry `"` as commands
     "#);
+
+    Ok(())
 }
 
-/// Initialize a repo that does not exist.
+/// Files larger than `max_file_size` and binary files with `skip_binary_files` are skipped.
+/// Hooks whose files were all skipped are reported as skipped, with the skipped files.
 #[test]
-fn init_nonexistent_repo() {
+fn max_file_size_and_binary_files() -> Result<()> {
     let context = TestContext::new();
+
     context.init_project();
+
+    let cwd = context.work_dir();
+    cwd.child("small.txt").write_str("Hello, world!\n")?;
+    cwd.child("large.txt").write_str(&"x".repeat(2048))?;
+    cwd.child("image.bin").write_binary(&[0, 159, 146, 150])?;
+
     context.write_pre_commit_config(indoc::indoc! {r"
+        max_file_size: 1KB
         repos:
-          - repo: https://notexistentatallnevergonnahappen.com/nonexistent/repo
-            rev: v1.0.0
+          - repo: local
             hooks:
-              - id: nonexistent
-                name: nonexistent
-        "});
+              - id: all-files
+                name: all files
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:])); exit(1)'
+                exclude: ^\.pre-commit-config\.yaml$
+              - id: large-files
+                name: large files
+                language: system
+                entry: echo
+                files: ^large
+    "});
     context.git_add(".");
 
-    let filters = context
-        .filters()
-        .into_iter()
-        .chain([(r"exit code: ", "exit status: "),
-            // Normalize Git error message to handle environment-specific variations
-            (
-                r"fatal: unable to access 'https://notexistentatallnevergonnahappen\.com/nonexistent/repo/':.*",
-                r"fatal: unable to access 'https://notexistentatallnevergonnahappen.com/nonexistent/repo/': [error]"
-            ),
-        ])
-        .collect::<Vec<_>>();
-
-    cmd_snapshot!(filters, context.run(), @r"
+    cmd_snapshot!(context.filters(), context.run()
+        .arg("--all-files")
+        .arg("--output").arg("json:report.json"), @r#"
     success: false
-    exit_code: 2
+    exit_code: 1
     ----- stdout -----
+    all files................................................................Failed
+    - hook id: all-files
+    - exit code: 1
+      ['image.bin', 'small.txt']
+    large files..............................................(files skipped)Skipped
 
     ----- stderr -----
-    error: Failed to initialize repo `https://notexistentatallnevergonnahappen.com/nonexistent/repo`
-      caused by: command `git full clone` exited with an error:
+    warning: Skipped files larger than 1KB: `large.txt`
+    "#);
 
-    [status]
-    exit status: 128
+    let report: serde_json::Value = serde_json::from_str(&context.read("report.json"))?;
+    assert_eq!(
+        report["hooks"][0]["skipped_files"],
+        serde_json::json!(["large.txt"])
+    );
+    assert_eq!(report["hooks"][1]["status"], "skipped");
+    assert_eq!(
+        report["hooks"][1]["skipped_files"],
+        serde_json::json!(["large.txt"])
+    );
+
+    cmd_snapshot!(context.filters(), context.run()
+        .arg("--all-files")
+        .arg("--max-file-size").arg("4KB")
+        .arg("--skip-binary-files"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    all files................................................................Failed
+    - hook id: all-files
+    - exit code: 1
+      ['large.txt', 'small.txt']
+    large files..............................................................Passed
 
-    [stderr]
-    fatal: unable to access 'https://notexistentatallnevergonnahappen.com/nonexistent/repo/': [error]
-    ");
+    ----- stderr -----
+    warning: Skipped binary files: `image.bin`
+    "#);
+
+    Ok(())
 }
 
-/// Test hooks that specifies `types: [directory]`.
+/// `--only-files-matching` and `--exclude-files-matching` intersect with hook level `files`.
 #[test]
-fn types_directory() -> Result<()> {
+fn only_files_matching() -> Result<()> {
     let context = TestContext::new();
+
     context.init_project();
-    context.write_pre_commit_config(indoc::indoc! {r"
+
+    let cwd = context.work_dir();
+    cwd.child("file.txt").write_str("Hello, world!\n")?;
+    cwd.child("valid.json").write_str("{}\n")?;
+    cwd.child("invalid.json").write_str("{")?;
+    cwd.child("main.py").write_str("print('abc')\n")?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: directory
-                name: directory
+              - id: check-json
+                name: check json
                 language: system
-                entry: echo
-                types: [directory]
-        "});
-    context.work_dir().child("dir").create_dir_all()?;
-    context
-        .work_dir()
-        .child("dir/file.txt")
-        .write_str("Hello, world!")?;
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                files: \.json$
+              - id: all-files
+                name: all files
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+              - id: python-files
+                name: python files
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                files: \.py$
+    "});
     context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run(), @r#"
-    success: true
-    exit_code: 0
-    ----- stdout -----
-    directory............................................(no files to check)Skipped
-
-    ----- stderr -----
-    "#);
-
-    cmd_snapshot!(context.filters(), context.run().arg("--files").arg("dir"), @r#"
-    success: true
-    exit_code: 0
+    cmd_snapshot!(context.filters(), context.run()
+        .arg("--all-files")
+        .arg("--only-files-matching").arg(r"\.(json|txt)$")
+        .arg("--exclude-files-matching").arg("^invalid"), @r#"
+    success: false
+    exit_code: 1
     ----- stdout -----
-    directory................................................................Passed
+    check json...............................................................Failed
+    - hook id: check-json
+    - exit code: 1
+      ['valid.json']
+    all files................................................................Failed
+    - hook id: all-files
+    - exit code: 1
+      ['valid.json', 'file.txt']
+    python files.........................................(no files to check)Skipped
 
     ----- stderr -----
     "#);
 
-    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r#"
-    success: true
-    exit_code: 0
+    cmd_snapshot!(context.filters(), context.run().arg("--only-files-matching").arg("("), @r"
+    success: false
+    exit_code: 2
     ----- stdout -----
-    directory............................................(no files to check)Skipped
 
     ----- stderr -----
-    "#);
+    error: invalid value '(' for '--only-files-matching <REGEX>': Parsing error at position 1: Opening parenthesis without closing parenthesis
 
-    cmd_snapshot!(context.filters(), context.run().arg("--files").arg("non-exist-files"), @r#"
-    success: true
-    exit_code: 0
-    ----- stdout -----
-    directory............................................(no files to check)Skipped
+    For more information, try '--help'.
+    ");
 
-    ----- stderr -----
-    warning: This file does not exist, it will be ignored: `non-exist-files`
-    "#);
     Ok(())
 }
 
+/// Test selecting files by type, `types`, `types_or`, and `exclude_types`.
 #[test]
-fn run_last_commit() -> Result<()> {
+fn file_types() -> Result<()> {
     let context = TestContext::new();
+
     context.init_project();
-    context.configure_git_author();
 
     let cwd = context.work_dir();
-    context.write_pre_commit_config(indoc::indoc! {r"
+    cwd.child("file.txt").write_str("Hello, world!  ")?;
+    cwd.child("json.json").write_str("{}\n  ")?;
+    cwd.child("main.py").write_str(r#"print "abc"  "#)?;
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
         repos:
-          - repo: https://github.com/pre-commit/pre-commit-hooks
-            rev: v5.0.0
+          - repo: local
             hooks:
               - id: trailing-whitespace
-              - id: end-of-file-fixer
-    "});
-
-    // Create initial files and make first commit
-    cwd.child("file1.txt").write_str("Hello, world!\n")?;
-    cwd.child("file2.txt")
-        .write_str("Initial content with trailing spaces   \n")?; // This has issues but won't be in last commit
-    context.git_add(".");
-    context.git_commit("Initial commit");
-
-    // Modify files and make second commit with trailing whitespace
-    cwd.child("file1.txt").write_str("Hello, world!   \n")?; // trailing whitespace
-    cwd.child("file3.txt").write_str("New file")?; // missing newline
-    // Note: file2.txt is NOT modified in this commit, so it should be filtered out by --last-commit
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                types: ["json"]
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                types_or: ["json", "python"]
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                exclude_types: ["json"]
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                types: ["json" ]
+                exclude_types: ["json"]
+    "#});
     context.git_add(".");
-    context.git_commit("Second commit with issues");
 
-    // Run with --last-commit should only check files from the last commit
-    // This should only process file1.txt and file3.txt, NOT file2.txt
-    cmd_snapshot!(context.filters(), context.run().arg("--last-commit"), @r#"
+    cmd_snapshot!(context.filters(), context.run(), @r#"
     success: false
     exit_code: 1
     ----- stdout -----
-    trim trailing whitespace.................................................Failed
+    trailing-whitespace......................................................Failed
     - hook id: trailing-whitespace
     - exit code: 1
-    - files were modified by this hook
-      Fixing file1.txt
-    fix end of files.........................................................Failed
-    - hook id: end-of-file-fixer
-    - exit code: 1
-    - files were modified by this hook
-      Fixing file3.txt
-
-    ----- stderr -----
-    "#);
-
-    // Now reset the files to their problematic state for comparison
-    cwd.child("file1.txt").write_str("Hello, world!   \n")?; // trailing whitespace
-    cwd.child("file3.txt").write_str("New file")?; // missing newline
-
-    // Run with --all-files should check ALL files including file2.txt
-    // This demonstrates that file2.txt was indeed filtered out in the previous test
-    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r#"
-    success: false
-    exit_code: 1
-    ----- stdout -----
-    trim trailing whitespace.................................................Failed
+      ['json.json']
+    trailing-whitespace......................................................Failed
     - hook id: trailing-whitespace
     - exit code: 1
-    - files were modified by this hook
-      Fixing file1.txt
-      Fixing file2.txt
-    fix end of files.........................................................Failed
-    - hook id: end-of-file-fixer
+      ['main.py', 'json.json']
+    trailing-whitespace......................................................Failed
+    - hook id: trailing-whitespace
     - exit code: 1
-    - files were modified by this hook
-      Fixing file3.txt
+      ['file.txt', '.pre-commit-config.yaml', 'main.py']
+    trailing-whitespace..................................(no files to check)Skipped
 
     ----- stderr -----
     "#);
@@ -1315,561 +1594,4929 @@ fn run_last_commit() -> Result<()> {
     Ok(())
 }
 
-/// Test `prek run --files` with multiple files.
+/// Identify files without an extension by the interpreter in their shebang.
+#[cfg(unix)]
 #[test]
-fn run_multiple_files() -> Result<()> {
+fn file_types_from_shebang() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
     let context = TestContext::new();
+
     context.init_project();
+
+    let cwd = context.work_dir();
+    cwd.child("script")
+        .write_str("#!/usr/bin/env python3\nprint('hello')\n")?;
+    fs_err::set_permissions(cwd.child("script"), std::fs::Permissions::from_mode(0o755))?;
+    // Shebangs of non-executable files are not considered.
+    cwd.child("notes")
+        .write_str("#!/usr/bin/env python3\nprint('hello')\n")?;
+
     context.write_pre_commit_config(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: multiple-files
-                name: multiple-files
+              - id: python-files
+                name: python-files
                 language: system
-                entry: echo
-                verbose: true
-                types: [text]
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                types: [python]
     "});
-    let cwd = context.work_dir();
-    cwd.child("file1.txt").write_str("Hello, world!")?;
-    cwd.child("file2.txt").write_str("Hello, world!")?;
     context.git_add(".");
-    // `--files` with multiple files
-    cmd_snapshot!(context.filters(), context.run().arg("--files").arg("file1.txt").arg("file2.txt"), @r#"
-    success: true
-    exit_code: 0
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 1
     ----- stdout -----
-    multiple-files...........................................................Passed
-    - hook id: multiple-files
-    - duration: [TIME]
-      file2.txt file1.txt
+    python-files.............................................................Failed
+    - hook id: python-files
+    - exit code: 1
+      ['script']
 
     ----- stderr -----
     "#);
+
     Ok(())
 }
 
-/// Test `prek run --files` with no files.
+/// Abort the run if a hook fails.
 #[test]
-fn run_no_files() {
+fn fail_fast() {
     let context = TestContext::new();
     context.init_project();
-    context.write_pre_commit_config(indoc::indoc! {r"
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
         repos:
           - repo: local
             hooks:
-              - id: no-files
-                name: no-files
+              - id: trailing-whitespace
+                name: trailing-whitespace
                 language: system
-                entry: echo
-                verbose: true
-    "});
+                entry: python3 -c 'print("Fixing files"); exit(1)'
+                always_run: true
+                fail_fast: false
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'print("Fixing files"); exit(1)'
+                always_run: true
+                fail_fast: true
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -V
+                always_run: true
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -V
+                always_run: true
+    "#});
     context.git_add(".");
-    // `--files` with no files
-    cmd_snapshot!(context.filters(), context.run().arg("--files"), @r#"
-    success: true
-    exit_code: 0
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 1
     ----- stdout -----
-    no-files.................................................................Passed
-    - hook id: no-files
-    - duration: [TIME]
-      .pre-commit-config.yaml
+    trailing-whitespace......................................................Failed
+    - hook id: trailing-whitespace
+    - exit code: 1
+      Fixing files
+    trailing-whitespace......................................................Failed
+    - hook id: trailing-whitespace
+    - exit code: 1
+      Fixing files
 
     ----- stderr -----
     "#);
 }
 
-/// Test `prek run --directory` flags.
+/// Top-level `fail_fast` stops the run after the first failing hook.
 #[test]
-fn run_directory() -> Result<()> {
+fn global_fail_fast() {
     let context = TestContext::new();
     context.init_project();
-    context.write_pre_commit_config(indoc::indoc! {r"
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        fail_fast: true
         repos:
           - repo: local
             hooks:
-              - id: directory
-                name: directory
+              - id: format
+                name: format
                 language: system
-                entry: echo
-                verbose: true
-    "});
+                entry: python3 -c 'print("Badly formatted"); exit(1)'
+                always_run: true
+              - id: lint
+                name: lint
+                language: system
+                entry: python3 -c 'print("Lint errors"); exit(1)'
+                always_run: true
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    format...................................................................Failed
+    - hook id: format
+    - exit code: 1
+      Badly formatted
+
+    ----- stderr -----
+    "#);
+}
+
+/// Run from a subdirectory. File arguments should be fixed to be relative to the root.
+#[test]
+fn subdirectory() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.work_dir();
+    let child = cwd.child("foo/bar/baz");
+    child.create_dir_all()?;
+    child.child("file.txt").write_str("Hello, world!\n")?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1]); exit(1)'
+                always_run: true
+    "});
+
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().current_dir(&child).arg("--files").arg("file.txt"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    trailing-whitespace......................................................Failed
+    - hook id: trailing-whitespace
+    - exit code: 1
+      foo/bar/baz/file.txt
+
+    ----- stderr -----
+    "#);
+
+    cmd_snapshot!(context.filters(), context.run().arg("--cd").arg(&*child).arg("--files").arg("file.txt"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    trailing-whitespace......................................................Failed
+    - hook id: trailing-whitespace
+    - exit code: 1
+      foo/bar/baz/file.txt
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// Test hook `log_file` option.
+#[test]
+fn log_file() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'print("Fixing files"); exit(1)'
+                always_run: true
+                log_file: log.txt
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    trailing-whitespace......................................................Failed
+    - hook id: trailing-whitespace
+    - exit code: 1
+      Fixing files
+
+    ----- stderr -----
+    "#);
+
+    let log = context.read("log.txt");
+    assert_eq!(log, "Fixing files\n");
+
+    // Output of later runs is appended.
+    context.run().assert().failure();
+    let log = context.read("log.txt");
+    assert_eq!(log, "Fixing files\nFixing files\n");
+}
+
+/// Failures of hooks with `severity: warning` don't fail the run, unless `--strict` is given.
+#[test]
+fn severity_warning() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: new-linter
+                name: new linter
+                language: fail
+                entry: found 3 problems
+                always_run: true
+                severity: warning
+              - id: success
+                name: success
+                language: system
+                entry: echo
+                always_run: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    new linter...............................................................Warning
+    - hook id: new-linter
+    - exit code: 1
+      found 3 problems
+
+      .pre-commit-config.yaml
+    success..................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--strict"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    new linter...............................................................Failed
+    - hook id: new-linter
+    - exit code: 1
+      found 3 problems
+
+      .pre-commit-config.yaml
+    success..................................................................Passed
+
+    ----- stderr -----
+    ");
+}
+
+/// `--require-pinned-revs` and `require_pinned_revs` refuse repos whose `rev` can be moved.
+#[test]
+fn require_pinned_revs() {
+    let context = TestContext::new();
+    context.init_project();
+
+    let config = indoc::indoc! {r"
+        repos:
+          - repo: https://github.com/pre-commit/pre-commit-hooks
+            rev: v5.0.0
+            hooks:
+              - id: check-added-large-files
+          - repo: https://github.com/pre-commit/pre-commit-hooks
+            rev: cef0300fd0fc4d2a87a85fa2093c6b283ea36f4b
+            hooks:
+              - id: end-of-file-fixer
+          - repo: local
+            hooks:
+              - id: local
+                name: local
+                language: system
+                entry: echo
+    "};
+    context.write_pre_commit_config(config);
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--require-pinned-revs"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: The `rev` of these repos is not a full commit SHA (`require_pinned_revs`):
+      - https://github.com/pre-commit/pre-commit-hooks@v5.0.0
+    Pin them with `prek auto-update --freeze`
+    ");
+
+    // Only the hooks that run are checked.
+    cmd_snapshot!(context.filters(), context.run().arg("--require-pinned-revs").arg("end-of-file-fixer").arg("local"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    fix end of files.........................................................Passed
+    local....................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    context.write_pre_commit_config(&format!("require_pinned_revs: true\n{config}"));
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: The `rev` of these repos is not a full commit SHA (`require_pinned_revs`):
+      - https://github.com/pre-commit/pre-commit-hooks@v5.0.0
+    Pin them with `prek auto-update --freeze`
+    ");
+}
+
+/// Print `on_fail_message` after the output of a failed hook.
+#[test]
+fn on_fail_message() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: fmt
+                name: fmt
+                language: system
+                entry: python3 -c 'print("Badly formatted"); exit(1)'
+                always_run: true
+                on_fail_message: Run `make fmt` to fix locally.
+              - id: lint
+                name: lint
+                language: system
+                entry: python3 -c 'exit(0)'
+                always_run: true
+                on_fail_message: Never printed.
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fmt......................................................................Failed
+    - hook id: fmt
+    - exit code: 1
+      Badly formatted
+    Run `make fmt` to fix locally.
+    lint.....................................................................Passed
+
+    ----- stderr -----
+    "#);
+}
+
+/// Pass pre-commit environment variables to the hook.
+#[test]
+fn pass_env_vars() {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: env-vars
+                name: Pass environment
+                language: system
+                entry: python3 -c "import os, sys; print(os.getenv('PRE_COMMIT')); sys.exit(1)"
+                always_run: true
+    "#});
+
+    cmd_snapshot!(context.filters(), context.run(), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Pass environment.........................................................Failed
+    - hook id: env-vars
+    - exit code: 1
+      1
+
+    ----- stderr -----
+    "###);
+}
+
+/// Hooks only see the curated variables of prek, and the ones allowed with `pass_env`.
+#[test]
+fn pass_env_allowlist() {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        pass_env: [DEPLOY_TARGET]
+        repos:
+          - repo: local
+            hooks:
+              - id: env-vars
+                name: Pass environment
+                language: system
+                entry: python3 -c "import os, sys; print(os.getenv('SECRET_TOKEN'), os.getenv('AWS_REGION'), os.getenv('DEPLOY_TARGET')); sys.exit(1)"
+                always_run: true
+                pass_env: [AWS_*]
+    "#});
+
+    cmd_snapshot!(context.filters(), context.run().env("SECRET_TOKEN", "secret").env("AWS_REGION", "eu-west-1").env("DEPLOY_TARGET", "staging"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Pass environment.........................................................Failed
+    - hook id: env-vars
+    - exit code: 1
+      None eu-west-1 staging
+
+    ----- stderr -----
+    ");
+}
+
+/// `extra_env_keep` is an alias of `pass_env`, and the variables of toolchains are passed by
+/// default.
+#[test]
+fn extra_env_keep() {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        extra_env_keep: [DEPLOY_TARGET]
+        repos:
+          - repo: local
+            hooks:
+              - id: env-vars
+                name: Pass environment
+                language: system
+                entry: python3 -c "import os, sys; print(os.getenv('SECRET_TOKEN'), os.getenv('AWS_REGION'), os.getenv('DEPLOY_TARGET'), os.getenv('CARGO_HOME')); sys.exit(1)"
+                always_run: true
+                extra_env_keep: [AWS_REGION]
+    "#});
+
+    cmd_snapshot!(context.filters(), context.run().env("SECRET_TOKEN", "secret").env("AWS_REGION", "eu-west-1").env("DEPLOY_TARGET", "staging").env("CARGO_HOME", "cargo"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Pass environment.........................................................Failed
+    - hook id: env-vars
+    - exit code: 1
+      None eu-west-1 staging cargo
+
+    ----- stderr -----
+    ");
+}
+
+/// The `env` of a hook is set for its processes, with `${VAR}` replaced by the variables of prek.
+#[test]
+fn hook_env() {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: env
+                name: env
+                language: system
+                entry: python3 -c "import os, sys; print(os.getenv('LINT_MODE'), os.getenv('LINT_TARGET')); sys.exit(1)"
+                always_run: true
+                env:
+                  LINT_MODE: strict
+                  LINT_TARGET: ${DEPLOY_TARGET}-build
+    "#});
+
+    cmd_snapshot!(context.filters(), context.run().env("DEPLOY_TARGET", "staging"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    env......................................................................Failed
+    - hook id: env
+    - exit code: 1
+      strict staging-build
+
+    ----- stderr -----
+    ");
+}
+
+/// Placeholders in `entry` and `args` are expanded when the hook runs, unknown ones are kept.
+#[test]
+fn entry_placeholders() {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: placeholders
+                name: placeholders
+                language: system
+                entry: python3 -c "import sys; print(sys.argv[1:]); sys.exit(1)" {config_root}
+                args: ['--jobs={nproc}', '{git_root}/build', '{unknown}']
+                pass_filenames: false
+                always_run: true
+    "#});
+
+    cmd_snapshot!(context.filters(), context.run().env("PREK_NO_CONCURRENCY", "1"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    placeholders.............................................................Failed
+    - hook id: placeholders
+    - exit code: 1
+      ['[TEMP_DIR]/', '--jobs=1', '[TEMP_DIR]/build', '{unknown}']
+
+    ----- stderr -----
+    ");
+
+    // `--jobs` takes precedence over `PREK_NO_CONCURRENCY`.
+    cmd_snapshot!(context.filters(), context.run().env("PREK_NO_CONCURRENCY", "1").arg("--jobs").arg("3"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    placeholders.............................................................Failed
+    - hook id: placeholders
+    - exit code: 1
+      ['[TEMP_DIR]/', '--jobs=3', '[TEMP_DIR]/build', '{unknown}']
+
+    ----- stderr -----
+    ");
+}
+
+/// With `pass_file_contents: stdin`, hooks read the content of each file in the index on stdin.
+#[test]
+fn pass_file_contents_stdin() -> Result<()> {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: stdin
+                name: stdin
+                language: system
+                entry: python3 -c "import sys; print(repr(sys.stdin.read()), sys.argv[1:]); sys.exit(1)"
+                files: ^file\.txt$
+                pass_file_contents: stdin
+    "#});
+
+    let cwd = context.work_dir();
+    cwd.child("file.txt").write_str("staged\n")?;
+    context.git_add(".");
+    cwd.child("file.txt").write_str("unstaged\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    stdin....................................................................Failed
+    - hook id: stdin
+    - exit code: 1
+      'staged/n' ['file.txt']
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// With `changed_lines`, hooks get the line ranges changed in each staged file.
+#[test]
+fn changed_lines() -> Result<()> {
+    let context = TestContext::new();
+
+    context.init_project();
+    context.configure_git_author();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: changed-lines
+                name: changed-lines
+                language: system
+                entry: python3 -c "import os, sys; print(os.environ['PREK_CHANGED_LINES']); sys.exit(1)"
+                pass_filenames: false
+                changed_lines: true
+    "#});
+
+    let cwd = context.work_dir();
+    cwd.child("file.txt").write_str("a\nb\nc\nd\ne\n")?;
+    cwd.child("other.txt").write_str("unchanged\n")?;
+    context.git_add(".");
+    context.git_commit("Initial commit");
+
+    cwd.child("file.txt").write_str("a\nB\nc\nD\nE\nf\n")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    changed-lines............................................................Failed
+    - hook id: changed-lines
+    - exit code: 1
+      {"file.txt":[[2,2],[4,6]]}
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// Hooks run in their `working_directory`, with filenames relative to it.
+#[cfg(unix)]
+#[test]
+fn working_directory() -> Result<()> {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: lint
+                name: lint
+                language: system
+                entry: python3 -c "import os, sys; print(os.path.basename(os.getcwd()), sorted(sys.argv[1:])); sys.exit(1)"
+                files: \.txt$
+                working_directory: web
+    "#});
+
+    context.work_dir().child("web/app.txt").write_str("app")?;
+    context
+        .work_dir()
+        .child("docs/readme.txt")
+        .write_str("readme")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    lint.....................................................................Failed
+    - hook id: lint
+    - exit code: 1
+      web ['../docs/readme.txt', 'app.txt']
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Hooks run after the hooks they depend on, and are skipped if one of them fails.
+#[test]
+fn depends_on() {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: format
+                name: format
+                language: system
+                entry: python3 -c "print('format')"
+                always_run: true
+                depends_on: [sort]
+              - id: test
+                name: test
+                language: system
+                entry: python3 -c "print('test')"
+                always_run: true
+                depends_on: [build]
+              - id: sort
+                name: sort
+                language: system
+                entry: python3 -c "print('sort')"
+                always_run: true
+              - id: build
+                name: build
+                language: system
+                entry: python3 -c "import sys; sys.exit(1)"
+                always_run: true
+    "#});
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    sort.....................................................................Passed
+    format...................................................................Passed
+    build....................................................................Failed
+    - hook id: build
+    - exit code: 1
+    test.................................................(dependency failed)Skipped
+
+    ----- stderr -----
+    ");
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: a
+                name: a
+                language: system
+                entry: python3 -c "print('a')"
+                depends_on: [b]
+              - id: b
+                name: b
+                language: system
+                entry: python3 -c "print('b')"
+                depends_on: [a]
+    "#});
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Hook `a` is invalid
+      caused by: `depends_on` has a cycle: a -> b -> a
+    "#);
+}
+
+/// `--group` selects the hooks of a group.
+#[test]
+fn run_group() {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: sort
+                name: sort
+                language: system
+                entry: python3 -c "print('sort')"
+                always_run: true
+                group: format
+              - id: lint
+                name: lint
+                language: system
+                entry: python3 -c "print('lint')"
+                always_run: true
+                group: lint
+              - id: fmt
+                name: fmt
+                language: system
+                entry: python3 -c "print('fmt')"
+                always_run: true
+                group: format
+    "#});
+
+    cmd_snapshot!(context.filters(), context.run().arg("--group").arg("format"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    sort.....................................................................Passed
+    fmt......................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--group").arg("security"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: selector `--group=security` did not match any hooks
+    error: No hooks found after filtering with the given selectors
+    Available hooks: sort, lint, fmt
+    ");
+}
+
+/// `--tag` selects the hooks with all the tags joined by `+`, and any of the `--tag` flags.
+#[test]
+fn run_tags() {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: audit
+                name: audit
+                language: system
+                entry: python3 -c "print('audit')"
+                always_run: true
+                tags: [security, fast]
+              - id: scan
+                name: scan
+                language: system
+                entry: python3 -c "print('scan')"
+                always_run: true
+                tags: [security]
+              - id: style
+                name: style
+                language: system
+                entry: python3 -c "print('style')"
+                always_run: true
+                tags: [style, fast]
+    "#});
+
+    cmd_snapshot!(context.filters(), context.run().arg("--tag").arg("security"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    audit....................................................................Passed
+    scan.....................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--tag").arg("security+fast").arg("--tag").arg("style"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    audit....................................................................Passed
+    style....................................................................Passed
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn staged_files_only() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'print(open("file.txt", "rt").read())'
+                verbose: true
+                types: [text]
+   "#});
+
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello, world!")?;
+    context.git_add(".");
+
+    // Non-staged files should be stashed and restored.
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello world again!")?;
+
+    let filters: Vec<_> = context
+        .filters()
+        .into_iter()
+        .chain([(r"/\d+-\d+.patch", "/[TIME]-[PID].patch")])
+        .collect();
+
+    cmd_snapshot!(filters, context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    trailing-whitespace......................................................Passed
+    - hook id: trailing-whitespace
+    - duration: [TIME]
+      Hello, world!
+
+    ----- stderr -----
+    Non-staged changes detected, saving to `[HOME]/patches/[TIME]-[PID].patch`
+
+    Restored working tree changes from `[HOME]/patches/[TIME]-[PID].patch`
+    "#);
+
+    let content = context.read("file.txt");
+    assert_snapshot!(content, @"Hello world again!");
+
+    Ok(())
+}
+
+/// Intent-to-add files are hidden from hooks and restored afterwards, also from a subdirectory.
+#[test]
+fn intent_to_add() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: print-files
+                name: print-files
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:])'
+                verbose: true
+                exclude: ^\.pre-commit-config\.yaml$
+   "});
+
+    let cwd = context.work_dir();
+    cwd.child("sub/staged.txt").write_str("staged\n")?;
+    context.git_add(".");
+    cwd.child("sub/new.txt").write_str("new\n")?;
+    Command::new("git")
+        .args(["add", "--intent-to-add", "sub/new.txt"])
+        .current_dir(cwd)
+        .assert()
+        .success();
+
+    cmd_snapshot!(context.filters(), context.run().current_dir(cwd.child("sub")), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['sub/staged.txt']
+
+    ----- stderr -----
+    ");
+
+    let output = Command::new("git")
+        .args(["diff", "--name-only", "--diff-filter=A"])
+        .current_dir(cwd)
+        .output()?;
+    assert_snapshot!(String::from_utf8_lossy(&output.stdout), @"sub/new.txt");
+
+    Ok(())
+}
+
+/// `--no-stash` and `stash_mode` control what happens to non-staged changes.
+#[test]
+fn stash_mode() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: print-file
+                name: print-file
+                language: system
+                entry: python3 -c 'print(open("file.txt", "rt").read())'
+                verbose: true
+                types: [text]
+   "#});
+
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello, world!")?;
+    context.git_add(".");
+
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello world again!")?;
+
+    // Hooks see the non-staged changes.
+    cmd_snapshot!(context.filters(), context.run().arg("--no-stash"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-file...............................................................Passed
+    - hook id: print-file
+    - duration: [TIME]
+      Hello world again!
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--stash-mode").arg("refuse"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: You have changes that are not staged, stage or stash them before running prek (`stash_mode: refuse`)
+    ");
+
+    // The config sets the default, the command line overrides it.
+    let config = context.read(".pre-commit-config.yaml");
+    context.write_pre_commit_config(&format!("stash_mode: refuse\n{config}"));
+    context.git_add(".pre-commit-config.yaml");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: You have changes that are not staged, stage or stash them before running prek (`stash_mode: refuse`)
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--stash-mode").arg("keep"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-file...............................................................Passed
+    - hook id: print-file
+    - duration: [TIME]
+      Hello world again!
+
+    ----- stderr -----
+    ");
+
+    let content = context.read("file.txt");
+    assert_snapshot!(content, @"Hello world again!");
+
+    Ok(())
+}
+
+/// `stash_mode: isolate` runs hooks on the staged content, and applies their fixes to the index
+/// and the working tree.
+#[test]
+fn stash_mode_isolate() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        stash_mode: isolate
+        repos:
+          - repo: local
+            hooks:
+              - id: fix
+                name: fix
+                language: system
+                entry: python3 fix.py
+                files: \.txt$
+   "});
+    let cwd = context.work_dir();
+    cwd.child("fix.py").write_str(indoc::indoc! {r#"
+        import sys
+        for name in sys.argv[1:]:
+            with open(name) as f:
+                content = f.read()
+            with open(name, "w") as f:
+                f.write(content.replace("foo", "FOO"))
+    "#})?;
+    cwd.child("file.txt").write_str("foo\na\nb\nc\n")?;
+    cwd.child("other.txt").write_str("foo\n")?;
+    context.git_add(".");
+    cwd.child("file.txt")
+        .write_str("foo\na\nb\nc\nunstaged\n")?;
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fix......................................................................Failed
+    - hook id: fix
+    - files were modified by this hook
+
+    Applied the fixes of hooks to the index and the working tree:
+      - file.txt
+      - other.txt
+
+    ----- stderr -----
+    ");
+
+    // The fixes are merged with the changes that are not staged.
+    assert_snapshot!(context.read("file.txt"), @r"
+    FOO
+    a
+    b
+    c
+    unstaged
+    ");
+    assert_snapshot!(context.read("other.txt"), @"FOO");
+    let output = Command::new("git")
+        .args(["show", ":file.txt"])
+        .current_dir(cwd)
+        .output()?;
+    assert_snapshot!(String::from_utf8_lossy(&output.stdout), @r"
+    FOO
+    a
+    b
+    c
+    ");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    fix......................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    // Fixes that conflict with the changes that are not staged are not applied.
+    cwd.child("file.txt").write_str("foo\n")?;
+    context.git_add("file.txt");
+    cwd.child("file.txt").write_str("bar\n")?;
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+    fix......................................................................Failed
+    - hook id: fix
+    - files were modified by this hook
+
+    ----- stderr -----
+    error: The fixes of hooks conflict with changes that are not staged, they were not applied:
+      - file.txt
+    Stage or stash the changes and run the hooks again
+    ");
+    assert_snapshot!(context.read("file.txt"), @"bar");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn restore_on_interrupt() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    // The hook will sleep for 3 seconds.
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'import time; open("out.txt", "wt").write(open("file.txt", "rt").read()); time.sleep(10)'
+                verbose: true
+                types: [text]
+   "#});
+
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello, world!")?;
+    context.git_add(".");
+
+    // Non-staged files should be stashed and restored.
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello world again!")?;
+
+    let mut child = context.run().spawn()?;
+    let child_id = child.id();
+
+    // Send an interrupt signal to the process.
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        #[allow(clippy::cast_possible_wrap)]
+        unsafe {
+            libc::kill(child_id as i32, libc::SIGINT)
+        };
+    });
+
+    handle.join().unwrap();
+    child.wait()?;
+
+    let content = context.read("out.txt");
+    assert_snapshot!(content, @"Hello, world!");
+
+    let content = context.read("file.txt");
+    assert_snapshot!(content, @"Hello world again!");
+
+    Ok(())
+}
+
+/// Non-staged changes stashed by a killed run are restored by the next run.
+#[cfg(unix)]
+#[test]
+fn recover_after_crash() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: crash
+                name: crash
+                language: system
+                entry: python3 -c 'import os, signal; os.environ.get("CRASH") and os.kill(os.getppid(), signal.SIGKILL)'
+                types: [text]
+                pass_env: [CRASH]
+   "#});
+
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello, world!")?;
+    context.git_add(".");
+
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello world again!")?;
+
+    let status = context.run().env("CRASH", "1").status()?;
+    assert!(!status.success());
+
+    // The killed run left the working tree stashed.
+    let content = context.read("file.txt");
+    assert_snapshot!(content, @"Hello, world!");
+
+    let filters: Vec<_> = context
+        .filters()
+        .into_iter()
+        .chain([(r"/\d+-\d+.patch", "/[TIME]-[PID].patch")])
+        .collect();
+
+    cmd_snapshot!(filters, context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    crash....................................................................Passed
+
+    ----- stderr -----
+    Restored working tree changes of an interrupted run from `[HOME]/patches/[TIME]-[PID].patch`
+    Non-staged changes detected, saving to `[HOME]/patches/[TIME]-[PID].patch`
+
+    Restored working tree changes from `[HOME]/patches/[TIME]-[PID].patch`
+    ");
+
+    let content = context.read("file.txt");
+    assert_snapshot!(content, @"Hello world again!");
+
+    Ok(())
+}
+
+/// When in merge conflict, runs on files that have conflicts fixed.
+#[test]
+fn merge_conflicts() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    // Create a merge conflict.
+    let cwd = context.work_dir();
+    cwd.child("file.txt").write_str("Hello, world!")?;
+    context.git_add(".");
+    context.configure_git_author();
+    context.git_commit("Initial commit");
+
+    Command::new("git")
+        .arg("checkout")
+        .arg("-b")
+        .arg("feature")
+        .current_dir(cwd)
+        .assert()
+        .success();
+    cwd.child("file.txt").write_str("Hello, world again!")?;
+    context.git_add(".");
+    context.git_commit("Feature commit");
+
+    Command::new("git")
+        .arg("checkout")
+        .arg("master")
+        .current_dir(cwd)
+        .assert()
+        .success();
+    cwd.child("file.txt")
+        .write_str("Hello, world from master!")?;
+    context.git_add(".");
+    context.git_commit("Master commit");
+
+    Command::new("git")
+        .arg("merge")
+        .arg("feature")
+        .current_dir(cwd)
+        .assert()
+        .code(1);
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                verbose: true
+    "});
+
+    // Abort on merge conflicts.
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: You have unmerged paths. Resolve them before running prek:
+      - file.txt
+    "#);
+
+    // Mark the conflict as resolved without removing the conflict markers.
+    context.git_add(".");
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Conflict markers found in merged files. Resolve them before running prek:
+      - file.txt
+    "#);
+
+    // Fix the conflict and run again.
+    cwd.child("file.txt").write_str("Hello, world merged!")?;
+    context.git_add(".");
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    trailing-whitespace......................................................Passed
+    - hook id: trailing-whitespace
+    - duration: [TIME]
+      ['.pre-commit-config.yaml', 'file.txt']
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// Submodules are not passed to hooks as files, `--include-submodules` runs their own hooks with
+/// the options of the run.
+#[test]
+fn submodules() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    // A repository with its own config, added as a submodule.
+    let lib = context.home_dir().child("lib");
+    lib.create_dir_all()?;
+    Command::new("git")
+        .arg("init")
+        .current_dir(&lib)
+        .assert()
+        .success();
+    lib.child(CONFIG_FILE).write_str(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: lib-hook
+                name: lib-hook
+                language: system
+                entry: echo lib
+                pass_filenames: false
+                verbose: true
+    "})?;
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(&lib)
+        .assert()
+        .success();
+    Command::new("git")
+        .args([
+            "-c",
+            "user.name=Prek",
+            "-c",
+            "user.email=prek@example.com",
+            "commit",
+            "-m",
+            "Initial commit",
+        ])
+        .current_dir(&lib)
+        .assert()
+        .success();
+
+    let cwd = context.work_dir();
+    Command::new("git")
+        .args(["-c", "protocol.file.allow=always", "submodule", "add"])
+        .arg(&*lib)
+        .arg("lib")
+        .current_dir(cwd)
+        .assert()
+        .success();
+
+    cwd.child("main.txt").write_str("Hello, world!\n")?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: print-files
+                name: print-files
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                types_or: [file, directory]
+                files: ^(main\.txt|lib)$
+                verbose: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['main.txt']
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files").arg("--include-submodules"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['main.txt']
+
+    Running hooks in submodule `lib`:
+    lib-hook.................................................................Passed
+    - hook id: lib-hook
+    - duration: [TIME]
+      lib
+
+    ----- stderr -----
+    ");
+
+    // Submodules only run on the given files inside them.
+    cmd_snapshot!(context.filters(), context.run().arg("--files").arg("main.txt").arg("--include-submodules"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['main.txt']
+
+    ----- stderr -----
+    ");
+
+    // The reports of submodules are added to the report of the repository.
+    context
+        .run()
+        .arg("--all-files")
+        .arg("--include-submodules")
+        .arg("--output")
+        .arg("json:report.json")
+        .assert()
+        .success();
+    let report: serde_json::Value = serde_json::from_str(&context.read("report.json"))?;
+    assert_eq!(report["hooks"][0]["id"], "print-files");
+    assert_eq!(report["submodules"]["lib"]["hooks"][0]["id"], "lib-hook");
+
+    Ok(())
+}
+
+/// Local python hook with no additional dependencies.
+#[test]
+fn local_python_hook() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: local-python-hook
+                name: local-python-hook
+                language: python
+                entry: python3 -c 'import sys; print("Hello, world!"); sys.exit(1)'
+    "#});
+
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    local-python-hook........................................................Failed
+    - hook id: local-python-hook
+    - exit code: 1
+      Hello, world!
+
+    ----- stderr -----
+    "#);
+}
+
+/// Invalid `entry`
+#[test]
+fn invalid_entry() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: entry
+                name: entry
+                language: python
+                entry: '"'
+    "#});
+
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+    entry....................................................................
+    ----- stderr -----
+    error: Failed to run hook `entry`
+      caused by: Hook `entry` is invalid
+      caused by: Failed to parse entry `"` as commands
+    "#);
+}
+
+/// Initialize a repo that does not exist.
+#[test]
+fn init_nonexistent_repo() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: https://notexistentatallnevergonnahappen.com/nonexistent/repo
+            rev: v1.0.0
+            hooks:
+              - id: nonexistent
+                name: nonexistent
+        "});
+    context.git_add(".");
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([(r"exit code: ", "exit status: "),
+            // Normalize Git error message to handle environment-specific variations
+            (
+                r"fatal: unable to access 'https://notexistentatallnevergonnahappen\.com/nonexistent/repo/':.*",
+                r"fatal: unable to access 'https://notexistentatallnevergonnahappen.com/nonexistent/repo/': [error]"
+            ),
+        ])
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters, context.run(), @r"
+    success: false
+    exit_code: 5
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to initialize repo `https://notexistentatallnevergonnahappen.com/nonexistent/repo`
+      caused by: command `git full clone` exited with an error:
+
+    [status]
+    exit status: 128
+
+    [stderr]
+    fatal: unable to access 'https://notexistentatallnevergonnahappen.com/nonexistent/repo/': [error]
+    ");
+}
+
+/// Test hooks that specifies `types: [directory]`.
+#[test]
+fn types_directory() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: directory
+                name: directory
+                language: system
+                entry: echo
+                types: [directory]
+        "});
+    context.work_dir().child("dir").create_dir_all()?;
+    context
+        .work_dir()
+        .child("dir/file.txt")
+        .write_str("Hello, world!")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory............................................(no files to check)Skipped
+
+    ----- stderr -----
+    "#);
+
+    cmd_snapshot!(context.filters(), context.run().arg("--files").arg("dir"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory................................................................Passed
+
+    ----- stderr -----
+    "#);
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory............................................(no files to check)Skipped
+
+    ----- stderr -----
+    "#);
+
+    cmd_snapshot!(context.filters(), context.run().arg("--files").arg("non-exist-files"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory............................................(no files to check)Skipped
+
+    ----- stderr -----
+    warning: This file does not exist, it will be ignored: `non-exist-files`
+    "#);
+    Ok(())
+}
+
+#[test]
+fn run_last_commit() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+
+    let cwd = context.work_dir();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: https://github.com/pre-commit/pre-commit-hooks
+            rev: v5.0.0
+            hooks:
+              - id: trailing-whitespace
+              - id: end-of-file-fixer
+    "});
+
+    // Create initial files and make first commit
+    cwd.child("file1.txt").write_str("Hello, world!\n")?;
+    cwd.child("file2.txt")
+        .write_str("Initial content with trailing spaces   \n")?; // This has issues but won't be in last commit
+    context.git_add(".");
+    context.git_commit("Initial commit");
+
+    // Modify files and make second commit with trailing whitespace
+    cwd.child("file1.txt").write_str("Hello, world!   \n")?; // trailing whitespace
+    cwd.child("file3.txt").write_str("New file")?; // missing newline
+    // Note: file2.txt is NOT modified in this commit, so it should be filtered out by --last-commit
+    context.git_add(".");
+    context.git_commit("Second commit with issues");
+
+    // Run with --last-commit should only check files from the last commit
+    // This should only process file1.txt and file3.txt, NOT file2.txt
+    cmd_snapshot!(context.filters(), context.run().arg("--last-commit"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    trim trailing whitespace.................................................Failed
+    - hook id: trailing-whitespace
+    - exit code: 1
+    - files were modified by this hook
+      Fixing file1.txt
+    fix end of files.........................................................Failed
+    - hook id: end-of-file-fixer
+    - exit code: 1
+    - files were modified by this hook
+      Fixing file3.txt
+
+    ----- stderr -----
+    "#);
+
+    // Now reset the files to their problematic state for comparison
+    cwd.child("file1.txt").write_str("Hello, world!   \n")?; // trailing whitespace
+    cwd.child("file3.txt").write_str("New file")?; // missing newline
+
+    // Run with --all-files should check ALL files including file2.txt
+    // This demonstrates that file2.txt was indeed filtered out in the previous test
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    trim trailing whitespace.................................................Failed
+    - hook id: trailing-whitespace
+    - exit code: 1
+    - files were modified by this hook
+      Fixing file1.txt
+      Fixing file2.txt
+    fix end of files.........................................................Failed
+    - hook id: end-of-file-fixer
+    - exit code: 1
+    - files were modified by this hook
+      Fixing file3.txt
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// `--last-commit` on the root commit runs on all files of the commit.
+#[test]
+fn run_last_commit_root() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: show-files
+                name: show files
+                language: system
+                entry: python3 -c 'import sys; print(*sorted(sys.argv[1:]))'
+                verbose: true
+    "});
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello, world!\n")?;
+    context.git_add(".");
+    context.git_commit("Initial commit");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--last-commit"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      .pre-commit-config.yaml file.txt
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// `--from-ref` / `--to-ref` run hooks on the files changed since the merge base.
+#[test]
+fn run_from_ref_to_ref() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+
+    let cwd = context.work_dir();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: show-files
+                name: show files
+                language: system
+                entry: echo
+                verbose: true
+    "});
+    cwd.child("shared.txt").write_str("shared\n")?;
+    context.git_add(".");
+    context.git_commit("Initial commit");
+
+    Command::new("git")
+        .args(["checkout", "-b", "feature"])
+        .current_dir(cwd)
+        .assert()
+        .success();
+    cwd.child("feature.txt").write_str("feature\n")?;
+    context.git_add(".");
+    context.git_commit("Add feature");
+
+    // Changes on the base branch after the branch point are not part of the diff.
+    Command::new("git")
+        .args(["checkout", "master"])
+        .current_dir(cwd)
+        .assert()
+        .success();
+    cwd.child("shared.txt").write_str("changed on master\n")?;
+    context.git_add(".");
+    context.git_commit("Change shared file");
+
+    // Hooks run on the files of the checked out `feature` branch.
+    Command::new("git")
+        .args(["checkout", "feature"])
+        .current_dir(cwd)
+        .assert()
+        .success();
+
+    cmd_snapshot!(context.filters(), context.run().arg("--from-ref").arg("master").arg("--to-ref").arg("feature"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      feature.txt
+
+    ----- stderr -----
+    "#);
+
+    // `--to-ref` defaults to `HEAD`.
+    cmd_snapshot!(context.filters(), context.run().arg("--from-ref").arg("master"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      feature.txt
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// Test `prek run --files` with multiple files.
+#[test]
+fn run_multiple_files() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: multiple-files
+                name: multiple-files
+                language: system
+                entry: echo
+                verbose: true
+                types: [text]
+    "});
+    let cwd = context.work_dir();
+    cwd.child("file1.txt").write_str("Hello, world!")?;
+    cwd.child("file2.txt").write_str("Hello, world!")?;
+    context.git_add(".");
+    // `--files` with multiple files
+    cmd_snapshot!(context.filters(), context.run().arg("--files").arg("file1.txt").arg("file2.txt"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    multiple-files...........................................................Passed
+    - hook id: multiple-files
+    - duration: [TIME]
+      file2.txt file1.txt
+
+    ----- stderr -----
+    "#);
+    Ok(())
+}
+
+/// `--files` expands glob patterns against the files tracked by git.
+#[test]
+fn run_files_glob() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: show-files
+                name: show files
+                language: system
+                entry: python3 -c 'import sys; print(*sorted(sys.argv[1:]))'
+                verbose: true
+    "});
+    let cwd = context.work_dir();
+    cwd.child("src/a.py").write_str("a")?;
+    cwd.child("src/sub/b.py").write_str("b")?;
+    cwd.child("src/c.txt").write_str("c")?;
+    cwd.child("docs/index.md").write_str("index")?;
+    cwd.child("docs/sub/page.md").write_str("page")?;
+    context.git_add(".");
+    // Untracked files are not matched.
+    cwd.child("src/untracked.py").write_str("untracked")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--files").arg("src/**/*.py").arg("--files").arg("docs/*.md").arg("--files").arg("*.rs"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      docs/index.md src/a.py src/sub/b.py
+
+    ----- stderr -----
+    warning: No files matched the pattern: `*.rs`
+    "#);
+
+    // Patterns are relative to the current directory.
+    cmd_snapshot!(context.filters(), context.run().current_dir(cwd.join("src")).arg("--files").arg("*.py"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      src/a.py
+
+    ----- stderr -----
+    "#);
+
+    // Patterns that match nothing don't fall back to the staged files.
+    cmd_snapshot!(context.filters(), context.run().arg("--files").arg("*.rs"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    No files to run hooks on
+
+    ----- stderr -----
+    warning: No files matched the pattern: `*.rs`
+    ");
+
+    Ok(())
+}
+
+/// `--files-from` reads filenames from a file or stdin, separated by newlines or NUL.
+#[test]
+fn run_files_from() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: show-files
+                name: show files
+                language: system
+                entry: python3 -c 'import sys; print(*sorted(sys.argv[1:]))'
+                verbose: true
+    "});
+    let cwd = context.work_dir();
+    cwd.child("file1.txt").write_str("Hello, world!")?;
+    cwd.child("file 2.txt").write_str("Hello, world!")?;
+    cwd.child("file3.txt").write_str("Hello, world!")?;
+    context.git_add(".");
+
+    let list = context.home_dir().child("files.txt");
+    list.write_str("file1.txt\r\nfile 2.txt\n\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--files-from").arg(&*list).arg("--files").arg("file3.txt"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      file 2.txt file1.txt file3.txt
+
+    ----- stderr -----
+    "#);
+
+    // NUL-separated filenames from stdin.
+    list.write_str("file1.txt\0file3.txt\0")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--files-from").arg("-").stdin(std::fs::File::open(&*list)?), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      file1.txt file3.txt
+
+    ----- stderr -----
+    "#);
+
+    // Filenames are taken literally, glob patterns are not expanded.
+    list.write_str("file1.txt\n*.txt\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--files-from").arg(&*list), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      file1.txt
+
+    ----- stderr -----
+    warning: This file does not exist, it will be ignored: `*.txt`
+    "#);
+
+    // An empty list runs no hooks, instead of running them on the staged files.
+    list.write_str("")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--files-from").arg(&*list), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    No files to run hooks on
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// `--files-from` accepts filenames that aren't valid UTF-8 when they are separated by NUL.
+#[cfg(target_os = "linux")]
+#[test]
+fn run_files_from_non_utf8() -> Result<()> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: count-files
+                name: count files
+                language: system
+                entry: python3 -c 'import sys; print(len(sys.argv) - 1)'
+                verbose: true
+    "});
+    let cwd = context.work_dir();
+    cwd.child(OsStr::from_bytes(b"file\xff.txt"))
+        .write_str("Hello, world!")?;
+    cwd.child("file1.txt").write_str("Hello, world!")?;
+    context.git_add(".");
+
+    let list = context.home_dir().child("files.txt");
+    list.write_binary(b"file\xff.txt\0file1.txt\0")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--files-from").arg(&*list), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    count files..............................................................Passed
+    - hook id: count-files
+    - duration: [TIME]
+      2
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// Test `prek run --files` with no files.
+#[test]
+fn run_no_files() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: no-files
+                name: no-files
+                language: system
+                entry: echo
+                verbose: true
+    "});
+    context.git_add(".");
+    // `--files` with no files
+    cmd_snapshot!(context.filters(), context.run().arg("--files"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    no-files.................................................................Passed
+    - hook id: no-files
+    - duration: [TIME]
+      .pre-commit-config.yaml
+
+    ----- stderr -----
+    "#);
+}
+
+/// Test `prek run --directory` flags.
+#[test]
+fn run_directory() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: directory
+                name: directory
+                language: system
+                entry: echo
+                verbose: true
+    "});
+
+    let cwd = context.work_dir();
+    cwd.child("dir1").create_dir_all()?;
+    cwd.child("dir1/file.txt").write_str("Hello, world!")?;
+    cwd.child("dir2").create_dir_all()?;
+    cwd.child("dir2/file.txt").write_str("Hello, world!")?;
+    context.git_add(".");
+
+    // one `--directory`
+    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("dir1"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory................................................................Passed
+    - hook id: directory
+    - duration: [TIME]
+      dir1/file.txt
+
+    ----- stderr -----
+    "#);
+
+    // repeated `--directory`
+    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("dir1").arg("--directory").arg("dir1"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory................................................................Passed
+    - hook id: directory
+    - duration: [TIME]
+      dir1/file.txt
+
+    ----- stderr -----
+    "#);
+
+    // multiple `--directory`
+    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("dir1").arg("--directory").arg("dir2"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory................................................................Passed
+    - hook id: directory
+    - duration: [TIME]
+      dir2/file.txt dir1/file.txt
+
+    ----- stderr -----
+    "#);
+
+    // non-existing directory
+    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("non-existing-dir"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory............................................(no files to check)Skipped
+
+    ----- stderr -----
+    "#);
+
+    // `--directory` with `--files`
+    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("dir1").arg("--files").arg("dir1/file.txt"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory................................................................Passed
+    - hook id: directory
+    - duration: [TIME]
+      dir1/file.txt
+
+    ----- stderr -----
+    "#);
+    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("dir1").arg("--files").arg("dir2/file.txt"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory................................................................Passed
+    - hook id: directory
+    - duration: [TIME]
+      dir2/file.txt dir1/file.txt
+
+    ----- stderr -----
+    "#);
+
+    // run `--directory` inside a subdirectory
+    cmd_snapshot!(context.filters(), context.run().current_dir(cwd.join("dir1")).arg("--directory").arg("."), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory................................................................Passed
+    - hook id: directory
+    - duration: [TIME]
+      dir1/file.txt
+
+    ----- stderr -----
+    "#);
+
+    cmd_snapshot!(context.filters(), context.run().arg("--cd").arg("dir1").arg("--directory").arg("."), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    directory................................................................Passed
+    - hook id: directory
+    - duration: [TIME]
+      dir1/file.txt
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// Test `minimum_prek_version` option.
+#[test]
+fn minimum_prek_version() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        minimum_prek_version: 10.0.0
+        repos:
+          - repo: local
+            hooks:
+              - id: directory
+                name: directory
+                language: system
+                entry: echo
+                verbose: true
+    "});
+    context.git_add(".");
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([(
+            r"current version `\d+\.\d+\.\d+(?:-[0-9A-Za-z]+(?:\.[0-9A-Za-z]+)*)?`",
+            "current version `[CURRENT_VERSION]`",
+        )])
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters, context.run(), @r#"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to parse `.pre-commit-config.yaml`
+      caused by: Required minimum prek version `10.0.0` is greater than current version `[CURRENT_VERSION]`. Please consider updating prek.
+    "#);
+}
+
+/// Test `minimum_pre_commit_version` option.
+#[test]
+fn minimum_pre_commit_version() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        minimum_pre_commit_version: 100.0.0
+        repos:
+          - repo: local
+            hooks:
+              - id: directory
+                name: directory
+                language: system
+                entry: echo
+    "});
+    context.git_add(".");
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([(
+            r"prek `\d+\.\d+\.\d+(?:-[0-9A-Za-z]+(?:\.[0-9A-Za-z]+)*)?`",
+            "prek `[CURRENT_VERSION]`",
+        )])
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters, context.run(), @r#"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to parse `.pre-commit-config.yaml`
+      caused by: Required minimum pre-commit version `100.0.0` is greater than the pre-commit version `4.3.0` supported by prek `[CURRENT_VERSION]`. Please consider updating prek.
+    "#);
+}
+
+/// Run hooks that would echo color.
+#[test]
+#[cfg(not(windows))]
+fn color() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+      repos:
+        - repo: local
+          hooks:
+            - id: color
+              name: color
+              language: python
+              entry: python ./color.py
+              verbose: true
+              pass_filenames: false
+  "});
+
+    let script = indoc::indoc! {r"
+      import sys
+      if sys.stdout.isatty():
+          print('\033[1;32mHello, world!\033[0m')
+      else:
+          print('Hello, world!')
+  "};
+    context.work_dir().child("color.py").write_str(script)?;
+
+    context.git_add(".");
+
+    // Run default. In integration tests, we don't have a TTY.
+    // So this prints without color.
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+  success: true
+  exit_code: 0
+  ----- stdout -----
+  color....................................................................Passed
+  - hook id: color
+  - duration: [TIME]
+    Hello, world!
+
+  ----- stderr -----
+  "#);
+
+    // Force color output
+    cmd_snapshot!(context.filters(), context.run().arg("--color=always"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    color....................................................................[42mPassed[49m
+    [2m- hook id: color[0m
+    [2m- duration: [TIME][0m
+    [2m  [1;32mHello, world![0m[0m
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// Test running hook whose `entry` is script with shebang on Windows.
+#[test]
+fn shebang_script() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    // Create a script with shebang.
+    let script = indoc::indoc! {r"
+        #!/usr/bin/env python
+        import sys
+        print('Hello, world!')
+        sys.exit(0)
+    "};
+    context.work_dir().child("script.py").write_str(script)?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+      repos:
+        - repo: local
+          hooks:
+            - id: shebang-script
+              name: shebang-script
+              language: python
+              entry: script.py
+              verbose: true
+              pass_filenames: false
+              always_run: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    shebang-script...........................................................Passed
+    - hook id: shebang-script
+    - duration: [TIME]
+      Hello, world!
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// Test `git commit -a` works without `.git/index.lock exists` error.
+#[test]
+fn git_commit_a() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+    context.disable_auto_crlf();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: echo
+                name: echo
+                language: system
+                entry: echo
+                verbose: true
+    "});
+
+    // Create a file and commit it.
+    let cwd = context.work_dir();
+    let file = cwd.child("file.txt");
+    file.write_str("Hello, world!\n")?;
+
+    cmd_snapshot!(context.filters(), context.install(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    prek installed at `.git/hooks/pre-commit`
+
+    ----- stderr -----
+    "#);
+
+    context.git_add(".");
+    context.git_commit("Initial commit");
+
+    // Edit the file
+    file.write_str("Hello, world again!\n")?;
+
+    let mut commit = Command::new("git");
+    commit
+        .arg("commit")
+        .arg("-a")
+        .arg("-m")
+        .arg("Update file")
+        .current_dir(cwd);
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([(r"\[master \w{7}\]", r"[master COMMIT]")])
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters, commit, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [master COMMIT] Update file
+     1 file changed, 1 insertion(+), 1 deletion(-)
+
+    ----- stderr -----
+    echo.....................................................................Passed
+    - hook id: echo
+    - duration: [TIME]
+      file.txt
+    "#);
+
+    Ok(())
+}
+
+fn write_pre_commit_config(path: &Path, hooks: &[(&str, &str)]) -> Result<()> {
+    let mut yaml = String::from(indoc::indoc! {"
+        repos:
+          - repo: local
+            hooks:
+    "});
+    for (id, name) in hooks {
+        let hook = textwrap::indent(
+            &indoc::formatdoc! {"
+        - id: {}
+          name: {}
+          entry: echo
+          language: system
+        ", id, name
+            },
+            "      ",
+        );
+        yaml.push_str(&hook);
+    }
+
+    std::fs::create_dir_all(path)?;
+    std::fs::write(path.join(CONFIG_FILE), yaml)?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn selectors_completion() -> Result<()> {
+    let context = TestContext::new();
+    let cwd = context.work_dir();
+    context.init_project();
+
+    // Root project with one hook
+    write_pre_commit_config(cwd, &[("root-hook", "Root Hook")])?;
+
+    // Nested project at app/ with one hook
+    let app = cwd.join("app");
+    write_pre_commit_config(&app, &[("app-hook", "App Hook")])?;
+
+    // Deeper nested project at app/lib/ with one hook
+    let app_lib = app.join("lib");
+    write_pre_commit_config(&app_lib, &[("lib-hook", "Lib Hook")])?;
+
+    // Unrelated non-project dir should not appear in subdir suggestions
+    cwd.child("scratch").create_dir_all()?;
+
+    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg(""), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    install	Install the prek git hook
+    install-hooks	Create hook environments for all hooks used in the config file
+    run	Run hooks
+    watch	Watch the working tree and run hooks on the files that change
+    list	List available hooks
+    info	Show how a hook is resolved and run
+    exec	Run a command in the environment of a hook
+    compare	Compare the JSON reports of two runs
+    stats	Show trends of the recorded runs of the workspace
+    baseline	Record the current failures of hooks to adopt them in an existing codebase
+    uninstall	Uninstall the prek git hook
+    validate-config	Validate `.pre-commit-config.yaml` files
+    validate-manifest	Validate `.pre-commit-hooks.yaml` files
+    sample-config	Produce a sample `.pre-commit-config.yaml` file
+    new-hook	Create a new hook repository
+    schema	Print the JSON Schema of the config or manifest files
+    auto-update	Auto-update pre-commit config to the latest repos' versions
+    audit	Check the remote repos of the config against supply-chain policies
+    cache	Manage the prek cache
+    debug-bundle	Create a zip archive with debug information to attach to bug reports
+    init-template-dir	Install hook script in a directory intended for use with `git config init.templateDir`
+    try-repo	Try the pre-commit hooks in the current repo
+    self	`prek` self management
+    completions	Print the completion script of a shell
+    app/
+    app:
+    app-hook	App Hook
+    lib-hook	Lib Hook
+    root-hook	Root Hook
+    --skip	Skip the specified hooks or projects
+    --project	Run only the hooks of the specified project, including the projects nested in it
+    --group	Run only the hooks of the specified group, as set by the `group` hook option
+    --tag	Run only the hooks with the specified tag, as set by the `tags` hook option
+    --all-files	Run on all files in the repo
+    --files	Specific filenames to run hooks on
+    --files-from	Read the filenames to run hooks on from a file, or from stdin with `-`
+    --directory	Run hooks on all files in the specified directories
+    --from-ref	The original ref in a `<from_ref>...<to_ref>` diff expression. Files changed in this diff will be run through the hooks
+    --to-ref	The destination ref in a `from_ref...to_ref` diff expression. Defaults to `HEAD` if `from_ref` is specified
+    --only-files-matching	Only run hooks on files matching this regex
+    --exclude-files-matching	Do not run hooks on files matching this regex
+    --max-file-size	Do not run hooks on files larger than this size, e.g. `500KB` or `2MB`
+    --skip-binary-files	Do not run hooks on binary files
+    --skip-lfs-files	Do not run hooks on Git LFS files
+    --fail-on-sparse	Fail instead of skipping files that are not materialized by a sparse checkout
+    --no-baseline	Run hooks on the files recorded in `.prek-baseline.json` as well
+    --last-commit	Run hooks against the last commit. Equivalent to `--from-ref HEAD~1 --to-ref HEAD`
+    --rebase-exec	Run hooks as a `git rebase --exec` command, e.g. `git rebase -x 'prek run --rebase-exec'`
+    --incremental	Run on the files changed since the last successful `--incremental` run
+    --failed	Run only the hooks that failed in the last run, on the files they failed on
+    --interactive	Pick the hooks to run from a checklist, with the result of each hook in the last run
+    --workspace	Run hooks in every repository listed in the file, one path per line
+    --hook-stage	The stage during which the hook is fired
+    --include-submodules	Also run hooks in initialized submodules that have their own config
+    --show-diff-on-failure	When hooks fail, run `git diff` directly afterward
+    --stash-mode	What to do with changes that are not staged before running hooks
+    --no-stash	Do not stash changes that are not staged, hooks see the working tree as is
+    --dry-run	Do not run the hooks, but print the hooks that would have been run
+    --jobs	The number of hooks and environment installs to run in parallel
+    --diff	With `--dry-run`, preview the changes of hooks as a diff
+    --fix-and-verify	When hooks modify files, run all hooks once more to verify the fixes
+    --fix-and-retry	When hooks modify staged files, stage the fixes and run all hooks once more
+    --strict	Treat failures of hooks with `severity: warning` as errors
+    --strict-warnings	Exit with a non-zero status if deprecations were found
+    --require-pinned-revs	Refuse to run hooks of repos whose `rev` is not a full commit SHA
+    --output	Where to send hook results, in addition to the terminal output
+    --porcelain	Print one stable, tab-separated record per hook instead of the human readable output
+    --stream	Print the output of hooks while they run, each line prefixed with the hook id
+    --pty	Run hooks in a pseudo-terminal, so tools print colors and progress like they do interactively
+    --sandbox	Run all hooks in a sandbox, with a read-only filesystem except for their project, and the home directory hidden
+    --durations	Print the `N` slowest hooks and environment installs once all hooks ran
+    --timeout	Stop the run if it takes longer than this, e.g. `[TIME]`, `10m` or `1h`
+    --config	Path to alternate config file
+    --cd	Change to directory before running
+    --color	Whether to use color in output
+    --isolated	Ignore the user config, the policy file and environment variables that override prek
+    --refresh	Refresh all cached data
+    --help	Display the concise help for this command
+    --no-progress	Hide all progress outputs
+    --quiet	Use quiet output
+    --verbose	Use verbose output
+    --log-file	Write trace logs to the specified file. If not specified, trace logs will be written to `$PREK_HOME/prek.log`
+    --profile	Record a profile of the command to the specified file, in the Chrome trace format
+    --version	Display the prek version
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("ap"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    app/
+    app:
+    app-hook	App Hook
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app:"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    app:app-hook	App Hook
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app:app"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    app:app-hook	App Hook
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app/"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    app/lib/
+    app/lib:
+
+    ----- stderr -----
+    ");
+    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app/li"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    app/lib/
+    app/lib:
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app/lib:"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    app/lib:lib-hook	Lib Hook
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app/lib/"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    app/lib/
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Test reusing hook environments only when dependencies are exactly same. (ignore order)
+#[test]
+fn reuse_env() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+    repos:
+      - repo: https://github.com/PyCQA/flake8
+        rev: 7.1.1
+        hooks:
+          - id: flake8
+            additional_dependencies: [flake8-errmsg]
+    "});
+
+    context
+        .work_dir()
+        .child("err.py")
+        .write_str("raise ValueError('error')\n")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    flake8...................................................................Failed
+    - hook id: flake8
+    - exit code: 1
+      err.py:1:1: EM101 Exceptions must not use a string literal; assign to a variable first
+
+    ----- stderr -----
+    ");
+
+    // Remove dependencies, so the environment should not be reused.
+    context.write_pre_commit_config(indoc::indoc! {r"
+    repos:
+      - repo: https://github.com/PyCQA/flake8
+        rev: 7.1.1
+        hooks:
+          - id: flake8
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    flake8...................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    // There should be two hook environments.
+    assert_eq!(context.home_dir().child("hooks").read_dir()?.count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn dry_run() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: fail
+                name: fail
+                entry: fail
+                language: fail
+    "});
+    context.git_add(".");
+
+    // Run with `--dry-run`
+    cmd_snapshot!(context.filters(), context.run().arg("--dry-run").arg("-v"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    fail.....................................................................Dry Run
+    - hook id: fail
+    - duration: [TIME]
+      `fail` would be run on 1 files:
+      - .pre-commit-config.yaml
+
+    ----- stderr -----
+    ");
+}
+
+/// `--dry-run --diff` previews the changes of hooks without modifying the working tree.
+#[test]
+fn dry_run_diff() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.disable_auto_crlf();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: fix-whitespace
+                name: fix-whitespace
+                language: system
+                entry: python3 -c "import sys, pathlib; [pathlib.Path(f).write_text(pathlib.Path(f).read_text().rstrip() + '\n') for f in sys.argv[1:]]"
+                files: \.txt$
+    "#});
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello, world!  \n")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--dry-run").arg("--diff"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    fix-whitespace...........................................................Dry Run
+
+    Changes hooks would make:
+    diff --git a/file.txt b/file.txt
+    index 20d4ae4..af5626b 100644
+    --- a/file.txt
+    +++ b/file.txt
+    @@ -1 +1 @@
+    -Hello, world!  
+    +Hello, world!
+
+    ----- stderr -----
+    ");
+
+    assert_eq!(context.read("file.txt"), "Hello, world!  \n");
+
+    Ok(())
+}
+
+/// Supports reading `pre-commit-config.yml` as well.
+#[test]
+fn alternate_config_file() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context
+        .work_dir()
+        .child(ALT_CONFIG_FILE)
+        .write_str(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: local-python-hook
+                name: local-python-hook
+                language: python
+                entry: python3 -c 'import sys; print("Hello, world!")'
+    "#})?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("-v"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    local-python-hook........................................................Passed
+    - hook id: local-python-hook
+    - duration: [TIME]
+      Hello, world!
+
+    ----- stderr -----
+    ");
+
+    context
+        .work_dir()
+        .child(CONFIG_FILE)
+        .write_str(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: local-python-hook
+                name: local-python-hook
+                language: python
+                entry: python3 -c 'import sys; print("Hello, world!")'
+    "#})?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--refresh").arg("-v"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    local-python-hook........................................................Passed
+    - hook id: local-python-hook
+    - duration: [TIME]
+      Hello, world!
+
+    ----- stderr -----
+    warning: Both `[TEMP_DIR]/.pre-commit-config.yaml` and `[TEMP_DIR]/.pre-commit-config.yml` exist, using `[TEMP_DIR]/.pre-commit-config.yaml` only
+    ");
+
+    Ok(())
+}
+
+/// Defaults from the user config file apply when the flags are not passed.
+#[test]
+fn user_settings() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.disable_auto_crlf();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: modify
+                name: modify
+                language: system
+                entry: python3 -c "open('file.txt', 'a').write('Added line\n')"
+                pass_filenames: false
+    "#});
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Original line\n")?;
+    context.git_add(".");
+    context
+        .config_dir()
+        .child("prek/config.toml")
+        .write_str("show-diff-on-failure = true\n")?;
+
+    let mut filters = context.filters();
+    filters.push((r"index \w{7}\.\.\w{7} \d{6}", "index [OLD]..[NEW] 100644"));
+
+    cmd_snapshot!(filters.clone(), context.run().env(EnvVars::CI, "1"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    modify...................................................................Failed
+    - hook id: modify
+    - files were modified by this hook
+
+    Hint: Some hooks made changes to the files.
+    If you are seeing this message in CI, reproduce locally with: `prek run --all-files`
+    To run prek as part of git workflow, use `prek install` to set up git hooks.
+
+    All changes made by hooks:
+    diff --git a/file.txt b/file.txt
+    index [OLD]..[NEW] 100644
+    --- a/file.txt
+    +++ b/file.txt
+    @@ -1 +1,2 @@
+     Original line
+    +Added line
+
+    ----- stderr -----
+    ");
+
+    // Unknown settings are rejected.
+    context
+        .config_dir()
+        .child("prek/config.toml")
+        .write_str("show-diff = true\n")?;
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to parse user config file `[CONFIG_DIR]/prek/config.toml`
+      caused by: TOML parse error at line 1, column 1
+      |
+    1 | show-diff = true
+      | ^^^^^^^^^
+    unknown field `show-diff`, expected one of `color`, `jobs`, `cache-dir`, `proxy`, `url-rewrites`, `show-diff-on-failure`, `check-updates`
+    ");
+
+    Ok(())
+}
+
+/// The `proxy` user setting is passed to the commands run by prek, and `cache-dir` must be
+/// absolute.
+#[test]
+fn user_settings_proxy_and_cache_dir() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: proxy
+                name: proxy
+                language: system
+                entry: python3 -c "import os; print(os.environ.get('HTTPS_PROXY'))"
+                pass_filenames: false
+                verbose: true
+    "#});
+    context.git_add(".");
+    context
+        .config_dir()
+        .child("prek/config.toml")
+        .write_str("proxy = \"http://proxy.example.com:3128\"\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().env_remove(EnvVars::HTTP_PROXY).env_remove(EnvVars::HTTPS_PROXY), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    proxy....................................................................Passed
+    - hook id: proxy
+    - duration: [TIME]
+      http://proxy.example.com:3128
+
+    ----- stderr -----
+    ");
+
+    context
+        .config_dir()
+        .child("prek/config.toml")
+        .write_str("cache-dir = \"prek-data\"\n")?;
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+
+    ----- stderr -----
+    error: `cache-dir` in `[CONFIG_DIR]/prek/config.toml` must be an absolute path, got `prek-data`
+    ");
+
+    Ok(())
+}
+
+/// A failed update check is recorded, so that it's not retried on every command while offline.
+#[test]
+fn user_settings_check_updates_offline() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: ok
+                name: ok
+                language: system
+                entry: python3 -c 'pass'
+                pass_filenames: false
+    "});
+    context.git_add(".");
+    context
+        .config_dir()
+        .child("prek/config.toml")
+        .write_str("check-updates = true\nproxy = \"http://127.0.0.1:1\"\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().env_remove(EnvVars::HTTP_PROXY).env_remove(EnvVars::HTTPS_PROXY), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    ok.......................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    let check = fs_err::read_to_string(context.home_dir().child("update-check.json"))?;
+    let check: serde_json::Value = serde_json::from_str(&check)?;
+    assert!(
+        check["checked_at"]
+            .as_u64()
+            .is_some_and(|checked_at| checked_at > 0)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn show_diff_on_failure() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.disable_auto_crlf();
+
+    let config = indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: modify
+                name: modify
+                language: python
+                entry: python -c "import sys; open('file.txt', 'a').write('Added line\n')"
+                pass_filenames: false
+    "#};
+    context.write_pre_commit_config(config);
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Original line\n")?;
+    context.git_add(".");
+
+    let mut filters = context.filters();
+    filters.push((r"index \w{7}\.\.\w{7} \d{6}", "index [OLD]..[NEW] 100644"));
+
+    // When failed in CI environment
+    cmd_snapshot!(filters.clone(), context.run().env(EnvVars::CI, "1").arg("--show-diff-on-failure").arg("-v"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    modify...................................................................Failed
+    - hook id: modify
+    - duration: [TIME]
+    - files were modified by this hook
+
+    Hint: Some hooks made changes to the files.
+    If you are seeing this message in CI, reproduce locally with: `prek run --all-files`
+    To run prek as part of git workflow, use `prek install` to set up git hooks.
+
+    All changes made by hooks:
+    diff --git a/file.txt b/file.txt
+    index [OLD]..[NEW] 100644
+    --- a/file.txt
+    +++ b/file.txt
+    @@ -1 +1,2 @@
+     Original line
+    +Added line
+
+    ----- stderr -----
+    ");
+
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Original line\n")?;
+    context.git_add(".");
+    // When failed in non-CI environment
+    cmd_snapshot!(filters.clone(), context.run().env_remove(EnvVars::CI).arg("--show-diff-on-failure").arg("-v"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    modify...................................................................Failed
+    - hook id: modify
+    - duration: [TIME]
+    - files were modified by this hook
+    All changes made by hooks:
+    diff --git a/file.txt b/file.txt
+    index [OLD]..[NEW] 100644
+    --- a/file.txt
+    +++ b/file.txt
+    @@ -1 +1,2 @@
+     Original line
+    +Added line
+
+    ----- stderr -----
+    ");
+
+    // Run in the `app` subproject.
+    let app = context.work_dir().child("app");
+    app.create_dir_all()?;
+    app.child("file.txt").write_str("Original line\n")?;
+    app.child(CONFIG_FILE).write_str(config)?;
+
+    Command::new("git")
+        .arg("add")
+        .arg(".")
+        .current_dir(&app)
+        .assert()
+        .success();
+
+    cmd_snapshot!(filters.clone(), context.run().env_remove(EnvVars::CI).current_dir(&app).arg("--show-diff-on-failure"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    modify...................................................................Failed
+    - hook id: modify
+    - files were modified by this hook
+    All changes made by hooks:
+    diff --git a/app/file.txt b/app/file.txt
+    index [OLD]..[NEW] 100644
+    --- a/app/file.txt
+    +++ b/app/file.txt
+    @@ -1 +1,2 @@
+     Original line
+    +Added line
+
+    ----- stderr -----
+    ");
+
+    context.git_add(".");
+
+    // Run in the root
+    // Since we add a new subproject, use `--refresh` to find that.
+    cmd_snapshot!(filters.clone(), context.run().env_remove(EnvVars::CI).arg("--show-diff-on-failure").arg("--refresh"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Running hooks for `app`:
+    modify...................................................................Failed
+    - hook id: modify
+    - files were modified by this hook
+
+    Running hooks for `.`:
+    modify...................................................................Failed
+    - hook id: modify
+    - files were modified by this hook
+    All changes made by hooks:
+    diff --git a/app/file.txt b/app/file.txt
+    index [OLD]..[NEW] 100644
+    --- a/app/file.txt
+    +++ b/app/file.txt
+    @@ -1,2 +1,3 @@
+     Original line
+     Added line
+    +Added line
+    diff --git a/file.txt b/file.txt
+    index [OLD]..[NEW] 100644
+    --- a/file.txt
+    +++ b/file.txt
+    @@ -1,2 +1,3 @@
+     Original line
+     Added line
+    +Added line
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// In CI, the diff is shown without `--show-diff-on-failure`, and the details of hooks are
+/// wrapped in collapsible groups.
+#[test]
+fn ci_detection() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.disable_auto_crlf();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: modify
+                name: modify
+                language: system
+                entry: python3 -c "open('file.txt', 'a').write('Added line\n')"
+                pass_filenames: false
+    "#});
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Original line\n")?;
+    context.git_add(".");
+
+    let mut filters = context.filters();
+    filters.push((r"index \w{7}\.\.\w{7} \d{6}", "index [OLD]..[NEW] 100644"));
+
+    cmd_snapshot!(filters.clone(), context.run().env(EnvVars::GITHUB_ACTIONS, "true"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    modify...................................................................Failed
+    ::group::modify
+    - hook id: modify
+    - files were modified by this hook
+    ::endgroup::
+
+    Hint: Some hooks made changes to the files.
+    If you are seeing this message in CI, reproduce locally with: `prek run --all-files`
+    To run prek as part of git workflow, use `prek install` to set up git hooks.
+
+    All changes made by hooks:
+    diff --git a/file.txt b/file.txt
+    index [OLD]..[NEW] 100644
+    --- a/file.txt
+    +++ b/file.txt
+    @@ -1 +1,2 @@
+     Original line
+    +Added line
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(filters, context.run().env(EnvVars::GITHUB_ACTIONS, "true").arg("--interactive"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: `--interactive` is not available in GitHub Actions
+    ");
+
+    Ok(())
+}
+
+#[test]
+fn run_quiet() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: success
+                name: success
+                entry: echo
+                language: system
+              - id: fail
+                name: fail
+                entry: fail
+                language: fail
+    "});
+    context.git_add(".");
+
+    // Run with `--quiet`, only print failed hooks and a summary.
+    cmd_snapshot!(context.filters(), context.run().arg("--quiet"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+      fail
+
+      .pre-commit-config.yaml
+    Ran 2 hooks: 1 passed, 1 failed
+
+    ----- stderr -----
+    ");
+
+    // Run with `-qq`, do not print anything.
+    cmd_snapshot!(context.filters(), context.run().arg("-qq"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+}
+
+/// Test `prek run --log-file <file>` flag.
+#[test]
+fn run_log_file() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: fail
+                name: fail
+                entry: fail
+                language: fail
+    "});
+    context.git_add(".");
+
+    // Run with `--no-log-file`, no `prek.log` is created.
+    cmd_snapshot!(context.filters(), context.run().arg("--no-log-file"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+      fail
+
+      .pre-commit-config.yaml
+
+    ----- stderr -----
+    ");
+    context
+        .home_dir()
+        .child("prek.log")
+        .assert(predicate::path::missing());
+
+    // Write log to `log`.
+    cmd_snapshot!(context.filters(), context.run().arg("--log-file").arg("log"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+      fail
+
+      .pre-commit-config.yaml
+
+    ----- stderr -----
+    ");
+    context
+        .work_dir()
+        .child("log")
+        .assert(predicate::path::exists());
+}
+
+/// The logs of previous runs are kept, and `PREK_LOG` selects what is logged.
+#[test]
+fn run_log_file_rotation() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: success
+                name: success
+                entry: echo
+                language: system
+    "});
+    context.git_add(".");
+
+    context.run().assert().success();
+    context
+        .run()
+        .env(EnvVars::PREK_LOG, "off")
+        .assert()
+        .success();
+
+    let log = context.home_dir().child("prek.log");
+    let previous = context.home_dir().child("prek.log.1");
+    assert!(fs_err::read_to_string(&log)?.is_empty());
+    assert!(fs_err::read_to_string(&previous)?.contains("run_hook"));
+
+    Ok(())
+}
+
+/// `--profile` records the spans of the run in the Chrome trace format.
+#[test]
+fn run_profile() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: success
+                name: success
+                entry: echo
+                language: system
+    "});
+    context.git_add(".");
+
+    context
+        .run()
+        .arg("--profile")
+        .arg("trace.json")
+        .assert()
+        .success();
+
+    let trace = fs_err::read_to_string(context.work_dir().join("trace.json"))?;
+    let events: Vec<serde_json::Value> = serde_json::from_str(&trace)?;
+    assert!(events.iter().any(|event| event["name"] == "run_hook"));
+    assert!(events.iter().any(|event| event["name"] == "batch"));
+
+    Ok(())
+}
+
+/// Test `prek run --output custom:<name>` streams hook results to an external reporter.
+#[cfg(unix)]
+#[test]
+fn run_custom_output_reporter() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: success
+                name: success
+                entry: echo
+                language: system
+              - id: fail
+                name: fail
+                entry: fail
+                language: fail
+    "});
+    context.git_add(".");
+
+    let bin_dir = context.home_dir().child("bin");
+    bin_dir.create_dir_all()?;
+    let reporter = bin_dir.child("prek-reporter-collect");
+    reporter.write_str("#!/bin/sh\ncat > events.jsonl\n")?;
+    fs_err::set_permissions(&reporter, std::fs::Permissions::from_mode(0o755))?;
+
+    let path = std::env::join_paths(std::iter::once(bin_dir.to_path_buf()).chain(
+        std::env::split_paths(&EnvVars::var_os(EnvVars::PATH).unwrap_or_default()),
+    ))?;
+
+    context
+        .run()
+        .arg("--output")
+        .arg("custom:collect")
+        .env(EnvVars::PATH, path)
+        .assert()
+        .failure();
+
+    let events = context.read("events.jsonl");
+    let events: Vec<serde_json::Value> = events
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0]["event"], "hook_result");
+    assert_eq!(events[0]["id"], "success");
+    assert_eq!(events[0]["status"], "passed");
+    assert_eq!(events[1]["id"], "fail");
+    assert_eq!(events[1]["status"], "failed");
+    assert_eq!(events[2]["event"], "run_complete");
+    assert_eq!(events[2]["failed"], 1);
+
+    // Unknown reporters are reported as errors.
+    cmd_snapshot!(context.filters(), context.run().arg("--output").arg("custom:missing"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Reporter `missing` not found, `prek-reporter-missing` is not in PATH
+      caused by: cannot find binary path
+    ");
+
+    Ok(())
+}
+
+/// Test `prek run --output json:<path>` writes a report of the run.
+#[test]
+fn run_json_output() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: success
+                name: success
+                entry: echo
+                language: system
+              - id: fail
+                name: fail
+                entry: fail
+                language: fail
+    "});
+    context.git_add(".");
+
+    context
+        .run()
+        .arg("--output")
+        .arg("json:report.json")
+        .assert()
+        .failure();
+
+    let report: serde_json::Value = serde_json::from_str(&context.read("report.json"))?;
+    assert_eq!(report["summary"]["success"], false);
+    assert_eq!(report["summary"]["passed"], 1);
+    assert_eq!(report["summary"]["failed"], 1);
+    assert_eq!(report["hooks"][0]["full_id"], ".:success");
+    assert_eq!(report["hooks"][1]["full_id"], ".:fail");
+    assert_eq!(report["hooks"][1]["exit_code"], 1);
+
+    Ok(())
+}
+
+/// Hooks with `pass_filenames: false` run once without filenames, but only if files match.
+/// Output that is not valid UTF-8 is decoded lossily for display, kept raw in the log file,
+/// and encoded as base64 in the JSON report.
+#[test]
+fn non_utf8_output() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: latin1
+                name: latin1
+                entry: python3 -c 'import sys; sys.stdout.buffer.write(b"caf\xe9\n"); sys.exit(1)'
+                language: system
+                pass_filenames: false
+                log_file: hook.log
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--output").arg("json:report.json"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    latin1...................................................................Failed
+    - hook id: latin1
+    - exit code: 1
+      caf�
+
+    ----- stderr -----
+    ");
+
+    let report: serde_json::Value = serde_json::from_str(&context.read("report.json"))?;
+    assert_eq!(report["hooks"][0]["output"], "caf\u{fffd}");
+    assert_eq!(report["hooks"][0]["output_base64"], "Y2Fm6Q==");
+
+    let log = fs_err::read(context.work_dir().join("hook.log"))?;
+    assert_eq!(log, b"caf\xe9\n");
+
+    Ok(())
+}
+
+#[test]
+fn pass_filenames_false() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
 
     let cwd = context.work_dir();
-    cwd.child("dir1").create_dir_all()?;
-    cwd.child("dir1/file.txt").write_str("Hello, world!")?;
-    cwd.child("dir2").create_dir_all()?;
-    cwd.child("dir2/file.txt").write_str("Hello, world!")?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: whole-project
+                name: whole-project
+                entry: echo whole
+                language: system
+                pass_filenames: false
+                verbose: true
+              - id: rust-only
+                name: rust-only
+                entry: echo rust
+                language: system
+                files: \.rs$
+                pass_filenames: false
+    "});
+    cwd.child("a.txt").write_str("a")?;
+    cwd.child("b.txt").write_str("b")?;
     context.git_add(".");
 
-    // one `--directory`
-    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("dir1"), @r#"
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    directory................................................................Passed
-    - hook id: directory
+    whole-project............................................................Passed
+    - hook id: whole-project
     - duration: [TIME]
-      dir1/file.txt
+      whole
+    rust-only............................................(no files to check)Skipped
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--dry-run"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    whole-project............................................................Dry Run
+    - hook id: whole-project
+    - duration: [TIME]
+      `whole-project` would be run once without filenames
+    rust-only............................................(no files to check)Skipped
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Test `prek run --rebase-exec` only checks the files changed by `HEAD`.
+#[test]
+fn rebase_exec() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+
+    let cwd = context.work_dir();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: forbidden
+                name: forbidden
+                entry: forbidden file
+                language: fail
+                files: ^forbidden
+    "});
+    cwd.child("forbidden-a.txt").write_str("a")?;
+    context.git_add(".");
+    context.git_commit("first");
+
+    cwd.child("ok.txt").write_str("ok")?;
+    context.git_add(".");
+    context.git_commit("second");
+
+    // Only `ok.txt` is checked, passing hooks print nothing.
+    cmd_snapshot!(context.filters(), context.run().arg("--rebase-exec"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+
+    // Passing commits are cached.
+    cmd_snapshot!(context.filters(), context.run().arg("--rebase-exec"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+
+    // A different config is not served from the cache, even if it's not in the tree.
+    context
+        .home_dir()
+        .child("other.yaml")
+        .write_str(indoc::indoc! {r"
+            repos:
+              - repo: local
+                hooks:
+                  - id: no-ok
+                    name: no-ok
+                    entry: ok file
+                    language: fail
+                    files: ^ok
+        "})?;
+    cmd_snapshot!(context.filters(), context.run().arg("--rebase-exec").arg("-c").arg(context.home_dir().child("other.yaml").path()), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    no-ok....................................................................Failed
+    - hook id: no-ok
+    - exit code: 1
+      ok file
+
+      ok.txt
+
+    ----- stderr -----
+    ");
+
+    cwd.child("forbidden-c.txt").write_str("c")?;
+    context.git_add(".");
+    context.git_commit("third");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--rebase-exec"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    forbidden................................................................Failed
+    - hook id: forbidden
+    - exit code: 1
+      forbidden file
+
+      forbidden-c.txt
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// `always_run` hooks run even if no files match, without passing any filenames.
+#[test]
+fn always_run_without_matching_files() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.work_dir();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: always
+                name: always
+                entry: echo files
+                language: system
+                files: \.rs$
+                always_run: true
+                verbose: true
+              - id: always-matching
+                name: always-matching
+                entry: echo files
+                language: system
+                files: \.txt$
+                always_run: true
+                verbose: true
+              - id: not-always
+                name: not-always
+                entry: echo files
+                language: system
+                files: \.rs$
+    "});
+    cwd.child("a.txt").write_str("a")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    always...................................................................Passed
+    - hook id: always
+    - duration: [TIME]
+      files
+    always-matching..........................................................Passed
+    - hook id: always-matching
+    - duration: [TIME]
+      files a.txt
+    not-always...........................................(no files to check)Skipped
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Passing hooks with `verbose: true` print their output, other passing hooks stay silent.
+#[test]
+fn hook_verbose() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: coverage
+                name: coverage
+                entry: echo coverage is 100%
+                language: system
+                pass_filenames: false
+                verbose: true
+              - id: silent
+                name: silent
+                entry: echo not shown
+                language: system
+                pass_filenames: false
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    coverage.................................................................Passed
+    - hook id: coverage
+    - duration: [TIME]
+      coverage is 100%
+    silent...................................................................Passed
+
+    ----- stderr -----
+    ");
+}
+
+/// Settings in the `PREK_POLICY_FILE` policy file override the repository config.
+#[test]
+fn policy_file() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        fail_fast: true
+        repos:
+          - repo: local
+            hooks:
+              - id: fail-1
+                name: fail-1
+                entry: fail-1
+                language: fail
+              - id: fail-2
+                name: fail-2
+                entry: fail-2
+                language: fail
+                fail_fast: true
+    "});
+    context.git_add(".");
+
+    let policy = context.home_dir().child("policy.yaml");
+    policy.write_str("fail_fast: false\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().env(EnvVars::PREK_POLICY_FILE, &*policy), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fail-1...................................................................Failed
+    - hook id: fail-1
+    - exit code: 1
+      fail-1
+
+      .pre-commit-config.yaml
+    fail-2...................................................................Failed
+    - hook id: fail-2
+    - exit code: 1
+      fail-2
+
+      .pre-commit-config.yaml
+
+    ----- stderr -----
+    ");
+
+    policy.write_str("fail_fast: false\nsandbox: true\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().env(EnvVars::PREK_POLICY_FILE, &*policy), @r"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to parse policy file `[HOME]/policy.yaml`
+      caused by: unknown field `sandbox`, expected one of `fail_fast`, `builtin_fast_path`, `required_config`, `require_sandbox` at line 2 column 1
+    ");
+
+    Ok(())
+}
+
+/// `require_sandbox: true` in the policy file runs every hook in the sandbox.
+#[cfg(target_os = "linux")]
+#[test]
+fn policy_require_sandbox() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: hello
+                name: hello
+                entry: echo hello
+                language: system
+                pass_filenames: false
+                verbose: true
+    "});
+    context.git_add(".");
+
+    // A stand-in for bubblewrap that only runs the command after `--`.
+    let bin_dir = context.home_dir().child("bin");
+    bin_dir.create_dir_all()?;
+    let bwrap = bin_dir.child("bwrap");
+    bwrap.write_str(indoc::indoc! {r#"
+        #!/bin/sh
+        echo sandboxed
+        while [ "$1" != "--" ]; do shift; done
+        shift
+        exec "$@"
+    "#})?;
+    fs_err::set_permissions(&bwrap, std::fs::Permissions::from_mode(0o755))?;
+    let path = std::env::join_paths(std::iter::once(bin_dir.to_path_buf()).chain(
+        std::env::split_paths(&EnvVars::var_os(EnvVars::PATH).unwrap_or_default()),
+    ))?;
+
+    let policy = context.home_dir().child("policy.yaml");
+    policy.write_str("require_sandbox: true\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().env(EnvVars::PATH, &path).env(EnvVars::PREK_POLICY_FILE, &*policy), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    hello....................................................................Passed
+    - hook id: hello
+    - duration: [TIME]
+      sandboxed
+      hello
+
+    ----- stderr -----
+    ");
+
+    // Without the policy, the hook runs outside of the sandbox.
+    cmd_snapshot!(context.filters(), context.run().env(EnvVars::PATH, &path), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    hello....................................................................Passed
+    - hook id: hello
+    - duration: [TIME]
+      hello
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Hooks of the `PREK_REQUIRED_CONFIG` config run in every project and cannot be skipped.
+#[test]
+fn required_config() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: project-hook
+                name: project-hook
+                entry: echo project
+                language: system
+                pass_filenames: false
+    "});
+    context.git_add(".");
 
-    ----- stderr -----
-    "#);
+    let required = context.home_dir().child("required.yaml");
+    required.write_str(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: required-hook
+                name: required-hook
+                entry: echo required
+                language: system
+                pass_filenames: false
+                verbose: true
+    "})?;
 
-    // repeated `--directory`
-    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("dir1").arg("--directory").arg("dir1"), @r#"
+    cmd_snapshot!(context.filters(), context.run().env(EnvVars::PREK_REQUIRED_CONFIG, &*required), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    directory................................................................Passed
-    - hook id: directory
+    project-hook.............................................................Passed
+    required-hook............................................................Passed
+    - hook id: required-hook
     - duration: [TIME]
-      dir1/file.txt
+      required
 
     ----- stderr -----
-    "#);
+    ");
 
-    // multiple `--directory`
-    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("dir1").arg("--directory").arg("dir2"), @r#"
+    cmd_snapshot!(context.filters(), context.run()
+        .env(EnvVars::PREK_REQUIRED_CONFIG, &*required)
+        .env(EnvVars::SKIP, "project-hook,required-hook"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    directory................................................................Passed
-    - hook id: directory
+    project-hook............................................................Skipped
+    required-hook............................................................Passed
+    - hook id: required-hook
     - duration: [TIME]
-      dir2/file.txt dir1/file.txt
+      required
 
     ----- stderr -----
-    "#);
+    warning: Hook `required-hook` is required and cannot be skipped
+    ");
 
-    // non-existing directory
-    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("non-existing-dir"), @r#"
+    Ok(())
+}
+
+/// `--isolated` ignores the user config, the policy file and environment variable overrides.
+#[test]
+fn isolated() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: project-hook
+                name: project-hook
+                entry: echo project
+                language: system
+                pass_filenames: false
+    "});
+    context.git_add(".");
+
+    context
+        .config_dir()
+        .child("prek/config.toml")
+        .write_str("show-diff = true\n")?;
+    let required = context.home_dir().child("required.yaml");
+    required.write_str(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: required-hook
+                name: required-hook
+                entry: echo required
+                language: system
+                pass_filenames: false
+    "})?;
+
+    cmd_snapshot!(context.filters(), context.run()
+        .arg("--isolated")
+        .env(EnvVars::PREK_REQUIRED_CONFIG, &*required)
+        .env(EnvVars::SKIP, "project-hook"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    directory............................................(no files to check)Skipped
+    project-hook.............................................................Passed
 
     ----- stderr -----
-    "#);
+    ");
 
-    // `--directory` with `--files`
-    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("dir1").arg("--files").arg("dir1/file.txt"), @r#"
-    success: true
-    exit_code: 0
+    Ok(())
+}
+
+/// Test `prek run --workspace <list-file>` runs hooks in every listed repository.
+#[test]
+fn run_workspace_list_file() -> Result<()> {
+    let context = TestContext::new();
+    let cwd = context.work_dir();
+
+    for (repo, entry, language) in [("repo-a", "echo", "system"), ("repo-b", "fail", "fail")] {
+        let dir = cwd.child(repo);
+        dir.create_dir_all()?;
+        Command::new("git")
+            .arg("init")
+            .current_dir(&dir)
+            .assert()
+            .success();
+        dir.child(CONFIG_FILE).write_str(&indoc::formatdoc! {r"
+            repos:
+              - repo: local
+                hooks:
+                  - id: {entry}
+                    name: {entry}
+                    entry: {entry}
+                    language: {language}
+        "})?;
+        Command::new("git")
+            .arg("add")
+            .arg(".")
+            .current_dir(&dir)
+            .assert()
+            .success();
+    }
+    cwd.child("repos.txt")
+        .write_str("# Repositories to audit\nrepo-a\n\nrepo-b\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--workspace").arg("repos.txt").arg("--output").arg("json:report.json"), @r"
+    success: false
+    exit_code: 1
     ----- stdout -----
-    directory................................................................Passed
-    - hook id: directory
-    - duration: [TIME]
-      dir1/file.txt
+    Running hooks in `repo-a`:
+    echo.....................................................................Passed
+    Running hooks in `repo-b`:
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+      fail
+
+      .pre-commit-config.yaml
+    Summary:
+      Passed repo-a
+      Failed repo-b
 
     ----- stderr -----
-    "#);
-    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("dir1").arg("--files").arg("dir2/file.txt"), @r#"
+    ");
+
+    let report: serde_json::Value = serde_json::from_str(&context.read("report.json"))?;
+    assert_eq!(report["repos"]["repo-a"]["summary"]["success"], true);
+    assert_eq!(report["repos"]["repo-b"]["summary"]["success"], false);
+
+    Ok(())
+}
+
+/// Test `prek run --workspace <list-file> --output custom:<name>` reports the results of every
+/// repository.
+#[cfg(unix)]
+#[test]
+fn run_workspace_custom_output() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let context = TestContext::new();
+    let cwd = context.work_dir();
+
+    for repo in ["repo-a", "repo-b"] {
+        let dir = cwd.child(repo);
+        dir.create_dir_all()?;
+        Command::new("git")
+            .arg("init")
+            .current_dir(&dir)
+            .assert()
+            .success();
+        dir.child(CONFIG_FILE).write_str(indoc::indoc! {r"
+            repos:
+              - repo: local
+                hooks:
+                  - id: echo
+                    name: echo
+                    entry: echo
+                    language: system
+        "})?;
+        Command::new("git")
+            .arg("add")
+            .arg(".")
+            .current_dir(&dir)
+            .assert()
+            .success();
+    }
+    cwd.child("repos.txt").write_str("repo-a\nrepo-b\n")?;
+
+    let bin_dir = context.home_dir().child("bin");
+    bin_dir.create_dir_all()?;
+    let reporter = bin_dir.child("prek-reporter-collect");
+    reporter.write_str("#!/bin/sh\ncat > events.jsonl\n")?;
+    fs_err::set_permissions(&reporter, std::fs::Permissions::from_mode(0o755))?;
+
+    let path = std::env::join_paths(std::iter::once(bin_dir.to_path_buf()).chain(
+        std::env::split_paths(&EnvVars::var_os(EnvVars::PATH).unwrap_or_default()),
+    ))?;
+
+    context
+        .run()
+        .arg("--workspace")
+        .arg("repos.txt")
+        .arg("--output")
+        .arg("custom:collect")
+        .env(EnvVars::PATH, path)
+        .assert()
+        .success();
+
+    for repo in ["repo-a", "repo-b"] {
+        let events = context.read(format!("{repo}/events.jsonl"));
+        let last: serde_json::Value =
+            serde_json::from_str(events.lines().last().expect("has events"))?;
+        assert_eq!(last["event"], "run_complete");
+    }
+
+    Ok(())
+}
+
+/// Hooks sharing the same id can be selected by their `alias`.
+#[test]
+fn select_by_alias() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: mypy
+                name: mypy strict
+                alias: mypy-strict
+                entry: echo --strict
+                language: system
+                pass_filenames: false
+                verbose: true
+              - id: mypy
+                name: mypy loose
+                alias: mypy-loose
+                entry: echo --loose
+                language: system
+                pass_filenames: false
+                verbose: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("mypy-strict"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    directory................................................................Passed
-    - hook id: directory
+    mypy strict..............................................................Passed
+    - hook id: mypy
     - duration: [TIME]
-      dir2/file.txt dir1/file.txt
+      --strict
 
     ----- stderr -----
-    "#);
+    ");
 
-    // run `--directory` inside a subdirectory
-    cmd_snapshot!(context.filters(), context.run().current_dir(cwd.join("dir1")).arg("--directory").arg("."), @r#"
+    cmd_snapshot!(context.filters(), context.run().arg("--skip").arg("mypy-strict"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    directory................................................................Passed
-    - hook id: directory
+    mypy loose...............................................................Passed
+    - hook id: mypy
     - duration: [TIME]
-      dir1/file.txt
+      --loose
 
     ----- stderr -----
-    "#);
+    ");
 
-    cmd_snapshot!(context.filters(), context.run().arg("--cd").arg("dir1").arg("--directory").arg("."), @r#"
+    // The hook id still selects all variants.
+    cmd_snapshot!(context.filters(), context.run().arg("mypy"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    directory................................................................Passed
-    - hook id: directory
+    mypy strict..............................................................Passed
+    - hook id: mypy
     - duration: [TIME]
-      dir1/file.txt
+      --strict
+    mypy loose...............................................................Passed
+    - hook id: mypy
+    - duration: [TIME]
+      --loose
 
     ----- stderr -----
-    "#);
-
-    Ok(())
+    ");
 }
 
-/// Test `minimum_prek_version` option.
+/// Run hooks again after they modified files with `--fix-and-verify`.
 #[test]
-fn minimum_prek_version() {
+fn fix_and_verify() -> Result<()> {
     let context = TestContext::new();
     context.init_project();
-    context.write_pre_commit_config(indoc::indoc! {r"
-        minimum_prek_version: 10.0.0
+    context.disable_auto_crlf();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
         repos:
           - repo: local
             hooks:
-              - id: directory
-                name: directory
+              - id: fix-whitespace
+                name: fix-whitespace
                 language: system
-                entry: echo
-                verbose: true
-    "});
+                entry: python3 -c "import sys, pathlib; [pathlib.Path(f).write_text(pathlib.Path(f).read_text().rstrip() + '\n') for f in sys.argv[1:]]"
+                files: \.txt$
+    "#});
+    context
+        .work_dir()
+        .child("file.txt")
+        .write_str("Hello, world!  \n")?;
     context.git_add(".");
 
-    let filters = context
-        .filters()
-        .into_iter()
-        .chain([(
-            r"current version `\d+\.\d+\.\d+(?:-[0-9A-Za-z]+(?:\.[0-9A-Za-z]+)*)?`",
-            "current version `[CURRENT_VERSION]`",
-        )])
-        .collect::<Vec<_>>();
-
-    cmd_snapshot!(filters, context.run(), @r#"
-    success: false
-    exit_code: 2
+    cmd_snapshot!(context.filters(), context.run().arg("--fix-and-verify"), @r"
+    success: true
+    exit_code: 0
     ----- stdout -----
+    fix-whitespace...........................................................Failed
+    - hook id: fix-whitespace
+    - files were modified by this hook
+
+    Files were modified by hooks, running hooks again to verify the fixes:
+    fix-whitespace...........................................................Passed
 
     ----- stderr -----
-    error: Failed to parse `.pre-commit-config.yaml`
-      caused by: Required minimum prek version `10.0.0` is greater than current version `[CURRENT_VERSION]`. Please consider updating prek.
-    "#);
+    ");
+
+    assert_eq!(context.read("file.txt"), "Hello, world!\n");
+
+    Ok(())
 }
 
-/// Run hooks that would echo color.
+/// Stage files fixed by hooks and run hooks again with `auto_stage_fixes` or `--fix-and-retry`.
 #[test]
-#[cfg(not(windows))]
-fn color() -> Result<()> {
+fn fix_and_retry() -> Result<()> {
     let context = TestContext::new();
     context.init_project();
-    context.write_pre_commit_config(indoc::indoc! {r"
-      repos:
-        - repo: local
-          hooks:
-            - id: color
-              name: color
-              language: python
-              entry: python ./color.py
-              verbose: true
-              pass_filenames: false
-  "});
-
-    let script = indoc::indoc! {r"
-      import sys
-      if sys.stdout.isatty():
-          print('\033[1;32mHello, world!\033[0m')
-      else:
-          print('Hello, world!')
-  "};
-    context.work_dir().child("color.py").write_str(script)?;
+    context.disable_auto_crlf();
 
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        auto_stage_fixes: true
+        repos:
+          - repo: local
+            hooks:
+              - id: fix-whitespace
+                name: fix-whitespace
+                language: system
+                entry: python3 -c "import sys, pathlib; [pathlib.Path(f).write_text(pathlib.Path(f).read_text().rstrip() + '\n') for f in sys.argv[1:]]"
+                files: \.txt$
+    "#});
+    let cwd = context.work_dir();
+    cwd.child("file.txt").write_str("Hello, world!  \n")?;
     context.git_add(".");
 
-    // Run default. In integration tests, we don't have a TTY.
-    // So this prints without color.
-    cmd_snapshot!(context.filters(), context.run(), @r#"
-  success: true
-  exit_code: 0
-  ----- stdout -----
-  color....................................................................Passed
-  - hook id: color
-  - duration: [TIME]
-    Hello, world!
-
-  ----- stderr -----
-  "#);
-
-    // Force color output
-    cmd_snapshot!(context.filters(), context.run().arg("--color=always"), @r#"
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    color....................................................................[42mPassed[49m
-    [2m- hook id: color[0m
-    [2m- duration: [TIME][0m
-    [2m  [1;32mHello, world![0m[0m
+    fix-whitespace...........................................................Failed
+    - hook id: fix-whitespace
+    - files were modified by this hook
+
+    Staged files modified by hooks, running hooks again:
+      - file.txt
+    fix-whitespace...........................................................Passed
 
     ----- stderr -----
-    "#);
+    ");
+
+    // The fix is staged.
+    let output = Command::new("git")
+        .args(["diff", "--name-only"])
+        .current_dir(cwd)
+        .output()?;
+    assert_snapshot!(String::from_utf8_lossy(&output.stdout), @"");
+    assert_eq!(context.read("file.txt"), "Hello, world!\n");
+
+    // Fixes of files with changes that are not staged are not staged.
+    cwd.child("other.txt").write_str("Hello  \n")?;
+    context.git_add("other.txt");
+    cwd.child("other.txt").write_str("Hello  \nworld\n")?;
+
+    let filters: Vec<_> = context
+        .filters()
+        .into_iter()
+        .chain([(r"/\d+-\d+.patch", "/[TIME]-[PID].patch")])
+        .collect();
+
+    cmd_snapshot!(filters, context.run().arg("--fix-and-retry"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fix-whitespace...........................................................Failed
+    - hook id: fix-whitespace
+    - files were modified by this hook
+
+    ----- stderr -----
+    Non-staged changes detected, saving to `[HOME]/patches/[TIME]-[PID].patch`
+    warning: Fixes were not staged, files modified by hooks also have changes that are not staged:
+      - other.txt
+    Failed to apply the patch, rolling back changes
+
+    Restored working tree changes from `[HOME]/patches/[TIME]-[PID].patch`
+    ");
+
+    assert_eq!(context.read("other.txt"), "Hello  \nworld\n");
 
     Ok(())
 }
 
-/// Test running hook whose `entry` is script with shebang on Windows.
+/// Hooks with `cache_results` are not run again on files they passed on.
 #[test]
-fn shebang_script() -> Result<()> {
+fn cache_results() -> Result<()> {
     let context = TestContext::new();
     context.init_project();
 
-    // Create a script with shebang.
-    let script = indoc::indoc! {r"
-        #!/usr/bin/env python
-        import sys
-        print('Hello, world!')
-        sys.exit(0)
-    "};
-    context.work_dir().child("script.py").write_str(script)?;
-
     context.write_pre_commit_config(indoc::indoc! {r"
-      repos:
-        - repo: local
-          hooks:
-            - id: shebang-script
-              name: shebang-script
-              language: python
-              entry: script.py
-              verbose: true
-              pass_filenames: false
-              always_run: true
+        repos:
+          - repo: local
+            hooks:
+              - id: print-files
+                name: print-files
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                files: \.txt$
+                verbose: true
+                cache_results: true
     "});
+    let cwd = context.work_dir();
+    cwd.child("a.txt").write_str("a")?;
+    cwd.child("b.txt").write_str("b")?;
     context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run(), @r#"
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    shebang-script...........................................................Passed
-    - hook id: shebang-script
+    print-files..............................................................Passed
+    - hook id: print-files
     - duration: [TIME]
-      Hello, world!
+      ['a.txt', 'b.txt']
 
     ----- stderr -----
-    "#);
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-files..............................................(passed before)Skipped
+
+    ----- stderr -----
+    ");
+
+    // Only the changed file is checked again.
+    cwd.child("b.txt").write_str("b changed")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['b.txt']
+
+    ----- stderr -----
+    ");
+
+    // Changing the config of a tool checks all files again.
+    cwd.child("pyproject.toml").write_str("[tool.ruff]\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['a.txt', 'b.txt']
+
+    ----- stderr -----
+    ");
 
     Ok(())
 }
 
-/// Test `git commit -a` works without `.git/index.lock exists` error.
+/// `--incremental` only runs on the files changed since the last successful run.
 #[test]
-fn git_commit_a() -> Result<()> {
+fn incremental() -> Result<()> {
     let context = TestContext::new();
     context.init_project();
     context.configure_git_author();
-    context.disable_auto_crlf();
 
     context.write_pre_commit_config(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: echo
-                name: echo
+              - id: print-files
+                name: print-files
                 language: system
-                entry: echo
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                files: \.txt$
                 verbose: true
     "});
-
-    // Create a file and commit it.
     let cwd = context.work_dir();
-    let file = cwd.child("file.txt");
-    file.write_str("Hello, world!\n")?;
+    cwd.child("a.txt").write_str("a")?;
+    cwd.child("b.txt").write_str("b")?;
+    context.git_add(".");
+    context.git_commit("Initial commit");
 
-    cmd_snapshot!(context.filters(), context.install(), @r#"
+    // The first run checks all files.
+    cmd_snapshot!(context.filters(), context.run().arg("--incremental"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    prek installed at `.git/hooks/pre-commit`
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['a.txt', 'b.txt']
 
     ----- stderr -----
-    "#);
-
-    context.git_add(".");
-    context.git_commit("Initial commit");
+    ");
 
-    // Edit the file
-    file.write_str("Hello, world again!\n")?;
+    cwd.child("b.txt").write_str("b changed")?;
+    cwd.child("c.txt").write_str("c")?;
+    context.git_add("c.txt");
 
-    let mut commit = Command::new("git");
-    commit
-        .arg("commit")
-        .arg("-a")
-        .arg("-m")
-        .arg("Update file")
-        .current_dir(cwd);
+    cmd_snapshot!(context.filters(), context.run().arg("--incremental"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['b.txt', 'c.txt']
 
-    let filters = context
-        .filters()
-        .into_iter()
-        .chain([(r"\[master \w{7}\]", r"[master COMMIT]")])
-        .collect::<Vec<_>>();
+    ----- stderr -----
+    ");
 
-    cmd_snapshot!(filters, commit, @r#"
+    cmd_snapshot!(context.filters(), context.run().arg("--incremental"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    [master COMMIT] Update file
-     1 file changed, 1 insertion(+), 1 deletion(-)
+    print-files..........................................(no files to check)Skipped
 
     ----- stderr -----
-    echo.....................................................................Passed
-    - hook id: echo
+    ");
+
+    // Changing the config checks all files again.
+    let config = context.read(".pre-commit-config.yaml");
+    context.write_pre_commit_config(&config.replace("print-files", "list-files"));
+
+    cmd_snapshot!(context.filters(), context.run().arg("--incremental"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    list-files...............................................................Passed
+    - hook id: list-files
     - duration: [TIME]
-      file.txt
-    "#);
+      ['a.txt', 'b.txt', 'c.txt']
+
+    ----- stderr -----
+    ");
 
     Ok(())
 }
 
-fn write_pre_commit_config(path: &Path, hooks: &[(&str, &str)]) -> Result<()> {
-    let mut yaml = String::from(indoc::indoc! {"
+/// Hooks sharing a `concurrency_group` run one at a time.
+#[test]
+fn concurrency_group() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
         repos:
           - repo: local
             hooks:
-    "});
-    for (id, name) in hooks {
-        let hook = textwrap::indent(
-            &indoc::formatdoc! {"
-        - id: {}
-          name: {}
-          entry: echo
-          language: system
-        ", id, name
-            },
-            "      ",
-        );
-        yaml.push_str(&hook);
-    }
+              - id: migrate
+                name: migrate
+                language: system
+                entry: python3 -c 'print("migrating")'
+                pass_filenames: false
+                concurrency_group: database
+                verbose: true
+              - id: seed
+                name: seed
+                language: system
+                entry: python3 -c 'print("seeding")'
+                pass_filenames: false
+                concurrency_group: database
+                verbose: true
+    "#});
+    context.git_add(".");
 
-    std::fs::create_dir_all(path)?;
-    std::fs::write(path.join(CONFIG_FILE), yaml)?;
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    migrate..................................................................Passed
+    - hook id: migrate
+    - duration: [TIME]
+      migrating
+    seed.....................................................................Passed
+    - hook id: seed
+    - duration: [TIME]
+      seeding
 
-    Ok(())
+    ----- stderr -----
+    "#);
 }
 
-#[cfg(unix)]
+/// Files outside a sparse checkout are skipped, or fail the run with `--fail-on-sparse`.
 #[test]
-fn selectors_completion() -> Result<()> {
+fn sparse_checkout() -> Result<()> {
     let context = TestContext::new();
-    let cwd = context.work_dir();
     context.init_project();
+    context.configure_git_author();
 
-    // Root project with one hook
-    write_pre_commit_config(cwd, &[("root-hook", "Root Hook")])?;
+    let cwd = context.work_dir();
+    cwd.child("src/main.txt").write_str("Hello, world!\n")?;
+    cwd.child("docs/guide.txt").write_str("Hello, world!\n")?;
+    cwd.child("docs/api.txt").write_str("Hello, world!\n")?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: print-files
+                name: print-files
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                files: \.txt$
+                verbose: true
+    "});
+    context.git_add(".");
+    context.git_commit("Initial commit");
 
-    // Nested project at app/ with one hook
-    let app = cwd.join("app");
-    write_pre_commit_config(&app, &[("app-hook", "App Hook")])?;
+    Command::new("git")
+        .args(["sparse-checkout", "set", "src"])
+        .current_dir(cwd)
+        .assert()
+        .success();
+    cwd.child("docs").assert(predicates::path::missing());
 
-    // Deeper nested project at app/lib/ with one hook
-    let app_lib = app.join("lib");
-    write_pre_commit_config(&app_lib, &[("lib-hook", "Lib Hook")])?;
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['src/main.txt']
 
-    // Unrelated non-project dir should not appear in subdir suggestions
-    cwd.child("scratch").create_dir_all()?;
+    ----- stderr -----
+    ");
 
-    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg(""), @r"
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files").arg("--fail-on-sparse"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Files are not materialized by the sparse checkout, add them to the sparse checkout to run hooks on them:
+      - docs/api.txt
+      - docs/guide.txt
+    ");
+
+    Ok(())
+}
+
+/// Git LFS pointers are never passed to hooks, `--skip-lfs-files` skips all Git LFS files, and
+/// non-staged changes of Git LFS files are restored as is.
+#[test]
+fn lfs_files() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.work_dir();
+    cwd.child(".gitattributes")
+        .write_str("*.bin filter=lfs diff=lfs merge=lfs -text\n")?;
+    cwd.child("main.txt").write_str("Hello, world!\n")?;
+    cwd.child("image.bin")
+        .write_binary(&[0x89, b'P', b'N', b'G', 0x00, 0x01])?;
+    cwd.child("pointer.bin").write_str(indoc::indoc! {"
+        version https://git-lfs.github.com/spec/v1
+        oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393
+        size 12345
+    "})?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: print-files
+                name: print-files
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                files: \.(bin|txt)$
+                verbose: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    install	Install the prek git hook
-    install-hooks	Create hook environments for all hooks used in the config file
-    run	Run hooks
-    list	List available hooks
-    uninstall	Uninstall the prek git hook
-    validate-config	Validate `.pre-commit-config.yaml` files
-    validate-manifest	Validate `.pre-commit-hooks.yaml` files
-    sample-config	Produce a sample `.pre-commit-config.yaml` file
-    auto-update	Auto-update pre-commit config to the latest repos' versions
-    cache	Manage the prek cache
-    init-template-dir	Install hook script in a directory intended for use with `git config init.templateDir`
-    try-repo	Try the pre-commit hooks in the current repo
-    self	`prek` self management
-    app/
-    app:
-    app-hook	App Hook
-    lib-hook	Lib Hook
-    root-hook	Root Hook
-    --skip	Skip the specified hooks or projects
-    --all-files	Run on all files in the repo
-    --files	Specific filenames to run hooks on
-    --directory	Run hooks on all files in the specified directories
-    --from-ref	The original ref in a `<from_ref>...<to_ref>` diff expression. Files changed in this diff will be run through the hooks
-    --to-ref	The destination ref in a `from_ref...to_ref` diff expression. Defaults to `HEAD` if `from_ref` is specified
-    --last-commit	Run hooks against the last commit. Equivalent to `--from-ref HEAD~1 --to-ref HEAD`
-    --hook-stage	The stage during which the hook is fired
-    --show-diff-on-failure	When hooks fail, run `git diff` directly afterward
-    --dry-run	Do not run the hooks, but print the hooks that would have been run
-    --config	Path to alternate config file
-    --cd	Change to directory before running
-    --color	Whether to use color in output
-    --refresh	Refresh all cached data
-    --help	Display the concise help for this command
-    --no-progress	Hide all progress outputs
-    --quiet	Use quiet output
-    --verbose	Use verbose output
-    --log-file	Write trace logs to the specified file. If not specified, trace logs will be written to `$PREK_HOME/prek.log`
-    --version	Display the prek version
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['image.bin', 'main.txt']
 
     ----- stderr -----
+    warning: Skipped Git LFS files whose content is not downloaded, run `git lfs pull` to run hooks on them: `pointer.bin`
     ");
 
-    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("ap"), @r"
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files").arg("--skip-lfs-files"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    app/
-    app:
-    app-hook	App Hook
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['main.txt']
 
     ----- stderr -----
+    warning: Skipped Git LFS files: `image.bin`, `pointer.bin`
     ");
 
-    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app:"), @r"
+    // Non-staged changes of Git LFS files are saved and restored byte for byte.
+    cwd.child("image.bin")
+        .write_binary(&[0x89, b'P', b'N', b'G', 0x00, 0x02, 0x03])?;
+    cwd.child("main.txt").write_str("Hello, world again!\n")?;
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([(r"/\d+-\d+.patch", "/[TIME]-[PID].patch")])
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters, context.run(), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    app:app-hook	App Hook
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['image.bin', 'main.txt']
 
     ----- stderr -----
+    Non-staged changes detected, saving to `[HOME]/patches/[TIME]-[PID].patch`
+    warning: Skipped Git LFS files whose content is not downloaded, run `git lfs pull` to run hooks on them: `pointer.bin`
+
+    Restored working tree changes from `[HOME]/patches/[TIME]-[PID].patch`
     ");
 
-    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app:app"), @r"
+    assert_eq!(
+        fs_err::read(cwd.join("image.bin"))?,
+        [0x89, b'P', b'N', b'G', 0x00, 0x02, 0x03]
+    );
+    assert_eq!(context.read("main.txt"), "Hello, world again!\n");
+    let leftovers = fs_err::read_dir(context.home_dir().join("patches"))?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "lfs"))
+        .count();
+    assert_eq!(leftovers, 0);
+
+    Ok(())
+}
+
+/// File names that aren't valid UTF-8 or contain newlines are passed to hooks as is.
+#[cfg(target_os = "linux")]
+#[test]
+fn non_utf8_filenames() -> Result<()> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.work_dir();
+    // `café.txt` encoded in Latin-1.
+    fs_err::write(
+        cwd.join(OsStr::from_bytes(b"caf\xe9.txt")),
+        "Hello, world!\n",
+    )?;
+    cwd.child("new\nline.txt").write_str("Hello, world!\n")?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: print-files
+                name: print-files
+                language: system
+                entry: python3 -c 'import os, sys; print(sorted(os.fsencode(f) for f in sys.argv[1:]))'
+                files: ^caf.\.txt$|line
+                verbose: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    app:app-hook	App Hook
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      [b'caf/xe9.txt', b'new/nline.txt']
 
     ----- stderr -----
     ");
 
-    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app/"), @r"
+    Ok(())
+}
+
+/// Symlinks only match the `symlink` type by default, `follow_symlinks` matches them by the type
+/// of their target, and `skip_symlinks` never passes them to the hook.
+#[cfg(unix)]
+#[test]
+fn symlinks() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.work_dir();
+    cwd.child("real.py").write_str("print('Hello')\n")?;
+    cwd.child("link.py").symlink_to_file("real.py")?;
+    cwd.child("broken.py").symlink_to_file("missing.py")?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: default
+                name: default
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                verbose: true
+              - id: symlinks
+                name: symlinks
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                types: []
+                types_or: [file, symlink]
+                verbose: true
+              - id: follow-symlinks
+                name: follow-symlinks
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                types: [python]
+                follow_symlinks: true
+                verbose: true
+              - id: skip-symlinks
+                name: skip-symlinks
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                types: []
+                types_or: [file, symlink]
+                skip_symlinks: true
+                verbose: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    app/lib/
-    app/lib:
+    default..................................................................Passed
+    - hook id: default
+    - duration: [TIME]
+      ['.pre-commit-config.yaml', 'real.py']
+    symlinks.................................................................Passed
+    - hook id: symlinks
+    - duration: [TIME]
+      ['.pre-commit-config.yaml', 'broken.py', 'link.py', 'real.py']
+    follow-symlinks..........................................................Passed
+    - hook id: follow-symlinks
+    - duration: [TIME]
+      ['link.py', 'real.py']
+    skip-symlinks............................................................Passed
+    - hook id: skip-symlinks
+    - duration: [TIME]
+      ['.pre-commit-config.yaml', 'real.py']
 
     ----- stderr -----
     ");
-    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app/li"), @r"
+
+    Ok(())
+}
+
+/// `--failed` only runs the hooks that failed in the last run, on the files they failed on.
+#[test]
+fn run_failed() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: print-files
+                name: print-files
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                verbose: true
+              - id: no-todo
+                name: no-todo
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:])); sys.exit(any("TODO" in open(f).read() for f in sys.argv[1:]))'
+                files: \.txt$
+    "#});
+    let cwd = context.work_dir();
+    cwd.child("a.txt").write_str("TODO")?;
+    cwd.child("b.txt").write_str("done")?;
+    cwd.child("c.py").write_str("")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--failed"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    app/lib/
-    app/lib:
+    No hooks failed in the last run
 
     ----- stderr -----
     ");
 
-    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app/lib:"), @r"
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    print-files..............................................................Passed
+    - hook id: print-files
+    - duration: [TIME]
+      ['.pre-commit-config.yaml', 'a.txt', 'b.txt', 'c.py']
+    no-todo..................................................................Failed
+    - hook id: no-todo
+    - exit code: 1
+      ['a.txt', 'b.txt']
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--failed"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    no-todo..................................................................Failed
+    - hook id: no-todo
+    - exit code: 1
+      ['a.txt', 'b.txt']
+
+    ----- stderr -----
+    ");
+
+    cwd.child("a.txt").write_str("done")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--failed"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    app/lib:lib-hook	Lib Hook
+    no-todo..................................................................Passed
 
     ----- stderr -----
     ");
 
-    cmd_snapshot!(context.filters(), context.run().env("COMPLETE", "fish").arg("--").arg("prek").arg("app/lib/"), @r"
+    cmd_snapshot!(context.filters(), context.run().arg("--failed"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    app/lib/
+    No hooks failed in the last run
 
     ----- stderr -----
     ");
@@ -1877,402 +6524,388 @@ fn selectors_completion() -> Result<()> {
     Ok(())
 }
 
-/// Test reusing hook environments only when dependencies are exactly same. (ignore order)
+/// `--interactive` needs a terminal to show the checklist of hooks.
 #[test]
-fn reuse_env() -> Result<()> {
+fn run_interactive_requires_terminal() {
     let context = TestContext::new();
     context.init_project();
-
     context.write_pre_commit_config(indoc::indoc! {r"
-    repos:
-      - repo: https://github.com/PyCQA/flake8
-        rev: 7.1.1
-        hooks:
-          - id: flake8
-            additional_dependencies: [flake8-errmsg]
+        repos:
+          - repo: local
+            hooks:
+              - id: echo
+                name: echo
+                language: system
+                entry: echo
     "});
-
-    context
-        .work_dir()
-        .child("err.py")
-        .write_str("raise ValueError('error')\n")?;
     context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run(), @r"
+    cmd_snapshot!(context.filters(), context.run().arg("--interactive"), @r"
     success: false
-    exit_code: 1
-    ----- stdout -----
-    flake8...................................................................Failed
-    - hook id: flake8
-    - exit code: 1
-      err.py:1:1: EM101 Exceptions must not use a string literal; assign to a variable first
-
-    ----- stderr -----
-    ");
-
-    // Remove dependencies, so the environment should not be reused.
-    context.write_pre_commit_config(indoc::indoc! {r"
-    repos:
-      - repo: https://github.com/PyCQA/flake8
-        rev: 7.1.1
-        hooks:
-          - id: flake8
-    "});
-    context.git_add(".");
-
-    cmd_snapshot!(context.filters(), context.run(), @r"
-    success: true
-    exit_code: 0
+    exit_code: 2
     ----- stdout -----
-    flake8...................................................................Passed
 
     ----- stderr -----
+    error: `--interactive` requires a terminal
     ");
-
-    // There should be two hook environments.
-    assert_eq!(context.home_dir().child("hooks").read_dir()?.count(), 2);
-
-    Ok(())
 }
 
+/// `stream_output` and `--stream` print the output of hooks while they run.
 #[test]
-fn dry_run() {
+fn run_stream_output() {
     let context = TestContext::new();
     context.init_project();
-    context.write_pre_commit_config(indoc::indoc! {r"
+    context.write_pre_commit_config(indoc::indoc! {r#"
         repos:
           - repo: local
             hooks:
+              - id: greet
+                name: greet
+                language: system
+                entry: python3 -c 'print("hello"); print("world")'
+                pass_filenames: false
+                stream_output: true
               - id: fail
                 name: fail
-                entry: fail
-                language: fail
-    "});
+                language: system
+                entry: python3 -c 'import sys; print("oops"); sys.exit(1)'
+                pass_filenames: false
+    "#});
     context.git_add(".");
 
-    // Run with `--dry-run`
-    cmd_snapshot!(context.filters(), context.run().arg("--dry-run").arg("-v"), @r"
-    success: true
-    exit_code: 0
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
     ----- stdout -----
-    fail.....................................................................Dry Run
+    [greet] hello
+    [greet] world
+    greet....................................................................Passed
+    fail.....................................................................Failed
     - hook id: fail
-    - duration: [TIME]
-      `fail` would be run on 1 files:
-      - .pre-commit-config.yaml
+    - exit code: 1
+      oops
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--stream"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    [greet] hello
+    [greet] world
+    greet....................................................................Passed
+    [fail] oops
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
 
     ----- stderr -----
     ");
 }
 
-/// Supports reading `pre-commit-config.yml` as well.
+/// Hooks with `tty: true` run in a pseudo-terminal, even if the output of prek is not colored.
+#[cfg(unix)]
 #[test]
-fn alternate_config_file() -> Result<()> {
+fn run_tty() {
     let context = TestContext::new();
     context.init_project();
-
-    context
-        .work_dir()
-        .child(ALT_CONFIG_FILE)
-        .write_str(indoc::indoc! {r#"
+    context.write_pre_commit_config(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: local-python-hook
-                name: local-python-hook
-                language: python
-                entry: python3 -c 'import sys; print("Hello, world!")'
-    "#})?;
+              - id: isatty
+                name: isatty
+                language: system
+                entry: python3 -c 'import sys; print(sys.stdout.isatty())'
+                pass_filenames: false
+                verbose: true
+                tty: true
+    "});
     context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run().arg("-v"), @r"
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    local-python-hook........................................................Passed
-    - hook id: local-python-hook
+    isatty...................................................................Passed
+    - hook id: isatty
     - duration: [TIME]
-      Hello, world!
+      True
 
     ----- stderr -----
     ");
+}
 
-    context
-        .work_dir()
-        .child(CONFIG_FILE)
-        .write_str(indoc::indoc! {r#"
+/// `--porcelain` prints one tab-separated record per hook instead of the human readable output.
+#[test]
+fn run_porcelain() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
         repos:
           - repo: local
             hooks:
-              - id: local-python-hook
-                name: local-python-hook
-                language: python
-                entry: python3 -c 'import sys; print("Hello, world!")'
-    "#})?;
+              - id: pass
+                name: pass
+                language: system
+                entry: python3 -c 'print("ok")'
+              - id: fail
+                name: fail
+                language: system
+                entry: python3 -c 'import sys; sys.exit(1)'
+                pass_filenames: false
+              - id: skip
+                name: skip
+                language: system
+                entry: python3 -c 'print("skip")'
+                files: \.rs$
+    "#});
+    context.work_dir().child("a.txt").write_str("a")?;
     context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run().arg("--refresh").arg("-v"), @r"
-    success: true
-    exit_code: 0
+    let mut filters = context.filters();
+    filters.push((r"\t\d+\.\d{3}\t", "\t[TIME]\t"));
+
+    cmd_snapshot!(filters, context.run().arg("--porcelain"), @r"
+    success: false
+    exit_code: 1
     ----- stdout -----
-    local-python-hook........................................................Passed
-    - hook id: local-python-hook
-    - duration: [TIME]
-      Hello, world!
+    # prek porcelain v1
+    passed	pass	[TIME]	2
+    failed	fail	[TIME]	0
+    skipped	skip	[TIME]	0
 
     ----- stderr -----
-    warning: Both `[TEMP_DIR]/.pre-commit-config.yaml` and `[TEMP_DIR]/.pre-commit-config.yml` exist, using `[TEMP_DIR]/.pre-commit-config.yaml` only
     ");
 
     Ok(())
 }
 
+/// Hooks are told whether prek uses color with the conventional environment variables.
 #[test]
-fn show_diff_on_failure() -> Result<()> {
+fn run_color_env() {
     let context = TestContext::new();
     context.init_project();
-    context.disable_auto_crlf();
-
-    let config = indoc::indoc! {r#"
+    context.write_pre_commit_config(indoc::indoc! {r#"
         repos:
           - repo: local
             hooks:
-              - id: modify
-                name: modify
-                language: python
-                entry: python -c "import sys; open('file.txt', 'a').write('Added line\n')"
+              - id: color
+                name: color
+                language: system
+                entry: python3 -c 'import os; print(*(os.environ.get(k) for k in ("NO_COLOR", "CLICOLOR_FORCE", "FORCE_COLOR")))'
                 pass_filenames: false
-    "#};
-    context.write_pre_commit_config(config);
-    context
-        .work_dir()
-        .child("file.txt")
-        .write_str("Original line\n")?;
+                verbose: true
+    "#});
     context.git_add(".");
 
-    let mut filters = context.filters();
-    filters.push((r"index \w{7}\.\.\w{7} \d{6}", "index [OLD]..[NEW] 100644"));
-
-    // When failed in CI environment
-    cmd_snapshot!(filters.clone(), context.run().env(EnvVars::CI, "1").arg("--show-diff-on-failure").arg("-v"), @r"
-    success: false
-    exit_code: 1
+    cmd_snapshot!(context.filters(), context.run().env("FORCE_COLOR", "1").arg("--color").arg("never"), @r"
+    success: true
+    exit_code: 0
     ----- stdout -----
-    modify...................................................................Failed
-    - hook id: modify
+    color....................................................................Passed
+    - hook id: color
     - duration: [TIME]
-    - files were modified by this hook
-
-    Hint: Some hooks made changes to the files.
-    If you are seeing this message in CI, reproduce locally with: `prek run --all-files`
-    To run prek as part of git workflow, use `prek install` to set up git hooks.
-
-    All changes made by hooks:
-    diff --git a/file.txt b/file.txt
-    index [OLD]..[NEW] 100644
-    --- a/file.txt
-    +++ b/file.txt
-    @@ -1 +1,2 @@
-     Original line
-    +Added line
+      1 None None
 
     ----- stderr -----
     ");
+}
 
-    context
-        .work_dir()
-        .child("file.txt")
-        .write_str("Original line\n")?;
+/// `--durations` prints the slowest hooks once all hooks ran.
+#[test]
+fn run_durations() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: fast
+                name: fast
+                language: system
+                entry: python3 -c 'pass'
+                pass_filenames: false
+              - id: slow
+                name: slow
+                language: system
+                entry: python3 -c 'import time; time.sleep(1)'
+                pass_filenames: false
+    "});
     context.git_add(".");
-    // When failed in non-CI environment
-    cmd_snapshot!(filters.clone(), context.run().env_remove(EnvVars::CI).arg("--show-diff-on-failure").arg("-v"), @r"
-    success: false
-    exit_code: 1
+
+    cmd_snapshot!(context.filters(), context.run().arg("--durations").arg("1"), @r"
+    success: true
+    exit_code: 0
     ----- stdout -----
-    modify...................................................................Failed
-    - hook id: modify
-    - duration: [TIME]
-    - files were modified by this hook
-    All changes made by hooks:
-    diff --git a/file.txt b/file.txt
-    index [OLD]..[NEW] 100644
-    --- a/file.txt
-    +++ b/file.txt
-    @@ -1 +1,2 @@
-     Original line
-    +Added line
+    fast.....................................................................Passed
+    slow.....................................................................Passed
+
+    Slowest hooks:
+         [TIME]  slow
 
     ----- stderr -----
     ");
+}
 
-    // Run in the `app` subproject.
-    let app = context.work_dir().child("app");
-    app.create_dir_all()?;
-    app.child("file.txt").write_str("Original line\n")?;
-    app.child(CONFIG_FILE).write_str(config)?;
+/// Hooks running longer than `timeout_seconds` are killed and reported with their partial output.
+#[test]
+fn run_timeout() {
+    let context = TestContext::new();
+    context.init_project();
 
-    Command::new("git")
-        .arg("add")
-        .arg(".")
-        .current_dir(&app)
-        .assert()
-        .success();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        default_timeout_seconds: 1
+        repos:
+          - repo: local
+            hooks:
+              - id: fast
+                name: fast
+                language: system
+                entry: python3 -c 'pass'
+                pass_filenames: false
+                timeout_seconds: 30
+              - id: hang
+                name: hang
+                language: system
+                entry: python3 -c 'import time; print("waiting", flush=True); time.sleep(60)'
+                pass_filenames: false
+    "#});
+    context.git_add(".");
 
-    cmd_snapshot!(filters.clone(), context.run().env_remove(EnvVars::CI).current_dir(&app).arg("--show-diff-on-failure"), @r"
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: false
     exit_code: 1
     ----- stdout -----
-    modify...................................................................Failed
-    - hook id: modify
-    - files were modified by this hook
-    All changes made by hooks:
-    diff --git a/app/file.txt b/app/file.txt
-    index [OLD]..[NEW] 100644
-    --- a/app/file.txt
-    +++ b/app/file.txt
-    @@ -1 +1,2 @@
-     Original line
-    +Added line
+    fast.....................................................................Passed
+    hang.....................................................................Timed Out
+    - hook id: hang
+    - timed out after [TIME]
+      waiting
 
     ----- stderr -----
     ");
+}
 
+/// Hooks that exceed their `limits` are reported as over the limit.
+#[cfg(unix)]
+#[test]
+fn run_limits() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: spin
+                name: spin
+                language: system
+                entry: python3 -c 'print("spinning", flush=True); [0 for _ in iter(int, 1)]'
+                pass_filenames: false
+                limits:
+                  cpu_seconds: 1
+    "#});
     context.git_add(".");
 
-    // Run in the root
-    // Since we add a new subproject, use `--refresh` to find that.
-    cmd_snapshot!(filters.clone(), context.run().env_remove(EnvVars::CI).arg("--show-diff-on-failure").arg("--refresh"), @r"
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: false
     exit_code: 1
     ----- stdout -----
-    Running hooks for `app`:
-    modify...................................................................Failed
-    - hook id: modify
-    - files were modified by this hook
-
-    Running hooks for `.`:
-    modify...................................................................Failed
-    - hook id: modify
-    - files were modified by this hook
-    All changes made by hooks:
-    diff --git a/app/file.txt b/app/file.txt
-    index [OLD]..[NEW] 100644
-    --- a/app/file.txt
-    +++ b/app/file.txt
-    @@ -1,2 +1,3 @@
-     Original line
-     Added line
-    +Added line
-    diff --git a/file.txt b/file.txt
-    index [OLD]..[NEW] 100644
-    --- a/file.txt
-    +++ b/file.txt
-    @@ -1,2 +1,3 @@
-     Original line
-     Added line
-    +Added line
+    spin.....................................................................Over Limit
+    - hook id: spin
+    - exceeded the CPU time limit of [TIME]
+      spinning
 
     ----- stderr -----
     ");
-
-    Ok(())
 }
 
+/// `limits.processes` is not supported on Unix, it's ignored with a warning.
+#[cfg(unix)]
 #[test]
-fn run_quiet() {
+fn run_limits_processes() {
     let context = TestContext::new();
     context.init_project();
+
     context.write_pre_commit_config(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: success
-                name: success
-                entry: echo
+              - id: echo
+                name: echo
                 language: system
-              - id: fail
-                name: fail
-                entry: fail
-                language: fail
+                entry: echo
+                pass_filenames: false
+                limits:
+                  processes: 1
     "});
     context.git_add(".");
 
-    // Run with `--quiet`, only print failed hooks.
-    cmd_snapshot!(context.filters(), context.run().arg("--quiet"), @r"
-    success: false
-    exit_code: 1
-    ----- stdout -----
-    fail.....................................................................Failed
-    - hook id: fail
-    - exit code: 1
-      fail
-
-      .pre-commit-config.yaml
-
-    ----- stderr -----
-    ");
-
-    // Run with `-qq`, do not print anything.
-    cmd_snapshot!(context.filters(), context.run().arg("-qq"), @r"
-    success: false
-    exit_code: 1
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
     ----- stdout -----
+    echo.....................................................................Passed
 
     ----- stderr -----
+    warning: Hook `echo` sets `limits.processes`, which is only supported on Windows and is ignored
     ");
 }
 
-/// Test `prek run --log-file <file>` flag.
+/// `--timeout` kills the running hook and skips the remaining hooks.
 #[test]
-fn run_log_file() {
+fn run_global_timeout() {
     let context = TestContext::new();
     context.init_project();
-    context.write_pre_commit_config(indoc::indoc! {r"
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
         repos:
           - repo: local
             hooks:
-              - id: fail
-                name: fail
-                entry: fail
-                language: fail
-    "});
+              - id: hang
+                name: hang
+                language: system
+                entry: python3 -c 'import time; print("waiting", flush=True); time.sleep(60)'
+                pass_filenames: false
+              - id: fast
+                name: fast
+                language: system
+                entry: python3 -c 'pass'
+                pass_filenames: false
+    "#});
     context.git_add(".");
 
-    // Run with `--no-log-file`, no `prek.log` is created.
-    cmd_snapshot!(context.filters(), context.run().arg("--no-log-file"), @r"
+    cmd_snapshot!(context.filters(), context.run().arg("--timeout").arg("1s"), @r"
     success: false
-    exit_code: 1
+    exit_code: 124
     ----- stdout -----
-    fail.....................................................................Failed
-    - hook id: fail
-    - exit code: 1
-      fail
-
-      .pre-commit-config.yaml
+    hang.....................................................................Timed Out
+    - hook id: hang
+    - killed at the timeout of the run
+      waiting
+    fast.....................................................(run timed out)Skipped
 
     ----- stderr -----
+    warning: The run timed out, the remaining hooks were skipped
     ");
-    context
-        .home_dir()
-        .child("prek.log")
-        .assert(predicate::path::missing());
+}
 
-    // Write log to `log`.
-    cmd_snapshot!(context.filters(), context.run().arg("--log-file").arg("log"), @r"
+/// `--timeout` rejects durations that don't fit instead of overflowing.
+#[test]
+fn run_timeout_too_long() {
+    let context = TestContext::new();
+    context.init_project();
+
+    cmd_snapshot!(context.filters(), context.run().arg("--timeout").arg("6000000000000000h"), @r"
     success: false
-    exit_code: 1
+    exit_code: 2
     ----- stdout -----
-    fail.....................................................................Failed
-    - hook id: fail
-    - exit code: 1
-      fail
-
-      .pre-commit-config.yaml
 
     ----- stderr -----
+    error: invalid value '6000000000000000h' for '--timeout <DURATION>': duration `6000000000000000h` is too long
+
+    For more information, try '--help'.
     ");
-    context
-        .work_dir()
-        .child("log")
-        .assert(predicate::path::exists());
 }
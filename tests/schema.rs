@@ -0,0 +1,82 @@
+use anyhow::Result;
+
+use crate::common::TestContext;
+
+mod common;
+
+fn schema(context: &TestContext, kind: &str) -> Result<serde_json::Value> {
+    let output = context.command().args(["schema", kind]).output()?;
+    assert!(output.status.success());
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+#[test]
+fn config_schema() -> Result<()> {
+    let context = TestContext::new();
+    let schema = schema(&context, "config")?;
+
+    let mut properties = schema["properties"]
+        .as_object()
+        .expect("config schema has properties")
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    properties.sort_unstable();
+    insta::assert_debug_snapshot!(properties, @r#"
+    [
+        "auto_stage_fixes",
+        "ci",
+        "default_install_hook_types",
+        "default_language_version",
+        "default_stages",
+        "default_timeout_seconds",
+        "exclude",
+        "extends",
+        "fail_fast",
+        "files",
+        "max_file_size",
+        "minimum_pre_commit_version",
+        "minimum_prek_version",
+        "pass_env",
+        "repos",
+        "require_pinned_revs",
+        "skip_binary_files",
+        "skip_lfs_files",
+        "stash_mode",
+        "suppress_warnings",
+    ]
+    "#);
+    assert_eq!(schema["required"], serde_json::json!(["repos"]));
+
+    // Options of hooks are flattened into the hooks of each kind of repo.
+    let defs = schema["$defs"].as_object().expect("config schema has defs");
+    for hook in ["RemoteHook", "ManifestHook", "MetaHook", "BuiltinHook"] {
+        assert!(
+            defs[hook]["properties"]["additional_dependencies"].is_object(),
+            "`{hook}` has hook options"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn manifest_schema() -> Result<()> {
+    let context = TestContext::new();
+    let schema = schema(&context, "manifest")?;
+
+    assert_eq!(schema["type"], "array");
+    assert_eq!(schema["items"]["$ref"], "#/$defs/ManifestHook");
+    let hook = &schema["$defs"]["ManifestHook"];
+    for key in ["id", "name", "entry", "language"] {
+        assert!(
+            hook["required"]
+                .as_array()
+                .is_some_and(|required| required.iter().any(|k| k == key)),
+            "`{key}` is required"
+        );
+    }
+    assert!(hook["properties"]["stages"].is_object());
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use assert_cmd::assert::OutputAssertExt;
+use assert_fs::fixture::{FileWriteStr, PathChild};
+
+use crate::common::{TestContext, cmd_snapshot};
+
+mod common;
+
+#[test]
+fn stats() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: always-pass
+                name: always-pass
+                language: system
+                entry: python3 -c 'import sys; sys.exit(0)'
+              - id: always-fail
+                name: always-fail
+                language: system
+                entry: python3 -c 'import sys; sys.exit(1)'
+              - id: no-todo
+                name: no-todo
+                language: system
+                entry: python3 -c 'import sys; sys.exit(any("TODO" in open(f).read() for f in sys.argv[1:]))'
+                files: \.txt$
+    "#});
+    let cwd = context.work_dir();
+    cwd.child("a.txt").write_str("TODO")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.command().arg("stats"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    No runs recorded yet
+
+    ----- stderr -----
+    ");
+
+    context.run().arg("--all-files").assert().failure();
+    cwd.child("a.txt").write_str("done")?;
+    context.run().arg("--all-files").assert().failure();
+    // Dry runs are not recorded.
+    context
+        .run()
+        .arg("--all-files")
+        .arg("--dry-run")
+        .assert()
+        .success();
+
+    cmd_snapshot!(context.filters(), context.command().arg("stats"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Last 2 runs:
+    Hook            Runs  Failures  Flips   Average       Max
+    .:always-fail      2      100%      0     [TIME]     [TIME]
+    .:no-todo          2       50%      1     [TIME]     [TIME]
+    .:always-pass      2        0%      0     [TIME]     [TIME]
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
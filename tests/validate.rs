@@ -136,7 +136,6 @@ fn unexpected_keys_warning() {
         minimum_pre_commit_version: 1.0.0
     "});
 
-    // TODO: warning about `unexpected_key_in_hook` currently not working
     cmd_snapshot!(context.filters(), context.validate_config().arg(CONFIG_FILE), @r#"
     success: true
     exit_code: 0
@@ -144,5 +143,50 @@ fn unexpected_keys_warning() {
 
     ----- stderr -----
     warning: Ignored unexpected keys in `.pre-commit-config.yaml`: `unexpected_key`, `another_unknown`
+    warning: Ignored unexpected keys in hook `test-hook` of `.pre-commit-config.yaml`: `unexpected_key_in_hook`
+    "#);
+}
+
+#[test]
+fn lint_warnings() {
+    let context = TestContext::new();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        default_stages: [pre-commit]
+        repos:
+          - repo: local
+            hooks:
+              - id: test-hook
+                name: Test Hook
+                entry: echo test
+                language: system
+                stages: [pre-push]
+                additional_dependencys: [requests]
+                files: ^\./src/.*\.py$
+        fail_fats: true
+    "});
+
+    cmd_snapshot!(context.filters(), context.validate_config().arg(CONFIG_FILE), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: Ignored unexpected keys in `.pre-commit-config.yaml`: `fail_fats` (did you mean `fail_fast`?)
+    warning: Ignored unexpected keys in hook `test-hook` of `.pre-commit-config.yaml`: `additional_dependencys` (did you mean `additional_dependencies`?)
+    warning: The `files` pattern `^\./src/.*\.py$` of hook `test-hook` never matches: paths are relative to the project root, without a leading `/` or `./`
+    warning: `default_stages` is not used, all hooks set `stages`
+    "#);
+
+    cmd_snapshot!(context.filters(), context.validate_config().arg("--strict").arg(CONFIG_FILE), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Ignored unexpected keys in `.pre-commit-config.yaml`: `fail_fats` (did you mean `fail_fast`?)
+    error: Ignored unexpected keys in hook `test-hook` of `.pre-commit-config.yaml`: `additional_dependencys` (did you mean `additional_dependencies`?)
+    error: The `files` pattern `^\./src/.*\.py$` of hook `test-hook` never matches: paths are relative to the project root, without a leading `/` or `./`
+    error: `default_stages` is not used, all hooks set `stages`
     "#);
 }
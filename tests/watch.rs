@@ -0,0 +1,79 @@
+use std::io::{BufRead, BufReader};
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use assert_fs::fixture::{FileWriteStr, PathChild};
+
+use crate::common::TestContext;
+
+mod common;
+
+/// Hooks are run on the files that change, and failures are reported without stopping. Writes
+/// to the log files of hooks don't trigger another run.
+#[test]
+fn watch_changed_files() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: no-todo
+                name: no-todo
+                language: system
+                entry: python3 -c 'import sys; sys.exit(any("TODO" in open(f).read() for f in sys.argv[1:]))'
+                files: \.txt$
+              - id: echo
+                name: echo
+                language: system
+                entry: echo
+                verbose: true
+                log_file: hook.log
+    "#});
+    context.git_add(".");
+
+    let mut child = context
+        .command()
+        .arg("watch")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let next_status = || -> anyhow::Result<String> {
+        loop {
+            let line = rx.recv_timeout(Duration::from_secs(30))?;
+            if line.starts_with("Watching")
+                || line.starts_with("Passed")
+                || line.starts_with("Failed")
+            {
+                return Ok(line);
+            }
+        }
+    };
+
+    let result = (|| -> anyhow::Result<()> {
+        assert!(next_status()?.starts_with("Watching"));
+
+        let cwd = context.work_dir();
+        cwd.child("a.txt").write_str("TODO")?;
+        assert_eq!(next_status()?, "Failed `a.txt`");
+
+        cwd.child("a.txt").write_str("done")?;
+        assert_eq!(next_status()?, "Passed `a.txt`");
+        Ok(())
+    })();
+
+    child.kill()?;
+    child.wait()?;
+    result
+}
@@ -459,6 +459,7 @@ fn run_with_selectors() -> Result<()> {
 
     ----- stderr -----
     error: No hooks found after filtering with the given selectors
+    Available hooks: show-cwd
     ");
 
     cmd_snapshot!(context.filters(), context.run().arg("--skip").arg("project2:show-cwd").arg("--skip").arg("nested:show-cwd"), @r"
@@ -571,6 +572,144 @@ fn run_with_selectors() -> Result<()> {
     Ok(())
 }
 
+/// Each project only sees the files under its directory, and a failure in one project
+/// fails the whole run without stopping the other projects.
+#[test]
+fn per_project_configs() -> Result<()> {
+    let context = TestContext::new();
+    let cwd = context.work_dir();
+    context.init_project();
+
+    let config = |id: &str, exit_code: u8| {
+        format!(
+            indoc! {r"
+            repos:
+              - repo: local
+                hooks:
+                - id: {id}
+                  name: {id}
+                  language: system
+                  entry: python3 -c 'import sys; print(*sorted(sys.argv[1:])); sys.exit({exit_code})'
+                  files: \.py$
+                  verbose: true
+            "},
+            id = id,
+            exit_code = exit_code,
+        )
+    };
+    cwd.child(".pre-commit-config.yaml")
+        .write_str(&config("root-check", 0))?;
+    cwd.child("services/api/.pre-commit-config.yaml")
+        .write_str(&config("api-check", 1))?;
+    cwd.child("services/web/.pre-commit-config.yaml")
+        .write_str(&config("web-check", 0))?;
+    cwd.child("services/api/app.py").write_str("")?;
+    cwd.child("services/web/main.py").write_str("")?;
+    cwd.child("setup.py").write_str("")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Running hooks for `services/api`:
+    api-check................................................................Failed
+    - hook id: api-check
+    - duration: [TIME]
+    - exit code: 1
+      app.py
+
+    Running hooks for `services/web`:
+    web-check................................................................Passed
+    - hook id: web-check
+    - duration: [TIME]
+      main.py
+
+    Running hooks for `.`:
+    root-check...............................................................Passed
+    - hook id: root-check
+    - duration: [TIME]
+      services/api/app.py services/web/main.py setup.py
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+#[test]
+fn project_option() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let config = indoc! {r"
+    repos:
+      - repo: local
+        hooks:
+        - id: show-files
+          name: Show files
+          language: system
+          entry: python3 -c 'import sys; print(*sorted(sys.argv[1:]))'
+          verbose: true
+    "};
+
+    context.setup_workspace(&["project2", "project3", "project3/project4"], config)?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.list().arg("--projects"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    project3/project4
+    project2
+    project3
+    .
+
+    ----- stderr -----
+    ");
+
+    // Nested projects are included.
+    cmd_snapshot!(context.filters(), context.run().arg("--project").arg("project3"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Running hooks for `project3/project4`:
+    Show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      .pre-commit-config.yaml
+
+    Running hooks for `project3`:
+    Show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      .pre-commit-config.yaml project4/.pre-commit-config.yaml
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--project").arg("project2").arg("--project").arg("project3/project4"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Running hooks for `project3/project4`:
+    Show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      .pre-commit-config.yaml
+
+    Running hooks for `project2`:
+    Show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      .pre-commit-config.yaml
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
 #[test]
 fn skips() -> Result<()> {
     let context = TestContext::new();